@@ -0,0 +1,48 @@
+//! Build script for MadTyping
+//!
+//! Embeds the git commit hash and build date so `--version`, the about
+//! screen and the log file can identify exactly which build is running.
+
+use std::{env, process::Command, time::SystemTime};
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Honor SOURCE_DATE_EPOCH for reproducible builds
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+    println!("cargo:rustc-env=MADTYPING_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=MADTYPING_BUILD_DATE={}", civil_date(secs));
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// Format seconds since the Unix epoch as YYYY-MM-DD (UTC) without external crates.
+fn civil_date(secs: u64) -> String {
+    // Days-to-civil algorithm by Howard Hinnant
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,222 @@
+//! Abbreviations for MadTyping
+//!
+//! The `[abbreviations]` table of the config file maps short names to the
+//! text they stand for:
+//!
+//! ```toml
+//! [abbreviations]
+//! glhf = "good luck have fun everyone!"
+//! open = "~glhf ward your jungle"
+//! ```
+//!
+//! A line containing `~glhf` (the `abbreviation_sigil` followed by a name)
+//! is sent with the token replaced, whether it is the whole line or part of
+//! it. Only tokens starting with the sigil are expanded, so plain words
+//! never change. An expansion may use other abbreviations up to
+//! [`MAX_ABBREVIATION_DEPTH`] levels deep, which stops one that uses
+//! itself. Files keep the raw text: lines are expanded when sent, and
+//! unknown or too deeply nested tokens are validation warnings on load.
+
+use std::{borrow::Cow, collections::BTreeMap, sync::Mutex};
+
+use crate::config::{Config, MAX_ABBREVIATION_DEPTH};
+use crate::files::Line;
+use crate::logging::log;
+
+/// Abbreviations in effect for files loaded from now on, set from the
+/// config at startup and on every reload.
+static CURRENT: Mutex<Abbreviations> = Mutex::new(Abbreviations::empty());
+
+/// Whether `c` can be part of an abbreviation name.
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// A text with its abbreviations expanded.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Expansion {
+    pub text: String,
+    /// Tokens with no abbreviation, left as written
+    pub unknown: Vec<String>,
+    /// Tokens still left after [`MAX_ABBREVIATION_DEPTH`] levels
+    pub too_deep: Vec<String>,
+}
+
+/// Replace every `sigil` token in `text` that names an entry of `table`
+/// by its text, expanding tokens in that text as well.
+pub fn expand(text: &str, sigil: &str, table: &BTreeMap<String, String>) -> Expansion {
+    let mut expansion = Expansion::default();
+    if sigil.is_empty() {
+        expansion.text = text.to_string();
+    } else {
+        expansion.text = expand_at(text, sigil, table, 0, &mut expansion);
+    }
+    expansion
+}
+
+/// [`expand`] at nesting level `depth`, noting problems in `found`.
+fn expand_at(text: &str, sigil: &str, table: &BTreeMap<String, String>, depth: usize, found: &mut Expansion) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(sigil) {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + sigil.len()..];
+        let name_len = after.find(|c: char| !is_name_char(c)).unwrap_or(after.len());
+        let (name, token) = (&after[..name_len], &rest[start..start + sigil.len() + name_len]);
+        match table.get(name) {
+            // A sigil on its own is just a character
+            _ if name.is_empty() => expanded.push_str(token),
+            Some(value) if depth < MAX_ABBREVIATION_DEPTH => {
+                expanded.push_str(&expand_at(value, sigil, table, depth + 1, found));
+            }
+            Some(_) => {
+                note(&mut found.too_deep, token);
+                expanded.push_str(token);
+            }
+            None => {
+                note(&mut found.unknown, token);
+                expanded.push_str(token);
+            }
+        }
+        rest = &after[name_len..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Add `token` to `tokens` unless it is there already.
+fn note(tokens: &mut Vec<String>, token: &str) {
+    if !tokens.iter().any(|t| t == token) {
+        tokens.push(token.to_string());
+    }
+}
+
+/// The abbreviation table and the sigil that marks its tokens.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Abbreviations {
+    sigil: String,
+    table: BTreeMap<String, String>,
+}
+
+impl From<&Config> for Abbreviations {
+    fn from(config: &Config) -> Self {
+        Self::new(&config.abbreviation_sigil, config.abbreviations.clone())
+    }
+}
+
+impl Abbreviations {
+    pub fn new(sigil: &str, table: BTreeMap<String, String>) -> Self {
+        Self { sigil: sigil.to_string(), table }
+    }
+
+    /// No abbreviations; nothing is expanded.
+    pub const fn empty() -> Self {
+        Self { sigil: String::new(), table: BTreeMap::new() }
+    }
+
+    /// Whether there is anything to expand.
+    pub fn is_active(&self) -> bool {
+        !self.sigil.is_empty() && !self.table.is_empty()
+    }
+
+    /// Expand the tokens in `text`.
+    pub fn expand(&self, text: &str) -> Expansion {
+        expand(text, &self.sigil, &self.table)
+    }
+
+    /// `lines` with their tokens expanded, borrowed if none has any.
+    pub fn expand_lines<'a>(&self, lines: &'a [Line]) -> Cow<'a, [Line]> {
+        if !self.is_active() || !lines.iter().any(|l| l.text.contains(&self.sigil)) {
+            return Cow::Borrowed(lines);
+        }
+        Cow::Owned(lines.iter().map(|line| Line { text: self.expand(&line.text).text, ..line.clone() }).collect())
+    }
+
+    /// Validation warnings for the tokens of `lines` that can't be
+    /// expanded; they are sent as written.
+    pub fn warnings(&self, lines: &[Line]) -> Vec<String> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        let mut warnings = Vec::new();
+        for (index, line) in lines.iter().enumerate().filter(|(_, l)| l.text.contains(&self.sigil)) {
+            let expansion = self.expand(&line.text);
+            for token in &expansion.unknown {
+                warnings.push(format!("line {}: unknown abbreviation {}, sent as is", index + 1, token));
+            }
+            for token in &expansion.too_deep {
+                warnings.push(format!(
+                    "line {}: abbreviation {} nests more than {} levels deep (does it use itself?)",
+                    index + 1,
+                    token,
+                    MAX_ABBREVIATION_DEPTH
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Use `abbreviations` for files loaded from now on.
+pub fn set_abbreviations(abbreviations: Abbreviations) {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    if *current != abbreviations {
+        log(&format!("Abbreviations: {} with sigil '{}'", abbreviations.table.len(), abbreviations.sigil));
+        *current = abbreviations;
+    }
+}
+
+/// The abbreviations set with [`set_abbreviations`].
+pub fn current() -> Abbreviations {
+    CURRENT.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries.iter().map(|(name, text)| (name.to_string(), text.to_string())).collect()
+    }
+
+    #[test]
+    fn test_tokens_expanded_in_place() {
+        let table = table(&[("glhf", "good luck have fun everyone!"), ("open", "~glhf ward your jungle"), ("gg", "gg")]);
+        assert_eq!(expand("~glhf", "~", &table).text, "good luck have fun everyone!");
+        assert_eq!(expand("hi, ~open.", "~", &table).text, "hi, good luck have fun everyone! ward your jungle.");
+        // Only sigil tokens change; a lone sigil stays
+        assert_eq!(expand("glhf ~ gg~gg", "~", &table).text, "glhf ~ gggg");
+        assert_eq!(expand("::glhf", "::", &table).text, "good luck have fun everyone!");
+
+        let expansion = expand("~glfh and ~30s ~glfh", "~", &table);
+        assert_eq!(expansion.text, "~glfh and ~30s ~glfh");
+        assert_eq!(expansion.unknown, vec!["~glfh", "~30s"]);
+    }
+
+    #[test]
+    fn test_nesting_is_capped() {
+        let table = table(&[("loop", "again ~loop"), ("a", "~b"), ("b", "done")]);
+        let expansion = expand("~loop", "~", &table);
+        assert_eq!(expansion.text, format!("{}~loop", "again ".repeat(MAX_ABBREVIATION_DEPTH)));
+        assert_eq!(expansion.too_deep, vec!["~loop"]);
+        assert_eq!(expand("~a", "~", &table), Expansion { text: "done".to_string(), ..Expansion::default() });
+    }
+
+    #[test]
+    fn test_lines_and_warnings() {
+        let abbreviations = Abbreviations::new("~", table(&[("gg", "good game"), ("loop", "~loop")]));
+        let lines = vec![Line::new("~gg wp"), Line::new("plain"), Line::new("~ff ~loop")];
+        let expanded = abbreviations.expand_lines(&lines);
+        assert_eq!(expanded[0].text, "good game wp");
+        assert_eq!(expanded[1].text, "plain");
+        assert_eq!(abbreviations.warnings(&lines), vec![
+            "line 3: unknown abbreviation ~ff, sent as is",
+            "line 3: abbreviation ~loop nests more than 8 levels deep (does it use itself?)",
+        ]);
+
+        // Without a table nothing is expanded or flagged
+        let off = Abbreviations::new("~", BTreeMap::new());
+        assert!(matches!(off.expand_lines(&lines), Cow::Borrowed(_)));
+        assert!(off.warnings(&lines).is_empty());
+    }
+}
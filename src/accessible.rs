@@ -0,0 +1,544 @@
+//! Screen-reader friendly frontend for MadTyping
+//!
+//! `--accessible` replaces the cell-addressed terminal UI with plain lines:
+//! the file list is printed as a numbered list, a file is picked by typing
+//! its number, questions are asked as plain questions and every sent line
+//! is reported on a line of its own. Nothing is redrawn in place and no
+//! colors are used, so screen readers read the output in order. The state
+//! lives in the same [`App`] and sends go through the same [`SendSession`]
+//! as in the terminal UI.
+
+use std::{
+    io::{BufRead, Write},
+    time::{Duration, Instant},
+};
+
+use crate::app::App;
+use crate::abbreviations::Abbreviations;
+use crate::archive;
+use crate::charfilter::{self, CharFilter};
+use crate::config::{Config, SendMethod, NOTIFY_MIN_SEND_SECS, READ_ONLY_ERROR};
+use crate::files::{scan, scan_directory, Line, ScanOptions, TextFile};
+use crate::focus_quirks;
+use crate::hooks;
+use crate::paste;
+use crate::logging::log;
+use crate::platform::{
+    check_keyboard_state, focus_console_window, game_guard, is_window_running, notify, restore_keyboard_state, KeyMode,
+};
+use crate::report;
+use crate::send::{
+    finish_notice, message_count, BusyPolicy, Control, LineSender, SendChoices, SendDelays, SendEvent, SendReport, SendSession,
+};
+use crate::timed::{format_clock, parse_clock, GameClock};
+use crate::titles::not_running_error;
+
+/// Commands understood at the main prompt.
+const HELP: &str = "Commands: a number sends that file; v and a number reads it; m and a number switches it to \
+                    manual advance; / and text searches, / alone clears the search; l lists the files again; \
+                    r scans for files again; h shows this help; q quits.";
+
+/// What the user typed at the main prompt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Command {
+    /// Send file N of the list (1-based)
+    Send(usize),
+    /// Read out the lines of file N
+    View(usize),
+    /// Switch file N between automatic and manual advance
+    Manual(usize),
+    /// Filter the list (empty: show everything)
+    Search(String),
+    List,
+    Refresh,
+    Help,
+    Quit,
+    Unknown(String),
+}
+
+impl Command {
+    fn parse(input: &str) -> Self {
+        let input = input.trim();
+        if let Some(query) = input.strip_prefix('/') {
+            return Command::Search(query.trim().to_string());
+        }
+        if let Ok(number) = input.parse() {
+            return Command::Send(number);
+        }
+        let (word, rest) = input.split_once(' ').unwrap_or((input, ""));
+        let number = rest.trim().parse();
+        match (word.to_lowercase().as_str(), number) {
+            ("v" | "view", Ok(number)) => Command::View(number),
+            ("m" | "manual", Ok(number)) => Command::Manual(number),
+            ("" | "l" | "list", _) => Command::List,
+            ("r" | "refresh", _) => Command::Refresh,
+            ("h" | "help" | "?", _) => Command::Help,
+            ("q" | "quit" | "exit", _) => Command::Quit,
+            _ => Command::Unknown(input.to_string()),
+        }
+    }
+}
+
+/// Line-oriented frontend reading commands from `input` and writing
+/// everything to `output`.
+pub struct Accessible<R, W> {
+    input: R,
+    output: W,
+    window_titles: Vec<String>,
+    config: Config,
+}
+
+impl<R: BufRead, W: Write> Accessible<R, W> {
+    /// Create the frontend for the given default target windows.
+    pub fn new(input: R, output: W, window_titles: Vec<String>, config: Config) -> Self {
+        Self { input, output, window_titles, config }
+    }
+
+    /// Run until the user quits or the input ends, sending through `sender`.
+    pub fn run(&mut self, app: &mut App, sender: &mut impl LineSender) -> Result<(), String> {
+        log("Accessible mode started");
+        if let Some(error) = app.get_error() {
+            say(&mut self.output, error);
+        }
+        if self.config.read_only {
+            say(&mut self.output, &format!("Read-only mode: {}.", READ_ONLY_ERROR));
+        }
+        self.list(app);
+        say(&mut self.output, HELP);
+        loop {
+            let Some(answer) = ask(&mut self.input, &mut self.output, "Command?") else {
+                return Ok(());
+            };
+            match Command::parse(&answer) {
+                Command::Send(number) => {
+                    if let Some(file) = self.pick(app, number) {
+                        self.send(app, sender, &file);
+                    }
+                }
+                Command::View(number) => {
+                    if let Some(file) = self.pick(app, number) {
+                        self.view(&file);
+                    }
+                }
+                Command::Manual(number) => {
+                    if let Some(file) = self.pick(app, number) {
+                        let text = if app.toggle_manual_advance() {
+                            "now sends one line each time you press Enter"
+                        } else {
+                            "now sends all lines automatically"
+                        };
+                        say(&mut self.output, &format!("{} {}.", file.name, text));
+                    }
+                }
+                Command::Search(query) => {
+                    app.set_search(&query);
+                    self.list(app);
+                }
+                Command::List => self.list(app),
+                Command::Refresh => match scan(ScanOptions::from(&self.config)) {
+                    Ok(scan) => {
+                        app.set_files(scan.files);
+                        let newly_skipped = app.set_skipped(scan.skipped);
+                        app.set_capped(scan.cap_warning.is_some());
+                        self.list(app);
+                        if let Some(warning) = &scan.cap_warning {
+                            say(&mut self.output, &format!("{}.", warning));
+                        }
+                        if !newly_skipped.is_empty() {
+                            say(
+                                &mut self.output,
+                                &format!("Skipped, not text or unreadable: {}.", newly_skipped.join(", ")),
+                            );
+                        }
+                    }
+                    Err(e) => say(&mut self.output, &format!("Scan failed: {}", e)),
+                },
+                Command::Help => say(&mut self.output, HELP),
+                Command::Quit => return Ok(()),
+                Command::Unknown(input) => {
+                    say(&mut self.output, &format!("Unknown command '{}'. Type h for help.", input));
+                }
+            }
+        }
+    }
+
+    /// Print the (filtered) file list, numbered from 1.
+    fn list(&mut self, app: &App) {
+        let files = app.filtered_files();
+        let heading = if app.is_search_empty() {
+            let more = if app.is_capped() { "+" } else { "" };
+            format!("{}{} message file(s):", files.len(), more)
+        } else {
+            format!("{} of {} file(s) match '{}':", files.len(), app.total_count(), app.search_query())
+        };
+        say(&mut self.output, &heading);
+        for (i, file) in files.iter().enumerate() {
+            let mut row = format!("{}. {}, {} line(s)", i + 1, file.name, file.lines.len());
+            if let Some(original) = app.duplicate_of(file) {
+                row.push_str(&format!(", duplicate of {}", original));
+            }
+            if app.is_manual_advance(file) {
+                row.push_str(", manual advance");
+            }
+            if !file.warnings.is_empty() {
+                row.push_str(&format!(", {} warning(s)", file.warnings.len()));
+            }
+            let commands = file.lines.iter().filter(|l| l.is_command()).count();
+            if self.config.slash_commands && commands > 0 {
+                row.push_str(&format!(", {} chat command(s)", commands));
+            }
+            if message_count(&file.lines, self.config.split_long_lines, self.config.char_limit()) as u64 > self.config.max_lines_per_send {
+                row.push_str(&format!(", over the {} line limit", self.config.max_lines_per_send));
+            }
+            say(&mut self.output, &row);
+        }
+    }
+
+    /// Select file `number` of the list, or say why there is none.
+    fn pick(&mut self, app: &mut App, number: usize) -> Option<TextFile> {
+        let count = app.filtered_count();
+        let Some(file) = number.checked_sub(1).and_then(|i| app.filtered_files().get(i).map(|f| (*f).clone())) else {
+            say(&mut self.output, &format!("There is no file {}; pick 1 to {}.", number, count));
+            return None;
+        };
+        app.select_by_name(&file.name);
+        Some(file)
+    }
+
+    /// Read out the lines of `file`.
+    fn view(&mut self, file: &TextFile) {
+        say(&mut self.output, &format!("{}, {} line(s):", file.name, file.lines.len()));
+        let filter = CharFilter::from(&self.config);
+        for (i, line) in file.lines.iter().enumerate() {
+            let kind = if self.config.slash_commands && line.is_command() { "command " } else { "" };
+            match line.at {
+                Some(at) => say(&mut self.output, &format!("{}. {}at {}: {}", i + 1, kind, format_clock(at), line.text)),
+                None if kind.is_empty() => say(&mut self.output, &format!("{}. {}", i + 1, line.text)),
+                None => say(&mut self.output, &format!("{}. command: {}", i + 1, line.text)),
+            }
+            let filtered = filter.apply(&line.text);
+            if filter.is_active() && filtered.text.is_empty() {
+                say(&mut self.output, "   Skipped: nothing is left after the character filter.");
+            } else if filter.is_active() && filtered.lost > 0 {
+                say(&mut self.output, &format!("   Sent as: {}", filtered.text));
+            }
+        }
+        for warning in &file.warnings {
+            say(&mut self.output, &format!("Warning: {}", warning));
+        }
+    }
+
+    /// Check that `file` can be sent, then send it.
+    fn send(&mut self, app: &mut App, sender: &mut impl LineSender, file: &TextFile) {
+        if self.config.read_only {
+            say(&mut self.output, &format!("Can't send: {}.", READ_ONLY_ERROR));
+            return;
+        }
+        let targets = file.targets(&self.window_titles);
+        if !targets.iter().any(|t| is_window_running(t)) {
+            say(&mut self.output, &not_running_error(&targets));
+            return;
+        }
+        if let Some(problem) = self.config.game_guard.then(|| game_guard(&targets)).flatten() {
+            log(&format!("Game guard: {}", problem));
+            let question = format!("Game guard: {}. Send anyway? (y/n)", problem);
+            if !is_yes(ask(&mut self.input, &mut self.output, &question)) {
+                say(&mut self.output, "Not sent.");
+                return;
+            }
+        }
+        let game_clock = if self.config.ask_game_time && file.lines.iter().any(|l| l.at.is_some()) {
+            self.ask_game_clock()
+        } else {
+            None
+        };
+        if let Err(e) = hooks::pre_send(&self.config.pre_send_hook, &file.name, file.lines.len(), &targets) {
+            say(&mut self.output, &e);
+            return;
+        }
+
+        log(&format!("Accessible send of '{}' ({} lines)", file.name, file.lines.len()));
+        say(&mut self.output, &format!("Sending {}, {} line(s), to {}.", file.name, file.lines.len(), targets.join(", ")));
+        if let Some(summary) = charfilter::summary(&CharFilter::from(&self.config), &file.lines) {
+            say(&mut self.output, &summary);
+        }
+        report::record_before_send(&targets, &self.config);
+        let output = &mut self.output;
+        let caps_was_on = match check_keyboard_state(self.config.compensate_keyboard_state, |held| {
+            say(output, &format!("Release {} to continue.", held.join(" and ")));
+        }) {
+            Ok(caps_was_on) => caps_was_on,
+            Err(e) => {
+                say(&mut self.output, &e);
+                return;
+            }
+        };
+
+        let manual = app.is_manual_advance(file);
+        let started = Instant::now();
+        let result = self.send_lines(app, sender, &file.lines, &targets, game_clock, manual);
+        restore_keyboard_state(caps_was_on);
+        if self.config.refocus_console_after_send && !focus_console_window() {
+            log("No console window to refocus");
+        }
+        let report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                log(&format!("Send refused: {}", e));
+                say(&mut self.output, &e);
+                return;
+            }
+        };
+
+        let total = file.lines.len();
+        let (_, summary) = finish_notice(&file.name, &report, total);
+        say(&mut self.output, &summary);
+        if let Some(summary) = report.verification_summary() {
+            say(&mut self.output, &format!("{}.", summary));
+        }
+        for target in &report.targets {
+            if let Some(reason) = &target.paste_fallback {
+                say(&mut self.output, &format!(
+                    "Pasting didn't work in {} ({}); it is typed into from now on.",
+                    target.window_title, reason
+                ));
+            }
+        }
+        if let Err(e) = paste::save(&report) {
+            log(&e);
+        }
+        if let Err(e) = focus_quirks::save(&report) {
+            log(&e);
+        }
+        if let Some(path) = &report.trace_file {
+            say(&mut self.output, &format!("Input trace: {}", path.display()));
+        }
+        if let Some(path) = &report.session_file {
+            say(&mut self.output, &format!("Session recording: {}", path.display()));
+        }
+        if self.config.notify_when_finished && started.elapsed().as_secs() >= NOTIFY_MIN_SEND_SECS {
+            let (title, text) = finish_notice(&file.name, &report, total);
+            notify(&title, &text);
+        }
+        if let Err(e) = hooks::post_send(&self.config.post_send_hook, &file.name, &report, started.elapsed()) {
+            log(&e);
+            say(&mut self.output, &format!("Warning: {}", e));
+        }
+        if archive::should_archive(file, &self.config, &report) {
+            match scan_directory().and_then(|dir| archive::archive(file, &dir)) {
+                Ok(path) => {
+                    app.remove_file(&file.path);
+                    say(&mut self.output, &format!("Fully sent; moved to {}.", path.display()));
+                }
+                Err(e) => say(&mut self.output, &format!("Sent, but not archived: {}", e)),
+            }
+        }
+    }
+
+    /// Run the send session, saying what happens to each line. In manual
+    /// advance mode each line after the first waits for Enter.
+    fn send_lines(
+        &mut self,
+        app: &mut App,
+        sender: &mut impl LineSender,
+        lines: &[Line],
+        targets: &[String],
+        game_clock: Option<GameClock>,
+        manual: bool,
+    ) -> Result<SendReport, String> {
+        let total = lines.len();
+        let broadcast = targets.len() > 1;
+        let choices = SendChoices { manual_advance: manual, ..SendChoices::from(&self.config) };
+        let session = SendSession::new(lines, targets.to_vec(), choices.order)
+            .with_clock_start(game_clock.map(|c| c.now()).unwrap_or_default())
+            .with_delays(SendDelays::from(&self.config))
+            .with_busy_policy(BusyPolicy::from(&self.config))
+            .with_split_long_lines(self.config.split_long_lines)
+            .with_char_limit(self.config.char_limit())
+            .with_key_mode(KeyMode::from(&self.config))
+            .with_trace_input(self.config.trace_input)
+            .with_recording(self.config.record_sessions)
+            .with_max_lines_per_send(self.config.max_lines_per_send)
+            .with_slash_commands(self.config.slash_commands)
+            .with_window_lost_wait(Duration::from_secs(self.config.window_lost_wait_secs))
+            .with_send_method(self.config.send_method)
+            .with_typed_windows(paste::load())
+            .with_focus_delays(focus_quirks::load())
+            .with_char_retries(self.config.char_retry_limit)
+            .with_paste_check_hook(&self.config.paste_check_hook)
+            .with_verify_hook(&self.config.verify_hook)
+            .with_char_filter(CharFilter::from(&self.config))
+            .with_abbreviations(Abbreviations::from(&self.config))
+            .with_choices(choices);
+        if manual {
+            say(&mut self.output, "Manual advance: press Enter for each line, s and Enter to skip it, q and Enter to stop.");
+        }
+
+        let (input, output) = (&mut self.input, &mut self.output);
+        let mut announced = None;
+        let mut lost_at = None;
+        session.run(sender, |event| {
+            app.record_send_event(event);
+            match *event {
+                SendEvent::LineFinished { index, line, target, result, .. } => {
+                    let on = if broadcast { format!(" on {}", target) } else { String::new() };
+                    match result {
+                        Ok(_) => say(output, &format!("Line {} of {} sent{}: {}", index + 1, total, on, line.text)),
+                        Err(e) => say(output, &format!("Line {} of {} failed{}: {}", index + 1, total, on, e)),
+                    }
+                }
+                // Say each wait once rather than every tick
+                SendEvent::Waiting { index, line, at, left, .. } if announced != Some(index) => {
+                    announced = Some(index);
+                    say(output, &format!(
+                        "Line {} of {} waits for {}, {} from now: {}",
+                        index + 1,
+                        total,
+                        format_clock(at),
+                        format_clock(left),
+                        line.text
+                    ));
+                }
+                SendEvent::MethodChosen { target, method, reason } => {
+                    let what = match method {
+                        SendMethod::Paste => "Pasting into",
+                        SendMethod::PostMessage => "Posting to",
+                        _ => "Typing into",
+                    };
+                    say(output, &format!("{} {}, {}.", what, target, reason));
+                }
+                SendEvent::WindowLost { index, target, left, .. } if lost_at != Some(index) => {
+                    lost_at = Some(index);
+                    say(output, &format!(
+                        "The game window {} closed. Waiting up to {} seconds for it to come back before line {}.",
+                        target,
+                        left.as_secs_f64().ceil() as u64,
+                        index + 1
+                    ));
+                }
+                SendEvent::AwaitingAdvance { index, line, .. } => {
+                    // Blocks until the user answers, so this runs once per line
+                    if !focus_console_window() {
+                        log("No console window to focus for manual advance");
+                    }
+                    let question = format!("Next, line {} of {}: {}. Send it?", index + 1, total, line.text);
+                    return match ask(input, output, &question).map(|a| a.trim().to_lowercase()).as_deref() {
+                        Some("") | Some("y") | Some("yes") => Control::Advance,
+                        Some("s") | Some("skip") => Control::Skip,
+                        _ => {
+                            say(output, "Stopped.");
+                            Control::Cancel
+                        }
+                    };
+                }
+                _ => {}
+            }
+            Control::Continue
+        })
+    }
+
+    /// Ask for the current game time. Empty or bad input counts from the
+    /// send start.
+    fn ask_game_clock(&mut self) -> Option<GameClock> {
+        let answer = ask(&mut self.input, &mut self.output, "Game time now, as minutes:seconds? Leave empty for 00:00.")?;
+        if answer.trim().is_empty() {
+            return None;
+        }
+        let clock = parse_clock(&answer).map(GameClock::new);
+        if clock.is_none() {
+            say(&mut self.output, &format!("'{}' is not a game time; counting from the send start.", answer.trim()));
+        }
+        clock
+    }
+}
+
+/// Write one line of output.
+fn say(output: &mut impl Write, text: &str) {
+    let _ = writeln!(output, "{}", text);
+    let _ = output.flush();
+}
+
+/// Ask a question on its own line and read the answer. None when the
+/// input has ended.
+fn ask(input: &mut impl BufRead, output: &mut impl Write, question: &str) -> Option<String> {
+    say(output, question);
+    let mut answer = String::new();
+    match input.read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim_end_matches(['\r', '\n']).to_string()),
+    }
+}
+
+fn is_yes(answer: Option<String>) -> bool {
+    answer.is_some_and(|a| matches!(a.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::MockBackend;
+    use crate::send::BackendSender;
+
+    /// Run a scripted session and return everything it printed.
+    fn script(app: &mut App, input: &str, config: Config) -> (String, String) {
+        let mut output = Vec::new();
+        let mut sender = BackendSender::new(MockBackend::new());
+        Accessible::new(input.as_bytes(), &mut output, vec!["Game".to_string()], config)
+            .run(app, &mut sender)
+            .unwrap();
+        (String::from_utf8(output).unwrap(), sender.backend.typed_text())
+    }
+
+    #[test]
+    fn test_commands_parsed() {
+        assert_eq!(Command::parse(" 2 "), Command::Send(2));
+        assert_eq!(Command::parse("v 3"), Command::View(3));
+        assert_eq!(Command::parse("M 1"), Command::Manual(1));
+        assert_eq!(Command::parse("/ gg"), Command::Search("gg".to_string()));
+        assert_eq!(Command::parse(""), Command::List);
+        assert_eq!(Command::parse("q"), Command::Quit);
+        assert_eq!(Command::parse("v"), Command::Unknown("v".to_string()));
+    }
+
+    #[test]
+    fn test_list_search_and_view_read_linearly() {
+        let mut app = App::new(vec![TextFile::with_lines("greetings.txt", &["gl hf", "have fun"]), TextFile::with_lines("taunts.txt", &["ez"])]);
+        let (output, typed) = script(&mut app, "/ taunt\nv 1\n/\nv 5\nq\n", Config::default());
+
+        assert!(output.starts_with("2 message file(s):\n1. greetings.txt, 2 line(s)\n2. taunts.txt, 1 line(s)\n"));
+        assert!(output.contains("1 of 2 file(s) match 'taunt':\n1. taunts.txt, 1 line(s)\n"));
+        assert!(output.contains("taunts.txt, 1 line(s):\n1. ez\n"));
+        assert!(output.contains("There is no file 5; pick 1 to 2."));
+        // Plain lines only: no escape sequences or carriage returns
+        assert!(!output.contains(['\x1b', '\r']));
+        assert_eq!(typed, "");
+        assert_eq!(app.selection().map(|f| f.name.as_str()), Some("taunts.txt"));
+    }
+
+    #[test]
+    fn test_send_reports_each_line_in_order() {
+        let mut app = App::new(vec![TextFile::with_lines("greetings.txt", &["gl hf", "have fun"])]);
+        let (output, typed) = script(&mut app, "1\nq\n", Config::default());
+
+        assert!(output.contains("Sending greetings.txt, 2 line(s), to Game.\nLine 1 of 2 sent: gl hf\nLine 2 of 2 sent: have fun\n"));
+        assert_eq!(typed, "gl hfhave fun");
+        assert_eq!(app.session_stats().lines_sent, 2);
+
+        let config = Config { read_only: true, ..Config::default() };
+        let (output, typed) = script(&mut app, "1\n", config);
+        assert!(output.contains("Can't send: read-only mode: sending is disabled."));
+        assert_eq!(typed, "");
+    }
+
+    #[test]
+    fn test_manual_advance_asks_before_each_line() {
+        let mut app = App::new(vec![TextFile::with_lines("calls.txt", &["a", "b", "c", "d"])]);
+        let (output, typed) = script(&mut app, "m 1\nl\n1\n\ns\nq\n", Config::default());
+
+        assert!(output.contains("calls.txt now sends one line each time you press Enter."));
+        assert!(output.contains("1. calls.txt, 4 line(s), manual advance"));
+        assert!(output.contains("Next, line 2 of 4: b. Send it?\nLine 2 of 4 sent: b\nNext, line 3 of 4: c. Send it?\n"));
+        assert!(output.contains("Next, line 4 of 4: d. Send it?\nStopped.\n"));
+        assert_eq!(typed, "ab");
+    }
+}
@@ -3,7 +3,118 @@
 //! Contains the App struct which manages file list state,
 //! selection, filtering, and error handling.
 
-use crate::files::{self, TextFile};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::config::{LIST_COLUMN_WIDTH, LIST_MAX_COLUMNS, PERSIST_LIFETIME_STATS};
+use crate::cooldown::Cooldown;
+use crate::files::{self, content_hash, path_key, LoadReason, Provenance, SkippedFile, TextFile};
+use crate::logging::log;
+use crate::send::{SendChoices, SendEvent, SendReport};
+use crate::stats::Stats;
+use crate::tags;
+use crate::warnings;
+use crate::watch::FileEvent;
+
+/// How the last send of a file from the list ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendResult {
+    /// Every line reached every window
+    Sent,
+    /// Cancelled or stopped early after `reached` lines
+    Partial { reached: usize },
+    /// The line at this zero-based index failed
+    Failed { line: usize },
+}
+
+impl SendResult {
+    /// Sum up `report`: the first failed line wins over a cancel.
+    pub fn from_report(report: &SendReport) -> Self {
+        let failed = report.targets.iter().filter_map(|t| t.failed.as_ref().map(|(line, _, _)| *line)).min();
+        if let Some(line) = failed {
+            return SendResult::Failed { line };
+        }
+        if report.cancelled || report.skipped() > 0 {
+            return SendResult::Partial { reached: report.targets.iter().map(|t| t.sent).max().unwrap_or(0) };
+        }
+        SendResult::Sent
+    }
+}
+
+/// The last send of a file from the list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LastSend {
+    /// When it finished
+    pub at: Instant,
+    /// Most lines any one window took
+    pub delivered: usize,
+    pub result: SendResult,
+    /// Content hash of the file when it was sent
+    hash: u64,
+}
+
+/// Which part of the main screen typed characters go to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Focus {
+    /// Keys navigate and run actions; typing a character starts a search
+    #[default]
+    List,
+    /// Every printable key edits the search query
+    Search,
+}
+
+/// Layout of the file list: one or more columns, each filled top to
+/// bottom before the next one starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListGrid {
+    pub columns: usize,
+    /// Entries per column (the last column may have fewer)
+    pub rows: usize,
+}
+
+impl ListGrid {
+    /// Lay out `count` entries on a terminal `width` characters wide.
+    pub fn new(count: usize, width: usize) -> Self {
+        let columns = (width / LIST_COLUMN_WIDTH).clamp(1, LIST_MAX_COLUMNS);
+        let rows = count.div_ceil(columns).max(1);
+        // Drop columns that would stay empty, e.g. 3 entries in 4 columns
+        Self { columns: count.div_ceil(rows).max(1), rows }
+    }
+
+    /// Column and row of entry `index`.
+    pub fn cell(&self, index: usize) -> (usize, usize) {
+        (index / self.rows, index % self.rows)
+    }
+
+    /// Entry shown at `column`, `row`, if any.
+    pub fn index(&self, column: usize, row: usize, count: usize) -> Option<usize> {
+        let index = column * self.rows + row;
+        (column < self.columns && row < self.rows && index < count).then_some(index)
+    }
+
+    /// First row to show so entry `selected` is on screen with `visible` rows.
+    pub fn scroll_offset(&self, selected: usize, visible: usize) -> usize {
+        let (_, row) = self.cell(selected);
+        (row + 1).saturating_sub(visible)
+    }
+}
+
+/// One row of the file list. Files in subfolders are listed under a
+/// header row for their folder.
+#[derive(Clone, Copy, Debug)]
+pub enum ListRow<'a> {
+    /// Header of a folder with its number of listed files
+    Folder { name: &'a str, count: usize, collapsed: bool },
+    File(&'a TextFile),
+}
+
+/// Folder part of a discovered file's name ("" for the scan directory).
+fn folder_of(name: &str) -> &str {
+    name.rsplit_once('/').map_or("", |(folder, _)| folder)
+}
 
 /// Application state for the CLI.
 /// 
@@ -14,72 +125,328 @@ pub struct App {
     files: Vec<TextFile>,
     /// Indices into `files` that match the current search query
     filtered_indices: Vec<usize>,
-    /// Currently selected index in `filtered_indices`
-    selected_index: usize,
+    /// Path of the selected file; always one of the filtered files, or
+    /// None when none match. Positions are derived from it, so refreshes
+    /// and filter changes can't leave it pointing at another file.
+    selected: Option<PathBuf>,
+    /// Folder whose header row is selected (then no file is)
+    selected_folder: Option<String>,
+    /// Folders collapsed to their header row (this session only)
+    collapsed: HashSet<String>,
     /// Current search query
     search_query: String,
+    /// Whether the search box has input focus
+    focus: Focus,
     /// Error message to display (if any)
     error_message: Option<String>,
+    /// Informational message shown when there is no error
+    notice: Option<String>,
+    /// Registry id of the warning shown as the error or notice
+    shown_warning: Option<String>,
+    /// Path keys of files to highlight (e.g. just imported)
+    highlighted: HashSet<String>,
+    /// Path keys of files marked for export
+    marked: HashSet<String>,
+    /// Path keys of files sent one line per keypress (this session only)
+    manual_advance: HashSet<String>,
+    /// Counters for this run of the program
+    session_stats: Stats,
+    /// Cumulative counters across runs (persisted when enabled)
+    lifetime_stats: Stats,
+    /// Start of the background scan in progress, if any
+    scanning_since: Option<Instant>,
+    /// Ad-hoc lines sent this session, oldest first
+    adhoc_history: Vec<String>,
+    /// Files with the same messages (path and name), original first
+    duplicates: Vec<Vec<(PathBuf, String)>>,
+    /// Supported files the last scan left out (binary or unreadable)
+    skipped: Vec<SkippedFile>,
+    /// Path key and message index the viewer opens a file at next
+    view_target: Option<(String, usize)>,
+    /// Lines left out of sends (this session only), by path key, with the
+    /// content hash of the file they were marked in
+    excluded: HashMap<String, (u64, HashSet<usize>)>,
+    /// The last send of each file from the list, by path key
+    last_sends: HashMap<String, LastSend>,
+    /// Send options picked for each file (this session only), by path key
+    send_choices: HashMap<String, SendChoices>,
+    /// Lines sent to each target window, for the chat limit cooldown
+    cooldown: Cooldown,
+    /// The last scan stopped at the file cap
+    capped: bool,
+    /// Files were listed before, so the next scan is a rescan
+    listed: bool,
 }
 
 impl App {
     /// Create a new App instance with the given files.
     pub fn new(files: Vec<TextFile>) -> Self {
+        let mut app = Self::with_files(files);
+        app.load_lifetime_stats();
+        app
+    }
+
+    /// An empty list for the terminal UI to draw before the first scan
+    /// arrives. Lifetime stats are read later with
+    /// [`App::load_lifetime_stats`].
+    pub fn empty() -> Self {
+        Self::with_files(Vec::new())
+    }
+
+    fn with_files(files: Vec<TextFile>) -> Self {
         let filtered_indices: Vec<usize> = (0..files.len()).collect();
-        Self {
+        let selected = files.first().map(|f| f.path.clone());
+        let listed = !files.is_empty();
+        let mut app = Self {
             files,
             filtered_indices,
-            selected_index: 0,
+            selected,
+            selected_folder: None,
+            collapsed: HashSet::new(),
             search_query: String::new(),
+            focus: Focus::default(),
             error_message: None,
-        }
+            notice: None,
+            shown_warning: None,
+            highlighted: HashSet::new(),
+            marked: HashSet::new(),
+            manual_advance: HashSet::new(),
+            session_stats: Stats::default(),
+            lifetime_stats: Stats::default(),
+            scanning_since: None,
+            adhoc_history: Vec::new(),
+            duplicates: Vec::new(),
+            skipped: Vec::new(),
+            view_target: None,
+            excluded: HashMap::new(),
+            last_sends: HashMap::new(),
+            send_choices: HashMap::new(),
+            cooldown: Cooldown::new(),
+            capped: false,
+            listed,
+        };
+        app.update_duplicates();
+        app
     }
 
     /// Update filtered indices based on search query.
     fn update_filter(&mut self) {
+        let position = self.selected_row();
+        self.refilter(position);
+    }
+
+    /// Rebuild the filtered list, then [`Self::keep_selection`] from `position`.
+    fn refilter(&mut self, position: usize) {
         let query = self.search_query.to_lowercase();
         self.filtered_indices = self.files
             .iter()
             .enumerate()
-            .filter(|(_, f)| query.is_empty() || f.name.to_lowercase().contains(&query))
+            .filter(|(_, f)| matches_query(f, &query))
             .map(|(i, _)| i)
             .collect();
-        
-        // Reset selection if out of bounds
-        if self.selected_index >= self.filtered_indices.len() {
-            self.selected_index = 0;
+        self.keep_selection(position);
+    }
+
+    /// After the filtered list changed: keep the selected row if it is
+    /// still listed, else select the row at its old `position`, clamped
+    /// to the list.
+    fn keep_selection(&mut self, position: usize) {
+        let rows = self.list_rows();
+        if !rows.iter().any(|row| self.is_selected_row(row)) {
+            let mut position = position.min(rows.len().saturating_sub(1));
+            // An expanded folder's first file rather than its header
+            if matches!(rows.get(position), Some(ListRow::Folder { collapsed: false, .. })) {
+                position += 1;
+            }
+            self.select_row_at(position);
         }
     }
 
-    /// Move selection up (wraps to bottom).
-    pub fn move_up(&mut self) {
-        if self.filtered_indices.is_empty() {
-            return;
+    /// Position of the selected file in the filtered list.
+    fn position_of_selected(&self) -> Option<usize> {
+        let selected = self.selected.as_ref()?;
+        self.filtered_indices.iter().position(|&i| &self.files[i].path == selected)
+    }
+
+    /// Select the filtered entry at `position` (nothing if out of range),
+    /// expanding its folder if it was collapsed.
+    fn select_at(&mut self, position: usize) {
+        self.selected = self.filtered_indices.get(position).map(|&i| self.files[i].path.clone());
+        self.selected_folder = None;
+        if let Some(folder) = self.selection().map(|f| folder_of(&f.name).to_string()) {
+            self.collapsed.remove(&folder);
         }
-        if self.selected_index > 0 {
-            self.selected_index -= 1;
-        } else {
-            self.selected_index = self.filtered_indices.len().saturating_sub(1);
+    }
+
+    /// Select the list row at `position` (nothing if out of range).
+    fn select_row_at(&mut self, position: usize) {
+        let (file, folder) = match self.list_rows().get(position) {
+            Some(ListRow::File(file)) => (Some(file.path.clone()), None),
+            Some(ListRow::Folder { name, .. }) => (None, Some(name.to_string())),
+            None => (None, None),
+        };
+        self.selected = file;
+        self.selected_folder = folder;
+    }
+
+    fn is_selected_row(&self, row: &ListRow) -> bool {
+        match row {
+            ListRow::File(file) => self.selected.as_ref() == Some(&file.path),
+            ListRow::Folder { name, .. } => self.selected_folder.as_deref() == Some(*name),
         }
     }
 
+    /// Move selection up (wraps to bottom).
+    pub fn move_up(&mut self) {
+        let count = self.list_rows().len();
+        self.step_selection(count.saturating_sub(1));
+    }
+
     /// Move selection down (wraps to top).
     pub fn move_down(&mut self) {
-        if self.filtered_indices.is_empty() {
-            return;
+        self.step_selection(1);
+    }
+
+    /// Move the selection `step` rows on, wrapping around, to the next
+    /// file or collapsed folder. Expanded folder headers are passed over;
+    /// their files are right below them.
+    fn step_selection(&mut self, step: usize) {
+        let rows = self.list_rows();
+        let count = rows.len();
+        let start = self.selected_row();
+        let next = (1..=count)
+            .map(|n| (start + n * step) % count.max(1))
+            .find(|&position| !matches!(rows[position], ListRow::Folder { collapsed: false, .. }));
+        if let Some(position) = next {
+            self.select_row_at(position);
         }
-        if self.selected_index < self.filtered_indices.len().saturating_sub(1) {
-            self.selected_index += 1;
+    }
+
+    /// Whether files are listed under folder headers: when any of them is
+    /// in a subfolder.
+    pub fn is_grouped(&self) -> bool {
+        self.files.iter().any(|f| f.name.contains('/'))
+    }
+
+    /// Layout of the list on a terminal `width` characters wide. A list
+    /// grouped by folder always uses one column, so each folder's files
+    /// read top to bottom under its header.
+    pub fn list_grid(&self, width: usize) -> ListGrid {
+        let rows = self.list_rows().len();
+        if self.is_grouped() {
+            ListGrid { columns: 1, rows: rows.max(1) }
         } else {
-            self.selected_index = 0;
+            ListGrid::new(rows, width)
         }
     }
 
-    /// Get the currently selected file.
-    pub fn get_selected(&self) -> Option<&TextFile> {
-        self.filtered_indices
-            .get(self.selected_index)
-            .and_then(|&i| self.files.get(i))
+    /// The rows of the file list: the filtered files, with files in the
+    /// scan directory first and every folder's files under its header.
+    /// Collapsed folders show only the header; while searching, every
+    /// folder with a match is shown expanded.
+    pub fn list_rows(&self) -> Vec<ListRow<'_>> {
+        let files = self.filtered_files();
+        if !self.is_grouped() {
+            return files.into_iter().map(ListRow::File).collect();
+        }
+        let mut sections: Vec<(&str, Vec<&TextFile>)> = Vec::new();
+        for file in files {
+            let folder = folder_of(&file.name);
+            match sections.iter_mut().find(|(name, _)| *name == folder) {
+                Some((_, files)) => files.push(file),
+                None => sections.push((folder, vec![file])),
+            }
+        }
+        sections.sort_by_key(|(name, _)| name.to_lowercase());
+
+        let mut rows = Vec::new();
+        for (name, files) in sections {
+            if !name.is_empty() {
+                let collapsed = self.search_query.is_empty() && self.collapsed.contains(name);
+                rows.push(ListRow::Folder { name, count: files.len(), collapsed });
+                if collapsed {
+                    continue;
+                }
+            }
+            rows.extend(files.into_iter().map(ListRow::File));
+        }
+        rows
+    }
+
+    /// Position of the selected row in [`App::list_rows`] (0 if none).
+    pub fn selected_row(&self) -> usize {
+        self.list_rows().iter().position(|row| self.is_selected_row(row)).unwrap_or(0)
+    }
+
+    /// Folder whose header row is selected, if one is.
+    pub fn selected_folder(&self) -> Option<&str> {
+        self.selected_folder.as_deref()
+    }
+
+    /// Collapse or expand the folder whose header is selected.
+    pub fn toggle_folder(&mut self) {
+        if let Some(folder) = self.selected_folder.clone() {
+            if !self.collapsed.remove(&folder) {
+                self.collapsed.insert(folder);
+            }
+        }
+    }
+
+    /// Collapse the selected folder, or the folder of the selected file
+    /// and select its header.
+    pub fn collapse_folder(&mut self) {
+        let folder = match (&self.selected_folder, self.selection()) {
+            (Some(folder), _) => folder.clone(),
+            (None, Some(file)) if !folder_of(&file.name).is_empty() => folder_of(&file.name).to_string(),
+            _ => return,
+        };
+        self.selected = None;
+        self.selected_folder = Some(folder.clone());
+        self.collapsed.insert(folder);
+    }
+
+    /// Expand the folder whose header is selected.
+    pub fn expand_folder(&mut self) {
+        if let Some(folder) = &self.selected_folder {
+            self.collapsed.remove(folder);
+        }
+    }
+
+    /// Move selection one column left in `grid`.
+    pub fn move_left(&mut self, grid: ListGrid) {
+        let position = self.selected_index();
+        if position >= grid.rows {
+            self.select_at(position - grid.rows);
+        }
+    }
+
+    /// Move selection one column right in `grid`, to the last entry if the
+    /// next column is shorter.
+    pub fn move_right(&mut self, grid: ListGrid) {
+        let position = self.selected_index();
+        let (column, _) = grid.cell(position);
+        if column + 1 < grid.columns {
+            self.select_at((position + grid.rows).min(self.filtered_indices.len().saturating_sub(1)));
+        }
+    }
+
+    /// The selected file.
+    pub fn selection(&self) -> Option<&TextFile> {
+        let selected = self.selected.as_ref()?;
+        self.files.iter().find(|f| &f.path == selected)
+    }
+
+    /// Select the listed file named `name` (case-insensitive). Returns
+    /// false, leaving the selection alone, if no listed file has that name.
+    pub fn select_by_name(&mut self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        match self.filtered_indices.iter().position(|&i| self.files[i].name.to_lowercase() == name) {
+            Some(position) => {
+                self.select_at(position);
+                true
+            }
+            None => false,
+        }
     }
 
     /// Get filtered files for display.
@@ -90,32 +457,467 @@ impl App {
             .collect()
     }
 
-    /// Get selected index.
+    /// Position of the selected file in [`App::filtered_files`] (0 if none).
     pub fn selected_index(&self) -> usize {
-        self.selected_index
+        self.position_of_selected().unwrap_or(0)
     }
 
     /// Set an error message to display.
     pub fn set_error(&mut self, message: String) {
         self.error_message = Some(message);
+        self.shown_warning = None;
     }
 
-    /// Clear the error message.
+    /// Clear the error message and any notice.
     pub fn clear_error(&mut self) {
         self.error_message = None;
+        self.notice = None;
+        self.shown_warning = None;
+    }
+
+    /// Set an informational message to display.
+    pub fn set_notice(&mut self, message: String) {
+        self.notice = Some(message);
+        self.shown_warning = None;
+    }
+
+    /// Show a recurring warning as the error, unless warning `id` was
+    /// already shown this session or is hidden (see [`warnings`]).
+    /// Returns whether it's shown.
+    pub fn set_warning(&mut self, id: &str, message: String) -> bool {
+        let shown = warnings::report(id);
+        if shown {
+            self.set_error(message);
+            self.shown_warning = Some(id.to_string());
+        }
+        shown
+    }
+
+    /// Show a recurring warning as a notice, as [`App::set_warning`] does.
+    pub fn set_warning_notice(&mut self, id: &str, message: String) -> bool {
+        let shown = warnings::report(id);
+        if shown {
+            self.set_notice(message);
+            self.shown_warning = Some(id.to_string());
+        }
+        shown
+    }
+
+    /// Registry id of the warning on the status line, if one is shown
+    /// with [`App::set_warning`] or [`App::set_warning_notice`].
+    pub fn shown_warning(&self) -> Option<&str> {
+        self.shown_warning.as_deref()
+    }
+
+    /// Get the current notice, if any.
+    pub fn get_notice(&self) -> Option<&String> {
+        self.notice.as_ref()
+    }
+
+    /// Highlight these files in the list (replacing earlier highlights).
+    pub fn set_highlighted(&mut self, paths: &[PathBuf]) {
+        self.highlighted = paths.iter().map(|p| path_key(p)).collect();
+    }
+
+    /// Whether `file` is highlighted.
+    pub fn is_highlighted(&self, file: &TextFile) -> bool {
+        !self.highlighted.is_empty() && self.highlighted.contains(&path_key(&file.path))
+    }
+
+    /// Mark or unmark the selected file.
+    pub fn toggle_mark(&mut self) {
+        if let Some(key) = self.selection().map(|f| path_key(&f.path)) {
+            if !self.marked.remove(&key) {
+                self.marked.insert(key);
+            }
+        }
+    }
+
+    /// Whether `file` is marked.
+    pub fn is_marked(&self, file: &TextFile) -> bool {
+        !self.marked.is_empty() && self.marked.contains(&path_key(&file.path))
+    }
+
+    /// Switch the selected file between automatic and manual advance,
+    /// returning whether it is now manual.
+    pub fn toggle_manual_advance(&mut self) -> bool {
+        let Some(key) = self.selection().map(|f| path_key(&f.path)) else {
+            return false;
+        };
+        if self.manual_advance.remove(&key) {
+            return false;
+        }
+        self.manual_advance.insert(key);
+        true
+    }
+
+    /// Whether `file` is sent one line per keypress.
+    pub fn is_manual_advance(&self, file: &TextFile) -> bool {
+        !self.manual_advance.is_empty() && self.manual_advance.contains(&path_key(&file.path))
+    }
+
+    /// Unmark all files.
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    /// The marked files in list order (including ones hidden by the
+    /// search), or the selected file if none is marked.
+    pub fn marked_or_selected(&self) -> Vec<&TextFile> {
+        if self.marked.is_empty() {
+            return self.selection().into_iter().collect();
+        }
+        self.files.iter().filter(|f| self.is_marked(f)).collect()
     }
 
-    /// Refresh the file list by re-discovering files.
+    /// Refresh the file list by re-discovering files on this thread.
     /// Returns the number of files changed (added + removed).
+    ///
+    /// The UI scans on a background worker and calls [`App::set_files`].
     pub fn refresh_files(&mut self) -> Result<usize, String> {
-        let new_files = files::discover()?;
+        Ok(self.set_files(files::discover()?))
+    }
+
+    /// Replace the file list with freshly discovered files.
+    /// Returns the number of files changed (added + removed).
+    ///
+    /// The previously selected file stays selected if it still exists.
+    /// Every file but those of the first scan is marked as rescanned.
+    pub fn set_files(&mut self, mut new_files: Vec<TextFile>) -> usize {
         let new_count = new_files.len();
         let old_count = self.files.len();
+        let reason = if self.listed { LoadReason::Rescan } else { LoadReason::Initial };
+        for file in &mut new_files {
+            file.provenance.reason = reason;
+        }
+        log(&format!("Loaded {} file(s) ({})", new_count, reason.label()));
+        self.listed = true;
         self.files = new_files;
         self.search_query.clear();
         self.filtered_indices = (0..self.files.len()).collect();
-        self.selected_index = 0;
-        Ok(new_count.saturating_sub(old_count.min(new_count)) + old_count.saturating_sub(new_count.min(old_count)))
+        self.keep_selection(0);
+        self.update_duplicates();
+        self.forget_stale_exclusions();
+        self.scanning_since = None;
+        new_count.saturating_sub(old_count.min(new_count)) + old_count.saturating_sub(new_count.min(old_count))
+    }
+
+    /// Note whether the last scan stopped at the file cap.
+    pub fn set_capped(&mut self, capped: bool) {
+        self.capped = capped;
+    }
+
+    /// Whether the last scan stopped at the file cap, so more files exist
+    /// than are listed.
+    pub fn is_capped(&self) -> bool {
+        self.capped
+    }
+
+    /// Replace the files skipped by the last scan. Returns the names of
+    /// the ones that weren't skipped before.
+    pub fn set_skipped(&mut self, skipped: Vec<SkippedFile>) -> Vec<String> {
+        let known: HashSet<String> = self.skipped.iter().map(|f| path_key(&f.path)).collect();
+        let new = skipped.iter().filter(|f| !known.contains(&path_key(&f.path))).map(|f| f.name.clone()).collect();
+        self.skipped = skipped;
+        new
+    }
+
+    /// Supported files the last scan left out, by name.
+    pub fn skipped(&self) -> &[SkippedFile] {
+        &self.skipped
+    }
+
+    /// Open the file at `path` at message `index` the next time it is
+    /// viewed (e.g. the line a send failed on).
+    pub fn set_view_target(&mut self, path: &Path, index: usize) {
+        self.view_target = Some((path_key(path), index));
+    }
+
+    /// Message to open `file` at in the viewer: the target set for it,
+    /// else the line its first warning points at.
+    pub fn take_view_target(&mut self, file: &TextFile) -> Option<usize> {
+        match self.view_target.take() {
+            Some((key, index)) if key == path_key(&file.path) => Some(index),
+            other => {
+                self.view_target = other;
+                file.warning_line()
+            }
+        }
+    }
+
+    /// Zero-based indexes of the lines of `file` left out of sends.
+    pub fn excluded_lines(&self, file: &TextFile) -> HashSet<usize> {
+        if self.excluded.is_empty() {
+            return HashSet::new();
+        }
+        match self.excluded.get(&path_key(&file.path)) {
+            Some((hash, lines)) if *hash == content_hash(&file.lines) => lines.clone(),
+            _ => HashSet::new(),
+        }
+    }
+
+    /// Leave `lines` of the listed file at `path` out of sends for the rest
+    /// of the session, until its contents change. Empty sends every line
+    /// again.
+    pub fn set_excluded_lines(&mut self, path: &Path, lines: HashSet<usize>) {
+        let key = path_key(path);
+        let hash = self.files.iter().find(|f| path_key(&f.path) == key).map(|f| content_hash(&f.lines));
+        match hash {
+            Some(hash) if !lines.is_empty() => {
+                self.excluded.insert(key, (hash, lines));
+            }
+            _ => {
+                self.excluded.remove(&key);
+            }
+        }
+    }
+
+    /// Remember how a send of `file` that just finished went.
+    pub fn record_file_send(&mut self, file: &TextFile, report: &SendReport) {
+        let last = LastSend {
+            at: Instant::now(),
+            delivered: report.targets.iter().map(|t| t.sent).max().unwrap_or(0),
+            result: SendResult::from_report(report),
+            hash: content_hash(&file.lines),
+        };
+        self.last_sends.insert(path_key(&file.path), last);
+    }
+
+    /// The last send of `file` this session, unless the file changed
+    /// since.
+    pub fn last_send(&self, file: &TextFile) -> Option<&LastSend> {
+        if self.last_sends.is_empty() {
+            return None;
+        }
+        self.last_sends.get(&path_key(&file.path)).filter(|last| last.hash == content_hash(&file.lines))
+    }
+
+    /// How long ago `file` was last sent and how many lines that send
+    /// delivered, if it delivered any less than `window` ago.
+    pub fn recent_send(&self, file: &TextFile, window: Duration) -> Option<(Duration, usize)> {
+        let last = self.last_send(file).filter(|last| last.delivered > 0)?;
+        let ago = last.at.elapsed();
+        (ago < window).then_some((ago, last.delivered))
+    }
+
+    /// The send options last picked for `file`, or `defaults` if there are
+    /// none yet. Manual advance follows the file's toggle in the list.
+    pub fn send_choices(&self, file: &TextFile, defaults: SendChoices) -> SendChoices {
+        let choices = self.send_choices.get(&path_key(&file.path)).copied().unwrap_or(defaults);
+        SendChoices { manual_advance: self.is_manual_advance(file), ..choices }
+    }
+
+    /// Remember the send options picked for `file` for the rest of the
+    /// session, including its manual advance toggle.
+    pub fn set_send_choices(&mut self, file: &TextFile, choices: SendChoices) {
+        let key = path_key(&file.path);
+        if choices.manual_advance {
+            self.manual_advance.insert(key.clone());
+        } else {
+            self.manual_advance.remove(&key);
+        }
+        self.send_choices.insert(key, choices);
+    }
+
+    /// Drop the excluded lines of files that are gone or whose contents
+    /// changed; the marks may point at other lines now.
+    fn forget_stale_exclusions(&mut self) {
+        let files = &self.files;
+        self.excluded.retain(|key, (hash, _)| {
+            let current = files.iter().any(|f| path_key(&f.path) == *key && content_hash(&f.lines) == *hash);
+            if !current {
+                log(&format!("Excluded lines of '{}' cleared, the file changed", key));
+            }
+            current
+        });
+    }
+
+    /// Read the lifetime stats from the state file, if they're kept.
+    pub fn load_lifetime_stats(&mut self) {
+        if PERSIST_LIFETIME_STATS {
+            self.lifetime_stats = Stats::load_lifetime();
+        }
+    }
+
+    /// Mark a background scan as running or finished.
+    pub fn set_scanning(&mut self, scanning: bool) {
+        if !scanning {
+            self.scanning_since = None;
+        } else if self.scanning_since.is_none() {
+            self.scanning_since = Some(Instant::now());
+        }
+    }
+
+    /// When the running background scan started, if one is running.
+    pub fn scanning_since(&self) -> Option<Instant> {
+        self.scanning_since
+    }
+
+    /// Replace a file's contents after it was reloaded from disk.
+    pub fn replace_file(&mut self, updated: TextFile) {
+        let key = path_key(&updated.path);
+        if let Some(file) = self.files.iter_mut().find(|f| path_key(&f.path) == key) {
+            // Keep the discovery name (relative path) and tags
+            let name = std::mem::take(&mut file.name);
+            let tags = std::mem::take(&mut file.tags);
+            *file = TextFile { name, tags, ..updated };
+        }
+        self.update_duplicates();
+        self.forget_stale_exclusions();
+    }
+
+    /// Ad-hoc lines sent this session, oldest first.
+    pub fn adhoc_history(&self) -> &[String] {
+        &self.adhoc_history
+    }
+
+    /// Remember an ad-hoc line, moving a repeated one to the end.
+    pub fn push_adhoc(&mut self, text: &str) {
+        self.adhoc_history.retain(|line| line != text);
+        self.adhoc_history.push(text.to_string());
+    }
+
+    /// Apply one change seen by the directory watcher in the scan
+    /// directory `dir`, re-reading only that file. A new file is inserted
+    /// in name order (without tags until the next rescan); the search and
+    /// the selection stay. Returns whether the list changed.
+    pub fn apply_file_event(&mut self, event: &FileEvent, dir: &Path) -> bool {
+        let key = path_key(event.path());
+        let listed = self.files.iter().any(|f| path_key(&f.path) == key);
+        let loaded = match event {
+            FileEvent::Removed(_) => None,
+            FileEvent::Changed(path) => files::load_in(dir, path.clone()).unwrap_or_else(|skipped| {
+                log(&format!("Watcher skipped {}: {}", skipped.name, skipped.reason));
+                None
+            }),
+        };
+        match loaded {
+            Some(mut file) if listed => {
+                file.mark_reloaded(LoadReason::Watcher);
+                self.replace_file(file);
+            }
+            Some(mut file) => {
+                log(&format!("Watcher added {}", file.name));
+                file.provenance = Provenance::new(LoadReason::Watcher);
+                let name = file.name.to_lowercase();
+                let index = self.files.partition_point(|f| f.name.to_lowercase() < name);
+                self.files.insert(index, file);
+                self.update_filter();
+                self.update_duplicates();
+            }
+            None if listed => {
+                log(&format!("Watcher removed {}", event.path().display()));
+                self.remove_file(event.path());
+            }
+            None => return false,
+        }
+        true
+    }
+
+    /// Remove a file that no longer exists on disk.
+    pub fn remove_file(&mut self, path: &Path) {
+        // The selected file may be the one removed; its neighbour takes over
+        let position = self.selected_row();
+        let key = path_key(path);
+        self.files.retain(|f| path_key(&f.path) != key);
+        self.refilter(position);
+        self.update_duplicates();
+        self.forget_stale_exclusions();
+    }
+
+    /// Recompute which files are copies of each other.
+    fn update_duplicates(&mut self) {
+        self.duplicates = files::duplicate_groups(&self.files)
+            .into_iter()
+            .map(|group| group.into_iter().map(|i| (self.files[i].path.clone(), self.files[i].name.clone())).collect())
+            .collect();
+    }
+
+    /// Name of the file that `file` is a copy of, if it is one. The first
+    /// file of a group in list order counts as the original.
+    pub fn duplicate_of(&self, file: &TextFile) -> Option<&str> {
+        self.duplicates
+            .iter()
+            .find(|group| group[1..].iter().any(|(path, _)| path == &file.path))
+            .map(|group| group[0].1.as_str())
+    }
+
+    /// Number of files that are copies of an earlier file.
+    pub fn duplicate_count(&self) -> usize {
+        self.duplicates.iter().map(|group| group.len() - 1).sum()
+    }
+
+    /// Select the next listed file with the same messages as the selected
+    /// one, wrapping around its group. False if it has no listed copy.
+    pub fn select_next_duplicate(&mut self) -> bool {
+        let Some(selected) = self.selected.clone() else {
+            return false;
+        };
+        let Some(group) = self.duplicates.iter().find(|group| group.iter().any(|(path, _)| path == &selected)) else {
+            return false;
+        };
+        let start = group.iter().position(|(path, _)| path == &selected).unwrap_or(0);
+        let next = (1..group.len()).map(|step| &group[(start + step) % group.len()].0).find_map(|path| {
+            self.filtered_indices.iter().position(|&i| &self.files[i].path == path)
+        });
+        match next {
+            Some(position) => {
+                self.select_at(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Update session and lifetime stats, and the cooldown, from a send
+    /// event.
+    ///
+    /// Lifetime stats are saved once the session finishes.
+    pub fn record_send_event(&mut self, event: &SendEvent) {
+        self.session_stats.record(event);
+        self.lifetime_stats.record(event);
+        if let SendEvent::LineFinished { target, result: Ok(_), .. } = event {
+            self.cooldown.record(target, Instant::now());
+        }
+        if let SendEvent::Finished { report } = event {
+            if let Some(requester) = &report.cancelled_by {
+                self.set_notice(format!("Send cancelled by {}", requester));
+            }
+        }
+        if PERSIST_LIFETIME_STATS && matches!(event, SendEvent::Finished { .. }) {
+            if let Err(e) = self.lifetime_stats.save_lifetime() {
+                log(&e);
+            }
+        }
+    }
+
+    /// Lines sent this session per target window.
+    pub fn cooldown(&self) -> &Cooldown {
+        &self.cooldown
+    }
+
+    pub fn cooldown_mut(&mut self) -> &mut Cooldown {
+        &mut self.cooldown
+    }
+
+    /// Get stats for this run of the program.
+    pub fn session_stats(&self) -> &Stats {
+        &self.session_stats
+    }
+
+    /// Get cumulative stats across runs.
+    pub fn lifetime_stats(&self) -> &Stats {
+        &self.lifetime_stats
+    }
+
+    /// Reset the lifetime stats to zero (and save if persisted).
+    pub fn reset_lifetime_stats(&mut self) -> Result<(), String> {
+        self.lifetime_stats = Stats::default();
+        log("Lifetime stats reset");
+        if PERSIST_LIFETIME_STATS {
+            self.lifetime_stats.save_lifetime()?;
+        }
+        Ok(())
     }
 
     /// Get the current error message.
@@ -129,12 +931,46 @@ impl App {
         self.update_filter();
     }
 
+    /// Replace the whole search query.
+    pub fn set_search(&mut self, query: &str) {
+        self.search_query = query.to_string();
+        self.update_filter();
+    }
+
     /// Remove last character from search query.
     pub fn remove_search_char(&mut self) {
         self.search_query.pop();
         self.update_filter();
     }
 
+    /// Cycle the search through `tag:` filters for every known tag, then
+    /// back to no filter.
+    pub fn cycle_tag_filter(&mut self) {
+        let tags = tags::all_tags(&self.files);
+        let current = self.search_query.strip_prefix("tag:");
+        let next = match current.and_then(|c| tags.iter().position(|t| t == c)) {
+            Some(i) => tags.get(i + 1),
+            None => tags.first(),
+        };
+        self.search_query = next.map(|t| format!("tag:{}", t)).unwrap_or_default();
+        self.update_filter();
+    }
+
+    /// Get the input focus.
+    pub fn focus(&self) -> Focus {
+        self.focus
+    }
+
+    /// Send typed characters to the search box.
+    pub fn focus_search(&mut self) {
+        self.focus = Focus::Search;
+    }
+
+    /// Give focus back to the file list, keeping the query.
+    pub fn focus_list(&mut self) {
+        self.focus = Focus::List;
+    }
+
     /// Get the search query.
     pub fn search_query(&self) -> &str {
         &self.search_query
@@ -155,3 +991,495 @@ impl App {
         self.files.len()
     }
 }
+
+/// Check a file against a lowercase search query.
+///
+/// Words starting with `tag:` must each match one of the file's tags; the
+/// remaining text must appear in the file name.
+fn matches_query(file: &TextFile, query: &str) -> bool {
+    let mut name_words = Vec::new();
+    for word in query.split_whitespace() {
+        match word.strip_prefix("tag:") {
+            Some(tag) if !tag.is_empty() => {
+                if !file.tags.iter().any(|t| t == tag) {
+                    return false;
+                }
+            }
+            Some(_) => {}
+            None => name_words.push(word),
+        }
+    }
+    let name = name_words.join(" ");
+    name.is_empty() || file.name.to_lowercase().contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::files::Line;
+    use crate::send::TargetReport;
+
+    fn file(name: &str, tags: &[&str]) -> TextFile {
+        TextFile { tags: tags.iter().map(|t| t.to_string()).collect(), ..TextFile::with_lines(name, &[]) }
+    }
+
+    #[test]
+    fn test_tag_query() {
+        let greetings = file("Greetings.txt", &["aram", "ranked"]);
+        assert!(matches_query(&greetings, ""));
+        assert!(matches_query(&greetings, "greet"));
+        assert!(matches_query(&greetings, "tag:aram"));
+        assert!(matches_query(&greetings, "tag:aram greet"));
+        assert!(!matches_query(&greetings, "tag:troll"));
+        assert!(!matches_query(&greetings, "tag:aram taunt"));
+        assert!(matches_query(&greetings, "tag:"));
+    }
+
+    #[test]
+    fn test_cycle_tag_filter() {
+        let mut app = App::new(vec![file("a.txt", &["ranked"]), file("b.txt", &["aram"]), file("c.txt", &[])]);
+        app.cycle_tag_filter();
+        assert_eq!((app.search_query(), app.filtered_count()), ("tag:aram", 1));
+        app.cycle_tag_filter();
+        assert_eq!(app.search_query(), "tag:ranked");
+        app.cycle_tag_filter();
+        assert_eq!((app.search_query(), app.filtered_count()), ("", 3));
+    }
+
+    #[test]
+    fn test_marks_survive_search() {
+        let mut app = App::new(vec![file("a.txt", &[]), file("b.txt", &[]), file("c.txt", &[])]);
+        assert_eq!(app.marked_or_selected()[0].name, "a.txt");
+        app.move_down();
+        app.toggle_mark();
+        app.move_down();
+        app.toggle_mark();
+        app.add_search_char('b');
+        let names: Vec<&str> = app.marked_or_selected().iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["b.txt", "c.txt"]);
+        app.toggle_mark();
+        assert_eq!(app.marked_or_selected().len(), 1);
+    }
+
+    fn files(names: &[&str]) -> Vec<TextFile> {
+        names.iter().map(|name| file(name, &[])).collect()
+    }
+
+    fn selected_name(app: &App) -> Option<&str> {
+        app.selection().map(|f| f.name.as_str())
+    }
+
+    #[test]
+    fn test_selection_wraps_around() {
+        let mut app = App::new(files(&["a.txt", "b.txt", "c.txt"]));
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("a.txt"), 0));
+        app.move_up();
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("c.txt"), 2));
+        app.move_down();
+        assert_eq!(selected_name(&app), Some("a.txt"));
+
+        let mut empty = App::new(Vec::new());
+        empty.move_up();
+        empty.move_down();
+        assert_eq!((selected_name(&empty), empty.selected_index()), (None, 0));
+    }
+
+    #[test]
+    fn test_selection_follows_file_through_filters() {
+        let mut app = App::new(files(&["aram.txt", "banter.txt", "bans.txt", "cheer.txt"]));
+        app.move_down();
+        app.move_down();
+        assert_eq!(selected_name(&app), Some("bans.txt"));
+
+        // Still listed: same file, new position
+        app.add_search_char('b');
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("bans.txt"), 1));
+        app.remove_search_char();
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("bans.txt"), 2));
+
+        // Filtered out: the entry at the old position, clamped to the list
+        app.move_down();
+        app.add_search_char('b');
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("bans.txt"), 1));
+        app.remove_search_char();
+        app.add_search_char('z');
+        assert_eq!((selected_name(&app), app.filtered_count()), (None, 0));
+        app.remove_search_char();
+        assert_eq!(selected_name(&app), Some("aram.txt"));
+    }
+
+    #[test]
+    fn test_selection_survives_rescans() {
+        let mut app = App::new(Vec::new());
+        assert_eq!(selected_name(&app), None);
+        app.set_files(files(&["b.txt", "c.txt"]));
+        assert_eq!(selected_name(&app), Some("b.txt"));
+        app.move_down();
+
+        // Added before it and reordered: still the same file
+        app.set_files(files(&["a.txt", "d.txt", "c.txt", "b.txt"]));
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("c.txt"), 2));
+
+        // A rescan clears the search but keeps the file
+        app.add_search_char('c');
+        app.set_files(files(&["c.txt", "a.txt"]));
+        assert_eq!((app.search_query(), selected_name(&app), app.selected_index()), ("", Some("c.txt"), 0));
+
+        // Gone from the rescan: back to the first file
+        app.set_files(files(&["a.txt", "b.txt"]));
+        assert_eq!(selected_name(&app), Some("a.txt"));
+    }
+
+    #[test]
+    fn test_provenance_follows_scans_and_reloads() {
+        let reasons = |app: &App| app.files.iter().map(|f| f.provenance.reason).collect::<Vec<_>>();
+        let mut app = App::new(Vec::new());
+        app.set_files(files(&["a.txt", "b.txt"]));
+        assert_eq!(reasons(&app), vec![LoadReason::Initial; 2]);
+
+        let mut edited = file("a.txt", &[]);
+        edited.mark_reloaded(LoadReason::Edit);
+        app.replace_file(edited);
+        assert_eq!(reasons(&app), vec![LoadReason::Edit, LoadReason::Initial]);
+
+        let mut changed = file("b.txt", &[]);
+        changed.mark_reloaded(LoadReason::Watcher);
+        app.replace_file(changed);
+        assert_eq!(reasons(&app), vec![LoadReason::Edit, LoadReason::Watcher]);
+
+        // A rescan reads every file again
+        app.set_files(files(&["a.txt", "b.txt"]));
+        assert_eq!(reasons(&app), vec![LoadReason::Rescan; 2]);
+
+        // Files handed to the constructor count as the first scan
+        let mut app = App::new(files(&["a.txt"]));
+        app.set_files(files(&["a.txt"]));
+        assert_eq!(reasons(&app), vec![LoadReason::Rescan]);
+    }
+
+    #[test]
+    fn test_watched_burst_applied_once_keeping_selection() {
+        use crate::watch::Debouncer;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("madtyping-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["b.txt", "c.txt", "d.txt"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let mut app = App::new(Vec::new());
+        app.set_files(files::scan_in(&dir, files::ScanOptions::default()).unwrap().files);
+        app.move_down();
+        app.add_search_char('.');
+        assert_eq!(selected_name(&app), Some("c.txt"));
+
+        // Saving c.txt several times, adding a.txt and deleting d.txt
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_millis(300));
+        fs::write(dir.join("c.txt"), "gl\nhf").unwrap();
+        fs::write(dir.join("a.txt"), "new").unwrap();
+        fs::remove_file(dir.join("d.txt")).unwrap();
+        for (ms, event) in [
+            (0, FileEvent::Changed(dir.join("c.txt"))),
+            (5, FileEvent::Changed(dir.join(".c.txt.swp"))),
+            (20, FileEvent::Changed(dir.join("c.txt"))),
+            (40, FileEvent::Changed(dir.join("a.txt"))),
+            (60, FileEvent::Removed(dir.join("d.txt"))),
+            (80, FileEvent::Changed(dir.join("c.txt"))),
+        ] {
+            debouncer.push(event, start + Duration::from_millis(ms));
+        }
+        let batch = debouncer.take(start + Duration::from_millis(400));
+        assert_eq!(batch.len(), 3);
+        let changed = batch.iter().filter(|event| app.apply_file_event(event, &dir)).count();
+        assert_eq!(changed, 3);
+
+        let names: Vec<&str> = app.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["a.txt", "b.txt", "c.txt"]);
+        assert_eq!((selected_name(&app), app.search_query()), (Some("c.txt"), "."));
+        assert_eq!(app.selection().unwrap().lines.len(), 2);
+        assert_eq!(app.selection().unwrap().provenance.reason, LoadReason::Watcher);
+        assert!(!app.apply_file_event(&FileEvent::Removed(dir.join("d.txt")), &dir));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_newly_skipped_files_reported_once() {
+        let skipped = |names: &[&str]| -> Vec<SkippedFile> {
+            names
+                .iter()
+                .map(|name| SkippedFile {
+                    name: name.to_string(),
+                    path: PathBuf::from(name),
+                    reason: files::BINARY_SKIP_REASON.to_string(),
+                })
+                .collect()
+        };
+        let mut app = App::new(Vec::new());
+        assert_eq!(app.set_skipped(skipped(&["a.txt"])), ["a.txt"]);
+        assert_eq!(app.set_skipped(skipped(&["a.txt", "b.txt"])), ["b.txt"]);
+        assert!(app.set_skipped(skipped(&["b.txt"])).is_empty());
+        assert_eq!(app.skipped().len(), 1);
+        assert_eq!(app.set_skipped(skipped(&["a.txt"])), ["a.txt"]);
+    }
+
+    /// Folder headers as "name/ (count)" (with "+" when collapsed) and
+    /// file names, in list order.
+    fn row_names(app: &App) -> Vec<String> {
+        app.list_rows()
+            .iter()
+            .map(|row| match row {
+                ListRow::Folder { name, count, collapsed } => {
+                    format!("{}/ ({}){}", name, count, if *collapsed { "+" } else { "" })
+                }
+                ListRow::File(file) => file.name.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_folders_grouped_under_headers() {
+        let mut app = App::new(files(&["aram/b.txt", "aram/sub/c.txt", "aram/z.txt", "gg.txt", "ranked/a.txt"]));
+        assert_eq!(
+            row_names(&app),
+            ["gg.txt", "aram/ (2)", "aram/b.txt", "aram/z.txt", "aram/sub/ (1)", "aram/sub/c.txt", "ranked/ (1)", "ranked/a.txt"]
+        );
+        assert_eq!(app.list_grid(200).columns, 1);
+        assert!(!App::new(files(&["a.txt", "b.txt"])).is_grouped());
+
+        // Expanded headers are passed over
+        app.select_by_name("gg.txt");
+        app.move_down();
+        assert_eq!(selected_name(&app), Some("aram/b.txt"));
+        app.move_up();
+        app.move_up();
+        assert_eq!(selected_name(&app), Some("ranked/a.txt"));
+    }
+
+    #[test]
+    fn test_collapsed_folders() {
+        let mut app = App::new(files(&["aram/b.txt", "aram/z.txt", "gg.txt", "ranked/a.txt"]));
+        app.select_by_name("aram/z.txt");
+        app.collapse_folder();
+        assert_eq!((selected_name(&app), app.selected_folder()), (None, Some("aram")));
+        assert_eq!(row_names(&app), ["gg.txt", "aram/ (2)+", "ranked/ (1)", "ranked/a.txt"]);
+
+        // The collapsed header is a stop; its files are skipped
+        app.move_down();
+        assert_eq!(selected_name(&app), Some("ranked/a.txt"));
+        app.move_up();
+        assert_eq!(app.selected_folder(), Some("aram"));
+
+        // Searching shows matching folders expanded, and the collapse is kept
+        app.set_search("z");
+        assert_eq!(row_names(&app), ["aram/ (1)", "aram/z.txt"]);
+        app.set_search("");
+        assert_eq!(row_names(&app)[1], "aram/ (2)+");
+
+        app.toggle_folder();
+        assert_eq!(row_names(&app)[1..4], ["aram/ (2)", "aram/b.txt", "aram/z.txt"]);
+        app.collapse_folder();
+        // Jumping to a file in a collapsed folder opens it
+        assert!(app.select_by_name("aram/b.txt"));
+        assert_eq!(row_names(&app)[1], "aram/ (2)");
+    }
+
+    #[test]
+    fn test_view_target_used_once_for_its_file() {
+        let mut app = App::new(files(&["a.txt", "b.txt"]));
+        let (a, mut b) = (file("a.txt", &[]), file("b.txt", &[]));
+        b.warnings.push("line(s) 1 truncated to 4096 characters".to_string());
+        assert_eq!(app.take_view_target(&a), None);
+        app.set_view_target(Path::new("a.txt"), 7);
+        assert_eq!(app.take_view_target(&b), Some(0));
+        assert_eq!(app.take_view_target(&a), Some(7));
+        assert_eq!(app.take_view_target(&a), None);
+    }
+
+    #[test]
+    fn test_excluded_lines_cleared_when_contents_change() {
+        let mut gg = file("gg.txt", &[]);
+        gg.lines = vec![Line::new("gl"), Line::new("hf"), Line::new("gg")];
+        let mut app = App::new(vec![gg.clone(), file("b.txt", &[])]);
+        app.set_excluded_lines(Path::new("gg.txt"), HashSet::from([0, 2]));
+        app.set_excluded_lines(Path::new("missing.txt"), HashSet::from([0]));
+        assert_eq!(app.excluded_lines(&gg), HashSet::from([0, 2]));
+        assert!(app.excluded_lines(&file("b.txt", &[])).is_empty());
+
+        // A rescan with the same contents keeps them, an edit drops them
+        app.set_files(vec![gg.clone(), file("b.txt", &[])]);
+        assert_eq!(app.excluded_lines(&gg).len(), 2);
+        gg.lines[1] = Line::new("wp");
+        app.replace_file(gg.clone());
+        assert!(app.excluded_lines(&gg).is_empty());
+        app.set_excluded_lines(Path::new("gg.txt"), HashSet::from([1]));
+        assert_eq!(app.excluded_lines(&gg), HashSet::from([1]));
+        app.set_excluded_lines(Path::new("gg.txt"), HashSet::new());
+        assert!(app.excluded_lines(&gg).is_empty());
+    }
+
+    #[test]
+    fn test_removing_files_keeps_selection_in_place() {
+        let mut app = App::new(files(&["a.txt", "b.txt", "c.txt"]));
+        app.move_down();
+        app.remove_file(Path::new("a.txt"));
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("b.txt"), 0));
+        app.remove_file(Path::new("b.txt"));
+        assert_eq!(selected_name(&app), Some("c.txt"));
+        app.remove_file(Path::new("c.txt"));
+        assert_eq!((selected_name(&app), app.total_count()), (None, 0));
+
+        // The last entry removed: its new last neighbour
+        let mut app = App::new(files(&["a.txt", "b.txt", "c.txt"]));
+        app.move_up();
+        app.remove_file(Path::new("c.txt"));
+        assert_eq!(selected_name(&app), Some("b.txt"));
+    }
+
+    #[test]
+    fn test_select_by_name() {
+        let mut app = App::new(files(&["aram/ez.txt", "gg.txt", "taunts.md"]));
+        assert!(app.select_by_name("TAUNTS.md"));
+        assert_eq!((selected_name(&app), app.selected_index()), (Some("taunts.md"), 2));
+        assert!(!app.select_by_name("missing.txt"));
+        assert_eq!(selected_name(&app), Some("taunts.md"));
+
+        // Only listed files can be selected
+        app.add_search_char('g');
+        assert!(!app.select_by_name("taunts.md"));
+        assert!(app.select_by_name("gg.txt"));
+        app.replace_file(file("gg.txt", &[]));
+        assert_eq!(selected_name(&app), Some("gg.txt"));
+    }
+
+    #[test]
+    fn test_duplicates_found_and_stepped_through() {
+        let mut app = App::new(vec![
+            TextFile::with_lines("openers.txt", &["gl hf", "ward drag"]),
+            TextFile::with_lines("gg.txt", &["gg"]),
+            TextFile::with_lines("openers (1).txt", &["gl hf", "ward drag"]),
+            TextFile::with_lines("reordered.txt", &["ward drag", "gl hf"]),
+            TextFile::with_lines("aram/openers.txt", &["gl hf", "ward drag"]),
+        ]);
+        assert_eq!(app.duplicate_count(), 2);
+        assert_eq!(app.duplicate_of(&app.files[2]), Some("openers.txt"));
+        assert_eq!(app.duplicate_of(&app.files[0]), None);
+        assert_eq!(app.duplicate_of(&app.files[3]), None);
+
+        assert!(app.select_next_duplicate());
+        assert_eq!(selected_name(&app), Some("openers (1).txt"));
+        assert!(app.select_next_duplicate());
+        assert_eq!(selected_name(&app), Some("aram/openers.txt"));
+        assert!(app.select_next_duplicate());
+        assert_eq!(selected_name(&app), Some("openers.txt"));
+
+        // Copies hidden by the search are skipped
+        app.add_search_char('a');
+        assert!(app.select_by_name("aram/openers.txt"));
+        assert!(!app.select_next_duplicate());
+        app.remove_search_char();
+        assert!(app.select_by_name("gg.txt"));
+        assert!(!app.select_next_duplicate());
+
+        app.remove_file(Path::new("openers.txt"));
+        assert_eq!(app.duplicate_of(&app.files[1]), None);
+        assert_eq!(app.duplicate_of(&app.files[3]), Some("openers (1).txt"));
+    }
+
+    #[test]
+    fn test_recent_send_within_window() {
+        let mut app = App::new(vec![file("greeting.txt", &[]), file("gg.txt", &[])]);
+        let (greeting, gg) = (app.files[0].clone(), app.files[1].clone());
+        assert_eq!(app.recent_send(&greeting, Duration::from_secs(60)), None);
+
+        app.record_file_send(&greeting, &report(&[22, 20]));
+        let (ago, delivered) = app.recent_send(&greeting, Duration::from_secs(60)).unwrap();
+        assert!(ago < Duration::from_secs(60));
+        assert_eq!(delivered, 22);
+        assert_eq!(app.recent_send(&gg, Duration::from_secs(60)), None);
+        // A window of 0 never asks
+        assert_eq!(app.recent_send(&greeting, Duration::ZERO), None);
+    }
+
+    /// A finished send with one target per count of sent lines.
+    fn report(sent: &[usize]) -> SendReport {
+        let targets = sent.iter().map(|&sent| TargetReport { sent, ..TargetReport::default() }).collect();
+        SendReport { targets, ..SendReport::default() }
+    }
+
+    #[test]
+    fn test_last_send_result_until_the_file_changes() {
+        let mut app = App::new(vec![file("greeting.txt", &[])]);
+        let mut greeting = app.files[0].clone();
+        greeting.lines = vec![Line::new("gl"), Line::new("hf")];
+        assert_eq!(app.last_send(&greeting), None);
+
+        app.record_file_send(&greeting, &report(&[2]));
+        assert_eq!(app.last_send(&greeting).map(|l| l.result), Some(SendResult::Sent));
+
+        let mut cancelled = report(&[1]);
+        cancelled.cancelled = true;
+        cancelled.targets[0].skipped = 1;
+        app.record_file_send(&greeting, &cancelled);
+        assert_eq!(app.last_send(&greeting).map(|l| l.result), Some(SendResult::Partial { reached: 1 }));
+
+        let mut failed = report(&[1, 0]);
+        failed.targets[1].failed = Some((0, "gl".to_string(), "window closed".to_string()));
+        app.record_file_send(&greeting, &failed);
+        assert_eq!(app.last_send(&greeting).map(|l| l.result), Some(SendResult::Failed { line: 0 }));
+
+        // An edited file drops the marker, and nothing delivered never asks
+        // before a resend
+        assert_eq!(app.recent_send(&greeting, Duration::from_secs(60)).map(|(_, d)| d), Some(1));
+        greeting.lines.push(Line::new("gg"));
+        assert_eq!(app.last_send(&greeting), None);
+        app.record_file_send(&greeting, &report(&[0]));
+        assert_eq!(app.recent_send(&greeting, Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn test_send_choices_are_remembered_per_file() {
+        let mut app = App::new(vec![file("greeting.txt", &[]), file("gg.txt", &[])]);
+        let (greeting, gg) = (app.files[0].clone(), app.files[1].clone());
+        let defaults = SendChoices::default();
+        assert_eq!(app.send_choices(&greeting, defaults), defaults);
+
+        let picked = SendChoices { skip_repeats: true, manual_advance: true, ..defaults };
+        app.set_send_choices(&greeting, picked);
+        assert_eq!(app.send_choices(&greeting, defaults), picked);
+        assert!(app.is_manual_advance(&greeting));
+        assert_eq!(app.send_choices(&gg, defaults), defaults);
+
+        // Turning manual advance off in the list shows in the options
+        app.toggle_manual_advance();
+        assert!(!app.send_choices(&greeting, defaults).manual_advance);
+    }
+
+    #[test]
+    fn test_list_grid() {
+        assert_eq!(ListGrid::new(80, 79), ListGrid { columns: 1, rows: 80 });
+        assert_eq!(ListGrid::new(80, 200), ListGrid { columns: 2, rows: 40 });
+        assert_eq!(ListGrid::new(3, 400), ListGrid { columns: 3, rows: 1 });
+        assert_eq!(ListGrid::new(0, 200), ListGrid { columns: 1, rows: 1 });
+
+        let grid = ListGrid::new(79, 200);
+        assert_eq!(grid.cell(45), (1, 5));
+        assert_eq!(grid.index(1, 39, 79), None);
+        assert_eq!(grid.scroll_offset(45, 30), 0);
+        assert_eq!(grid.scroll_offset(39, 30), 10);
+
+        let mut app = App::new((0..79).map(|i| file(&format!("{}.txt", i), &[])).collect());
+        app.move_right(grid);
+        assert_eq!(app.selected_index(), 40);
+        app.move_right(grid);
+        assert_eq!(app.selected_index(), 40);
+        app.move_left(grid);
+        app.move_left(grid);
+        assert_eq!(app.selected_index(), 0);
+        app.move_up();
+        app.move_left(grid);
+        app.move_right(grid);
+        assert_eq!(app.selected_index(), 78);
+    }
+}
@@ -0,0 +1,119 @@
+//! Archiving sent files for MadTyping
+//!
+//! One-shot files, such as tournament announcements, can move themselves
+//! out of the list once they have been sent. A file is archived when its
+//! front matter says `archive_after_send: true`, or when its name matches
+//! the `archive_after_send` glob of the config (`false` in the front matter
+//! keeps a matching file). It is moved after a send that reached every
+//! window with every line ([`SendReport::fully_sent`]); cancelled, failed or
+//! partial sends and dry runs leave it where it is.
+//!
+//! Files go to the [`ARCHIVE_DIRECTORY`] folder of the scan directory, named
+//! with a `-YYYYMMDD-HHMMSS` suffix (UTC, like report files) and a number on
+//! top if that name is taken. The folder is never scanned, so archived files
+//! don't show up again. Every move is logged.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::{Config, ARCHIVE_DIRECTORY};
+use crate::files::TextFile;
+use crate::logging::log;
+use crate::pack::glob_match;
+use crate::report::{file_stamp, now_secs};
+use crate::send::SendReport;
+
+/// Whether `file` is to be archived after a full send with `config`.
+pub fn wants_archive(file: &TextFile, config: &Config) -> bool {
+    file.front
+        .archive_after_send
+        .unwrap_or_else(|| !config.archive_after_send.is_empty() && glob_match(&config.archive_after_send, &file.name))
+}
+
+/// Whether `file` is to be archived now that a send ended with `report`.
+pub fn should_archive(file: &TextFile, config: &Config, report: &SendReport) -> bool {
+    report.fully_sent() && wants_archive(file, config)
+}
+
+/// Free path in `archive_dir` for the file at `path`, stamped with `stamp`:
+/// `name-stamp.ext`, then `name-stamp-2.ext` and so on.
+pub fn archive_path(archive_dir: &Path, path: &Path, stamp: &str) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let mut candidate = archive_dir.join(format!("{}-{}{}", stem, stamp, extension));
+    let mut number = 2;
+    while candidate.exists() {
+        candidate = archive_dir.join(format!("{}-{}-{}{}", stem, stamp, number, extension));
+        number += 1;
+    }
+    candidate
+}
+
+/// Move `file` into the archive folder of `scan_dir`, returning where it
+/// went.
+pub fn archive(file: &TextFile, scan_dir: &Path) -> Result<PathBuf, String> {
+    let archive_dir = scan_dir.join(ARCHIVE_DIRECTORY);
+    fs::create_dir_all(&archive_dir).map_err(|e| format!("Can't create {}: {}", archive_dir.display(), e))?;
+    let destination = archive_path(&archive_dir, &file.path, &file_stamp(now_secs()));
+    fs::rename(&file.path, &destination).map_err(|e| format!("Can't archive {}: {}", file.name, e))?;
+    log(&format!("Archived {} to {}", file.name, destination.display()));
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::{scan_in, ScanOptions};
+    use crate::send::TargetReport;
+
+    fn report(sent: usize) -> SendReport {
+        SendReport {
+            targets: vec![TargetReport { window_title: "League".to_string(), sent, ..TargetReport::default() }],
+            ..SendReport::default()
+        }
+    }
+
+    #[test]
+    fn test_only_full_sends_of_chosen_files_archive() {
+        let dir = std::env::temp_dir().join(format!("madtyping-archive-choice-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("finals.txt"), "---\narchive_after_send: true\n---\nfinals at 8\n").unwrap();
+        fs::write(dir.join("announce-day1.txt"), "day 1\n").unwrap();
+        fs::write(dir.join("gg.txt"), "gg\n").unwrap();
+        let files = scan_in(&dir, ScanOptions::default()).unwrap().files;
+        let config = Config { archive_after_send: "announce-*".to_string(), ..Config::default() };
+
+        let chosen: Vec<&str> = files.iter().filter(|f| wants_archive(f, &config)).map(|f| f.name.as_str()).collect();
+        assert_eq!(chosen, vec!["announce-day1.txt", "finals.txt"]);
+        assert!(should_archive(&files[0], &config, &report(1)));
+        assert!(!should_archive(&files[0], &config, &SendReport { cancelled: true, ..report(1) }));
+        assert!(!should_archive(&files[0], &config, &report(0)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_archived_files_get_free_names_and_leave_the_scan() {
+        let dir = std::env::temp_dir().join(format!("madtyping-archive-{}", std::process::id()));
+        let archive_dir = dir.join(ARCHIVE_DIRECTORY);
+        fs::create_dir_all(&archive_dir).unwrap();
+        fs::write(archive_dir.join("finals-20240115-134510.txt"), "old").unwrap();
+        assert_eq!(
+            archive_path(&archive_dir, &dir.join("finals.txt"), "20240115-134510"),
+            archive_dir.join("finals-20240115-134510-2.txt")
+        );
+        assert_eq!(archive_path(&archive_dir, Path::new("notes"), "x"), archive_dir.join("notes-x"));
+
+        fs::write(dir.join("finals.txt"), "finals at 8\n").unwrap();
+        fs::write(dir.join("gg.txt"), "gg\n").unwrap();
+        let files = scan_in(&dir, ScanOptions::default()).unwrap().files;
+        assert_eq!(files.len(), 2);
+        let destination = archive(&files[0], &dir).unwrap();
+        assert!(destination.starts_with(&archive_dir) && !dir.join("finals.txt").exists());
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "finals at 8\n");
+        let names: Vec<String> = scan_in(&dir, ScanOptions::default()).unwrap().files.into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["gg.txt"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
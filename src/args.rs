@@ -0,0 +1,524 @@
+//! Command-line argument parsing for MadTyping
+//!
+//! A small hand-rolled parser; the interactive UI needs no arguments,
+//! so the flags only cover informational and scripting use. All flags
+//! are described in [`FLAGS`], which also drives `--help` and the
+//! generated shell completions.
+
+use crate::completions::Shell;
+use crate::config::Config;
+use crate::platform::{ChatScope, Speed};
+use crate::progress::ProgressMode;
+use crate::send::{BroadcastMode, SendChoices};
+
+/// Description of a command-line flag.
+pub struct Flag {
+    /// Long form without dashes (e.g. "version")
+    pub long: &'static str,
+    /// Optional single-letter short form
+    pub short: Option<char>,
+    /// Name of the value the flag takes, if any (e.g. "SHELL")
+    pub value: Option<&'static str>,
+    /// Fixed set of accepted values, used for completion
+    pub choices: &'static [&'static str],
+    /// One-line description for `--help`
+    pub help: &'static str,
+}
+
+/// Values accepted by `--speed`, the steps of `SPEED_STEPS_PERCENT`.
+const SPEEDS: &[&str] = &["25", "50", "75", "100", "150", "200", "300", "400"];
+
+/// All supported flags, in `--help` order.
+pub const FLAGS: &[Flag] = &[
+    Flag {
+        long: "version",
+        short: Some('V'),
+        value: None,
+        choices: &[],
+        help: "Print version information and exit",
+    },
+    Flag {
+        long: "help",
+        short: Some('h'),
+        value: None,
+        choices: &[],
+        help: "Print this help and exit",
+    },
+    Flag {
+        long: "window",
+        short: Some('w'),
+        value: Some("TITLE"),
+        choices: &[],
+        help: "Target window title; repeat to broadcast to several windows",
+    },
+    Flag {
+        long: "dir",
+        short: Some('d'),
+        value: Some("DIR"),
+        choices: &[],
+        help: "Directory to read message files from (default: see README)",
+    },
+    Flag {
+        long: "ext",
+        short: None,
+        value: Some("EXT"),
+        choices: &[],
+        help: "Extension to scan for instead of the configured ones; repeatable",
+    },
+    Flag {
+        long: "doctor",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "Check files, target windows and display mode, then exit",
+    },
+    Flag {
+        long: "ping",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --doctor, also type a test message into team chat",
+    },
+    Flag {
+        long: "list",
+        short: Some('l'),
+        value: None,
+        choices: &[],
+        help: "List the message files found and exit (no terminal UI)",
+    },
+    Flag {
+        long: "send",
+        short: Some('s'),
+        value: Some("FILE"),
+        choices: &[],
+        help: "Send a message file by name without the terminal UI",
+    },
+    Flag {
+        long: "json-result",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --send, print a JSON summary on stdout (progress goes to stderr)",
+    },
+    Flag {
+        long: "team",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --send, type into team chat instead of all chat",
+    },
+    Flag {
+        long: "per-file",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --send and several windows, send the whole file to each in turn",
+    },
+    Flag {
+        long: "speed",
+        short: None,
+        value: Some("PERCENT"),
+        choices: SPEEDS,
+        help: "With --send, start at this typing speed in percent (default: 100)",
+    },
+    Flag {
+        long: "skip-repeats",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --send, leave out lines repeating an earlier line",
+    },
+    Flag {
+        long: "dry-run",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --send, type into a mock keyboard instead of the game",
+    },
+    Flag {
+        long: "progress",
+        short: None,
+        value: Some("MODE"),
+        choices: &ProgressMode::NAMES,
+        help: "With --send, print every line (full), one status line (compact) or nothing (none)",
+    },
+    Flag {
+        long: "view",
+        short: None,
+        value: Some("FILE"),
+        choices: &[],
+        help: "Open a message file in the viewer at startup, then show the file list",
+    },
+    Flag {
+        long: "view-only",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --view, quit when the viewer is closed instead of showing the list",
+    },
+    Flag {
+        long: "import",
+        short: None,
+        value: Some("ZIP"),
+        choices: &[],
+        help: "Extract the message files of a zip pack into the message folder and exit",
+    },
+    Flag {
+        long: "export",
+        short: None,
+        value: Some("GLOB"),
+        choices: &[],
+        help: "Export the message files matching a pattern (e.g. \"aram/*\") and exit",
+    },
+    Flag {
+        long: "output",
+        short: Some('o'),
+        value: Some("FILE"),
+        choices: &[],
+        help: "With --export, the .zip or .md file to write",
+    },
+    Flag {
+        long: "report",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "Bundle the last session's log and send snapshot into a file for a bug report",
+    },
+    Flag {
+        long: "trace-input",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "Write every injected key event of each send to a trace file",
+    },
+    Flag {
+        long: "replay",
+        short: None,
+        value: Some("FILE"),
+        choices: &[],
+        help: "Type a recorded .session.json again as a dry run, with its timing, and exit",
+    },
+    Flag {
+        long: "live",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "With --replay, type into the real game windows instead of a dry run",
+    },
+    Flag {
+        long: "accessible",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "Use a plain line-by-line interface for screen readers instead of the terminal UI",
+    },
+    Flag {
+        long: "read-only",
+        short: None,
+        value: None,
+        choices: &[],
+        help: "Browse and view files without typing anything into the game",
+    },
+    Flag {
+        long: "completions",
+        short: None,
+        value: Some("SHELL"),
+        choices: Shell::NAMES,
+        help: "Print a completion script for bash or powershell",
+    },
+];
+
+/// Parsed command-line arguments.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    /// Print version information and exit
+    pub version: bool,
+    /// Print usage and exit
+    pub help: bool,
+    /// Print a shell completion script and exit
+    pub completions: Option<Shell>,
+    /// Target window titles (empty = default title)
+    pub windows: Vec<String>,
+    /// Directory to read message files from
+    pub dir: Option<String>,
+    /// Extensions to scan for (empty = from the config)
+    pub extensions: Vec<String>,
+    /// Print the diagnostics report and exit
+    pub doctor: bool,
+    /// Include a test send in the diagnostics report
+    pub ping: bool,
+    /// List message files and exit
+    pub list: bool,
+    /// Send this message file headless and exit
+    pub send: Option<String>,
+    /// Print the result of `--send` as JSON
+    pub json_result: bool,
+    /// Send `--send` to team chat
+    pub team: bool,
+    /// Broadcast `--send` file by file
+    pub per_file: bool,
+    /// Starting speed of `--send` in percent
+    pub speed: Option<u32>,
+    /// Leave repeated lines out of `--send`
+    pub skip_repeats: bool,
+    /// Type `--send` into a mock keyboard
+    pub dry_run: bool,
+    /// How much progress `--send` prints
+    pub progress: ProgressMode,
+    /// Open this message file in the viewer at startup
+    pub view: Option<String>,
+    /// Quit when the `--view` viewer is closed
+    pub view_only: bool,
+    /// Import this zip pack and exit
+    pub import: Option<String>,
+    /// Export the files matching this pattern and exit
+    pub export: Option<String>,
+    /// File written by `--export`
+    pub output: Option<String>,
+    /// Disable sending and the test send
+    pub read_only: bool,
+    /// Use the line-oriented frontend instead of the terminal UI
+    pub accessible: bool,
+    /// Write a bug report file and exit
+    pub report: bool,
+    /// Trace every injected key event of each send
+    pub trace_input: bool,
+    /// Replay this recorded session and exit
+    pub replay: Option<String>,
+    /// Replay into the real windows instead of a dry run
+    pub live: bool,
+}
+
+impl Args {
+    /// Parse arguments from the current process (skipping the program name).
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Parse arguments from an iterator.
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_ref() {
+                "--version" | "-V" => parsed.version = true,
+                "--help" | "-h" => parsed.help = true,
+                "--doctor" => parsed.doctor = true,
+                "--ping" => parsed.ping = true,
+                "--list" | "-l" => parsed.list = true,
+                "--read-only" => parsed.read_only = true,
+                "--accessible" => parsed.accessible = true,
+                "--report" => parsed.report = true,
+                "--trace-input" => parsed.trace_input = true,
+                "--json-result" => parsed.json_result = true,
+                "--team" => parsed.team = true,
+                "--per-file" => parsed.per_file = true,
+                "--skip-repeats" => parsed.skip_repeats = true,
+                "--dry-run" => parsed.dry_run = true,
+                "--speed" => {
+                    let value = args.next().ok_or("--speed requires a percentage")?;
+                    let speed = value.as_ref().trim_end_matches('%').parse().ok().and_then(Speed::from_percent);
+                    let speed = speed.ok_or_else(|| format!("--speed must be one of {}", SPEEDS.join(", ")))?;
+                    parsed.speed = Some(speed.percent());
+                }
+                "--progress" => {
+                    let value = args.next().ok_or("--progress requires a mode")?;
+                    let mode = ProgressMode::from_name(value.as_ref());
+                    parsed.progress = mode.ok_or_else(|| format!("--progress must be one of {}", ProgressMode::NAMES.join(", ")))?;
+                }
+                "--view-only" => parsed.view_only = true,
+                "--live" => parsed.live = true,
+                "--replay" => {
+                    let value = args.next().ok_or("--replay requires a session file")?;
+                    parsed.replay = Some(value.as_ref().to_string());
+                }
+                "--import" => {
+                    let value = args.next().ok_or("--import requires a zip file")?;
+                    parsed.import = Some(value.as_ref().to_string());
+                }
+                "--export" => {
+                    let value = args.next().ok_or("--export requires a file name pattern")?;
+                    parsed.export = Some(value.as_ref().to_string());
+                }
+                "--output" | "-o" => {
+                    let value = args.next().ok_or("--output requires a file name")?;
+                    parsed.output = Some(value.as_ref().to_string());
+                }
+                "--send" | "-s" => {
+                    let value = args.next().ok_or("--send requires a file name")?;
+                    parsed.send = Some(value.as_ref().to_string());
+                }
+                "--view" => {
+                    let value = args.next().ok_or("--view requires a file name")?;
+                    parsed.view = Some(value.as_ref().to_string());
+                }
+                "--dir" | "-d" => {
+                    let value = args.next().ok_or("--dir requires a directory")?;
+                    parsed.dir = Some(value.as_ref().to_string());
+                }
+                "--ext" => {
+                    let value = args.next().ok_or("--ext requires an extension")?;
+                    let value = value.as_ref();
+                    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric()) {
+                        return Err(format!("--ext expects letters and digits only, like msg, got '{}'", value));
+                    }
+                    parsed.extensions.push(value.to_string());
+                }
+                "--window" | "-w" => {
+                    let value = args.next().ok_or("--window requires a window title")?;
+                    parsed.windows.push(value.as_ref().to_string());
+                }
+                "--completions" => {
+                    let value = args.next().ok_or("--completions requires a shell name")?;
+                    parsed.completions = Some(value.as_ref().parse()?);
+                }
+                other => return Err(format!("Unknown argument '{}' (see --help)", other)),
+            }
+        }
+        if parsed.ping && !parsed.doctor {
+            return Err("--ping can only be used with --doctor".to_string());
+        }
+        if parsed.json_result && parsed.send.is_none() {
+            return Err("--json-result can only be used with --send".to_string());
+        }
+        let send_flags = [
+            ("--team", parsed.team),
+            ("--per-file", parsed.per_file),
+            ("--speed", parsed.speed.is_some()),
+            ("--skip-repeats", parsed.skip_repeats),
+            ("--dry-run", parsed.dry_run),
+            ("--progress", parsed.progress != ProgressMode::Full),
+        ];
+        if let Some((flag, _)) = send_flags.iter().find(|(_, set)| *set && parsed.send.is_none()) {
+            return Err(format!("{} can only be used with --send", flag));
+        }
+        if parsed.live && parsed.replay.is_none() {
+            return Err("--live can only be used with --replay".to_string());
+        }
+        if parsed.view_only && parsed.view.is_none() {
+            return Err("--view-only can only be used with --view".to_string());
+        }
+        if parsed.export.is_some() != parsed.output.is_some() {
+            return Err("--export and --output must be used together".to_string());
+        }
+        Ok(parsed)
+    }
+
+    /// The send options given by `--team`, `--per-file`, `--speed`,
+    /// `--skip-repeats` and `--dry-run`, on top of the configured ones.
+    pub fn send_choices(&self, config: &Config) -> SendChoices {
+        let defaults = SendChoices::from(config);
+        SendChoices {
+            chat: if self.team { ChatScope::Team } else { defaults.chat },
+            order: if self.per_file { BroadcastMode::PerFile } else { defaults.order },
+            speed: self.speed.and_then(Speed::from_percent).unwrap_or(defaults.speed),
+            skip_repeats: self.skip_repeats,
+            manual_advance: false,
+            dry_run: self.dry_run,
+        }
+    }
+}
+
+/// Usage text printed by `--help`, generated from [`FLAGS`].
+pub fn usage() -> String {
+    let mut text = String::from("Usage: mad_typing [OPTIONS]\n\nOptions:");
+    for flag in FLAGS {
+        let short = flag.short.map(|c| format!("-{}, ", c)).unwrap_or_default();
+        let value = flag.value.map(|v| format!(" <{}>", v)).unwrap_or_default();
+        let spec = format!("{}--{}{}", short, flag.long, value);
+        text.push_str(&format!("\n  {:<24}{}", spec, flag.help));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flags() {
+        assert_eq!(Args::parse(Vec::<String>::new()).unwrap(), Args::default());
+        assert!(Args::parse(["--version"]).unwrap().version);
+        assert!(Args::parse(["-V"]).unwrap().version);
+        assert!(Args::parse(["-h"]).unwrap().help);
+        assert!(Args::parse(["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_completions() {
+        assert_eq!(Args::parse(["--completions", "bash"]).unwrap().completions, Some(Shell::Bash));
+        assert_eq!(Args::parse(["--completions", "PowerShell"]).unwrap().completions, Some(Shell::PowerShell));
+        assert!(Args::parse(["--completions"]).is_err());
+        assert!(Args::parse(["--completions", "fish"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_repeated_windows() {
+        let args = Args::parse(["--window", "League A", "-w", "League B"]).unwrap();
+        assert_eq!(args.windows, vec!["League A", "League B"]);
+        assert!(Args::parse(["--window"]).is_err());
+        assert_eq!(Args::parse(["-d", "C:\\msgs"]).unwrap().dir.as_deref(), Some("C:\\msgs"));
+        assert!(Args::parse(["--dir"]).is_err());
+        assert_eq!(Args::parse(["--ext", "msg", "--ext", "LOG"]).unwrap().extensions, vec!["msg", "LOG"]);
+        assert!(Args::parse(["--ext", ".msg"]).is_err());
+        assert!(Args::parse(["--ext"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_headless_modes() {
+        assert!(Args::parse(["-l"]).unwrap().list);
+        assert_eq!(Args::parse(["--send", "gg.txt"]).unwrap().send.as_deref(), Some("gg.txt"));
+        assert!(Args::parse(["--send"]).is_err());
+        assert!(Args::parse(["--read-only", "-l"]).unwrap().read_only);
+        assert!(Args::parse(["--accessible"]).unwrap().accessible);
+        assert!(Args::parse(["--report"]).unwrap().report);
+        assert!(Args::parse(["-s", "gg.txt", "--json-result"]).unwrap().json_result);
+        assert!(Args::parse(["--json-result"]).is_err());
+        let args = Args::parse(["--view", "funny/openers.txt", "--view-only", "--read-only"]).unwrap();
+        assert_eq!((args.view.as_deref(), args.view_only), (Some("funny/openers.txt"), true));
+        assert!(Args::parse(["--view"]).is_err());
+        assert!(Args::parse(["--view-only"]).is_err());
+        assert_eq!(Args::parse(["--import", "pack.zip"]).unwrap().import.as_deref(), Some("pack.zip"));
+        let args = Args::parse(["--export", "aram/*", "-o", "aram.zip"]).unwrap();
+        assert_eq!((args.export.as_deref(), args.output.as_deref()), (Some("aram/*"), Some("aram.zip")));
+        assert!(Args::parse(["--export", "*"]).is_err());
+        assert!(Args::parse(["-o", "out.zip"]).is_err());
+        let args = Args::parse(["--replay", "demo.session.json", "--live"]).unwrap();
+        assert_eq!((args.replay.as_deref(), args.live), (Some("demo.session.json"), true));
+        assert!(Args::parse(["--replay"]).is_err());
+        assert!(Args::parse(["--live"]).is_err());
+    }
+
+    #[test]
+    fn test_send_flags_fill_send_choices() {
+        let config = Config { broadcast_per_file: true, ..Config::default() };
+        let args = Args::parse(["-s", "gg.txt", "--team", "--speed", "150%", "--skip-repeats", "--dry-run"]).unwrap();
+        let choices = args.send_choices(&config);
+        assert_eq!((choices.chat, choices.order), (ChatScope::Team, BroadcastMode::PerFile));
+        assert_eq!(choices.speed.percent(), 150);
+        assert!(choices.skip_repeats && choices.dry_run && !choices.manual_advance);
+        assert_eq!(Args::parse(["-s", "gg.txt"]).unwrap().send_choices(&Config::default()), SendChoices::default());
+        assert!(Args::parse(["-s", "gg.txt", "--speed", "120"]).is_err());
+        assert_eq!(Args::parse(["--dry-run"]).unwrap_err(), "--dry-run can only be used with --send");
+    }
+
+    #[test]
+    fn test_parse_progress_mode() {
+        assert_eq!(Args::parse(["-s", "gg.txt"]).unwrap().progress, ProgressMode::Full);
+        assert_eq!(Args::parse(["-s", "gg.txt", "--progress", "compact"]).unwrap().progress, ProgressMode::Compact);
+        assert_eq!(Args::parse(["-s", "gg.txt", "--progress", "none"]).unwrap().progress, ProgressMode::None);
+        assert_eq!(Args::parse(["-s", "gg.txt", "--progress", "quiet"]).unwrap_err(), "--progress must be one of compact, full, none");
+        assert!(Args::parse(["-s", "gg.txt", "--progress"]).is_err());
+        assert_eq!(Args::parse(["--progress", "none"]).unwrap_err(), "--progress can only be used with --send");
+    }
+
+    #[test]
+    fn test_ping_requires_doctor() {
+        assert!(Args::parse(["--doctor", "--ping"]).unwrap().ping);
+        assert!(Args::parse(["--ping"]).is_err());
+    }
+}
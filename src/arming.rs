@@ -0,0 +1,155 @@
+//! Send arming
+//!
+//! With `require_arming` on, the terminal UI refuses every send until the
+//! tool is armed with Ctrl+A, so a stray Enter in the list can't type into
+//! the game. Arming lapses after `arming_timeout_secs` without a key press.
+//! The state is process-wide and [`SendSession::run`](crate::send::SendSession::run)
+//! checks it, so every way of starting a send in the session is gated.
+//! Frontends that never [`configure`] it, like `--send`, are always armed.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::config::DISARMED_ERROR;
+
+/// Whether sending is armed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Arming {
+    /// Off: always armed
+    enabled: bool,
+    /// Idle time after which arming lapses
+    timeout: Duration,
+    /// Last key press while armed; None while disarmed
+    active: Option<Instant>,
+}
+
+impl Arming {
+    /// Disarmed, if `enabled`; arming lapses after `timeout` idle.
+    pub fn new(enabled: bool, timeout: Duration) -> Self {
+        Self { enabled, timeout, active: None }
+    }
+
+    /// Whether sends need arming at all.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether a send may start at `now`.
+    pub fn is_armed(&self, now: Instant) -> bool {
+        !self.enabled || self.active.is_some_and(|at| now.saturating_duration_since(at) < self.timeout)
+    }
+
+    /// Arm if disarmed, disarm if armed. Returns whether it's armed now.
+    pub fn toggle(&mut self, now: Instant) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        self.active = if self.is_armed(now) { None } else { Some(now) };
+        self.active.is_some()
+    }
+
+    /// Count a key press at `now`, keeping an armed tool armed.
+    pub fn touch(&mut self, now: Instant) {
+        if self.active.is_some() && self.is_armed(now) {
+            self.active = Some(now);
+        }
+    }
+
+    /// Disarm if it was idle for the timeout. Returns whether this call
+    /// disarmed it.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        if self.active.is_some() && !self.is_armed(now) {
+            self.active = None;
+            return true;
+        }
+        false
+    }
+}
+
+/// The session's arming; always armed until [`configure`].
+static ARMING: Mutex<Option<Arming>> = Mutex::new(None);
+
+fn with_arming<T>(f: impl FnOnce(&mut Arming) -> T) -> T {
+    let mut arming = ARMING.lock().unwrap_or_else(|e| e.into_inner());
+    f(arming.get_or_insert_with(|| Arming::new(false, Duration::ZERO)))
+}
+
+/// Require arming (or not) from now on, lapsing after `timeout` idle. An
+/// armed tool stays armed.
+pub fn configure(enabled: bool, timeout: Duration) {
+    with_arming(|a| {
+        a.enabled = enabled;
+        a.timeout = timeout;
+    })
+}
+
+/// Whether sends need arming in this session.
+pub fn is_enabled() -> bool {
+    with_arming(|a| a.is_enabled())
+}
+
+/// Whether a send may start now.
+pub fn is_armed() -> bool {
+    with_arming(|a| a.is_armed(Instant::now()))
+}
+
+/// Arm or disarm; see [`Arming::toggle`].
+pub fn toggle() -> bool {
+    with_arming(|a| a.toggle(Instant::now()))
+}
+
+/// Count a key press; see [`Arming::touch`].
+pub fn touch() {
+    with_arming(|a| a.touch(Instant::now()))
+}
+
+/// Disarm after the idle timeout; see [`Arming::expire`].
+pub fn expire() -> bool {
+    with_arming(|a| a.expire(Instant::now()))
+}
+
+/// Fails with [`DISARMED_ERROR`] while disarmed.
+pub fn check() -> Result<(), String> {
+    if is_armed() { Ok(()) } else { Err(DISARMED_ERROR.to_string()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_always_armed_unless_enabled() {
+        let now = Instant::now();
+        let mut arming = Arming::new(false, Duration::from_secs(60));
+        assert!(arming.is_armed(now));
+        assert!(arming.toggle(now));
+        assert!(arming.is_armed(now + Duration::from_secs(3600)));
+        assert!(!arming.expire(now + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_arming_toggles_and_lapses_when_idle() {
+        let start = Instant::now();
+        let secs = |n: u64| start + Duration::from_secs(n);
+        let mut arming = Arming::new(true, Duration::from_secs(60));
+        assert!(!arming.is_armed(start));
+
+        assert!(arming.toggle(secs(0)));
+        assert!(!arming.toggle(secs(10)));
+        assert!(arming.toggle(secs(20)));
+
+        // Key presses keep it armed
+        arming.touch(secs(50));
+        assert!(arming.is_armed(secs(100)));
+        assert!(!arming.expire(secs(100)));
+
+        assert!(!arming.is_armed(secs(110)));
+        arming.touch(secs(115));
+        assert!(arming.expire(secs(120)));
+        assert!(!arming.expire(secs(121)));
+        assert!(!arming.is_armed(secs(121)));
+        assert!(arming.toggle(secs(130)));
+    }
+}
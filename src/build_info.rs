@@ -0,0 +1,18 @@
+//! Build information for MadTyping
+//!
+//! Version, git commit and build date embedded by `build.rs`, used by
+//! `--version`, the about screen and the log header.
+
+/// Crate version from Cargo.toml
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short git commit hash ("unknown" when built outside a git checkout)
+pub const GIT_HASH: &str = env!("MADTYPING_GIT_HASH");
+
+/// Build date (YYYY-MM-DD, UTC)
+pub const BUILD_DATE: &str = env!("MADTYPING_BUILD_DATE");
+
+/// One-line version string, e.g. "MadTyping 0.1.0 (1a2b3c4, built 2025-01-31)".
+pub fn version_string() -> String {
+    format!("MadTyping {} ({}, built {})", VERSION, GIT_HASH, BUILD_DATE)
+}
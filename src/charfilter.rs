@@ -0,0 +1,220 @@
+//! Character filter for MadTyping
+//!
+//! The game's chat shows some characters as boxes or drops them, so lines
+//! can be cleaned before they are typed: characters in `char_blacklist`
+//! (codepoint ranges such as `U+2500-U+257F`), or everything but printable
+//! ASCII with `ascii_only`, are replaced by `char_substitute` or removed.
+//! A line with nothing left to say is skipped rather than sent as a bare
+//! Enter press.
+
+use crate::config::Config;
+use crate::files::Line;
+use crate::logging::log;
+
+/// Which characters are kept, and what replaces the others.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CharFilter {
+    /// Blocked codepoint ranges, inclusive
+    ranges: Vec<(u32, u32)>,
+    /// Replacement for blocked characters; None removes them
+    substitute: Option<char>,
+    /// Block everything but printable ASCII
+    ascii_only: bool,
+}
+
+impl From<&Config> for CharFilter {
+    fn from(config: &Config) -> Self {
+        let (ranges, problems) = parse_ranges(&config.char_blacklist);
+        for problem in problems {
+            log(&problem);
+        }
+        Self::new(ranges, config.char_substitute.chars().next(), config.ascii_only)
+    }
+}
+
+/// A line after filtering.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Filtered {
+    /// The text to type; empty if nothing but blanks was left
+    pub text: String,
+    /// Characters replaced or removed
+    pub lost: usize,
+}
+
+impl CharFilter {
+    /// Block `ranges` (and everything but printable ASCII with
+    /// `ascii_only`), replacing blocked characters by `substitute`. A
+    /// substitute that is blocked itself removes them instead.
+    pub fn new(ranges: Vec<(u32, u32)>, substitute: Option<char>, ascii_only: bool) -> Self {
+        let mut filter = Self { ranges, substitute: None, ascii_only };
+        filter.substitute = substitute.filter(|&c| !filter.blocks(c));
+        filter
+    }
+
+    /// Whether the filter can change anything.
+    pub fn is_active(&self) -> bool {
+        self.ascii_only || !self.ranges.is_empty()
+    }
+
+    /// Whether `c` is replaced or removed.
+    pub fn blocks(&self, c: char) -> bool {
+        let code = u32::from(c);
+        (self.ascii_only && !(' '..='~').contains(&c)) || self.ranges.iter().any(|&(start, end)| (start..=end).contains(&code))
+    }
+
+    /// Filter `text`. A result with only blanks left is empty, so callers
+    /// can skip it.
+    pub fn apply(&self, text: &str) -> Filtered {
+        let mut filtered = String::with_capacity(text.len());
+        let mut lost = 0;
+        for c in text.chars() {
+            if !self.blocks(c) {
+                filtered.push(c);
+                continue;
+            }
+            lost += 1;
+            if let Some(substitute) = self.substitute {
+                filtered.push(substitute);
+            }
+        }
+        let text = filtered.trim();
+        Filtered { text: text.to_string(), lost }
+    }
+}
+
+/// Parse a blacklist like `U+2500-U+257F, 1F600`: `,`- or `;`-separated
+/// hex codepoints (`U+` optional) or ranges of them. Returns the ranges
+/// that could be read and a problem for each entry that couldn't.
+pub fn parse_ranges(spec: &str) -> (Vec<(u32, u32)>, Vec<String>) {
+    let mut ranges = Vec::new();
+    let mut problems = Vec::new();
+    for entry in spec.split([',', ';']).map(str::trim).filter(|e| !e.is_empty()) {
+        let (start, end) = entry.split_once('-').unwrap_or((entry, entry));
+        match (codepoint(start), codepoint(end)) {
+            (Some(start), Some(end)) if start <= end => ranges.push((start, end)),
+            _ => problems.push(format!("char_blacklist entry '{}' is not a codepoint or range like U+2500-U+257F, ignored", entry)),
+        }
+    }
+    (ranges, problems)
+}
+
+/// A hex codepoint with an optional `U+` prefix.
+fn codepoint(text: &str) -> Option<u32> {
+    let text = text.trim();
+    let hex = text.strip_prefix("U+").or_else(|| text.strip_prefix("u+")).unwrap_or(text);
+    u32::from_str_radix(hex, 16).ok().filter(|&code| code <= u32::from(char::MAX))
+}
+
+/// What the filter does to `lines`: which lines lose characters and which
+/// end up empty and are skipped. None if it leaves them alone.
+pub fn summary(filter: &CharFilter, lines: &[Line]) -> Option<String> {
+    if !filter.is_active() {
+        return None;
+    }
+    let mut losing = Vec::new();
+    let mut emptied = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let filtered = filter.apply(&line.text);
+        if filtered.text.is_empty() {
+            emptied.push((index + 1).to_string());
+        } else if filtered.lost > 0 {
+            losing.push(format!("line {}: {}", index + 1, filtered.lost));
+        }
+    }
+    if losing.is_empty() && emptied.is_empty() {
+        return None;
+    }
+    let mut parts = Vec::new();
+    if !losing.is_empty() {
+        let shown = losing.iter().take(5).cloned().collect::<Vec<_>>().join(", ");
+        let more = if losing.len() > 5 { format!(", {} more", losing.len() - 5) } else { String::new() };
+        parts.push(format!("{} line(s) lose characters ({}{})", losing.len(), shown, more));
+    }
+    if !emptied.is_empty() {
+        parts.push(format!("line(s) {} are empty after filtering and will be skipped", emptied.join(", ")));
+    }
+    Some(format!("Character filter: {}", parts.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random strings mixing ASCII, blanks, accents,
+    /// box drawing and emoji.
+    fn samples() -> Vec<String> {
+        const ALPHABET: &[char] = &[' ', ' ', '\t', 'a', 'Z', '7', '!', '~', 'é', 'ß', '─', '│', '□', '😀', '🎉', '\u{7f}'];
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        (0..500)
+            .map(|_| {
+                seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                let len = (seed >> 59) as usize;
+                (0..len)
+                    .map(|i| ALPHABET[((seed >> (i * 4 % 56)) as usize) % ALPHABET.len()])
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn filters() -> Vec<CharFilter> {
+        let (box_drawing, _) = parse_ranges("U+2500-U+257F, U+1F300-U+1FAFF");
+        vec![
+            CharFilter::new(box_drawing.clone(), None, false),
+            CharFilter::new(box_drawing.clone(), Some('?'), false),
+            CharFilter::new(Vec::new(), Some('?'), true),
+            CharFilter::new(Vec::new(), Some('é'), true),
+            CharFilter::new(box_drawing, Some(' '), true),
+        ]
+    }
+
+    #[test]
+    fn test_filtered_text_is_clean_and_never_blank() {
+        for filter in filters() {
+            for text in samples() {
+                let filtered = filter.apply(&text);
+                assert!(!filtered.text.chars().any(|c| filter.blocks(c)), "{:?} kept a blocked character", text);
+                assert_eq!(filtered.text, filtered.text.trim(), "{:?}", text);
+                assert!(filtered.lost <= text.chars().count());
+                assert_eq!(filtered.lost, text.chars().filter(|&c| filter.blocks(c)).count());
+                if !filtered.text.is_empty() {
+                    assert!(filtered.text.chars().any(|c| !c.is_whitespace()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_substitute_and_ascii_only() {
+        let (ranges, _) = parse_ranges("U+2500-U+257F");
+        let filter = CharFilter::new(ranges, Some('-'), false);
+        assert_eq!(filter.apply("gg ──── wp"), Filtered { text: "gg ---- wp".to_string(), lost: 4 });
+        assert_eq!(filter.apply("ok é"), Filtered { text: "ok é".to_string(), lost: 0 });
+
+        let strict = CharFilter::new(Vec::new(), None, true);
+        assert_eq!(strict.apply("gg wp 😀"), Filtered { text: "gg wp".to_string(), lost: 1 });
+        assert_eq!(strict.apply("😀 🎉").text, "");
+        // A blocked substitute removes instead
+        assert_eq!(CharFilter::new(Vec::new(), Some('□'), true).apply("a□b").text, "ab");
+        assert!(!CharFilter::default().is_active());
+    }
+
+    #[test]
+    fn test_parse_ranges_reports_bad_entries() {
+        let (ranges, problems) = parse_ranges("U+2500-U+257F; 1f600 ,, u+20-u+10, nope, U+110000");
+        assert_eq!(ranges, vec![(0x2500, 0x257f), (0x1f600, 0x1f600)]);
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].contains("'u+20-u+10'"));
+    }
+
+    #[test]
+    fn test_summary_lists_losses_and_skips() {
+        let filter = CharFilter::new(Vec::new(), None, true);
+        let lines = vec![Line::new("gg"), Line::new("gg é"), Line::new("😀")];
+        assert_eq!(
+            summary(&filter, &lines).as_deref(),
+            Some("Character filter: 1 line(s) lose characters (line 2: 1); line(s) 3 are empty after filtering and will be skipped")
+        );
+        assert_eq!(summary(&filter, &lines[..1]), None);
+        assert_eq!(summary(&CharFilter::default(), &lines), None);
+    }
+}
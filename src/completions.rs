@@ -0,0 +1,202 @@
+//! Shell completion scripts for MadTyping
+//!
+//! Generates bash and PowerShell completion scripts from the flag table
+//! in [`args`](crate::args), so new flags are picked up automatically.
+//! Message file names are completed from what `--list` prints when the
+//! completion runs, and paths by the shell's own file completion.
+
+use std::str::FromStr;
+
+use crate::args::{Flag, FLAGS};
+
+/// Program names the completions are registered for.
+const COMMAND_NAMES: &[&str] = &["mad_typing", "mad_typing.exe"];
+
+/// Flags whose value is a message file name (or a pattern of them).
+const MESSAGE_FILE_FLAGS: &[&str] = &["send", "view", "export"];
+
+/// Flags whose value is a file path.
+const FILE_FLAGS: &[&str] = &["import", "output", "replay"];
+
+/// Flags whose value is a directory.
+const DIRECTORY_FLAGS: &[&str] = &["dir"];
+
+/// Strips the line count off a row of `--list`, leaving the file name.
+const LIST_ROW_SUFFIX: &str = r" +[0-9]+ line\(s\).*$";
+
+/// Shells we can generate completion scripts for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    PowerShell,
+}
+
+impl Shell {
+    /// Accepted shell names (lowercase).
+    pub const NAMES: &'static [&'static str] = &["bash", "powershell"];
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "powershell" | "pwsh" => Ok(Shell::PowerShell),
+            other => Err(format!(
+                "Unsupported shell '{}' (expected one of: {})",
+                other,
+                Shell::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Generate the completion script for a shell.
+pub fn generate(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::PowerShell => powershell(),
+    }
+}
+
+/// All flag spellings (long and short).
+fn flag_words() -> Vec<String> {
+    let mut words = Vec::new();
+    for flag in FLAGS {
+        words.push(format!("--{}", flag.long));
+        if let Some(short) = flag.short {
+            words.push(format!("-{}", short));
+        }
+    }
+    words
+}
+
+/// Every spelling of the flags named in `longs`.
+fn spellings(longs: &[&str]) -> Vec<String> {
+    let mut words = Vec::new();
+    for flag in FLAGS.iter().filter(|f| longs.contains(&f.long)) {
+        words.push(format!("--{}", flag.long));
+        if let Some(short) = flag.short {
+            words.push(format!("-{}", short));
+        }
+    }
+    words
+}
+
+/// Flags that take a value from a fixed set of choices.
+fn flags_with_choices() -> impl Iterator<Item = &'static Flag> {
+    FLAGS.iter().filter(|f| f.value.is_some() && !f.choices.is_empty())
+}
+
+fn bash() -> String {
+    let mut script = String::from(
+        "# bash completion for mad_typing\n\
+         _mad_typing() {\n    \
+             local cur prev\n    \
+             cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    \
+             prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    \
+             case \"$prev\" in\n",
+    );
+    for flag in flags_with_choices() {
+        script.push_str(&format!(
+            "        --{})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n            return 0\n            ;;\n",
+            flag.long,
+            flag.choices.join(" ")
+        ));
+    }
+    script.push_str(&format!(
+        "        {})\n            \
+             local IFS=$'\\n'\n            \
+             COMPREPLY=($(compgen -W \"$(\"${{COMP_WORDS[0]}}\" --list 2>/dev/null | tail -n +2 | sed -E 's/{}//')\" -- \"$cur\"))\n            \
+             return 0\n            \
+             ;;\n",
+        spellings(MESSAGE_FILE_FLAGS).join("|"),
+        LIST_ROW_SUFFIX
+    ));
+    for (flags, kind) in [(FILE_FLAGS, "-f"), (DIRECTORY_FLAGS, "-d")] {
+        script.push_str(&format!(
+            "        {})\n            local IFS=$'\\n'\n            COMPREPLY=($(compgen {} -- \"$cur\"))\n            return 0\n            ;;\n",
+            spellings(flags).join("|"),
+            kind
+        ));
+    }
+    script.push_str(&format!(
+        "    esac\n    COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n}}\ncomplete -F _mad_typing {}\n",
+        flag_words().join(" "),
+        COMMAND_NAMES.join(" ")
+    ));
+    script
+}
+
+fn powershell() -> String {
+    let names: Vec<String> = COMMAND_NAMES.iter().map(|n| format!("'{}'", n)).collect();
+    let mut script = format!(
+        "# PowerShell completion for mad_typing\n\
+         Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{\n    \
+             param($wordToComplete, $commandAst, $cursorPosition)\n    \
+             $before = @($commandAst.CommandElements | Where-Object {{ $_.Extent.EndOffset -lt $cursorPosition }} | ForEach-Object {{ $_.ToString() }})\n    \
+             $prev = if ($before.Count -gt 0) {{ $before[-1] }} else {{ '' }}\n    \
+             if ($prev -in @({})) {{\n        \
+                 return [System.Management.Automation.CompletionCompleters]::CompleteFilename($wordToComplete)\n    \
+             }}\n    \
+             if ($prev -in @({})) {{\n        \
+                 return [System.Management.Automation.CompletionCompleters]::CompleteFilename($wordToComplete) | Where-Object {{ $_.ResultType -eq 'ProviderContainer' }}\n    \
+             }}\n    \
+             $candidates = switch ($prev) {{\n",
+        names.join(", "),
+        quoted(&spellings(FILE_FLAGS)),
+        quoted(&spellings(DIRECTORY_FLAGS))
+    );
+    for flag in flags_with_choices() {
+        let choices: Vec<String> = flag.choices.iter().map(|c| format!("'{}'", c)).collect();
+        script.push_str(&format!("        '--{}' {{ @({}) }}\n", flag.long, choices.join(", ")));
+    }
+    script.push_str(&format!(
+        "        {{ $_ -in @({}) }} {{ @(& $commandAst.CommandElements[0].ToString() --list 2>$null | Select-Object -Skip 1 | ForEach-Object {{ $_ -replace '{}', '' }}) }}\n",
+        quoted(&spellings(MESSAGE_FILE_FLAGS)),
+        LIST_ROW_SUFFIX
+    ));
+    script.push_str(&format!(
+        "        default {{ @({}) }}\n    \
+         }}\n    \
+         $candidates | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{\n        \
+             $text = if ($_ -match '\\s') {{ \"'$_'\" }} else {{ $_ }}\n        \
+             [System.Management.Automation.CompletionResult]::new($text, $_, 'ParameterValue', $_)\n    \
+         }}\n\
+         }}\n",
+        quoted(&flag_words())
+    ));
+    script
+}
+
+/// `words` as a PowerShell list of single-quoted strings.
+fn quoted(words: &[String]) -> String {
+    words.iter().map(|w| format!("'{}'", w)).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Snapshot tests: if a flag change is intended, regenerate the files with
+    // `mad_typing --completions bash > tests/snapshots/completions.bash` (and powershell).
+
+    #[test]
+    fn test_bash_snapshot() {
+        assert_eq!(generate(Shell::Bash), include_str!("../tests/snapshots/completions.bash"));
+    }
+
+    #[test]
+    fn test_powershell_snapshot() {
+        assert_eq!(generate(Shell::PowerShell), include_str!("../tests/snapshots/completions.ps1"));
+    }
+
+    #[test]
+    fn test_completed_flags_take_values() {
+        for long in MESSAGE_FILE_FLAGS.iter().chain(FILE_FLAGS).chain(DIRECTORY_FLAGS) {
+            let flag = FLAGS.iter().find(|f| f.long == *long).unwrap();
+            assert!(flag.value.is_some() && flag.choices.is_empty(), "--{}", long);
+        }
+    }
+}
@@ -3,6 +3,10 @@
 //! This module centralizes all configurable delays and settings
 //! for easy tuning and maintenance.
 
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::paths::{expand_home, home_directory};
+
 // ============== KEYBOARD INPUT DELAYS ==============
 
 /// Delay between each character typed (lower = faster typing)
@@ -26,28 +30,357 @@ pub const KEY_PRESS_DELAY_MS: u64 = 10;
 /// Delay for Shift key operations
 pub const SHIFT_KEY_DELAY_MS: u64 = 15;
 
+/// Delay after a batched key sequence (Shift+Enter or Enter sent in one
+/// SendInput call), in place of the gaps between its keys
+pub const KEY_BATCH_DELAY_MS: u64 = 20;
+
 /// Delay after SetForegroundWindow
 pub const WINDOW_FOCUS_DELAY_MS: u64 = 100;
 
+/// Longest time to wait for a restored (previously minimized) window to
+/// report focus before typing anyway
+pub const RESTORE_FOCUS_TIMEOUT_MS: u64 = 2_000;
+
+/// Longest time to wait, after activating it again, for a window that
+/// didn't have focus once the focus delay was over
+pub const FOCUS_RETRY_TIMEOUT_MS: u64 = 1_000;
+
+/// Most extra focus delay learned for a window title (see
+/// [`crate::focus_quirks`])
+pub const MAX_LEARNED_FOCUS_DELAY_MS: u64 = 1_000;
+
+/// Share of a learned focus delay dropped after each send that found the
+/// window focused in time
+pub const LEARNED_FOCUS_DECAY_PERCENT: u64 = 10;
+
+/// Learned focus delays below this are forgotten
+pub const MIN_LEARNED_FOCUS_DELAY_MS: u64 = 10;
+
 /// Delay for unicode character input
 pub const UNICODE_KEY_DELAY_MS: u64 = 5;
 
+/// How many times to retry a character whose key events were dropped
+/// (default for `Config::char_retry_limit`)
+pub const CHAR_RETRY_LIMIT: u64 = 2;
+
+/// Delay before retrying a dropped character
+pub const CHAR_RETRY_DELAY_MS: u64 = 30;
+
+/// How often to check whether held modifiers were released
+pub const MODIFIER_POLL_MS: u64 = 50;
+
+/// How long to wait for held modifiers to be released before giving up
+pub const MODIFIER_RELEASE_TIMEOUT_MS: u64 = 10_000;
+
 // ============== CLI DELAYS ==============
 
 /// Delay between sending each line of text
 pub const NEXT_LINE_DELAY_MS: u64 = 100;
 
-/// Delay for user to read messages (in seconds)
-pub const USER_READ_DELAY_SECS: u64 = 2;
+/// Delay before sending to a different window when broadcasting, so focus
+/// has time to settle after switching
+pub const BROADCAST_SETTLE_DELAY_MS: u64 = 300;
+
+/// Pause before a line for each blank line right above it in a plain text
+/// file, so blank lines can mark deliberate pauses (0 = blank lines are
+/// ignored; default for `Config::blank_line_pause_ms`)
+pub const BLANK_LINE_PAUSE_MS: u64 = 0;
+
+/// How often a timed (`!at`) line that is waiting re-checks the clock and
+/// updates its countdown
+pub const TIMED_LINE_TICK_MS: u64 = 250;
+
+/// How often a manual-advance send waiting for the next key asks again
+pub const MANUAL_ADVANCE_TICK_MS: u64 = 50;
+
+/// Before sending a file with timed lines, ask for the current game time
+/// and run the send clock from it instead of 00:00
+/// (default for `Config::ask_game_time`)
+pub const ASK_GAME_TIME: bool = false;
+
+/// Speed multipliers (in percent) that `+`/`-` step through while sending.
+/// Higher is faster: delays are divided by the multiplier.
+pub const SPEED_STEPS_PERCENT: &[u32] = &[25, 50, 75, 100, 150, 200, 300, 400];
+
+/// How long the progress line shows inverted after Esc cancels a send
+pub const CANCEL_FLASH_MS: u64 = 300;
+
+/// Flash the progress line when Esc cancels a send
+/// (default for `Config::flash_on_cancel`)
+pub const FLASH_ON_CANCEL: bool = true;
+
+/// How often the file viewer checks the viewed file for changes
+pub const VIEWER_POLL_MS: u64 = 500;
+
+/// How often the file list checks for background scan results while idle
+pub const UI_POLL_MS: u64 = 100;
+
+/// How often an idle background worker checks whether it should stop
+pub const WORKER_POLL_MS: u64 = 100;
+
+/// Width of one file list column; terminals at least twice as wide show
+/// the list in several columns
+pub const LIST_COLUMN_WIDTH: usize = 80;
+
+/// Most columns the file list is split into
+pub const LIST_MAX_COLUMNS: usize = 4;
+
+/// Narrowest width screens are laid out for; narrower terminals wrap
+pub const MIN_LAYOUT_WIDTH: usize = 40;
+
+/// Screen size laid out for when the terminal's own size can't be trusted
+pub const FALLBACK_SCREEN_SIZE: (u16, u16) = (80, 24);
+
+/// Largest terminal size taken at face value; bigger reports are bogus
+pub const MAX_SCREEN_SIZE: (u16, u16) = (1000, 500);
+
+/// Half period of the search box cursor blink
+pub const CURSOR_BLINK_MS: u64 = 500;
+
+/// How long the "reloaded" note stays in the viewer footer (in seconds)
+pub const RELOAD_NOTE_SECS: u64 = 2;
+
+/// How often the config file is checked for changes
+pub const CONFIG_POLL_MS: u64 = 1000;
+
+// ============== WINDOW BEHAVIOR ==============
+
+/// Bring the console window back to the foreground after a send
+/// completes or is cancelled (default for `Config::refocus_console_after_send`).
+/// There is no "restore the previously focused window" behaviour; the
+/// nearest is `MINIMIZE_CONSOLE_ON_SEND`, whose restore runs first, so with
+/// both on the console is restored and then focused.
+pub const REFOCUS_CONSOLE_AFTER_SEND: bool = false;
+
+/// Minimize the console window while sending so it doesn't cover the game
+/// (default for `Config::minimize_console_on_send`)
+pub const MINIMIZE_CONSOLE_ON_SEND: bool = false;
+
+/// Turn Caps Lock off and wait for held modifiers instead of refusing to send
+/// (default for `Config::compensate_keyboard_state`)
+pub const COMPENSATE_KEYBOARD_STATE: bool = true;
+
+/// When broadcasting to several windows, send the whole file to one window
+/// before the next instead of line by line (default for `Config::broadcast_per_file`)
+pub const BROADCAST_PER_FILE: bool = false;
+
+/// Warn before sending when the target looks like exclusive fullscreen
+/// (default for `Config::check_fullscreen`)
+pub const CHECK_FULLSCREEN: bool = true;
+
+/// Press the chat-open and send keys one SendInput call at a time with
+/// delays in between, instead of as one batch (default for `Config::stepped_chat_keys`)
+pub const STEPPED_CHAT_KEYS: bool = false;
+
+/// Executables of the in-game window; sends prefer it when the client
+/// matches the target title too
+pub const GAME_PROCESS_NAMES: &[&str] = &["League of Legends.exe"];
+
+/// Executables of the League client (lobby, patcher); it has no chat to
+/// type into, so it is never a send target
+pub const CLIENT_PROCESS_NAMES: &[&str] =
+    &["LeagueClient.exe", "LeagueClientUx.exe", "LeagueClientUxRender.exe", "RiotClientServices.exe", "RiotClientUx.exe"];
+
+/// Window class of the in-game window, used when the process can't be read
+pub const GAME_WINDOW_CLASSES: &[&str] = &["RiotWindowClass"];
+
+/// Window classes of the League client, used when the process can't be read
+pub const CLIENT_WINDOW_CLASSES: &[&str] = &["RCLIENT", "RiotClientWindowClass"];
+
+/// Refuse to start a send unless every target is the in-game window and it
+/// responds, so nothing is typed during champion select or a loading
+/// screen (default for `Config::game_guard`). Turn it off for targets that
+/// aren't League of Legends.
+pub const GAME_GUARD: bool = false;
+
+/// Start a send that was triggered while another is running once the first
+/// one finishes, instead of rejecting it
+pub const QUEUE_SENDS: bool = false;
+
+/// Ask before sending a file again from the list within this many seconds
+/// of its last send, so a double-pressed Enter doesn't send it twice
+/// (default for `Config::resend_guard_secs`, 0 = never ask)
+pub const RESEND_GUARD_SECS: u64 = 60;
+
+/// Show the send options overlay (channel, order, speed and so on) after
+/// Enter in the file list; Enter again sends with the shown choices
+/// (default for `Config::send_options_prompt`)
+pub const SEND_OPTIONS_PROMPT: bool = true;
+
+/// Extra wait after restoring a minimized target window, so the first
+/// characters don't land during the restore animation
+/// (default for `Config::restore_settle_delay_ms`)
+pub const RESTORE_SETTLE_DELAY_MS: u64 = 500;
+
+/// Ask before sending to a minimized window instead of just waiting longer
+/// after restoring it (default for `Config::prompt_if_minimized`)
+pub const PROMPT_IF_MINIMIZED: bool = false;
+
+/// How long a send waits for a target window that closed after taking
+/// lines (e.g. during the end-of-game transition) to come back before the
+/// line fails; 0 fails it right away (default for `Config::window_lost_wait_secs`)
+pub const WINDOW_LOST_WAIT_SECS: u64 = 20;
+
+/// How often a send waiting for a lost window looks for it again
+pub const WINDOW_LOST_TICK_MS: u64 = 500;
+
+// ============== CHAT LIMITS ==============
+
+/// Longest message League's chat box accepts, in characters (the line
+/// limit of the `league` game profile)
+pub const GAME_MESSAGE_MAX_CHARS: usize = 200;
+
+/// Longest message of Dota 2's chat, roughly (the `dota` game profile)
+pub const DOTA_MESSAGE_MAX_CHARS: usize = 127;
+
+/// Whose chat rules apply (default for `Config::game_profile`)
+pub const GAME_PROFILE: GameProfile = GameProfile::League;
+
+/// Chat line limit in characters overriding the profile's; 0 uses the
+/// profile's (default for `Config::chat_char_limit`)
+pub const CHAT_CHAR_LIMIT: u64 = 0;
+
+/// Send lines longer than the game limit as several messages instead of
+/// refusing them (default for `Config::split_long_lines`)
+pub const SPLIT_LONG_LINES: bool = false;
+
+/// Open chat with plain Enter for lines starting with `/` so the game runs
+/// them as commands (default for `Config::slash_commands`)
+pub const SLASH_COMMANDS: bool = true;
+
+/// Roughly how many messages the game accepts in a short burst before it
+/// mutes the sender for spamming (default for `Config::chat_limit_messages`)
+pub const CHAT_LIMIT_MESSAGES: u64 = 8;
+
+/// Length of that burst window in seconds
+/// (default for `Config::chat_limit_window_secs`)
+pub const CHAT_LIMIT_WINDOW_SECS: u64 = 10;
+
+/// Most messages a send may type into each window, after splitting long
+/// lines; sends waiting in the queue count towards it too
+/// (default for `Config::max_lines_per_send`)
+pub const MAX_LINES_PER_SEND: u64 = 100;
+
+// ============== SEND METHOD ==============
+
+/// How messages get into the chat box (default for `Config::send_method`)
+pub const SEND_METHOD: SendMethod = SendMethod::Type;
+
+/// Command run after the first line pasted with the `auto` send method; a
+/// non-zero exit means the chat box stayed empty and the window is typed
+/// into from then on (default for `Config::paste_check_hook`, empty = off)
+pub const PASTE_CHECK_HOOK: &str = "";
+
+// ============== CHARACTER FILTER ==============
+
+/// Codepoint ranges replaced or removed before sending, e.g.
+/// `U+2500-U+257F, U+1F300-U+1FAFF` (default for `Config::char_blacklist`,
+/// empty = none)
+pub const CHAR_BLACKLIST: &str = "";
+
+/// Replacement for filtered characters (default for
+/// `Config::char_substitute`, empty = remove them)
+pub const CHAR_SUBSTITUTE: &str = "";
+
+/// Only send printable ASCII, filtering everything else
+/// (default for `Config::ascii_only`)
+pub const ASCII_ONLY: bool = false;
+
+// ============== FEEDBACK ==============
+
+/// Audible cue while sending (default for `Config::send_sound`)
+pub const SEND_SOUND: SendSound = SendSound::Off;
+
+/// Tone (frequency in Hz, duration in ms) after each sent line
+pub const LINE_SENT_TONE: (u32, u32) = (880, 40);
+
+/// Tone when a line fails to send
+pub const FAILED_TONE: (u32, u32) = (220, 300);
+
+/// Tone when a send finishes
+pub const FINISHED_TONE: (u32, u32) = (1320, 150);
+
+/// Show a desktop notification when a send finishes, fails or is cancelled
+/// (default for `Config::notify_when_finished`)
+pub const NOTIFY_WHEN_FINISHED: bool = false;
+
+/// Sends shorter than this don't notify; you're probably still watching
+pub const NOTIFY_MIN_SEND_SECS: u64 = 10;
 
-/// Delay after cancel before returning (in seconds)
-pub const CANCEL_DELAY_SECS: u64 = 1;
+/// How long the notification icon stays in the tray
+pub const NOTIFICATION_SECS: u64 = 10;
+
+// ============== HOOKS ==============
+
+/// Command run before each send; a non-zero exit cancels the send
+/// (default for `Config::pre_send_hook`, empty = off)
+pub const PRE_SEND_HOOK: &str = "";
+
+/// Command run after each send (default for `Config::post_send_hook`,
+/// empty = off)
+pub const POST_SEND_HOOK: &str = "";
+
+/// A hook still running after this long is killed; a pre-send hook that
+/// times out cancels the send
+pub const HOOK_TIMEOUT_MS: u64 = 5000;
+
+/// Command run after each sent line; a non-zero exit means the line didn't
+/// show up in chat (default for `Config::verify_hook`, empty = off)
+pub const VERIFY_HOOK: &str = "";
+
+/// Line checks (hook or callback) still running after this long count as
+/// not seen, so a slow check holds up the send by this much at most
+pub const VERIFY_TIMEOUT_MS: u64 = 1500;
+
+/// Time the shutdown after the console is closed may take before the
+/// process exits anyway; Windows kills it after 5 seconds
+pub const SHUTDOWN_TIMEOUT_MS: u64 = 3000;
+
+/// How often the shutdown checks whether the cancelled send has stopped
+pub const SHUTDOWN_TICK_MS: u64 = 20;
 
 // ============== LOGGING ==============
 
 /// Set to false to disable logging
 pub const LOG_ENABLED: bool = false;
 
+/// Also log verbose details, e.g. keyboard layout, elevation and delays in
+/// the snapshot taken before each send
+pub const LOG_DEBUG: bool = false;
+
+/// Write a trace file with every injected key event for each send
+/// (default for `Config::trace_input`, also set by `--trace-input`)
+pub const TRACE_INPUT: bool = false;
+
+/// Most entries kept in memory for one trace; later ones are only counted
+pub const TRACE_MAX_ENTRIES: usize = 200_000;
+
+/// A trace file stops listing entries at about this size (the gap summary
+/// is always written)
+pub const TRACE_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+/// Gaps between key injections at least this long are counted as slow in
+/// the trace summary
+pub const TRACE_SLOW_GAP_MS: u64 = 50;
+
+/// Save each send as a `.session.json` file that `--replay` can run again
+/// (default for `Config::record_sessions`)
+pub const RECORD_SESSIONS: bool = false;
+
+// ============== LANGUAGE ==============
+
+/// Translation to use, e.g. `de` for `lang/de.toml` (default for
+/// `Config::language`, empty = from the `LANG` environment variable)
+pub const LANGUAGE: &str = "";
+
+/// Folder next to the executable holding translation files
+pub const LANGUAGE_DIR: &str = "lang";
+
+// ============== STATISTICS ==============
+
+/// Keep cumulative lifetime stats in the state file next to the executable
+pub const PERSIST_LIFETIME_STATS: bool = true;
+
 // ============== APPLICATION ==============
 
 /// Default application header name
@@ -56,5 +389,869 @@ pub const DEFAULT_HEADER_NAME: &str = "MadTyping - LoL Chat Tool";
 /// Default target window title to search for
 pub const DEFAULT_WINDOW_TITLE: &str = "League of Legends (TM) Client";
 
+/// How many similar window titles to suggest when the target isn't found
+pub const TITLE_SUGGESTIONS: usize = 3;
+
+/// Minimum similarity (0.0 - 1.0) for a window title to be suggested
+pub const TITLE_SUGGESTION_MIN_SCORE: f64 = 0.4;
+
+/// How many similar file names to suggest when `--send` or `--view` finds no file
+pub const FILE_SUGGESTIONS: usize = 3;
+
+/// Minimum similarity (0.0 - 1.0) for a file name to be suggested
+pub const FILE_SUGGESTION_MIN_SCORE: f64 = 0.3;
+
+/// Also discover message files in subdirectories of the scan directory
+/// (default for `Config::recursive_discovery`)
+pub const RECURSIVE_DISCOVERY: bool = false;
+
+/// File name endings of editor temp and backup files, left out of scans
+/// and ignored by the directory watcher
+pub const TEMP_FILE_SUFFIXES: &[&str] = &[".tmp", ".swp", ".swo", ".swx", ".bak", ".orig"];
+
+/// Watch the scan directory while the file list is shown and apply added,
+/// changed and removed files without a full rescan (default for
+/// `Config::watch_files`)
+pub const WATCH_FILES: bool = true;
+
+/// How often the directory watcher lists the scan directory
+pub const WATCH_POLL_MS: u64 = 250;
+
+/// Changes are applied once the directory has been quiet this long, so an
+/// editor writing a file several times per save causes one update
+pub const WATCH_DEBOUNCE_MS: u64 = 300;
+
+/// How many directory levels below the scan directory are searched when
+/// recursive discovery is on
+pub const MAX_SCAN_DEPTH: usize = 4;
+
+/// A scan stops after this many supported files, so pointing `--dir` at a
+/// huge folder can't load all of it (default for
+/// `Config::max_discovered_files`)
+pub const MAX_DISCOVERED_FILES: u64 = 2000;
+
+/// Extra directories to look for message files in, separated by `;`
+/// (default for `Config::message_dirs`). The first one that exists is used
+/// unless `--dir` is given.
+pub const MESSAGE_DIRS: &str = "";
+
+/// Window class to send to when no window title matches the target, e.g.
+/// `RiotWindowClass` for a localized game; empty for none
+/// (default for `Config::window_class`)
+pub const WINDOW_CLASS: &str = "";
+
+/// Directory used for message files when the executable's directory has
+/// none (default for `Config::user_message_dir`); `~` is the home folder
+pub const USER_MESSAGE_DIR: &str = "~/Documents/MadTyping";
+
+/// Start in read-only mode: browse and view files but never type into the
+/// game (default for `Config::read_only`, also set by `--read-only`)
+pub const READ_ONLY: bool = false;
+
+/// Refuse sends from the terminal UI until the tool is armed with Ctrl+A,
+/// for tournaments (default for `Config::require_arming`)
+pub const REQUIRE_ARMING: bool = false;
+
+/// Arming lapses after this long without a key press (default for
+/// `Config::arming_timeout_secs`)
+pub const ARMING_TIMEOUT_SECS: u64 = 300;
+
+/// Harmless text typed into team chat by the test send (default for
+/// `Config::ping_text`, empty = don't type anything)
+pub const PING_TEXT: &str = ".";
+
+/// File in the scan directory that ad-hoc lines (`:` in the file list) are
+/// appended to when `save_adhoc_lines` is on
+pub const SCRATCH_FILE_NAME: &str = "scratch.txt";
+
+/// Append each ad-hoc line to the scratch file for reuse
+/// (default for `Config::save_adhoc_lines`)
+pub const SAVE_ADHOC_LINES: bool = false;
+
+/// Character that starts an abbreviation token such as `~glhf`
+/// (default for `Config::abbreviation_sigil`)
+pub const ABBREVIATION_SIGIL: &str = "~";
+
+/// Most levels of abbreviations used inside other abbreviations; a token
+/// still left after that (one that uses itself) is sent as written
+pub const MAX_ABBREVIATION_DEPTH: usize = 8;
+
+/// Folder of the scan directory that fully sent files are moved to; it is
+/// not scanned itself
+pub const ARCHIVE_DIRECTORY: &str = "archive";
+
+/// Files whose name matches this glob are archived after a full send
+/// (default for `Config::archive_after_send`, empty = only files asking
+/// for it in their front matter)
+pub const ARCHIVE_AFTER_SEND: &str = "";
+
+/// Local hours at which `{?morning:…}`, `{?afternoon:…}`, `{?evening:…}`
+/// and `{?night:…}` lines start to apply; each lasts until the next
+pub const MORNING_START_HOUR: u8 = 5;
+pub const AFTERNOON_START_HOUR: u8 = 12;
+pub const EVENING_START_HOUR: u8 = 17;
+pub const NIGHT_START_HOUR: u8 = 22;
+
+/// Lines longer than this (in characters) are truncated when a file is
+/// loaded, so a pasted blob can't freeze the viewer or the send path
+pub const MAX_LINE_CHARS: usize = 4096;
+
+/// Largest (removed × added) block of messages compared line by line when
+/// a file changed before sending; bigger changes are summarized as all
+/// removed and all added so the prompt can't stall
+pub const DIFF_MAX_CELLS: usize = 250_000;
+
+/// A message file with more than this percentage of control characters
+/// (besides tabs, line breaks and form feeds) is treated as binary and skipped
+pub const BINARY_CONTROL_PERCENT: usize = 10;
+
 /// Supported file extensions for text files
-pub const SUPPORTED_EXTENSIONS: &[&str] = &["txt", "md"];
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["txt", "md", "csv"];
+
+/// Extensions scanned for, separated by `;` (default for
+/// `Config::extensions`). Anything besides `md` and `csv` is read as plain
+/// text.
+pub const EXTENSIONS: &str = "txt;md;csv";
+
+// ============== RUNTIME SETTINGS ==============
+
+/// When to play an audible cue while sending.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SendSound {
+    /// No sound
+    Off,
+    /// Only when the send finishes or a line fails
+    End,
+    /// After every sent line, plus the end and failure cues
+    Line,
+}
+
+impl SendSound {
+    /// All values, in the same order as [`SendSound::NAMES`].
+    pub const ALL: [SendSound; 3] = [SendSound::Off, SendSound::End, SendSound::Line];
+    /// Names used in the config file and settings screen.
+    pub const NAMES: &'static [&'static str] = &["off", "end", "line"];
+}
+
+/// How a message gets into the chat box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SendMethod {
+    /// Type it character by character
+    Type,
+    /// Put it on the clipboard and press Ctrl+V
+    Paste,
+    /// Paste until a window turns out to ignore Ctrl+V, then type into it
+    Auto,
+    /// Post WM_CHAR messages to the target's focused control without
+    /// focusing it. Most games read raw input and ignore them; meant for
+    /// testing against Notepad and other standard windows
+    PostMessage,
+}
+
+impl SendMethod {
+    /// All values, in the same order as [`SendMethod::NAMES`].
+    pub const ALL: [SendMethod; 4] = [SendMethod::Type, SendMethod::Paste, SendMethod::Auto, SendMethod::PostMessage];
+    /// Names used in the config file and settings screen.
+    pub const NAMES: &'static [&'static str] = &["type", "paste", "auto", "post_message"];
+}
+
+/// The game being typed into, which sets the chat line limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GameProfile {
+    /// League of Legends, 200 characters a message
+    League,
+    /// Dota 2, about 127 characters a message
+    Dota,
+    /// No line limit (MUD clients, test windows)
+    Unlimited,
+}
+
+impl GameProfile {
+    /// All values, in the same order as [`GameProfile::NAMES`].
+    pub const ALL: [GameProfile; 3] = [GameProfile::League, GameProfile::Dota, GameProfile::Unlimited];
+    /// Names used in the config file and settings screen.
+    pub const NAMES: &'static [&'static str] = &["league", "dota", "none"];
+
+    /// Longest chat message the game accepts, None if there's no limit.
+    pub fn char_limit(self) -> Option<usize> {
+        match self {
+            GameProfile::League => Some(GAME_MESSAGE_MAX_CHARS),
+            GameProfile::Dota => Some(DOTA_MESSAGE_MAX_CHARS),
+            GameProfile::Unlimited => None,
+        }
+    }
+}
+
+/// Runtime settings, loaded from the config file and changeable from the
+/// settings screen.
+///
+/// Defaults come from the constants above.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Delay after each typed character
+    pub char_type_delay_ms: u64,
+    /// Delay between sending each line
+    pub next_line_delay_ms: u64,
+    /// Delay before switching to another window when broadcasting
+    pub broadcast_settle_delay_ms: u64,
+    /// Focus our own console window once a send finishes or is cancelled,
+    /// after `minimize_console_on_send` restored it
+    pub refocus_console_after_send: bool,
+    /// Minimize our console window before focusing the game, restore it afterwards
+    pub minimize_console_on_send: bool,
+    /// Compensate for Caps Lock / held modifiers (off = refuse to send)
+    pub compensate_keyboard_state: bool,
+    /// Broadcast file-by-file instead of line-by-line to multiple windows
+    pub broadcast_per_file: bool,
+    /// Warn when the target window seems to be in exclusive fullscreen
+    pub check_fullscreen: bool,
+    /// Queue a send triggered while another is running (off = reject it)
+    pub queue_sends: bool,
+    /// Ask before sending the same file again within this many seconds (0 = don't)
+    pub resend_guard_secs: u64,
+    /// Show the send options overlay before each send from the file list
+    pub send_options_prompt: bool,
+    /// Send chat-open/send keys one at a time instead of as one batch
+    pub stepped_chat_keys: bool,
+    /// Extra delay after restoring a minimized target window
+    pub restore_settle_delay_ms: u64,
+    /// Wait for a target window that closed mid-send to come back (0 = don't)
+    pub window_lost_wait_secs: u64,
+    /// Retries of a character whose key events were dropped
+    pub char_retry_limit: u64,
+    /// Pause per blank line above a line in a plain text file (0 = off)
+    pub blank_line_pause_ms: u64,
+    /// Most message files a scan lists
+    pub max_discovered_files: u64,
+    /// Ask before sending to a minimized window (off = just wait longer)
+    pub prompt_if_minimized: bool,
+    /// Split lines over the game's message limit instead of refusing to send
+    pub split_long_lines: bool,
+    /// Game whose chat line limit applies
+    pub game_profile: GameProfile,
+    /// Chat line limit overriding the profile's, 0 for the profile's
+    pub chat_char_limit: u64,
+    /// Send lines starting with `/` as chat commands
+    pub slash_commands: bool,
+    /// Audible cue after each line and/or at the end of a send
+    pub send_sound: SendSound,
+    /// Type, paste, or paste with a fallback to typing
+    pub send_method: SendMethod,
+    /// Desktop notification when a long send is over
+    pub notify_when_finished: bool,
+    /// Flash the progress line when Esc cancels a send
+    pub flash_on_cancel: bool,
+    /// Messages allowed within the chat limit window before a mute is likely
+    /// (0 = no limit, no cooldown)
+    pub chat_limit_messages: u64,
+    /// Chat limit window in seconds
+    pub chat_limit_window_secs: u64,
+    /// Sends over this many messages per window are refused
+    pub max_lines_per_send: u64,
+    /// Text typed into team chat by the test send
+    pub ping_text: String,
+    /// Extra message file directories, `;`-separated
+    pub message_dirs: String,
+    /// Message file directory used when the executable's directory has none
+    pub user_message_dir: String,
+    /// Extensions scanned for, `;`-separated
+    pub extensions: String,
+    /// Window class matched when no title does, empty for none
+    pub window_class: String,
+    /// Refuse every action that types into the game
+    pub read_only: bool,
+    /// Refuse sends until the tool is armed with Ctrl+A
+    pub require_arming: bool,
+    /// Idle time after which arming lapses, in seconds
+    pub arming_timeout_secs: u64,
+    /// Also discover files in subdirectories
+    pub recursive_discovery: bool,
+    /// Apply file changes in the scan directory while the list is shown
+    pub watch_files: bool,
+    /// Ask for the game time before sending timed lines
+    pub ask_game_time: bool,
+    /// Write a keystroke trace file for each send
+    pub trace_input: bool,
+    /// Save each send for `--replay`
+    pub record_sessions: bool,
+    /// Append ad-hoc lines to the scratch file
+    pub save_adhoc_lines: bool,
+    /// Only send to the in-game window (ask before sending anywhere else)
+    pub game_guard: bool,
+    /// Command run before each send, empty for none
+    pub pre_send_hook: String,
+    /// Command run after each send, empty for none
+    pub post_send_hook: String,
+    /// Command checking that the first pasted line arrived, empty for none
+    pub paste_check_hook: String,
+    /// Command checking that each sent line showed up, empty for none
+    pub verify_hook: String,
+    /// Codepoint ranges filtered before sending
+    pub char_blacklist: String,
+    /// Replacement for filtered characters, empty to remove them
+    pub char_substitute: String,
+    /// Filter everything but printable ASCII
+    pub ascii_only: bool,
+    /// Translation code, empty to follow `LANG`
+    pub language: String,
+    /// Text that starts an abbreviation token
+    pub abbreviation_sigil: String,
+    /// Abbreviation names (without the sigil) and the text they stand for,
+    /// from the `[abbreviations]` table
+    pub abbreviations: BTreeMap<String, String>,
+    /// Glob of file names archived after a full send, empty for none
+    pub archive_after_send: String,
+}
+
+impl Config {
+    /// Longest chat message in characters: `chat_char_limit` if set,
+    /// otherwise the game profile's. None if there's no limit.
+    pub fn char_limit(&self) -> Option<usize> {
+        match self.chat_char_limit {
+            0 => self.game_profile.char_limit(),
+            limit => Some(limit as usize),
+        }
+    }
+
+    /// Directories from `message_dirs`, in order, with `~` expanded.
+    pub fn message_dirs(&self) -> Vec<PathBuf> {
+        self.message_dirs
+            .split(';')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(expand_home)
+            .collect()
+    }
+
+    /// `user_message_dir` with `~` expanded (`None` if there's no home folder).
+    pub fn user_message_dir(&self) -> Option<PathBuf> {
+        let dir = self.user_message_dir.trim();
+        if dir.starts_with('~') && home_directory().is_none() {
+            return None;
+        }
+        Some(expand_home(dir))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            char_type_delay_ms: CHAR_TYPE_DELAY_MS,
+            next_line_delay_ms: NEXT_LINE_DELAY_MS,
+            broadcast_settle_delay_ms: BROADCAST_SETTLE_DELAY_MS,
+            refocus_console_after_send: REFOCUS_CONSOLE_AFTER_SEND,
+            minimize_console_on_send: MINIMIZE_CONSOLE_ON_SEND,
+            compensate_keyboard_state: COMPENSATE_KEYBOARD_STATE,
+            broadcast_per_file: BROADCAST_PER_FILE,
+            check_fullscreen: CHECK_FULLSCREEN,
+            queue_sends: QUEUE_SENDS,
+            resend_guard_secs: RESEND_GUARD_SECS,
+            send_options_prompt: SEND_OPTIONS_PROMPT,
+            stepped_chat_keys: STEPPED_CHAT_KEYS,
+            restore_settle_delay_ms: RESTORE_SETTLE_DELAY_MS,
+            window_lost_wait_secs: WINDOW_LOST_WAIT_SECS,
+            char_retry_limit: CHAR_RETRY_LIMIT,
+            blank_line_pause_ms: BLANK_LINE_PAUSE_MS,
+            max_discovered_files: MAX_DISCOVERED_FILES,
+            prompt_if_minimized: PROMPT_IF_MINIMIZED,
+            split_long_lines: SPLIT_LONG_LINES,
+            game_profile: GAME_PROFILE,
+            chat_char_limit: CHAT_CHAR_LIMIT,
+            slash_commands: SLASH_COMMANDS,
+            send_sound: SEND_SOUND,
+            send_method: SEND_METHOD,
+            notify_when_finished: NOTIFY_WHEN_FINISHED,
+            flash_on_cancel: FLASH_ON_CANCEL,
+            chat_limit_messages: CHAT_LIMIT_MESSAGES,
+            chat_limit_window_secs: CHAT_LIMIT_WINDOW_SECS,
+            max_lines_per_send: MAX_LINES_PER_SEND,
+            ping_text: PING_TEXT.to_string(),
+            message_dirs: MESSAGE_DIRS.to_string(),
+            user_message_dir: USER_MESSAGE_DIR.to_string(),
+            extensions: EXTENSIONS.to_string(),
+            window_class: WINDOW_CLASS.to_string(),
+            read_only: READ_ONLY,
+            require_arming: REQUIRE_ARMING,
+            arming_timeout_secs: ARMING_TIMEOUT_SECS,
+            recursive_discovery: RECURSIVE_DISCOVERY,
+            watch_files: WATCH_FILES,
+            ask_game_time: ASK_GAME_TIME,
+            trace_input: TRACE_INPUT,
+            record_sessions: RECORD_SESSIONS,
+            save_adhoc_lines: SAVE_ADHOC_LINES,
+            game_guard: GAME_GUARD,
+            pre_send_hook: PRE_SEND_HOOK.to_string(),
+            post_send_hook: POST_SEND_HOOK.to_string(),
+            paste_check_hook: PASTE_CHECK_HOOK.to_string(),
+            verify_hook: VERIFY_HOOK.to_string(),
+            char_blacklist: CHAR_BLACKLIST.to_string(),
+            char_substitute: CHAR_SUBSTITUTE.to_string(),
+            ascii_only: ASCII_ONLY,
+            language: LANGUAGE.to_string(),
+            abbreviation_sigil: ABBREVIATION_SIGIL.to_string(),
+            abbreviations: BTreeMap::new(),
+            archive_after_send: ARCHIVE_AFTER_SEND.to_string(),
+        }
+    }
+}
+
+/// A numeric setting with its sane range.
+///
+/// Config file values outside `min..=max` are clamped with a warning.
+pub struct Limit {
+    /// Key in the config file
+    pub key: &'static str,
+    /// Short label shown in the settings screen
+    pub label: &'static str,
+    /// Amount added or removed per key press in the settings screen
+    pub step: u64,
+    pub min: u64,
+    pub max: u64,
+    /// Value used when the config file doesn't set the key
+    pub default: u64,
+    /// Accessor for the backing field
+    pub field: fn(&mut Config) -> &mut u64,
+}
+
+/// All numeric settings and their allowed ranges.
+pub const LIMITS: &[Limit] = &[
+    Limit {
+        key: "char_type_delay_ms",
+        label: "Delay per character (ms)",
+        step: 1,
+        min: 1,
+        max: 200,
+        default: CHAR_TYPE_DELAY_MS,
+        field: |c| &mut c.char_type_delay_ms,
+    },
+    Limit {
+        key: "next_line_delay_ms",
+        label: "Delay between lines (ms)",
+        step: 20,
+        min: 20,
+        max: 10_000,
+        default: NEXT_LINE_DELAY_MS,
+        field: |c| &mut c.next_line_delay_ms,
+    },
+    Limit {
+        key: "broadcast_settle_delay_ms",
+        label: "Delay between windows (ms)",
+        step: 50,
+        min: 50,
+        max: 5_000,
+        default: BROADCAST_SETTLE_DELAY_MS,
+        field: |c| &mut c.broadcast_settle_delay_ms,
+    },
+    Limit {
+        key: "restore_settle_delay_ms",
+        label: "Delay after restoring (ms)",
+        step: 50,
+        min: 0,
+        max: 5_000,
+        default: RESTORE_SETTLE_DELAY_MS,
+        field: |c| &mut c.restore_settle_delay_ms,
+    },
+    Limit {
+        key: "window_lost_wait_secs",
+        label: "Wait for a lost window (s)",
+        step: 5,
+        min: 0,
+        max: 300,
+        default: WINDOW_LOST_WAIT_SECS,
+        field: |c| &mut c.window_lost_wait_secs,
+    },
+    Limit {
+        key: "char_retry_limit",
+        label: "Retries per dropped character",
+        step: 1,
+        min: 0,
+        max: 10,
+        default: CHAR_RETRY_LIMIT,
+        field: |c| &mut c.char_retry_limit,
+    },
+    Limit {
+        key: "resend_guard_secs",
+        label: "Confirm resend within (s)",
+        step: 10,
+        min: 0,
+        max: 3600,
+        default: RESEND_GUARD_SECS,
+        field: |c| &mut c.resend_guard_secs,
+    },
+    Limit {
+        key: "blank_line_pause_ms",
+        label: "Pause per blank line (ms)",
+        step: 250,
+        min: 0,
+        max: 10_000,
+        default: BLANK_LINE_PAUSE_MS,
+        field: |c| &mut c.blank_line_pause_ms,
+    },
+    Limit {
+        key: "chat_char_limit",
+        label: "Chat line limit (0 = game profile's)",
+        step: 10,
+        min: 0,
+        max: 10_000,
+        default: CHAT_CHAR_LIMIT,
+        field: |c| &mut c.chat_char_limit,
+    },
+    Limit {
+        key: "chat_limit_messages",
+        label: "Chat limit: messages (0 = off)",
+        step: 1,
+        min: 0,
+        max: 100,
+        default: CHAT_LIMIT_MESSAGES,
+        field: |c| &mut c.chat_limit_messages,
+    },
+    Limit {
+        key: "chat_limit_window_secs",
+        label: "Chat limit: window (s)",
+        step: 1,
+        min: 1,
+        max: 300,
+        default: CHAT_LIMIT_WINDOW_SECS,
+        field: |c| &mut c.chat_limit_window_secs,
+    },
+    Limit {
+        key: "max_lines_per_send",
+        label: "Max lines per send",
+        step: 10,
+        min: 1,
+        max: 10_000,
+        default: MAX_LINES_PER_SEND,
+        field: |c| &mut c.max_lines_per_send,
+    },
+    Limit {
+        key: "max_discovered_files",
+        label: "Max files listed",
+        step: 100,
+        min: 10,
+        max: 100_000,
+        default: MAX_DISCOVERED_FILES,
+        field: |c| &mut c.max_discovered_files,
+    },
+    Limit {
+        key: "arming_timeout_secs",
+        label: "Disarm when idle for (s)",
+        step: 30,
+        min: 10,
+        max: 3600,
+        default: ARMING_TIMEOUT_SECS,
+        field: |c| &mut c.arming_timeout_secs,
+    },
+];
+
+/// A text setting; the config file value must be a quoted string.
+pub struct TextSetting {
+    /// Key in the config file
+    pub key: &'static str,
+    /// Value used when the config file value is invalid
+    pub default: &'static str,
+    /// Whether `""` is a value of its own (e.g. turning a hook off) rather
+    /// than a mistake
+    pub allow_empty: bool,
+    /// Accessor for the backing field
+    pub field: fn(&mut Config) -> &mut String,
+}
+
+/// All text settings.
+pub const TEXT_SETTINGS: &[TextSetting] = &[
+    TextSetting {
+        key: "ping_text",
+        default: PING_TEXT,
+        allow_empty: true,
+        field: |c| &mut c.ping_text,
+    },
+    TextSetting {
+        key: "message_dirs",
+        default: MESSAGE_DIRS,
+        allow_empty: true,
+        field: |c| &mut c.message_dirs,
+    },
+    TextSetting {
+        key: "user_message_dir",
+        default: USER_MESSAGE_DIR,
+        allow_empty: false,
+        field: |c| &mut c.user_message_dir,
+    },
+    TextSetting {
+        key: "extensions",
+        default: EXTENSIONS,
+        allow_empty: false,
+        field: |c| &mut c.extensions,
+    },
+    TextSetting {
+        key: "window_class",
+        default: WINDOW_CLASS,
+        allow_empty: true,
+        field: |c| &mut c.window_class,
+    },
+    TextSetting {
+        key: "pre_send_hook",
+        default: PRE_SEND_HOOK,
+        allow_empty: true,
+        field: |c| &mut c.pre_send_hook,
+    },
+    TextSetting {
+        key: "post_send_hook",
+        default: POST_SEND_HOOK,
+        allow_empty: true,
+        field: |c| &mut c.post_send_hook,
+    },
+    TextSetting {
+        key: "paste_check_hook",
+        default: PASTE_CHECK_HOOK,
+        allow_empty: true,
+        field: |c| &mut c.paste_check_hook,
+    },
+    TextSetting {
+        key: "verify_hook",
+        default: VERIFY_HOOK,
+        allow_empty: true,
+        field: |c| &mut c.verify_hook,
+    },
+    TextSetting {
+        key: "char_blacklist",
+        default: CHAR_BLACKLIST,
+        allow_empty: true,
+        field: |c| &mut c.char_blacklist,
+    },
+    TextSetting {
+        key: "char_substitute",
+        default: CHAR_SUBSTITUTE,
+        allow_empty: true,
+        field: |c| &mut c.char_substitute,
+    },
+    TextSetting {
+        key: "language",
+        default: LANGUAGE,
+        allow_empty: true,
+        field: |c| &mut c.language,
+    },
+    TextSetting {
+        key: "abbreviation_sigil",
+        default: ABBREVIATION_SIGIL,
+        allow_empty: false,
+        field: |c| &mut c.abbreviation_sigil,
+    },
+    TextSetting {
+        key: "archive_after_send",
+        default: ARCHIVE_AFTER_SEND,
+        allow_empty: true,
+        field: |c| &mut c.archive_after_send,
+    },
+];
+
+/// A setting with a fixed set of named values, cycled in the settings screen.
+pub struct Choice {
+    /// Key in the config file
+    pub key: &'static str,
+    /// Short label shown in the list
+    pub label: &'static str,
+    /// One-line explanation shown for the selected entry
+    pub description: &'static str,
+    /// Allowed values as written in the config file
+    pub options: &'static [&'static str],
+    /// Index of the current value in `options`
+    pub get: fn(&Config) -> usize,
+    /// Set the value by its index in `options`
+    pub set: fn(&mut Config, usize),
+}
+
+/// All choice settings in settings screen order.
+pub const CHOICES: &[Choice] = &[
+    Choice {
+        key: "send_sound",
+        label: "Sound while sending",
+        description: "Beep after every line, only at the end / on failure, or never",
+        options: SendSound::NAMES,
+        get: |c| c.send_sound as usize,
+        set: |c, i| c.send_sound = SendSound::ALL[i],
+    },
+    Choice {
+        key: "send_method",
+        label: "Send method",
+        description: "Type each character, paste with Ctrl+V, paste and fall back to typing, or post messages (test windows only)",
+        options: SendMethod::NAMES,
+        get: |c| c.send_method as usize,
+        set: |c, i| c.send_method = SendMethod::ALL[i],
+    },
+    Choice {
+        key: "game_profile",
+        label: "Game profile",
+        description: "Chat line limit of the game: League (200 characters), Dota 2 (127) or none",
+        options: GameProfile::NAMES,
+        get: |c| c.game_profile as usize,
+        set: |c, i| c.game_profile = GameProfile::ALL[i],
+    },
+];
+
+/// An on/off setting shown in the settings screen.
+pub struct Toggle {
+    /// Key in the config file
+    pub key: &'static str,
+    /// Short label shown in the list
+    pub label: &'static str,
+    /// One-line explanation shown for the selected entry
+    pub description: &'static str,
+    /// Accessor for the backing field
+    pub field: fn(&mut Config) -> &mut bool,
+}
+
+/// All toggles in settings screen order.
+pub const TOGGLES: &[Toggle] = &[
+    Toggle {
+        key: "refocus_console_after_send",
+        label: "Refocus console after send",
+        description: "Bring this window back to the front when a send finishes or is cancelled, after any restore",
+        field: |c| &mut c.refocus_console_after_send,
+    },
+    Toggle {
+        key: "minimize_console_on_send",
+        label: "Minimize console while sending",
+        description: "Minimize this window while sending and restore it after; focus stays on the game unless refocus is on",
+        field: |c| &mut c.minimize_console_on_send,
+    },
+    Toggle {
+        key: "compensate_keyboard_state",
+        label: "Fix Caps Lock / held keys",
+        description: "Turn Caps Lock off and wait for Ctrl/Alt/Shift release (off: refuse to send)",
+        field: |c| &mut c.compensate_keyboard_state,
+    },
+    Toggle {
+        key: "broadcast_per_file",
+        label: "Broadcast file-by-file",
+        description: "With several --window targets, send the whole file to each in turn (off: line by line)",
+        field: |c| &mut c.broadcast_per_file,
+    },
+    Toggle {
+        key: "check_fullscreen",
+        label: "Warn about exclusive fullscreen",
+        description: "Check the game's display mode before sending and suggest borderless mode",
+        field: |c| &mut c.check_fullscreen,
+    },
+    Toggle {
+        key: "queue_sends",
+        label: "Queue overlapping sends",
+        description: "If a send starts while another is running, wait for it instead of refusing",
+        field: |c| &mut c.queue_sends,
+    },
+    Toggle {
+        key: "send_options_prompt",
+        label: "Ask for send options",
+        description: "Show channel, order, speed, repeats, manual advance and dry run before each send",
+        field: |c| &mut c.send_options_prompt,
+    },
+    Toggle {
+        key: "stepped_chat_keys",
+        label: "Stepped chat keys",
+        description: "Press Shift and Enter with real gaps between keys (for games that drop batched input)",
+        field: |c| &mut c.stepped_chat_keys,
+    },
+    Toggle {
+        key: "prompt_if_minimized",
+        label: "Ask if the game is minimized",
+        description: "Ask before sending to a minimized window (off: wait longer after restoring it)",
+        field: |c| &mut c.prompt_if_minimized,
+    },
+    Toggle {
+        key: "recursive_discovery",
+        label: "Include subfolders",
+        description: "Also list message files in folders next to the executable (applied on F5)",
+        field: |c| &mut c.recursive_discovery,
+    },
+    Toggle {
+        key: "watch_files",
+        label: "Watch message files",
+        description: "Pick up added, changed and removed files while the list is shown (off: only on F5)",
+        field: |c| &mut c.watch_files,
+    },
+    Toggle {
+        key: "notify_when_finished",
+        label: "Notify when a send is over",
+        description: "Show a Windows notification when a send of 10s or more finishes, fails or is cancelled",
+        field: |c| &mut c.notify_when_finished,
+    },
+    Toggle {
+        key: "flash_on_cancel",
+        label: "Flash when cancelling",
+        description: "Invert the progress line the moment Esc cancels a send, before it stops",
+        field: |c| &mut c.flash_on_cancel,
+    },
+    Toggle {
+        key: "ask_game_time",
+        label: "Ask for the game time",
+        description: "Before sending a file with !at lines, ask for the in-game clock (off: count from the send start)",
+        field: |c| &mut c.ask_game_time,
+    },
+    Toggle {
+        key: "split_long_lines",
+        label: "Split long lines",
+        description: "Send lines over the game's chat line limit in parts (off: refuse to send them)",
+        field: |c| &mut c.split_long_lines,
+    },
+    Toggle {
+        key: "slash_commands",
+        label: "Slash commands",
+        description: "Open chat with Enter for lines starting with / so the game runs them as commands",
+        field: |c| &mut c.slash_commands,
+    },
+    Toggle {
+        key: "ascii_only",
+        label: "Printable ASCII only",
+        description: "Replace (char_substitute) or drop every character that isn't printable ASCII before sending",
+        field: |c| &mut c.ascii_only,
+    },
+    Toggle {
+        key: "game_guard",
+        label: "Game guard",
+        description: "Ask before sending unless the in-game window is up (off for targets other than LoL)",
+        field: |c| &mut c.game_guard,
+    },
+    Toggle {
+        key: "save_adhoc_lines",
+        label: "Keep ad-hoc lines",
+        description: "Append lines sent with [:] to scratch.txt in the message folder",
+        field: |c| &mut c.save_adhoc_lines,
+    },
+    Toggle {
+        key: "trace_input",
+        label: "Trace input",
+        description: "Write every injected key event of a send to a madtyping-trace file (for reporting dropped characters)",
+        field: |c| &mut c.trace_input,
+    },
+    Toggle {
+        key: "record_sessions",
+        label: "Record sessions",
+        description: "Save each send to a .session.json file that --replay can run again",
+        field: |c| &mut c.record_sessions,
+    },
+];
+
+/// Toggles that can only be set at startup (config file or command line),
+/// so they can't be switched off from the settings screen.
+pub const STARTUP_TOGGLES: &[Toggle] = &[
+    Toggle {
+        key: "read_only",
+        label: "Read-only mode",
+        description: "Browse and view files, but disable sending and the test send",
+        field: |c| &mut c.read_only,
+    },
+    Toggle {
+        key: "require_arming",
+        label: "Require arming",
+        description: "Refuse sends from the file list until Ctrl+A arms the tool",
+        field: |c| &mut c.require_arming,
+    },
+];
+
+/// Error returned by actions that are disabled in read-only mode.
+pub const READ_ONLY_ERROR: &str = "read-only mode: sending is disabled";
+
+/// Error returned by sends while the tool is disarmed.
+pub const DISARMED_ERROR: &str = "tool is disarmed — press Ctrl+A to arm";
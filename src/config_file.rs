@@ -0,0 +1,359 @@
+//! Config file loading for MadTyping
+//!
+//! Reads `madtyping.toml` from the executable's directory. Only flat
+//! `key = value` lines are supported (numbers, `true`/`false` and quoted
+//! strings), plus the `[abbreviations]` table of `name = "text"` entries
+//! (see [`crate::abbreviations`]). Keys are validated against [`LIMITS`], [`TOGGLES`],
+//! [`STARTUP_TOGGLES`], [`CHOICES`] and [`TEXT_SETTINGS`]: out-of-range
+//! numbers are clamped and bad or unknown entries are skipped, each with a
+//! warning.
+//!
+//! While the UI runs, a [`ConfigWatcher`] picks up edits to the file. A
+//! reload is stricter than startup: a file that can't be read or has lines
+//! that aren't `key = value` (e.g. saved halfway) is rejected as a whole.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::abbreviations::is_name_char;
+use crate::charfilter;
+use crate::config::{Config, CHOICES, CONFIG_POLL_MS, LIMITS, STARTUP_TOGGLES, TEXT_SETTINGS, TOGGLES};
+use crate::files::{modified_time, parse_extensions};
+use crate::logging::log;
+use crate::paths;
+
+/// File name of the config file, stored next to the executable.
+pub const CONFIG_FILE_NAME: &str = "madtyping.toml";
+
+/// Name of the table holding the abbreviations.
+pub const ABBREVIATIONS_TABLE: &str = "abbreviations";
+
+/// Result of loading the config file.
+#[derive(Clone, Debug, Default)]
+pub struct LoadedConfig {
+    /// Validated settings (defaults for anything not set)
+    pub config: Config,
+    /// Path of the file that was read, if one exists
+    pub path: Option<PathBuf>,
+    /// Problems found while reading, one per clamped or ignored entry
+    pub warnings: Vec<String>,
+}
+
+/// Path of the config file next to the executable.
+pub fn config_path() -> Result<PathBuf, String> {
+    paths::exe_directory().map(|dir| dir.join(CONFIG_FILE_NAME))
+}
+
+/// Load and validate the config file. A missing file gives the defaults.
+pub fn load() -> LoadedConfig {
+    let Ok(path) = config_path() else {
+        return LoadedConfig::default();
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return LoadedConfig::default(),
+        Err(e) => {
+            return LoadedConfig {
+                warnings: vec![format!("could not read {}: {}", path.display(), e)],
+                ..LoadedConfig::default()
+            };
+        }
+    };
+
+    let (config, warnings) = parse(&content);
+    log(&format!("Loaded {} ({} warning(s))", path.display(), warnings.len()));
+    for warning in &warnings {
+        log(&format!("  Config warning: {}", warning));
+    }
+    LoadedConfig { config, path: Some(path), warnings }
+}
+
+/// Load the config file again after it changed.
+///
+/// Fails, so the caller can keep its current settings, if the file is gone,
+/// unreadable or has a line that isn't `key = value`.
+pub fn reload(path: &Path) -> Result<LoadedConfig, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    let malformed = content
+        .lines()
+        .map(str::trim)
+        .position(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('[') && !line.contains('='));
+    if let Some(index) = malformed {
+        return Err(format!("line {}: expected `key = value`", index + 1));
+    }
+
+    let (config, warnings) = parse(&content);
+    log(&format!("Reloaded {} ({} warning(s))", path.display(), warnings.len()));
+    for warning in &warnings {
+        log(&format!("  Config warning: {}", warning));
+    }
+    Ok(LoadedConfig { config, path: Some(path.to_path_buf()), warnings })
+}
+
+/// Watches the config file for changes by polling its modification time.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    /// Modification time of the last version seen
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl ConfigWatcher {
+    /// Watch `path`, taking its current version as already loaded.
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+        Self { path, modified, checked: Instant::now() }
+    }
+
+    /// Reload the file if it changed since the last call. Returns None if
+    /// it didn't, or if it was checked less than [`CONFIG_POLL_MS`] ago.
+    pub fn poll(&mut self) -> Option<Result<LoadedConfig, String>> {
+        if self.checked.elapsed() < Duration::from_millis(CONFIG_POLL_MS) {
+            return None;
+        }
+        self.checked = Instant::now();
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(reload(&self.path))
+    }
+}
+
+/// Parse config file contents into a validated [`Config`] plus warnings.
+pub fn parse(content: &str) -> (Config, Vec<String>) {
+    let mut config = Config::default();
+    let mut warnings = Vec::new();
+    // Table the lines belong to, None before the first `[table]` line
+    let mut table: Option<&str> = None;
+
+    for (number, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = strip_comment(line).strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim();
+            if name != ABBREVIATIONS_TABLE {
+                warnings.push(format!("unknown table [{}] on line {}, its entries are ignored", name, number + 1));
+            }
+            table = Some(name);
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            warnings.push(format!("line {}: expected `key = value`, ignored", number + 1));
+            continue;
+        };
+        let key = key.trim();
+        let value = strip_comment(value.trim());
+
+        if let Some(table) = table {
+            if table == ABBREVIATIONS_TABLE {
+                if let Err(e) = add_abbreviation(&mut config, key, value) {
+                    warnings.push(e);
+                }
+            }
+            continue;
+        }
+
+        if let Some(limit) = LIMITS.iter().find(|l| l.key == key) {
+            let field = (limit.field)(&mut config);
+            match value.replace('_', "").parse::<u64>() {
+                Ok(v) if v < limit.min => {
+                    warnings.push(format!("{} = {} is below the minimum, using {}", key, v, limit.min));
+                    *field = limit.min;
+                }
+                Ok(v) if v > limit.max => {
+                    warnings.push(format!("{} = {} is above the maximum, using {}", key, v, limit.max));
+                    *field = limit.max;
+                }
+                Ok(v) => *field = v,
+                Err(_) => {
+                    warnings.push(format!("{} = {} is not a number, using {}", key, value, limit.default));
+                    *field = limit.default;
+                }
+            }
+        } else if let Some(toggle) = TOGGLES.iter().chain(STARTUP_TOGGLES).find(|t| t.key == key) {
+            match value {
+                "true" => *(toggle.field)(&mut config) = true,
+                "false" => *(toggle.field)(&mut config) = false,
+                _ => warnings.push(format!("{} = {} is not true or false, ignored", key, value)),
+            }
+        } else if let Some(choice) = CHOICES.iter().find(|c| c.key == key) {
+            let name = unquote(value).unwrap_or(value);
+            match choice.options.iter().position(|o| o.eq_ignore_ascii_case(name)) {
+                Some(index) => (choice.set)(&mut config, index),
+                None => warnings.push(format!(
+                    "{} = {} must be one of {}, ignored",
+                    key,
+                    value,
+                    choice.options.join(", ")
+                )),
+            }
+        } else if let Some(setting) = TEXT_SETTINGS.iter().find(|t| t.key == key) {
+            match unquote(value) {
+                Some(text) if setting.allow_empty || !text.trim().is_empty() => {
+                    *(setting.field)(&mut config) = text.to_string()
+                }
+                _ if setting.allow_empty => {
+                    warnings.push(format!("{} must be a quoted string, using \"{}\"", key, setting.default))
+                }
+                _ => warnings.push(format!(
+                    "{} must be a non-empty quoted string, using \"{}\"",
+                    key, setting.default
+                )),
+            }
+        } else {
+            warnings.push(format!("unknown key '{}' on line {}, ignored", key, number + 1));
+        }
+    }
+    // The blacklist is free text; say which entries can't be read
+    warnings.extend(charfilter::parse_ranges(&config.char_blacklist).1);
+    warnings.extend(parse_extensions(&config.extensions).1);
+
+    (config, warnings)
+}
+
+/// Add the `[abbreviations]` entry `key = value` to `config`. The name may
+/// be quoted and may start with the sigil.
+fn add_abbreviation(config: &mut Config, key: &str, value: &str) -> Result<(), String> {
+    let name = unquote(key).unwrap_or(key);
+    let name = name.strip_prefix(config.abbreviation_sigil.as_str()).unwrap_or(name);
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return Err(format!("abbreviation '{}' may only use letters, digits, '_' and '-', ignored", name));
+    }
+    match unquote(value) {
+        Some(text) if !text.trim().is_empty() => {
+            config.abbreviations.insert(name.to_string(), text.to_string());
+            Ok(())
+        }
+        _ => Err(format!("abbreviation {} must be a non-empty quoted string, ignored", name)),
+    }
+}
+
+/// Remove a trailing `# comment`, ignoring `#` inside a quoted string.
+fn strip_comment(value: &str) -> &str {
+    let search_from = match value.strip_prefix('"') {
+        Some(rest) => rest.find('"').map_or(value.len(), |end| end + 2),
+        None => 0,
+    };
+    match value[search_from..].find('#') {
+        Some(pos) => value[..search_from + pos].trim_end(),
+        None => value,
+    }
+}
+
+/// Contents of a `"quoted"` value, or None if it isn't quoted.
+fn unquote(value: &str) -> Option<&str> {
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SendSound;
+
+    #[test]
+    fn test_values_clamped_to_limits() {
+        let (config, warnings) = parse("char_type_delay_ms = 0\nnext_line_delay_ms = 600_000\n");
+        assert_eq!(config.char_type_delay_ms, 1);
+        assert_eq!(config.next_line_delay_ms, 10_000);
+        assert_eq!(warnings, vec![
+            "char_type_delay_ms = 0 is below the minimum, using 1",
+            "next_line_delay_ms = 600000 is above the maximum, using 10000",
+        ]);
+    }
+
+    #[test]
+    fn test_unknown_and_invalid_entries_reported() {
+        let content = "# comment\nchar_delay = 3\ncheck_fullscreen = false # trailing\nbroadcast_per_file = yes\njunk\n";
+        let (config, warnings) = parse(content);
+        assert!(!config.check_fullscreen);
+        assert_eq!(config.broadcast_per_file, Config::default().broadcast_per_file);
+        assert_eq!(warnings, vec![
+            "unknown key 'char_delay' on line 2, ignored",
+            "broadcast_per_file = yes is not true or false, ignored",
+            "line 5: expected `key = value`, ignored",
+        ]);
+    }
+
+    #[test]
+    fn test_quoted_text_values() {
+        let (config, warnings) = parse("ping_text = \"gl #1\" # comment\n");
+        assert_eq!(config.ping_text, "gl #1");
+        assert!(warnings.is_empty());
+
+        let (config, warnings) = parse("ping_text = unquoted\n");
+        assert_eq!(config.ping_text, ".");
+        assert_eq!(warnings, vec!["ping_text must be a quoted string, using \".\""]);
+
+        // An empty string clears settings where that means something
+        let (config, warnings) = parse("ping_text = \"\"\npost_send_hook = \"\"\nabbreviation_sigil = \"\"\n");
+        assert_eq!((config.ping_text.as_str(), config.post_send_hook.as_str()), ("", ""));
+        assert_eq!(config.abbreviation_sigil, "~");
+        assert_eq!(warnings, vec!["abbreviation_sigil must be a non-empty quoted string, using \"~\""]);
+    }
+
+    #[test]
+    fn test_abbreviations_table() {
+        let content = "abbreviation_sigil = \"::\"\n\n[abbreviations]\nglhf = \"good luck have fun everyone!\" # opener\n\"::gg\" = \"good game\"\nbad name = \"x\"\nff = 15\n\n[aliases]\nwp = \"well played\"\n";
+        let (config, warnings) = parse(content);
+        assert_eq!(config.abbreviation_sigil, "::");
+        let entries: Vec<(&str, &str)> = config.abbreviations.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(entries, vec![("gg", "good game"), ("glhf", "good luck have fun everyone!")]);
+        assert_eq!(warnings, vec![
+            "abbreviation 'bad name' may only use letters, digits, '_' and '-', ignored",
+            "abbreviation ff must be a non-empty quoted string, ignored",
+            "unknown table [aliases] on line 9, its entries are ignored",
+        ]);
+    }
+
+    #[test]
+    fn test_startup_toggle_read_from_file() {
+        let (config, warnings) = parse("read_only = true\n");
+        assert!(config.read_only);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_reload_rejects_malformed_file() {
+        let path = std::env::temp_dir().join(format!("madtyping-reload-{}.toml", std::process::id()));
+        fs::write(&path, "next_line_delay_ms = 900\nsplit_long_lines = maybe\n").unwrap();
+        let reloaded = reload(&path);
+        fs::write(&path, "next_line_delay_ms = 900\nnext_line_del").unwrap();
+        let truncated = reload(&path);
+        fs::remove_file(&path).unwrap();
+
+        let reloaded = reloaded.unwrap();
+        assert_eq!(reloaded.config.next_line_delay_ms, 900);
+        assert_eq!(reloaded.warnings.len(), 1);
+        assert_eq!(truncated.unwrap_err(), "line 2: expected `key = value`");
+        assert!(reload(&path).is_err());
+    }
+
+    #[test]
+    fn test_choice_values() {
+        let (config, warnings) = parse("send_sound = \"LINE\"\n");
+        assert_eq!(config.send_sound, SendSound::Line);
+        assert!(warnings.is_empty());
+
+        let (config, warnings) = parse("send_sound = loud\n");
+        assert_eq!(config.send_sound, SendSound::Off);
+        assert_eq!(warnings, vec!["send_sound = loud must be one of off, end, line, ignored"]);
+    }
+
+    #[test]
+    fn test_limit_defaults_match_config_defaults() {
+        let mut config = Config::default();
+        for limit in LIMITS {
+            let value = *(limit.field)(&mut config);
+            assert_eq!(value, limit.default, "{}", limit.key);
+            assert!((limit.min..=limit.max).contains(&value), "{}", limit.key);
+        }
+    }
+}
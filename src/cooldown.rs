@@ -0,0 +1,122 @@
+//! Send cooldown
+//!
+//! The game mutes a chat that takes more than `chat_limit_messages` within
+//! `chat_limit_window_secs`. [`Cooldown`] remembers when lines went out to
+//! each target window title, so the budget left under that limit is known
+//! at any time. A send that needs more messages than are left has to wait
+//! until enough of the recent ones are older than the window: the header
+//! counts that down ("ready in 12s") and Enter asks before sending early.
+//!
+//! Every title has its own budget, since every game has its own chat, so
+//! switching to another target starts with a full one. With
+//! `chat_limit_messages = 0` the limit, and with it the cooldown, is off.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::send::ChatLimit;
+
+/// Recent messages per target window title.
+#[derive(Clone, Debug, Default)]
+pub struct Cooldown {
+    sent: HashMap<String, VecDeque<Instant>>,
+}
+
+impl Cooldown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note a message that went out to `title` at `at`.
+    pub fn record(&mut self, title: &str, at: Instant) {
+        self.sent.entry(title.to_string()).or_default().push_back(at);
+    }
+
+    /// Times of the messages to `title` still within `limit`'s window at
+    /// `now`, oldest first.
+    fn recent(&self, title: &str, limit: ChatLimit, now: Instant) -> Vec<Instant> {
+        self.sent
+            .get(title)
+            .map(|times| times.iter().copied().filter(|&at| now.saturating_duration_since(at) < limit.window).collect())
+            .unwrap_or_default()
+    }
+
+    /// Messages `title` can still take at `now` without going over
+    /// `limit`.
+    pub fn remaining(&self, title: &str, limit: ChatLimit, now: Instant) -> u64 {
+        limit.messages.saturating_sub(self.recent(title, limit, now).len() as u64)
+    }
+
+    /// How long until `title` has room for `needed` more messages (at
+    /// most a full budget); None if it has room now or the limit is off.
+    pub fn ready_in(&self, title: &str, needed: u64, limit: ChatLimit, now: Instant) -> Option<Duration> {
+        if limit.messages == 0 {
+            return None;
+        }
+        let needed = needed.clamp(1, limit.messages);
+        let recent = self.recent(title, limit, now);
+        let remaining = limit.messages.saturating_sub(recent.len() as u64);
+        if remaining >= needed {
+            return None;
+        }
+        // The oldest messages leave the window first, each freeing one
+        let freed_by = recent[(needed - remaining - 1) as usize];
+        Some((freed_by + limit.window).saturating_duration_since(now))
+    }
+
+    /// Forget messages that left the window, keeping the history short.
+    pub fn prune(&mut self, limit: ChatLimit, now: Instant) {
+        for times in self.sent.values_mut() {
+            while times.front().is_some_and(|&at| now.saturating_duration_since(at) >= limit.window) {
+                times.pop_front();
+            }
+        }
+        self.sent.retain(|_, times| !times.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit() -> ChatLimit {
+        ChatLimit { messages: 4, window: Duration::from_secs(10) }
+    }
+
+    #[test]
+    fn test_cooldown_follows_the_budget() {
+        let start = Instant::now();
+        let mut cooldown = Cooldown::new();
+        for secs in [0, 1, 2] {
+            cooldown.record("League", start + Duration::from_secs(secs));
+        }
+        let now = start + Duration::from_secs(3);
+        assert_eq!(cooldown.remaining("League", limit(), now), 1);
+        assert_eq!(cooldown.ready_in("League", 1, limit(), now), None);
+        // Three messages need the two oldest to leave the window
+        assert_eq!(cooldown.ready_in("League", 3, limit(), now), Some(Duration::from_secs(8)));
+        // More than the limit waits for a full budget
+        assert_eq!(cooldown.ready_in("League", 20, limit(), now), Some(Duration::from_secs(9)));
+        assert_eq!(cooldown.ready_in("League", 20, limit(), start + Duration::from_secs(12)), None);
+
+        // Another game has its own chat
+        assert_eq!(cooldown.remaining("Notepad", limit(), now), 4);
+        assert_eq!(cooldown.ready_in("Notepad", 4, limit(), now), None);
+
+        let off = ChatLimit { messages: 0, ..limit() };
+        assert_eq!(cooldown.ready_in("League", 20, off, now), None);
+    }
+
+    #[test]
+    fn test_prune_drops_old_messages() {
+        let start = Instant::now();
+        let mut cooldown = Cooldown::new();
+        cooldown.record("League", start);
+        cooldown.record("Notepad", start + Duration::from_secs(5));
+        cooldown.prune(limit(), start + Duration::from_secs(11));
+        assert_eq!(cooldown.sent.len(), 1);
+        assert_eq!(cooldown.remaining("Notepad", limit(), start + Duration::from_secs(11)), 3);
+    }
+}
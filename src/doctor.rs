@@ -0,0 +1,165 @@
+//! Environment diagnostics for MadTyping
+//!
+//! `--doctor` prints a report of everything that commonly breaks sending:
+//! missing message files, target windows that aren't running, and game
+//! display modes SendInput may not reach.
+
+use crate::build_info::version_string;
+use crate::config_file::{LoadedConfig, CONFIG_FILE_NAME};
+use crate::files::{self, ScanOptions};
+use crate::config::{READ_ONLY_ERROR, TITLE_SUGGESTIONS, TITLE_SUGGESTION_MIN_SCORE};
+use crate::platform::{self, is_window_running, target_kind, window_display_mode, DisplayMode, WindowKind, CLIENT_ONLY_WARNING};
+use crate::send::{self, PlatformSender};
+use crate::titles::closest;
+
+/// Result of a single diagnostic check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One line of the doctor report.
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub label: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(label: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self { label: label.into(), status, detail: detail.into() }
+    }
+}
+
+/// Warning text for a display mode that may swallow input, if any.
+pub(crate) fn display_mode_warning(mode: DisplayMode) -> Option<&'static str> {
+    match mode {
+        DisplayMode::ExclusiveFullscreen => Some(
+            "appears to be in exclusive fullscreen; switching focus may minimize it and \
+             messages may not arrive. Use borderless mode instead.",
+        ),
+        _ => None,
+    }
+}
+
+/// Run all checks for the given target windows.
+pub fn run_checks(window_titles: &[String], loaded: &LoadedConfig) -> Vec<Check> {
+    let config = &loaded.config;
+    let mut checks = vec![Check::new("Version", Status::Ok, version_string())];
+
+    match &loaded.path {
+        Some(path) => checks.push(Check::new("Config file", Status::Ok, path.display().to_string())),
+        None => checks.push(Check::new("Config file", Status::Ok, format!("no {}, using defaults", CONFIG_FILE_NAME))),
+    }
+    for warning in &loaded.warnings {
+        checks.push(Check::new("  Config warning", Status::Warn, warning.clone()));
+    }
+
+    match files::scan_directory() {
+        Ok(dir) => checks.push(Check::new("Scan directory", Status::Ok, dir.display().to_string())),
+        Err(e) => checks.push(Check::new("Scan directory", Status::Fail, e)),
+    }
+    match files::discover_with(ScanOptions::from(&loaded.config)) {
+        Ok(found) => checks.push(Check::new("Message files", Status::Ok, format!("{} found", found.len()))),
+        Err(e) => checks.push(Check::new("Message files", Status::Fail, e)),
+    }
+
+    for title in window_titles {
+        let kind = target_kind(title);
+        if !is_window_running(title) {
+            let open = platform::window_titles();
+            let detail = match closest(title, &open, TITLE_SUGGESTIONS, TITLE_SUGGESTION_MIN_SCORE).as_slice() {
+                _ if kind == Some(WindowKind::Client) => CLIENT_ONLY_WARNING.to_string(),
+                [] => "not running".to_string(),
+                similar => format!("not running; similar open windows: '{}'", similar.join("', '")),
+            };
+            checks.push(Check::new(format!("Window '{}'", title), Status::Fail, detail));
+            continue;
+        }
+        checks.push(Check::new(format!("Window '{}'", title), Status::Ok, "running"));
+        if let Some(kind) = kind {
+            let status = if kind == WindowKind::Game { Status::Ok } else { Status::Warn };
+            checks.push(Check::new("  Window kind", status, kind.describe()));
+        }
+
+        if !config.check_fullscreen {
+            checks.push(Check::new("  Display mode", Status::Ok, "check disabled in settings"));
+            continue;
+        }
+        match window_display_mode(title) {
+            Some(mode) => {
+                let status = if display_mode_warning(mode).is_some() { Status::Warn } else { Status::Ok };
+                let detail = match display_mode_warning(mode) {
+                    Some(warning) => format!("{} — {}", mode.describe(), warning),
+                    None => mode.describe().to_string(),
+                };
+                checks.push(Check::new("  Display mode", status, detail));
+            }
+            None => checks.push(Check::new("  Display mode", Status::Warn, "could not be determined")),
+        }
+    }
+
+    // Class names to copy into `window_class` when a localized title
+    // doesn't match
+    let fallback = match config.window_class.trim() {
+        "" => "off (window_class is not set)".to_string(),
+        class => format!("{} (used when no title matches)", class),
+    };
+    checks.push(Check::new("Window class", Status::Ok, fallback));
+    let open = platform::window_classes();
+    checks.push(Check::new("Open windows", Status::Ok, format!("{} visible", open.len())));
+    for (title, class) in open {
+        checks.push(Check::new(format!("  Class {}", class), Status::Ok, format!("'{}'", title)));
+    }
+
+    checks
+}
+
+/// Type the configured ping text into team chat of every running target
+/// window and report the result per window.
+pub fn run_ping(window_titles: &[String], loaded: &LoadedConfig) -> Vec<Check> {
+    let config = &loaded.config;
+    if config.read_only {
+        return vec![Check::new("Test send", Status::Fail, READ_ONLY_ERROR)];
+    }
+    if config.ping_text.is_empty() {
+        return vec![Check::new("Test send", Status::Warn, "ping_text is empty, nothing typed")];
+    }
+    let targets: Vec<String> = window_titles.iter().filter(|t| is_window_running(t)).cloned().collect();
+    if targets.is_empty() {
+        return vec![Check::new("Test send", Status::Fail, "no target window is running")];
+    }
+
+    let report = match send::ping(&mut PlatformSender, &config.ping_text, targets, config) {
+        Ok(report) => report,
+        Err(e) => return vec![Check::new("Test send", Status::Fail, e)],
+    };
+    report
+        .targets
+        .iter()
+        .map(|target| {
+            let label = format!("Test send '{}'", target.window_title);
+            match &target.failed {
+                Some((_, _, error)) => Check::new(label, Status::Fail, error.clone()),
+                None => Check::new(label, Status::Ok, format!("typed \"{}\" into team chat", config.ping_text)),
+            }
+        })
+        .collect()
+}
+
+/// Print the report to stdout. Returns false if any check failed.
+pub fn print_report(checks: &[Check]) -> bool {
+    println!("MadTyping doctor\n");
+    for check in checks {
+        let mark = match check.status {
+            Status::Ok => "✓",
+            Status::Warn => "!",
+            Status::Fail => "✗",
+        };
+        println!(" {} {:<28} {}", mark, check.label, check.detail);
+    }
+    !checks.iter().any(|c| c.status == Status::Fail)
+}
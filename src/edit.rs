@@ -0,0 +1,204 @@
+//! In-place line editing for MadTyping
+//!
+//! The viewer can change the text of one message and write it back to the
+//! file it came from. Only the message part of that one line on disk is
+//! rewritten; every other line, comment and blank line stays byte for byte
+//! as it was, and so do the `!at` prefix, CSV delay and other table cells.
+
+use std::{fs, path::Path};
+
+use crate::files::{has_extension, modified_time, parse_csv_record, split_table_row, LoadReason, TextFile};
+use crate::logging::log;
+
+/// How the message is stored on its line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Layout {
+    Plain,
+    Csv,
+    Table,
+}
+
+/// Whether the file changed on disk since it was loaded.
+pub fn changed_on_disk(file: &TextFile) -> bool {
+    modified_time(&file.path) != file.modified
+}
+
+/// Replace the text of message `index` with `text`, on disk and in `file`.
+///
+/// Fails without touching the file if the message can't be edited
+/// (truncated, or not read from a file line), the new text is empty, or
+/// the line on disk no longer holds the message (changed outside).
+pub fn rewrite_line(file: &mut TextFile, index: usize, text: &str) -> Result<(), String> {
+    let text = text.trim();
+    let line = file.lines.get(index).ok_or_else(|| format!("No line {}", index + 1))?;
+    if text.is_empty() {
+        return Err("A message can't be empty".to_string());
+    }
+    if line.truncated {
+        return Err("This line was truncated when loaded; edit it in a text editor".to_string());
+    }
+    let source = line.source.ok_or("This line can't be edited here")?;
+
+    let contents = fs::read_to_string(&file.path).map_err(|e| format!("Failed to read {}: {}", file.name, e))?;
+    let updated = replace_in_contents(&contents, source, &line.text, text, layout(&file.path))
+        .ok_or_else(|| format!("Line {} of {} changed on disk; reopen it first", source + 1, file.name))?;
+    fs::write(&file.path, updated).map_err(|e| format!("Failed to save {}: {}", file.name, e))?;
+
+    log(&format!("Edited line {} of {}", source + 1, file.name));
+    file.lines[index].text = text.to_string();
+    file.modified = modified_time(&file.path);
+    file.mark_reloaded(LoadReason::Edit);
+    Ok(())
+}
+
+fn layout(path: &Path) -> Layout {
+    if has_extension(path, "csv") {
+        Layout::Csv
+    } else if has_extension(path, "md") {
+        Layout::Table
+    } else {
+        Layout::Plain
+    }
+}
+
+/// Rewrite line `number` of `contents`, keeping line endings intact.
+fn replace_in_contents(contents: &str, number: usize, old: &str, new: &str, layout: Layout) -> Option<String> {
+    let mut rows: Vec<&str> = contents.split('\n').collect();
+    let raw = *rows.get(number)?;
+    let (body, cr) = match raw.strip_suffix('\r') {
+        Some(body) => (body, "\r"),
+        None => (raw, ""),
+    };
+    let replaced = format!("{}{}", replace_message(body, old, new, layout)?, cr);
+    rows[number] = &replaced;
+    Some(rows.join("\n"))
+}
+
+/// Replace the message `old` on one line with `new`, or None if the line
+/// doesn't hold `old`.
+fn replace_message(raw: &str, old: &str, new: &str, layout: Layout) -> Option<String> {
+    match layout {
+        // Markdown files without a table are plain text
+        Layout::Table if raw.trim_start().starts_with('|') => {
+            replace_cell(raw, old, new).or_else(|| replace_text(raw, old, new))
+        }
+        Layout::Csv => replace_csv_message(raw, old, new),
+        _ => replace_text(raw, old, new),
+    }
+}
+
+/// Replace the last occurrence of `old`: the message ends the line, after
+/// any `!at MM:SS`.
+fn replace_text(raw: &str, old: &str, new: &str) -> Option<String> {
+    let at = raw.rfind(old)?;
+    Some(format!("{}{}{}", &raw[..at], new, &raw[at + old.len()..]))
+}
+
+/// Replace the table cell holding `old`, keeping the padding around it.
+fn replace_cell(raw: &str, old: &str, new: &str) -> Option<String> {
+    let cells = split_table_row(raw);
+    let column = cells.iter().position(|c| c == old)?;
+    // Positions of the cell separators; `\|` is a literal pipe
+    let mut pipes = Vec::new();
+    let mut after_backslash = false;
+    for (i, c) in raw.char_indices() {
+        if c == '|' && !after_backslash {
+            pipes.push(i);
+        }
+        after_backslash = c == '\\';
+    }
+    let offset = usize::from(raw.trim_start().starts_with('|'));
+    let start = if column + offset == 0 { 0 } else { pipes[column + offset - 1] + 1 };
+    let end = pipes.get(column + offset).copied().unwrap_or(raw.len());
+    let cell = &raw[start..end];
+    let padded_start = start + (cell.len() - cell.trim_start().len());
+    let padded_end = end - (cell.len() - cell.trim_end().len());
+    Some(format!("{}{}{}", &raw[..padded_start], new.replace('|', "\\|"), &raw[padded_end..]))
+}
+
+/// Replace the message column of a CSV record, keeping the delay column
+/// and a weight column after the message.
+fn replace_csv_message(raw: &str, old: &str, new: &str) -> Option<String> {
+    let fields = parse_csv_record(raw);
+    let message = match fields.split_first() {
+        Some((_, rest)) if !rest.is_empty() => rest.join(","),
+        _ => fields.first()?.clone(),
+    };
+    let weight = match fields.split_last() {
+        Some((last, rest)) if message.trim() != old && rest.len() >= 2 && rest[1..].join(",").trim() == old => Some(last),
+        _ => None,
+    };
+    if message.trim() != old && weight.is_none() {
+        return None;
+    }
+    // Unquoted text can simply be swapped; anything else is written out again
+    if raw.rfind(old).is_some_and(|at| !raw[..at].ends_with('"')) && !new.contains(['"', ',']) {
+        return replace_text(raw, old, new);
+    }
+    let quoted = if new.contains(['"', ',']) { format!("\"{}\"", new.replace('"', "\"\"")) } else { new.to_string() };
+    Some(match (fields.len(), weight) {
+        (1, _) => quoted,
+        (_, Some(weight)) => format!("{},{},{}", fields[0], quoted, weight),
+        _ => format!("{},{}", fields[0], quoted),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_plain_line_rewritten_in_place() {
+        let contents = "# greetings\r\n\r\n  gl hf  \r\n!at 15:00 ff?\r\ngl hf\r\n";
+        assert_eq!(
+            replace_in_contents(contents, 2, "gl hf", "glhf all", Layout::Plain).unwrap(),
+            "# greetings\r\n\r\n  glhf all  \r\n!at 15:00 ff?\r\ngl hf\r\n"
+        );
+        assert_eq!(
+            replace_in_contents(contents, 3, "ff?", "15?", Layout::Plain).unwrap(),
+            "# greetings\r\n\r\n  gl hf  \r\n!at 15:00 15?\r\ngl hf\r\n"
+        );
+        assert_eq!(replace_in_contents(contents, 3, "gl hf", "x", Layout::Plain), None);
+        assert_eq!(replace_in_contents(contents, 9, "gl hf", "x", Layout::Plain), None);
+    }
+
+    #[test]
+    fn test_table_cell_rewritten_in_place() {
+        let row = "|  gl hf  | all  | 500 |";
+        assert_eq!(replace_message(row, "gl hf", "a | b", Layout::Table).unwrap(), "|  a \\| b  | all  | 500 |");
+        assert_eq!(replace_message("| team | x \\| y |", "x | y", "z", Layout::Table).unwrap(), "| team | z |");
+        assert_eq!(replace_message("plain text", "text", "words", Layout::Table).unwrap(), "plain words");
+    }
+
+    #[test]
+    fn test_csv_message_rewritten() {
+        assert_eq!(replace_message("500, gl hf", "gl hf", "wp", Layout::Csv).unwrap(), "500, wp");
+        assert_eq!(replace_message("500,gl hf", "gl hf", "gl, hf", Layout::Csv).unwrap(), "500,\"gl, hf\"");
+        assert_eq!(replace_message(r#","say ""hi""""#, "say \"hi\"", "bye", Layout::Csv).unwrap(), ",bye");
+        let quoted = replace_message("0,\"a, b\"", "a, b", "say \"c\"", Layout::Csv).unwrap();
+        assert_eq!(parse_csv_record(&quoted), vec!["0", "say \"c\""]);
+        // The weight column stays after the message
+        assert_eq!(replace_message("500,gg,5", "gg", "gl, hf", Layout::Csv).unwrap(), "500,\"gl, hf\",5");
+        assert_eq!(replace_message("ff {w=5}", "ff", "go", Layout::Plain).unwrap(), "go {w=5}");
+        assert_eq!(replace_message("0,other", "a, b", "c", Layout::Csv), None);
+    }
+
+    #[test]
+    fn test_rewrite_line_on_disk() {
+        let path = std::env::temp_dir().join(format!("madtyping-edit-{}.txt", std::process::id()));
+        fs::write(&path, "---\nwindow_title: Game\n---\n\n# taunts\nez\n!at 10:00 gg\n").unwrap();
+        let mut file = TextFile::from_path(PathBuf::from(&path)).unwrap();
+
+        rewrite_line(&mut file, 2, " gg wp ").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "---\nwindow_title: Game\n---\n\n# taunts\nez\n!at 10:00 gg wp\n");
+        assert_eq!(file.lines[2].text, "gg wp");
+        assert_eq!(file.provenance.reason, LoadReason::Edit);
+        assert!(!changed_on_disk(&file));
+        assert!(rewrite_line(&mut file, 0, "  ").is_err());
+
+        file.lines[0].text = "stale".to_string();
+        assert!(rewrite_line(&mut file, 0, "new").unwrap_err().contains("changed on disk"));
+        let _ = fs::remove_file(&path);
+    }
+}
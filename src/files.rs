@@ -1,15 +1,133 @@
 //! File discovery and management for MadTyping
 //!
-//! Handles discovering text files (.txt, .md) from the executable's directory
-//! and loading their contents.
+//! Handles discovering text files (.txt, .md, .csv) from the scan
+//! directory and loading their contents.
+//!
+//! The scan directory is chosen once at startup, first match wins:
+//!
+//! 1. `--dir DIR`
+//! 2. the first existing directory in the `message_dirs` setting
+//! 3. the executable's directory, if it has message files (and they
+//!    weren't moved to the user directory, see [`crate::migration`])
+//! 4. the user directory (`user_message_dir`, `~/Documents/MadTyping`),
+//!    if it exists
+//! 5. the executable's directory
 
 use std::{
-    env,
+    collections::{HashMap, HashSet},
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use crate::abbreviations;
+use crate::config::{
+    Config, ARCHIVE_DIRECTORY, BINARY_CONTROL_PERCENT, DIFF_MAX_CELLS, MAX_DISCOVERED_FILES, MAX_LINE_CHARS, MAX_SCAN_DEPTH, SUPPORTED_EXTENSIONS,
+    TEMP_FILE_SUFFIXES,
 };
+use crate::front_matter::{self, FrontMatter};
+use crate::logging::log;
+use crate::platform::ChatScope;
+use crate::paths;
+use crate::tags;
+use crate::template;
+use crate::timed;
+use crate::weights::{self, WEIGHT_HEADERS};
+
+/// A single message line together with its per-line metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// The message text (trimmed)
+    pub text: String,
+    /// Extra delay in milliseconds to wait before sending this line
+    pub delay_ms: Option<u64>,
+    /// The original line was longer than [`MAX_LINE_CHARS`] and was cut
+    pub truncated: bool,
+    /// Chat channel for this line, overriding the send's channel
+    pub chat: Option<ChatScope>,
+    /// Send clock time to wait for before sending (`!at MM:SS`)
+    pub at: Option<Duration>,
+    /// Zero-based line of the file on disk the message was read from
+    pub source: Option<usize>,
+    /// Blank lines right above this one in a plain text file; each is a
+    /// pause when `blank_line_pause_ms` is set
+    pub blank_before: usize,
+    /// How often a random pick lands on this line relative to the others
+    /// (`{w=N}`, default 1); 0 never
+    pub weight: u32,
+}
+
+impl Line {
+    /// Whether the line is a chat command like `/mute all` or `/ff`.
+    pub fn is_command(&self) -> bool {
+        let mut chars = self.text.chars();
+        chars.next() == Some('/') && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+    }
+
+    /// Create a plain line without metadata.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            delay_ms: None,
+            truncated: false,
+            chat: None,
+            at: None,
+            source: None,
+            blank_before: 0,
+            weight: 1,
+        }
+    }
+}
+
+/// Why a file's lines were last read from disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadReason {
+    /// The first scan after startup
+    Initial,
+    /// A later scan: F5, an import or a saved ad-hoc line
+    Rescan,
+    /// The viewer saw the file change on disk
+    Watcher,
+    /// A line was edited in the viewer
+    Edit,
+    /// Read again before sending because it changed on disk
+    SendReload,
+}
+
+impl LoadReason {
+    /// Short name used in the log.
+    pub fn label(self) -> &'static str {
+        match self {
+            LoadReason::Initial => "initial",
+            LoadReason::Rescan => "F5/rescan",
+            LoadReason::Watcher => "watcher",
+            LoadReason::Edit => "edit",
+            LoadReason::SendReload => "send-reload",
+        }
+    }
+}
+
+/// When and why a file's lines were loaded, so the copy on screen can be
+/// told apart from the one on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    pub loaded_at: SystemTime,
+    pub reason: LoadReason,
+}
+
+impl Provenance {
+    /// Loaded just now for `reason`.
+    pub fn new(reason: LoadReason) -> Self {
+        Self { loaded_at: SystemTime::now(), reason }
+    }
+}
 
-use crate::config::SUPPORTED_EXTENSIONS;
+impl Default for Provenance {
+    fn default() -> Self {
+        Self::new(LoadReason::Initial)
+    }
+}
 
 /// Represents a discovered text file with its contents.
 #[derive(Clone, Debug)]
@@ -19,48 +137,599 @@ pub struct TextFile {
     /// Full path to the file
     pub path: PathBuf,
     /// Non-empty lines from the file (trimmed)
-    pub lines: Vec<String>,
+    pub lines: Vec<Line>,
+    /// Problems found while parsing (shown in the UI, never fatal)
+    pub warnings: Vec<String>,
+    /// Modification time when the file was loaded
+    pub modified: Option<SystemTime>,
+    /// Lowercase labels from the tags file
+    pub tags: Vec<String>,
+    /// Per-file settings from the file's front-matter block
+    pub front: FrontMatter,
+    /// When and why the lines were loaded
+    pub provenance: Provenance,
 }
 
 impl TextFile {
     /// Create a new TextFile from a path, reading and parsing its contents.
-    /// Returns None if the file can't be read or has no non-empty lines.
+    /// Returns None if the file can't be read, isn't text or has no
+    /// non-empty lines.
     pub fn from_path(path: PathBuf) -> Option<Self> {
+        Self::load(path).unwrap_or_else(|skipped| {
+            log(&format!("Skipped {}: {}", skipped.name, skipped.reason));
+            None
+        })
+    }
+
+    /// Read and parse a file as [`TextFile::from_path`] does, telling why
+    /// it was skipped if it can't be read or isn't text.
+    pub fn load(path: PathBuf) -> Result<Option<Self>, SkippedFile> {
         let name = path.file_name()
             .map(|n| n.to_string_lossy().to_string())
             .unwrap_or_else(|| "unknown".to_string());
 
-        match fs::read_to_string(&path) {
-            Ok(contents) => {
-                let lines: Vec<String> = contents
-                    .lines()
-                    .map(|l| l.trim())
-                    .filter(|l| !l.is_empty())
-                    .map(|l| l.to_string())
-                    .collect();
-                
-                if lines.is_empty() {
-                    None
-                } else {
-                    Some(Self { name, path, lines })
-                }
-            }
-            Err(e) => {
-                eprintln!("Warning: Could not read {}: {}", name, e);
-                None
+        let modified = modified_time(&path);
+
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => return Err(SkippedFile { name, path, reason: format!("couldn't be read: {}", e) }),
+        };
+        if is_binary(&bytes) {
+            return Err(SkippedFile { name, path, reason: BINARY_SKIP_REASON.to_string() });
+        }
+
+        // Valid UTF-8 once is_binary passed, so this borrows
+        let contents = String::from_utf8_lossy(&bytes);
+        let (front, body, front_warnings) = front_matter::split(&contents);
+        // Message sources count from the top of the file, front matter included
+        let front_lines = contents[..contents.len() - body.len()].matches('\n').count();
+        let contents = body;
+        let table = if has_extension(&path, "md") { parse_markdown_table(contents) } else { None };
+        let (mut lines, mut warnings) = if has_extension(&path, "csv") {
+            parse_csv(contents)
+        } else if let Some(parsed) = table {
+            parsed
+        } else {
+            (parse_plain(contents), Vec::new())
+        };
+        for line in &mut lines {
+            line.source = line.source.map(|n| n + front_lines);
+        }
+        warnings.splice(0..0, front_warnings);
+        warnings.extend(timed::apply_directives(&mut lines));
+        warnings.extend(weights::apply_suffixes(&mut lines));
+        warnings.extend(cap_lines(&mut lines, MAX_LINE_CHARS));
+        warnings.extend(abbreviations::current().warnings(&lines));
+        warnings.extend(template::warnings(&lines));
+
+        for warning in &warnings {
+            if crate::warnings::report(&file_warning_id(&path, warning)) {
+                log(&format!("Warning in {}: {}", name, warning));
             }
         }
+        
+        if lines.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(Self { name, path, lines, warnings, modified, tags: Vec::new(), front, provenance: Provenance::default() }))
+        }
+    }
+
+    /// A file called `name` holding `lines`, loaded from nowhere.
+    #[cfg(test)]
+    pub fn with_lines(name: &str, lines: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            lines: lines.iter().map(|&text| Line::new(text)).collect(),
+            warnings: Vec::new(),
+            modified: None,
+            tags: Vec::new(),
+            front: FrontMatter::default(),
+            provenance: Provenance::default(),
+        }
+    }
+
+    /// Note that the lines were just replaced for `reason`, and log it.
+    pub fn mark_reloaded(&mut self, reason: LoadReason) {
+        self.provenance = Provenance::new(reason);
+        log(&format!("Reloaded {} ({})", self.name, reason.label()));
     }
 
     /// Get the number of lines in this file.
     pub fn line_count(&self) -> usize {
         self.lines.len()
     }
+
+    /// Index of the message the first warning points at, for warnings
+    /// that start with `line N` or `line(s) N, ...`.
+    pub fn warning_line(&self) -> Option<usize> {
+        let warning = self.warnings.first()?;
+        let rest = warning.strip_prefix("line(s) ").or_else(|| warning.strip_prefix("line "))?;
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        let number: usize = digits.parse().ok()?;
+        Some(number.saturating_sub(1).min(self.lines.len().saturating_sub(1)))
+    }
+
+    /// Windows this file is sent to: its front-matter `window_title` if set,
+    /// otherwise the session's targets.
+    pub fn targets(&self, session_targets: &[String]) -> Vec<String> {
+        match &self.front.window_title {
+            Some(title) => vec![title.clone()],
+            None => session_targets.to_vec(),
+        }
+    }
+}
+
+/// Where discovery looks for message files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Also search subdirectories, up to [`MAX_SCAN_DEPTH`] levels deep
+    pub recursive: bool,
+    /// Stop after this many supported files
+    pub max_files: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self { recursive: false, max_files: MAX_DISCOVERED_FILES as usize }
+    }
+}
+
+impl From<&Config> for ScanOptions {
+    fn from(config: &Config) -> Self {
+        Self { recursive: config.recursive_discovery, max_files: config.max_discovered_files as usize }
+    }
+}
+
+/// Reason given for a supported file whose content isn't text.
+pub const BINARY_SKIP_REASON: &str = "binary — skipped";
+
+/// A supported file that discovery left out, and why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedFile {
+    /// Display name, as for [`TextFile::name`]
+    pub name: String,
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Result of a scan: the message files and the files skipped on the way.
+#[derive(Clone, Debug, Default)]
+pub struct Scan {
+    pub files: Vec<TextFile>,
+    pub skipped: Vec<SkippedFile>,
+    /// Set when the scan stopped at [`ScanOptions::max_files`], saying so
+    pub cap_warning: Option<String>,
+}
+
+/// Why a directory was picked as the scan directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanSource {
+    /// Given with `--dir`
+    Explicit,
+    /// From the `message_dirs` setting
+    Config,
+    /// The executable's directory
+    Exe,
+    /// The per-user directory (`user_message_dir`)
+    User,
+}
+
+impl ScanSource {
+    /// Short description for the log and the help screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            ScanSource::Explicit => "--dir",
+            ScanSource::Config => "message_dirs",
+            ScanSource::Exe => "executable directory",
+            ScanSource::User => "user directory",
+        }
+    }
+}
+
+/// The directory message files are discovered in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanDirectory {
+    pub path: PathBuf,
+    pub source: ScanSource,
+}
+
+impl ScanDirectory {
+    /// One-line description for the log and the help screen.
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self.path.display(), self.source.label())
+    }
+}
+
+/// State key remembering the answer to the offer to move files to the user
+/// directories: "moved" or "declined" ("copied" from versions that copied
+/// message files only).
+pub const MIGRATION_STATE_KEY: &str = "messages.migration";
+
+/// Scan directory chosen at startup; `None` means the executable's directory.
+static SCAN_DIRECTORY: Mutex<Option<ScanDirectory>> = Mutex::new(None);
+
+/// Pick the scan directory in the order documented at the top of this module.
+///
+/// Config directories that don't exist are skipped. With `skip_exe`, the
+/// executable's directory is only used as the last resort.
+pub fn choose_scan_directory(
+    explicit: Option<&Path>,
+    config_dirs: &[PathBuf],
+    exe_dir: &Path,
+    user_dir: Option<&Path>,
+    skip_exe: bool,
+) -> Result<ScanDirectory, String> {
+    if let Some(dir) = explicit {
+        if !dir.is_dir() {
+            return Err(format!("--dir {} is not a directory", dir.display()));
+        }
+        return Ok(ScanDirectory { path: dir.to_path_buf(), source: ScanSource::Explicit });
+    }
+    for dir in config_dirs {
+        if dir.is_dir() {
+            return Ok(ScanDirectory { path: dir.clone(), source: ScanSource::Config });
+        }
+        log(&format!("message_dirs: {} doesn't exist, skipped", dir.display()));
+    }
+    if !skip_exe && has_message_files(exe_dir) {
+        return Ok(ScanDirectory { path: exe_dir.to_path_buf(), source: ScanSource::Exe });
+    }
+    match user_dir {
+        Some(dir) if dir.is_dir() => Ok(ScanDirectory { path: dir.to_path_buf(), source: ScanSource::User }),
+        _ => Ok(ScanDirectory { path: exe_dir.to_path_buf(), source: ScanSource::Exe }),
+    }
+}
+
+/// Use `dir` as the scan directory from now on.
+pub fn set_scan_directory(dir: ScanDirectory) {
+    log(&format!("Scan directory: {}", dir.describe()));
+    *SCAN_DIRECTORY.lock().unwrap_or_else(|e| e.into_inner()) = Some(dir);
+}
+
+/// The scan directory chosen at startup, if one was.
+pub fn chosen_scan_directory() -> Option<ScanDirectory> {
+    SCAN_DIRECTORY.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Whether `dir` directly contains a supported file.
+pub fn has_message_files(dir: &Path) -> bool {
+    let mut paths = Vec::new();
+    collect_paths(dir, 0, 1, &mut paths).is_ok() && !paths.is_empty()
+}
+
+/// The message files in `dir` (and subdirectories up to
+/// [`MAX_SCAN_DEPTH`] levels) plus its `tags.toml` if there is one.
+pub fn message_file_paths(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    collect_paths(dir, MAX_SCAN_DEPTH, usize::MAX, &mut paths).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    paths.push(dir.join(tags::TAGS_FILE_NAME));
+    paths.retain(|p| p.is_file());
+    Ok(paths)
+}
+
+/// Copy the message files in `from` (and subdirectories up to
+/// [`MAX_SCAN_DEPTH`] levels) plus its `tags.toml` to `to`, keeping their
+/// relative paths. Files that already exist in `to` are left alone.
+///
+/// Returns the number of files copied.
+pub fn copy_message_files(from: &Path, to: &Path) -> Result<usize, String> {
+    let mut copied = 0;
+    for path in &message_file_paths(from)? {
+        let dest = to.join(path.strip_prefix(from).unwrap_or(path));
+        if dest.exists() {
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::copy(path, &dest).map_err(|e| format!("Failed to copy {}: {}", path.display(), e))?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// Append `text` as a line of `name` in `dir`, creating the file if needed.
+/// Returns the file's path.
+pub fn append_line(dir: &Path, name: &str, text: &str) -> Result<PathBuf, String> {
+    let path = dir.join(name);
+    let mut contents = fs::read_to_string(&path).unwrap_or_default();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(text);
+    contents.push('\n');
+    fs::write(&path, contents).map_err(|e| format!("Failed to save to {}: {}", name, e))?;
+    Ok(path)
+}
+
+/// Get a file's last modification time, if available.
+pub fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Whether a file's content is something other than text: invalid UTF-8,
+/// a NUL byte, or more than [`BINARY_CONTROL_PERCENT`] percent control
+/// characters (tabs, line breaks and form feeds don't count).
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if std::str::from_utf8(bytes).is_err() || bytes.contains(&0) {
+        return true;
+    }
+    let controls = bytes
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c)) || b == 0x7f)
+        .count();
+    controls * 100 > bytes.len() * BINARY_CONTROL_PERCENT
+}
+
+/// Parse plain text: every non-empty line (trimmed) is a message.
+fn parse_plain(contents: &str) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut blanks = 0;
+    for (i, raw) in contents.lines().enumerate() {
+        let text = raw.trim();
+        if text.is_empty() {
+            blanks += 1;
+            continue;
+        }
+        // Blank lines before the first message are not a pause between lines
+        let blank_before = if lines.is_empty() { 0 } else { blanks };
+        lines.push(Line { source: Some(i), blank_before, ..Line::new(text) });
+        blanks = 0;
+    }
+    lines
+}
+
+/// Truncate lines longer than `max_chars`, flagging them as truncated.
+/// Returns a warning listing the affected lines, if any, numbered from 1
+/// as the viewer shows them.
+fn cap_lines(lines: &mut [Line], max_chars: usize) -> Option<String> {
+    let mut capped = Vec::new();
+    for (i, line) in lines.iter_mut().enumerate() {
+        if let Some((end, _)) = line.text.char_indices().nth(max_chars) {
+            line.text.truncate(end);
+            line.truncated = true;
+            capped.push((i + 1).to_string());
+        }
+    }
+    if capped.is_empty() {
+        return None;
+    }
+    Some(format!("line(s) {} truncated to {} characters", capped.join(", "), max_chars))
+}
+
+/// Parse a two-column `delay, message` CSV file.
+///
+/// The delay column is optional (empty means no extra delay). Unquoted
+/// commas after the first column are kept as part of the message. A header
+/// row is detected and skipped; when it ends in a `weight` column, the last
+/// field of each row is the line's weight. Rows with a non-numeric delay
+/// or weight are still sent without it and reported in a single warning.
+fn parse_csv(contents: &str) -> (Vec<Line>, Vec<String>) {
+    let mut lines = Vec::new();
+    let mut bad_rows = Vec::new();
+    let (mut weighted, mut bad_weight, mut unpicked) = (false, Vec::new(), Vec::new());
+
+    for (i, raw) in contents.lines().enumerate() {
+        let row_num = i + 1;
+        if raw.trim().is_empty() {
+            continue;
+        }
+
+        let fields = parse_csv_record(raw);
+        if row_num == 1 && is_csv_header(&fields) {
+            weighted = fields.len() >= 3 && fields.last().is_some_and(|f| is_weight_header(f));
+            continue;
+        }
+
+        let mut fields = fields;
+        let weight_field = if weighted && fields.len() >= 3 { fields.pop() } else { None };
+        let (delay_field, message) = match fields.split_first() {
+            Some((first, rest)) if !rest.is_empty() => (first.trim(), rest.join(",")),
+            Some((only, _)) => ("", only.clone()),
+            None => continue,
+        };
+
+        let message = message.trim();
+        if message.is_empty() {
+            continue;
+        }
+
+        let delay_ms = if delay_field.is_empty() {
+            None
+        } else {
+            match delay_field.parse::<u64>() {
+                Ok(ms) => Some(ms),
+                Err(_) => {
+                    bad_rows.push(row_num.to_string());
+                    None
+                }
+            }
+        };
+
+        let mut line = Line { delay_ms, source: Some(i), ..Line::new(message) };
+        if let Some(field) = weight_field.filter(|f| !f.trim().is_empty()) {
+            apply_weight_cell(&mut line, &field, row_num, &mut bad_weight, &mut unpicked);
+        }
+        lines.push(line);
+    }
+
+    let mut warnings = Vec::new();
+    if !bad_rows.is_empty() {
+        warnings.push(format!(
+            "non-numeric delay ignored on row(s) {}",
+            bad_rows.join(", ")
+        ));
+    }
+    warnings.extend(weight_warnings(bad_weight, unpicked));
+    (lines, warnings)
+}
+
+fn is_weight_header(cell: &str) -> bool {
+    WEIGHT_HEADERS.contains(&cell.trim().to_lowercase().as_str())
+}
+
+/// Set `line`'s weight from a weight column cell of row `row_num`, noting
+/// the row if the cell isn't a number or leaves the line out of picks.
+fn apply_weight_cell(line: &mut Line, cell: &str, row_num: usize, bad: &mut Vec<String>, unpicked: &mut Vec<String>) {
+    match weights::parse_weight(cell) {
+        Ok(weight) => {
+            if weight < 1 {
+                unpicked.push(row_num.to_string());
+            }
+            line.weight = weights::clamp_weight(weight);
+        }
+        Err(_) => bad.push(row_num.to_string()),
+    }
+}
+
+/// Warnings for the rows [`apply_weight_cell`] noted.
+fn weight_warnings(bad: Vec<String>, unpicked: Vec<String>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if !bad.is_empty() {
+        warnings.push(format!("non-numeric weight ignored on row(s) {}", bad.join(", ")));
+    }
+    if !unpicked.is_empty() {
+        warnings.push(format!("row(s) {} left out of random picks (weight 0 or less)", unpicked.join(", ")));
+    }
+    warnings
+}
+
+/// Header names (lowercase) recognized for the message column.
+const MESSAGE_HEADERS: &[&str] = &["message", "text", "msg", "line"];
+
+/// Header names (lowercase) recognized for the delay column.
+const DELAY_HEADERS: &[&str] = &["delay", "delay_ms", "delay (ms)", "ms", "wait"];
+
+/// Header names (lowercase) recognized for the channel column.
+const CHANNEL_HEADERS: &[&str] = &["channel", "chat"];
+
+/// Parse the first markdown table with a `message` column, if there is one.
+///
+/// Optional `channel` (`all`/`team`) and `delay` (ms) columns set per-line
+/// metadata. Rows with the wrong number of cells are skipped, and bad
+/// channel or delay cells are ignored; each kind is reported once with the
+/// affected line numbers. `\|` writes a literal pipe.
+fn parse_markdown_table(contents: &str) -> Option<(Vec<Line>, Vec<String>)> {
+    let rows: Vec<(usize, &str)> = contents.lines().enumerate().map(|(i, l)| (i + 1, l.trim())).collect();
+    let header_at = rows.iter().position(|(_, l)| {
+        l.starts_with('|') && split_table_row(l).iter().any(|c| MESSAGE_HEADERS.contains(&c.to_lowercase().as_str()))
+    })?;
+    let header = split_table_row(rows[header_at].1);
+    let column = |names: &[&str]| header.iter().position(|c| names.contains(&c.to_lowercase().as_str()));
+    let message_col = column(MESSAGE_HEADERS)?;
+    let channel_col = column(CHANNEL_HEADERS);
+    let delay_col = column(DELAY_HEADERS);
+    let weight_col = column(WEIGHT_HEADERS);
+
+    let mut lines = Vec::new();
+    let (mut malformed, mut bad_channel, mut bad_delay) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut bad_weight, mut unpicked) = (Vec::new(), Vec::new());
+    for &(row_num, raw) in rows[header_at + 1..].iter().take_while(|(_, l)| l.starts_with('|')) {
+        let cells = split_table_row(raw);
+        if cells.iter().all(|c| !c.is_empty() && c.chars().all(|ch| matches!(ch, '-' | ':' | ' '))) {
+            continue; // separator row
+        }
+        if cells.len() != header.len() {
+            malformed.push(row_num.to_string());
+            continue;
+        }
+        let message = cells[message_col].as_str();
+        if message.is_empty() {
+            continue;
+        }
+        let mut line = Line { source: Some(row_num - 1), ..Line::new(message) };
+        if let Some(cell) = channel_col.map(|c| cells[c].to_lowercase()).filter(|c| !c.is_empty()) {
+            match cell.as_str() {
+                "all" => line.chat = Some(ChatScope::All),
+                "team" => line.chat = Some(ChatScope::Team),
+                _ => bad_channel.push(row_num.to_string()),
+            }
+        }
+        if let Some(cell) = delay_col.map(|c| &cells[c]).filter(|c| !c.is_empty()) {
+            match cell.parse::<u64>() {
+                Ok(ms) => line.delay_ms = Some(ms),
+                Err(_) => bad_delay.push(row_num.to_string()),
+            }
+        }
+        if let Some(cell) = weight_col.map(|c| &cells[c]).filter(|c| !c.is_empty()) {
+            apply_weight_cell(&mut line, cell, row_num, &mut bad_weight, &mut unpicked);
+        }
+        lines.push(line);
+    }
+
+    let mut warnings = Vec::new();
+    for (rows, what) in [
+        (malformed, "malformed table row(s) {} skipped"),
+        (bad_channel, "unknown channel ignored on row(s) {}"),
+        (bad_delay, "non-numeric delay ignored on row(s) {}"),
+    ] {
+        if !rows.is_empty() {
+            warnings.push(what.replace("{}", &rows.join(", ")));
+        }
+    }
+    warnings.extend(weight_warnings(bad_weight, unpicked));
+    Some((lines, warnings))
+}
+
+/// Split a markdown table row into trimmed cells, honouring `\|` escapes.
+pub(crate) fn split_table_row(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = if row.ends_with('|') && !row.ends_with("\\|") { &row[..row.len() - 1] } else { row };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
 }
 
-/// Discover all supported text files from the executable's directory.
+/// Split a single CSV record into fields.
+///
+/// Supports double-quoted fields containing commas and `""` escapes.
+pub(crate) fn parse_csv_record(raw: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Guess whether the first CSV row is a header rather than data.
+fn is_csv_header(fields: &[String]) -> bool {
+    let first = fields.first().map(|f| f.trim().to_lowercase()).unwrap_or_default();
+    let second = fields.get(1).map(|f| f.trim().to_lowercase()).unwrap_or_default();
+    DELAY_HEADERS.contains(&first.as_str()) || MESSAGE_HEADERS.contains(&second.as_str())
+}
+
+/// Discover all supported text files from the scan directory.
 /// 
-/// Scans the directory containing the executable for .txt and .md files,
+/// Scans the [`scan_directory`] for .txt, .md and .csv files,
 /// reads their contents, and returns a list of TextFile objects.
 /// 
 /// # Errors
@@ -69,55 +738,441 @@ impl TextFile {
 /// - The directory cannot be read
 /// - No valid text files are found
 pub fn discover() -> Result<Vec<TextFile>, String> {
-    let exe_dir = get_exe_directory()?;
+    discover_with(ScanOptions::default())
+}
+
+/// Discover message files as [`discover`] does, optionally including
+/// subdirectories. Files found there are named by their relative path
+/// (e.g. `aram/gg.txt`). Skipped files are reported on stderr.
+pub fn discover_with(options: ScanOptions) -> Result<Vec<TextFile>, String> {
+    let scan = scan(options)?;
+    for skipped in &scan.skipped {
+        eprintln!("Warning: Skipped {}: {}", skipped.name, skipped.reason);
+    }
+    if let Some(warning) = &scan.cap_warning {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(scan.files)
+}
+
+/// Discover message files as [`discover_with`] does, also returning the
+/// supported files that were left out because they can't be read or
+/// aren't text.
+pub fn scan(options: ScanOptions) -> Result<Scan, String> {
+    scan_in(&scan_directory()?, options)
+}
+
+/// [`scan`] of `dir` instead of the scan directory. Stops looking once
+/// `max_files` supported files are found, so files past the cap are never
+/// read.
+pub fn scan_in(dir: &Path, options: ScanOptions) -> Result<Scan, String> {
+    let exe_dir = dir.to_path_buf();
     let mut files: Vec<TextFile> = Vec::new();
+    let mut skipped = Vec::new();
 
-    let entries = fs::read_dir(&exe_dir)
+    let depth = if options.recursive { MAX_SCAN_DEPTH } else { 0 };
+    // One more than the cap tells whether there were more
+    let mut paths = supported_paths(&exe_dir, depth, options.max_files + 1)
         .map_err(|e| format!("Failed to read directory: {}", e))?;
+    let cap_warning = (paths.len() > options.max_files).then(|| {
+        paths.truncate(options.max_files);
+        let warning = format!(
+            "Stopped after {} files in {}; only those are listed (raise max_discovered_files or use a smaller --dir)",
+            options.max_files,
+            exe_dir.display()
+        );
+        log(&warning);
+        warning
+    });
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        
-        if path.is_file() && is_supported_extension(&path) {
-            if let Some(text_file) = TextFile::from_path(path) {
+    for path in paths {
+        match TextFile::load(path) {
+            Ok(Some(mut text_file)) => {
+                text_file.name = relative_name(&exe_dir, &text_file.path);
                 files.push(text_file);
             }
+            Ok(None) => {}
+            Err(mut file) => {
+                file.name = relative_name(&exe_dir, &file.path);
+                log(&format!("Skipped {}: {}", file.name, file.reason));
+                skipped.push(file);
+            }
         }
     }
+    skipped.sort_by_key(|f| f.name.to_lowercase());
 
     if files.is_empty() {
-        return Err(format!(
-            "No .txt or .md files with content found in directory: {}",
-            exe_dir.display()
-        ));
+        let mut message =
+            format!("No {} files with content found in directory: {}", extension_list(&supported_extensions()), exe_dir.display());
+        if let Some(first) = skipped.first() {
+            message.push_str(&format!(" ({} skipped, e.g. {}: {})", skipped.len(), first.name, first.reason));
+        }
+        return Err(message);
     }
 
     // Sort files alphabetically by name for consistent ordering
-    files.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    files.sort_by_key(|f| f.name.to_lowercase());
+    tags::apply(&mut files, &exe_dir);
 
-    Ok(files)
+    Ok(Scan { files, skipped, cap_warning })
 }
 
-/// Get the directory containing the executable.
-fn get_exe_directory() -> Result<PathBuf, String> {
-    let exe_path = env::current_exe()
-        .map_err(|e| format!("Failed to get executable path: {}", e))?;
-    
-    exe_path.parent()
-        .map(|p| p.to_path_buf())
-        .ok_or_else(|| "Failed to get executable directory".to_string())
+/// Supported files in `dir` and `depth` levels of subdirectories, at most
+/// `limit` of them, without duplicates. Files aren't opened.
+pub fn supported_paths(dir: &Path, depth: usize, limit: usize) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    collect_paths(dir, depth, limit, &mut paths)?;
+    Ok(dedup_paths(paths, path_key))
 }
 
-/// Check if a file has a supported extension.
-fn is_supported_extension(path: &PathBuf) -> bool {
+/// Load one file of the scan directory `dir` as a scan would, named by its
+/// path relative to `dir`. Tags aren't applied.
+pub fn load_in(dir: &Path, path: PathBuf) -> Result<Option<TextFile>, SkippedFile> {
+    match TextFile::load(path) {
+        Ok(Some(mut file)) => {
+            file.name = relative_name(dir, &file.path);
+            Ok(Some(file))
+        }
+        Ok(None) => Ok(None),
+        Err(mut skipped) => {
+            skipped.name = relative_name(dir, &skipped.path);
+            Err(skipped)
+        }
+    }
+}
+
+/// Registry id of `warning` in the file at `path` (see [`crate::warnings`]).
+pub fn file_warning_id(path: &Path, warning: &str) -> String {
+    format!("file:{}:{}", path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(), warning)
+}
+
+/// Whether `path` is an editor's temporary, backup or lock file
+/// (`notes.txt~`, `.notes.txt.swp`, `.#notes.txt`, `~$notes.txt`, ...)
+/// rather than a message file.
+pub fn is_temp_file(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    name.ends_with('~')
+        || name.starts_with(".#")
+        || name.starts_with("~$")
+        || name.starts_with(".~lock.")
+        || TEMP_FILE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Collect supported files in `dir`, descending `depth` more levels, until
+/// `paths` holds `limit` of them. Hidden directories (`.git`, ...) and
+/// [`ARCHIVE_DIRECTORY`] folders are skipped; unreadable subdirectories are
+/// logged and skipped.
+fn collect_paths(dir: &Path, depth: usize, limit: usize, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        if paths.len() >= limit {
+            break;
+        }
+        let path = entry.path();
+        if path.is_file() && is_supported_extension(&path) && !is_temp_file(&path) {
+            paths.push(path);
+        } else if depth > 0 && path.is_dir() && !is_skipped_directory(&entry.file_name().to_string_lossy()) {
+            if let Err(e) = collect_paths(&path, depth - 1, limit, paths) {
+                log(&format!("Skipping {}: {}", path.display(), e));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a subdirectory named `name` is left out of scans.
+fn is_skipped_directory(name: &str) -> bool {
+    name.starts_with('.') || name.eq_ignore_ascii_case(ARCHIVE_DIRECTORY)
+}
+
+/// Display name of a discovered file: its path relative to the scan
+/// directory, with `/` separators.
+fn relative_name(scan_dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(scan_dir).unwrap_or(path);
+    let parts: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    parts.join("/")
+}
+
+/// Get the directory scanned for text files: the one chosen at startup
+/// with [`set_scan_directory`], or the executable's directory.
+pub fn scan_directory() -> Result<PathBuf, String> {
+    match chosen_scan_directory() {
+        Some(dir) => Ok(dir.path),
+        None => paths::exe_directory(),
+    }
+}
+
+/// FNV-1a hash of a file's messages, to spot copies without comparing
+/// every pair of files.
+pub fn content_hash(lines: &[Line]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for line in lines {
+        for byte in line.text.bytes().chain([b'\n']) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Groups of files with the same messages, as indices into `files` in list
+/// order; the first of each group is taken as the original. Files with the
+/// same hash are compared in full, so a collision can't group them.
+pub fn duplicate_groups(files: &[TextFile]) -> Vec<Vec<usize>> {
+    let same = |a: &[Line], b: &[Line]| a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.text == y.text);
+    let mut by_hash: HashMap<u64, Vec<Vec<usize>>> = HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        let groups = by_hash.entry(content_hash(&file.lines)).or_default();
+        match groups.iter_mut().find(|group| same(&files[group[0]].lines, &file.lines)) {
+            Some(group) => group.push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+    let mut groups: Vec<Vec<usize>> = by_hash.into_values().flatten().filter(|group| group.len() > 1).collect();
+    groups.sort();
+    groups
+}
+
+/// How the messages of a file changed between two loads.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineDiff {
+    /// Messages only in the new version
+    pub added: usize,
+    /// Messages only in the old version
+    pub removed: usize,
+    /// The first message that differs: zero-based position, old text and
+    /// new text (None where that version has no message there)
+    pub first_change: Option<(usize, Option<String>, Option<String>)>,
+}
+
+impl LineDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0
+    }
+
+    /// e.g. "2 line(s) added, 1 removed".
+    pub fn summary(&self) -> String {
+        format!("{} line(s) added, {} removed", self.added, self.removed)
+    }
+}
+
+/// Compare the texts of two versions of a file's messages line by line.
+///
+/// Unchanged lines at the start and end are skipped, and the rest is
+/// matched with a longest common subsequence. A changed block of more than
+/// [`DIFF_MAX_CELLS`] line pairs counts as entirely removed and added.
+pub fn diff_lines(old: &[Line], new: &[Line]) -> LineDiff {
+    let old: Vec<&str> = old.iter().map(|l| l.text.as_str()).collect();
+    let new: Vec<&str> = new.iter().map(|l| l.text.as_str()).collect();
+    let (prefix, suffix) = common_ends(&old, &new);
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    if old_mid.is_empty() && new_mid.is_empty() {
+        return LineDiff::default();
+    }
+
+    let common = if old_mid.len().saturating_mul(new_mid.len()) > DIFF_MAX_CELLS {
+        0
+    } else {
+        // LCS length, one row at a time
+        let mut row = vec![0usize; new_mid.len() + 1];
+        for a in old_mid {
+            let mut diagonal = 0;
+            for (j, b) in new_mid.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = if a == b { diagonal + 1 } else { above.max(row[j]) };
+                diagonal = above;
+            }
+        }
+        row[new_mid.len()]
+    };
+    LineDiff {
+        added: new_mid.len() - common,
+        removed: old_mid.len() - common,
+        first_change: Some((prefix, old_mid.first().map(|s| s.to_string()), new_mid.first().map(|s| s.to_string()))),
+    }
+}
+
+/// Lengths of the unchanged start and end of `old` and `new`, not
+/// overlapping.
+fn common_ends(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    (prefix, suffix)
+}
+
+/// One row of a side-by-side comparison: the zero-based indexes of the
+/// messages shown on each side, None where that side is blank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiffRow {
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+}
+
+/// Line up two files' messages side by side, matched like
+/// [`diff_lines`]. Between matching messages, the removed and added ones
+/// share rows, so a changed message sits next to its replacement.
+pub fn diff_rows(old: &[Line], new: &[Line]) -> Vec<DiffRow> {
+    let old: Vec<&str> = old.iter().map(|l| l.text.as_str()).collect();
+    let new: Vec<&str> = new.iter().map(|l| l.text.as_str()).collect();
+    let (prefix, suffix) = common_ends(&old, &new);
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // Matched (old, new) pairs of the changed block, in order
+    let mut matches = Vec::new();
+    if old_mid.len().saturating_mul(new_mid.len()) <= DIFF_MAX_CELLS {
+        // common[i][j]: LCS length of old_mid[i..] and new_mid[j..]
+        let width = new_mid.len() + 1;
+        let mut common = vec![0usize; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                common[i * width + j] = if old_mid[i] == new_mid[j] {
+                    common[(i + 1) * width + j + 1] + 1
+                } else {
+                    common[(i + 1) * width + j].max(common[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() && j < new_mid.len() {
+            if old_mid[i] == new_mid[j] {
+                matches.push((i, j));
+                (i, j) = (i + 1, j + 1);
+            } else if common[(i + 1) * width + j] >= common[i * width + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    matches.push((old_mid.len(), new_mid.len()));
+
+    let mut rows: Vec<DiffRow> = (0..prefix).map(|i| DiffRow { old: Some(i), new: Some(i) }).collect();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in matches {
+        // The unmatched messages before this match, paired up
+        for k in 0..(next_i - i).max(next_j - j) {
+            rows.push(DiffRow {
+                old: (i + k < next_i).then_some(prefix + i + k),
+                new: (j + k < next_j).then_some(prefix + j + k),
+            });
+        }
+        if next_i < old_mid.len() {
+            rows.push(DiffRow { old: Some(prefix + next_i), new: Some(prefix + next_j) });
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    rows.extend((0..suffix).map(|k| DiffRow { old: Some(old_end + k), new: Some(new_end + k) }));
+    rows
+}
+
+/// Key identifying a file for duplicate detection and matching.
+///
+/// The path is canonicalized when possible. On Windows it is also
+/// case-folded, so `Greetings.txt` and `greetings.txt` are the same file;
+/// on Unix paths stay case-sensitive.
+pub fn path_key(path: &Path) -> String {
+    let resolved = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    fold_path(&resolved.to_string_lossy(), cfg!(windows))
+}
+
+/// Normalize a path string; `windows` selects Windows path semantics.
+///
+/// Verbatim prefixes added by canonicalize (`\\?\C:\`, `\\?\UNC\server`)
+/// are stripped so they match the plain forms.
+fn fold_path(path: &str, windows: bool) -> String {
+    if !windows {
+        return path.to_string();
+    }
+    let path = path.replace('/', "\\");
+    let path = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        path
+    };
+    path.to_lowercase()
+}
+
+/// Drop paths whose key was already seen, keeping the first occurrence.
+fn dedup_paths(paths: Vec<PathBuf>, key: impl Fn(&Path) -> String) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths.into_iter().filter(|p| seen.insert(key(p))).collect()
+}
+
+/// Check if a file has the given extension (case-insensitive).
+pub(crate) fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+        .unwrap_or(false)
+}
+
+/// Extensions set at startup from `extensions` or `--ext`.
+static EXTENSIONS: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Read a list of extensions separated by `;` or `,`, lowercased, plus a
+/// warning per entry that isn't plain letters and digits. An empty list
+/// falls back to [`SUPPORTED_EXTENSIONS`].
+pub fn parse_extensions(text: &str) -> (Vec<String>, Vec<String>) {
+    let mut extensions = Vec::new();
+    let mut warnings = Vec::new();
+    for entry in text.split([';', ',']).map(str::trim).filter(|e| !e.is_empty()) {
+        if !entry.chars().all(|c| c.is_ascii_alphanumeric()) {
+            warnings.push(format!("extension '{}' must be letters and digits only (no dot), ignored", entry));
+            continue;
+        }
+        let extension = entry.to_ascii_lowercase();
+        if !extensions.contains(&extension) {
+            extensions.push(extension);
+        }
+    }
+    if extensions.is_empty() {
+        extensions = SUPPORTED_EXTENSIONS.iter().map(|e| e.to_string()).collect();
+    }
+    (extensions, warnings)
+}
+
+/// Use `extensions` for every scan from now on.
+pub fn set_supported_extensions(extensions: Vec<String>) {
+    log(&format!("Supported extensions: {}", extensions.join(", ")));
+    *EXTENSIONS.lock().unwrap_or_else(|e| e.into_inner()) = Some(extensions);
+}
+
+/// Extensions scanned for: the ones set at startup, else the defaults.
+pub fn supported_extensions() -> Vec<String> {
+    EXTENSIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| SUPPORTED_EXTENSIONS.iter().map(|e| e.to_string()).collect())
+}
+
+/// `extensions` for a message, e.g. ".txt, .md or .csv".
+fn extension_list(extensions: &[String]) -> String {
+    let dotted: Vec<String> = extensions.iter().map(|e| format!(".{}", e)).collect();
+    match dotted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => dotted.join(""),
+    }
+}
+
+/// Check if a file has one of `extensions` (lowercase).
+fn has_any_extension(path: &Path, extensions: &[String]) -> bool {
     path.extension()
         .map(|ext| {
             let ext_lower = ext.to_string_lossy().to_lowercase();
-            SUPPORTED_EXTENSIONS.contains(&ext_lower.as_str())
+            extensions.contains(&ext_lower)
         })
         .unwrap_or(false)
 }
 
+/// Check if a file has a supported extension.
+pub(crate) fn is_supported_extension(path: &Path) -> bool {
+    has_any_extension(path, &supported_extensions())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,6 +1184,323 @@ mod tests {
         assert!(is_supported_extension(&PathBuf::from("test.TXT")));
         assert!(is_supported_extension(&PathBuf::from("test.MD")));
         assert!(!is_supported_extension(&PathBuf::from("test.rs")));
+        assert!(is_supported_extension(&PathBuf::from("test.csv")));
         assert!(!is_supported_extension(&PathBuf::from("test")));
+
+        let (extensions, warnings) = parse_extensions("txt; MSG,log;.cfg; txt");
+        assert_eq!(extensions, vec!["txt", "msg", "log"]);
+        assert_eq!(warnings, vec!["extension '.cfg' must be letters and digits only (no dot), ignored"]);
+        assert!(has_any_extension(&PathBuf::from("chat.MSG"), &extensions));
+        assert!(has_any_extension(&PathBuf::from("chat.log"), &extensions));
+        assert!(!has_any_extension(&PathBuf::from("chat.md"), &extensions));
+        assert!(!has_any_extension(&PathBuf::from("chat.cfg"), &extensions));
+        assert_eq!(parse_extensions(" ; ").0, vec!["txt", "md", "csv"]);
+        assert_eq!(extension_list(&parse_extensions(" ; ").0), ".txt, .md or .csv");
+        assert_eq!(extension_list(&extensions), ".txt, .msg or .log");
+        assert_eq!(extension_list(&["log".to_string()]), ".log");
+    }
+
+    #[test]
+    fn test_warning_line() {
+        let mut file = TextFile::with_lines("a.txt", &["gl", "hf", "gg"]);
+        assert_eq!(file.warning_line(), None);
+        for (warning, line) in [
+            ("line 2: '9:99' is not a MM:SS time, sent as is", Some(1)),
+            ("line(s) 3, 4 truncated to 4096 characters", Some(2)),
+            ("line 40: !at 1:00 has no message, ignored", Some(2)),
+            ("front-matter line 2: expected `key: value`, ignored", None),
+            ("non-numeric delay ignored on row(s) 2", None),
+        ] {
+            file.warnings = vec![warning.to_string()];
+            assert_eq!(file.warning_line(), line, "{}", warning);
+        }
+    }
+
+    #[test]
+    fn test_front_matter_window_title_overrides_targets() {
+        let mut file = TextFile::with_lines("server.txt", &["hello"]);
+        let session = vec!["League of Legends (TM) Client".to_string()];
+        assert_eq!(file.targets(&session), session);
+
+        file.front.window_title = Some("Private Server".to_string());
+        assert_eq!(file.targets(&session), vec!["Private Server"]);
+    }
+
+    #[test]
+    fn test_relative_names() {
+        let dir = Path::new("games");
+        assert_eq!(relative_name(dir, &dir.join("gg.txt")), "gg.txt");
+        assert_eq!(relative_name(dir, &dir.join("aram").join("ez.md")), "aram/ez.md");
+    }
+
+    #[test]
+    fn test_csv_record_quoted_fields() {
+        assert_eq!(parse_csv_record("500,hello"), vec!["500", "hello"]);
+        assert_eq!(parse_csv_record(r#"0,"gl, hf""#), vec!["0", "gl, hf"]);
+        assert_eq!(parse_csv_record(r#","say ""hi""""#), vec!["", r#"say "hi""#]);
+    }
+
+    #[test]
+    fn test_csv_delays_and_header() {
+        let (lines, warnings) = parse_csv("delay,message\n1000,first\n,second\n\n250,\"third, with comma\"\n");
+        assert!(warnings.is_empty());
+        assert_eq!(lines, vec![
+            Line { delay_ms: Some(1000), source: Some(1), ..Line::new("first") },
+            Line { source: Some(2), ..Line::new("second") },
+            Line { delay_ms: Some(250), source: Some(4), ..Line::new("third, with comma") },
+        ]);
+    }
+
+    #[test]
+    fn test_blank_lines_counted_as_pauses() {
+        let lines = parse_plain("\n\ngl hf\n\nwp\n  \n\t\n\nff?\nez\n\n\n");
+        let blanks: Vec<(&str, usize)> = lines.iter().map(|l| (l.text.as_str(), l.blank_before)).collect();
+        assert_eq!(blanks, vec![("gl hf", 0), ("wp", 1), ("ff?", 3), ("ez", 0)]);
+    }
+
+    #[test]
+    fn test_long_lines_capped_and_flagged() {
+        let mut lines = parse_plain(&format!("short\n{}\n{}éz\n", "x".repeat(10), "y".repeat(7)));
+        let warning = cap_lines(&mut lines, 8);
+        assert_eq!(warning.as_deref(), Some("line(s) 2, 3 truncated to 8 characters"));
+        assert_eq!(lines.iter().map(|l| l.truncated).collect::<Vec<_>>(), vec![false, true, true]);
+        assert_eq!(lines[1].text, "x".repeat(8));
+        assert_eq!(lines[2].text, format!("{}é", "y".repeat(7)));
+        assert_eq!(cap_lines(&mut lines, 8), None);
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let contents = "# Pack\n\n| Message | Channel | Delay |\n|---|:---:|---|\n| gl hf | all | |\n| \\| top \\| | team | 500 |\n| only two | team |\n| ward | guild | soon |\n\nNotes below\n| not | part |\n";
+        let (lines, warnings) = parse_markdown_table(contents).unwrap();
+        assert_eq!(lines, vec![
+            Line { chat: Some(ChatScope::All), source: Some(4), ..Line::new("gl hf") },
+            Line { chat: Some(ChatScope::Team), delay_ms: Some(500), source: Some(5), ..Line::new("| top |") },
+            Line { source: Some(7), ..Line::new("ward") },
+        ]);
+        assert_eq!(warnings, vec![
+            "malformed table row(s) 7 skipped",
+            "unknown channel ignored on row(s) 8",
+            "non-numeric delay ignored on row(s) 8",
+        ]);
+
+        assert!(parse_markdown_table("just\n| a | b |\nlines\n").is_none());
+    }
+
+    #[test]
+    fn test_csv_bad_delay_warning() {
+        let (lines, warnings) = parse_csv("100,ok\nsoon,not ok\n5,fine\nlater,also not ok\n");
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1].delay_ms, None);
+        assert_eq!(warnings, vec!["non-numeric delay ignored on row(s) 2, 4"]);
+    }
+
+    #[test]
+    fn test_weight_columns() {
+        let (lines, warnings) = parse_csv("delay,message,weight\n0,gl, hf,3\n,ff,0\n,wp,\n,bad,x\n");
+        let weights: Vec<(&str, u32)> = lines.iter().map(|l| (l.text.as_str(), l.weight)).collect();
+        assert_eq!(weights, vec![("gl, hf", 3), ("ff", 0), ("wp", 1), ("bad", 1)]);
+        assert_eq!(
+            warnings,
+            vec!["non-numeric weight ignored on row(s) 5", "row(s) 3 left out of random picks (weight 0 or less)"]
+        );
+        // Without the header, a third field is part of the message
+        assert_eq!(parse_csv("0,gl,3\n").0[0].text, "gl,3");
+
+        let (lines, warnings) = parse_markdown_table("| message | weight |\n|---|---|\n| gg | 4 |\n| ff | -1 |\n").unwrap();
+        assert_eq!(lines.iter().map(|l| l.weight).collect::<Vec<_>>(), vec![4, 0]);
+        assert_eq!(warnings, vec!["row(s) 4 left out of random picks (weight 0 or less)"]);
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let lines = |texts: &[&str]| texts.iter().map(|t| Line::new(*t)).collect::<Vec<_>>();
+        let old = lines(&["gl hf", "ward drake", "back", "wp"]);
+        assert!(diff_lines(&old, &old).is_empty());
+
+        let diff = diff_lines(&old, &lines(&["gl hf", "ward baron", "back", "gg", "wp"]));
+        assert_eq!((diff.added, diff.removed), (2, 1));
+        assert_eq!(diff.first_change, Some((1, Some("ward drake".to_string()), Some("ward baron".to_string()))));
+        assert_eq!(diff.summary(), "2 line(s) added, 1 removed");
+
+        let diff = diff_lines(&old, &lines(&["gl hf", "ward drake"]));
+        assert_eq!((diff.added, diff.removed), (0, 2));
+        assert_eq!(diff.first_change, Some((2, Some("back".to_string()), None)));
+
+        // Too big to match line by line: the changed block counts whole
+        let big_old: Vec<Line> = (0..600).map(|i| Line::new(format!("old {}", i))).collect();
+        let mut big_new: Vec<Line> = (0..600).map(|i| Line::new(format!("new {}", i))).collect();
+        big_new[300] = Line::new("old 300");
+        let diff = diff_lines(&big_old, &big_new);
+        assert_eq!((diff.added, diff.removed), (600, 600));
+    }
+
+    #[test]
+    fn test_diff_rows_side_by_side() {
+        let lines = |texts: &[&str]| texts.iter().map(|t| Line::new(*t)).collect::<Vec<_>>();
+        let row = |old: Option<usize>, new: Option<usize>| DiffRow { old, new };
+        let old = lines(&["gl hf", "ward drake", "back", "wp"]);
+        let new = lines(&["gl hf", "ward baron", "back", "gg", "wp"]);
+        assert_eq!(diff_rows(&old, &new), vec![
+            row(Some(0), Some(0)),
+            row(Some(1), Some(1)),
+            row(Some(2), Some(2)),
+            row(None, Some(3)),
+            row(Some(3), Some(4)),
+        ]);
+
+        // Very different lengths, either way round, and empty files
+        let long = lines(&["a", "b", "c", "d", "e", "f", "g"]);
+        let short = lines(&["x", "d"]);
+        assert_eq!(diff_rows(&short, &long), vec![
+            row(Some(0), Some(0)),
+            row(None, Some(1)),
+            row(None, Some(2)),
+            row(Some(1), Some(3)),
+            row(None, Some(4)),
+            row(None, Some(5)),
+            row(None, Some(6)),
+        ]);
+        assert_eq!(diff_rows(&long, &short).len(), 7);
+        assert_eq!(diff_rows(&[], &short), vec![row(None, Some(0)), row(None, Some(1))]);
+        assert!(diff_rows(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_mixed_case_duplicates_on_windows() {
+        let paths = vec![
+            PathBuf::from(r"C:\Tools\Greetings.txt"),
+            PathBuf::from(r"c:/tools/greetings.TXT"),
+            PathBuf::from(r"\\?\C:\TOOLS\GREETINGS.txt"),
+            PathBuf::from(r"C:\Tools\other.txt"),
+        ];
+        let kept = dedup_paths(paths, |p| fold_path(&p.to_string_lossy(), true));
+        assert_eq!(kept, vec![PathBuf::from(r"C:\Tools\Greetings.txt"), PathBuf::from(r"C:\Tools\other.txt")]);
+    }
+
+    #[test]
+    fn test_unc_paths_on_windows() {
+        assert_eq!(
+            fold_path(r"\\?\UNC\Server\Share\GG.txt", true),
+            fold_path(r"\\server\share\gg.txt", true)
+        );
+        assert_ne!(fold_path(r"\\server\share\gg.txt", true), fold_path(r"\\server\other\gg.txt", true));
+    }
+
+    #[test]
+    fn test_unix_paths_stay_case_sensitive() {
+        let paths = vec![PathBuf::from("/home/me/Greetings.txt"), PathBuf::from("/home/me/greetings.txt")];
+        assert_eq!(dedup_paths(paths.clone(), |p| fold_path(&p.to_string_lossy(), false)), paths);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(b"gl hf\r\n\tgg wp\n"));
+        assert!(!is_binary("ça va? 😀\n".as_bytes()));
+        assert!(!is_binary(b""));
+        // A few stray escapes in otherwise normal text are fine
+        assert!(!is_binary(b"\x1b[1mgg\x1b[0m wp, well played everyone"));
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n"));
+        assert!(is_binary(b"gg\0wp"));
+        assert!(is_binary(b"\x01\x02\x03gg wp\x04"));
+        assert!(is_binary(&[0xff, 0xfe, b'g', 0, b'g', 0]));
+    }
+
+    #[test]
+    fn test_binary_file_skipped_with_reason() {
+        let dir = std::env::temp_dir().join(format!("madtyping-binary-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("renamed.txt");
+        fs::write(&path, b"PK\x03\x04\x14\0\x08\0").unwrap();
+        let skipped = TextFile::load(path.clone()).unwrap_err();
+        assert_eq!((skipped.name.as_str(), skipped.reason.as_str()), ("renamed.txt", BINARY_SKIP_REASON));
+        assert!(TextFile::from_path(path.clone()).is_none());
+        fs::write(&path, "gg").unwrap();
+        assert_eq!(TextFile::load(path).unwrap().unwrap().lines.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scan_stops_at_the_file_cap() {
+        let dir = std::env::temp_dir().join(format!("madtyping-cap-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for i in 0..3000 {
+            let folder = if i % 2 == 0 { dir.clone() } else { dir.join("sub") };
+            fs::write(folder.join(format!("{}.txt", i)), "gg wp").unwrap();
+        }
+        let options = ScanOptions { recursive: true, max_files: 200 };
+
+        let started = std::time::Instant::now();
+        let scan = scan_in(&dir, options).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5), "capped scan took {:?}", started.elapsed());
+        assert_eq!(scan.files.len(), 200);
+        let warning = scan.cap_warning.unwrap();
+        assert!(warning.contains("200 files") && warning.contains(&dir.display().to_string()), "{}", warning);
+
+        let all = scan_in(&dir, ScanOptions { max_files: 3000, ..options }).unwrap();
+        assert_eq!((all.files.len(), all.cap_warning), (3000, None));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_editor_temp_files_not_listed() {
+        for name in ["notes.txt~", ".notes.txt.swp", ".#notes.txt", "~$notes.txt", ".~lock.notes.txt#", "notes.txt.bak", "x.TMP"] {
+            assert!(is_temp_file(Path::new(name)), "{}", name);
+        }
+        assert!(!is_temp_file(Path::new("notes.txt")) && !is_temp_file(Path::new("tmp.txt")));
+
+        let dir = std::env::temp_dir().join(format!("madtyping-temp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["notes.txt", ".#notes.txt", "~$notes.txt"] {
+            fs::write(dir.join(name), "gg").unwrap();
+        }
+        let names: Vec<String> = scan_in(&dir, ScanOptions::default()).unwrap().files.into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["notes.txt"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_append_line() {
+        let dir = std::env::temp_dir().join(format!("madtyping-append-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = append_line(&dir, "scratch.txt", "gl hf").unwrap();
+        fs::write(&path, "gl hf\r\nwp").unwrap();
+        append_line(&dir, "scratch.txt", "ff at 15").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "gl hf\r\nwp\nff at 15\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scan_directory_precedence() {
+        let root = std::env::temp_dir().join(format!("madtyping-scan-{}", std::process::id()));
+        let (explicit, config, exe, user) = (root.join("dir"), root.join("config"), root.join("exe"), root.join("user"));
+        for dir in [&explicit, &config, &exe] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        let missing = root.join("missing");
+        let choose = |explicit: Option<&Path>, config_dirs: &[PathBuf], skip_exe| {
+            choose_scan_directory(explicit, config_dirs, &exe, Some(&user), skip_exe).map(|d| (d.path, d.source))
+        };
+
+        assert_eq!(choose(Some(&explicit), std::slice::from_ref(&config), false), Ok((explicit.clone(), ScanSource::Explicit)));
+        assert!(choose(Some(&missing), &[], false).is_err());
+        assert_eq!(choose(None, &[missing.clone(), config.clone()], false), Ok((config.clone(), ScanSource::Config)));
+
+        // Without message files or a user directory, the exe directory is the last resort
+        assert_eq!(choose(None, std::slice::from_ref(&missing), false), Ok((exe.clone(), ScanSource::Exe)));
+        fs::create_dir_all(&user).unwrap();
+        assert_eq!(choose(None, &[], false), Ok((user.clone(), ScanSource::User)));
+        fs::write(exe.join("gg.txt"), "gg wp").unwrap();
+        assert_eq!(choose(None, &[], false), Ok((exe.clone(), ScanSource::Exe)));
+
+        // Once copied over, the user directory wins
+        fs::create_dir_all(exe.join("aram")).unwrap();
+        fs::write(exe.join("aram").join("hi.md"), "hi").unwrap();
+        assert_eq!(copy_message_files(&exe, &user), Ok(2));
+        assert_eq!(copy_message_files(&exe, &user), Ok(0));
+        assert!(user.join("aram").join("hi.md").is_file());
+        assert_eq!(choose(None, &[], true), Ok((user.clone(), ScanSource::User)));
+
+        let _ = fs::remove_dir_all(&root);
     }
 }
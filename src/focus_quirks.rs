@@ -0,0 +1,195 @@
+//! Learned focus delays for MadTyping
+//!
+//! Some games take longer than `FOCUS_DELAY_MS` to take keyboard input
+//! after being brought to the front. When a window isn't focused once the
+//! delay is over, the send activates it again and waits, and the line
+//! reports how much longer it took ([`FocusCheck::Late`]). After the send,
+//! [`learn`] turns each window's checks into an extra delay for that title:
+//! raised by the time that was missing, capped at
+//! `MAX_LEARNED_FOCUS_DELAY_MS`, and decaying by
+//! `LEARNED_FOCUS_DECAY_PERCENT` after every send that was focused in time,
+//! so a one-off hiccup doesn't slow every later send down. Learned delays
+//! are kept in the state file and added before every line sent to that
+//! title. The settings screen lists them and can forget them.
+
+use std::collections::BTreeMap;
+
+use crate::config::{LEARNED_FOCUS_DECAY_PERCENT, MAX_LEARNED_FOCUS_DELAY_MS, MIN_LEARNED_FOCUS_DELAY_MS};
+use crate::logging::log;
+use crate::persistence::{self, State};
+use crate::platform::FocusCheck;
+use crate::send::SendReport;
+
+/// State key of the learned delays, a [`State::set_list`] list of
+/// `ms:title` entries.
+const STATE_KEY: &str = "focus_delays.windows";
+
+/// The extra focus delay for a window after a send with `checks`, starting
+/// from `current_ms`. A late focus adds the time that was missing (rounded
+/// up to 10 ms); a send focused in time every line lets it decay; misses
+/// alone say nothing about how long would have been enough.
+pub fn learn(current_ms: u64, checks: &[FocusCheck]) -> u64 {
+    let missing = checks
+        .iter()
+        .filter_map(|check| match check {
+            FocusCheck::Late { extra_ms } => Some(*extra_ms),
+            _ => None,
+        })
+        .max();
+    if let Some(extra_ms) = missing {
+        return (current_ms + extra_ms.div_ceil(10) * 10).min(MAX_LEARNED_FOCUS_DELAY_MS);
+    }
+    if checks.is_empty() || !checks.iter().all(|c| *c == FocusCheck::Focused) {
+        return current_ms;
+    }
+    let decayed = current_ms.saturating_sub((current_ms * LEARNED_FOCUS_DECAY_PERCENT / 100).max(1));
+    if decayed < MIN_LEARNED_FOCUS_DELAY_MS {
+        0
+    } else {
+        decayed
+    }
+}
+
+/// Learned delays per window title.
+pub fn learned_delays(state: &State) -> BTreeMap<String, u64> {
+    state
+        .get_list(STATE_KEY)
+        .iter()
+        .filter_map(|entry| entry.split_once(':'))
+        .filter_map(|(ms, title)| Some((title.to_string(), ms.parse().ok()?)))
+        .filter(|(title, ms): &(String, u64)| !title.is_empty() && *ms > 0)
+        .collect()
+}
+
+fn set_learned_delays(state: &mut State, delays: &BTreeMap<String, u64>) {
+    state.set_list(STATE_KEY, delays.iter().map(|(title, ms)| format!("{}:{}", ms, title)));
+}
+
+/// Learn from the focus checks of every window of `report`. Returns
+/// whether any delay changed.
+pub fn remember(state: &mut State, report: &SendReport) -> bool {
+    let mut delays = learned_delays(state);
+    let before = delays.clone();
+    for target in &report.targets {
+        let current = delays.get(&target.window_title).copied().unwrap_or(0);
+        let learned = learn(current, &target.focus_checks);
+        if learned == current {
+            continue;
+        }
+        log(&format!("Learned focus delay of '{}': {} ms -> {} ms", target.window_title, current, learned));
+        if learned == 0 {
+            delays.remove(&target.window_title);
+        } else {
+            delays.insert(target.window_title.clone(), learned);
+        }
+    }
+    if delays == before {
+        return false;
+    }
+    set_learned_delays(state, &delays);
+    true
+}
+
+/// Learned delays in the state file.
+pub fn load() -> BTreeMap<String, u64> {
+    learned_delays(&persistence::load())
+}
+
+/// Learn from the focus checks of `report`.
+pub fn save(report: &SendReport) -> Result<(), String> {
+    let mut state = persistence::load();
+    if remember(&mut state, report) {
+        persistence::save(&state)?;
+    }
+    Ok(())
+}
+
+/// Forget every learned delay. Returns how many there were.
+pub fn forget() -> Result<usize, String> {
+    let mut state = persistence::load();
+    let count = learned_delays(&state).len();
+    if count > 0 {
+        state.set(STATE_KEY, "");
+        persistence::save(&state)?;
+    }
+    Ok(count)
+}
+
+/// One-line list of learned delays, e.g. `Game A +120 ms, Game B +40 ms`.
+pub fn describe(delays: &BTreeMap<String, u64>) -> String {
+    delays.iter().map(|(title, ms)| format!("{} +{} ms", title, ms)).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::send::TargetReport;
+
+    #[test]
+    fn test_learning_is_bounded_and_decays() {
+        let late = |extra_ms| FocusCheck::Late { extra_ms };
+        assert_eq!(learn(0, &[FocusCheck::Focused, late(113), late(40)]), 120);
+        // On top of what was already waited
+        assert_eq!(learn(120, &[late(60)]), 180);
+        assert_eq!(learn(900, &[late(400)]), MAX_LEARNED_FOCUS_DELAY_MS);
+
+        // Focused in time: decays, then goes away
+        assert_eq!(learn(200, &[FocusCheck::Focused, FocusCheck::Focused]), 180);
+        assert_eq!(learn(10, &[FocusCheck::Focused]), 0);
+        let mut delay = MAX_LEARNED_FOCUS_DELAY_MS;
+        let mut sends = 0;
+        while delay > 0 {
+            delay = learn(delay, &[FocusCheck::Focused]);
+            sends += 1;
+        }
+        assert!((20..100).contains(&sends), "{} sends", sends);
+
+        // Nothing checked, or only misses: unchanged
+        assert_eq!(learn(200, &[]), 200);
+        assert_eq!(learn(200, &[FocusCheck::Missed, FocusCheck::Focused]), 200);
+    }
+
+    #[test]
+    fn test_delays_remembered_per_window() {
+        let target = |title: &str, checks: Vec<FocusCheck>| TargetReport {
+            window_title: title.to_string(),
+            focus_checks: checks,
+            ..TargetReport::default()
+        };
+        let report = SendReport {
+            targets: vec![
+                target("Game: A", vec![FocusCheck::Late { extra_ms: 150 }]),
+                target("Game B", vec![FocusCheck::Focused]),
+            ],
+            ..SendReport::default()
+        };
+        let mut state = State::default();
+        assert!(remember(&mut state, &report));
+        assert_eq!(learned_delays(&state), BTreeMap::from([("Game: A".to_string(), 150)]));
+        assert_eq!(describe(&learned_delays(&state)), "Game: A +150 ms");
+
+        let report = SendReport { targets: vec![target("Game: A", vec![FocusCheck::Focused])], ..SendReport::default() };
+        assert!(remember(&mut state, &report));
+        assert_eq!(learned_delays(&state).get("Game: A"), Some(&135));
+        assert!(!remember(&mut state, &SendReport::default()));
+    }
+
+    #[test]
+    fn test_titles_with_separators_survive_a_reload() {
+        let late = vec![FocusCheck::Late { extra_ms: 40 }];
+        let titles = ["Lobby; EU West", "C:\\Games\\Client", "Plain"];
+        let report = SendReport {
+            targets: titles
+                .iter()
+                .map(|title| TargetReport { window_title: title.to_string(), focus_checks: late.clone(), ..TargetReport::default() })
+                .collect(),
+            ..SendReport::default()
+        };
+        let mut state = State::default();
+        remember(&mut state, &report);
+        let reloaded = State::parse(&state.serialize());
+        let delays = learned_delays(&reloaded);
+        assert_eq!(delays.keys().map(String::as_str).collect::<Vec<_>>(), vec!["C:\\Games\\Client", "Lobby; EU West", "Plain"]);
+        assert!(delays.values().all(|&ms| ms == 40));
+    }
+}
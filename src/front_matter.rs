@@ -0,0 +1,136 @@
+//! Per-file settings for MadTyping
+//!
+//! A message file may start with a front-matter block that changes how that
+//! one file is sent:
+//!
+//! ```text
+//! ---
+//! window_title: "My Private Server"
+//! ---
+//! first message
+//! ```
+//!
+//! Keys are followed by `:` or `=`, and values may be quoted. Unknown keys
+//! and malformed entries are skipped with a warning, like the config file.
+//! A block without any known key isn't front matter but messages, such as
+//! `---` separator lines in a plain text file, and is sent as is.
+
+/// Line that opens and closes a front-matter block.
+const DELIMITER: &str = "---";
+
+/// Settings read from a file's front-matter block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrontMatter {
+    /// Target window for this file, replacing the session's targets
+    pub window_title: Option<String>,
+    /// Move the file to the archive after a full send (see
+    /// [`crate::archive`]); None leaves it to the config's glob
+    pub archive_after_send: Option<bool>,
+}
+
+/// Split a leading front-matter block off `contents`.
+///
+/// Returns the parsed settings, the remaining contents and any warnings.
+/// Contents without a block (or with an unclosed one, or one with no known
+/// key) are returned whole.
+pub fn split(contents: &str) -> (FrontMatter, &str, Vec<String>) {
+    let mut front = FrontMatter::default();
+    let mut warnings = Vec::new();
+
+    let start = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+    let Some((first, after)) = start.split_once('\n') else {
+        return (front, contents, warnings);
+    };
+    if first.trim() != DELIMITER {
+        return (front, contents, warnings);
+    }
+    let Some((block, body)) = find_closing(after) else {
+        return (front, contents, warnings);
+    };
+
+    let mut recognised = false;
+    for (number, raw) in block.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once([':', '=']) else {
+            warnings.push(format!("front-matter line {}: expected `key: value`, ignored", number + 1));
+            continue;
+        };
+        let key = key.trim();
+        let value = unquote(value.trim());
+        recognised |= matches!(key, "window_title" | "archive_after_send");
+        match key {
+            "window_title" if !value.is_empty() => front.window_title = Some(value.to_string()),
+            "window_title" => warnings.push("front-matter window_title is empty, ignored".to_string()),
+            "archive_after_send" => match value {
+                "true" => front.archive_after_send = Some(true),
+                "false" => front.archive_after_send = Some(false),
+                _ => warnings.push(format!("front-matter archive_after_send = {} is not true or false, ignored", value)),
+            },
+            _ => warnings.push(format!("unknown front-matter key '{}', ignored", key)),
+        }
+    }
+
+    if !recognised {
+        return (FrontMatter::default(), contents, Vec::new());
+    }
+    (front, body, warnings)
+}
+
+/// Find the closing delimiter line, returning (block, remaining contents).
+fn find_closing(contents: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        if line.trim() == DELIMITER {
+            return Some((&contents[..offset], &contents[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Strip one pair of matching quotes, if present.
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_title_split_off() {
+        let (front, body, warnings) = split("---\nwindow_title: \"Private Server\"\ncolor: red\n---\nhello\n");
+        assert_eq!(front.window_title.as_deref(), Some("Private Server"));
+        assert_eq!(body, "hello\n");
+        assert_eq!(warnings, vec!["unknown front-matter key 'color', ignored"]);
+    }
+
+    #[test]
+    fn test_archive_flag() {
+        let (front, _, warnings) = split("---\narchive_after_send = true\n---\nhello\n");
+        assert_eq!(front.archive_after_send, Some(true));
+        assert!(warnings.is_empty());
+        let (front, _, warnings) = split("---\narchive_after_send: sure\n---\nhello\n");
+        assert_eq!(front.archive_after_send, None);
+        assert_eq!(warnings, vec!["front-matter archive_after_send = sure is not true or false, ignored"]);
+    }
+
+    #[test]
+    fn test_no_or_unclosed_block_kept() {
+        let contents = "---\nwindow_title: X\nno closing line\n";
+        assert_eq!(split(contents), (FrontMatter::default(), contents, Vec::new()));
+        assert_eq!(split("hello\n---\n").1, "hello\n---\n");
+    }
+
+    #[test]
+    fn test_block_without_known_keys_kept() {
+        let contents = "---\ngl hf\nmid: 2 min\n---\nwp\n";
+        assert_eq!(split(contents), (FrontMatter::default(), contents, Vec::new()));
+    }
+}
@@ -0,0 +1,496 @@
+//! Headless modes for MadTyping
+//!
+//! `--list`, `--send FILE`, `--replay FILE`, `--import ZIP` and `--export`
+//! work without the terminal UI, for launchers and scripts that don't provide an interactive
+//! terminal. Output is plain text, one line per event (`--progress` makes
+//! `--send` quieter); `--json-result` adds a JSON summary of the send on
+//! stdout for wrapper scripts.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::abbreviations::Abbreviations;
+use crate::archive;
+use crate::charfilter::{self, CharFilter};
+use crate::config::{
+    Config, FILE_SUGGESTIONS, FILE_SUGGESTION_MIN_SCORE, NOTIFY_MIN_SEND_SECS, PERSIST_LIFETIME_STATS, READ_ONLY_ERROR,
+};
+use crate::files::{scan_directory, supported_extensions, TextFile};
+use crate::focus_quirks;
+use crate::hooks;
+use crate::logging::log;
+use crate::outcome::{ErrorKind, SendError, SendOutcome};
+use crate::replay::{self, DryRunSender, Step, StepOutcome};
+use crate::report;
+use crate::pack::{self, default_subdirectory, format_size, glob_match, Collision, ImportReport};
+use crate::paste;
+use crate::platform::{
+    check_keyboard_state, game_guard, is_window_minimized, is_window_running, notify, play_cue, restore_keyboard_state, KeyMode,
+};
+use crate::progress::{Progress, ProgressMode};
+use crate::send::{
+    finish_notice, line_cap_problem, message_count, send_cue, BusyPolicy, Control, LineSender, PlatformSender, SendChoices, SendDelays, SendReport,
+    SendSession,
+};
+use crate::stats::Stats;
+use crate::timed::{parse_clock, GameClock};
+use crate::titles::{closest, not_running_error};
+
+/// Exit code of `--send` when the file is over `max_lines_per_send`, so
+/// scripts can tell it from other failures (1).
+pub const EXIT_TOO_MANY_LINES: i32 = 3;
+
+/// Print the extensions scanned for, then every discovered file with its
+/// line count.
+pub fn list(files: &[TextFile]) {
+    println!("Extensions: {}", supported_extensions().join(", "));
+    let width = files.iter().map(|f| f.name.chars().count()).max().unwrap_or(0);
+    for file in files {
+        let mut row = format!("{:<width$}  {:>4} line(s)", file.name, file.lines.len(), width = width);
+        if !file.warnings.is_empty() {
+            row.push_str(&format!("  ({} warning(s))", file.warnings.len()));
+        }
+        println!("{}", row);
+    }
+}
+
+/// Find a file by name, case-insensitively, with or without its extension.
+/// Files in subdirectories go by their relative path (`aram/ez.txt`, or
+/// `aram\ez` as typed on Windows) or their bare name.
+pub fn find_file<'a>(files: &'a [TextFile], name: &str) -> Option<&'a TextFile> {
+    let name = name.replace('\\', "/").to_lowercase();
+    let without_extension = |n: &str| n.rsplit_once('.').map_or(n.to_string(), |(stem, _)| stem.to_string());
+    files
+        .iter()
+        .find(|f| f.name.to_lowercase() == name)
+        .or_else(|| files.iter().find(|f| without_extension(&f.name.to_lowercase()) == name))
+        .or_else(|| {
+            files.iter().find(|f| {
+                f.path
+                    .file_stem()
+                    .is_some_and(|stem| stem.to_string_lossy().to_lowercase() == name)
+            })
+        })
+}
+
+/// Error for a file name that matches no file, listing the closest names.
+pub fn file_not_found(files: &[TextFile], name: &str) -> String {
+    let names: Vec<String> = files.iter().map(|f| f.name.clone()).collect();
+    match closest(name, &names, FILE_SUGGESTIONS, FILE_SUGGESTION_MIN_SCORE).as_slice() {
+        [] => format!("No message file named '{}' (see --list)", name),
+        similar => format!("No message file named '{}'; closest: '{}'", name, similar.join("', '")),
+    }
+}
+
+/// Send a file to the target windows, printing one line per sent line.
+///
+/// With `json`, progress goes to stderr and a [`SendOutcome`] JSON object
+/// is printed to stdout once the send is over, whatever happened. `mode`
+/// decides how much progress is printed along the way.
+///
+/// Returns an error if the file or windows can't be found, if read-only mode
+/// is on, if the file is over `max_lines_per_send`, or if any line failed
+/// to send.
+pub fn send(
+    files: &[TextFile],
+    name: &str,
+    window_titles: &[String],
+    config: &Config,
+    choices: SendChoices,
+    json: bool,
+    mode: ProgressMode,
+) -> Result<SendReport, SendError> {
+    let started = Instant::now();
+    let mut outcome = SendOutcome::new(name);
+    let mut printer = progress_printer(mode, json);
+    let result = match send_file(files, name, window_titles, config, choices, &mut printer, &mut outcome) {
+        Ok((report, total)) if report.failed() > 0 => {
+            let lost = report.targets.iter().filter(|t| t.window_lost).count();
+            let error = if lost > 0 {
+                SendError::new(ErrorKind::WindowLost, format!("{} target window(s) closed mid-send and didn't come back", lost))
+            } else {
+                SendError::new(ErrorKind::LineFailed, format!("{} target window(s) stopped after a failed line", report.failed()))
+            };
+            outcome.finish(Some(&report), total, started.elapsed(), Some(error.clone()));
+            Err(error)
+        }
+        Ok((report, total)) => {
+            outcome.finish(Some(&report), total, started.elapsed(), None);
+            Ok(report)
+        }
+        Err(error) => {
+            outcome.finish(None, 0, started.elapsed(), Some(error.clone()));
+            Err(error)
+        }
+    };
+    if json {
+        println!("{}", outcome.to_json());
+    }
+    result
+}
+
+/// Where `--send` progress goes in `mode`: stdout, or stderr when stdout
+/// carries JSON. Only the `compact` status line goes to stderr when it is
+/// a terminal.
+fn progress_printer(mode: ProgressMode, json: bool) -> Progress<Box<dyn Write>> {
+    let out: Box<dyn Write> = if json { Box::new(io::stderr()) } else { Box::new(io::stdout()) };
+    let live = (mode == ProgressMode::Compact && io::stderr().is_terminal()).then(|| Box::new(io::stderr()) as Box<dyn Write>);
+    Progress::new(mode, out, live)
+}
+
+/// Run the send, recording events into `outcome`. Returns the report and
+/// the file's line count.
+fn send_file(
+    files: &[TextFile],
+    name: &str,
+    window_titles: &[String],
+    config: &Config,
+    choices: SendChoices,
+    printer: &mut Progress<Box<dyn Write>>,
+    outcome: &mut SendOutcome,
+) -> Result<(SendReport, usize), SendError> {
+    if config.read_only {
+        return Err(SendError::new(ErrorKind::ReadOnly, format!("Can't send '{}': {}", name, READ_ONLY_ERROR)));
+    }
+    let file = find_file(files, name).ok_or_else(|| SendError::new(ErrorKind::FileNotFound, file_not_found(files, name)))?;
+    if let Some(problem) = line_cap_problem(message_count(&file.lines, config.split_long_lines, config.char_limit()), 0, config.max_lines_per_send) {
+        return Err(SendError::new(ErrorKind::TooManyLines, format!("Can't send '{}': {}", file.name, problem)));
+    }
+    let targets = file.targets(window_titles);
+    // A dry run types into a mock keyboard, so the game needn't be open
+    if !choices.dry_run && !targets.iter().any(|t| is_window_running(t)) {
+        return Err(SendError::new(ErrorKind::NotRunning, not_running_error(&targets)));
+    }
+    if let Some(problem) = (config.game_guard && !choices.dry_run).then(|| game_guard(&targets)).flatten() {
+        log(&format!("Game guard: {}", problem));
+        if !confirm_send_anyway(printer, &problem) {
+            return Err(SendError::new(ErrorKind::NotInGame, format!("Game guard: {}", problem)));
+        }
+    }
+
+    log(&format!("Headless send of '{}' ({} lines)", file.name, file.lines.len()));
+    let total = file.lines.len();
+    printer.note(&format!("Sending {} ({} lines)...", file.name, total));
+    if choices.dry_run {
+        printer.note("Dry run: nothing is typed into the game.");
+    }
+    if file.front.window_title.is_some() {
+        printer.note(&format!("Target window (set by the file): {}", targets.join(", ")));
+    }
+    // No one to ask here, so minimized windows always get the longer settle delay
+    for title in targets.iter().filter(|t| is_window_minimized(t)) {
+        printer.note(&format!("'{}' is minimized; restoring it first.", title));
+    }
+
+    let filter = CharFilter::from(config);
+    if let Some(summary) = charfilter::summary(&filter, &file.lines) {
+        printer.note(&summary);
+    }
+
+    hooks::pre_send(&config.pre_send_hook, &file.name, file.lines.len(), &targets)
+        .map_err(|e| SendError::new(ErrorKind::Refused, e))?;
+    report::record_before_send(&targets, config);
+    let caps_was_on = if choices.dry_run {
+        false
+    } else {
+        check_keyboard_state(config.compensate_keyboard_state, |held| {
+            printer.message(&format!("Release {} to continue...", held.join("+")));
+        })
+        .map_err(|e| SendError::new(ErrorKind::Keyboard, e))?
+    };
+
+    let game_clock = if config.ask_game_time && file.lines.iter().any(|l| l.at.is_some()) {
+        ask_game_clock(printer)
+    } else {
+        None
+    };
+
+    let mut lifetime = Stats::load_lifetime();
+    let started = Instant::now();
+    let (mut platform, mut dry_run) = (PlatformSender, DryRunSender::default());
+    let sender: &mut dyn LineSender = if choices.dry_run { &mut dry_run } else { &mut platform };
+    let session = SendSession::new(&file.lines, targets, choices.order)
+        .with_clock_start(game_clock.map(|c| c.now()).unwrap_or_default())
+        .with_delays(SendDelays::from(config))
+        .with_busy_policy(BusyPolicy::from(config))
+        .with_split_long_lines(config.split_long_lines)
+        .with_char_limit(config.char_limit())
+        .with_key_mode(KeyMode::from(config))
+        .with_trace_input(config.trace_input)
+        .with_recording(config.record_sessions)
+        .with_max_lines_per_send(config.max_lines_per_send)
+        .with_slash_commands(config.slash_commands)
+        .with_window_lost_wait(Duration::from_secs(config.window_lost_wait_secs))
+        .with_send_method(config.send_method)
+        .with_typed_windows(paste::load())
+        .with_focus_delays(focus_quirks::load())
+        .with_char_retries(config.char_retry_limit)
+        .with_paste_check_hook(&config.paste_check_hook)
+        .with_verify_hook(&config.verify_hook)
+        .with_char_filter(filter)
+        .with_abbreviations(Abbreviations::from(config))
+        .with_choices(choices);
+    let session = if choices.dry_run { session } else { session.with_journal(file.path.clone()) };
+    let report = session
+        .run(sender, |event| {
+            if !choices.dry_run {
+                lifetime.record(event);
+            }
+            outcome.record(event);
+            if let Some(cue) = send_cue(event, config.send_sound) {
+                play_cue(cue);
+            }
+            printer.event(event);
+            Control::Continue
+        });
+    printer.finish();
+    restore_keyboard_state(caps_was_on);
+    let report = report.map_err(|e| SendError::new(ErrorKind::Refused, e))?;
+    if PERSIST_LIFETIME_STATS && !choices.dry_run {
+        if let Err(e) = lifetime.save_lifetime() {
+            log(&e);
+        }
+    }
+
+    printer.message(&format!("Sent {} of {} line(s).", report.sent(), total * report.targets.len()));
+    if let Some(summary) = report.verification_summary() {
+        printer.message(&format!("{}.", summary));
+    }
+    for target in &report.targets {
+        if let Some(reason) = &target.paste_fallback {
+            printer.message(&format!("Pasting didn't work in '{}' ({}); it is typed into from now on.", target.window_title, reason));
+        }
+    }
+    if let Err(e) = paste::save(&report) {
+        log(&e);
+    }
+    if let Err(e) = focus_quirks::save(&report) {
+        log(&e);
+    }
+    if let Some(path) = &report.trace_file {
+        printer.message(&format!("Input trace: {}", path.display()));
+    }
+    if let Some(path) = &report.session_file {
+        printer.message(&format!("Session recording: {}", path.display()));
+    }
+    if config.notify_when_finished && started.elapsed().as_secs() >= NOTIFY_MIN_SEND_SECS {
+        let (title, text) = finish_notice(&file.name, &report, total);
+        notify(&title, &text);
+    }
+    if let Err(e) = hooks::post_send(&config.post_send_hook, &file.name, &report, started.elapsed()) {
+        log(&e);
+        printer.message(&format!("Warning: {}", e));
+    }
+    if !choices.dry_run && archive::should_archive(file, config, &report) {
+        match scan_directory().and_then(|dir| archive::archive(file, &dir)) {
+            Ok(path) => printer.message(&format!("Archived to {}", path.display())),
+            Err(e) => printer.message(&format!("Warning: sent, but not archived: {}", e)),
+        }
+    }
+    Ok((report, total))
+}
+
+/// Type a recorded session again with its recorded timing: into a mock
+/// keyboard (dry run), or into the real windows when `live`.
+///
+/// Returns an error if the file can't be read, if a live replay finds no
+/// window or read-only mode on, or if any line failed.
+pub fn replay(path: &str, live: bool, config: &Config) -> Result<SendReport, String> {
+    let recording = replay::load(Path::new(path))?;
+    if live {
+        if config.read_only {
+            return Err(format!("Can't replay '{}' live: {}", path, READ_ONLY_ERROR));
+        }
+        if !recording.targets.iter().any(|t| is_window_running(t)) {
+            return Err(not_running_error(&recording.targets));
+        }
+    }
+    let typed = recording.steps.iter().filter(|s| s.outcome != StepOutcome::Skipped).count();
+    println!(
+        "Replaying {} line(s) to {}{}...",
+        typed,
+        recording.targets.join(", "),
+        if live { "" } else { " (dry run, nothing is typed)" }
+    );
+    log(&format!("Replay of {} ({} steps, live: {})", path, recording.steps.len(), live));
+
+    let on_step = |step: &Step, result: &Result<_, String>| match result {
+        Ok(_) => println!("[{} +{}ms] ✓ {} → {}", step.line + 1, step.at_ms, step.text, step.target),
+        Err(e) => println!("[{} +{}ms] ✗ {} → {}: {}", step.line + 1, step.at_ms, step.text, step.target, e),
+    };
+    let report = if live {
+        let caps_was_on = check_keyboard_state(config.compensate_keyboard_state, |held| {
+            println!("Release {} to continue...", held.join("+"));
+        })?;
+        let report = replay::replay(&recording, &mut PlatformSender, on_step);
+        restore_keyboard_state(caps_was_on);
+        report
+    } else {
+        replay::replay(&recording, &mut DryRunSender::default(), on_step)
+    };
+
+    println!("Replayed {} of {} line(s).", report.sent(), typed);
+    if report.failed() > 0 {
+        return Err(format!("{} target window(s) stopped after a failed line", report.failed()));
+    }
+    Ok(report)
+}
+
+/// Ask on stdin whether to send despite a game guard `problem`. Never
+/// without a terminal to ask on.
+fn confirm_send_anyway(printer: &mut Progress<impl Write>, problem: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        return false;
+    }
+    printer.message(&format!("Game guard: {}", problem));
+    eprint!("Send anyway? [y/N] ");
+    let mut answer = String::new();
+    let _ = io::stdin().read_line(&mut answer);
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Ask on stdin for the current game time. Without a terminal, or on empty
+/// or bad input, timed lines count from the send start.
+fn ask_game_clock(printer: &mut Progress<impl Write>) -> Option<GameClock> {
+    if !io::stdin().is_terminal() {
+        printer.message("No terminal to ask for the game time; timed lines count from the send start.");
+        return None;
+    }
+    eprint!("Game time now (MM:SS, empty: 00:00): ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).ok()?;
+    if answer.trim().is_empty() {
+        return None;
+    }
+    match parse_clock(&answer) {
+        Some(time) => Some(GameClock::new(time)),
+        None => {
+            printer.message(&format!("'{}' is not a game time (MM:SS); counting from the send start.", answer.trim()));
+            None
+        }
+    }
+}
+
+/// Import a zip pack into the scan directory, asking on stdin what to do
+/// with files that already exist (skipping them when stdin isn't a terminal).
+///
+/// With recursive discovery on, the pack goes into a subdirectory named
+/// after the archive; otherwise into the scan directory itself so the
+/// files show up in the list.
+pub fn import(archive: &str, config: &Config) -> Result<ImportReport, String> {
+    if config.read_only {
+        return Err(format!("Can't import '{}': read-only mode", archive));
+    }
+    let archive = Path::new(archive);
+    let scan_dir = scan_directory()?;
+    let subdir = if config.recursive_discovery { default_subdirectory(archive) } else { String::new() };
+    println!("Importing {} into {}...", archive.display(), scan_dir.join(&subdir).display());
+
+    let interactive = io::stdin().is_terminal();
+    let report = pack::import(archive, &scan_dir, Path::new(&subdir), |path| {
+        if !interactive {
+            println!("{} already exists, skipped", path.display());
+            return Collision::Skip;
+        }
+        ask_collision(path)
+    })?;
+    for path in &report.imported {
+        println!("+ {}", path.display());
+    }
+    println!("{}.", report.summary());
+    Ok(report)
+}
+
+/// Export the files whose name matches `pattern` to `output` (.zip or .md).
+///
+/// An existing output file is only replaced after confirming on stdin.
+pub fn export(files: &[TextFile], pattern: &str, output: &str) -> Result<(), String> {
+    let matching: Vec<&TextFile> = files.iter().filter(|f| glob_match(pattern, &f.name)).collect();
+    if matching.is_empty() {
+        return Err(format!("No message file matches '{}' (see --list)", pattern));
+    }
+    let output = Path::new(output);
+    if output.exists() {
+        if !io::stdin().is_terminal() {
+            return Err(format!("{} already exists; remove it or pick another name", output.display()));
+        }
+        print!("{} already exists. Overwrite it? [y/N] ", output.display());
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        let _ = io::stdin().read_line(&mut answer);
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            return Err("Export cancelled".to_string());
+        }
+    }
+    let size = pack::export(&matching, output)?;
+    for file in &matching {
+        println!("+ {}", file.name);
+    }
+    println!("Exported {} file(s) to {} ({}).", matching.len(), output.display(), format_size(size));
+    Ok(())
+}
+
+/// Ask on stdin whether to overwrite, rename or skip an existing file.
+fn ask_collision(path: &Path) -> Collision {
+    loop {
+        print!("{} already exists: [o]verwrite, [r]ename or [s]kip? ", path.display());
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+            return Collision::Skip;
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "o" | "overwrite" => return Collision::Overwrite,
+            "r" | "rename" => return Collision::Rename,
+            "s" | "skip" | "" => return Collision::Skip,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Line;
+
+    fn file(name: &str) -> TextFile {
+        TextFile::with_lines(name, &[])
+    }
+
+    #[test]
+    fn test_find_file_by_name_or_stem() {
+        let files = vec![file("Greetings.txt"), file("taunts.md")];
+        assert_eq!(find_file(&files, "greetings.TXT").map(|f| f.name.as_str()), Some("Greetings.txt"));
+        assert_eq!(find_file(&files, "taunts").map(|f| f.name.as_str()), Some("taunts.md"));
+        assert!(find_file(&files, "missing").is_none());
+
+        let nested = vec![file("aram/openers.txt"), file("funny/openers.txt")];
+        assert_eq!(find_file(&nested, "funny/openers.txt").map(|f| f.name.as_str()), Some("funny/openers.txt"));
+        assert_eq!(find_file(&nested, r"Funny\openers").map(|f| f.name.as_str()), Some("funny/openers.txt"));
+        assert_eq!(
+            file_not_found(&nested, "funy/opener.txt"),
+            "No message file named 'funy/opener.txt'; closest: 'funny/openers.txt', 'aram/openers.txt'"
+        );
+        assert_eq!(file_not_found(&nested, "zzz"), "No message file named 'zzz' (see --list)");
+    }
+
+    #[test]
+    fn test_send_refused_in_read_only_mode() {
+        let config = Config { read_only: true, ..Config::default() };
+        let error = send(&[file("gg.txt")], "gg", &["game".to_string()], &config, SendChoices::default(), false, ProgressMode::Full).unwrap_err();
+        assert_eq!(error.message, "Can't send 'gg': read-only mode: sending is disabled");
+    }
+
+    #[test]
+    fn test_send_over_line_cap_refused() {
+        let config = Config { max_lines_per_send: 2, ..Config::default() };
+        let mut gg = file("gg.txt");
+        gg.lines = ["gl", "hf", "wp"].map(Line::new).to_vec();
+        let error = send(&[gg], "gg", &["game".to_string()], &config, SendChoices::default(), false, ProgressMode::Full).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::TooManyLines);
+        assert!(error.message.starts_with("Can't send 'gg.txt': This send is 3 messages, over the limit of 2"));
+    }
+}
@@ -0,0 +1,214 @@
+//! File health for MadTyping
+//!
+//! A quick check of what would go wrong, or come out differently than
+//! written, if a file were sent now: lines over the chat limit and sends
+//! over the line cap (errors, the send is refused), characters that can't
+//! be typed (control characters, encoding leftovers such as a byte order
+//! mark), characters the filter removes, unknown abbreviations and the
+//! warnings found when the file was loaded.
+//!
+//! The file list runs the check when a file is first selected and shows
+//! the result next to its name: a green dot when clean, the number of
+//! warnings in yellow, a red cross with errors. [`HealthCache`] keeps
+//! results per path and modification time, so a render never checks a
+//! file again until it is reloaded or the settings change.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::abbreviations::Abbreviations;
+use crate::charfilter::CharFilter;
+use crate::config::Config;
+use crate::files::TextFile;
+use crate::send::{line_cap_problem, message_count};
+
+/// Characters that take no room on screen, mostly left over from
+/// converting between encodings.
+const INVISIBLE_CHARS: &[char] = &['\u{feff}', '\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}'];
+
+/// Problems found in a file, each starting with the line it is on where
+/// there is one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Health {
+    /// Problems that make the send refuse to start
+    pub errors: Vec<String>,
+    /// Problems the send goes ahead with
+    pub warnings: Vec<String>,
+}
+
+/// Summary of a [`Health`], as shown in the list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Clean,
+    Warnings(usize),
+    Errors(usize),
+}
+
+impl Health {
+    pub fn level(&self) -> Level {
+        match (self.errors.len(), self.warnings.len()) {
+            (0, 0) => Level::Clean,
+            (0, warnings) => Level::Warnings(warnings),
+            (errors, _) => Level::Errors(errors),
+        }
+    }
+
+    /// The problem to show first: the first error, else the first warning.
+    pub fn first_issue(&self) -> Option<&String> {
+        self.errors.first().or_else(|| self.warnings.first())
+    }
+}
+
+/// Check `file` as it would be sent with `config`.
+pub fn validate(file: &TextFile, config: &Config) -> Health {
+    let mut health = Health::default();
+    let lines = Abbreviations::from(config).expand_lines(&file.lines);
+
+    if let Some(max) = config.char_limit().filter(|_| !config.split_long_lines) {
+        for (index, line) in lines.iter().enumerate() {
+            let chars = line.text.chars().count();
+            if chars > max {
+                health.errors.push(format!("line {}: {} characters, over the game's {}-character limit", index + 1, chars, max));
+            }
+        }
+    }
+    let count = message_count(&lines, config.split_long_lines, config.char_limit());
+    health.errors.extend(line_cap_problem(count, 0, config.max_lines_per_send));
+
+    health.warnings.extend(file.warnings.iter().cloned());
+    for warning in Abbreviations::from(config).warnings(&file.lines) {
+        // Loading already flagged the tokens unknown at the time
+        if !health.warnings.contains(&warning) {
+            health.warnings.push(warning);
+        }
+    }
+    let filter = CharFilter::from(config);
+    for (index, line) in lines.iter().enumerate() {
+        let number = index + 1;
+        if let Some(c) = line.text.chars().find(|c| c.is_control()) {
+            health.warnings.push(format!("line {}: control character U+{:04X} can't be typed", number, u32::from(c)));
+        }
+        if let Some(c) = line.text.chars().find(|c| INVISIBLE_CHARS.contains(c)) {
+            health.warnings.push(format!("line {}: invisible U+{:04X}, likely left over from the encoding", number, u32::from(c)));
+        }
+        if filter.is_active() {
+            let filtered = filter.apply(&line.text);
+            if filtered.text.is_empty() {
+                health.warnings.push(format!("line {}: empty after the character filter, skipped", number));
+            } else if filtered.lost > 0 {
+                health.warnings.push(format!("line {}: {} character(s) removed by the character filter", number, filtered.lost));
+            }
+        }
+    }
+    health
+}
+
+/// [`Health`] of the files checked so far, per path and modification time.
+#[derive(Clone, Debug, Default)]
+pub struct HealthCache {
+    entries: HashMap<PathBuf, (Option<SystemTime>, Health)>,
+    /// Settings the entries were checked with
+    config: Option<Config>,
+}
+
+impl HealthCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The health of `file` if it was checked at its current version.
+    pub fn get(&self, file: &TextFile) -> Option<&Health> {
+        self.entries.get(&file.path).filter(|(modified, _)| *modified == file.modified).map(|(_, health)| health)
+    }
+
+    /// The health of `file` under `config`, checking it only if it changed
+    /// (or the settings did) since it was last checked.
+    pub fn check(&mut self, file: &TextFile, config: &Config) -> &Health {
+        if self.config.as_ref() != Some(config) {
+            self.entries.clear();
+            self.config = Some(config.clone());
+        }
+        let entry = self.entries.entry(file.path.clone()).or_insert_with(|| (file.modified, validate(file, config)));
+        if entry.0 != file.modified {
+            *entry = (file.modified, validate(file, config));
+        }
+        &entry.1
+    }
+
+    /// Forget the result for `path`, e.g. after the file was reloaded.
+    pub fn invalidate(&mut self, path: &Path) {
+        self.entries.remove(path);
+    }
+
+    /// Number of files with a result.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Line;
+    use std::time::Duration;
+
+    fn file(lines: &[&str]) -> TextFile {
+        TextFile { modified: Some(SystemTime::UNIX_EPOCH), ..TextFile::with_lines("a.txt", lines) }
+    }
+
+    #[test]
+    fn test_validation_finds_errors_and_warnings() {
+        let config = Config { max_lines_per_send: 3, ..Config::default() };
+        assert_eq!(validate(&file(&["gl", "hf"]), &config).level(), Level::Clean);
+
+        let mut loaded = file(&["\u{feff}gl", "tab\there", "wp"]);
+        loaded.warnings.push("line 3: weight 0 leaves it out of random picks".to_string());
+        let health = validate(&loaded, &config);
+        assert_eq!(health.level(), Level::Warnings(3));
+        assert_eq!(health.warnings, vec![
+            "line 3: weight 0 leaves it out of random picks",
+            "line 1: invisible U+FEFF, likely left over from the encoding",
+            "line 2: control character U+0009 can't be typed",
+        ]);
+
+        let long = "x".repeat(201);
+        let health = validate(&file(&["gl", &long, "a", "b"]), &config);
+        assert_eq!(health.level(), Level::Errors(2));
+        assert_eq!(health.first_issue().unwrap(), "line 2: 201 characters, over the game's 200-character limit");
+        assert!(health.errors[1].starts_with("This send is 4 messages"));
+
+        // No limit, no length errors
+        let unlimited = Config { game_profile: crate::config::GameProfile::Unlimited, ..config };
+        assert_eq!(validate(&file(&[&long]), &unlimited).level(), Level::Clean);
+    }
+
+    #[test]
+    fn test_cache_checks_each_version_once() {
+        let config = Config::default();
+        let mut cache = HealthCache::new();
+        let mut file = file(&["gl"]);
+        assert!(cache.get(&file).is_none());
+        assert_eq!(cache.check(&file, &config).level(), Level::Clean);
+        assert!(cache.get(&file).is_some());
+
+        // A reload with a new modification time is checked again
+        file.lines.push(Line::new("\u{7}"));
+        assert_eq!(cache.check(&file, &config).level(), Level::Clean);
+        file.modified = file.modified.map(|m| m + Duration::from_secs(1));
+        assert!(cache.get(&file).is_none());
+        assert_eq!(cache.check(&file, &config).level(), Level::Warnings(1));
+
+        // So is every file after the settings change
+        let strict = Config { chat_char_limit: 1, ..config };
+        assert_eq!(cache.check(&file, &strict).level(), Level::Errors(1));
+        cache.invalidate(&file.path);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+//! Send hooks for MadTyping
+//!
+//! `pre_send_hook` runs before each send and cancels it by exiting with a
+//! non-zero code; `post_send_hook` runs after each send, e.g. to play a
+//! sound or switch an OBS scene; `paste_check_hook` tells whether a pasted
+//! line showed up in chat, and `verify_hook` whether any sent line did
+//! (e.g. by reading the screen). All are shell commands (`cmd /C` on
+//! Windows, `sh -c` elsewhere) that get the send described in `MADTYPING_*`
+//! environment variables. Their output is discarded and they are killed
+//! after `HOOK_TIMEOUT_MS` (`VERIFY_TIMEOUT_MS` for `verify_hook`), so a
+//! stuck hook can't hang the UI.
+
+use std::{
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config::{HOOK_TIMEOUT_MS, VERIFY_TIMEOUT_MS};
+use crate::logging::log;
+use crate::send::SendReport;
+
+/// How often a running hook is checked for exit.
+const POLL_MS: u64 = 20;
+
+/// Run `pre_send_hook` (if set) before sending `lines` lines of `file` to
+/// `targets`. An error means the send must not go ahead.
+pub fn pre_send(command: &str, file: &str, lines: usize, targets: &[String]) -> Result<(), String> {
+    if command.trim().is_empty() {
+        return Ok(());
+    }
+    let env = [
+        ("MADTYPING_FILE", file.to_string()),
+        ("MADTYPING_LINES", lines.to_string()),
+        ("MADTYPING_TARGETS", targets.join(";")),
+    ];
+    run(command, &env, Duration::from_millis(HOOK_TIMEOUT_MS)).map_err(|e| format!("pre_send_hook {}; send cancelled", e))
+}
+
+/// Run `post_send_hook` (if set) after a send of `file` that took `duration`.
+/// An error is a warning to show; the send itself is over.
+pub fn post_send(command: &str, file: &str, report: &SendReport, duration: Duration) -> Result<(), String> {
+    if command.trim().is_empty() {
+        return Ok(());
+    }
+    run(command, &post_send_env(file, report, duration), Duration::from_millis(HOOK_TIMEOUT_MS))
+        .map_err(|e| format!("post_send_hook {}", e))
+}
+
+/// Run `paste_check_hook` (if set) after `text` was pasted into `window`.
+/// An error means the chat text stayed empty (or the hook couldn't say
+/// otherwise) and the window should be typed into instead.
+pub fn paste_check(command: &str, window: &str, text: &str) -> Result<(), String> {
+    if command.trim().is_empty() {
+        return Ok(());
+    }
+    let env = [("MADTYPING_WINDOW", window.to_string()), ("MADTYPING_TEXT", text.to_string())];
+    run(command, &env, Duration::from_millis(HOOK_TIMEOUT_MS)).map_err(|e| format!("paste_check_hook {}", e))
+}
+
+/// Run `verify_hook` (if set) after line `index` (zero-based) was sent to
+/// `window`. An error means the line wasn't seen in chat.
+pub fn verify(command: &str, window: &str, index: usize, text: &str) -> Result<(), String> {
+    if command.trim().is_empty() {
+        return Ok(());
+    }
+    let env = [
+        ("MADTYPING_WINDOW", window.to_string()),
+        ("MADTYPING_LINE", (index + 1).to_string()),
+        ("MADTYPING_TEXT", text.to_string()),
+    ];
+    run(command, &env, Duration::from_millis(VERIFY_TIMEOUT_MS)).map_err(|e| format!("verify_hook {}", e))
+}
+
+/// Environment describing a finished send.
+fn post_send_env(file: &str, report: &SendReport, duration: Duration) -> [(&'static str, String); 6] {
+    [
+        ("MADTYPING_FILE", file.to_string()),
+        ("MADTYPING_SENT", report.sent().to_string()),
+        ("MADTYPING_FAILED", report.failed().to_string()),
+        ("MADTYPING_SKIPPED", report.skipped().to_string()),
+        ("MADTYPING_CANCELLED", u8::from(report.cancelled).to_string()),
+        ("MADTYPING_DURATION_MS", duration.as_millis().to_string()),
+    ]
+}
+
+/// Run `command` through the shell with `env`, killing it after `timeout`.
+fn run(command: &str, env: &[(&str, String)], timeout: Duration) -> Result<(), String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    log(&format!("Running hook: {}", command));
+    let mut child = shell.spawn().map_err(|e| format!("could not start: {}", e))?;
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                let code = status.code().map(|c| c.to_string()).unwrap_or_else(|| "none".to_string());
+                return Err(format!("exited with code {}", code));
+            }
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {} ms", timeout.as_millis()));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(POLL_MS)),
+            Err(e) => return Err(format!("failed: {}", e)),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::send::TargetReport;
+
+    #[test]
+    fn test_hook_exit_codes_and_timeout() {
+        let timeout = Duration::from_secs(5);
+        assert_eq!(run("exit 0", &[], timeout), Ok(()));
+        assert_eq!(run("exit 3", &[], timeout), Err("exited with code 3".to_string()));
+        assert_eq!(
+            run("sleep 5", &[], Duration::from_millis(100)),
+            Err("timed out after 100 ms".to_string())
+        );
+        assert!(pre_send("exit 1", "gg.txt", 2, &[]).unwrap_err().ends_with("send cancelled"));
+        assert_eq!(pre_send("  ", "gg.txt", 2, &[]), Ok(()));
+        assert_eq!(paste_check(r#"[ "$MADTYPING_TEXT" = "gg wp" ]"#, "Game", "gg wp"), Ok(()));
+        assert_eq!(paste_check("exit 1", "Game", "gg wp"), Err("paste_check_hook exited with code 1".to_string()));
+        assert_eq!(verify(r#"[ "$MADTYPING_LINE" = 3 ] && [ "$MADTYPING_WINDOW" = Game ]"#, "Game", 2, "gg"), Ok(()));
+        assert_eq!(verify("sleep 5", "Game", 2, "gg"), Err(format!("verify_hook timed out after {} ms", VERIFY_TIMEOUT_MS)));
+    }
+
+    #[test]
+    fn test_post_send_environment() {
+        let failed = Some((3, "ff".to_string(), "not focused".to_string()));
+        let report = SendReport { targets: vec![TargetReport { sent: 3, failed, ..Default::default() }], ..Default::default() };
+        let env = post_send_env("gg.txt", &report, Duration::from_millis(1234));
+        let check = r#"[ "$MADTYPING_FILE" = gg.txt ] && [ "$MADTYPING_SENT" = 3 ] && [ "$MADTYPING_FAILED" = 1 ] && [ "$MADTYPING_DURATION_MS" = 1234 ]"#;
+        assert_eq!(run(check, &env, Duration::from_secs(5)), Ok(()));
+    }
+}
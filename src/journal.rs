@@ -0,0 +1,215 @@
+//! Send journal
+//!
+//! While a file is sent, `madtyping.journal` in the data directory records
+//! which file it is, when the send started and every line that went out,
+//! each record flushed to disk before the next line is typed. A send that
+//! ends, however it ends, appends a completion record. If MadTyping is
+//! killed mid-send, the completion record is missing; the next start
+//! reports how far the send got and offers to resume after the last line
+//! that went out.
+//!
+//! Records are one line each:
+//!
+//! ```text
+//! start 1767225600 20 C:\Games\MadTyping\gg.txt
+//! line 0 League of Legends (TM) Client
+//! end 1
+//! ```
+//!
+//! A record cut off by the crash (no newline yet) is ignored, and so is
+//! everything from the first record that doesn't parse.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::logging::log;
+use crate::paths;
+use crate::report::now_secs;
+
+/// File name of the journal, stored in the data directory.
+pub const JOURNAL_FILE_NAME: &str = "madtyping.journal";
+
+/// Path of the journal file.
+pub fn journal_path() -> Result<PathBuf, String> {
+    paths::data_file(JOURNAL_FILE_NAME)
+}
+
+/// Journal of the send in progress.
+#[derive(Debug)]
+pub struct Journal {
+    file: File,
+}
+
+impl Journal {
+    /// Start a journal at `path` for sending the `total` lines of `source`,
+    /// replacing the journal of an earlier send.
+    pub fn start(path: &Path, source: &Path, total: usize) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut journal = Self { file };
+        journal.append(&format!("start {} {} {}", now_secs(), total, one_line(&source.display().to_string())))?;
+        Ok(journal)
+    }
+
+    /// Record that line `index` went out to `target`.
+    pub fn line_sent(&mut self, index: usize, target: &str) -> Result<(), String> {
+        self.append(&format!("line {} {}", index, one_line(target)))
+    }
+
+    /// Record that the send ended with `sent` lines out.
+    pub fn finish(mut self, sent: usize) -> Result<(), String> {
+        self.append(&format!("end {}", sent))
+    }
+
+    fn append(&mut self, record: &str) -> Result<(), String> {
+        self.file
+            .write_all(format!("{}\n", record).as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .map_err(|e| format!("Failed to write the send journal: {}", e))
+    }
+}
+
+/// Newlines would split a record in two.
+fn one_line(text: &str) -> String {
+    text.replace(['\n', '\r'], " ")
+}
+
+/// A send that was cut off before its completion record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interrupted {
+    /// The file that was being sent
+    pub source: PathBuf,
+    /// When the send started, in Unix seconds
+    pub started: u64,
+    /// Lines in the file
+    pub total: usize,
+    /// Zero-based index and target of every line that went out, in order
+    pub sent: Vec<(usize, String)>,
+}
+
+impl Interrupted {
+    /// Zero-based index where a resumed send starts: the line after the
+    /// last one that went out to the target that got the fewest, so no
+    /// target misses a line.
+    pub fn resume_from(&self) -> usize {
+        let mut next: HashMap<&str, usize> = HashMap::new();
+        for (index, target) in &self.sent {
+            let after = next.entry(target.as_str()).or_insert(0);
+            *after = (*after).max(index + 1);
+        }
+        next.into_values().min().unwrap_or(0)
+    }
+
+    /// Whether a file of `lines` lines can be resumed: it has as many lines
+    /// as the interrupted send had, and some are left to send.
+    pub fn is_resumable(&self, lines: usize) -> bool {
+        lines == self.total && self.resume_from() < lines
+    }
+}
+
+/// The interrupted send `text` records, None if it's finished or there is
+/// no start record.
+pub fn parse(text: &str) -> Option<Interrupted> {
+    // Only records with their newline were written completely
+    let complete = text.rfind('\n').map_or("", |end| &text[..end]);
+    let mut records = complete.lines();
+    let start: Vec<&str> = records.next()?.splitn(4, ' ').collect();
+    let mut interrupted = match start[..] {
+        ["start", started, total, source] => Interrupted {
+            source: PathBuf::from(source),
+            started: started.parse().ok()?,
+            total: total.parse().ok()?,
+            sent: Vec::new(),
+        },
+        _ => return None,
+    };
+    for record in records {
+        match record.splitn(3, ' ').collect::<Vec<_>>()[..] {
+            ["line", index, target] => match index.parse() {
+                Ok(index) => interrupted.sent.push((index, target.to_string())),
+                Err(_) => break,
+            },
+            ["end", ..] => return None,
+            _ => break,
+        }
+    }
+    Some(interrupted)
+}
+
+/// The send the last run left unfinished, if any. The journal is removed,
+/// so an interrupted send is reported once.
+pub fn take_interrupted() -> Option<Interrupted> {
+    let path = journal_path().ok()?;
+    let text = fs::read(&path).ok()?;
+    if let Err(e) = fs::remove_file(&path) {
+        log(&format!("Failed to remove {}: {}", path.display(), e));
+    }
+    parse(&String::from_utf8_lossy(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOURNAL: &str = "start 1767225600 20 C:\\Games\\gg wp.txt\nline 0 League of Legends (TM) Client\nline 1 Notepad\nline 1 League of Legends (TM) Client\n";
+
+    #[test]
+    fn test_interrupted_send_recovered() {
+        let interrupted = parse(JOURNAL).unwrap();
+        assert_eq!(interrupted.source, PathBuf::from("C:\\Games\\gg wp.txt"));
+        assert_eq!((interrupted.started, interrupted.total), (1_767_225_600, 20));
+        assert_eq!(interrupted.sent.len(), 3);
+        assert_eq!(interrupted.sent[1], (1, "Notepad".to_string()));
+        assert_eq!(interrupted.resume_from(), 2);
+        assert!(interrupted.is_resumable(20));
+        assert!(!interrupted.is_resumable(19));
+        assert!(!interrupted.is_resumable(2));
+
+        assert_eq!(parse(&format!("{}end 3\n", JOURNAL)), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("line 0 Notepad\n"), None);
+    }
+
+    #[test]
+    fn test_resume_follows_the_target_furthest_behind() {
+        let interrupted = parse(&format!("{}line 2 League of Legends (TM) Client\n", JOURNAL)).unwrap();
+        assert_eq!(interrupted.resume_from(), 2);
+        let done = parse("start 1767225600 2 gg.txt\nline 0 Notepad\nline 1 Notepad\n").unwrap();
+        assert_eq!(done.resume_from(), 2);
+        assert!(!done.is_resumable(2));
+    }
+
+    #[test]
+    fn test_truncated_journal_keeps_complete_records() {
+        // Cut anywhere, the journal yields the records written completely
+        for cut in 0..JOURNAL.len() {
+            let torn = &JOURNAL[..cut];
+            let complete = torn.matches('\n').count();
+            match parse(torn) {
+                None => assert_eq!(complete, 0, "cut at {}", cut),
+                Some(interrupted) => assert_eq!(interrupted.sent.len(), complete - 1, "cut at {}", cut),
+            }
+        }
+        // A torn end record leaves the send interrupted
+        assert_eq!(parse(&format!("{}en", JOURNAL)).unwrap().resume_from(), 2);
+        // Garbage from a half-written block ends the journal there
+        let garbled = format!("{}line x\0\0\nline 5 Notepad\n", JOURNAL);
+        assert_eq!(parse(&garbled).unwrap().resume_from(), 2);
+    }
+
+    #[test]
+    fn test_journal_written_record_by_record() {
+        let path = std::env::temp_dir().join(format!("madtyping-journal-{}", std::process::id()));
+        let mut journal = Journal::start(&path, Path::new("gg.txt"), 3).unwrap();
+        journal.line_sent(0, "Notepad").unwrap();
+        let interrupted = parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!((interrupted.total, interrupted.resume_from()), (3, 1));
+
+        journal.finish(1).unwrap();
+        assert_eq!(parse(&fs::read_to_string(&path).unwrap()), None);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,271 @@
+//! Minimal JSON for MadTyping
+//!
+//! Just enough to write and read back the small documents MadTyping
+//! produces itself (`--json-result` output and recorded sessions) without
+//! a serialization crate. Numbers are kept as `f64`, which holds every
+//! integer these documents use exactly.
+
+/// A parsed JSON value. Object members keep their order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// A non-negative whole number.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= u64::MAX as f64 => Some(n as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Quote and escape `text` as a JSON string.
+pub fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parse a JSON document.
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { text, pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(parser.error("unexpected text after the document"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, what: &str) -> String {
+        format!("Invalid JSON at byte {}: {}", self.pos, what)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t') => self.literal("true", Value::Bool(true)),
+            Some('f') => self.literal("false", Value::Bool(false)),
+            Some('n') => self.literal("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            self.pos += 1;
+        }
+        self.text[start..self.pos].parse().map(Value::Number).map_err(|_| self.error("bad number"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.next().ok_or_else(|| self.error("unterminated string"))? {
+                '"' => return Ok(text),
+                '\\' => match self.next().ok_or_else(|| self.error("unterminated string"))? {
+                    '"' => text.push('"'),
+                    '\\' => text.push('\\'),
+                    '/' => text.push('/'),
+                    'b' => text.push('\u{8}'),
+                    'f' => text.push('\u{c}'),
+                    'n' => text.push('\n'),
+                    'r' => text.push('\r'),
+                    't' => text.push('\t'),
+                    'u' => text.push(self.unicode_escape()?),
+                    _ => return Err(self.error("bad escape")),
+                },
+                c => text.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\uXXXX` escape (after the `u`), joining
+    /// surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("bad \\u escape"));
+        }
+        if !self.text[self.pos..].starts_with("\\u") {
+            return Err(self.error("unpaired surrogate"));
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate"));
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or_else(|| self.error("bad \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.pos..self.pos + 4).ok_or_else(|| self.error("bad \\u escape"))?;
+        let value = u32::from_str_radix(digits, 16).map_err(|_| self.error("bad \\u escape"))?;
+        self.pos += 4;
+        Ok(value)
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Object(members)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trips_strings() {
+        let text = "say \"hi\"\n\tö 😀 \u{1}";
+        assert_eq!(parse(&string(text)).unwrap(), Value::String(text.to_string()));
+        assert_eq!(parse(r#""😀 é""#).unwrap(), Value::String("😀 é".to_string()));
+    }
+
+    #[test]
+    fn test_parse_document() {
+        let value = parse(r#" {"version": 1, "lines": [{"text": "gg", "ok": true}, null], "empty": {}, "neg": -1.5} "#).unwrap();
+        assert_eq!(value.get("version").and_then(Value::as_u64), Some(1));
+        let lines = value.get("lines").and_then(Value::as_array).unwrap();
+        assert_eq!(lines[0].get("text").and_then(Value::as_str), Some("gg"));
+        assert_eq!(lines[0].get("ok").and_then(Value::as_bool), Some(true));
+        assert_eq!(lines[1], Value::Null);
+        assert_eq!(value.get("neg").and_then(Value::as_u64), None);
+        assert_eq!(value.get("empty"), Some(&Value::Object(Vec::new())));
+
+        assert!(parse("{\"a\": 1,}").unwrap_err().contains("byte 8"));
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("{} x").is_err());
+    }
+}
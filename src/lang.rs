@@ -0,0 +1,546 @@
+//! Translations for MadTyping
+//!
+//! Every text the terminal UI and the startup code show goes through a
+//! message id. English is built in; a translation file such as
+//! `lang/de.toml` next to the executable, picked by the `language` setting
+//! or the `LANG` environment variable, replaces it id by id. Ids the file
+//! doesn't have, and entries whose `{placeholders}` don't match the
+//! English text, stay English.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::config::{DEFAULT_HEADER_NAME, DISARMED_ERROR, LANGUAGE_DIR, READ_ONLY_ERROR};
+use crate::logging::log;
+use crate::paths;
+
+/// Built-in English text for every message id.
+pub const ENGLISH: &[(&str, &str)] = &[
+    ("app.title", DEFAULT_HEADER_NAME),
+    ("header.read_only", "read-only mode"),
+    ("header.cooldown", "ready in {secs}s"),
+    ("header.armed", "ARMED"),
+    ("header.disarmed", "DISARMED, Ctrl+A arms"),
+    // Footer hints
+    ("hint.type_filter", "[Type] Filter"),
+    ("hint.navigate", "[↑↓] Navigate"),
+    ("hint.run", "[Enter] Run"),
+    ("hint.back_to_list", "[Esc] Back to the list"),
+    ("hint.search", "[/] Search"),
+    ("hint.view", "[Tab] View"),
+    ("hint.help", "[F1] Help"),
+    ("hint.quit", "[Esc] Quit"),
+    ("hint.settings", "[F2] Settings"),
+    ("hint.tag", "[F3] Tag"),
+    ("hint.import", "[F4] Import"),
+    ("hint.refresh", "[F5] Refresh"),
+    ("hint.export", "[F6] Export"),
+    ("hint.stats", "[F9] Stats"),
+    ("hint.help_back", "[Esc/F1] Back"),
+    ("hint.settings_back", "[Esc/F2] Back"),
+    ("hint.toggle", "[Enter/Space] Toggle"),
+    ("hint.adjust", "[←→] Adjust"),
+    ("hint.test_send", "[T] Test send"),
+    ("hint.forget_paste", "[P] Forget paste fallbacks"),
+    ("hint.focus_delays", "[L] Learned focus delays"),
+    ("hint.hidden_warnings", "[W] Hidden warnings"),
+    ("hint.show_warning", "[Enter] Show again"),
+    ("hint.reset_confirm", "Reset lifetime stats? [Y] Yes"),
+    ("hint.no", "[N] No"),
+    ("hint.stats_back", "[Esc/F9] Back"),
+    ("hint.reset_lifetime", "[R] Reset lifetime"),
+    ("hint.skipped_back", "[Esc/F10] Back"),
+    ("hint.viewer_back", "[Esc/Tab] Back"),
+    ("hint.move", "[↑↓] Move"),
+    ("hint.edit_line", "[i] Edit line"),
+    ("hint.exclude", "[d] Don't send"),
+    ("hint.go_to_line", "[g] Go to line"),
+    ("hint.filtered", "[f] Filtered"),
+    ("hint.original", "[f] Original"),
+    ("hint.expanded", "[x] Expanded"),
+    ("hint.raw", "[x] Raw"),
+    // File list
+    ("search.label", " Search: "),
+    ("search.placeholder", "[/] to search"),
+    ("search.count", "({count} files)"),
+    ("search.count_capped", "{count}+ files (capped)"),
+    ("list.scanning", "Scanning for files…"),
+    ("list.no_match", "No files match your search."),
+    ("list.folder_one", "(1 file)"),
+    ("list.folder_many", "({count} files)"),
+    ("list.lines", "({count} lines)"),
+    ("list.lines_excluded", "({count} lines, {excluded} excluded)"),
+    ("list.warning_one", "⚠ 1 warning"),
+    ("list.warning_many", "⚠ {count} warnings"),
+    ("list.commands", "/ {count} command(s)"),
+    ("list.over_limit", "⛔ over the {max}-line limit"),
+    ("list.long_lines", "⛔ {count} line(s) over {max} characters"),
+    ("list.duplicate", "⧉ duplicate of {name}"),
+    ("list.manual", "✋ manual"),
+    ("list.sent_ago", "sent {ago} ago"),
+    ("list.sent_now", "sent just now"),
+    ("status.scanning", "scanning…"),
+    ("status.skipped", "{count} file(s) skipped — [F10] details"),
+    ("status.last_sent", "Sent {ago} ago: all lines went out ({count} delivered)"),
+    ("status.last_partial", "Sent {ago} ago: stopped after {count} line(s)"),
+    ("status.last_failed", "Sent {ago} ago: failed at line {line}"),
+    ("status.loaded", "loaded {ago} ago ({reason})"),
+    ("loaded.initial", "initial scan"),
+    ("loaded.rescan", "F5 / rescan"),
+    ("loaded.watcher", "changed on disk"),
+    ("loaded.edit", "edited here"),
+    ("loaded.send_reload", "reloaded before send"),
+    // Status row errors and notices
+    ("error.read_only", READ_ONLY_ERROR),
+    ("error.disarmed", DISARMED_ERROR),
+    ("error.cant_send", "Can't send: {reason}"),
+    ("error.cant_import", "Can't import: {reason}"),
+    ("error.cant_archive", "Sent, but not archived: {error}"),
+    ("error.test_send", "Test send failed: {reason}"),
+    ("error.compare_two", "Mark exactly two files with [Insert] to compare them"),
+    ("error.no_random_line", "No line of {name} can be picked at random (weights of 0, or excluded in the viewer)"),
+    ("error.render", "Render error: {error}"),
+    ("error.terminal", "This terminal doesn't support the interactive UI ({error}).\nTry running from Windows Terminal or cmd.exe, or use {flags}."),
+    ("error.terminal_setup", "Failed to setup terminal: {error}"),
+    ("error.terminal_cleanup", "Failed to cleanup terminal: {error}"),
+    ("error.raw_mode", "Failed to disable raw mode: {error}"),
+    ("error.refresh_failed", "Refresh failed: {error}"),
+    ("error.skipped_one", "'{name}' was skipped (not text or unreadable); [F10] lists skipped files"),
+    ("error.skipped_many", "{count} files were skipped (not text or unreadable); [F10] lists skipped files"),
+    ("error.line_failed", "Line {line} failed to send; [Tab] opens the file there"),
+    ("error.not_a_folder", "'{path}' is not a folder inside the scan directory"),
+    ("error.game_guard", "Game guard: {problem}"),
+    ("error.not_game_time", "'{input}' is not a game time (MM:SS)"),
+    ("error.changed_unreadable", "'{name}' changed on disk and is now empty or unreadable"),
+    ("error.config_rejected", "Config not reloaded, keeping the previous settings: {error}"),
+    ("notice.warning_hidden", "That warning won't be shown again ([F2] then [W] lists hidden warnings)"),
+    ("notice.duplicates", "{count} file(s) have the same messages as another file; [F7] steps through the copies"),
+    ("notice.no_copies", "The selected file has no listed copies"),
+    ("notice.manual_on", "{name}: sends one line per keypress ([Space] next, [S] skip)"),
+    ("notice.manual_off", "{name}: sends all lines automatically"),
+    ("notice.armed", "Armed: Enter sends ([Ctrl+A] disarms)"),
+    ("notice.disarmed", "Disarmed: sending is off until [Ctrl+A]"),
+    ("notice.arming_expired", "Disarmed after {secs}s without input; [Ctrl+A] arms again"),
+    ("notice.interrupted", "The last send of {name} was cut off at line {line} of {total}; send it again to resume"),
+    ("notice.import_subfolders", " (turn on \"Include subfolders\" in F2 to list them)"),
+    ("notice.exported", "Exported {count} file(s) to {path} ({size})"),
+    ("notice.config_reloaded", "Config reloaded"),
+    ("notice.archived", "{name} was fully sent and moved to {folder}/"),
+    ("notice.config_reloaded_warnings", "Config reloaded ({count} warning(s), first: {first})"),
+    // Prompts
+    ("prompt.import", "Import pack (zip path): "),
+    ("prompt.subfolder", "Into subfolder (empty: scan directory): "),
+    ("prompt.export", "Export {count} file(s) to (.zip or .md): "),
+    ("prompt.overwrite", "{name} already exists. Overwrite it? [y/n]"),
+    ("prompt.cooldown", "Needs {needed} message(s), {left} left under the chat limit (ready in {secs}s) — send anyway? [y/n]"),
+    ("prompt.resend", "Sent {ago} ago ({count} line(s) delivered) — send again? [y/n]"),
+    ("prompt.resume", "Resume {name} from line {line}? (n: send it all) [y/n]"),
+    ("prompt.send_anyway", "{problem}. Send anyway? [y/n]"),
+    ("prompt.game_time", "Game time now (MM:SS, empty: 00:00): "),
+    ("prompt.changed", "File changed on disk: {summary}"),
+    ("prompt.changed_line", "; line {line}: {change}"),
+    ("prompt.new_or_original", "Send the [N]ew version or the [O]riginal one? (Esc: cancel)"),
+    ("prompt.chat", "Send to [A]ll chat or [T]eam chat? (Enter: all, Esc: cancel)"),
+    ("prompt.say", "Say: "),
+    ("prompt.collision", "{name} already exists: [O]verwrite [R]ename [S]kip"),
+    ("prompt.minimized", "⚠ '{title}' is minimized — restore and continue? [y/n] "),
+    ("prompt.go_to_line", "Go to line: "),
+    ("prompt.edit", "Edit: "),
+    ("prompt.save_changed", "The file changed on disk since it was opened. Save this line anyway? [y/n]"),
+    // Send options overlay
+    ("options.title", "Send {name}"),
+    ("options.hint", "[↑↓] Choose  [←→] Change  [Enter] Send  [Esc] Back"),
+    ("options.channel", "Channel"),
+    ("options.order", "Broadcast order"),
+    ("options.speed", "Speed"),
+    ("options.skip_repeats", "Skip repeated lines ({count})"),
+    ("options.manual", "Manual advance"),
+    ("options.dry_run", "Dry run"),
+    ("options.all", "All chat"),
+    ("options.team", "Team chat"),
+    ("options.per_line", "Line by line"),
+    ("options.per_file", "File by file"),
+    ("options.on", "On"),
+    ("options.off", "Off"),
+    // Sending
+    ("send.selected", ">>> Selected: {name}"),
+    ("send.target_override", ">>> Target window (set by the file): {windows}"),
+    ("send.lines", ">>> Sending {count} lines..."),
+    ("send.lines_excluded", ">>> Sending {count} lines ({excluded} excluded in the viewer)..."),
+    ("send.dry_run", ">>> Dry run: nothing is typed into the game"),
+    ("send.adhoc_command", ">>> Ad-hoc command: {text}"),
+    ("send.adhoc_all", ">>> Ad-hoc line to all chat: {text}"),
+    ("send.adhoc_team", ">>> Ad-hoc line to team chat: {text}"),
+    ("send.test", ">>> Test send: \"{text}\" to team chat"),
+    ("send.random", ">>> Random line {line} of {name}: {text}"),
+    ("send.manual_keys", "Manual advance: [Space] sends the next line, [S] skips it, [Esc] stops."),
+    ("send.cancel_keys", "Press [Esc] to cancel at any time, [+]/[-] to change the typing speed."),
+    ("send.paste_keys", "Press [T] if pasted lines don't show up in chat, to type them instead."),
+    ("send.press_key", "Press any key to return to file selection."),
+    ("send.release", "⚠ Release {keys} to continue..."),
+    ("send.reloaded", "⚙ {notice}; new delays apply from this line"),
+    ("send.cancelled", "⚠ Cancelled by user."),
+    ("send.cancelling", "⏹ CANCELLING… the line in progress finishes first"),
+    ("send.cancelled_by", "⚠ Cancelled by {requester}."),
+    ("send.sending", "Sending: {text}"),
+    ("send.left", "({speed}, ~{left} left)"),
+    ("send.failed_at", "❌ Failed at line {line}: {text}"),
+    ("send.error", "   Error: {error}"),
+    ("send.no_more", "   No more lines will be sent to '{target}'."),
+    ("send.remaining", "   {count} remaining line(s) were not sent."),
+    ("send.stopping", "Stopping. Make sure '{target}' is open."),
+    ("send.waiting", "Waiting for {at}: {text}"),
+    ("send.time_left", "(⏱ {left} left)"),
+    ("send.window_lost", "⚠ window lost — waiting ({secs}s)… [Esc] cancel"),
+    ("send.next", "Next: {text}"),
+    ("send.advance_keys", "[Space] send  [S] skip  [Esc] stop"),
+    ("send.pasting", "📋 Pasting into '{target}' ({reason})"),
+    ("send.typing", "⌨ Typing into '{target}' ({reason})"),
+    ("send.posting", "✉ Posting to '{target}' ({reason})"),
+    ("send.trace", "   Input trace: {path}"),
+    ("send.recording", "   Session recording: {path}"),
+    ("send.paste_fallback", "⚠ Pasting didn't work in '{window}' ({reason}); it is typed into from now on. [F2] [P] forgets this."),
+    // Send summary
+    ("summary.done", "✅ Done! Sent {count} messages."),
+    ("summary.excluded_left_out", "   {count} excluded line(s) were left out."),
+    ("summary.retried", "   {count} dropped character(s) were retried."),
+    ("summary.title", "Summary:"),
+    ("summary.sent", "Sent:    {count}"),
+    ("summary.skipped", "Skipped: {count}"),
+    ("summary.excluded", "⊘ Excluded: {count}"),
+    ("summary.failed", "Failed:  {count}"),
+    ("summary.failed_line", "Failed line {line}: {text}"),
+    ("summary.window_lost", "Window lost at line {line}: {text}"),
+    ("summary.retried_chars", "  Retried characters: {count}"),
+    // Help screen
+    ("help.title", "Help"),
+    ("help.navigate", "Navigate file list"),
+    ("help.columns", "Move between list columns (wide terminals), or collapse and expand folders"),
+    ("help.send", "Send file contents to chat"),
+    ("help.adhoc", "Type and send one line that isn't in a file (↑ recalls earlier ones)"),
+    ("help.view", "Preview file contents ([i] there: edit the line, [d] don't send it this session, [g] go to a line, [f] preview the character filter)"),
+    ("help.help", "This help screen"),
+    ("help.settings", "Settings ([T] there: test send to team chat, [P]: forget paste fallbacks)"),
+    ("help.tag", "Cycle tag filter (tags come from tags.toml)"),
+    ("help.import", "Import a message pack (.zip) into the scan directory"),
+    ("help.mark", "Mark or unmark a file for export or comparing"),
+    ("help.compare", "Compare the two marked files side by side"),
+    ("help.random", "Send one line of the selected file picked at random ({w=N} weights it)"),
+    ("help.arm", "Arm or disarm sending (when require_arming is on)"),
+    ("help.hide_warning", "Don't show the warning on the status line again"),
+    ("help.export", "Export the marked (or selected) files as a .zip or .md pack"),
+    ("help.duplicate", "Jump to the next file with the same messages (⧉ duplicate of ...)"),
+    ("help.manual", "Send the selected file one line per keypress (✋ manual)"),
+    ("help.stats", "Usage statistics"),
+    ("help.skipped", "Files skipped by the last scan (binary or unreadable)"),
+    ("help.refresh", "Refresh file list"),
+    ("help.search", "Focus the search box (or click it)"),
+    ("help.type_key", "Type"),
+    ("help.type", "Filter files by name, or by tag with tag:NAME"),
+    ("help.esc", "Leave the search box / quit / cancel sending"),
+    ("about.title", "About"),
+    ("about.version", "Version"),
+    ("about.commit", "Commit"),
+    ("about.built", "Built"),
+    ("about.config", "Config"),
+    ("about.scan_dir", "Scan dir"),
+    ("about.extensions", "Extensions"),
+    ("about.data_dir", "Data dir"),
+    ("about.log", "Log"),
+    ("about.target", "Target"),
+    ("about.profile", "Game profile"),
+    ("about.defaults", "built-in defaults (no {file})"),
+    ("about.unavailable", "unavailable ({error})"),
+    ("about.off", "off"),
+    ("about.terminal", "Terminal"),
+    ("terminal.full", "full"),
+    ("terminal.no_colors", "full, no colors"),
+    ("terminal.plain", "plain ASCII, {width}x{height} layout ({problems})"),
+    ("terminal.fixed", "{width}x{height} layout ({problems})"),
+    ("terminal.dumb", "TERM=dumb"),
+    ("terminal.no_answer", "no answer to terminal queries"),
+    ("terminal.no_ansi", "legacy console without ANSI support"),
+    ("terminal.wine", "Wine"),
+    ("terminal.mintty", "mintty"),
+    ("terminal.bad_size", "no usable size reported"),
+    // Settings, statistics and skipped files
+    ("settings.title", "Settings"),
+    ("settings.selected_file", "Selected file: {risk}"),
+    ("settings.no_fallbacks", "No window has fallen back from pasting to typing."),
+    ("settings.forgot_fallbacks", "Forgot {count} window(s) that ignored pasting; the next send pastes again."),
+    ("settings.no_focus_delays", "No focus delays learned; every window took focus in time."),
+    ("settings.focus_delays", "Learned focus delays: {list}. [L] again forgets them."),
+    ("settings.forgot_focus_delays", "Forgot the learned focus delays of {count} window(s)."),
+    ("stats.title", "Statistics"),
+    ("stats.session", "Session"),
+    ("stats.lifetime", "Lifetime"),
+    ("stats.not_saved", "Lifetime stats are not saved between runs."),
+    ("hidden.title", "Hidden warnings"),
+    ("hidden.none", "No warnings are hidden. Press [Del] while a warning is shown to hide it."),
+    ("hidden.shown_again", "It will be shown again the next time it comes up."),
+    ("skipped.title", "Skipped files"),
+    ("skipped.none", "No files were skipped by the last scan."),
+    ("skipped.more", "… and {count} more (see the log)"),
+    // Viewer
+    ("viewer.title", "Viewing: {name} — {loaded}"),
+    ("compare.title", "Comparing: {old} ↔ {new}"),
+    ("compare.rows", "Rows {first}-{last} of {total}"),
+    ("viewer.command", "[cmd]"),
+    ("viewer.team", "[team]"),
+    ("viewer.truncated", "[truncated]"),
+    ("viewer.skipped", "[skipped]"),
+    ("viewer.too_long", "[over {max}]"),
+    ("viewer.split", "[{parts} msgs]"),
+    ("viewer.lines", "Lines {first}-{last} of {total}"),
+    ("viewer.excluded", ", {count} excluded"),
+    ("viewer.reloaded", "reloaded"),
+    ("viewer.exclusions_cleared", "; excluded lines cleared"),
+    ("viewer.reload_failed", "reload failed (file empty or unreadable)"),
+    ("viewer.not_a_line", "'{input}' is not a line number"),
+    ("viewer.not_saved", "edit not saved"),
+    ("viewer.saved", "line {line} saved"),
+    ("viewer.removed", "✗ This file was removed from disk."),
+    ("viewer.any_key", "Press any key to return to the file list"),
+    // Terminal checks
+    ("tty.stdin", "stdin is not a terminal"),
+    ("tty.stdout", "stdout is not a terminal"),
+    ("tty.both", "stdin and stdout are not terminals"),
+    // Startup and exit
+    ("main.migrate_data", "The log and state go to {path}."),
+    ("main.migrate", "Message files are next to the executable ({from}).\nMove them to {to} and read them from there from now on? [y/N] "),
+    ("main.moved", "Moved {count} file(s); what went where is in {path}."),
+    ("main.migrate_kept", "{count} file(s) left in place, as a different file of that name is already there (see the report)."),
+    ("main.report_written", "Bug report written to {path}"),
+    ("main.report_attach", "Attach it to your issue; it contains window titles, so look it over first."),
+    ("main.config_warnings", "Config: {count} warning(s), first: {first}"),
+    ("main.session", "This session: {summary}"),
+    ("main.no_terminal", "MadTyping needs an interactive terminal for its UI ({problem})."),
+    ("main.no_terminal_hint", "Run it from a console window, or use {flags} instead (see --help)."),
+    ("main.goodbye", "MadTyping exited. Goodbye!"),
+    ("main.error", "❌ Error: {error}"),
+    ("main.make_sure", "Make sure:"),
+    ("main.check_files", "  1. There are .txt, .md or .csv files next to the executable (or in --dir)"),
+    ("main.check_lines", "  2. The files contain non-empty lines"),
+    ("main.check_permissions", "  3. You have proper permissions to read the files"),
+];
+
+/// Translated text by message id, set once at startup.
+static TABLE: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+
+/// The text for message `id`: the loaded translation, else English, else
+/// the id itself.
+pub fn text(id: &'static str) -> &'static str {
+    if let Some(translated) = TABLE.get().and_then(|table| table.get(id)) {
+        return translated;
+    }
+    english(id).unwrap_or(id)
+}
+
+/// [`text`] with each `{name}` replaced by the value given for `name`.
+/// Placeholders without a value are left as they are.
+pub fn format(id: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    fill(text(id), args)
+}
+
+/// Built-in English text for `id`.
+fn english(id: &str) -> Option<&'static str> {
+    ENGLISH.iter().find(|(key, _)| *key == id).map(|(_, text)| *text)
+}
+
+/// Replace `{name}` placeholders in `template` in one pass, so values
+/// that contain braces are left alone.
+fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| args.iter().find(|(name, _)| *name == &after[..end]).map(|(_, value)| (end, value)));
+        match value {
+            Some((end, value)) => {
+                out.push_str(&value.to_string());
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Names of the `{placeholders}` in `text`.
+fn placeholders(text: &str) -> BTreeSet<&str> {
+    let mut names = BTreeSet::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        if let Some(end) = rest.find('}') {
+            let name = &rest[..end];
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                names.insert(name);
+                rest = &rest[end + 1..];
+            }
+        }
+    }
+    names
+}
+
+/// The translation to load: `configured` if set, else the language part
+/// of `lang_env` (`de_DE.UTF-8` → `de`). None for English.
+pub fn language_code(configured: &str, lang_env: Option<&str>) -> Option<String> {
+    let value = match configured.trim() {
+        "" => lang_env?.trim(),
+        configured => configured,
+    };
+    let code = value.split(['.', '@', '_', '-']).next().unwrap_or_default().to_ascii_lowercase();
+    match code.as_str() {
+        "" | "c" | "posix" | "en" => None,
+        _ => Some(code),
+    }
+}
+
+/// Parse a translation file: `id = "text"` lines with `#` comments and
+/// `\"`, `\\` and `\n` escapes. Returns the usable translations and a
+/// warning for each line that was ignored.
+pub fn parse(content: &str) -> (HashMap<&'static str, String>, Vec<String>) {
+    let mut table = HashMap::new();
+    let mut warnings = Vec::new();
+    for (number, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((id, text)) = line.split_once('=').and_then(|(id, value)| Some((id.trim(), unescape(value.trim())?)))
+        else {
+            warnings.push(format!("line {}: expected `id = \"text\"`, ignored", number + 1));
+            continue;
+        };
+        let Some(&(id, english)) = ENGLISH.iter().find(|(key, _)| *key == id) else {
+            warnings.push(format!("unknown message id '{}' on line {}, ignored", id, number + 1));
+            continue;
+        };
+        let expected = placeholders(english);
+        if placeholders(&text) != expected {
+            let names: Vec<String> = expected.iter().map(|name| format!("{{{}}}", name)).collect();
+            let names = if names.is_empty() { "no placeholders".to_string() } else { names.join(", ") };
+            warnings.push(format!("'{}' on line {} must use {}, using English", id, number + 1, names));
+            continue;
+        }
+        table.insert(id, text);
+    }
+    (table, warnings)
+}
+
+/// Contents of a `"quoted"` value with its escapes resolved, or None if it
+/// isn't quoted. A `#` after the closing quote starts a comment.
+fn unescape(value: &str) -> Option<String> {
+    let mut chars = value.strip_prefix('"')?.chars();
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => match chars.next()? {
+                'n' => text.push('\n'),
+                other => text.push(other),
+            },
+            c => text.push(c),
+        }
+    }
+    let rest = chars.as_str().trim();
+    (rest.is_empty() || rest.starts_with('#')).then_some(text)
+}
+
+/// Load the translation picked by `configured` (the `language` setting) or
+/// `LANG`. Returns warnings to show with the config warnings; a missing
+/// file is only reported when the language was configured.
+pub fn init(configured: &str) -> Vec<String> {
+    let Some(code) = language_code(configured, std::env::var("LANG").ok().as_deref()) else {
+        return Vec::new();
+    };
+    if !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return vec![format!("language '{}' is not a language code like de, using English", code)];
+    }
+    let path = match paths::exe_directory() {
+        Ok(dir) => dir.join(LANGUAGE_DIR).join(format!("{}.toml", code)),
+        Err(e) => return vec![e],
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            log(&format!("No translation at {}: {}", path.display(), e));
+            return if configured.trim().is_empty() {
+                Vec::new()
+            } else {
+                vec![format!("can't read {} ({}), using English", path.display(), e)]
+            };
+        }
+    };
+    let (table, warnings) = parse(&content);
+    log(&format!("Loaded {} of {} message(s) from {}", table.len(), ENGLISH.len(), path.display()));
+    let table = table.into_iter().map(|(id, text)| (id, &*Box::leak(text.into_boxed_str()))).collect();
+    if TABLE.set(table).is_err() {
+        log("Translation already loaded, ignoring the second one");
+    }
+    let file = format!("{}/{}.toml", LANGUAGE_DIR, code);
+    warnings.into_iter().map(|w| format!("{}: {}", file, w)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_ids_are_unique() {
+        let ids: BTreeSet<&str> = ENGLISH.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids.len(), ENGLISH.len());
+    }
+
+    #[test]
+    fn test_fill_replaces_known_placeholders() {
+        assert_eq!(fill("Sent {count} of {total}", &[("count", &3), ("total", &"7")]), "Sent 3 of 7");
+        assert_eq!(fill("{name} {missing} {", &[("name", &"{count}")]), "{count} {missing} {");
+        assert_eq!(format("hint.quit", &[]), "[Esc] Quit");
+        assert_eq!(text("no.such.id"), "no.such.id");
+    }
+
+    #[test]
+    fn test_parse_checks_placeholders() {
+        let (table, warnings) = parse(concat!(
+            "# German\n",
+            "hint.quit = \"[Esc] Beenden\"  # short\n",
+            "list.lines = \"({count} Zeilen)\"\n",
+            "list.commands = \"/ Befehle\"\n",
+            "summary.done = \"Fertig! {total} gesendet\"\n",
+            "main.migrate = \"Kopieren \\\"{from}\\\"\\nnach {to}?\"\n",
+            "nope.id = \"x\"\n",
+            "help.title = Hilfe\n",
+        ));
+        assert_eq!(table.get("hint.quit").map(String::as_str), Some("[Esc] Beenden"));
+        assert_eq!(table.get("list.lines").map(String::as_str), Some("({count} Zeilen)"));
+        assert_eq!(table.get("main.migrate").map(String::as_str), Some("Kopieren \"{from}\"\nnach {to}?"));
+        assert_eq!(table.len(), 3);
+        assert_eq!(warnings, vec![
+            "'list.commands' on line 4 must use {count}, using English",
+            "'summary.done' on line 5 must use {count}, using English",
+            "unknown message id 'nope.id' on line 7, ignored",
+            "line 8: expected `id = \"text\"`, ignored",
+        ]);
+    }
+
+    #[test]
+    fn test_language_code_from_config_or_lang() {
+        assert_eq!(language_code("", Some("de_DE.UTF-8")).as_deref(), Some("de"));
+        assert_eq!(language_code("FR", Some("de_DE.UTF-8")).as_deref(), Some("fr"));
+        assert_eq!(language_code("", Some("C")), None);
+        assert_eq!(language_code("", Some("en_US.UTF-8")), None);
+        assert_eq!(language_code("", None), None);
+        assert_eq!(language_code("pt-BR", None).as_deref(), Some("pt"));
+    }
+}
@@ -7,24 +7,155 @@
 //!
 //! The crate is organized into the following modules:
 //!
+//! - [`abbreviations`] - Expanding `~name` tokens from the `[abbreviations]` table
+//! - [`accessible`] - Line-oriented frontend for screen readers (`--accessible`)
+//! - [`archive`] - Moving fully sent one-shot files to `archive/`
+//! - [`args`] - Command-line argument parsing
+//! - [`arming`] - Refusing sends until the tool is armed (`require_arming`)
+//! - [`build_info`] - Version and build information
+//! - [`charfilter`] - Cleaning characters the game chat can't show
+//! - [`completions`] - Shell completion script generation
 //! - [`config`] - Centralized configuration constants
+//! - [`config_file`] - Config file loading and validation
+//! - [`cooldown`] - Chat limit budget per window and the wait before the next send
+//! - [`doctor`] - Environment diagnostics (`--doctor`)
+//! - [`edit`] - Writing viewer edits back to message files
 //! - [`logging`] - Simple file-based logging utilities
 //! - [`files`] - Text file discovery and management
+//! - [`focus_quirks`] - Extra focus delays learned per window title
+//! - [`front_matter`] - Per-file settings from a leading `---` block
+//! - [`headless`] - `--list` / `--send` without the terminal UI
+//! - [`health`] - Checking files for problems before they are sent
+//! - [`hooks`] - User commands run before and after each send
+//! - [`journal`] - Line-by-line send journal for resuming interrupted sends
+//! - [`json`] - Minimal JSON writer and parser for MadTyping's own files
+//! - [`lang`] - Translated UI text by message id
+//! - [`migration`] - Moving a legacy install next to the executable into the user folders
+//! - [`outcome`] - Machine-readable send results (`--json-result`)
+//! - [`pack`] - Importing and exporting message packs
+//! - [`paste`] - Windows that fell back from pasting to typing
+//! - [`paths`] - Where the log and state files go
+//! - [`persistence`] - Small key/value state kept between runs
 //! - [`platform`] - Platform-specific input simulation (Windows)
+//! - [`progress`] - How much `--send` prints while it goes (`--progress`)
+//! - [`replay`] - Recorded send sessions and `--replay`
+//! - [`report`] - Pre-send snapshots and `--report` bug report files
+//! - [`send`] - Send pipeline (lines → target windows) with progress events
+//! - [`shutdown`] - Stopping sends and background threads when the console closes
+//! - [`startup`] - First frame before disk access, and startup timings
+//! - [`stats`] - Session and lifetime usage statistics
+//! - [`tags`] - File tags from `tags.toml`
+//! - [`template`] - `{?morning:…}` time-of-day branches and `{dow}` in lines
+//! - [`timed`] - `!at MM:SS` lines held back until a send clock time
+//! - [`titles`] - "Did you mean" suggestions for window titles
+//! - [`trace`] - Per-send keystroke traces (`--trace-input`)
+//! - [`warnings`] - Showing recurring warnings once, and hiding them for good
+//! - [`watch`] - Picking up message file changes while the list is shown
+//! - [`weights`] - `{w=N}` line weights and weighted random picks
+//! - [`worker`] - Background thread for file scans and change checks
+//! - [`zip`] - Minimal zip reader and writer for message packs
 //! - [`app`] - Application state management
 //! - [`ui`] - Terminal UI rendering and event handling
+//! - [`prelude`] - The stable surface for embedding, in one import
+//!
+//! # Example
+//!
+//! Drive a send through the real typing sequence against the mock
+//! keyboard backend:
+//!
+//! ```
+//! use mad_typing::prelude::*;
+//!
+//! let lines = vec![Line::new("gl hf"), Line::new("ward dragon")];
+//! let mut sender = BackendSender::new(MockBackend::new());
+//! let report = SendSession::new(&lines, vec![DEFAULT_WINDOW_TITLE.to_string()], BroadcastMode::PerLine)
+//!     .with_delays(SendDelays::from(&Config::default()))
+//!     .run(&mut sender, |event| {
+//!         if let SendEvent::LineFinished { index, result, .. } = event {
+//!             assert!(result.is_ok(), "line {} failed", index + 1);
+//!         }
+//!         Control::Continue
+//!     })
+//!     .unwrap();
+//!
+//! assert_eq!(report.sent(), 2);
+//! assert_eq!(sender.backend.typed_text(), "gl hfward dragon");
+//! ```
 
+pub mod abbreviations;
+pub mod accessible;
+pub mod archive;
+pub mod args;
+pub mod arming;
+pub mod build_info;
+pub mod charfilter;
+pub mod completions;
 pub mod config;
+pub mod config_file;
+pub mod cooldown;
+pub mod doctor;
+pub mod edit;
 pub mod logging;
 pub mod files;
+pub mod focus_quirks;
+pub mod front_matter;
+pub mod headless;
+pub mod health;
+pub mod hooks;
+pub mod journal;
+pub mod json;
+pub mod lang;
+pub mod migration;
+pub mod outcome;
+pub mod pack;
+pub mod paste;
+pub mod paths;
+pub mod persistence;
 pub mod platform;
+pub mod progress;
+pub mod replay;
+pub mod report;
+pub mod send;
+pub mod shutdown;
+pub mod startup;
+pub mod stats;
+pub mod tags;
+pub mod template;
+pub mod timed;
+pub mod titles;
+pub mod trace;
+pub mod warnings;
+pub mod watch;
+pub mod weights;
+pub mod worker;
+pub mod zip;
 pub mod app;
 pub mod ui;
 
 // Re-export commonly used items for convenience
 pub use app::App;
-pub use config::{DEFAULT_HEADER_NAME, DEFAULT_WINDOW_TITLE};
-pub use files::{discover as discover_files, TextFile};
+pub use args::Args;
+pub use config::{Config, DEFAULT_HEADER_NAME, DEFAULT_WINDOW_TITLE};
+pub use files::{discover as discover_files, Line, TextFile};
 pub use logging::{init as init_logging, log};
-pub use platform::{focus_window, is_window_running, send_text};
+pub use platform::{capture_console_window, focus_window, is_window_focused, is_window_minimized, is_window_running, send_text};
 pub use ui::Cli;
+
+/// Everything needed to discover files and send them, in one import.
+///
+/// Items here are kept stable; reach into the modules for the rest.
+pub mod prelude {
+    pub use crate::app::App;
+    pub use crate::config::{Config, DEFAULT_WINDOW_TITLE, SUPPORTED_EXTENSIONS};
+    pub use crate::config_file::{load as load_config, LoadedConfig};
+    pub use crate::files::{discover as discover_files, scan_directory, Line, TextFile};
+    pub use crate::platform::{
+        focus_window, is_window_focused, is_window_running, send_line, send_line_with, send_text, CancelToken, ChatScope,
+        InputBackend, KeyDelays, KeyInput, LineOptions, LineReport, MockBackend, PlatformError, Speed, TargetMatcher,
+        TypeStats, TypingOptions,
+    };
+    pub use crate::send::{
+        cancel_phase, cancel_running_send, BackendSender, BroadcastMode, BusyPolicy, CancelPhase, Control, LineSender,
+        PlatformSender, SendDelays, SendEvent, SendReport, SendSession,
+    };
+}
@@ -3,8 +3,9 @@
 //! Provides simple file-based logging for debugging purposes.
 //! Logging can be enabled/disabled via config::LOG_ENABLED.
 
+#[cfg(test)]
+use std::cell::RefCell;
 use std::{
-    env,
     fs::{self, OpenOptions},
     io::Write,
     path::PathBuf,
@@ -12,31 +13,57 @@ use std::{
     time::SystemTime,
 };
 
-use crate::config::LOG_ENABLED;
+use crate::build_info::{version_string, BUILD_DATE, GIT_HASH, VERSION};
+use crate::config::{LOG_DEBUG, LOG_ENABLED};
+use crate::paths;
+
+/// File name of the log, stored in the data directory.
+pub const LOG_FILE_NAME: &str = "madtyping.log";
 
 /// Global log file path
 static LOG_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
 
-/// Initialize the log file in the same directory as the executable.
-/// Creates a new log file, overwriting any existing one.
+/// Initialize the log file in the data directory, see [`paths`].
+/// Overwrites any existing log. The first line holds the build
+/// information, so bug reports identify the exact build.
 pub fn init() {
     if !LOG_ENABLED {
         return;
     }
     
-    if let Ok(exe_path) = env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            let log_path = exe_dir.join("madtyping.log");
-            // Clear old log
-            let _ = fs::write(&log_path, "=== MadTyping Log Started ===\n");
-            *LOG_FILE.lock().unwrap() = Some(log_path);
-        }
+    if let Ok(log_path) = paths::data_file(LOG_FILE_NAME) {
+        // Clear old log
+        let header = format!(
+            "=== MadTyping Log Started ===\n{}\nversion={} commit={} built={}\n",
+            version_string(),
+            VERSION,
+            GIT_HASH,
+            BUILD_DATE
+        );
+        let _ = fs::write(&log_path, header);
+        *LOG_FILE.lock().unwrap() = Some(log_path);
     }
 }
 
+/// Keep logging to `path`, e.g. after the log was moved there.
+pub fn set_log_path(path: PathBuf) {
+    *LOG_FILE.lock().unwrap() = Some(path);
+}
+
+/// Path of the log file, once [`init`] has set it up.
+pub fn log_path() -> Option<PathBuf> {
+    LOG_FILE.lock().unwrap().clone()
+}
+
 /// Write a message to the log file with a timestamp.
 /// Does nothing if logging is disabled.
 pub fn log(message: &str) {
+    #[cfg(test)]
+    CAPTURED.with(|captured| {
+        if let Some(messages) = captured.borrow_mut().as_mut() {
+            messages.push(message.to_string());
+        }
+    });
     if !LOG_ENABLED {
         return;
     }
@@ -51,6 +78,28 @@ pub fn log(message: &str) {
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    /// Messages logged on this thread while [`capture`] runs
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Run `f` and return what it logged on this thread, for tests.
+#[cfg(test)]
+pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+    let result = f();
+    let messages = CAPTURED.with(|captured| captured.borrow_mut().take()).unwrap_or_default();
+    (result, messages)
+}
+
+/// Write a message to the log only when debug logging is on.
+pub fn debug(message: &str) {
+    if LOG_DEBUG {
+        log(message);
+    }
+}
+
 /// Generate a simple HH:MM:SS timestamp without external crates.
 fn timestamp() -> String {
     match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
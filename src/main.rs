@@ -4,30 +4,255 @@
 //! and types their contents into the game chat.
 
 use mad_typing::{
-    App, Cli, 
-    discover_files, init_logging, log,
-    DEFAULT_HEADER_NAME, DEFAULT_WINDOW_TITLE,
+    App, Args, Cli, 
+    capture_console_window, init_logging, log,
+    DEFAULT_WINDOW_TITLE,
 };
+use mad_typing::args::usage;
+use mad_typing::files::{
+    choose_scan_directory, discover_with, parse_extensions, set_scan_directory,
+    set_supported_extensions, ScanDirectory, ScanOptions, ScanSource, MIGRATION_STATE_KEY,
+};
+use mad_typing::build_info::version_string;
+use mad_typing::accessible::Accessible;
+use mad_typing::completions;
+use mad_typing::outcome::ErrorKind;
+use mad_typing::send::PlatformSender;
+use mad_typing::abbreviations::{self, Abbreviations};
+use mad_typing::{config_file, doctor, headless, lang, logging, migration, paths, persistence, platform, report};
+use mad_typing::logging::LOG_FILE_NAME;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use mad_typing::ui::{terminal_problem, HEADLESS_FLAGS};
+
+/// Offer once to move what an older version kept next to the executable:
+/// message files to the user directory, the log and state to the per-user
+/// data directory (see [`migration`]).
+fn offer_migration(scan_dir: &ScanDirectory, user_dir: Option<PathBuf>) {
+    let mut state = persistence::load();
+    if !matches!(scan_dir.source, ScanSource::Exe | ScanSource::User)
+        || matches!(state.get(MIGRATION_STATE_KEY), Some("moved" | "declined"))
+    {
+        return;
+    }
+    let (Ok(exe_dir), Some(user_dir)) = (paths::exe_directory(), user_dir) else {
+        return;
+    };
+    if !paths::is_writable(&exe_dir) {
+        return;
+    }
+    let data_dir = paths::user_data_directory();
+    let plan = match migration::plan(&exe_dir, Some(&user_dir), data_dir.as_deref()) {
+        Ok(plan) => plan,
+        Err(e) => {
+            log(&format!("Migration check failed: {}", e));
+            return;
+        }
+    };
+    // Only message files make it a legacy install; new ones log there too
+    if plan.messages.is_empty() {
+        return;
+    }
+
+    if let (false, Some(dir)) = (plan.data.is_empty(), &data_dir) {
+        println!("{}", lang::format("main.migrate_data", &[("path", &dir.display())]));
+    }
+    print!("{}", lang::format("main.migrate", &[("from", &exe_dir.display()), ("to", &user_dir.display())]));
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    let _ = io::stdin().read_line(&mut answer);
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        state.set(MIGRATION_STATE_KEY, "declined");
+        if let Err(e) = persistence::save(&state) {
+            log(&e);
+        }
+        return;
+    }
+
+    let done = match migration::migrate(&plan) {
+        Ok(done) => done,
+        Err(e) => {
+            log(&format!("Migration failed: {}", e));
+            eprintln!("❌ {}", e);
+            return;
+        }
+    };
+    // The log and state are read from their new place from now on
+    paths::set_data_directory(paths::choose_data_directory(&exe_dir, data_dir));
+    if let Ok(path) = paths::data_file(LOG_FILE_NAME) {
+        logging::set_log_path(path);
+    }
+    log(&format!("Migration: moved {} file(s), kept {}", done.moved.len(), done.kept.len()));
+    let report = paths::data_directory().and_then(|dir| done.write_report(&plan, &dir.path));
+    match &report {
+        Ok(path) => println!("{}", lang::format("main.moved", &[("count", &done.moved.len()), ("path", &path.display())])),
+        Err(e) => log(e),
+    }
+    if !done.kept.is_empty() {
+        println!("{}", lang::format("main.migrate_kept", &[("count", &done.kept.len())]));
+    }
+    set_scan_directory(ScanDirectory { path: user_dir, source: ScanSource::User });
+
+    let mut state = persistence::load();
+    state.set(MIGRATION_STATE_KEY, "moved");
+    if let Err(e) = persistence::save(&state) {
+        log(&e);
+    }
+}
 
 /// Run the application.
-fn run_app() -> Result<(), String> {
+fn run_app(args: Args) -> Result<(), String> {
+    let began = Instant::now();
+    if args.version {
+        println!("{}", version_string());
+        return Ok(());
+    }
+    if let Some(shell) = args.completions {
+        print!("{}", completions::generate(shell));
+        return Ok(());
+    }
+    if args.help {
+        println!("{}\n\n{}", version_string(), usage());
+        return Ok(());
+    }
+
+    // Before logging starts, which clears the previous session's log
+    if args.report {
+        let path = report::write_report()?;
+        println!("{}", lang::format("main.report_written", &[("path", &path.display())]));
+        println!("{}", lang::text("main.report_attach"));
+        return Ok(());
+    }
+
     init_logging();
     log("=== MadTyping Starting ===");
+    match paths::data_directory() {
+        Ok(dir) => log(&format!("Data directory: {}", dir.describe())),
+        Err(e) => log(&format!("No data directory: {}", e)),
+    }
+    capture_console_window();
+    platform::install_close_handler();
+
+    let mut loaded = config_file::load();
+    let language_warnings = lang::init(&loaded.config.language);
+    loaded.warnings.extend(language_warnings);
+    if args.read_only {
+        loaded.config.read_only = true;
+    }
+    if loaded.config.read_only {
+        log("Read-only mode: sending is disabled");
+    }
+    if args.trace_input {
+        loaded.config.trace_input = true;
+    }
+
+    let user_dir = loaded.config.user_message_dir();
+    let scan_dir = choose_scan_directory(
+        args.dir.as_deref().map(Path::new),
+        &loaded.config.message_dirs(),
+        &paths::exe_directory()?,
+        user_dir.as_deref(),
+        matches!(persistence::load().get(MIGRATION_STATE_KEY), Some("copied" | "moved")),
+    )?;
+    set_scan_directory(scan_dir.clone());
+    let extensions = if args.extensions.is_empty() {
+        loaded.config.extensions.clone()
+    } else {
+        args.extensions.join(";")
+    };
+    set_supported_extensions(parse_extensions(&extensions).0);
+    platform::set_window_class(&loaded.config.window_class);
+    abbreviations::set_abbreviations(Abbreviations::from(&loaded.config));
+
+    let window_titles = if args.windows.is_empty() {
+        vec![DEFAULT_WINDOW_TITLE.to_string()]
+    } else {
+        args.windows.clone()
+    };
+    log(&format!("Target window(s): {}", window_titles.join(", ")));
+
+    if args.doctor {
+        let mut checks = doctor::run_checks(&window_titles, &loaded);
+        if args.ping {
+            checks.extend(doctor::run_ping(&window_titles, &loaded));
+        }
+        if !doctor::print_report(&checks) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     
-    log("Scanning for .txt and .md files...");
-    println!("Scanning for .txt and .md files...");
-    
-    let files = discover_files()?;
-    log(&format!("Found {} files", files.len()));
-    println!("Found {} files.", files.len());
-
-    let mut cli = Cli::new(
-        DEFAULT_HEADER_NAME.to_string(),
-        DEFAULT_WINDOW_TITLE.to_string(),
-    );
+    if let Some(archive) = &args.import {
+        headless::import(archive, &loaded.config)?;
+        return Ok(());
+    }
+    if let (Some(pattern), Some(output)) = (&args.export, &args.output) {
+        headless::export(&discover_with(ScanOptions::from(&loaded.config))?, pattern, output)?;
+        return Ok(());
+    }
+    if args.list {
+        headless::list(&discover_with(ScanOptions::from(&loaded.config))?);
+        return Ok(());
+    }
+    if let Some(path) = &args.replay {
+        headless::replay(path, args.live, &loaded.config)?;
+        return Ok(());
+    }
+    if let Some(name) = &args.send {
+        let files = discover_with(ScanOptions::from(&loaded.config))?;
+        return match headless::send(&files, name, &window_titles, &loaded.config, args.send_choices(&loaded.config), args.json_result, args.progress) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind == ErrorKind::TooManyLines => {
+                eprintln!("❌ {}", e.message);
+                std::process::exit(headless::EXIT_TOO_MANY_LINES);
+            }
+            Err(e) => Err(e.message),
+        };
+    }
+
+    if args.accessible {
+        offer_migration(&scan_dir, user_dir);
+        let mut app = App::new(discover_with(ScanOptions::from(&loaded.config))?);
+        if let Some(first) = loaded.warnings.first() {
+            app.set_error(lang::format("main.config_warnings", &[("count", &loaded.warnings.len()), ("first", first)]));
+        }
+        let result = Accessible::new(io::stdin().lock(), io::stdout(), window_titles, loaded.config)
+            .run(&mut app, &mut PlatformSender);
+        let summary = app.session_stats().summary();
+        log(&format!("Session stats: {}", summary));
+        println!("{}", lang::format("main.session", &[("summary", &summary)]));
+        log("MadTyping exited");
+        return result;
+    }
+
+    // Raw mode fails with an opaque error when there is no real terminal
+    if let Some(problem) = terminal_problem() {
+        log(&format!("No interactive terminal: {}", problem));
+        eprintln!("{}", lang::format("main.no_terminal", &[("problem", &problem)]));
+        eprintln!("{}", lang::format("main.no_terminal_hint", &[("flags", &HEADLESS_FLAGS)]));
+        std::process::exit(2);
+    }
+
+    offer_migration(&scan_dir, user_dir);
+
+    let mut cli = Cli::new(lang::text("app.title").to_string(), window_titles)
+        .with_config(loaded.config.clone(), loaded.path.clone())
+        .with_start_time(began);
+    if let Some(name) = &args.view {
+        let files = discover_with(ScanOptions::from(&loaded.config))?;
+        let file = headless::find_file(&files, name).ok_or_else(|| headless::file_not_found(&files, name))?;
+        log(&format!("Opening '{}' in the viewer", file.name));
+        cli = cli.with_initial_view(file.clone(), args.view_only);
+    }
     
     log("Cli created, initializing...");
-    let mut app = App::new(files);
+    // Files are scanned in the background once the UI is up, and the
+    // lifetime stats are read after its first frame
+    let mut app = App::empty();
+    if let Some(first) = loaded.warnings.first() {
+        app.set_error(lang::format("main.config_warnings", &[("count", &loaded.warnings.len()), ("first", first)]));
+    }
     
     cli.init()?;
     log("Cli initialized, running main loop...");
@@ -35,18 +260,29 @@ fn run_app() -> Result<(), String> {
     let result = cli.run(&mut app);
     cli.cleanup()?;
 
+    let summary = app.session_stats().summary();
+    log(&format!("Session stats: {}", summary));
+    println!("{}", lang::format("main.session", &[("summary", &summary)]));
     log("MadTyping exited");
-    println!("MadTyping exited. Goodbye!");
+    println!("{}", lang::text("main.goodbye"));
     result
 }
 
 fn main() {
-    if let Err(e) = run_app() {
-        eprintln!("\n❌ Error: {}", e);
-        eprintln!("\nMake sure:");
-        eprintln!("  1. There are .txt or .md files in the same directory as the executable");
-        eprintln!("  2. The files contain non-empty lines");
-        eprintln!("  3. You have proper permissions to read the files");
+    let args = match Args::from_env() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run_app(args) {
+        eprintln!("\n{}", lang::format("main.error", &[("error", &e)]));
+        eprintln!("\n{}", lang::text("main.make_sure"));
+        eprintln!("{}", lang::text("main.check_files"));
+        eprintln!("{}", lang::text("main.check_lines"));
+        eprintln!("{}", lang::text("main.check_permissions"));
         std::process::exit(1);
     }
 }
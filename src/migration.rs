@@ -0,0 +1,304 @@
+//! Moving a legacy install into the per-user folders
+//!
+//! Older versions kept everything next to the executable: message files,
+//! `tags.toml`, archived files, the log, the state file and the send
+//! journal. Once on startup, MadTyping offers to move them: message files
+//! (with their subfolders) to `user_message_dir`, its own files to the
+//! per-user data directory (see [`crate::paths`]). It never moves anything
+//! without asking.
+//!
+//! A move copies every file first and only deletes the originals once all
+//! copies are written; if a copy fails, the copies made so far are removed
+//! and nothing has moved. A file already at the destination with the same
+//! contents counts as moved; one with other contents is left in place.
+//! The path in the journal of an interrupted send is rewritten, so the send
+//! can still be resumed. Running it again only finds what is left, so an
+//! install with nothing left to move is done. Every move ends with a
+//! report file in the data directory listing what went where.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::ARCHIVE_DIRECTORY;
+use crate::files::message_file_paths;
+use crate::journal::JOURNAL_FILE_NAME;
+use crate::logging::LOG_FILE_NAME;
+use crate::persistence::STATE_FILE_NAME;
+use crate::report::{file_stamp, now_secs, SNAPSHOT_FILE_NAME};
+
+/// MadTyping's own files moved to the data directory, the log last so it
+/// keeps as much of the run as it can.
+pub const DATA_FILE_NAMES: [&str; 4] = [STATE_FILE_NAME, JOURNAL_FILE_NAME, SNAPSHOT_FILE_NAME, LOG_FILE_NAME];
+
+/// One file to move.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// What is left to move out of a legacy directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// The executable's directory the files are in
+    pub legacy_dir: PathBuf,
+    /// Message files, `tags.toml` and archived files
+    pub messages: Vec<Move>,
+    /// Log, state, journal and snapshot
+    pub data: Vec<Move>,
+}
+
+impl Plan {
+    /// Whether everything was moved already.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.data.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len() + self.data.len()
+    }
+
+    fn moves(&self) -> impl Iterator<Item = &Move> {
+        self.messages.iter().chain(&self.data)
+    }
+}
+
+/// The files in `legacy_dir` to move: message files to `message_dir` and
+/// data files to `data_dir`. Either is left out when it is `legacy_dir`
+/// itself or `None`.
+pub fn plan(legacy_dir: &Path, message_dir: Option<&Path>, data_dir: Option<&Path>) -> Result<Plan, String> {
+    let mut plan = Plan { legacy_dir: legacy_dir.to_path_buf(), ..Plan::default() };
+    if let Some(message_dir) = message_dir.filter(|dir| *dir != legacy_dir) {
+        let mut paths = message_file_paths(legacy_dir)?;
+        if let Ok(entries) = fs::read_dir(legacy_dir.join(ARCHIVE_DIRECTORY)) {
+            let mut archived: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_file()).collect();
+            archived.sort();
+            paths.extend(archived);
+        }
+        for from in paths {
+            let to = message_dir.join(from.strip_prefix(legacy_dir).unwrap_or(&from));
+            plan.messages.push(Move { from, to });
+        }
+    }
+    if let Some(data_dir) = data_dir.filter(|dir| *dir != legacy_dir) {
+        for name in DATA_FILE_NAMES {
+            let from = legacy_dir.join(name);
+            if from.is_file() {
+                plan.data.push(Move { from, to: data_dir.join(name) });
+            }
+        }
+    }
+    Ok(plan)
+}
+
+/// How a move went.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Migration {
+    pub moved: Vec<Move>,
+    /// Files left in place, as a different file of that name is already at
+    /// the destination
+    pub kept: Vec<Move>,
+    /// Originals that were copied but couldn't be deleted, with the error
+    pub not_removed: Vec<(PathBuf, String)>,
+}
+
+impl Migration {
+    /// The report written after the move, started at `stamp`.
+    pub fn report(&self, plan: &Plan, stamp: &str) -> String {
+        let mut text = format!("MadTyping migration {}\nFrom: {}\n", stamp, plan.legacy_dir.display());
+        text.push_str(&format!("\nMoved {} file(s):\n", self.moved.len()));
+        for mv in &self.moved {
+            text.push_str(&format!("  {} -> {}\n", mv.from.display(), mv.to.display()));
+        }
+        if !self.kept.is_empty() {
+            text.push_str(&format!("\nLeft in place, a different file is already there ({}):\n", self.kept.len()));
+            for mv in &self.kept {
+                text.push_str(&format!("  {} (kept {})\n", mv.from.display(), mv.to.display()));
+            }
+        }
+        if !self.not_removed.is_empty() {
+            text.push_str(&format!("\nCopied but not removed ({}):\n", self.not_removed.len()));
+            for (path, e) in &self.not_removed {
+                text.push_str(&format!("  {}: {}\n", path.display(), e));
+            }
+        }
+        text
+    }
+
+    /// Write the report to `dir`, returning its path.
+    pub fn write_report(&self, plan: &Plan, dir: &Path) -> Result<PathBuf, String> {
+        let stamp = file_stamp(now_secs());
+        let path = dir.join(format!("madtyping-migration-{}.txt", stamp));
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        fs::write(&path, self.report(plan, &stamp)).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+/// `text` of a send journal with its source file path updated to where
+/// `moves` put it.
+pub fn rewrite_journal(text: &str, moves: &[Move]) -> String {
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let parts: Vec<&str> = first.splitn(4, ' ').collect();
+    match parts[..] {
+        ["start", started, total, source] => match moves.iter().find(|mv| mv.from == Path::new(source)) {
+            Some(mv) => {
+                let newline = if text.contains('\n') { "\n" } else { "" };
+                format!("start {} {} {}{}{}", started, total, mv.to.display(), newline, rest)
+            }
+            None => text.to_string(),
+        },
+        _ => text.to_string(),
+    }
+}
+
+/// The contents `mv` writes: the file as it is, except for the journal,
+/// which follows the files in `moved`.
+fn contents(mv: &Move, moved: &[Move]) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(&mv.from).map_err(|e| format!("Failed to read {}: {}", mv.from.display(), e))?;
+    if mv.from.file_name().is_some_and(|n| n == JOURNAL_FILE_NAME) {
+        return Ok(rewrite_journal(&String::from_utf8_lossy(&bytes), moved).into_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Move the files of `plan`: copy them all, then delete the originals.
+///
+/// Returns an error, with nothing moved, if a file can't be copied.
+pub fn migrate(plan: &Plan) -> Result<Migration, String> {
+    let mut migration = Migration::default();
+    let mut created = Vec::new();
+    for mv in plan.moves() {
+        if let Err(e) = copy(mv, &mut migration, &mut created) {
+            for path in &created {
+                let _ = fs::remove_file(path);
+            }
+            return Err(format!("{}; nothing was moved", e));
+        }
+    }
+
+    for mv in &migration.moved {
+        if let Err(e) = fs::remove_file(&mv.from) {
+            migration.not_removed.push((mv.from.clone(), e.to_string()));
+        }
+    }
+    // Subfolders emptied by the move go too
+    for mv in &plan.messages {
+        for dir in mv.from.ancestors().skip(1).take_while(|dir| *dir != plan.legacy_dir) {
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(migration)
+}
+
+/// Copy one file of a move, noting it in `migration` and the copies it
+/// made in `created`. Message files come first, so the journal only
+/// points at those that were really moved.
+fn copy(mv: &Move, migration: &mut Migration, created: &mut Vec<PathBuf>) -> Result<(), String> {
+    let bytes = contents(mv, &migration.moved)?;
+    if mv.to.exists() {
+        if fs::read(&mv.to).is_ok_and(|existing| existing == bytes) {
+            migration.moved.push(mv.clone());
+        } else {
+            migration.kept.push(mv.clone());
+        }
+        return Ok(());
+    }
+    if let Some(parent) = mv.to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&mv.to, &bytes).map_err(|e| format!("Failed to copy {}: {}", mv.from.display(), e))?;
+    created.push(mv.to.clone());
+    migration.moved.push(mv.clone());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An executable folder as older versions left it, with empty user
+    /// folders next to it.
+    fn legacy_layout(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("madtyping-migration-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (exe, messages, data) = (root.join("exe"), root.join("Documents"), root.join("AppData"));
+        fs::create_dir_all(exe.join("aram")).unwrap();
+        fs::create_dir_all(exe.join(ARCHIVE_DIRECTORY)).unwrap();
+        fs::write(exe.join("gg.txt"), "gg\n").unwrap();
+        fs::write(exe.join("aram").join("openers.txt"), "gl hf\n").unwrap();
+        fs::write(exe.join("tags.toml"), "\"gg.txt\" = [\"short\"]\n").unwrap();
+        fs::write(exe.join(ARCHIVE_DIRECTORY).join("finals-20240115-134510.txt"), "finals\n").unwrap();
+        fs::write(exe.join(STATE_FILE_NAME), "messages.migration=declined\n").unwrap();
+        fs::write(exe.join(LOG_FILE_NAME), "=== MadTyping Log Started ===\n").unwrap();
+        let journal = format!("start 1767225600 1 {}\nline 0 League\n", exe.join("aram").join("openers.txt").display());
+        fs::write(exe.join(JOURNAL_FILE_NAME), journal).unwrap();
+        fs::write(exe.join("mad_typing.exe"), "").unwrap();
+        (exe, messages, data)
+    }
+
+    #[test]
+    fn test_migration_moves_everything_once() {
+        let (exe, messages, data) = legacy_layout("full");
+        let plan = plan(&exe, Some(&messages), Some(&data)).unwrap();
+        assert_eq!((plan.messages.len(), plan.data.len()), (4, 3));
+
+        let migration = migrate(&plan).unwrap();
+        assert_eq!(migration.moved.len(), 7);
+        assert!(migration.kept.is_empty() && migration.not_removed.is_empty());
+        assert_eq!(fs::read_to_string(messages.join("aram").join("openers.txt")).unwrap(), "gl hf\n");
+        assert!(messages.join(ARCHIVE_DIRECTORY).join("finals-20240115-134510.txt").is_file());
+        assert!(messages.join("tags.toml").is_file() && data.join(STATE_FILE_NAME).is_file());
+        let journal = fs::read_to_string(data.join(JOURNAL_FILE_NAME)).unwrap();
+        assert_eq!(journal, format!("start 1767225600 1 {}\nline 0 League\n", messages.join("aram").join("openers.txt").display()));
+
+        // Only the executable is left, and a second run finds nothing
+        let left: Vec<_> = fs::read_dir(&exe).unwrap().flatten().map(|e| e.file_name()).collect();
+        assert_eq!(left, vec!["mad_typing.exe"]);
+        assert!(super::plan(&exe, Some(&messages), Some(&data)).unwrap().is_empty());
+
+        let report = migration.report(&plan, "20260101-000000");
+        assert!(report.starts_with(&format!("MadTyping migration 20260101-000000\nFrom: {}\n\nMoved 7 file(s):\n", exe.display())));
+        let path = migration.write_report(&plan, &data).unwrap();
+        assert!(path.starts_with(&data));
+        let _ = fs::remove_dir_all(exe.parent().unwrap());
+    }
+
+    #[test]
+    fn test_failed_copy_moves_nothing() {
+        let (exe, messages, data) = legacy_layout("abort");
+        let plan = plan(&exe, Some(&messages), Some(&data)).unwrap();
+        // A file where the data folder should be: every data copy fails
+        fs::write(&data, "").unwrap();
+        assert!(migrate(&plan).unwrap_err().ends_with("; nothing was moved"));
+        assert!(plan.moves().all(|mv| mv.from.is_file()));
+        assert!(!messages.join("gg.txt").exists());
+
+        // Fixed, it goes through; an edited copy already there stays
+        fs::remove_file(&data).unwrap();
+        fs::create_dir_all(&messages).unwrap();
+        fs::write(messages.join("gg.txt"), "gg wp\n").unwrap();
+        let journal = format!("start 1767225600 1 {}\n", exe.join("gg.txt").display());
+        fs::write(exe.join(JOURNAL_FILE_NAME), &journal).unwrap();
+        let migration = migrate(&plan).unwrap();
+        // The journal still points at the file left in place
+        assert_eq!(fs::read_to_string(data.join(JOURNAL_FILE_NAME)).unwrap(), journal);
+        assert_eq!(migration.kept, vec![plan.messages.iter().find(|mv| mv.to == messages.join("gg.txt")).unwrap().clone()]);
+        assert_eq!(fs::read_to_string(exe.join("gg.txt")).unwrap(), "gg\n");
+        assert_eq!(fs::read_to_string(messages.join("gg.txt")).unwrap(), "gg wp\n");
+        assert_eq!(super::plan(&exe, Some(&messages), Some(&data)).unwrap().len(), 1);
+        let _ = fs::remove_dir_all(exe.parent().unwrap());
+    }
+
+    #[test]
+    fn test_journal_path_rewritten() {
+        let moves = vec![Move { from: PathBuf::from("/games/mt/gg.txt"), to: PathBuf::from("/home/me/MadTyping/gg.txt") }];
+        assert_eq!(rewrite_journal("start 1 3 /games/mt/gg.txt\nend 3\n", &moves), "start 1 3 /home/me/MadTyping/gg.txt\nend 3\n");
+        assert_eq!(rewrite_journal("start 1 3 /games/mt/other.txt\n", &moves), "start 1 3 /games/mt/other.txt\n");
+        assert_eq!(rewrite_journal("", &moves), "");
+    }
+}
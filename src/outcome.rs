@@ -0,0 +1,252 @@
+//! Machine-readable send results for MadTyping
+//!
+//! [`SendOutcome`] collects what happened during a send, line by line, and
+//! renders it as one JSON object (`--send FILE --json-result`). It is the
+//! only place the result format is defined, so any future frontend that
+//! reports sends shares it.
+
+use std::time::Duration;
+
+use crate::json;
+use crate::send::{SendEvent, SendReport};
+
+/// What happened to one line for one target window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineStatus {
+    Sent,
+    /// Sent, but `verify_hook` didn't see it in chat
+    Unverified,
+    Failed,
+    /// Not sent because of an earlier failure or a cancel
+    Skipped,
+}
+
+impl LineStatus {
+    /// Name used in the JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineStatus::Sent => "sent",
+            LineStatus::Unverified => "unverified",
+            LineStatus::Failed => "failed",
+            LineStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Result for one line and target window.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineResult {
+    /// Zero-based line index
+    pub index: usize,
+    /// Window the line was meant for
+    pub target: String,
+    pub status: LineStatus,
+    /// Why the line failed
+    pub error: Option<String>,
+}
+
+/// Why a send stopped early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Read-only mode is on
+    ReadOnly,
+    /// No message file with the given name
+    FileNotFound,
+    /// None of the target windows is running
+    NotRunning,
+    /// Game guard found no in-game window and the send wasn't confirmed
+    NotInGame,
+    /// Keyboard state couldn't be fixed (Caps Lock, held keys)
+    Keyboard,
+    /// The session refused to start (another send running, line too long,
+    /// vetoed by `pre_send_hook`)
+    Refused,
+    /// A line failed to send
+    LineFailed,
+    /// A game window closed mid-send and didn't come back in time
+    WindowLost,
+    /// The send is over `max_lines_per_send`
+    TooManyLines,
+}
+
+impl ErrorKind {
+    /// Name used in the JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::ReadOnly => "read_only",
+            ErrorKind::FileNotFound => "file_not_found",
+            ErrorKind::NotRunning => "not_running",
+            ErrorKind::NotInGame => "not_in_game",
+            ErrorKind::Keyboard => "keyboard",
+            ErrorKind::Refused => "refused",
+            ErrorKind::LineFailed => "line_failed",
+            ErrorKind::WindowLost => "window_lost",
+            ErrorKind::TooManyLines => "too_many_lines",
+        }
+    }
+}
+
+/// An error together with its kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl SendError {
+    /// Create an error of the given kind.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+/// Everything that happened during one send.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SendOutcome {
+    /// Name the file was requested by
+    pub file: String,
+    /// Per line and target, in sending order (skipped lines last)
+    pub lines: Vec<LineResult>,
+    /// Wall-clock time of the whole send
+    pub duration: Duration,
+    /// Set if the send stopped early or a line failed
+    pub error: Option<SendError>,
+}
+
+impl SendOutcome {
+    /// Start collecting results for `file`.
+    pub fn new(file: impl Into<String>) -> Self {
+        Self { file: file.into(), ..Self::default() }
+    }
+
+    /// Record a send session event.
+    pub fn record(&mut self, event: &SendEvent) {
+        if let SendEvent::LineFinished { index, target, result, .. } = event {
+            let (status, error) = match result {
+                Ok(_) => (LineStatus::Sent, None),
+                Err(e) => (LineStatus::Failed, Some(e.clone())),
+            };
+            self.lines.push(LineResult { index: *index, target: target.to_string(), status, error });
+        }
+    }
+
+    /// Complete the results: lines `report` lists as unverified are marked
+    /// so, and every line of `total` not recorded for a target is added as
+    /// skipped.
+    pub fn finish(&mut self, report: Option<&SendReport>, total: usize, duration: Duration, error: Option<SendError>) {
+        for target in report.map(|r| r.targets.as_slice()).unwrap_or_default() {
+            for line in self.lines.iter_mut().filter(|l| l.target == target.window_title && target.unverified.contains(&l.index)) {
+                line.status = LineStatus::Unverified;
+            }
+            for index in 0..total {
+                let recorded = self.lines.iter().any(|l| l.index == index && l.target == target.window_title);
+                if !recorded {
+                    self.lines.push(LineResult {
+                        index,
+                        target: target.window_title.clone(),
+                        status: LineStatus::Skipped,
+                        error: None,
+                    });
+                }
+            }
+        }
+        self.duration = duration;
+        self.error = error;
+    }
+
+    /// Number of line results with `status`.
+    pub fn count(&self, status: LineStatus) -> usize {
+        self.lines.iter().filter(|l| l.status == status).count()
+    }
+
+    /// Render as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let lines: Vec<String> = self
+            .lines
+            .iter()
+            .map(|l| {
+                let error = l.error.as_deref().map(|e| format!(",\"error\":{}", json::string(e))).unwrap_or_default();
+                format!(
+                    "{{\"line\":{},\"target\":{},\"status\":\"{}\"{}}}",
+                    l.index + 1,
+                    json::string(&l.target),
+                    l.status.name(),
+                    error
+                )
+            })
+            .collect();
+        let error = match &self.error {
+            Some(e) => format!("{{\"type\":\"{}\",\"message\":{}}}", e.kind.name(), json::string(&e.message)),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"file\":{},\"ok\":{},\"sent\":{},\"unverified\":{},\"failed\":{},\"skipped\":{},\"duration_ms\":{},\"lines\":[{}],\"error\":{}}}",
+            json::string(&self.file),
+            self.error.is_none(),
+            self.count(LineStatus::Sent),
+            self.count(LineStatus::Unverified),
+            self.count(LineStatus::Failed),
+            self.count(LineStatus::Skipped),
+            self.duration.as_millis(),
+            lines.join(","),
+            error
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Line;
+    use crate::platform::{TypeStats, TypingOptions};
+    use crate::send::{BroadcastMode, Control, LineSender, SendSession};
+
+    struct FailOn(&'static str);
+
+    impl LineSender for FailOn {
+        fn send_line(&mut self, text: &str, _window_title: &str, _options: &TypingOptions) -> Result<TypeStats, String> {
+            if text == self.0 {
+                return Err("window closed".to_string());
+            }
+            Ok(TypeStats { chars_typed: text.chars().count(), ..TypeStats::default() })
+        }
+
+        fn sleep(&mut self, _ms: u64) {}
+    }
+
+    #[test]
+    fn test_json_format_is_stable() {
+        let lines = vec![Line::new("gl \"hf\""), Line::new("bad"), Line::new("never")];
+        let mut outcome = SendOutcome::new("gg.txt");
+        let report = SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+            .run(&mut FailOn("bad"), |event| {
+                outcome.record(event);
+                Control::Continue
+            })
+            .unwrap();
+        let error = SendError::new(ErrorKind::LineFailed, "1 target window(s) stopped after a failed line");
+        outcome.finish(Some(&report), lines.len(), Duration::from_millis(1234), Some(error));
+
+        assert_eq!(
+            outcome.to_json(),
+            concat!(
+                "{\"file\":\"gg.txt\",\"ok\":false,\"sent\":1,\"unverified\":0,\"failed\":1,\"skipped\":1,\"duration_ms\":1234,",
+                "\"lines\":[{\"line\":1,\"target\":\"game\",\"status\":\"sent\"},",
+                "{\"line\":2,\"target\":\"game\",\"status\":\"failed\",\"error\":\"window closed\"},",
+                "{\"line\":3,\"target\":\"game\",\"status\":\"skipped\"}],",
+                "\"error\":{\"type\":\"line_failed\",\"message\":\"1 target window(s) stopped after a failed line\"}}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_early_error_without_lines() {
+        let mut outcome = SendOutcome::new("missing");
+        outcome.finish(None, 0, Duration::ZERO, Some(SendError::new(ErrorKind::FileNotFound, "no file\tnamed")));
+        assert_eq!(
+            outcome.to_json(),
+            "{\"file\":\"missing\",\"ok\":false,\"sent\":0,\"unverified\":0,\"failed\":0,\"skipped\":0,\"duration_ms\":0,\"lines\":[],\"error\":{\"type\":\"file_not_found\",\"message\":\"no file\\tnamed\"}}"
+        );
+    }
+}
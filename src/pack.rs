@@ -0,0 +1,435 @@
+//! Message pack import and export for MadTyping
+//!
+//! Friends share message files as zip archives ("packs"). [`import`]
+//! extracts the supported files of a pack into a directory, refusing
+//! entries that would escape it and skipping everything that isn't a
+//! message file. What happens when a file already exists is up to the
+//! caller (overwrite, rename or skip). The tags of the pack's `tags.toml`
+//! are added to the scan directory's under the names the files got.
+//!
+//! [`export`] writes files the other way round, as a zip archive or as a
+//! single markdown document for pasting into a chat or forum.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::files::{is_supported_extension, TextFile};
+use crate::logging::log;
+use crate::tags::{self, TAGS_FILE_NAME};
+use crate::zip::{self, Entry};
+
+/// What to do with an entry whose target file already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Collision {
+    Overwrite,
+    /// Write it as `name (2).txt` (or the next free number)
+    Rename,
+    Skip,
+}
+
+/// What was done with the entries of a pack.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Files written, in archive order
+    pub imported: Vec<PathBuf>,
+    /// Entries that aren't message files
+    pub unsupported: Vec<String>,
+    /// Entries with absolute or `..` paths, refused
+    pub unsafe_paths: Vec<String>,
+    /// Entries skipped because the file already existed
+    pub skipped: Vec<String>,
+}
+
+impl ImportReport {
+    /// One-line summary, e.g. "3 file(s) imported, 1 unsupported skipped".
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} file(s) imported", self.imported.len());
+        if !self.skipped.is_empty() {
+            summary.push_str(&format!(", {} existing skipped", self.skipped.len()));
+        }
+        if !self.unsupported.is_empty() {
+            summary.push_str(&format!(", {} unsupported skipped", self.unsupported.len()));
+        }
+        if !self.unsafe_paths.is_empty() {
+            summary.push_str(&format!(", {} unsafe path(s) refused", self.unsafe_paths.len()));
+        }
+        summary
+    }
+}
+
+/// Turn a `/` or `\` separated relative path into a [`PathBuf`], or None if
+/// it is absolute, has a drive prefix or contains `..`.
+pub fn safe_relative_path(name: &str) -> Option<PathBuf> {
+    if name.starts_with(['/', '\\']) || name.contains(':') {
+        return None;
+    }
+    let mut path = PathBuf::new();
+    for part in name.split(['/', '\\']).filter(|p| !p.is_empty() && *p != ".") {
+        match Path::new(part).components().next() {
+            Some(Component::Normal(_)) => path.push(part),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+/// Default subdirectory for a pack: the archive's file name without
+/// extension.
+pub fn default_subdirectory(archive: &Path) -> String {
+    archive.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// First of `path`, `stem (2).ext`, `stem (3).ext`, ... that doesn't exist.
+pub fn free_name(path: &Path, exists: impl Fn(&Path) -> bool) -> PathBuf {
+    if !exists(path) {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !exists(candidate))
+        .expect("some numbered name is free")
+}
+
+/// Extract the message files of the pack at `archive` into `subdir` of
+/// the scan directory `scan_dir`.
+///
+/// `on_collision` is asked for every file that already exists. The tags
+/// of the pack's `tags.toml` are merged into `scan_dir`'s for the files
+/// imported. Fails only if the archive can't be read or a file can't be
+/// written.
+pub fn import(
+    archive: &Path,
+    scan_dir: &Path,
+    subdir: &Path,
+    mut on_collision: impl FnMut(&Path) -> Collision,
+) -> Result<ImportReport, String> {
+    let dest = scan_dir.join(subdir);
+    let bytes = fs::read(archive).map_err(|e| format!("Can't read {}: {}", archive.display(), e))?;
+    let entries = zip::read_entries(&bytes).map_err(|e| format!("Can't import {}: {}", archive.display(), e))?;
+    log(&format!("Importing {} ({} entries) into {}", archive.display(), entries.len(), dest.display()));
+
+    let mut report = ImportReport::default();
+    let mut pack_tags = None;
+    // Name in the pack, name in the scan directory
+    let mut renames = Vec::new();
+    for entry in entries {
+        let Some(relative) = safe_relative_path(&entry.name) else {
+            log(&format!("  Refused unsafe path '{}'", entry.name));
+            report.unsafe_paths.push(entry.name);
+            continue;
+        };
+        if relative == Path::new(TAGS_FILE_NAME) {
+            pack_tags = Some(String::from_utf8_lossy(&entry.data).into_owned());
+            continue;
+        }
+        if !is_supported_extension(&relative) {
+            report.unsupported.push(entry.name);
+            continue;
+        }
+
+        let mut target = dest.join(&relative);
+        if target.exists() {
+            match on_collision(&target) {
+                Collision::Overwrite => {}
+                Collision::Rename => target = free_name(&target, |p| p.exists()),
+                Collision::Skip => {
+                    report.skipped.push(entry.name);
+                    continue;
+                }
+            }
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Can't create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&target, &entry.data).map_err(|e| format!("Can't write {}: {}", target.display(), e))?;
+        log(&format!("  Imported {}", target.display()));
+        if let Ok(name) = target.strip_prefix(scan_dir) {
+            renames.push((list_name(&relative), list_name(name)));
+        }
+        report.imported.push(target);
+    }
+    if let Some(content) = pack_tags {
+        merge_tags(scan_dir, &content, &renames)?;
+    }
+    Ok(report)
+}
+
+/// `relative` as the file list names it, `/` separated.
+fn list_name(relative: &Path) -> String {
+    let parts: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    parts.join("/")
+}
+
+/// Add the tags `content` (a pack's `tags.toml`) gives the imported files
+/// to the `tags.toml` of `scan_dir`, under their names there. Tags a file
+/// already has there aren't repeated.
+fn merge_tags(scan_dir: &Path, content: &str, renames: &[(String, String)]) -> Result<(), String> {
+    let path = scan_dir.join(TAGS_FILE_NAME);
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let (known, _) = tags::parse(&existing);
+    let (entries, _) = tags::parse(content);
+
+    let mut added = String::new();
+    for (pack_name, tags) in entries {
+        let Some((_, name)) = renames.iter().find(|(from, _)| from.eq_ignore_ascii_case(&pack_name)) else {
+            continue;
+        };
+        let new: Vec<String> = tags
+            .into_iter()
+            .filter(|tag| !known.iter().any(|(n, t)| n.eq_ignore_ascii_case(name) && t.contains(tag)))
+            .collect();
+        if !new.is_empty() {
+            added.push_str(&tags_line(name, &new));
+        }
+    }
+    if added.is_empty() {
+        return Ok(());
+    }
+    let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+    fs::write(&path, format!("{}{}{}", existing, separator, added))
+        .map_err(|e| format!("Can't write {}: {}", path.display(), e))?;
+    log(&format!("  Merged the pack's tags into {}", path.display()));
+    Ok(())
+}
+
+/// A `tags.toml` line giving `name` its `tags`.
+fn tags_line(name: &str, tags: &[String]) -> String {
+    let list: Vec<String> = tags.iter().map(|t| format!("\"{}\"", t)).collect();
+    format!("\"{}\" = [{}]\n", name, list.join(", "))
+}
+
+/// Format of an exported pack, picked from the output file's extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackFormat {
+    /// Zip archive of the files, plus a `tags.toml` with their tags
+    Zip,
+    /// One markdown document with a section per file
+    Markdown,
+}
+
+impl PackFormat {
+    /// Format for `output` (`.zip` or `.md`).
+    pub fn from_path(output: &Path) -> Result<Self, String> {
+        match output.extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+            Some("zip") => Ok(PackFormat::Zip),
+            Some("md") => Ok(PackFormat::Markdown),
+            _ => Err(format!("Can't export to {}: use a .zip or .md file name", output.display())),
+        }
+    }
+}
+
+/// Write `files` as a pack at `output`, overwriting it. Returns the size
+/// written in bytes.
+///
+/// Files are exported as they are on disk (front-matter included) under
+/// their list names, so subfolders are kept with recursive discovery on.
+pub fn export(files: &[&TextFile], output: &Path) -> Result<u64, String> {
+    let format = PackFormat::from_path(output)?;
+    if files.is_empty() {
+        return Err("No files to export".to_string());
+    }
+    let mut contents = Vec::with_capacity(files.len());
+    for file in files {
+        let data = fs::read(&file.path).map_err(|e| format!("Can't read {}: {}", file.path.display(), e))?;
+        contents.push((*file, data));
+    }
+    let bytes = match format {
+        PackFormat::Zip => zip_pack(&contents)?,
+        PackFormat::Markdown => markdown_pack(&contents).into_bytes(),
+    };
+    fs::write(output, &bytes).map_err(|e| format!("Can't write {}: {}", output.display(), e))?;
+    log(&format!("Exported {} file(s) to {} ({} bytes)", files.len(), output.display(), bytes.len()));
+    Ok(bytes.len() as u64)
+}
+
+/// Zip archive of the files, with a `tags.toml` if any of them is tagged.
+fn zip_pack(files: &[(&TextFile, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let mut entries: Vec<Entry> = files
+        .iter()
+        .map(|(file, data)| Entry { name: file.name.clone(), data: data.clone() })
+        .collect();
+    let tags: Vec<String> = files
+        .iter()
+        .filter(|(file, _)| !file.tags.is_empty())
+        .map(|(file, _)| tags_line(&file.name, &file.tags))
+        .collect();
+    if !tags.is_empty() {
+        entries.push(Entry { name: TAGS_FILE_NAME.to_string(), data: tags.concat().into_bytes() });
+    }
+    zip::write_entries(&entries)
+}
+
+/// Markdown document with a `## name` section per file, contents fenced.
+fn markdown_pack(files: &[(&TextFile, Vec<u8>)]) -> String {
+    let mut text = String::from("# MadTyping pack\n");
+    for (file, data) in files {
+        let contents = String::from_utf8_lossy(data);
+        // Fence longer than any backtick run in the contents
+        let longest = contents.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest.max(2) + 1);
+        text.push_str(&format!("\n## {}\n\n", file.name));
+        if !file.tags.is_empty() {
+            text.push_str(&format!("Tags: {}\n\n", file.tags.join(", ")));
+        }
+        text.push_str(&format!("{}\n{}", fence, contents));
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&format!("{}\n", fence));
+    }
+    text
+}
+
+/// Case-insensitive wildcard match: `*` matches any run of characters
+/// (including `/`), `?` any single one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, tried)) => {
+                    p = after;
+                    n = tried + 1;
+                    star = Some((after, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Human-readable size, e.g. "512 B" or "1.5 KB".
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsafe_paths_refused() {
+        assert_eq!(safe_relative_path("ranked/gg.txt"), Some(Path::new("ranked").join("gg.txt")));
+        assert_eq!(safe_relative_path("./a\\b.md"), Some(Path::new("a").join("b.md")));
+        for name in ["../evil.txt", "a/../../b.txt", "/etc/passwd", "\\\\server\\x.txt", "C:\\x.txt", "c:x.txt"] {
+            assert_eq!(safe_relative_path(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_free_name_numbers_collisions() {
+        let taken = [PathBuf::from("pack/gg.txt"), PathBuf::from("pack/gg (2).txt")];
+        let free = free_name(Path::new("pack/gg.txt"), |p| taken.iter().any(|t| t == p));
+        assert_eq!(free, PathBuf::from("pack/gg (3).txt"));
+    }
+
+    #[test]
+    fn test_import_fixture_pack() {
+        let archive = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pack.zip");
+        let dest = std::env::temp_dir().join(format!("madtyping-import-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dest);
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("taunts.md"), "old").unwrap();
+
+        let mut asked = Vec::new();
+        let report = import(&archive, &dest, Path::new(""), |path| {
+            asked.push(path.file_name().unwrap().to_string_lossy().to_string());
+            Collision::Rename
+        });
+        let files: Vec<String> = fs::read_dir(&dest).unwrap().flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect();
+        let renamed = fs::read_to_string(dest.join("taunts (2).md"));
+        fs::remove_dir_all(&dest).unwrap();
+
+        let report = report.unwrap();
+        assert_eq!(asked, vec!["taunts.md"]);
+        assert_eq!(report.imported.len(), 4);
+        assert_eq!(report.unsupported, vec!["readme.exe"]);
+        assert_eq!(report.unsafe_paths, vec!["../evil.txt"]);
+        assert!(renamed.unwrap().contains("too slow"));
+        assert!(files.contains(&"ranked".to_string()));
+        assert_eq!(report.summary(), "4 file(s) imported, 1 unsupported skipped, 1 unsafe path(s) refused");
+    }
+
+    #[test]
+    fn test_import_merges_pack_tags() {
+        let scan_dir = std::env::temp_dir().join(format!("madtyping-import-tags-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&scan_dir);
+        fs::create_dir_all(scan_dir.join("friends")).unwrap();
+        fs::write(scan_dir.join("friends/gg.txt"), "old").unwrap();
+        fs::write(scan_dir.join(TAGS_FILE_NAME), "notes.txt = [mine]").unwrap();
+        let archive = scan_dir.join("friends.zip");
+        let entry = |name: &str, data: &str| Entry { name: name.to_string(), data: data.as_bytes().to_vec() };
+        let pack = zip::write_entries(&[
+            entry("gg.txt", "gg wp"),
+            entry("ranked/ez.md", "ez"),
+            entry(TAGS_FILE_NAME, "\"gg.txt\" = [\"aram\", \"ranked\"]\n\"ranked/ez.md\" = [\"troll\"]\nmissing.txt = [x]\n"),
+        ]);
+        fs::write(&archive, pack.unwrap()).unwrap();
+
+        let report = import(&archive, &scan_dir, Path::new("friends"), |_| Collision::Rename);
+        let merged = fs::read_to_string(scan_dir.join(TAGS_FILE_NAME));
+        fs::remove_dir_all(&scan_dir).unwrap();
+
+        let report = report.unwrap();
+        assert_eq!(report.imported.len(), 2);
+        assert!(report.unsupported.is_empty());
+        assert_eq!(
+            merged.unwrap(),
+            "notes.txt = [mine]\n\"friends/gg (2).txt\" = [\"aram\", \"ranked\"]\n\"friends/ranked/ez.md\" = [\"troll\"]\n"
+        );
+    }
+
+    fn file(name: &str, tags: &[&str]) -> TextFile {
+        TextFile { tags: tags.iter().map(|t| t.to_string()).collect(), ..TextFile::with_lines(name, &[]) }
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let gg = file("ranked/gg.txt", &["ranked"]);
+        let ez = file("ez.md", &[]);
+        let files = vec![(&gg, b"---\nwindow_title: X\n---\ngg wp".to_vec()), (&ez, b"```ez```\n".to_vec())];
+
+        let entries = zip::read_entries(&zip_pack(&files).unwrap()).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["ranked/gg.txt", "ez.md", "tags.toml"]);
+        assert_eq!(entries[2].data, b"\"ranked/gg.txt\" = [\"ranked\"]\n");
+
+        assert_eq!(
+            markdown_pack(&files),
+            "# MadTyping pack\n\n## ranked/gg.txt\n\nTags: ranked\n\n```\n---\nwindow_title: X\n---\ngg wp\n```\n\n## ez.md\n\n````\n```ez```\n````\n"
+        );
+        assert!(PackFormat::from_path(Path::new("pack.txt")).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", "aram/ez.md"));
+        assert!(glob_match("aram/*.MD", "aram/ez.md"));
+        assert!(glob_match("g?.txt", "gg.txt"));
+        assert!(glob_match("*gg*", "ranked/gg wp.txt"));
+        assert!(!glob_match("*.txt", "taunts.md"));
+        assert!(!glob_match("g?.txt", "g.txt"));
+        assert_eq!(format_size(1536), "1.5 KB");
+    }
+}
@@ -0,0 +1,96 @@
+//! Paste fallback for MadTyping
+//!
+//! With the `auto` send method, messages are pasted until a window turns
+//! out to ignore Ctrl+V (the paste check hook says so, or the user flags
+//! it while sending). From then on that window is typed into, and the
+//! decision is kept in the state file so later sends start typing right
+//! away. The settings screen can forget it.
+
+use crate::persistence::{self, State};
+use crate::send::SendReport;
+
+/// State key of the window titles that are typed into, a
+/// [`State::set_list`] list.
+const STATE_KEY: &str = "paste_fallback.windows";
+
+/// Window titles remembered to ignore pasting.
+pub fn typed_windows(state: &State) -> Vec<String> {
+    state.get_list(STATE_KEY)
+}
+
+/// Add the windows of `report` that fell back to typing to `state`.
+/// Returns whether any of them is new.
+pub fn remember(state: &mut State, report: &SendReport) -> bool {
+    let mut windows = typed_windows(state);
+    let before = windows.len();
+    for target in report.targets.iter().filter(|t| t.paste_fallback.is_some()) {
+        if !windows.contains(&target.window_title) {
+            windows.push(target.window_title.clone());
+        }
+    }
+    if windows.len() == before {
+        return false;
+    }
+    state.set_list(STATE_KEY, &windows);
+    true
+}
+
+/// Window titles remembered in the state file.
+pub fn load() -> Vec<String> {
+    typed_windows(&persistence::load())
+}
+
+/// Remember the windows of `report` that fell back to typing.
+pub fn save(report: &SendReport) -> Result<(), String> {
+    let mut state = persistence::load();
+    if remember(&mut state, report) {
+        persistence::save(&state)?;
+    }
+    Ok(())
+}
+
+/// Forget every remembered window, so the next send tries pasting again.
+/// Returns how many there were.
+pub fn forget() -> Result<usize, String> {
+    let mut state = persistence::load();
+    let count = typed_windows(&state).len();
+    if count > 0 {
+        state.set(STATE_KEY, "");
+        persistence::save(&state)?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::send::TargetReport;
+
+    #[test]
+    fn test_fallbacks_remembered_once_per_window() {
+        let target = |title: &str, reason: Option<&str>| TargetReport {
+            window_title: title.to_string(),
+            paste_fallback: reason.map(str::to_string),
+            ..TargetReport::default()
+        };
+        let report = SendReport {
+            targets: vec![target("Game A", Some("flagged")), target("Game B", None)],
+            ..SendReport::default()
+        };
+        let mut state = State::default();
+        assert!(remember(&mut state, &report));
+        assert!(!remember(&mut state, &report));
+        assert_eq!(typed_windows(&state), vec!["Game A"]);
+
+        let report = SendReport { targets: vec![target("Game B", Some("hook"))], ..SendReport::default() };
+        assert!(remember(&mut state, &report));
+        assert_eq!(typed_windows(&state), vec!["Game A", "Game B"]);
+
+        let report = SendReport { targets: vec![target("Lobby; EU", Some("hook"))], ..SendReport::default() };
+        assert!(remember(&mut state, &report));
+        assert_eq!(typed_windows(&State::parse(&state.serialize())), vec!["Game A", "Game B", "Lobby; EU"]);
+
+        state.set(STATE_KEY, "");
+        assert!(typed_windows(&state).is_empty());
+    }
+}
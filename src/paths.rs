@@ -0,0 +1,157 @@
+//! Data file locations for MadTyping
+//!
+//! The log and state files live next to the executable when that directory
+//! is writable. Installs in a protected place (e.g. Program Files) fall back
+//! to a per-user directory instead, so logging and persistence keep working.
+//! So do installs whose files were moved there (see [`crate::migration`]).
+//! The choice is made on first use, and only changes when the files move.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::persistence::STATE_FILE_NAME;
+
+/// Name of the per-user fallback directory.
+pub const APP_DIR_NAME: &str = "MadTyping";
+
+/// Where data files go, and why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataDirectory {
+    pub path: PathBuf,
+    /// True when the executable's directory wasn't writable
+    pub fallback: bool,
+}
+
+impl DataDirectory {
+    /// One-line description for the log and the about screen.
+    pub fn describe(&self) -> String {
+        if self.fallback {
+            format!("{} (executable directory is not writable)", self.path.display())
+        } else {
+            self.path.display().to_string()
+        }
+    }
+}
+
+static DATA_DIRECTORY: Mutex<Option<Arc<DataDirectory>>> = Mutex::new(None);
+
+/// Directory containing the executable.
+pub fn exe_directory() -> Result<PathBuf, String> {
+    let exe_path = env::current_exe()
+        .map_err(|e| format!("Failed to get executable path: {}", e))?;
+    exe_path
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| "Failed to get executable directory".to_string())
+}
+
+/// The user's home folder (`%USERPROFILE%` on Windows, `$HOME` elsewhere).
+pub fn home_directory() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var).filter(|h| !h.is_empty()).map(PathBuf::from)
+}
+
+/// Replace a leading `~` with the home folder.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), home_directory()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches(['/', '\\'])),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Per-user data directory: `%LOCALAPPDATA%\MadTyping` on Windows,
+/// `$XDG_DATA_HOME/MadTyping` or `~/.local/share/MadTyping` elsewhere.
+pub fn user_data_directory() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share")))
+    };
+    base.filter(|b| !b.as_os_str().is_empty()).map(|b| b.join(APP_DIR_NAME))
+}
+
+/// Whether a file can be created in `dir` (probed with a throwaway file).
+pub fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".madtyping-write-test-{}", std::process::id()));
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Pick the data directory: `fallback` when the state file was moved there,
+/// else `exe_dir` when writable, else `fallback` (created if needed) when
+/// that works, else `exe_dir` anyway.
+pub fn choose_data_directory(exe_dir: &Path, fallback: Option<PathBuf>) -> DataDirectory {
+    if let Some(dir) = fallback.as_ref().filter(|dir| dir.join(STATE_FILE_NAME).is_file() && !exe_dir.join(STATE_FILE_NAME).exists()) {
+        return DataDirectory { path: dir.clone(), fallback: false };
+    }
+    if is_writable(exe_dir) {
+        return DataDirectory { path: exe_dir.to_path_buf(), fallback: false };
+    }
+    match fallback {
+        Some(dir) if fs::create_dir_all(&dir).is_ok() && is_writable(&dir) => DataDirectory { path: dir, fallback: true },
+        _ => DataDirectory { path: exe_dir.to_path_buf(), fallback: false },
+    }
+}
+
+/// The data directory for this run, chosen on first call.
+pub fn data_directory() -> Result<Arc<DataDirectory>, String> {
+    let mut current = DATA_DIRECTORY.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(dir) = current.as_ref() {
+        return Ok(Arc::clone(dir));
+    }
+    let chosen = Arc::new(choose_data_directory(&exe_directory()?, user_data_directory()));
+    *current = Some(Arc::clone(&chosen));
+    Ok(chosen)
+}
+
+/// Use `dir` as the data directory for the rest of the run, once the data
+/// files were moved there.
+pub fn set_data_directory(dir: DataDirectory) {
+    *DATA_DIRECTORY.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(dir));
+}
+
+/// Path of a data file (log, state) in the data directory.
+pub fn data_file(name: &str) -> Result<PathBuf, String> {
+    data_directory().map(|dir| dir.path.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_data_directory() {
+        let root = env::temp_dir().join(format!("madtyping-paths-{}", std::process::id()));
+        let exe_dir = root.join("exe");
+        let fallback = root.join("user").join(APP_DIR_NAME);
+        fs::create_dir_all(&exe_dir).unwrap();
+
+        let chosen = choose_data_directory(&exe_dir, Some(fallback.clone()));
+        assert_eq!(chosen, DataDirectory { path: exe_dir.clone(), fallback: false });
+
+        // A directory that doesn't exist can't be written to
+        let missing = root.join("missing");
+        let chosen = choose_data_directory(&missing, Some(fallback.clone()));
+        assert_eq!(chosen, DataDirectory { path: fallback.clone(), fallback: true });
+        assert!(fallback.is_dir());
+        assert_eq!(choose_data_directory(&missing, None).path, missing);
+
+        // Once the state file was moved, the per-user directory wins
+        fs::write(fallback.join(STATE_FILE_NAME), "").unwrap();
+        assert_eq!(choose_data_directory(&exe_dir, Some(fallback.clone())).path, fallback);
+        fs::write(exe_dir.join(STATE_FILE_NAME), "").unwrap();
+        assert_eq!(choose_data_directory(&exe_dir, Some(fallback.clone())).path, exe_dir);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,143 @@
+//! Persistent state for MadTyping
+//!
+//! Small pieces of state that should survive restarts are stored as
+//! `key=value` lines in `madtyping.state` in the data directory (next to
+//! the executable unless that isn't writable, see [`crate::paths`]). Unknown
+//! keys are kept untouched so newer and older builds can share the file.
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use crate::logging::log;
+use crate::paths;
+
+/// File name of the state file, stored in the data directory.
+pub const STATE_FILE_NAME: &str = "madtyping.state";
+
+/// Key/value state loaded from (and saved to) the state file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    values: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Get the raw value of a key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Get a key parsed as an unsigned number (missing or invalid → 0).
+    pub fn get_u64(&self, key: &str) -> u64 {
+        self.get(key).and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    /// Set a key, replacing any previous value.
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// Get a key holding a list written by [`State::set_list`]. Empty
+    /// entries are left out.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        let mut entries = vec![String::new()];
+        let mut chars = self.get(key).unwrap_or_default().chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => entries.last_mut().unwrap().extend(chars.next()),
+                ';' => entries.push(String::new()),
+                c => entries.last_mut().unwrap().push(c),
+            }
+        }
+        entries.retain(|e| !e.is_empty());
+        entries
+    }
+
+    /// Set a key to a list: entries are separated by `;`, and a `;` or `\`
+    /// inside an entry is escaped with `\`.
+    pub fn set_list<S: AsRef<str>>(&mut self, key: &str, items: impl IntoIterator<Item = S>) {
+        let entries: Vec<String> =
+            items.into_iter().map(|item| item.as_ref().replace('\\', "\\\\").replace(';', "\\;")).collect();
+        self.set(key, entries.join(";"));
+    }
+
+    /// Parse state file contents. Blank lines, `#` comments and lines
+    /// without `=` are ignored.
+    pub fn parse(content: &str) -> Self {
+        let values = content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        Self { values }
+    }
+
+    /// Serialize to state file contents (sorted by key).
+    pub fn serialize(&self) -> String {
+        let mut out = String::from("# MadTyping state - managed automatically\n");
+        for (key, value) in &self.values {
+            out.push_str(&format!("{}={}\n", key, value));
+        }
+        out
+    }
+}
+
+/// Path of the state file in the data directory.
+pub fn state_path() -> Result<PathBuf, String> {
+    paths::data_file(STATE_FILE_NAME)
+}
+
+/// Load the state file; a missing or unreadable file gives an empty state.
+pub fn load() -> State {
+    let Ok(path) = state_path() else {
+        return State::default();
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => State::parse(&content),
+        Err(_) => State::default(),
+    }
+}
+
+/// Save the state file. Callers should [`load`] first and modify the
+/// result so keys owned by other features are preserved.
+pub fn save(state: &State) -> Result<(), String> {
+    let path = state_path()?;
+    fs::write(&path, state.serialize()).map_err(|e| {
+        log(&format!("Failed to save state: {}", e));
+        format!("Failed to save {}: {}", path.display(), e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_serialize_roundtrip() {
+        let mut state = State::default();
+        state.set("stats.lines", 42);
+        state.set("name", "a=b");
+        let parsed = State::parse(&state.serialize());
+        assert_eq!(parsed, state);
+        assert_eq!(parsed.get("name"), Some("a=b"));
+        assert_eq!(parsed.get_u64("stats.lines"), 42);
+    }
+
+    #[test]
+    fn test_parse_ignores_junk() {
+        let state = State::parse("# comment\n\nnot a pair\n key = value \nnum=abc\n");
+        assert_eq!(state.get("key"), Some("value"));
+        assert_eq!(state.get_u64("num"), 0);
+        assert_eq!(state.get("not a pair"), None);
+    }
+
+    #[test]
+    fn test_lists_keep_separators_in_entries() {
+        let mut state = State::default();
+        let items = ["Lobby; EU West", "C:\\Games\\Client", "", "Plain"];
+        state.set_list("windows", items);
+        let parsed = State::parse(&state.serialize());
+        assert_eq!(parsed.get_list("windows"), vec!["Lobby; EU West", "C:\\Games\\Client", "Plain"]);
+        assert!(parsed.get_list("missing").is_empty());
+    }
+}
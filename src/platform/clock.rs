@@ -0,0 +1,50 @@
+//! Local time of day
+//!
+//! Lines with time conditions need the local hour and day of the week.
+//! Windows reports them directly; elsewhere, where sends are only dry
+//! runs, the time in UTC stands in.
+
+/// Hour, minute and day of the week on the local clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    /// 0 to 23
+    pub hour: u8,
+    pub minute: u8,
+    /// 0 for Sunday to 6 for Saturday
+    pub weekday: u8,
+}
+
+impl LocalTime {
+    /// The time `secs` seconds after the Unix epoch, in UTC.
+    pub fn from_unix_secs(secs: u64) -> Self {
+        let days = secs / 86_400;
+        Self {
+            hour: ((secs / 3600) % 24) as u8,
+            minute: ((secs / 60) % 60) as u8,
+            // 1 January 1970 was a Thursday
+            weekday: ((days + 4) % 7) as u8,
+        }
+    }
+}
+
+/// The current local time.
+#[cfg(not(windows))]
+pub fn local_time() -> LocalTime {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    LocalTime::from_unix_secs(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+}
+
+#[cfg(windows)]
+pub use super::windows::local_time;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_from_unix_secs() {
+        assert_eq!(LocalTime::from_unix_secs(0), LocalTime { hour: 0, minute: 0, weekday: 4 });
+        // Monday 15 January 2024, 13:45:10 UTC
+        assert_eq!(LocalTime::from_unix_secs(1_705_326_310), LocalTime { hour: 13, minute: 45, weekday: 1 });
+    }
+}
@@ -0,0 +1,123 @@
+//! Window display mode detection
+//!
+//! Guesses whether a game window is windowed, maximized, borderless
+//! full-screen or exclusive full-screen from its rectangle and style bits.
+//! The classification is pure so it can be tested on any platform.
+
+/// Window style: has a title bar.
+pub const WS_CAPTION: u32 = 0x00C0_0000;
+
+/// Window style: has a sizing border.
+pub const WS_THICKFRAME: u32 = 0x0004_0000;
+
+/// Window style: is maximized.
+pub const WS_MAXIMIZE: u32 = 0x0100_0000;
+
+/// Extended window style: stays above all non-topmost windows.
+pub const WS_EX_TOPMOST: u32 = 0x0000_0008;
+
+/// A screen rectangle in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    /// Check whether this rectangle covers all of `other`.
+    pub fn covers(&self, other: &Rect) -> bool {
+        self.left <= other.left
+            && self.top <= other.top
+            && self.right >= other.right
+            && self.bottom >= other.bottom
+    }
+}
+
+/// How a window is being displayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// A normal window smaller than the monitor
+    Windowed,
+    /// A maximized window with a title bar
+    Maximized,
+    /// A borderless window covering the monitor ("windowed fullscreen")
+    Borderless,
+    /// Probably true exclusive full-screen, where focus switching minimizes the game
+    ExclusiveFullscreen,
+}
+
+impl DisplayMode {
+    /// Human readable name for reports.
+    pub fn describe(self) -> &'static str {
+        match self {
+            DisplayMode::Windowed => "windowed",
+            DisplayMode::Maximized => "maximized",
+            DisplayMode::Borderless => "borderless fullscreen",
+            DisplayMode::ExclusiveFullscreen => "exclusive fullscreen",
+        }
+    }
+}
+
+/// Classify a window from its rectangle, its monitor's rectangle and styles.
+///
+/// Maximized windows also cover the monitor's work area but keep their
+/// caption, so the style bits are checked before treating a monitor-sized
+/// window as full-screen. Among caption-less monitor-sized windows, exclusive
+/// full-screen games are typically topmost while borderless ones are not.
+pub fn classify(window: Rect, monitor: Rect, style: u32, ex_style: u32) -> DisplayMode {
+    let has_frame = style & (WS_CAPTION | WS_THICKFRAME) != 0;
+
+    if style & WS_MAXIMIZE != 0 && has_frame {
+        return DisplayMode::Maximized;
+    }
+    if !window.covers(&monitor) {
+        return DisplayMode::Windowed;
+    }
+    if has_frame {
+        // Frame pushed off-screen by a maximize without the WS_MAXIMIZE bit
+        return DisplayMode::Maximized;
+    }
+    if ex_style & WS_EX_TOPMOST != 0 {
+        DisplayMode::ExclusiveFullscreen
+    } else {
+        DisplayMode::Borderless
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: Rect = Rect { left: 0, top: 0, right: 1920, bottom: 1080 };
+    const WS_POPUP: u32 = 0x8000_0000;
+
+    #[test]
+    fn test_windowed() {
+        let window = Rect { left: 100, top: 100, right: 1380, bottom: 820 };
+        assert_eq!(classify(window, MONITOR, WS_CAPTION | WS_THICKFRAME, 0), DisplayMode::Windowed);
+    }
+
+    #[test]
+    fn test_maximized_is_not_fullscreen() {
+        // Maximized windows extend a few pixels past the monitor edges
+        let window = Rect { left: -8, top: -8, right: 1928, bottom: 1088 };
+        let style = WS_CAPTION | WS_THICKFRAME | WS_MAXIMIZE;
+        assert_eq!(classify(window, MONITOR, style, WS_EX_TOPMOST), DisplayMode::Maximized);
+        assert_eq!(classify(window, MONITOR, WS_CAPTION | WS_THICKFRAME, 0), DisplayMode::Maximized);
+    }
+
+    #[test]
+    fn test_borderless_vs_exclusive() {
+        assert_eq!(classify(MONITOR, MONITOR, WS_POPUP, 0), DisplayMode::Borderless);
+        assert_eq!(classify(MONITOR, MONITOR, WS_POPUP, WS_EX_TOPMOST), DisplayMode::ExclusiveFullscreen);
+    }
+
+    #[test]
+    fn test_second_monitor() {
+        let monitor = Rect { left: 1920, top: 0, right: 4480, bottom: 1440 };
+        assert_eq!(classify(MONITOR, monitor, WS_POPUP, WS_EX_TOPMOST), DisplayMode::Windowed);
+        assert_eq!(classify(monitor, monitor, WS_POPUP, WS_EX_TOPMOST), DisplayMode::ExclusiveFullscreen);
+    }
+}
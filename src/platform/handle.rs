@@ -0,0 +1,134 @@
+//! Cached target window handles
+//!
+//! Finding the target means enumerating every top-level window and
+//! classifying the matches, which takes longer than typing a short line and
+//! can pick a different window when several match. A send looks each
+//! target up once; before every line [`HandleCache::resolve`] only checks
+//! that the handle still is a window with a matching title, and enumerates
+//! again only if it isn't.
+
+use std::collections::HashMap;
+
+use crate::logging::log;
+
+/// How windows are found and checked; the Win32 calls on Windows.
+pub trait WindowLookup {
+    /// Enumerate the windows and pick the one to send to for `title`.
+    fn find(&mut self, title: &str) -> Option<isize>;
+
+    /// Whether `handle` still is a window whose title contains `title`.
+    fn is_valid(&mut self, handle: isize, title: &str) -> bool;
+}
+
+/// How the cached handles were looked up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LookupCounts {
+    /// Cached handles that were still valid
+    pub cached: u32,
+    /// Full enumerations
+    pub scans: u32,
+}
+
+/// Target window handles by title.
+#[derive(Debug, Default)]
+pub struct HandleCache {
+    handles: HashMap<String, isize>,
+    counts: LookupCounts,
+}
+
+impl HandleCache {
+    /// Forget every handle, e.g. when a new send starts.
+    pub fn clear(&mut self) {
+        self.handles.clear();
+        self.counts = LookupCounts::default();
+    }
+
+    /// The window to send to for `title`: the cached handle while it's
+    /// valid, else what a fresh enumeration picks. None if nothing matches.
+    pub fn resolve(&mut self, lookup: &mut impl WindowLookup, title: &str) -> Option<isize> {
+        let cached = self.handles.get(title).copied();
+        if let Some(handle) = cached {
+            if lookup.is_valid(handle, title) {
+                self.counts.cached += 1;
+                return Some(handle);
+            }
+        }
+        self.counts.scans += 1;
+        let found = lookup.find(title);
+        match (cached, found) {
+            (Some(old), Some(new)) if old != new => {
+                log(&format!("Window for '{}' changed from {:#x} to {:#x}", title, old, new));
+            }
+            (Some(old), None) => log(&format!("Window {:#x} for '{}' is gone and nothing else matches", old, title)),
+            _ => {}
+        }
+        match found {
+            Some(handle) => self.handles.insert(title.to_string(), handle),
+            None => self.handles.remove(title),
+        };
+        found
+    }
+
+    /// Lookups since the last [`clear`](Self::clear).
+    pub fn counts(&self) -> LookupCounts {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Open windows by handle, counting enumerations.
+    #[derive(Default)]
+    struct MockLookup {
+        windows: Vec<(isize, String)>,
+        scans: u32,
+    }
+
+    impl WindowLookup for MockLookup {
+        fn find(&mut self, title: &str) -> Option<isize> {
+            self.scans += 1;
+            self.windows.iter().find(|(_, t)| t.contains(title)).map(|&(handle, _)| handle)
+        }
+
+        fn is_valid(&mut self, handle: isize, title: &str) -> bool {
+            self.windows.iter().any(|(h, t)| *h == handle && t.contains(title))
+        }
+    }
+
+    #[test]
+    fn test_handle_found_once_and_reused() {
+        let mut lookup = MockLookup { windows: vec![(0x10, "League of Legends (TM) Client".into())], ..MockLookup::default() };
+        let mut cache = HandleCache::default();
+        for _ in 0..5 {
+            assert_eq!(cache.resolve(&mut lookup, "League of Legends"), Some(0x10));
+        }
+        assert_eq!(lookup.scans, 1);
+        assert_eq!(cache.counts(), LookupCounts { cached: 4, scans: 1 });
+
+        cache.clear();
+        assert_eq!(cache.resolve(&mut lookup, "League of Legends"), Some(0x10));
+        assert_eq!(lookup.scans, 2);
+    }
+
+    #[test]
+    fn test_window_replaced_mid_file() {
+        let mut lookup = MockLookup { windows: vec![(0x10, "Notepad".into())], ..MockLookup::default() };
+        let mut cache = HandleCache::default();
+        assert_eq!(cache.resolve(&mut lookup, "Notepad"), Some(0x10));
+
+        // The window closes between lines and a new instance opens
+        lookup.windows = vec![(0x20, "Notepad".into())];
+        assert_eq!(cache.resolve(&mut lookup, "Notepad"), Some(0x20));
+        assert_eq!(cache.resolve(&mut lookup, "Notepad"), Some(0x20));
+        assert_eq!(cache.counts(), LookupCounts { cached: 1, scans: 2 });
+
+        // A handle whose window now has another title doesn't count either
+        lookup.windows = vec![(0x20, "Calculator".into())];
+        assert_eq!(cache.resolve(&mut lookup, "Notepad"), None);
+        lookup.windows.push((0x30, "Notepad".into()));
+        assert_eq!(cache.resolve(&mut lookup, "Notepad"), Some(0x30));
+        assert_eq!(lookup.scans, 4);
+    }
+}
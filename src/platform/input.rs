@@ -0,0 +1,1078 @@
+//! Platform-independent keyboard input sequencing
+//!
+//! Builds the key event sequences for typing a chat message (open chat,
+//! type characters, press Enter) on top of an [`InputBackend`], so the
+//! timing and retry logic can be tested without a real keyboard.
+
+use std::time::Duration;
+
+use crate::config::{
+    Config, CHAR_TYPE_DELAY_MS, CHAT_OPEN_DELAY_MS, AFTER_TYPE_DELAY_MS, AFTER_SEND_DELAY_MS, FOCUS_DELAY_MS,
+    KEY_BATCH_DELAY_MS, KEY_PRESS_DELAY_MS, RESTORE_SETTLE_DELAY_MS, SHIFT_KEY_DELAY_MS, UNICODE_KEY_DELAY_MS,
+    CHAR_RETRY_LIMIT, CHAR_RETRY_DELAY_MS, MODIFIER_POLL_MS, MODIFIER_RELEASE_TIMEOUT_MS,
+    SPEED_STEPS_PERCENT,
+};
+use crate::logging::log;
+use crate::trace;
+
+/// Virtual key code for Enter.
+pub const VK_RETURN: u16 = 0x0D;
+
+/// Virtual key code for Shift.
+pub const VK_SHIFT: u16 = 0x10;
+
+/// Virtual key code for Ctrl.
+pub const VK_CONTROL: u16 = 0x11;
+
+/// Virtual key code for Caps Lock.
+pub const VK_CAPITAL: u16 = 0x14;
+
+/// Virtual key code for V.
+pub const VK_V: u16 = 0x56;
+
+/// A single keyboard event to inject.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyInput {
+    /// A virtual key going down or up (sent via its scan code)
+    Key { vk: u16, up: bool },
+    /// A UTF-16 code unit going down or up (KEYEVENTF_UNICODE)
+    Unicode { unit: u16, up: bool },
+}
+
+/// A window message posted for a key event when lines are delivered with
+/// `SendMethod::PostMessage` instead of injected input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostedMessage {
+    /// WM_KEYDOWN for a virtual key
+    KeyDown(u16),
+    /// WM_KEYUP for a virtual key
+    KeyUp(u16),
+    /// WM_CHAR for a UTF-16 code unit
+    Char(u16),
+}
+
+impl PostedMessage {
+    /// The message posted for `event`. A Unicode key-up has none: its
+    /// character went out with the key-down.
+    pub fn for_event(event: KeyInput) -> Option<Self> {
+        match event {
+            KeyInput::Key { vk, up: false } => Some(PostedMessage::KeyDown(vk)),
+            KeyInput::Key { vk, up: true } => Some(PostedMessage::KeyUp(vk)),
+            KeyInput::Unicode { unit, up: false } => Some(PostedMessage::Char(unit)),
+            KeyInput::Unicode { up: true, .. } => None,
+        }
+    }
+}
+
+/// Keyboard state that would interfere with typing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyboardState {
+    /// Caps Lock is toggled on
+    pub caps_lock: bool,
+    /// Shift is physically held down
+    pub shift: bool,
+    /// Ctrl is physically held down
+    pub ctrl: bool,
+    /// Alt is physically held down
+    pub alt: bool,
+}
+
+impl KeyboardState {
+    /// Names of the modifiers currently held down.
+    pub fn held_modifiers(&self) -> Vec<&'static str> {
+        [(self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift")]
+            .iter()
+            .filter(|(held, _)| *held)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Low-level keyboard injection used by the typing logic.
+pub trait InputBackend {
+    /// Inject the events in order. Returns how many were accepted.
+    fn send(&mut self, events: &[KeyInput]) -> u32;
+
+    /// Look up the virtual key and Shift state for a character on the
+    /// active keyboard layout. None means it must be sent as Unicode.
+    fn key_for_char(&self, c: char) -> Option<(u16, bool)>;
+
+    /// Wait for the given number of milliseconds.
+    fn sleep(&mut self, ms: u64);
+
+    /// Query Caps Lock and the physically held modifiers.
+    fn keyboard_state(&mut self) -> KeyboardState;
+
+    /// Put `text` on the clipboard for pasting. Returns false if that
+    /// failed; backends without a clipboard always fail.
+    fn set_clipboard(&mut self, _text: &str) -> bool {
+        false
+    }
+}
+
+/// Typing speed multiplier, stepped through `SPEED_STEPS_PERCENT`.
+///
+/// Delays are divided by the multiplier, so 2x types twice as fast and
+/// 0.5x takes twice as long.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Speed {
+    percent: u32,
+}
+
+impl Speed {
+    /// The configured delays, unchanged.
+    pub const NORMAL: Speed = Speed { percent: 100 };
+
+    /// Multiplier in percent (100 = normal).
+    pub fn percent(self) -> u32 {
+        self.percent
+    }
+
+    /// The speed step with this multiplier in percent, if there is one.
+    pub fn from_percent(percent: u32) -> Option<Speed> {
+        SPEED_STEPS_PERCENT.contains(&percent).then_some(Speed { percent })
+    }
+
+    /// Next faster step, or the same speed at the upper bound.
+    pub fn faster(self) -> Speed {
+        SPEED_STEPS_PERCENT
+            .iter()
+            .find(|&&p| p > self.percent)
+            .map_or(self, |&percent| Speed { percent })
+    }
+
+    /// Next slower step, or the same speed at the lower bound.
+    pub fn slower(self) -> Speed {
+        SPEED_STEPS_PERCENT
+            .iter()
+            .rev()
+            .find(|&&p| p < self.percent)
+            .map_or(self, |&percent| Speed { percent })
+    }
+
+    /// Scale a delay by this speed.
+    pub fn scale(self, ms: u64) -> u64 {
+        ms * 100 / u64::from(self.percent)
+    }
+
+    /// Display label, e.g. "1.5x" or "0.25x".
+    pub fn label(self) -> String {
+        let whole = self.percent / 100;
+        match self.percent % 100 {
+            0 => format!("{}x", whole),
+            frac if frac % 10 == 0 => format!("{}.{}x", whole, frac / 10),
+            frac => format!("{}.{:02}x", whole, frac),
+        }
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed::NORMAL
+    }
+}
+
+/// Chat channel a message is typed into.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChatScope {
+    /// All chat, opened with Shift+Enter
+    #[default]
+    All,
+    /// Team chat, opened with Enter
+    Team,
+    /// A chat command such as `/mute all`, opened with Enter: the game
+    /// only runs commands typed into normal chat and sends them as text
+    /// after Shift+Enter
+    Command,
+}
+
+/// How the chat-open and send key sequences are injected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyMode {
+    /// The whole sequence in one SendInput call, so no key can be seen
+    /// without the others (e.g. Enter without Shift)
+    #[default]
+    Batched,
+    /// One call per key with delays in between
+    Stepped,
+}
+
+impl From<&Config> for KeyMode {
+    fn from(config: &Config) -> Self {
+        if config.stepped_chat_keys { KeyMode::Stepped } else { KeyMode::Batched }
+    }
+}
+
+impl KeyMode {
+    /// Name used in the log.
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyMode::Batched => "batched",
+            KeyMode::Stepped => "stepped",
+        }
+    }
+}
+
+/// Fixed pauses of the typing sequence, in milliseconds before speed
+/// scaling. The defaults are the `*_DELAY_MS` constants; they are not
+/// user settings but can be changed by frontends and tests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyDelays {
+    /// Between a key's down and up events
+    pub key_press_ms: u64,
+    /// Around Shift when it is pressed separately
+    pub shift_key_ms: u64,
+    /// After a batched chat-open or send sequence
+    pub key_batch_ms: u64,
+    /// Between the down and up events of a Unicode character
+    pub unicode_key_ms: u64,
+    /// After opening chat, before typing
+    pub chat_open_ms: u64,
+    /// After typing, before pressing Enter
+    pub after_type_ms: u64,
+    /// After pressing Enter
+    pub after_send_ms: u64,
+    /// After focusing the target window, before opening chat (not scaled)
+    pub focus_ms: u64,
+}
+
+impl Default for KeyDelays {
+    fn default() -> Self {
+        Self {
+            key_press_ms: KEY_PRESS_DELAY_MS,
+            shift_key_ms: SHIFT_KEY_DELAY_MS,
+            key_batch_ms: KEY_BATCH_DELAY_MS,
+            unicode_key_ms: UNICODE_KEY_DELAY_MS,
+            chat_open_ms: CHAT_OPEN_DELAY_MS,
+            after_type_ms: AFTER_TYPE_DELAY_MS,
+            after_send_ms: AFTER_SEND_DELAY_MS,
+            focus_ms: FOCUS_DELAY_MS,
+        }
+    }
+}
+
+/// How a message is typed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypingOptions {
+    /// Multiplier applied to all typing delays
+    pub speed: Speed,
+    /// Delay after each typed character (before scaling)
+    pub char_delay_ms: u64,
+    /// Chat channel to open before typing
+    pub chat: ChatScope,
+    /// How the chat-open and send keys are injected
+    pub key_mode: KeyMode,
+    /// Extra delay after restoring a minimized target window
+    pub restore_delay_ms: u64,
+    /// Pauses between the keys of the sequence
+    pub keys: KeyDelays,
+    /// Paste the message with Ctrl+V instead of typing it
+    pub paste: bool,
+    /// Post the message to the window's focused control instead of
+    /// injecting input; chat isn't opened first (see
+    /// `SendMethod::PostMessage`)
+    pub post: bool,
+    /// Retries of a character whose key events were dropped
+    pub char_retries: u64,
+}
+
+impl Default for TypingOptions {
+    fn default() -> Self {
+        Self {
+            speed: Speed::NORMAL,
+            char_delay_ms: CHAR_TYPE_DELAY_MS,
+            chat: ChatScope::All,
+            key_mode: KeyMode::Batched,
+            restore_delay_ms: RESTORE_SETTLE_DELAY_MS,
+            keys: KeyDelays::default(),
+            paste: false,
+            post: false,
+            char_retries: CHAR_RETRY_LIMIT,
+        }
+    }
+}
+
+/// Backend wrapper that scales every sleep by a [`Speed`].
+struct ScaledBackend<'a, B> {
+    inner: &'a mut B,
+    speed: Speed,
+}
+
+impl<'a, B: InputBackend> ScaledBackend<'a, B> {
+    /// Wrap `inner` so its delays run at `speed`.
+    fn new(inner: &'a mut B, speed: Speed) -> Self {
+        Self { inner, speed }
+    }
+}
+
+impl<B: InputBackend> InputBackend for ScaledBackend<'_, B> {
+    fn send(&mut self, events: &[KeyInput]) -> u32 {
+        let accepted = self.inner.send(events);
+        trace::record_input(events, accepted);
+        accepted
+    }
+
+    fn key_for_char(&self, c: char) -> Option<(u16, bool)> {
+        self.inner.key_for_char(c)
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        self.inner.sleep(self.speed.scale(ms));
+    }
+
+    fn keyboard_state(&mut self) -> KeyboardState {
+        self.inner.keyboard_state()
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> bool {
+        self.inner.set_clipboard(text)
+    }
+}
+
+/// Whether the target window had focus once the focus delay was over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FocusCheck {
+    /// Not checked: no window was focused (mock keyboards, posting)
+    #[default]
+    Unchecked,
+    /// Focused in time
+    Focused,
+    /// Focused only after activating it again and waiting `extra_ms` more
+    Late { extra_ms: u64 },
+    /// Still not focused after `FOCUS_RETRY_TIMEOUT_MS`; typed anyway
+    Missed,
+}
+
+/// Counters collected while typing a message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TypeStats {
+    /// Characters typed successfully
+    pub chars_typed: usize,
+    /// Character retries after the backend dropped events
+    pub retries: usize,
+    /// Time spent finding and focusing the window before typing
+    pub window_time: Duration,
+    /// How focusing the window went, for the first message that focused it
+    pub focus: FocusCheck,
+}
+
+impl TypeStats {
+    /// Add another message's counters to these.
+    pub fn add(&mut self, other: TypeStats) {
+        self.chars_typed += other.chars_typed;
+        self.retries += other.retries;
+        self.window_time += other.window_time;
+        if self.focus == FocusCheck::Unchecked {
+            self.focus = other.focus;
+        }
+    }
+}
+
+/// Send one event, returning whether it was accepted.
+fn send_event(backend: &mut impl InputBackend, event: KeyInput) -> bool {
+    backend.send(&[event]) == 1
+}
+
+/// Send a complete key press (down + delay + up).
+fn send_key_press(backend: &mut impl InputBackend, vk: u16, keys: &KeyDelays) -> bool {
+    let down = send_event(backend, KeyInput::Key { vk, up: false });
+    backend.sleep(keys.key_press_ms);
+    let up = send_event(backend, KeyInput::Key { vk, up: true });
+    down && up
+}
+
+/// Press Enter, holding Shift around it if `shift` is set.
+///
+/// Batched mode injects the whole sequence in one call and waits
+/// `key_batch_ms` afterwards; stepped mode sends each key separately with
+/// the usual gaps. Returns false if any event was dropped.
+fn send_enter(backend: &mut impl InputBackend, shift: bool, mode: KeyMode, keys: &KeyDelays) -> bool {
+    match mode {
+        KeyMode::Batched => {
+            let enter = [KeyInput::Key { vk: VK_RETURN, up: false }, KeyInput::Key { vk: VK_RETURN, up: true }];
+            let events: Vec<KeyInput> = if shift {
+                let mut events = vec![KeyInput::Key { vk: VK_SHIFT, up: false }];
+                events.extend(enter);
+                events.push(KeyInput::Key { vk: VK_SHIFT, up: true });
+                events
+            } else {
+                enter.to_vec()
+            };
+            let accepted = backend.send(&events) as usize == events.len();
+            backend.sleep(keys.key_batch_ms);
+            accepted
+        }
+        KeyMode::Stepped if shift => {
+            let mut accepted = send_event(backend, KeyInput::Key { vk: VK_SHIFT, up: false });
+            backend.sleep(keys.shift_key_ms);
+            accepted &= send_key_press(backend, VK_RETURN, keys);
+            backend.sleep(keys.shift_key_ms);
+            accepted & send_event(backend, KeyInput::Key { vk: VK_SHIFT, up: true })
+        }
+        KeyMode::Stepped => send_key_press(backend, VK_RETURN, keys),
+    }
+}
+
+/// What became of a character's key events.
+struct CharOutcome {
+    /// Whether its key-down went through, so the character was typed
+    typed: bool,
+    /// Release events that were dropped, to send again
+    unreleased: Vec<KeyInput>,
+}
+
+/// Send `event`, noting it in `unreleased` if it was dropped.
+fn send_release(backend: &mut impl InputBackend, event: KeyInput, unreleased: &mut Vec<KeyInput>) {
+    if !send_event(backend, event) {
+        unreleased.push(event);
+    }
+}
+
+/// Send a single character, handling shift and unicode as needed, then
+/// wait `options.char_delay_ms`. A dropped key-down stops the character
+/// (after letting go of Shift); dropped releases are reported to be sent
+/// again.
+fn send_char(backend: &mut impl InputBackend, c: char, options: &TypingOptions) -> CharOutcome {
+    let keys = &options.keys;
+    let mut unreleased = Vec::new();
+
+    let typed = if let Some((vk, shift)) = backend.key_for_char(c) {
+        let mut ready = true;
+        if shift {
+            ready = send_event(backend, KeyInput::Key { vk: VK_SHIFT, up: false });
+            backend.sleep(keys.shift_key_ms);
+        }
+
+        // Without Shift held the wrong character would come out
+        let down = ready && send_event(backend, KeyInput::Key { vk, up: false });
+        if down {
+            backend.sleep(keys.key_press_ms);
+            send_release(backend, KeyInput::Key { vk, up: true }, &mut unreleased);
+        }
+
+        if shift {
+            backend.sleep(keys.shift_key_ms);
+            // Always release Shift, even if the press was dropped
+            send_release(backend, KeyInput::Key { vk: VK_SHIFT, up: true }, &mut unreleased);
+        }
+        down
+    } else {
+        // Use Unicode input for characters not on the keyboard layout
+        let mut units = [0u16; 2];
+        let mut down = true;
+        for &unit in c.encode_utf16(&mut units).iter() {
+            if !send_event(backend, KeyInput::Unicode { unit, up: false }) {
+                down = false;
+                break;
+            }
+            backend.sleep(keys.unicode_key_ms);
+            send_release(backend, KeyInput::Unicode { unit, up: true }, &mut unreleased);
+        }
+        down
+    };
+
+    backend.sleep(options.char_delay_ms);
+    CharOutcome { typed, unreleased }
+}
+
+/// Type a string character by character, waiting `options.char_delay_ms`
+/// after each.
+///
+/// A character whose key-down was dropped is sent again, and so are the
+/// dropped releases of one that was typed (typing it again would double
+/// it), up to `options.char_retries` retries per character, waiting
+/// `CHAR_RETRY_DELAY_MS` before each.
+pub fn type_text(backend: &mut impl InputBackend, text: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+    let mut stats = TypeStats::default();
+
+    for c in text.chars() {
+        let mut attempts = 0;
+        let mut retry = |stats: &mut TypeStats, backend: &mut _, what: &str| -> Result<(), String> {
+            if attempts == options.char_retries {
+                log(&format!("  Giving up on '{}' after {} retries", c, attempts));
+                return Err(format!(
+                    "Character '{}' {} after {} retries (typed {} of {} characters)",
+                    c,
+                    what,
+                    attempts,
+                    stats.chars_typed,
+                    text.chars().count()
+                ));
+            }
+            attempts += 1;
+            stats.retries += 1;
+            log(&format!("  Input dropped for '{}', retry {}/{}", c, attempts, options.char_retries));
+            InputBackend::sleep(backend, CHAR_RETRY_DELAY_MS);
+            Ok(())
+        };
+        loop {
+            let CharOutcome { typed, mut unreleased } = send_char(backend, c, options);
+            while !unreleased.is_empty() {
+                retry(&mut stats, backend, "wasn't let go of")?;
+                unreleased.retain(|&event| !send_event(backend, event));
+            }
+            if typed {
+                break;
+            }
+            retry(&mut stats, backend, "was dropped")?;
+        }
+        stats.chars_typed += 1;
+    }
+
+    Ok(stats)
+}
+
+/// Put `text` on the clipboard and paste it with Ctrl+V in one batch.
+///
+/// The game can't be asked whether the paste arrived, so success only means
+/// the keys were injected.
+pub fn paste_text(backend: &mut impl InputBackend, text: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+    if !backend.set_clipboard(text) {
+        return Err("Couldn't put the message on the clipboard".to_string());
+    }
+    let events = [
+        KeyInput::Key { vk: VK_CONTROL, up: false },
+        KeyInput::Key { vk: VK_V, up: false },
+        KeyInput::Key { vk: VK_V, up: true },
+        KeyInput::Key { vk: VK_CONTROL, up: true },
+    ];
+    if backend.send(&events) != events.len() as u32 {
+        return Err("Keyboard input was blocked while pasting".to_string());
+    }
+    backend.sleep(options.keys.key_batch_ms);
+    Ok(TypeStats { chars_typed: text.chars().count(), ..TypeStats::default() })
+}
+
+/// Open chat, type (or paste) the message and send it with Enter.
+///
+/// Assumes the target window already has focus.
+pub fn type_message(
+    backend: &mut impl InputBackend,
+    text: &str,
+    options: &TypingOptions,
+) -> Result<TypeStats, String> {
+    let backend = &mut ScaledBackend::new(backend, options.speed);
+
+    // Step 1: Shift+Enter opens all chat, Enter alone opens team chat.
+    // Posted messages go straight to an input box that is already there.
+    let mode = options.key_mode;
+    let opened = match options.chat {
+        _ if options.post => {
+            log("Step 1: Posting to the focused control, no chat to open");
+            true
+        }
+        ChatScope::All => {
+            log(&format!("Step 1: Pressing Shift+Enter to open chat ({})...", mode.label()));
+            send_enter(backend, true, mode, &options.keys)
+        }
+        ChatScope::Team => {
+            log(&format!("Step 1: Pressing Enter to open team chat ({})...", mode.label()));
+            send_enter(backend, false, mode, &options.keys)
+        }
+        ChatScope::Command => {
+            log(&format!("Step 1: Pressing Enter to open chat for a command ({})...", mode.label()));
+            send_enter(backend, false, mode, &options.keys)
+        }
+    };
+    if !opened {
+        return Err("Keyboard input was blocked while opening chat".to_string());
+    }
+    log("  Chat opened");
+
+    // Wait for chat to open
+    backend.sleep(options.keys.chat_open_ms);
+
+    // Step 2: Type the message character by character, or paste it
+    let stats = if options.paste {
+        log(&format!("Step 2: Pasting message ({} chars)...", text.len()));
+        let stats = paste_text(backend, text, options)?;
+        log("  Text pasted");
+        stats
+    } else {
+        log(&format!("Step 2: Typing message ({} chars)...", text.len()));
+        let stats = type_text(backend, text, options)?;
+        log(&format!("  Text typed successfully ({} retries)", stats.retries));
+        stats
+    };
+
+    // Wait for text to be fully typed
+    backend.sleep(options.keys.after_type_ms);
+
+    // Step 3: Enter to send the message
+    log(&format!("Step 3: Pressing Enter to send ({})...", mode.label()));
+    if !send_enter(backend, false, mode, &options.keys) {
+        return Err("Keyboard input was blocked while pressing Enter".to_string());
+    }
+    log("  Enter pressed");
+
+    // Wait before next message
+    backend.sleep(options.keys.after_send_ms);
+
+    Ok(stats)
+}
+
+/// Make sure Caps Lock and held modifiers won't corrupt typed text.
+///
+/// With `compensate` set, waits (up to `MODIFIER_RELEASE_TIMEOUT_MS`) for
+/// held modifiers to be released, calling `on_wait` once with their names so
+/// the user can be prompted, then turns Caps Lock off. Without it, either
+/// condition is refused with an error. Returns whether Caps Lock was turned
+/// off and must be restored with [`restore_keyboard`].
+pub fn prepare_keyboard(
+    backend: &mut impl InputBackend,
+    compensate: bool,
+    mut on_wait: impl FnMut(&[&str]),
+) -> Result<bool, String> {
+    let mut state = backend.keyboard_state();
+
+    let held = state.held_modifiers();
+    if !held.is_empty() {
+        if !compensate {
+            return Err(format!("{} is held down; release it before sending", held.join("+")));
+        }
+
+        log(&format!("Waiting for {} to be released...", held.join("+")));
+        on_wait(&held);
+        let mut waited = 0;
+        while !state.held_modifiers().is_empty() {
+            if waited >= MODIFIER_RELEASE_TIMEOUT_MS {
+                return Err(format!(
+                    "{} was not released within {}s",
+                    state.held_modifiers().join("+"),
+                    MODIFIER_RELEASE_TIMEOUT_MS / 1000
+                ));
+            }
+            backend.sleep(MODIFIER_POLL_MS);
+            waited += MODIFIER_POLL_MS;
+            state = backend.keyboard_state();
+        }
+    }
+
+    if !state.caps_lock {
+        return Ok(false);
+    }
+    if !compensate {
+        return Err("Caps Lock is on and would invert the case of typed text".to_string());
+    }
+
+    log("Caps Lock is on, turning it off while typing");
+    if !send_key_press(backend, VK_CAPITAL, &KeyDelays::default()) {
+        return Err("Keyboard input was blocked while turning Caps Lock off".to_string());
+    }
+    Ok(true)
+}
+
+/// Turn Caps Lock back on if [`prepare_keyboard`] turned it off.
+pub fn restore_keyboard(backend: &mut impl InputBackend, caps_was_on: bool) {
+    if caps_was_on {
+        log("Restoring Caps Lock");
+        send_key_press(backend, VK_CAPITAL, &KeyDelays::default());
+    }
+}
+
+/// Let go of the modifiers the typing sequences press (Shift and Ctrl), in
+/// case a send was cut off between pressing and releasing one.
+pub fn release_modifiers(backend: &mut impl InputBackend) {
+    backend.send(&[KeyInput::Key { vk: VK_SHIFT, up: true }, KeyInput::Key { vk: VK_CONTROL, up: true }]);
+}
+
+/// In-memory backend that records events instead of injecting them.
+///
+/// Used by tests and dry runs. Individual `send` calls can be made to
+/// report rejection to simulate a game dropping input.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    /// Every accepted event, in order
+    pub events: Vec<KeyInput>,
+    /// Every requested sleep in milliseconds, in order
+    pub sleeps: Vec<u64>,
+    /// Number of events in each accepted `send` call, in order
+    pub batch_sizes: Vec<usize>,
+    /// Zero-based indices of `send` calls that should be rejected
+    pub reject_calls: Vec<usize>,
+    /// Simulated keyboard state (Caps Lock follows injected VK_CAPITAL presses)
+    pub keyboard: KeyboardState,
+    /// Number of state queries after which held modifiers are released
+    pub release_modifiers_after: Option<usize>,
+    /// Text last put on the clipboard
+    pub clipboard: Option<String>,
+    calls: usize,
+    state_queries: usize,
+}
+
+impl MockBackend {
+    /// Create a backend that accepts everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a backend that rejects the given `send` calls.
+    pub fn rejecting(reject_calls: &[usize]) -> Self {
+        Self { reject_calls: reject_calls.to_vec(), ..Self::default() }
+    }
+
+    /// Text typed so far, reconstructed from key-down events. Ctrl+V
+    /// counts as typing the clipboard.
+    pub fn typed_text(&self) -> String {
+        let (mut shift, mut ctrl) = (false, false);
+        let mut text = String::new();
+        for event in &self.events {
+            match *event {
+                KeyInput::Key { vk: VK_SHIFT, up } => shift = !up,
+                KeyInput::Key { vk: VK_CONTROL, up } => ctrl = !up,
+                KeyInput::Key { vk: VK_V, up: false } if ctrl => {
+                    text.push_str(self.clipboard.as_deref().unwrap_or_default());
+                }
+                KeyInput::Key { vk, up: false } if vk != VK_RETURN && vk != VK_CAPITAL => {
+                    let c = char::from(vk as u8);
+                    text.push(if shift { c } else { c.to_ascii_lowercase() });
+                }
+                KeyInput::Unicode { unit, up: false } => {
+                    text.extend(char::decode_utf16([unit]).flatten());
+                }
+                _ => {}
+            }
+        }
+        text
+    }
+}
+
+impl InputBackend for MockBackend {
+    fn send(&mut self, events: &[KeyInput]) -> u32 {
+        let call = self.calls;
+        self.calls += 1;
+        if self.reject_calls.contains(&call) {
+            return 0;
+        }
+        for event in events {
+            if *event == (KeyInput::Key { vk: VK_CAPITAL, up: false }) {
+                self.keyboard.caps_lock = !self.keyboard.caps_lock;
+            }
+        }
+        self.events.extend_from_slice(events);
+        self.batch_sizes.push(events.len());
+        events.len() as u32
+    }
+
+    /// Letters, digits and space map like a US layout; everything else is Unicode.
+    fn key_for_char(&self, c: char) -> Option<(u16, bool)> {
+        match c {
+            'a'..='z' => Some((c.to_ascii_uppercase() as u16, false)),
+            'A'..='Z' | '0'..='9' | ' ' => Some((c as u16, c.is_ascii_uppercase())),
+            _ => None,
+        }
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        self.sleeps.push(ms);
+    }
+
+    fn keyboard_state(&mut self) -> KeyboardState {
+        self.state_queries += 1;
+        if self.release_modifiers_after.is_some_and(|n| self.state_queries > n) {
+            self.keyboard = KeyboardState { caps_lock: self.keyboard.caps_lock, ..KeyboardState::default() };
+        }
+        self.keyboard
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> bool {
+        self.clipboard = Some(text.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_text_without_drops() {
+        let mut backend = MockBackend::new();
+        let stats = type_text(&mut backend, "gl Hf é", &TypingOptions::default()).unwrap();
+        assert_eq!(stats, TypeStats { chars_typed: 7, ..TypeStats::default() });
+        assert_eq!(backend.typed_text(), "gl Hf é");
+    }
+
+    #[test]
+    fn test_dropped_character_is_retried() {
+        // Calls 0-1 are 'g' down/up, call 2 is 'l' down
+        let mut backend = MockBackend::rejecting(&[2]);
+        let stats = type_text(&mut backend, "gl", &TypingOptions::default()).unwrap();
+        assert_eq!(stats, TypeStats { chars_typed: 2, retries: 1, ..TypeStats::default() });
+        assert_eq!(backend.typed_text(), "gl");
+        assert!(backend.sleeps.contains(&CHAR_RETRY_DELAY_MS));
+    }
+
+    #[test]
+    fn test_gives_up_after_retry_limit() {
+        // Every event after the first character is rejected
+        let rejected: Vec<usize> = (2..100).collect();
+        let mut backend = MockBackend::rejecting(&rejected);
+        let err = type_text(&mut backend, "gg", &TypingOptions::default()).unwrap_err();
+        assert!(err.contains("'g'"));
+        assert!(err.contains("typed 1 of 2"));
+        let retry_sleeps = backend.sleeps.iter().filter(|&&ms| ms == CHAR_RETRY_DELAY_MS).count();
+        assert_eq!(retry_sleeps, CHAR_RETRY_LIMIT as usize);
+
+        let mut backend = MockBackend::rejecting(&[2]);
+        let options = TypingOptions { char_retries: 0, ..TypingOptions::default() };
+        let err = type_text(&mut backend, "gg", &options).unwrap_err();
+        assert!(err.contains("after 0 retries"));
+        assert!(!backend.sleeps.contains(&CHAR_RETRY_DELAY_MS));
+    }
+
+    #[test]
+    fn test_dropped_release_is_sent_again_without_retyping() {
+        // Call 1 is the first 'g' up; only it is sent again
+        let mut backend = MockBackend::rejecting(&[1]);
+        let stats = type_text(&mut backend, "gg", &TypingOptions::default()).unwrap();
+        assert_eq!(stats, TypeStats { chars_typed: 2, retries: 1, ..TypeStats::default() });
+        assert_eq!(backend.typed_text(), "gg");
+        let g = backend.key_for_char('g').unwrap().0;
+        assert_eq!(backend.events, [
+            KeyInput::Key { vk: g, up: false },
+            KeyInput::Key { vk: g, up: true },
+            KeyInput::Key { vk: g, up: false },
+            KeyInput::Key { vk: g, up: true },
+        ]);
+    }
+
+    #[test]
+    fn test_shift_released_when_press_dropped() {
+        // 'H': call 0 Shift down, call 1 H down (rejected), call 2 Shift up
+        let mut backend = MockBackend::rejecting(&[1]);
+        let stats = type_text(&mut backend, "H", &TypingOptions::default()).unwrap();
+        assert_eq!(stats.retries, 1);
+        let shift_downs = backend.events.iter().filter(|e| **e == KeyInput::Key { vk: VK_SHIFT, up: false }).count();
+        let shift_ups = backend.events.iter().filter(|e| **e == KeyInput::Key { vk: VK_SHIFT, up: true }).count();
+        assert_eq!(shift_downs, shift_ups);
+    }
+
+    #[test]
+    fn test_caps_lock_compensated_and_restored() {
+        let mut backend = MockBackend::new();
+        backend.keyboard.caps_lock = true;
+
+        let toggled = prepare_keyboard(&mut backend, true, |_| panic!("no modifiers held")).unwrap();
+        assert!(toggled);
+        assert!(!backend.keyboard.caps_lock);
+
+        restore_keyboard(&mut backend, toggled);
+        assert!(backend.keyboard.caps_lock);
+    }
+
+    #[test]
+    fn test_release_modifiers_after_cut_off_send() {
+        let mut backend = MockBackend::new();
+        // Cut off between Shift down and Shift up
+        backend.send(&[KeyInput::Key { vk: VK_SHIFT, up: false }]);
+        release_modifiers(&mut backend);
+        assert!(backend.events.ends_with(&[
+            KeyInput::Key { vk: VK_SHIFT, up: true },
+            KeyInput::Key { vk: VK_CONTROL, up: true },
+        ]));
+    }
+
+    #[test]
+    fn test_caps_lock_refused_without_compensation() {
+        let mut backend = MockBackend::new();
+        backend.keyboard.caps_lock = true;
+        assert!(prepare_keyboard(&mut backend, false, |_| {}).is_err());
+        assert!(backend.events.is_empty());
+    }
+
+    #[test]
+    fn test_waits_for_held_modifier_release() {
+        let mut backend = MockBackend::new();
+        backend.keyboard.ctrl = true;
+        backend.release_modifiers_after = Some(3);
+
+        let mut prompted = Vec::new();
+        let toggled = prepare_keyboard(&mut backend, true, |held| {
+            prompted = held.iter().map(|s| s.to_string()).collect();
+        }).unwrap();
+
+        assert!(!toggled);
+        assert_eq!(prompted, vec!["Ctrl"]);
+        assert_eq!(backend.sleeps, vec![MODIFIER_POLL_MS; 3]);
+    }
+
+    #[test]
+    fn test_held_modifier_times_out() {
+        let mut backend = MockBackend::new();
+        backend.keyboard.alt = true;
+        let err = prepare_keyboard(&mut backend, true, |_| {}).unwrap_err();
+        assert!(err.contains("Alt"));
+        assert_eq!(backend.sleeps.iter().sum::<u64>(), MODIFIER_RELEASE_TIMEOUT_MS);
+    }
+
+    #[test]
+    fn test_speed_steps_are_clamped() {
+        let mut speed = Speed::NORMAL;
+        for _ in 0..10 {
+            speed = speed.faster();
+        }
+        assert_eq!(speed.label(), "4x");
+        for _ in 0..10 {
+            speed = speed.slower();
+        }
+        assert_eq!(speed.label(), "0.25x");
+        assert_eq!(Speed::NORMAL.faster().label(), "1.5x");
+    }
+
+    #[test]
+    fn test_scaled_backend_scales_delays() {
+        let mut backend = MockBackend::new();
+        let slow = Speed::NORMAL.slower().slower();
+        let options = TypingOptions { speed: slow, ..TypingOptions::default() };
+        type_message(&mut backend, "a", &options).unwrap();
+        assert_eq!(backend.typed_text(), "a");
+        assert!(backend.sleeps.contains(&(CHAT_OPEN_DELAY_MS * 2)));
+    }
+
+    #[test]
+    fn test_key_delays_used_for_each_pause() {
+        let mut backend = MockBackend::new();
+        let keys = KeyDelays {
+            key_press_ms: 3,
+            shift_key_ms: 4,
+            key_batch_ms: 5,
+            unicode_key_ms: 6,
+            chat_open_ms: 7,
+            after_type_ms: 8,
+            after_send_ms: 9,
+            focus_ms: 10,
+        };
+        let options = TypingOptions { char_delay_ms: 2, keys, ..TypingOptions::default() };
+        type_message(&mut backend, "aé", &options).unwrap();
+        // Open chat, 'a', 'é' as Unicode, then Enter
+        assert_eq!(backend.sleeps, vec![5, 7, 3, 2, 6, 2, 8, 5, 9]);
+
+        let stepped = TypingOptions { key_mode: KeyMode::Stepped, ..options };
+        let mut backend = MockBackend::new();
+        type_message(&mut backend, "", &stepped).unwrap();
+        assert_eq!(backend.sleeps, vec![4, 3, 4, 7, 8, 3, 9]);
+    }
+
+    #[test]
+    fn test_team_chat_opened_without_shift() {
+        let mut backend = MockBackend::new();
+        let options = TypingOptions { chat: ChatScope::Team, ..TypingOptions::default() };
+        type_message(&mut backend, ".", &options).unwrap();
+        assert!(!backend.events.contains(&KeyInput::Key { vk: VK_SHIFT, up: false }));
+        assert_eq!(backend.events.first(), Some(&KeyInput::Key { vk: VK_RETURN, up: false }));
+    }
+
+    /// Notepad-like edit control fed through [`PostedMessage`]s: WM_CHAR
+    /// inserts a character and a posted Enter turns into '\r' the way
+    /// TranslateMessage would. It has no keyboard layout, like the
+    /// PostMessage backend.
+    #[derive(Default)]
+    struct EditControl {
+        text: String,
+        messages: Vec<PostedMessage>,
+    }
+
+    impl InputBackend for EditControl {
+        fn send(&mut self, events: &[KeyInput]) -> u32 {
+            for message in events.iter().filter_map(|&e| PostedMessage::for_event(e)) {
+                match message {
+                    PostedMessage::Char(unit) => self.text.extend(char::decode_utf16([unit]).flatten()),
+                    PostedMessage::KeyDown(VK_RETURN) => self.text.push('\r'),
+                    _ => {}
+                }
+                self.messages.push(message);
+            }
+            events.len() as u32
+        }
+
+        fn key_for_char(&self, _c: char) -> Option<(u16, bool)> {
+            None
+        }
+
+        fn sleep(&mut self, _ms: u64) {}
+
+        fn keyboard_state(&mut self) -> KeyboardState {
+            KeyboardState::default()
+        }
+    }
+
+    #[test]
+    fn test_posted_message_skips_opening_chat() {
+        let mut control = EditControl::default();
+        let options = TypingOptions { post: true, ..TypingOptions::default() };
+        let stats = type_message(&mut control, "gg Wp ö", &options).unwrap();
+
+        assert_eq!(stats.chars_typed, 7);
+        assert_eq!(control.text, "gg Wp ö\r");
+        // Shift is never pressed: a posted Shift wouldn't reach the control's key state
+        assert!(!control.messages.contains(&PostedMessage::KeyDown(VK_SHIFT)));
+        assert_eq!(control.messages.last(), Some(&PostedMessage::KeyUp(VK_RETURN)));
+        assert_eq!(PostedMessage::for_event(KeyInput::Unicode { unit: 0x67, up: true }), None);
+    }
+
+    #[test]
+    fn test_paste_sends_ctrl_v_in_one_batch() {
+        let mut backend = MockBackend::new();
+        let options = TypingOptions { paste: true, ..TypingOptions::default() };
+        let stats = type_message(&mut backend, "gg wp ö", &options).unwrap();
+
+        assert_eq!(stats.chars_typed, 7);
+        assert_eq!(backend.clipboard.as_deref(), Some("gg wp ö"));
+        assert_eq!(backend.typed_text(), "gg wp ö");
+        // Shift+Enter, Ctrl+V, Enter
+        assert_eq!(backend.batch_sizes, vec![4, 4, 2]);
+    }
+
+    #[test]
+    fn test_command_opened_with_enter_and_run_by_the_last_enter() {
+        let mut backend = MockBackend::new();
+        let options = TypingOptions { chat: ChatScope::Command, ..TypingOptions::default() };
+        type_message(&mut backend, "/ff", &options).unwrap();
+
+        let enter = [KeyInput::Key { vk: VK_RETURN, up: false }, KeyInput::Key { vk: VK_RETURN, up: true }];
+        let events = &backend.events;
+        assert!(!events.contains(&KeyInput::Key { vk: VK_SHIFT, up: false }));
+        assert_eq!(events[..2], enter);
+        assert_eq!(events[events.len() - 2..], enter);
+        // The command is typed in between, with no Enter closing chat early
+        let typed = &events[2..events.len() - 2];
+        assert!(typed.iter().all(|e| !matches!(e, KeyInput::Key { vk: VK_RETURN, .. })));
+        assert_eq!(backend.typed_text(), "/ff");
+    }
+
+    #[test]
+    fn test_chat_keys_batched_in_one_call() {
+        let mut backend = MockBackend::new();
+        type_message(&mut backend, "a", &TypingOptions::default()).unwrap();
+
+        let open = [
+            KeyInput::Key { vk: VK_SHIFT, up: false },
+            KeyInput::Key { vk: VK_RETURN, up: false },
+            KeyInput::Key { vk: VK_RETURN, up: true },
+            KeyInput::Key { vk: VK_SHIFT, up: true },
+        ];
+        assert_eq!(backend.events[..4], open);
+        assert_eq!(backend.batch_sizes.first(), Some(&4));
+        assert_eq!(backend.batch_sizes.last(), Some(&2));
+        assert_eq!(backend.events[backend.events.len() - 2..], open[1..3]);
+        assert!(backend.sleeps.contains(&KEY_BATCH_DELAY_MS));
+    }
+
+    #[test]
+    fn test_stepped_chat_keys_sent_separately() {
+        let mut backend = MockBackend::new();
+        let options = TypingOptions { key_mode: KeyMode::Stepped, ..TypingOptions::default() };
+        type_message(&mut backend, "a", &options).unwrap();
+
+        assert_eq!(backend.events[..4], [
+            KeyInput::Key { vk: VK_SHIFT, up: false },
+            KeyInput::Key { vk: VK_RETURN, up: false },
+            KeyInput::Key { vk: VK_RETURN, up: true },
+            KeyInput::Key { vk: VK_SHIFT, up: true },
+        ]);
+        assert!(backend.batch_sizes.iter().all(|&n| n == 1));
+        assert!(!backend.sleeps.contains(&KEY_BATCH_DELAY_MS));
+    }
+}
@@ -0,0 +1,311 @@
+//! Single-line send API
+//!
+//! [`send_line`] types one string into a target window with every part of
+//! the sequence chosen by the caller: the window (title, and optionally
+//! class), chat channel and key mode, delays, send method and a
+//! [`CancelToken`]. [`send_line_with`] runs the same sequence on any
+//! [`InputBackend`], assuming the target already has focus, so embedders
+//! can try it without a game:
+//!
+//! ```
+//! use mad_typing::prelude::*;
+//!
+//! let options = LineOptions::new(DEFAULT_WINDOW_TITLE)
+//!     .with_chat(ChatScope::Team)
+//!     .with_speed(Speed::from_percent(400).unwrap());
+//! let mut backend = MockBackend::new();
+//! let report = send_line_with(&mut backend, "ward dragon", &options).unwrap();
+//!
+//! assert_eq!(report.chars_sent, 11);
+//! assert_eq!(backend.typed_text(), "ward dragon");
+//!
+//! // A cancelled token stops the line before a key is pressed
+//! let token = CancelToken::new();
+//! token.cancel();
+//! let error = send_line_with(&mut backend, "gg", &options.with_cancel(token)).unwrap_err();
+//! assert_eq!(error, PlatformError::Cancelled);
+//! ```
+//!
+//! [`send_text`] is the older entry point and wraps [`send_line`].
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::config::{SendMethod, CHAR_RETRY_LIMIT, CHAR_TYPE_DELAY_MS, RESTORE_SETTLE_DELAY_MS};
+
+use super::input::{type_message, ChatScope, FocusCheck, InputBackend, KeyDelays, KeyMode, Speed, TypeStats, TypingOptions};
+
+/// Why a line couldn't be sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlatformError {
+    /// No window matches the target title (or class)
+    WindowNotFound(String),
+    /// The input was blocked, dropped or refused by the window
+    Input(String),
+    /// The cancel token was set before typing started
+    Cancelled,
+    /// Keyboard simulation isn't available on this platform
+    Unsupported,
+}
+
+impl fmt::Display for PlatformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlatformError::WindowNotFound(title) => {
+                write!(f, "'{}' is not running. Please start the application first.", title)
+            }
+            PlatformError::Input(error) => f.write_str(error),
+            PlatformError::Cancelled => f.write_str("Cancelled before typing started"),
+            PlatformError::Unsupported => f.write_str("Keyboard simulation only supported on Windows"),
+        }
+    }
+}
+
+impl std::error::Error for PlatformError {}
+
+/// Which window a line goes to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetMatcher {
+    /// Part of the window title, compared ignoring case
+    pub title: String,
+    /// Window class used when no title matches; None for the configured
+    /// `window_class` fallback (see [`super::set_window_class`])
+    pub class: Option<String>,
+}
+
+/// Shared flag that stops a line before it starts typing.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every line holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// How [`send_line`] sends.
+#[derive(Clone, Debug)]
+pub struct LineOptions {
+    pub target: TargetMatcher,
+    /// Chat channel opened before typing
+    pub chat: ChatScope,
+    /// How the chat-open and send keys are injected
+    pub key_mode: KeyMode,
+    /// Multiplier applied to every delay but the focus delay
+    pub speed: Speed,
+    /// Delay after each typed character
+    pub char_delay_ms: u64,
+    /// Extra delay after restoring a minimized target window
+    pub restore_delay_ms: u64,
+    /// Pauses between the keys of the sequence
+    pub keys: KeyDelays,
+    /// Typed, pasted or posted; `Auto` pastes
+    pub method: SendMethod,
+    /// Retries of a character whose key events were dropped
+    pub char_retries: u64,
+    pub cancel: Option<CancelToken>,
+}
+
+impl LineOptions {
+    /// Send to the window whose title contains `title`, into all chat, with
+    /// the default delays.
+    pub fn new(title: &str) -> Self {
+        Self {
+            target: TargetMatcher { title: title.to_string(), class: None },
+            chat: ChatScope::All,
+            key_mode: KeyMode::Batched,
+            speed: Speed::NORMAL,
+            char_delay_ms: CHAR_TYPE_DELAY_MS,
+            restore_delay_ms: RESTORE_SETTLE_DELAY_MS,
+            keys: KeyDelays::default(),
+            method: SendMethod::Type,
+            char_retries: CHAR_RETRY_LIMIT,
+            cancel: None,
+        }
+    }
+
+    /// The options `typing` stands for, sending to `title`.
+    pub fn from_typing(title: &str, typing: &TypingOptions) -> Self {
+        let method = match (typing.post, typing.paste) {
+            (true, _) => SendMethod::PostMessage,
+            (false, true) => SendMethod::Paste,
+            (false, false) => SendMethod::Type,
+        };
+        Self {
+            chat: typing.chat,
+            key_mode: typing.key_mode,
+            speed: typing.speed,
+            char_delay_ms: typing.char_delay_ms,
+            restore_delay_ms: typing.restore_delay_ms,
+            keys: typing.keys,
+            method,
+            char_retries: typing.char_retries,
+            ..Self::new(title)
+        }
+    }
+
+    /// Fall back to windows of `class` when no title matches.
+    pub fn with_class(mut self, class: &str) -> Self {
+        self.target.class = Some(class.to_string());
+        self
+    }
+
+    pub fn with_chat(mut self, chat: ChatScope) -> Self {
+        self.chat = chat;
+        self
+    }
+
+    pub fn with_key_mode(mut self, key_mode: KeyMode) -> Self {
+        self.key_mode = key_mode;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_keys(mut self, keys: KeyDelays) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    pub fn with_method(mut self, method: SendMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Err if the cancel token is set.
+    pub fn check_cancelled(&self) -> Result<(), PlatformError> {
+        match &self.cancel {
+            Some(token) if token.is_cancelled() => Err(PlatformError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// The typing sequence these options describe.
+    pub fn typing(&self) -> TypingOptions {
+        TypingOptions {
+            speed: self.speed,
+            char_delay_ms: self.char_delay_ms,
+            chat: self.chat,
+            key_mode: self.key_mode,
+            restore_delay_ms: self.restore_delay_ms,
+            keys: self.keys,
+            paste: matches!(self.method, SendMethod::Paste | SendMethod::Auto),
+            post: self.method == SendMethod::PostMessage,
+            char_retries: self.char_retries,
+        }
+    }
+}
+
+/// What [`send_line`] did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineReport {
+    /// Characters typed or pasted
+    pub chars_sent: usize,
+    /// Character retries after the input was dropped
+    pub retries: usize,
+    /// Time spent finding and focusing the window
+    pub window_time: Duration,
+    /// Time the whole line took
+    pub elapsed: Duration,
+    /// Whether the window had focus after the focus delay
+    pub focus: FocusCheck,
+}
+
+impl From<LineReport> for TypeStats {
+    fn from(report: LineReport) -> Self {
+        TypeStats {
+            chars_typed: report.chars_sent,
+            retries: report.retries,
+            window_time: report.window_time,
+            focus: report.focus,
+        }
+    }
+}
+
+/// Open chat, type `text` and press Enter on `backend`, which must already
+/// deliver to the target.
+pub fn send_line_with(
+    backend: &mut impl InputBackend,
+    text: &str,
+    options: &LineOptions,
+) -> Result<LineReport, PlatformError> {
+    let started = Instant::now();
+    options.check_cancelled()?;
+    let stats = type_message(backend, text, &options.typing()).map_err(PlatformError::Input)?;
+    Ok(LineReport {
+        chars_sent: stats.chars_typed,
+        retries: stats.retries,
+        window_time: Duration::ZERO,
+        elapsed: started.elapsed(),
+        focus: FocusCheck::Unchecked,
+    })
+}
+
+/// Find and focus the target window, then send `text` to it.
+#[cfg(not(windows))]
+pub fn send_line(_text: &str, options: &LineOptions) -> Result<LineReport, PlatformError> {
+    options.check_cancelled()?;
+    Err(PlatformError::Unsupported)
+}
+
+#[cfg(windows)]
+pub use super::windows::send_line;
+
+/// Send `text` to the window `window_title` with `options`; see
+/// [`send_line`] for the full set of options.
+pub fn send_text(text: &str, window_title: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+    send_line(text, &LineOptions::from_typing(window_title, options)).map(TypeStats::from).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::MockBackend;
+
+    #[test]
+    fn test_line_options_map_to_typing() {
+        let typing = TypingOptions { chat: ChatScope::Team, paste: true, ..TypingOptions::default() };
+        let options = LineOptions::from_typing("Notepad", &typing);
+        assert_eq!(options.method, SendMethod::Paste);
+        assert_eq!(options.typing(), typing);
+        assert!(LineOptions::new("Notepad").with_method(SendMethod::PostMessage).typing().post);
+        assert_eq!(options.with_class("RiotWindowClass").target.class.as_deref(), Some("RiotWindowClass"));
+    }
+
+    #[test]
+    fn test_cancelled_line_presses_nothing() {
+        let token = CancelToken::new();
+        let options = LineOptions::new("Notepad").with_cancel(token.clone());
+        let mut backend = MockBackend::new();
+        assert_eq!(send_line_with(&mut backend, "gl", &options).unwrap().chars_sent, 2);
+
+        token.cancel();
+        let sent = backend.events.len();
+        assert_eq!(send_line_with(&mut backend, "hf", &options), Err(PlatformError::Cancelled));
+        assert_eq!(backend.events.len(), sent);
+    }
+}
@@ -0,0 +1,96 @@
+//! Target window matching
+//!
+//! A target matches the windows whose title contains it, ignoring case.
+//! Localized clients translate the title but keep the window class, so
+//! when no title matches and `window_class` is set, the windows of that
+//! class match instead. A title match always wins: the class is only
+//! consulted when the title finds nothing. The rules are pure so they can
+//! be tested on any platform.
+
+use std::sync::Mutex;
+
+use crate::logging::log;
+
+/// Class used when a target's title matches nothing, empty for none.
+static WINDOW_CLASS: Mutex<String> = Mutex::new(String::new());
+
+/// Fall back to windows of `class` when a title matches nothing; empty
+/// turns the fallback off.
+pub fn set_window_class(class: &str) {
+    let class = class.trim();
+    let mut current = WINDOW_CLASS.lock().unwrap_or_else(|e| e.into_inner());
+    if *current != class {
+        if class.is_empty() {
+            log("Window class fallback: off");
+        } else {
+            log(&format!("Window class fallback: {}", class));
+        }
+        *current = class.to_string();
+    }
+}
+
+/// The fallback window class, if one is set.
+pub fn window_class_fallback() -> Option<String> {
+    let class = WINDOW_CLASS.lock().unwrap_or_else(|e| e.into_inner());
+    (!class.is_empty()).then(|| class.clone())
+}
+
+/// Whether a window with `title` and `class` is one `target` may go to:
+/// its title contains `target`, or its class is `fallback`.
+pub fn is_match(title: &str, class: &str, target: &str, fallback: Option<&str>) -> bool {
+    title.to_lowercase().contains(&target.to_lowercase()) || fallback.is_some_and(|f| class.eq_ignore_ascii_case(f))
+}
+
+/// The windows `target` matches among `(window, title, class)`, in order,
+/// with their titles: the title matches, else the windows of class
+/// `fallback`.
+pub fn matching<T>(
+    windows: impl IntoIterator<Item = (T, String, String)>,
+    target: &str,
+    fallback: Option<&str>,
+) -> Vec<(T, String)> {
+    let term = target.to_lowercase();
+    let (mut by_title, mut by_class) = (Vec::new(), Vec::new());
+    for (window, title, class) in windows {
+        if title.to_lowercase().contains(&term) {
+            by_title.push((window, title));
+        } else if fallback.is_some_and(|f| class.eq_ignore_ascii_case(f)) {
+            by_class.push((window, title));
+        }
+    }
+    if by_title.is_empty() {
+        by_class
+    } else {
+        by_title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn windows() -> Vec<(u32, String, String)> {
+        vec![
+            (1, "리그 오브 레전드 (TM) 클라이언트".into(), "RiotWindowClass".into()),
+            (2, "Notepad".into(), "Notepad".into()),
+            (3, "League of Legends".into(), "RCLIENT".into()),
+        ]
+    }
+
+    #[test]
+    fn test_class_is_a_fallback_for_titles() {
+        // The title wins even when the class would match another window
+        let found = matching(windows(), "League of Legends", Some("RiotWindowClass"));
+        assert_eq!(found, vec![(3, "League of Legends".to_string())]);
+
+        // A localized title: only the class finds the game
+        let only_korean = || windows().into_iter().filter(|(id, _, _)| *id != 3);
+        assert!(matching(only_korean(), "League of Legends", None).is_empty());
+        let found = matching(only_korean(), "League of Legends", Some("riotwindowclass"));
+        assert_eq!(found.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![1]);
+
+        assert!(is_match("Notepad", "Notepad", "note", None));
+        assert!(is_match("리그 오브 레전드", "RiotWindowClass", "League", Some("RiotWindowClass")));
+        assert!(!is_match("리그 오브 레전드", "RiotWindowClass", "League", None));
+    }
+}
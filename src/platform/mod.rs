@@ -3,9 +3,35 @@
 //! This module provides cross-platform abstractions for window management
 //! and keyboard input simulation.
 
+mod clock;
+mod display;
+mod handle;
+mod input;
+mod line;
+mod matcher;
+mod sound;
+mod window_kind;
+
 #[cfg(windows)]
 mod windows;
 
+pub use clock::{local_time, LocalTime};
+pub use display::{classify as classify_display_mode, DisplayMode, Rect};
+pub use handle::{HandleCache, LookupCounts, WindowLookup};
+pub use line::{
+    send_line, send_line_with, send_text, CancelToken, LineOptions, LineReport, PlatformError, TargetMatcher,
+};
+pub use matcher::{is_match, matching, set_window_class, window_class_fallback};
+pub use sound::Cue;
+pub use window_kind::{
+    best_kind, classify as classify_window, guard_problem, pick as pick_window, WindowKind, CLIENT_ONLY_WARNING,
+};
+pub use input::{
+    prepare_keyboard, release_modifiers, restore_keyboard, type_message, type_text,
+    ChatScope, FocusCheck, InputBackend, KeyDelays, KeyInput, KeyMode, KeyboardState, MockBackend, PostedMessage, Speed, TypeStats,
+    TypingOptions,
+};
+
 #[cfg(windows)]
 pub use windows::*;
 
@@ -20,12 +46,108 @@ pub fn is_window_running(_title: &str) -> bool {
     true
 }
 
+#[cfg(not(windows))]
+pub fn window_titles() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(not(windows))]
+pub fn window_classes() -> Vec<(String, String)> {
+    Vec::new()
+}
+
+#[cfg(not(windows))]
+pub fn target_kind(_title: &str) -> Option<WindowKind> {
+    None
+}
+
+/// Windows can't be classified here, so the guard has nothing to check.
+#[cfg(not(windows))]
+pub fn game_guard(_targets: &[String]) -> Option<String> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn is_window_minimized(_title: &str) -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+pub fn foreground_window_title() -> Option<String> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn target_window(_title: &str) -> Option<(isize, String)> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn keyboard_layout() -> Option<u32> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn is_caps_lock_on() -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+pub fn is_elevated() -> Option<bool> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn resolve_windows(_targets: &[String]) {}
+
 #[cfg(not(windows))]
 pub fn focus_window(_title: &str) -> bool {
     true
 }
 
 #[cfg(not(windows))]
-pub fn send_text(_text: &str, _window_title: &str) -> Result<(), String> {
-    Err("Keyboard simulation only supported on Windows".to_string())
+pub fn capture_console_window() {}
+
+/// Closing the terminal sends a hang-up, which ends the process; a
+/// terminal lost without one is caught where the UI reads events.
+#[cfg(not(windows))]
+pub fn install_close_handler() {}
+
+#[cfg(not(windows))]
+pub fn focus_console_window() -> bool {
+    false
 }
+
+#[cfg(not(windows))]
+pub fn minimize_console_window() -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+pub fn restore_console_window() -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+pub fn check_keyboard_state(_compensate: bool, _on_wait: impl FnMut(&[&str])) -> Result<bool, String> {
+    Ok(false)
+}
+
+#[cfg(not(windows))]
+pub fn restore_keyboard_state(_caps_was_on: bool) {}
+
+#[cfg(not(windows))]
+pub fn release_modifier_keys() {}
+
+#[cfg(not(windows))]
+pub fn window_display_mode(_title: &str) -> Option<DisplayMode> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn play_cue(_cue: Cue) {
+    sound::bell();
+}
+
+#[cfg(not(windows))]
+pub fn notify(_title: &str, _text: &str) {}
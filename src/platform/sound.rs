@@ -0,0 +1,39 @@
+//! Audible send feedback
+//!
+//! Cues are short tones played while sending so the user can follow
+//! progress when the game covers the terminal. Playing a cue never blocks
+//! the caller.
+
+use std::io::{self, Write};
+
+use crate::config::{FAILED_TONE, FINISHED_TONE, LINE_SENT_TONE};
+
+/// A sound played at a point of a send.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Cue {
+    /// A line was sent
+    LineSent,
+    /// A line failed to send
+    Failed,
+    /// The whole send finished
+    Finished,
+}
+
+impl Cue {
+    /// Frequency in Hz and duration in ms of this cue's tone.
+    pub fn tone(self) -> (u32, u32) {
+        match self {
+            Cue::LineSent => LINE_SENT_TONE,
+            Cue::Failed => FAILED_TONE,
+            Cue::Finished => FINISHED_TONE,
+        }
+    }
+}
+
+/// Ring the terminal bell. Goes to stderr so piped output stays clean.
+pub fn bell() {
+    let mut stderr = io::stderr();
+    let _ = stderr.write_all(b"\x07");
+    let _ = stderr.flush();
+}
@@ -0,0 +1,143 @@
+//! Game window recognition
+//!
+//! The League client (lobby, patcher) and the in-game window can both match
+//! a target title, but only the game has a chat to type into; keys sent to
+//! the client do nothing useful and may trigger its hotkeys. Windows are
+//! told apart by their process and, failing that, their window class. The
+//! classification is pure so it can be tested on any platform.
+
+use crate::config::{CLIENT_PROCESS_NAMES, CLIENT_WINDOW_CLASSES, GAME_PROCESS_NAMES, GAME_WINDOW_CLASSES};
+
+/// Warning for a target title that only matches the client.
+pub const CLIENT_ONLY_WARNING: &str = "only the client is running — messages won't reach a game";
+
+/// What kind of window a title match is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WindowKind {
+    /// The in-game window
+    Game,
+    /// Some other window (e.g. a text editor used for testing)
+    Other,
+    /// The League client, never typed into
+    Client,
+}
+
+impl WindowKind {
+    /// Human readable name for the status line and reports.
+    pub fn describe(self) -> &'static str {
+        match self {
+            WindowKind::Game => "in game",
+            WindowKind::Other => "window found (not the game)",
+            WindowKind::Client => "client only",
+        }
+    }
+}
+
+/// Classify a window from its class name and the path or file name of its
+/// process, if that could be read. The process decides when it is known.
+pub fn classify(class: &str, process: Option<&str>) -> WindowKind {
+    let is = |names: &[&str], name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+    if let Some(process) = process {
+        let file = process.rsplit(['\\', '/']).next().unwrap_or(process);
+        if is(GAME_PROCESS_NAMES, file) {
+            return WindowKind::Game;
+        }
+        if is(CLIENT_PROCESS_NAMES, file) {
+            return WindowKind::Client;
+        }
+    }
+    if is(GAME_WINDOW_CLASSES, class) {
+        WindowKind::Game
+    } else if is(CLIENT_WINDOW_CLASSES, class) {
+        WindowKind::Client
+    } else {
+        WindowKind::Other
+    }
+}
+
+/// Best kind among the windows matching a target: the game, then anything
+/// else, then the client. None if nothing matched.
+pub fn best_kind(kinds: impl IntoIterator<Item = WindowKind>) -> Option<WindowKind> {
+    kinds.into_iter().min()
+}
+
+/// Why guard mode should hold back a send to `target`, given the best kind
+/// of window it matches and whether that window responds, or None if it is
+/// the in-game window and can take focus.
+pub fn guard_problem(target: &str, kind: Option<WindowKind>, responding: bool) -> Option<String> {
+    match kind {
+        None => Some(format!("'{}': no window found", target)),
+        Some(WindowKind::Client) => Some(format!("'{}': {} (lobby or champion select)", target, CLIENT_ONLY_WARNING)),
+        Some(WindowKind::Other) => Some(format!("'{}' matched a window that isn't the game", target)),
+        Some(WindowKind::Game) if !responding => {
+            Some(format!("'{}': the game isn't responding (loading screen?)", target))
+        }
+        Some(WindowKind::Game) => None,
+    }
+}
+
+/// The window to send to among title matches, in enumeration order: the
+/// first game window, else the first other window. Never the client.
+pub fn pick<T>(matches: impl IntoIterator<Item = (T, WindowKind)>) -> Option<T> {
+    let mut fallback = None;
+    for (window, kind) in matches {
+        match kind {
+            WindowKind::Game => return Some(window),
+            WindowKind::Other if fallback.is_none() => fallback = Some(window),
+            _ => {}
+        }
+    }
+    fallback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (title, class, process, expected kind) of windows seen on real systems
+    const SAMPLES: &[(&str, &str, Option<&str>, WindowKind)] = &[
+        (
+            "League of Legends (TM) Client",
+            "RiotWindowClass",
+            Some(r"C:\Riot Games\League of Legends\Game\League of Legends.exe"),
+            WindowKind::Game,
+        ),
+        ("League of Legends", "RCLIENT", Some(r"C:\Riot Games\League of Legends\LeagueClientUx.exe"), WindowKind::Client),
+        ("League of Legends", "RCLIENT", None, WindowKind::Client),
+        ("League of Legends (TM) Client", "RiotWindowClass", None, WindowKind::Game),
+        ("Riot Client", "Chrome_WidgetWin_1", Some(r"C:\Riot Games\Riot Client\RiotClientUx.exe"), WindowKind::Client),
+        ("league.txt - Notepad", "Notepad", Some(r"C:\Windows\System32\notepad.exe"), WindowKind::Other),
+        // The process wins over a misleading class
+        ("League of Legends", "RiotWindowClass", Some("leagueclientux.exe"), WindowKind::Client),
+    ];
+
+    #[test]
+    fn test_recorded_windows_classified() {
+        for &(title, class, process, kind) in SAMPLES {
+            assert_eq!(classify(class, process), kind, "{} ({})", title, class);
+        }
+    }
+
+    #[test]
+    fn test_game_preferred_and_client_never_picked() {
+        use WindowKind::*;
+        assert_eq!(pick([(1, Client), (2, Other), (3, Game)]), Some(3));
+        assert_eq!(pick([(1, Client), (2, Other), (3, Other)]), Some(2));
+        assert_eq!(pick([(1, Client)]), None);
+        assert_eq!(best_kind([Client, Other]), Some(Other));
+        assert_eq!(best_kind([Client]), Some(Client));
+        assert_eq!(best_kind([]), None);
+    }
+
+    #[test]
+    fn test_guard_problem() {
+        assert_eq!(guard_problem("League", Some(WindowKind::Game), true), None);
+        assert_eq!(
+            guard_problem("League", Some(WindowKind::Client), true).unwrap(),
+            "'League': only the client is running — messages won't reach a game (lobby or champion select)"
+        );
+        assert!(guard_problem("League", Some(WindowKind::Game), false).unwrap().contains("loading screen"));
+        assert!(guard_problem("Notepad", Some(WindowKind::Other), true).unwrap().contains("isn't the game"));
+        assert!(guard_problem("League", None, true).is_some());
+    }
+}
@@ -6,31 +6,59 @@
 use std::{
     sync::{Mutex, OnceLock},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetForegroundWindow, GetWindowTextW, EnumWindows, 
-    SetForegroundWindow, ShowWindow, SW_RESTORE, SW_SHOW,
+    GetClassNameW, GetForegroundWindow, GetGUIThreadInfo, GetWindowTextW, GetWindowThreadProcessId, EnumWindows, GetWindowLongW,
+    GetWindowRect, IsHungAppWindow, IsIconic, IsWindow, IsWindowVisible,
+    LoadIconW, PostMessageW, SetForegroundWindow, ShowWindow, GUITHREADINFO, GWL_EXSTYLE, GWL_STYLE, IDI_INFORMATION,
+    SW_MINIMIZE, SW_RESTORE, SW_SHOW, WM_CHAR, WM_KEYDOWN, WM_KEYUP,
 };
-use windows::Win32::Foundation::{HWND, LPARAM};
-use windows::core::BOOL;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND, LPARAM, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST};
+use windows::Win32::System::Console::{
+    GetConsoleWindow, SetConsoleCtrlHandler, CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT,
+};
+use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Ole::CF_UNICODETEXT;
+use windows::Win32::System::Diagnostics::Debug::Beep;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Shell::{
+    IsUserAnAdmin, Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_TIP, NIIF_INFO, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+};
+use windows::core::{BOOL, PWSTR};
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, 
+    SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, VIRTUAL_KEY,
     KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE,
-    VK_RETURN, VK_SHIFT, MapVirtualKeyW, MAPVK_VK_TO_VSC, VkKeyScanW,
+    MapVirtualKeyW, MAPVK_VK_TO_VSC, VkKeyScanW, GetAsyncKeyState, GetKeyState, GetKeyboardLayout,
+    VK_CAPITAL, VK_CONTROL, VK_MENU, VK_SHIFT,
 };
 
-use crate::config::{
-    CHAR_TYPE_DELAY_MS, FOCUS_DELAY_MS, CHAT_OPEN_DELAY_MS,
-    AFTER_TYPE_DELAY_MS, AFTER_SEND_DELAY_MS, KEY_PRESS_DELAY_MS,
-    SHIFT_KEY_DELAY_MS, WINDOW_FOCUS_DELAY_MS, UNICODE_KEY_DELAY_MS,
+use super::clock::LocalTime;
+use super::display::{classify, DisplayMode, Rect};
+use super::handle::{HandleCache, WindowLookup};
+use super::line::{send_line_with, LineOptions, LineReport, PlatformError, TargetMatcher};
+use super::matcher::{is_match, matching, window_class_fallback};
+use super::sound::{bell, Cue};
+use super::window_kind::{best_kind, classify as classify_window, guard_problem, pick as pick_window, WindowKind};
+use super::input::{
+    prepare_keyboard, release_modifiers, restore_keyboard, type_message, FocusCheck, InputBackend, KeyInput, KeyboardState,
+    PostedMessage, TypeStats, TypingOptions,
 };
+use crate::config::{FOCUS_RETRY_TIMEOUT_MS, NOTIFICATION_SECS, RESTORE_FOCUS_TIMEOUT_MS, WINDOW_FOCUS_DELAY_MS};
 use crate::logging::log;
+use crate::shutdown;
+use crate::trace;
 
 // ============== Window Management ==============
 
-/// Check if a window with the given title is currently focused.
+/// Check if a window with the given title is currently focused. The
+/// League client never counts, even if its title matches.
 pub fn is_window_focused(target_title: &str) -> bool {
     unsafe {
         let hwnd: HWND = GetForegroundWindow();
@@ -46,284 +74,718 @@ pub fn is_window_focused(target_title: &str) -> bool {
         }
 
         let title = String::from_utf16_lossy(&buffer[..len as usize]);
-        title.to_lowercase().contains(&target_title.to_lowercase())
+        title.to_lowercase().contains(&target_title.to_lowercase()) && window_kind(hwnd) != WindowKind::Client
     }
 }
 
 /// Check if a window with the given title exists (without focusing it).
+/// A title matching only the League client doesn't count.
 pub fn is_window_running(target_title: &str) -> bool {
-    static CHECK_FOUND: OnceLock<Mutex<bool>> = OnceLock::new();
-    static CHECK_TERM: OnceLock<Mutex<String>> = OnceLock::new();
-    
-    let found = CHECK_FOUND.get_or_init(|| Mutex::new(false));
-    let search_term = CHECK_TERM.get_or_init(|| Mutex::new(String::new()));
-    
-    {
-        let mut term = search_term.lock().unwrap();
-        *term = target_title.to_lowercase();
-        let mut f = found.lock().unwrap();
-        *f = false;
-    }
-    
-    unsafe extern "system" fn check_callback(hwnd: HWND, _: LPARAM) -> BOOL {
-        let found = CHECK_FOUND.get().unwrap();
-        let search_term = CHECK_TERM.get().unwrap();
-        
+    cached_window(target_title).is_some()
+}
+
+/// Titles of all visible windows with a non-empty title.
+pub fn window_titles() -> Vec<String> {
+    window_classes().into_iter().map(|(title, _)| title).collect()
+}
+
+/// Title and class name of all visible windows with a non-empty title.
+pub fn window_classes() -> Vec<(String, String)> {
+    titled_windows()
+        .into_iter()
+        .filter(|(hwnd, _, _)| unsafe { IsWindowVisible(*hwnd) }.as_bool())
+        .map(|(_, title, class)| (title, class))
+        .collect()
+}
+
+/// Find and focus a window by title (case-insensitive partial match).
+pub fn focus_window(target_title: &str) -> bool {
+    log(&format!("focus_window() called with target: '{}'", target_title));
+    log("  Enumerating windows...");
+    match target_window(target_title) {
+        Some((hwnd, title)) => {
+            log(&format!("  Found matching window: '{}'", title));
+            activate_window(HWND(hwnd as *mut std::ffi::c_void));
+            log("  Window focused successfully!");
+            true
+        }
+        None => {
+            log("  ERROR: Window not found!");
+            false
+        }
+    }
+}
+
+/// Enumerate all top-level windows that have a title, with their class.
+fn titled_windows() -> Vec<(HWND, String, String)> {
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam.0 as *mut Vec<(HWND, String, String)>);
         let mut buffer = [0u16; 256];
         let len = GetWindowTextW(hwnd, &mut buffer);
-        
         if len > 0 {
-            let title = String::from_utf16_lossy(&buffer[..len as usize]);
-            let term = search_term.lock().unwrap();
-            if title.to_lowercase().contains(term.as_str()) {
-                let mut f = found.lock().unwrap();
-                *f = true;
-                return BOOL(0);
-            }
+            windows.push((hwnd, String::from_utf16_lossy(&buffer[..len as usize]), window_class(hwnd)));
         }
         BOOL(1)
     }
-    
+
+    let mut windows: Vec<(HWND, String, String)> = Vec::new();
     unsafe {
-        let _ = EnumWindows(Some(check_callback), LPARAM(0));
+        let _ = EnumWindows(Some(collect), LPARAM(&mut windows as *mut _ as isize));
     }
-    
-    *found.lock().unwrap()
+    windows
 }
 
-/// Find and focus a window by title (case-insensitive partial match).
-pub fn focus_window(target_title: &str) -> bool {
-    static FOUND_HWND: OnceLock<Mutex<Option<isize>>> = OnceLock::new();
-    static SEARCH_TERM: OnceLock<Mutex<String>> = OnceLock::new();
-    
-    log(&format!("focus_window() called with target: '{}'", target_title));
-    
-    let found_hwnd = FOUND_HWND.get_or_init(|| Mutex::new(None));
-    let search_term = SEARCH_TERM.get_or_init(|| Mutex::new(String::new()));
-    
-    // Set the search term
-    {
-        let mut term = search_term.lock().unwrap();
-        *term = target_title.to_lowercase();
-    }
-    
-    unsafe extern "system" fn enum_callback(hwnd: HWND, _: LPARAM) -> BOOL {
-        let found_hwnd = FOUND_HWND.get().unwrap();
-        let search_term = SEARCH_TERM.get().unwrap();
-        
-        let mut buffer = [0u16; 256];
-        let len = GetWindowTextW(hwnd, &mut buffer);
-        
-        if len > 0 {
-            let title = String::from_utf16_lossy(&buffer[..len as usize]);
-            let term = search_term.lock().unwrap();
-            if title.to_lowercase().contains(term.as_str()) {
-                log(&format!("  Found matching window: '{}'", title));
-                let mut found = found_hwnd.lock().unwrap();
-                *found = Some(hwnd.0 as isize);
-                return BOOL(0); // Stop enumeration
-            }
+/// Windows `target_title` matches (see [`super::matching`]), in
+/// enumeration order, with their kind.
+fn matching_windows(target_title: &str) -> Vec<(HWND, String, WindowKind)> {
+    matching_windows_with(target_title, window_class_fallback().as_deref())
+}
+
+/// [`matching_windows`] with `class` as the fallback class.
+fn matching_windows_with(target_title: &str, class: Option<&str>) -> Vec<(HWND, String, WindowKind)> {
+    matching(titled_windows(), target_title, class)
+        .into_iter()
+        .map(|(hwnd, title)| (hwnd, title, window_kind(hwnd)))
+        .collect()
+}
+
+/// Find the window to send to for `target_title`: the game if it matches,
+/// else the first other match, never the League client.
+fn find_window(target_title: &str) -> Option<HWND> {
+    target_window(target_title).map(|(hwnd, _)| HWND(hwnd as *mut std::ffi::c_void))
+}
+
+/// Finds windows by enumerating them and checks handles with `IsWindow`.
+struct Win32Lookup;
+
+impl WindowLookup for Win32Lookup {
+    fn find(&mut self, title: &str) -> Option<isize> {
+        target_window(title).map(|(hwnd, _)| hwnd)
+    }
+
+    fn is_valid(&mut self, handle: isize, title: &str) -> bool {
+        let hwnd = HWND(handle as *mut std::ffi::c_void);
+        if !unsafe { IsWindow(Some(hwnd)) }.as_bool() {
+            return false;
         }
-        BOOL(1) // Continue enumeration
+        let mut buffer = [0u16; 256];
+        let len = unsafe { GetWindowTextW(hwnd, &mut buffer) };
+        let window_title = String::from_utf16_lossy(&buffer[..len as usize]);
+        is_match(&window_title, &window_class(hwnd), title, window_class_fallback().as_deref())
     }
-    
-    // Reset found handle
-    {
-        let mut found = found_hwnd.lock().unwrap();
-        *found = None;
+}
+
+/// Target windows of the current send, see [`resolve_windows`].
+static HANDLES: Mutex<Option<HandleCache>> = Mutex::new(None);
+
+fn with_handles<T>(f: impl FnOnce(&mut HandleCache) -> T) -> T {
+    let mut handles = HANDLES.lock().unwrap_or_else(|e| e.into_inner());
+    f(handles.get_or_insert_with(HandleCache::default))
+}
+
+/// Look the send's target windows up once. Later lines only check that
+/// the handles are still valid and enumerate again if one isn't.
+pub fn resolve_windows(targets: &[String]) {
+    let found = with_handles(|cache| {
+        cache.clear();
+        targets.iter().filter(|target| cache.resolve(&mut Win32Lookup, target).is_some()).count()
+    });
+    log(&format!("Resolved {} of {} target window(s)", found, targets.len()));
+}
+
+/// The window to send to for `target_title`, from the handle cache.
+fn cached_window(target_title: &str) -> Option<HWND> {
+    with_handles(|cache| cache.resolve(&mut Win32Lookup, target_title)).map(|hwnd| HWND(hwnd as *mut std::ffi::c_void))
+}
+
+/// Best kind of window `target_title` matches (see [`super::best_kind`]),
+/// None if it matches nothing.
+pub fn target_kind(target_title: &str) -> Option<WindowKind> {
+    best_kind(matching_windows(target_title).into_iter().map(|(_, _, kind)| kind))
+}
+
+/// Why guard mode should hold back a send to `targets` (see
+/// [`super::guard_problem`]), or None if they are all the responding game.
+pub fn game_guard(targets: &[String]) -> Option<String> {
+    targets.iter().find_map(|target| {
+        let responding = find_window(target).is_some_and(|hwnd| unsafe { !IsHungAppWindow(hwnd).as_bool() });
+        guard_problem(target, target_kind(target), responding)
+    })
+}
+
+/// Class name of a window.
+fn window_class(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 256];
+    let len = unsafe { GetClassNameW(hwnd, &mut buffer) };
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+}
+
+/// Full path of the executable that owns a window, if it can be read.
+fn process_path(hwnd: HWND) -> Option<String> {
+    unsafe {
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
     }
-    
+}
+
+/// Whether a window is the game, the League client or something else.
+fn window_kind(hwnd: HWND) -> WindowKind {
+    classify_window(&window_class(hwnd), process_path(hwnd).as_deref())
+}
+
+/// Title of the window that has focus right now, if any.
+pub fn foreground_window_title() -> Option<String> {
     unsafe {
-        log("  Enumerating windows...");
-        let _ = EnumWindows(Some(enum_callback), LPARAM(0));
-        
-        let found = found_hwnd.lock().unwrap();
-        if let Some(hwnd_val) = *found {
-            let hwnd = HWND(hwnd_val as *mut std::ffi::c_void);
-            log("  Calling ShowWindow(SW_RESTORE)...");
-            let _ = ShowWindow(hwnd, SW_RESTORE);
-            log("  Calling ShowWindow(SW_SHOW)...");
-            let _ = ShowWindow(hwnd, SW_SHOW);
-            log("  Calling SetForegroundWindow...");
-            let _ = SetForegroundWindow(hwnd);
-            thread::sleep(Duration::from_millis(WINDOW_FOCUS_DELAY_MS));
-            log("  Window focused successfully!");
-            return true;
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return None;
         }
+        let mut buffer = [0u16; 256];
+        let len = GetWindowTextW(hwnd, &mut buffer);
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
     }
-    
-    log("  ERROR: Window not found!");
-    false
 }
 
-// ============== Keyboard Input ==============
+/// Handle and full title of the window `target_title` matches, as
+/// [`focus_window`] would pick it.
+pub fn target_window(target_title: &str) -> Option<(isize, String)> {
+    pick_window(
+        matching_windows(target_title)
+            .into_iter()
+            .map(|(hwnd, title, kind)| ((hwnd.0 as isize, title), kind)),
+    )
+}
 
-/// Send a key down event using scan codes.
-fn send_key_down(vk: u16) {
+/// Keyboard layout id (HKL) of the focused window's thread.
+pub fn keyboard_layout() -> Option<u32> {
     unsafe {
-        let scan = MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) as u16;
-        let input = INPUT {
-            r#type: INPUT_KEYBOARD,
-            Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                ki: KEYBDINPUT {
-                    wVk: windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY(vk),
-                    wScan: scan,
-                    dwFlags: KEYEVENTF_SCANCODE,
-                    time: 0,
-                    dwExtraInfo: 0,
-                },
-            },
+        let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        let layout = GetKeyboardLayout(thread);
+        (!layout.0.is_null()).then_some(layout.0 as usize as u32)
+    }
+}
+
+/// Whether Caps Lock is on.
+pub fn is_caps_lock_on() -> bool {
+    WindowsBackend.keyboard_state().caps_lock
+}
+
+/// Whether MadTyping runs elevated (as administrator).
+pub fn is_elevated() -> Option<bool> {
+    Some(unsafe { IsUserAnAdmin() }.as_bool())
+}
+
+/// Check whether the target window exists and is minimized.
+pub fn is_window_minimized(target_title: &str) -> bool {
+    find_window(target_title).is_some_and(|hwnd| unsafe { IsIconic(hwnd).as_bool() })
+}
+
+/// Guess how the target window is displayed (windowed, borderless, exclusive...).
+/// Returns None if the window isn't found or can't be queried.
+pub fn window_display_mode(target_title: &str) -> Option<DisplayMode> {
+    let hwnd = find_window(target_title)?;
+    unsafe {
+        let mut window = RECT::default();
+        GetWindowRect(hwnd, &mut window).ok()?;
+
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
         };
-        SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return None;
+        }
+
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+        let to_rect = |r: RECT| Rect { left: r.left, top: r.top, right: r.right, bottom: r.bottom };
+        let mode = classify(to_rect(window), to_rect(info.rcMonitor), style, ex_style);
+        log(&format!("Display mode of '{}': {} (style={:#x}, ex_style={:#x})", target_title, mode.describe(), style, ex_style));
+        Some(mode)
     }
 }
 
-/// Send a key up event using scan codes.
-fn send_key_up(vk: u16) {
+/// Restore, show and bring a window to the foreground.
+///
+/// ShowWindow before SetForegroundWindow works around Windows refusing
+/// to activate minimized or hidden windows.
+fn activate_window(hwnd: HWND) {
     unsafe {
-        let scan = MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) as u16;
-        let input = INPUT {
+        log("  Calling ShowWindow(SW_RESTORE)...");
+        let _ = ShowWindow(hwnd, SW_RESTORE);
+        log("  Calling ShowWindow(SW_SHOW)...");
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        log("  Calling SetForegroundWindow...");
+        let _ = SetForegroundWindow(hwnd);
+    }
+    thread::sleep(Duration::from_millis(WINDOW_FOCUS_DELAY_MS));
+}
+
+// ============== Console Window ==============
+
+/// Our own console window handle, captured at startup (0 if there is none).
+static CONSOLE_HWND: OnceLock<isize> = OnceLock::new();
+
+/// Remember the console window hosting this process.
+/// Must be called at startup, before any other window takes focus.
+pub fn capture_console_window() {
+    let hwnd = unsafe { GetConsoleWindow() };
+    let _ = CONSOLE_HWND.set(hwnd.0 as isize);
+    log(&format!("Console window handle: {:?}", hwnd.0));
+}
+
+/// Get the captured console window, if there is one.
+fn console_window() -> Option<HWND> {
+    match CONSOLE_HWND.get() {
+        Some(&hwnd_val) if hwnd_val != 0 => Some(HWND(hwnd_val as *mut std::ffi::c_void)),
+        _ => None,
+    }
+}
+
+/// Bring the captured console window back to the foreground.
+/// Returns false if no console window was captured.
+pub fn focus_console_window() -> bool {
+    match console_window() {
+        Some(hwnd) => {
+            log("Refocusing console window...");
+            activate_window(hwnd);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Minimize the captured console window.
+/// Returns false (and does nothing) if there is no console window.
+pub fn minimize_console_window() -> bool {
+    match console_window() {
+        Some(hwnd) => {
+            log("Minimizing console window...");
+            unsafe {
+                let _ = ShowWindow(hwnd, SW_MINIMIZE);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Restore the captured console window after minimizing it.
+/// Returns false (and does nothing) if there is no console window.
+pub fn restore_console_window() -> bool {
+    match console_window() {
+        Some(hwnd) => {
+            log("Restoring console window...");
+            unsafe {
+                let _ = ShowWindow(hwnd, SW_RESTORE);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Run the [`shutdown`] when the console window is closed or Windows logs
+/// off or shuts down. Windows ends the process once the handler returns,
+/// or after 5 seconds; Ctrl+C keeps its default handling.
+pub fn install_close_handler() {
+    unsafe extern "system" fn on_console_event(event: u32) -> BOOL {
+        match event {
+            CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => {
+                log(&format!("Console event {}: shutting down", event));
+                shutdown::shutdown("console closed");
+                log("MadTyping exited");
+                true.into()
+            }
+            _ => false.into(),
+        }
+    }
+    if let Err(e) = unsafe { SetConsoleCtrlHandler(Some(on_console_event), true) } {
+        log(&format!("Console close handler not installed: {}", e));
+    }
+}
+
+// ============== Keyboard Input ==============
+
+/// Keyboard backend injecting events with SendInput.
+pub struct WindowsBackend;
+
+impl WindowsBackend {
+    /// Build the INPUT structure for a single key event.
+    fn to_input(event: KeyInput) -> INPUT {
+        let (vk, scan, flags) = match event {
+            KeyInput::Key { vk, up } => {
+                let scan = unsafe { MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) } as u16;
+                let flags = if up { KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP } else { KEYEVENTF_SCANCODE };
+                (vk, scan, flags)
+            }
+            KeyInput::Unicode { unit, up } => {
+                let flags = if up { KEYEVENTF_UNICODE | KEYEVENTF_KEYUP } else { KEYEVENTF_UNICODE };
+                (0, unit, flags)
+            }
+        };
+        INPUT {
             r#type: INPUT_KEYBOARD,
-            Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
+            Anonymous: INPUT_0 {
                 ki: KEYBDINPUT {
-                    wVk: windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY(vk),
+                    wVk: VIRTUAL_KEY(vk),
                     wScan: scan,
-                    dwFlags: KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP,
+                    dwFlags: flags,
                     time: 0,
                     dwExtraInfo: 0,
                 },
             },
+        }
+    }
+
+    /// Hand NUL-terminated UTF-16 `units` to the open clipboard as text.
+    fn place_clipboard_text(units: &[u16]) -> bool {
+        let Ok(memory) = (unsafe { GlobalAlloc(GMEM_MOVEABLE, units.len() * 2) }) else {
+            return false;
         };
-        SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+        unsafe {
+            let target = GlobalLock(memory) as *mut u16;
+            if target.is_null() {
+                let _ = GlobalFree(Some(memory));
+                return false;
+            }
+            std::ptr::copy_nonoverlapping(units.as_ptr(), target, units.len());
+            let _ = GlobalUnlock(memory);
+            // The clipboard owns the memory once this succeeds
+            if SetClipboardData(CF_UNICODETEXT.0 as u32, Some(HANDLE(memory.0))).is_err() {
+                let _ = GlobalFree(Some(memory));
+                return false;
+            }
+        }
+        true
     }
 }
 
-/// Send a complete key press (down + delay + up).
-fn send_key_press(vk: u16) {
-    send_key_down(vk);
-    thread::sleep(Duration::from_millis(KEY_PRESS_DELAY_MS));
-    send_key_up(vk);
+impl InputBackend for WindowsBackend {
+    fn send(&mut self, events: &[KeyInput]) -> u32 {
+        let inputs: Vec<INPUT> = events.iter().map(|&e| Self::to_input(e)).collect();
+        let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            log(&format!("  SendInput injected {}/{} events", sent, inputs.len()));
+        }
+        sent
+    }
+
+    fn key_for_char(&self, c: char) -> Option<(u16, bool)> {
+        // Characters outside the BMP never map to a single key
+        let unit = u16::try_from(u32::from(c)).ok()?;
+        let vk_result = unsafe { VkKeyScanW(unit) };
+        if vk_result == -1 {
+            return None;
+        }
+        let vk = (vk_result & 0xFF) as u16;
+        let shift = (vk_result >> 8) & 1 != 0;
+        Some((vk, shift))
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+
+    fn keyboard_state(&mut self) -> KeyboardState {
+        // High bit of GetAsyncKeyState = physically held, low bit of GetKeyState = toggled
+        let held = |vk: u16| unsafe { GetAsyncKeyState(vk as i32) } < 0;
+        KeyboardState {
+            caps_lock: unsafe { GetKeyState(VK_CAPITAL.0 as i32) } & 1 != 0,
+            shift: held(VK_SHIFT.0),
+            ctrl: held(VK_CONTROL.0),
+            alt: held(VK_MENU.0),
+        }
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> bool {
+        let units: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+        if unsafe { OpenClipboard(None) }.is_err() {
+            log("  Couldn't open the clipboard");
+            return false;
+        }
+        let placed = unsafe { EmptyClipboard() }.is_ok() && Self::place_clipboard_text(&units);
+        let _ = unsafe { CloseClipboard() };
+        placed
+    }
 }
 
-/// Send a single character, handling shift and unicode as needed.
-fn send_char(c: char) {
-    unsafe {
-        // Try using VkKeyScan for ASCII characters
-        let vk_result = VkKeyScanW(c as u16);
-        
-        if vk_result != -1 {
-            let vk = (vk_result & 0xFF) as u16;
-            let shift = (vk_result >> 8) & 1 != 0;
-            
-            if shift {
-                send_key_down(VK_SHIFT.0);
-                thread::sleep(Duration::from_millis(SHIFT_KEY_DELAY_MS));
-            }
-            
-            send_key_press(vk);
-            
-            if shift {
-                thread::sleep(Duration::from_millis(SHIFT_KEY_DELAY_MS));
-                send_key_up(VK_SHIFT.0);
-            }
-        } else {
-            // Use Unicode input for special characters
-            let input_down = INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY(0),
-                        wScan: c as u16,
-                        dwFlags: KEYEVENTF_UNICODE,
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
-            };
-            let input_up = INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY(0),
-                        wScan: c as u16,
-                        dwFlags: KEYEVENTF_UNICODE | KEYEVENTF_KEYUP,
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
+/// Keyboard backend posting WM_KEYDOWN, WM_KEYUP and WM_CHAR straight to
+/// one control. Nothing is focused and the real keyboard is untouched,
+/// but only standard windows (Notepad, chat clients) read these; games
+/// take raw input and never see them.
+pub struct PostMessageBackend {
+    target: HWND,
+}
+
+impl InputBackend for PostMessageBackend {
+    fn send(&mut self, events: &[KeyInput]) -> u32 {
+        let mut accepted = 0;
+        for &event in events {
+            let posted = match PostedMessage::for_event(event) {
+                None => Ok(()),
+                Some(message) => {
+                    let (msg, wparam, lparam) = match message {
+                        PostedMessage::KeyDown(vk) => (WM_KEYDOWN, vk as usize, key_lparam(vk, false)),
+                        PostedMessage::KeyUp(vk) => (WM_KEYUP, vk as usize, key_lparam(vk, true)),
+                        PostedMessage::Char(unit) => (WM_CHAR, unit as usize, 1),
+                    };
+                    unsafe { PostMessageW(Some(self.target), msg, WPARAM(wparam), LPARAM(lparam)) }
+                }
             };
-            SendInput(&[input_down], std::mem::size_of::<INPUT>() as i32);
-            thread::sleep(Duration::from_millis(UNICODE_KEY_DELAY_MS));
-            SendInput(&[input_up], std::mem::size_of::<INPUT>() as i32);
+            match posted {
+                Ok(()) => accepted += 1,
+                Err(e) => log(&format!("  PostMessageW failed: {}", e)),
+            }
         }
+        accepted
+    }
+
+    fn key_for_char(&self, _c: char) -> Option<(u16, bool)> {
+        // Shift can't be held through posted messages, so every character
+        // goes out as WM_CHAR
+        None
     }
-    thread::sleep(Duration::from_millis(CHAR_TYPE_DELAY_MS));
+
+    fn sleep(&mut self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+
+    fn keyboard_state(&mut self) -> KeyboardState {
+        // The physical keyboard doesn't affect posted messages
+        KeyboardState::default()
+    }
+}
+
+/// The lParam of a posted key message: repeat count 1 and the scan code,
+/// plus the previous-state and transition bits for a key-up.
+fn key_lparam(vk: u16, up: bool) -> isize {
+    let scan = unsafe { MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) } as isize;
+    let lparam = 1 | (scan << 16);
+    if up { lparam | (0b11 << 30) } else { lparam }
 }
 
-/// Type a string character by character.
-fn type_text(text: &str) {
-    for c in text.chars() {
-        send_char(c);
+/// The control with keyboard focus inside `hwnd`, e.g. Notepad's edit box.
+fn focused_control(hwnd: HWND) -> Option<HWND> {
+    let thread = unsafe { GetWindowThreadProcessId(hwnd, None) };
+    let mut info = GUITHREADINFO { cbSize: std::mem::size_of::<GUITHREADINFO>() as u32, ..Default::default() };
+    unsafe { GetGUIThreadInfo(thread, &mut info) }.ok()?;
+    (!info.hwndFocus.0.is_null()).then_some(info.hwndFocus)
+}
+
+/// Deliver `text` with posted messages (`send_method = "post_message"`).
+/// Games are refused up front since they'd silently drop every message.
+fn post_text(hwnd: HWND, text: &str, window_title: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+    if window_kind(hwnd) == WindowKind::Game {
+        return Err(format!(
+            "'{}' is the game, which reads raw input and ignores posted messages. Use send_method = \"type\" instead.",
+            window_title
+        ));
     }
+    let Some(target) = focused_control(hwnd) else {
+        return Err(format!("'{}' has no focused text control to post messages to.", window_title));
+    };
+    log(&format!("Posting to the focused control of '{}'", window_title));
+    let stats = type_message(&mut PostMessageBackend { target }, text, options)?;
+    log("send_line() completed successfully");
+    Ok(stats)
+}
+
+/// Check Caps Lock and held modifiers before typing starts.
+/// See [`prepare_keyboard`]; returns whether Caps Lock must be restored.
+pub fn check_keyboard_state(compensate: bool, on_wait: impl FnMut(&[&str])) -> Result<bool, String> {
+    prepare_keyboard(&mut WindowsBackend, compensate, on_wait)
+}
+
+/// Turn Caps Lock back on after [`check_keyboard_state`] turned it off.
+pub fn restore_keyboard_state(caps_was_on: bool) {
+    restore_keyboard(&mut WindowsBackend, caps_was_on);
+}
+
+/// Release Shift and Ctrl; see [`release_modifiers`].
+pub fn release_modifier_keys() {
+    release_modifiers(&mut WindowsBackend);
+}
+
+/// The current local time.
+pub fn local_time() -> LocalTime {
+    let now = unsafe { GetLocalTime() };
+    LocalTime { hour: now.wHour as u8, minute: now.wMinute as u8, weekday: now.wDayOfWeek as u8 }
+}
+
+/// The window `target` picks: from the handle cache with the configured
+/// class fallback, or by enumerating with the target's own class.
+fn target_handle(target: &TargetMatcher) -> Option<HWND> {
+    let Some(class) = &target.class else {
+        return cached_window(&target.title);
+    };
+    let matches = matching_windows_with(&target.title, Some(class));
+    pick_window(matches.into_iter().map(|(hwnd, _, kind)| (hwnd.0 as isize, kind)))
+        .map(|hwnd| HWND(hwnd as *mut std::ffi::c_void))
 }
 
 /// Send text to the target application.
 ///
 /// This function:
-/// 1. Checks if the target window is running
-/// 2. Focuses the target window
-/// 3. Opens all-chat with Shift+Enter
-/// 4. Types the message
+/// 1. Takes the target window from the handle cache (see [`resolve_windows`])
+/// 2. Focuses it, unless it already has focus
+/// 3. Opens chat (Shift+Enter for all chat, Enter for team chat)
+/// 4. Types the message (retrying dropped characters) or pastes it
 /// 5. Sends with Enter
-pub fn send_text(text: &str, window_title: &str) -> Result<(), String> {
+pub fn send_line(text: &str, options: &LineOptions) -> Result<LineReport, PlatformError> {
     let preview: String = text.chars().take(30).collect();
-    log(&format!("send_text() called with: '{}'", preview));
-    
-    // First check if the window is running
-    log(&format!("Checking if '{}' is running...", window_title));
-    if !is_window_running(window_title) {
+    log(&format!("send_line() called with: '{}'", preview));
+    options.check_cancelled()?;
+    let started = Instant::now();
+    let window_title = options.target.title.as_str();
+    let Some(hwnd) = target_handle(&options.target) else {
         log("ERROR: Application is not running!");
-        return Err(format!("'{}' is not running. Please start the application first.", window_title));
+        return Err(PlatformError::WindowNotFound(window_title.to_string()));
+    };
+    let typing = options.typing();
+    if typing.post {
+        let stats = post_text(hwnd, text, window_title, &typing).map_err(PlatformError::Input)?;
+        return Ok(LineReport {
+            chars_sent: stats.chars_typed,
+            retries: stats.retries,
+            elapsed: started.elapsed(),
+            ..LineReport::default()
+        });
     }
-    log("Application is running, proceeding to focus...");
-    
+
+    let window_started = Instant::now();
+    let has_focus = || unsafe { GetForegroundWindow() } == hwnd;
+
     // Focus target window before sending
-    if !focus_window(window_title) {
-        log("ERROR: Failed to focus window");
-        return Err(format!("Window '{}' not found.", window_title));
+    let minimized = unsafe { IsIconic(hwnd) }.as_bool();
+    let restore_started = Instant::now();
+    if minimized || !has_focus() {
+        log(&format!("Focusing '{}'", window_title));
+        activate_window(hwnd);
+    }
+
+    // Keys sent during the restore animation get lost, so wait for focus
+    // and then some more
+    if minimized {
+        let deadline = restore_started + Duration::from_millis(RESTORE_FOCUS_TIMEOUT_MS);
+        while !has_focus() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        log(&format!(
+            "'{}' was minimized: focus verified {} ms after restore, settling {} ms",
+            window_title,
+            restore_started.elapsed().as_millis(),
+            options.restore_delay_ms
+        ));
+        thread::sleep(Duration::from_millis(options.restore_delay_ms));
     }
 
     // Wait for window to be fully focused
-    thread::sleep(Duration::from_millis(FOCUS_DELAY_MS));
-
-    // Step 1: Shift+Enter to open all chat
-    log("Step 1: Pressing Shift+Enter to open chat...");
-    send_key_down(VK_SHIFT.0);
-    thread::sleep(Duration::from_millis(SHIFT_KEY_DELAY_MS));
-    send_key_press(VK_RETURN.0);
-    thread::sleep(Duration::from_millis(SHIFT_KEY_DELAY_MS));
-    send_key_up(VK_SHIFT.0);
-    log("  Shift+Enter sent");
-    
-    // Wait for chat to open
-    thread::sleep(Duration::from_millis(CHAT_OPEN_DELAY_MS));
-
-    // Step 2: Type the message character by character
-    log(&format!("Step 2: Typing message ({} chars)...", text.len()));
-    type_text(text);
-    log("  Text typed successfully");
-    
-    // Wait for text to be fully typed
-    thread::sleep(Duration::from_millis(AFTER_TYPE_DELAY_MS));
-
-    // Step 3: Enter to send the message
-    log("Step 3: Pressing Enter to send...");
-    send_key_press(VK_RETURN.0);
-    log("  Enter pressed");
-
-    // Wait before next message
-    thread::sleep(Duration::from_millis(AFTER_SEND_DELAY_MS));
-    log("send_text() completed successfully");
-
-    Ok(())
+    thread::sleep(Duration::from_millis(options.keys.focus_ms));
+    if trace::is_active() {
+        trace::record_focus(window_title, has_focus());
+    }
+    // Some games take longer to come up: activate again and wait, noting
+    // how much longer it took so later sends can wait that long up front
+    let focus = if has_focus() {
+        FocusCheck::Focused
+    } else {
+        let retry_started = Instant::now();
+        activate_window(hwnd);
+        let deadline = retry_started + Duration::from_millis(FOCUS_RETRY_TIMEOUT_MS);
+        while !has_focus() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let extra_ms = retry_started.elapsed().as_millis() as u64;
+        if has_focus() {
+            log(&format!("'{}' had no focus after {} ms; focused {} ms later", window_title, options.keys.focus_ms, extra_ms));
+            FocusCheck::Late { extra_ms }
+        } else {
+            log(&format!("'{}' still had no focus after {} ms more; typing anyway", window_title, extra_ms));
+            FocusCheck::Missed
+        }
+    };
+    let window_time = window_started.elapsed();
+
+    // The token may have been set while the window came up
+    let mut report = send_line_with(&mut WindowsBackend, text, options)?;
+    report.window_time = window_time;
+    report.focus = focus;
+    report.elapsed = started.elapsed();
+    log("send_line() completed successfully");
+
+    Ok(report)
+}
+
+// ============== Sound ==============
+
+/// Play a cue's tone on a background thread (`Beep` blocks for the whole
+/// tone). Falls back to the terminal bell if there is no sound device.
+pub fn play_cue(cue: Cue) {
+    let (frequency, duration_ms) = cue.tone();
+    thread::spawn(move || {
+        if unsafe { Beep(frequency, duration_ms) }.is_err() {
+            bell();
+        }
+    });
+}
+
+// ============== Notifications ==============
+
+/// Show a tray balloon notification, best-effort: failures are only logged.
+///
+/// The icon is attached to our console window and removed again after
+/// [`NOTIFICATION_SECS`] on a background thread.
+pub fn notify(title: &str, text: &str) {
+    let Some(hwnd) = console_window() else {
+        log("No console window to attach a notification to");
+        return;
+    };
+    let hwnd_val = hwnd.0 as isize;
+    let (title, text) = (title.to_string(), text.to_string());
+    thread::spawn(move || {
+        let mut data = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: HWND(hwnd_val as *mut std::ffi::c_void),
+            uID: NOTIFICATION_ICON_ID,
+            uFlags: NIF_ICON | NIF_TIP | NIF_INFO,
+            dwInfoFlags: NIIF_INFO,
+            ..Default::default()
+        };
+        copy_wide(&mut data.szTip, "MadTyping");
+        copy_wide(&mut data.szInfoTitle, &title);
+        copy_wide(&mut data.szInfo, &text);
+        unsafe {
+            data.hIcon = LoadIconW(None, IDI_INFORMATION).unwrap_or_default();
+            if !Shell_NotifyIconW(NIM_ADD, &data).as_bool() {
+                log("Notification failed: Shell_NotifyIconW(NIM_ADD) returned false");
+                return;
+            }
+            thread::sleep(Duration::from_secs(NOTIFICATION_SECS));
+            let _ = Shell_NotifyIconW(NIM_DELETE, &data);
+        }
+    });
+}
+
+/// Tray icon id used for notifications (one per console window).
+const NOTIFICATION_ICON_ID: u32 = 1;
+
+/// Copy `text` into a fixed-size UTF-16 buffer, truncating and keeping the
+/// terminating NUL.
+fn copy_wide(buffer: &mut [u16], text: &str) {
+    let max = buffer.len() - 1;
+    for (slot, unit) in buffer.iter_mut().zip(text.encode_utf16().take(max)) {
+        *slot = unit;
+    }
 }
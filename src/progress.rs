@@ -0,0 +1,223 @@
+//! Headless send progress for MadTyping
+//!
+//! `--send` reports each line as it goes. `--progress` picks how much of
+//! that is printed:
+//!
+//! - `full` (the default): one line per event
+//! - `compact`: a single status line rewritten in place on a terminal of
+//!   its own (stderr), with failures and notices printed like in `full`.
+//!   Without a terminal to rewrite on, every [`COMPACT_EVERY`]th line is
+//!   printed instead.
+//! - `none`: nothing until the final summary (or the `--json-result` JSON)
+//!
+//! [`Progress`] turns the [`SendEvent`]s of a [`SendSession`] into that
+//! output, on any writers.
+//!
+//! [`SendSession`]: crate::send::SendSession
+
+use std::io::Write;
+
+use crate::config::SendMethod;
+use crate::send::SendEvent;
+use crate::timed::format_clock;
+
+/// Lines between two status lines of `compact` when the output isn't a
+/// terminal.
+pub const COMPACT_EVERY: usize = 10;
+
+/// How much progress `--send` prints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProgressMode {
+    Compact,
+    #[default]
+    Full,
+    None,
+}
+
+impl ProgressMode {
+    pub const ALL: [ProgressMode; 3] = [ProgressMode::Compact, ProgressMode::Full, ProgressMode::None];
+    pub const NAMES: [&'static str; 3] = ["compact", "full", "none"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|&n| n == name).map(|i| Self::ALL[i])
+    }
+}
+
+/// Printer of send progress in one [`ProgressMode`].
+pub struct Progress<W: Write> {
+    mode: ProgressMode,
+    out: W,
+    /// Terminal the `compact` status line is rewritten on, if there is one
+    live: Option<W>,
+    waiting_for: Option<usize>,
+    lost_at: Option<usize>,
+    /// Lines finished so far
+    finished: usize,
+    /// Whether a status line is shown and not yet ended
+    status_shown: bool,
+}
+
+impl<W: Write> Progress<W> {
+    pub fn new(mode: ProgressMode, out: W, live: Option<W>) -> Self {
+        Self { mode, out, live, waiting_for: None, lost_at: None, finished: 0, status_shown: false }
+    }
+
+    /// Print a message about the send as a whole, e.g. the target window.
+    pub fn note(&mut self, text: &str) {
+        if self.mode != ProgressMode::None {
+            self.message(text);
+        }
+    }
+
+    /// Print what `event` says, as far as the mode wants it.
+    pub fn event(&mut self, event: &SendEvent) {
+        if self.mode == ProgressMode::None {
+            return;
+        }
+        match event {
+            SendEvent::LineFinished { index, total, line, target, result, .. } => {
+                self.finished += 1;
+                match result {
+                    Ok(_) => self.status(&format!("[{}/{}] ✓ {} → {}", index + 1, total, line.text, target), true),
+                    Err(e) => self.message(&format!("[{}/{}] ✗ {} → {}: {}", index + 1, total, line.text, target, e)),
+                }
+            }
+            // Announce each wait once rather than every tick
+            SendEvent::Waiting { index, total, line, at, left, .. } if self.waiting_for != Some(*index) => {
+                self.waiting_for = Some(*index);
+                let text = format!("[{}/{}] waiting for {} ({} left): {}", index + 1, total, format_clock(*at), format_clock(*left), line.text);
+                self.status(&text, false);
+            }
+            SendEvent::MethodChosen { target, method, reason } => {
+                let what = match method {
+                    SendMethod::Paste => "pasting into",
+                    SendMethod::PostMessage => "posting to",
+                    _ => "typing into",
+                };
+                self.message(&format!("{} '{}' ({})", what, target, reason));
+            }
+            SendEvent::WindowLost { index, total, target, left, .. } if self.lost_at != Some(*index) => {
+                self.lost_at = Some(*index);
+                let text = format!(
+                    "[{}/{}] window lost: waiting up to {}s for '{}' to come back",
+                    index + 1,
+                    total,
+                    left.as_secs_f64().ceil() as u64,
+                    target
+                );
+                self.status(&text, false);
+            }
+            _ => {}
+        }
+    }
+
+    /// End the status line, so the summary starts on a line of its own.
+    pub fn finish(&mut self) {
+        if let Some(live) = self.live.as_mut().filter(|_| self.status_shown) {
+            let _ = writeln!(live);
+            self.status_shown = false;
+        }
+    }
+
+    /// Print a line of its own in every mode, e.g. the final summary or a
+    /// question, clearing the status line first.
+    pub fn message(&mut self, text: &str) {
+        if let Some(live) = self.live.as_mut().filter(|_| self.status_shown) {
+            let _ = write!(live, "\r\x1b[2K");
+            let _ = live.flush();
+            self.status_shown = false;
+        }
+        let _ = writeln!(self.out, "{}", text);
+    }
+
+    /// A status update: rewritten in place by a live `compact`. Without a
+    /// terminal, `compact` prints only every [`COMPACT_EVERY`]th finished
+    /// line (`counted`) and the waits.
+    fn status(&mut self, text: &str, counted: bool) {
+        match (self.mode, self.live.as_mut()) {
+            (ProgressMode::Compact, Some(live)) => {
+                let _ = write!(live, "\r\x1b[2K{}", text);
+                let _ = live.flush();
+                self.status_shown = true;
+            }
+            (ProgressMode::Compact, None) if counted && !self.finished.is_multiple_of(COMPACT_EVERY) => {}
+            _ => self.message(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Line;
+    use crate::platform::TypeStats;
+    use std::time::Duration;
+
+    /// Output of `mode` for 12 lines, the 3rd failing, and a wait on the
+    /// 5th; what went to the status terminal, if `live`, comes second.
+    fn output(mode: ProgressMode, live: bool) -> (String, String) {
+        let (mut out, mut status) = (Vec::new(), Vec::new());
+        let mut progress = Progress::new(mode, &mut out, live.then_some(&mut status));
+        progress.note("Sending gg.txt (12 lines)...");
+        let line = Line::new("gg");
+        let ok = Ok(TypeStats::default());
+        let failed = Err("no focus".to_string());
+        for index in 0..12 {
+            if index == 4 {
+                for left in [2, 1] {
+                    let (at, left) = (Duration::from_secs(60), Duration::from_secs(left));
+                    progress.event(&SendEvent::Waiting { index, total: 12, line: &line, target: "League", at, left });
+                }
+            }
+            let result = if index == 2 { &failed } else { &ok };
+            let elapsed = Duration::ZERO;
+            progress.event(&SendEvent::LineFinished { index, total: 12, line: &line, target: "League", result, elapsed });
+        }
+        progress.finish();
+        (String::from_utf8(out).unwrap(), String::from_utf8(status).unwrap())
+    }
+
+    #[test]
+    fn test_full_prints_every_event() {
+        let (text, status) = output(ProgressMode::Full, true);
+        assert_eq!(status, "");
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[0], "Sending gg.txt (12 lines)...");
+        assert_eq!(lines[3], "[3/12] ✗ gg → League: no focus");
+        assert_eq!(lines[5], "[5/12] waiting for 01:00 (00:02 left): gg");
+        assert_eq!(lines[13], "[12/12] ✓ gg → League");
+        assert!(!text.contains('\r'));
+    }
+
+    #[test]
+    fn test_compact_rewrites_one_line_on_a_terminal() {
+        let (text, status) = output(ProgressMode::Compact, true);
+        // Messages print as they would without a terminal; only the status
+        // line goes to it, cleared before each message
+        assert_eq!(text, "Sending gg.txt (12 lines)...\n[3/12] ✗ gg → League: no focus\n");
+        assert_eq!(status.matches('\n').count(), 1);
+        assert!(status.starts_with("\r\x1b[2K[1/12] ✓ gg → League\r\x1b[2K[2/12] ✓ gg → League\r\x1b[2K\r\x1b[2K[4/12]"));
+        assert!(status.ends_with("\r\x1b[2K[12/12] ✓ gg → League\n"));
+    }
+
+    #[test]
+    fn test_compact_prints_every_tenth_line_without_a_terminal() {
+        assert_eq!(output(ProgressMode::Compact, false).0, [
+            "Sending gg.txt (12 lines)...\n",
+            "[3/12] ✗ gg → League: no focus\n",
+            "[5/12] waiting for 01:00 (00:02 left): gg\n",
+            "[10/12] ✓ gg → League\n",
+        ].concat());
+    }
+
+    #[test]
+    fn test_none_prints_only_messages() {
+        assert_eq!(output(ProgressMode::None, true), (String::new(), String::new()));
+        let mut out = Vec::new();
+        Progress::new(ProgressMode::None, &mut out, None).message("Sent 12 of 12 line(s).");
+        assert_eq!(out, b"Sent 12 of 12 line(s).\n");
+        assert_eq!(ProgressMode::from_name("compact"), Some(ProgressMode::Compact));
+        assert_eq!(ProgressMode::from_name("quiet"), None);
+    }
+}
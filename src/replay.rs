@@ -0,0 +1,386 @@
+//! Recorded send sessions for MadTyping
+//!
+//! With `record_sessions` on, each send saves what it did to a
+//! `madtyping-<time>.session.json` file in the data directory: the options
+//! it started with and, for every line and target, the text, the typing
+//! options in effect (after any speed or delay change), when it was typed
+//! and how it went. `--replay FILE` types the same lines again with the
+//! same pauses in between and leaves out the lines that were skipped, as a
+//! dry run (nothing reaches the keyboard) or into the real windows.
+//!
+//! The file carries a schema `version`; files from a newer version are
+//! refused rather than misread.
+
+use std::{fs, path::{Path, PathBuf}, thread, time::Duration};
+
+use crate::config::GAME_MESSAGE_MAX_CHARS;
+use crate::json::{self, Value};
+use crate::paths;
+use crate::platform::{self, ChatScope, KeyMode, MockBackend, Speed, TypeStats, TypingOptions};
+use crate::report::{file_stamp, now_secs};
+use crate::send::{message_parts, BroadcastMode, LineSender, SendDelays, SendReport, TargetReport};
+
+/// Version of the `.session.json` layout written by this build.
+pub const SESSION_SCHEMA_VERSION: u64 = 1;
+
+/// Suffix of recorded session files.
+pub const SESSION_FILE_SUFFIX: &str = ".session.json";
+
+/// What happened to one line on one target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepOutcome {
+    Sent,
+    Failed(String),
+    /// Sent, but the verifier didn't see it in chat; typed on replay
+    Unverified,
+    /// Skipped by the user (manual advance); not typed on replay
+    Skipped,
+}
+
+/// One line typed (or skipped) on one target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// Zero-based line index in the file
+    pub line: usize,
+    pub target: String,
+    pub text: String,
+    /// Typing options in effect for this line
+    pub options: TypingOptions,
+    /// Milliseconds from the start of the send until typing began
+    pub at_ms: u64,
+    /// How long typing took
+    pub duration_ms: u64,
+    pub outcome: StepOutcome,
+}
+
+/// A recorded send: the options it started with and every step in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    pub targets: Vec<String>,
+    pub mode: BroadcastMode,
+    /// Delays at the start of the send
+    pub delays: SendDelays,
+    pub split_long_lines: bool,
+    /// Game message limit the lines were split at, None for no limit
+    pub char_limit: Option<usize>,
+    pub manual_advance: bool,
+    /// Send clock start for timed lines, in milliseconds
+    pub clock_start_ms: u64,
+    pub steps: Vec<Step>,
+}
+
+impl Recording {
+    /// The recording as a JSON document.
+    pub fn to_json(&self) -> String {
+        let targets: Vec<String> = self.targets.iter().map(|t| json::string(t)).collect();
+        let steps: Vec<String> = self.steps.iter().map(step_json).collect();
+        let d = &self.delays;
+        format!(
+            "{{\n  \"version\": {},\n  \"targets\": [{}],\n  \"mode\": \"{}\",\n  \"split_long_lines\": {},\n  \
+             \"chat_char_limit\": {},\n  \"manual_advance\": {},\n  \"clock_start_ms\": {},\n  \"delays\": {{\"char_type_ms\": {}, \"next_line_ms\": {}, \
+             \"broadcast_settle_ms\": {}, \"restore_settle_ms\": {}, \"blank_line_pause_ms\": {}}},\n  \"steps\": [\n{}\n  ]\n}}\n",
+            SESSION_SCHEMA_VERSION,
+            targets.join(", "),
+            mode_name(self.mode),
+            self.split_long_lines,
+            self.char_limit.map_or("null".to_string(), |limit| limit.to_string()),
+            self.manual_advance,
+            self.clock_start_ms,
+            d.char_type_ms,
+            d.next_line_ms,
+            d.broadcast_settle_ms,
+            d.restore_settle_ms,
+            d.blank_line_pause_ms,
+            steps.join(",\n")
+        )
+    }
+
+    /// Read a recording from its JSON document.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let doc = json::parse(text)?;
+        let version = doc.get("version").and_then(Value::as_u64).ok_or("Not a recorded session (no version)")?;
+        if version > SESSION_SCHEMA_VERSION {
+            return Err(format!(
+                "This session was recorded by a newer MadTyping (schema {}; this build reads up to {})",
+                version, SESSION_SCHEMA_VERSION
+            ));
+        }
+        let delays = doc.get("delays").ok_or("Missing 'delays'")?;
+        let delay = |key: &str| number(delays, key);
+        Ok(Self {
+            targets: doc
+                .get("targets")
+                .and_then(Value::as_array)
+                .ok_or("Missing 'targets'")?
+                .iter()
+                .map(|t| t.as_str().map(str::to_string).ok_or("Bad target"))
+                .collect::<Result<_, _>>()?,
+            mode: match text_of(&doc, "mode")? {
+                "per_line" => BroadcastMode::PerLine,
+                "per_file" => BroadcastMode::PerFile,
+                other => return Err(format!("Unknown mode '{}'", other)),
+            },
+            delays: SendDelays {
+                char_type_ms: delay("char_type_ms")?,
+                next_line_ms: delay("next_line_ms")?,
+                broadcast_settle_ms: delay("broadcast_settle_ms")?,
+                restore_settle_ms: delay("restore_settle_ms")?,
+                blank_line_pause_ms: delay("blank_line_pause_ms")?,
+                ..SendDelays::default()
+            },
+            split_long_lines: flag(&doc, "split_long_lines")?,
+            // Sessions recorded before the limit was configurable used League's;
+            // 0 is no limit, as null is
+            char_limit: match doc.get("chat_char_limit") {
+                None => Some(GAME_MESSAGE_MAX_CHARS),
+                Some(Value::Null) => None,
+                Some(_) => Some(number(&doc, "chat_char_limit")? as usize).filter(|&limit| limit > 0),
+            },
+            manual_advance: flag(&doc, "manual_advance")?,
+            clock_start_ms: number(&doc, "clock_start_ms")?,
+            steps: doc
+                .get("steps")
+                .and_then(Value::as_array)
+                .ok_or("Missing 'steps'")?
+                .iter()
+                .enumerate()
+                .map(|(i, step)| parse_step(step).map_err(|e| format!("Step {}: {}", i + 1, e)))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn step_json(step: &Step) -> String {
+    let (status, error) = match &step.outcome {
+        StepOutcome::Sent => ("sent", String::new()),
+        StepOutcome::Failed(e) => ("failed", format!(", \"error\": {}", json::string(e))),
+        StepOutcome::Unverified => ("unverified", String::new()),
+        StepOutcome::Skipped => ("skipped", String::new()),
+    };
+    let o = &step.options;
+    format!(
+        "    {{\"line\": {}, \"target\": {}, \"text\": {}, \"at_ms\": {}, \"duration_ms\": {}, \"speed_percent\": {}, \
+         \"char_delay_ms\": {}, \"chat\": \"{}\", \"key_mode\": \"{}\", \"restore_delay_ms\": {}, \"paste\": {}, \"post\": {}, \"status\": \"{}\"{}}}",
+        step.line + 1,
+        json::string(&step.target),
+        json::string(&step.text),
+        step.at_ms,
+        step.duration_ms,
+        o.speed.percent(),
+        o.char_delay_ms,
+        chat_name(o.chat),
+        o.key_mode.label(),
+        o.restore_delay_ms,
+        o.paste,
+        o.post,
+        status,
+        error
+    )
+}
+
+fn parse_step(step: &Value) -> Result<Step, String> {
+    let percent = number(step, "speed_percent")?;
+    let speed = u32::try_from(percent).ok().and_then(Speed::from_percent).ok_or(format!("Unknown speed {}%", percent))?;
+    Ok(Step {
+        line: (number(step, "line")? as usize).checked_sub(1).ok_or("Lines count from 1")?,
+        target: text_of(step, "target")?.to_string(),
+        text: text_of(step, "text")?.to_string(),
+        options: TypingOptions {
+            speed,
+            char_delay_ms: number(step, "char_delay_ms")?,
+            chat: match text_of(step, "chat")? {
+                "all" => ChatScope::All,
+                "team" => ChatScope::Team,
+                "command" => ChatScope::Command,
+                other => return Err(format!("Unknown chat '{}'", other)),
+            },
+            key_mode: match text_of(step, "key_mode")? {
+                "batched" => KeyMode::Batched,
+                "stepped" => KeyMode::Stepped,
+                other => return Err(format!("Unknown key mode '{}'", other)),
+            },
+            restore_delay_ms: number(step, "restore_delay_ms")?,
+            // Recordings from before pasting existed typed every line
+            paste: step.get("paste").and_then(Value::as_bool).unwrap_or(false),
+            post: step.get("post").and_then(Value::as_bool).unwrap_or(false),
+            ..TypingOptions::default()
+        },
+        at_ms: number(step, "at_ms")?,
+        duration_ms: number(step, "duration_ms")?,
+        outcome: match text_of(step, "status")? {
+            "sent" => StepOutcome::Sent,
+            "failed" => StepOutcome::Failed(step.get("error").and_then(Value::as_str).unwrap_or_default().to_string()),
+            "unverified" => StepOutcome::Unverified,
+            "skipped" => StepOutcome::Skipped,
+            other => return Err(format!("Unknown status '{}'", other)),
+        },
+    })
+}
+
+fn number(value: &Value, key: &str) -> Result<u64, String> {
+    value.get(key).and_then(Value::as_u64).ok_or_else(|| format!("Missing or bad '{}'", key))
+}
+
+fn text_of<'a>(value: &'a Value, key: &str) -> Result<&'a str, String> {
+    value.get(key).and_then(Value::as_str).ok_or_else(|| format!("Missing or bad '{}'", key))
+}
+
+fn flag(value: &Value, key: &str) -> Result<bool, String> {
+    value.get(key).and_then(Value::as_bool).ok_or_else(|| format!("Missing or bad '{}'", key))
+}
+
+fn mode_name(mode: BroadcastMode) -> &'static str {
+    match mode {
+        BroadcastMode::PerLine => "per_line",
+        BroadcastMode::PerFile => "per_file",
+    }
+}
+
+fn chat_name(chat: ChatScope) -> &'static str {
+    match chat {
+        ChatScope::Team => "team",
+        ChatScope::Command => "command",
+        _ => "all",
+    }
+}
+
+/// Write `recording` to `madtyping-<time>.session.json` in the data
+/// directory, returning its path.
+pub fn save(recording: &Recording) -> Result<PathBuf, String> {
+    let path = paths::data_file(&format!("madtyping-{}{}", file_stamp(now_secs()), SESSION_FILE_SUFFIX))?;
+    fs::write(&path, recording.to_json()).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Read a recorded session file.
+pub fn load(path: &Path) -> Result<Recording, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Recording::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Sender for dry-run replays: types into a [`MockBackend`], so nothing
+/// reaches the keyboard, but waits for real between lines so the recorded
+/// pacing can be watched.
+#[derive(Debug, Default)]
+pub struct DryRunSender {
+    pub backend: MockBackend,
+}
+
+impl LineSender for DryRunSender {
+    fn send_line(&mut self, text: &str, _window_title: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+        platform::type_message(&mut self.backend, text, options)
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+}
+
+/// Type the steps of `recording` again through `sender`, pausing before
+/// each as long as the recording did between the end of the previous line
+/// and the start of this one. Skipped steps stay skipped, and a target
+/// stops at its first failure like in a send. `on_step` gets every typed
+/// step and its result.
+pub fn replay(
+    recording: &Recording,
+    sender: &mut impl LineSender,
+    mut on_step: impl FnMut(&Step, &Result<TypeStats, String>),
+) -> SendReport {
+    let mut report = SendReport {
+        targets: recording
+            .targets
+            .iter()
+            .map(|t| TargetReport { window_title: t.clone(), ..TargetReport::default() })
+            .collect(),
+        ..SendReport::default()
+    };
+    let mut previous_end = 0;
+    for step in &recording.steps {
+        let target = report.targets.iter_mut().position(|t| t.window_title == step.target);
+        let Some(target) = target.map(|i| &mut report.targets[i]) else {
+            continue;
+        };
+        if step.outcome == StepOutcome::Skipped || target.failed.is_some() {
+            target.skipped += 1;
+            continue;
+        }
+        let pause = step.at_ms.saturating_sub(previous_end);
+        if pause > 0 {
+            sender.sleep(pause);
+        }
+        previous_end = step.at_ms + step.duration_ms;
+
+        let result = type_step(sender, step, recording.char_limit, recording.delays.next_line_ms);
+        match &result {
+            Ok(stats) => {
+                target.sent += 1;
+                target.retries += stats.retries;
+            }
+            Err(e) => target.failed = Some((step.line, step.text.clone(), e.clone())),
+        }
+        on_step(step, &result);
+    }
+    report
+}
+
+/// Type one step, split into game-sized messages like the send did.
+fn type_step(
+    sender: &mut impl LineSender,
+    step: &Step,
+    char_limit: Option<usize>,
+    part_delay_ms: u64,
+) -> Result<TypeStats, String> {
+    let mut stats = TypeStats::default();
+    for (i, part) in message_parts(&step.text, char_limit).into_iter().enumerate() {
+        if i > 0 {
+            sender.sleep(step.options.speed.scale(part_delay_ms));
+        }
+        stats.add(sender.send_line(part, &step.target, &step.options)?);
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::send::BackendSender;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/demo.session.json");
+
+    #[test]
+    fn test_recording_round_trips() {
+        let recording = Recording::parse(FIXTURE).unwrap();
+        assert_eq!(recording.targets, vec!["League of Legends (TM) Client"]);
+        assert_eq!(recording.steps.len(), 4);
+        assert_eq!(recording.steps[2].options.chat, ChatScope::Team);
+        assert_eq!(recording.steps[2].options.speed.percent(), 150);
+        assert_eq!(recording.char_limit, Some(GAME_MESSAGE_MAX_CHARS));
+        assert_eq!(Recording::parse(&recording.to_json()).unwrap(), recording);
+        let unlimited = Recording { char_limit: None, ..recording };
+        assert_eq!(Recording::parse(&unlimited.to_json()).unwrap(), unlimited);
+        let zero = FIXTURE.replacen("\"split_long_lines\"", "\"chat_char_limit\": 0,\n  \"split_long_lines\"", 1);
+        assert_eq!(Recording::parse(&zero).unwrap().char_limit, None);
+
+        let newer = FIXTURE.replacen("\"version\": 1", "\"version\": 2", 1);
+        assert!(Recording::parse(&newer).unwrap_err().contains("newer MadTyping (schema 2"));
+    }
+
+    #[test]
+    fn test_fixture_replayed_with_recorded_pauses() {
+        let recording = Recording::parse(FIXTURE).unwrap();
+        let mut sender = BackendSender::new(MockBackend::new());
+        let mut typed = Vec::new();
+        let report = replay(&recording, &mut sender, |step, result| {
+            assert!(result.is_ok());
+            typed.push(step.line);
+        });
+
+        // Line 2 was skipped when recorded and stays skipped
+        assert_eq!(typed, vec![0, 2, 3]);
+        assert_eq!(sender.backend.typed_text(), "gl hfdragon in 30wp");
+        assert_eq!((report.sent(), report.skipped()), (3, 1));
+        // Pauses between lines: 1500 - (0 + 120), then 4000 - (1500 + 100)
+        assert!(sender.backend.sleeps.contains(&1380));
+        assert!(sender.backend.sleeps.contains(&2400));
+    }
+}
@@ -0,0 +1,202 @@
+//! Bug report helpers for MadTyping
+//!
+//! Before each send a [`Snapshot`] of the focus and keyboard state is
+//! logged and saved to `madtyping.snapshot`, so "it typed into the wrong
+//! window" can be traced afterwards. `--report` bundles the last snapshot
+//! and the last session's log into one file to attach to an issue.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use crate::build_info::version_string;
+use crate::config::{Config, LOG_ENABLED};
+use crate::logging::{debug, log, LOG_FILE_NAME};
+use crate::paths;
+use crate::platform::{
+    foreground_window_title, is_caps_lock_on, is_elevated, is_window_minimized, keyboard_layout, target_window, KeyMode,
+};
+use crate::send::SendDelays;
+
+/// File name of the last pre-send snapshot, stored in the data directory.
+pub const SNAPSHOT_FILE_NAME: &str = "madtyping.snapshot";
+
+/// Focus and keyboard state just before a send.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Title of the focused window
+    pub foreground: Option<String>,
+    /// Target titles and the window each matched (handle, full title)
+    pub targets: Vec<(String, Option<(isize, String)>)>,
+    /// Targets that were minimized
+    pub minimized: Vec<String>,
+    pub keyboard_layout: Option<u32>,
+    pub caps_lock: bool,
+    pub elevated: Option<bool>,
+    pub key_mode: KeyMode,
+    pub delays: SendDelays,
+}
+
+impl Snapshot {
+    /// Take a snapshot for a send to `targets`.
+    pub fn capture(targets: &[String], config: &Config) -> Self {
+        Self {
+            foreground: foreground_window_title(),
+            targets: targets.iter().map(|t| (t.clone(), target_window(t))).collect(),
+            minimized: targets.iter().filter(|t| is_window_minimized(t)).cloned().collect(),
+            keyboard_layout: keyboard_layout(),
+            caps_lock: is_caps_lock_on(),
+            elevated: is_elevated(),
+            key_mode: KeyMode::from(config),
+            delays: SendDelays::from(config),
+        }
+    }
+
+    /// One line: focus, target matches, minimized targets and Caps Lock.
+    pub fn summary(&self) -> String {
+        let targets: Vec<String> = self
+            .targets
+            .iter()
+            .map(|(target, matched)| match matched {
+                Some((hwnd, title)) => format!("'{}' → {:#x} '{}'", target, hwnd, title),
+                None => format!("'{}' → no match", target),
+            })
+            .collect();
+        let minimized = if self.minimized.is_empty() { "none".to_string() } else { self.minimized.join(", ") };
+        format!(
+            "focus '{}'; target {}; minimized {}; caps lock {}",
+            self.foreground.as_deref().unwrap_or("?"),
+            targets.join(", "),
+            minimized,
+            on_off(self.caps_lock)
+        )
+    }
+
+    /// One line: keyboard layout, elevation, key mode and delays.
+    pub fn details(&self) -> String {
+        let elevated = match self.elevated {
+            Some(elevated) => if elevated { "yes" } else { "no" },
+            None => "?",
+        };
+        format!(
+            "layout {}; elevated {}; keys {}; delays char {}ms, line {}ms, settle {}ms, restore {}ms",
+            self.keyboard_layout.map(|id| format!("{:08x}", id)).unwrap_or_else(|| "?".to_string()),
+            elevated,
+            self.key_mode.label(),
+            self.delays.char_type_ms,
+            self.delays.next_line_ms,
+            self.delays.broadcast_settle_ms,
+            self.delays.restore_settle_ms
+        )
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+/// Log a snapshot before a send (details only at debug level) and save it
+/// for `--report`.
+pub fn record_before_send(targets: &[String], config: &Config) {
+    let snapshot = Snapshot::capture(targets, config);
+    log(&format!("Before send: {}", snapshot.summary()));
+    debug(&format!("Before send: {}", snapshot.details()));
+
+    let text = format!("{}\n{}\n{}\n", file_stamp(now_secs()), snapshot.summary(), snapshot.details());
+    if let Err(e) = paths::data_file(SNAPSHOT_FILE_NAME).and_then(|path| fs::write(&path, text).map_err(|e| e.to_string())) {
+        log(&format!("Failed to save the send snapshot: {}", e));
+    }
+}
+
+/// Write `madtyping-report-<time>.txt` to the data directory with the last
+/// snapshot and log, returning its path. Must run before logging starts,
+/// which clears the log.
+pub fn write_report() -> Result<PathBuf, String> {
+    let read = |name: &str| paths::data_file(name).ok().and_then(|path| fs::read_to_string(path).ok());
+    let report = compose(&version_string(), read(SNAPSHOT_FILE_NAME).as_deref(), read(LOG_FILE_NAME).as_deref());
+    let path = paths::data_file(&format!("madtyping-report-{}.txt", file_stamp(now_secs())))?;
+    fs::write(&path, report).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Build the report text from its parts.
+fn compose(version: &str, snapshot: Option<&str>, log: Option<&str>) -> String {
+    let log = match log {
+        Some(log) => log.to_string(),
+        None if !LOG_ENABLED => "(none: logging is off in this build, see LOG_ENABLED in config.rs)\n".to_string(),
+        None => "(none)\n".to_string(),
+    };
+    format!(
+        "MadTyping bug report\n{}\n\n== Last snapshot before a send ==\n{}\n== Last session log ==\n{}",
+        version,
+        snapshot.unwrap_or("(none: nothing was sent yet)\n"),
+        log
+    )
+}
+
+/// Seconds since the Unix epoch.
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Format Unix seconds as a UTC `YYYYMMDD-HHMMSS` stamp.
+pub(crate) fn file_stamp(secs: u64) -> String {
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time = secs % 86_400;
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_lines() {
+        let snapshot = Snapshot {
+            foreground: Some("Untitled - Notepad".to_string()),
+            targets: vec![
+                ("League".to_string(), Some((0x1a2b, "League of Legends (TM) Client".to_string()))),
+                ("Second".to_string(), None),
+            ],
+            minimized: vec!["League".to_string()],
+            keyboard_layout: Some(0x0409_0409),
+            caps_lock: true,
+            elevated: Some(false),
+            key_mode: KeyMode::Batched,
+            delays: SendDelays::default(),
+        };
+        assert_eq!(
+            snapshot.summary(),
+            "focus 'Untitled - Notepad'; target 'League' → 0x1a2b 'League of Legends (TM) Client', 'Second' → no match; \
+             minimized League; caps lock on"
+        );
+        assert_eq!(
+            snapshot.details(),
+            "layout 04090409; elevated no; keys batched; delays char 5ms, line 100ms, settle 300ms, restore 500ms"
+        );
+    }
+
+    #[test]
+    fn test_report_parts() {
+        assert_eq!(file_stamp(0), "19700101-000000");
+        assert_eq!(file_stamp(1_709_210_096), "20240229-123456");
+
+        let report = compose("MadTyping 1.0", Some("snap\n"), Some("[12:00:00] log\n"));
+        assert_eq!(
+            report,
+            "MadTyping bug report\nMadTyping 1.0\n\n== Last snapshot before a send ==\nsnap\n\n== Last session log ==\n[12:00:00] log\n"
+        );
+        assert!(compose("v", None, None).contains("nothing was sent yet"));
+    }
+}
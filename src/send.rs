@@ -0,0 +1,2658 @@
+//! Send pipeline for MadTyping
+//!
+//! [`SendSession`] walks the lines of a file and types each one into one or
+//! more target windows, reporting progress through events so any frontend
+//! can render it. The actual typing goes through a [`LineSender`], which
+//! makes the pipeline testable without a real keyboard.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex, MutexGuard, TryLockError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::abbreviations::Abbreviations;
+use crate::arming;
+use crate::charfilter::CharFilter;
+use crate::config::{
+    Config, SendMethod, SendSound, BLANK_LINE_PAUSE_MS, BROADCAST_SETTLE_DELAY_MS, CHAR_RETRY_LIMIT, CHAR_TYPE_DELAY_MS, GAME_MESSAGE_MAX_CHARS,
+    NEXT_LINE_DELAY_MS, RESTORE_SETTLE_DELAY_MS, MANUAL_ADVANCE_TICK_MS, SHUTDOWN_TICK_MS, TIMED_LINE_TICK_MS, VERIFY_TIMEOUT_MS,
+    WINDOW_FOCUS_DELAY_MS, WINDOW_LOST_TICK_MS,
+};
+use crate::files::Line;
+use crate::hooks;
+use crate::journal::{self, Journal};
+use crate::logging::log;
+use crate::replay::{self, Recording, Step, StepOutcome};
+use crate::shutdown::{self, SHUTDOWN_REQUESTER};
+use crate::template;
+use crate::trace;
+use crate::platform::{
+    self, local_time, ChatScope, Cue, FocusCheck, InputBackend, KeyDelays, KeyMode, LocalTime, Speed, TypeStats, TypingOptions,
+};
+
+/// Types a single line into a window.
+pub trait LineSender {
+    /// Focus the window and type the line into its chat.
+    fn send_line(&mut self, text: &str, window_title: &str, options: &TypingOptions) -> Result<TypeStats, String>;
+
+    /// Wait for the given number of milliseconds.
+    fn sleep(&mut self, ms: u64);
+
+    /// Current time, used by the send clock for timed lines.
+    fn now(&mut self) -> Instant {
+        Instant::now()
+    }
+
+    /// Whether a window matching `window_title` is open, checked before
+    /// each line once the window took one (see
+    /// [`SendSession::with_window_lost_wait`]).
+    fn window_exists(&mut self, _window_title: &str) -> bool {
+        true
+    }
+
+    /// Called once before the first line with the send's target windows,
+    /// e.g. to look them up.
+    fn begin(&mut self, _targets: &[String]) {}
+}
+
+/// Sender using the real platform keyboard simulation.
+pub struct PlatformSender;
+
+impl LineSender for PlatformSender {
+    fn send_line(&mut self, text: &str, window_title: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+        platform::send_text(text, window_title, options)
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+
+    fn window_exists(&mut self, window_title: &str) -> bool {
+        platform::is_window_running(window_title)
+    }
+
+    fn begin(&mut self, targets: &[String]) {
+        platform::resolve_windows(targets);
+    }
+}
+
+/// Sender typing through any [`InputBackend`], without focusing a window.
+///
+/// With a [`MockBackend`](crate::platform::MockBackend) this drives the
+/// real typing sequence without touching the keyboard.
+#[derive(Debug, Default)]
+pub struct BackendSender<B> {
+    pub backend: B,
+}
+
+impl<B: InputBackend> BackendSender<B> {
+    /// Wrap a backend.
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+}
+
+impl<B: InputBackend> LineSender for BackendSender<B> {
+    fn send_line(&mut self, text: &str, _window_title: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+        platform::type_message(&mut self.backend, text, options)
+    }
+
+    fn sleep(&mut self, ms: u64) {
+        self.backend.sleep(ms);
+    }
+}
+
+/// Configurable delays used by a send session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendDelays {
+    /// Delay after each typed character
+    pub char_type_ms: u64,
+    /// Delay between lines sent to the same window
+    pub next_line_ms: u64,
+    /// Delay before sending to a different window
+    pub broadcast_settle_ms: u64,
+    /// Extra delay after restoring a minimized window
+    pub restore_settle_ms: u64,
+    /// Pause per blank line above a line (0 = off)
+    pub blank_line_pause_ms: u64,
+    /// Fixed pauses between the keys of each message
+    pub keys: KeyDelays,
+}
+
+impl Default for SendDelays {
+    fn default() -> Self {
+        Self {
+            char_type_ms: CHAR_TYPE_DELAY_MS,
+            next_line_ms: NEXT_LINE_DELAY_MS,
+            broadcast_settle_ms: BROADCAST_SETTLE_DELAY_MS,
+            restore_settle_ms: RESTORE_SETTLE_DELAY_MS,
+            blank_line_pause_ms: BLANK_LINE_PAUSE_MS,
+            keys: KeyDelays::default(),
+        }
+    }
+}
+
+impl From<&Config> for SendDelays {
+    fn from(config: &Config) -> Self {
+        Self {
+            char_type_ms: config.char_type_delay_ms,
+            next_line_ms: config.next_line_delay_ms,
+            broadcast_settle_ms: config.broadcast_settle_delay_ms,
+            restore_settle_ms: config.restore_settle_delay_ms,
+            blank_line_pause_ms: config.blank_line_pause_ms,
+            keys: KeyDelays::default(),
+        }
+    }
+}
+
+/// Held while any session is typing, so two sends never interleave
+/// keystrokes into the same chat box.
+static SEND_LOCK: Mutex<()> = Mutex::new(());
+
+/// What to do when a send starts while another one is still running.
+///
+/// Sessions queue by default; frontends reject unless the user opted into
+/// queueing, so a stray trigger doesn't type a file later unexpectedly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BusyPolicy {
+    /// Fail immediately with "send already in progress"
+    Reject,
+    /// Wait for the running send to finish, then start
+    #[default]
+    Queue,
+}
+
+impl From<&Config> for BusyPolicy {
+    fn from(config: &Config) -> Self {
+        if config.queue_sends { BusyPolicy::Queue } else { BusyPolicy::Reject }
+    }
+}
+
+/// How far a cancel request got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CancelPhase {
+    /// No cancel was requested
+    None,
+    /// Requested; the send is still typing or pausing and hasn't seen it
+    Requested,
+    /// The send saw the request and is skipping what's left
+    Acknowledged,
+    /// The send stopped; stays until the next send starts
+    Completed,
+}
+
+/// A cancel request and how far the send got with it.
+struct CancelRequest {
+    /// Who asked to cancel the running send from outside its event callback
+    requester: String,
+    phase: CancelPhase,
+}
+
+static CANCEL_REQUEST: Mutex<Option<CancelRequest>> = Mutex::new(None);
+
+/// Ask the running send, if any, to stop before its next line (or while a
+/// timed line waits). `requester` names who asked, e.g. "remote control",
+/// and ends up in [`SendReport::cancelled_by`].
+///
+/// Returns whether this call cancelled a send: false if none is running or
+/// it is already being cancelled, so repeating the request is harmless.
+pub fn cancel_running_send(requester: &str) -> bool {
+    if !matches!(SEND_LOCK.try_lock(), Err(TryLockError::WouldBlock)) {
+        return false;
+    }
+    let mut request = CANCEL_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
+    if request.is_some() {
+        return false;
+    }
+    log(&format!("Cancel requested by {}", requester));
+    *request = Some(CancelRequest { requester: requester.to_string(), phase: CancelPhase::Requested });
+    true
+}
+
+/// Where the cancel request of the running (or last) send is, so a
+/// frontend can show that a cancel registered before the send stops.
+pub fn cancel_phase() -> CancelPhase {
+    CANCEL_REQUEST.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map_or(CancelPhase::None, |r| r.phase)
+}
+
+/// Whether a session is sending right now.
+pub fn is_sending() -> bool {
+    matches!(SEND_LOCK.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+fn clear_cancel_request() {
+    *CANCEL_REQUEST.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// The send stopped; a pending request is done.
+fn complete_cancel_request() {
+    if let Some(request) = CANCEL_REQUEST.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        request.phase = CancelPhase::Completed;
+    }
+}
+
+/// Set by [`flag_paste_failed`] until the running send picks it up.
+static PASTE_FLAG: Mutex<bool> = Mutex::new(false);
+
+/// Tell the running send that pasted lines don't show up in chat. Windows
+/// pasted into with [`SendMethod::Auto`] are typed into from the next line
+/// on (see [`TargetReport::paste_fallback`]).
+///
+/// Returns false if no send is running.
+pub fn flag_paste_failed() -> bool {
+    if !matches!(SEND_LOCK.try_lock(), Err(TryLockError::WouldBlock)) {
+        return false;
+    }
+    log("Paste flagged as not working by the user");
+    *PASTE_FLAG.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    true
+}
+
+/// Whether the user flagged pasting since the last call.
+fn take_paste_flag() -> bool {
+    std::mem::take(&mut *PASTE_FLAG.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Sleep `ms` in ticks of [`TIMED_LINE_TICK_MS`], so a cancel request ends
+/// a long per-line delay or pause within a tick. Returns whether one did.
+fn sleep_or_cancel(sender: &mut (impl LineSender + ?Sized), ms: u64, report: &mut SendReport) -> bool {
+    let mut left = ms;
+    while left > 0 {
+        if check_cancel_request(report) {
+            return true;
+        }
+        let tick = left.min(TIMED_LINE_TICK_MS);
+        sender.sleep(tick);
+        left -= tick;
+    }
+    check_cancel_request(report)
+}
+
+/// Mark `report` cancelled if a cancel was requested. Returns whether it was.
+fn check_cancel_request(report: &mut SendReport) -> bool {
+    let mut request = CANCEL_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(request) = request.as_mut() else {
+        return false;
+    };
+    if request.phase == CancelPhase::Requested {
+        request.phase = CancelPhase::Acknowledged;
+    }
+    if !report.cancelled {
+        log(&format!("Send cancelled by {}", request.requester));
+        report.cancelled = true;
+        report.cancelled_by = Some(request.requester.clone());
+    }
+    true
+}
+
+/// Messages per window of the sessions running or waiting for the send
+/// lock, counted against [`SendSession::with_max_lines_per_send`].
+static PENDING_MESSAGES: Mutex<usize> = Mutex::new(0);
+
+/// Messages of a session counted in [`PENDING_MESSAGES`] until dropped.
+struct PendingMessages(usize);
+
+impl Drop for PendingMessages {
+    fn drop(&mut self) {
+        let mut pending = PENDING_MESSAGES.lock().unwrap_or_else(|e| e.into_inner());
+        *pending = pending.saturating_sub(self.0);
+    }
+}
+
+/// Count `count` messages as pending, unless that takes the running and
+/// queued sends over `max`.
+fn reserve_messages(count: usize, max: Option<u64>) -> Result<PendingMessages, String> {
+    let mut pending = PENDING_MESSAGES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(problem) = max.and_then(|max| line_cap_problem(count, *pending, max)) {
+        return Err(problem);
+    }
+    *pending += count;
+    Ok(PendingMessages(count))
+}
+
+/// Take the process-wide send lock according to `policy`.
+fn acquire_send_lock(policy: BusyPolicy) -> Result<MutexGuard<'static, ()>, String> {
+    // A panic mid-send leaves nothing to clean up, so poisoning is ignored
+    match policy {
+        BusyPolicy::Queue => Ok(SEND_LOCK.lock().unwrap_or_else(|e| e.into_inner())),
+        BusyPolicy::Reject => match SEND_LOCK.try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(e)) => Ok(e.into_inner()),
+            Err(TryLockError::WouldBlock) => Err("Send already in progress".to_string()),
+        },
+    }
+}
+
+/// Order in which lines are sent when there are several target windows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BroadcastMode {
+    /// Send each line to every window before moving to the next line
+    PerLine,
+    /// Send the whole file to one window, then the next window
+    PerFile,
+}
+
+/// What the user can pick for a single send: the channel, the broadcast
+/// order, the starting speed, whether repeated lines are left out, manual
+/// advance and dry run. The file list's options overlay and the `--send`
+/// flags both fill one in and hand it to [`SendSession::with_choices`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendChoices {
+    /// Channel for lines that don't pick their own
+    pub chat: ChatScope,
+    /// Order of lines and windows when broadcasting
+    pub order: BroadcastMode,
+    /// Speed the send starts at
+    pub speed: Speed,
+    /// Leave out lines with the same text as an earlier line
+    pub skip_repeats: bool,
+    /// Wait for a key before each line after the first
+    pub manual_advance: bool,
+    /// Type into a mock keyboard instead of the game (the frontend picks
+    /// the sender)
+    pub dry_run: bool,
+}
+
+impl Default for SendChoices {
+    fn default() -> Self {
+        Self {
+            chat: ChatScope::All,
+            order: BroadcastMode::PerLine,
+            speed: Speed::NORMAL,
+            skip_repeats: false,
+            manual_advance: false,
+            dry_run: false,
+        }
+    }
+}
+
+impl From<&Config> for SendChoices {
+    fn from(config: &Config) -> Self {
+        let order = if config.broadcast_per_file { BroadcastMode::PerFile } else { BroadcastMode::PerLine };
+        Self { order, ..Self::default() }
+    }
+}
+
+/// Indexes of the lines whose text (ignoring surrounding blanks) already
+/// appeared on an earlier line.
+pub fn repeated_lines(lines: &[Line]) -> HashSet<usize> {
+    let mut seen = HashSet::new();
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !seen.insert(line.text.trim()))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Progress notifications emitted while sending.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SendEvent<'a> {
+    /// A line is about to be sent
+    LineStarted {
+        /// Zero-based line index
+        index: usize,
+        /// Total number of lines in the file
+        total: usize,
+        line: &'a Line,
+        target: &'a str,
+        /// Current speed multiplier
+        speed: Speed,
+        /// Estimated time left, including this line
+        remaining: Duration,
+    },
+    /// A line finished sending (successfully or not)
+    LineFinished {
+        index: usize,
+        total: usize,
+        line: &'a Line,
+        target: &'a str,
+        result: &'a Result<TypeStats, String>,
+        /// Time spent focusing and typing this line
+        elapsed: Duration,
+    },
+    /// A timed line is waiting for the send clock; repeated every
+    /// [`TIMED_LINE_TICK_MS`] until its time comes
+    Waiting {
+        index: usize,
+        total: usize,
+        line: &'a Line,
+        target: &'a str,
+        /// Send clock time the line waits for
+        at: Duration,
+        /// Time left until then
+        left: Duration,
+    },
+    /// A target window that took earlier lines is gone, and the line waits
+    /// for it to come back; repeated every [`WINDOW_LOST_TICK_MS`] until it
+    /// does or the wait runs out
+    WindowLost {
+        index: usize,
+        total: usize,
+        line: &'a Line,
+        target: &'a str,
+        /// Time left before the line fails
+        left: Duration,
+    },
+    /// In manual advance mode, the next line waits for the user; repeated
+    /// every [`MANUAL_ADVANCE_TICK_MS`] until the callback returns
+    /// [`Control::Advance`], [`Control::Skip`] or [`Control::Cancel`]
+    AwaitingAdvance {
+        index: usize,
+        total: usize,
+        /// The line that will be sent next
+        line: &'a Line,
+        target: &'a str,
+    },
+    /// How lines get into a target's chat box, sent before its first line
+    /// and again when it falls back from pasting to typing. Only sent when
+    /// the session pastes or posts (see [`SendSession::with_send_method`]).
+    MethodChosen {
+        target: &'a str,
+        /// [`SendMethod::Type`], [`SendMethod::Paste`] or
+        /// [`SendMethod::PostMessage`]
+        method: SendMethod,
+        /// Why, e.g. "remembered from an earlier send"
+        reason: &'a str,
+    },
+    /// The session is over (all lines sent, cancelled or failed)
+    Finished { report: &'a SendReport },
+}
+
+/// What the frontend wants the session to do after an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Cancel,
+    /// Use a new speed for the delays of this and later lines. After a
+    /// `LineStarted` event, the event is emitted again with the new speed
+    /// and estimate.
+    SetSpeed(Speed),
+    /// Use new delays from now on (e.g. after the config file changed).
+    /// Emitted again like [`Control::SetSpeed`] after a `LineStarted` event.
+    SetDelays(SendDelays),
+    /// After `AwaitingAdvance`: send the line now
+    Advance,
+    /// After `AwaitingAdvance`: skip the line (on every target) and wait
+    /// again before the one after it
+    Skip,
+}
+
+/// Results for one target window.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TargetReport {
+    /// Window title the lines were sent to
+    pub window_title: String,
+    /// Lines sent successfully
+    pub sent: usize,
+    /// Lines not sent because of a cancel or an earlier failure
+    pub skipped: usize,
+    /// Lines left out on purpose (see [`SendSession::with_excluded_lines`])
+    pub excluded: usize,
+    /// Lines the user skipped while sending with manual advance; also
+    /// counted in `skipped`
+    pub skipped_by_user: usize,
+    /// Characters retried after the game dropped input
+    pub retries: usize,
+    /// Time spent finding and focusing the window before its lines
+    pub window_time: Duration,
+    /// Zero-based index, text and error of the line that failed
+    pub failed: Option<(usize, String, String)>,
+    /// The window closed after taking lines and didn't come back in time;
+    /// `failed` is the line that was waiting for it. Sending from there
+    /// again picks up where this send stopped.
+    pub window_lost: bool,
+    /// Why the window went from pasting to typing during this send
+    pub paste_fallback: Option<String>,
+    /// Sent lines the verifier saw in chat (see [`SendSession::with_verifier`])
+    pub verified: usize,
+    /// Zero-based indexes of sent lines the verifier didn't see, even
+    /// after typing them again; they also count as sent
+    pub unverified: Vec<usize>,
+    /// How focusing the window went for each sent line where it was
+    /// checked, in order (see [`crate::focus_quirks`])
+    pub focus_checks: Vec<FocusCheck>,
+}
+
+/// Results of a whole send, broken down per target window.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SendReport {
+    pub targets: Vec<TargetReport>,
+    /// The user cancelled before all lines were sent
+    pub cancelled: bool,
+    /// Who cancelled through [`cancel_running_send`], if that's how it ended
+    pub cancelled_by: Option<String>,
+    /// Keystroke trace written for this send (see [`SendSession::with_trace_input`])
+    pub trace_file: Option<PathBuf>,
+    /// Recording written for this send (see [`SendSession::with_recording`])
+    pub session_file: Option<PathBuf>,
+}
+
+impl SendReport {
+    /// Total lines sent across all targets.
+    pub fn sent(&self) -> usize {
+        self.targets.iter().map(|t| t.sent).sum()
+    }
+
+    /// Whether every line reached every window: nothing failed, was
+    /// cancelled, left out or skipped by the user. Lines skipped for being
+    /// empty don't count.
+    pub fn fully_sent(&self) -> bool {
+        let skipped_by_user: usize = self.targets.iter().map(|t| t.skipped_by_user).sum();
+        !self.cancelled && self.failed() == 0 && self.excluded() == 0 && skipped_by_user == 0 && self.sent() > 0
+    }
+
+    /// Total lines skipped across all targets.
+    pub fn skipped(&self) -> usize {
+        self.targets.iter().map(|t| t.skipped).sum()
+    }
+
+    /// Total lines left out on purpose across all targets.
+    pub fn excluded(&self) -> usize {
+        self.targets.iter().map(|t| t.excluded).sum()
+    }
+
+    /// Number of targets that stopped because of a failure.
+    pub fn failed(&self) -> usize {
+        self.targets.iter().filter(|t| t.failed.is_some()).count()
+    }
+
+    /// Total character retries across all targets.
+    pub fn retries(&self) -> usize {
+        self.targets.iter().map(|t| t.retries).sum()
+    }
+
+    /// Total lines the verifier saw across all targets.
+    pub fn verified(&self) -> usize {
+        self.targets.iter().map(|t| t.verified).sum()
+    }
+
+    /// Total lines the verifier didn't see across all targets.
+    pub fn unverified(&self) -> usize {
+        self.targets.iter().map(|t| t.unverified.len()).sum()
+    }
+
+    /// What the verifier made of the send, e.g. "Verified 4 line(s); not
+    /// seen in chat: line 3". None if lines weren't verified.
+    pub fn verification_summary(&self) -> Option<String> {
+        if self.verified() + self.unverified() == 0 {
+            return None;
+        }
+        let mut unseen = Vec::new();
+        for target in &self.targets {
+            for index in &target.unverified {
+                if self.targets.len() > 1 {
+                    unseen.push(format!("line {} in '{}'", index + 1, target.window_title));
+                } else {
+                    unseen.push(format!("line {}", index + 1));
+                }
+            }
+        }
+        let mut summary = format!("Verified {} line(s)", self.verified());
+        if !unseen.is_empty() {
+            summary.push_str(&format!("; not seen in chat even after typing it again: {}", unseen.join(", ")));
+        }
+        Some(summary)
+    }
+}
+
+/// Tells whether a sent line showed up in chat, given its zero-based
+/// index, its text and the target window (see [`SendSession::with_verifier`]).
+pub type Verifier = Arc<dyn Fn(usize, &str, &str) -> bool + Send + Sync>;
+
+/// A single run of sending a file's lines to one or more windows.
+pub struct SendSession<'a> {
+    lines: &'a [Line],
+    targets: Vec<String>,
+    mode: BroadcastMode,
+    delays: SendDelays,
+    chat: ChatScope,
+    busy: BusyPolicy,
+    split_long_lines: bool,
+    char_limit: Option<usize>,
+    key_mode: KeyMode,
+    clock_start: Duration,
+    trace_input: bool,
+    manual_advance: bool,
+    record: bool,
+    max_lines_per_send: Option<u64>,
+    slash_commands: bool,
+    window_lost_wait: Duration,
+    send_method: SendMethod,
+    typed_windows: Vec<String>,
+    focus_delays: BTreeMap<String, u64>,
+    char_retries: u64,
+    paste_check_hook: String,
+    filter: CharFilter,
+    abbreviations: Abbreviations,
+    local_time: Option<LocalTime>,
+    verifier: Option<Verifier>,
+    excluded: HashSet<usize>,
+    speed: Speed,
+    skip_repeats: bool,
+    journal: Option<PathBuf>,
+}
+
+impl<'a> SendSession<'a> {
+    /// Create a session sending `lines` to every window in `targets`.
+    pub fn new(lines: &'a [Line], targets: Vec<String>, mode: BroadcastMode) -> Self {
+        Self {
+            lines,
+            targets,
+            mode,
+            delays: SendDelays::default(),
+            chat: ChatScope::All,
+            busy: BusyPolicy::default(),
+            split_long_lines: false,
+            char_limit: Some(GAME_MESSAGE_MAX_CHARS),
+            key_mode: KeyMode::default(),
+            clock_start: Duration::ZERO,
+            trace_input: false,
+            manual_advance: false,
+            record: false,
+            max_lines_per_send: None,
+            slash_commands: false,
+            window_lost_wait: Duration::ZERO,
+            send_method: SendMethod::Type,
+            typed_windows: Vec::new(),
+            focus_delays: BTreeMap::new(),
+            char_retries: CHAR_RETRY_LIMIT,
+            paste_check_hook: String::new(),
+            filter: CharFilter::default(),
+            abbreviations: Abbreviations::default(),
+            local_time: None,
+            verifier: None,
+            excluded: HashSet::new(),
+            speed: Speed::NORMAL,
+            skip_repeats: false,
+            journal: None,
+        }
+    }
+
+    /// Start the send clock for timed lines at `time` (e.g. the current
+    /// game time) instead of 00:00.
+    pub fn with_clock_start(mut self, time: Duration) -> Self {
+        self.clock_start = time;
+        self
+    }
+
+    /// Choose how the chat-open and send keys are injected.
+    pub fn with_key_mode(mut self, key_mode: KeyMode) -> Self {
+        self.key_mode = key_mode;
+        self
+    }
+
+    /// Send lines over the game's message limit as several messages instead
+    /// of refusing to start.
+    pub fn with_split_long_lines(mut self, split: bool) -> Self {
+        self.split_long_lines = split;
+        self
+    }
+
+    /// Use `limit` characters as the game's message limit instead of
+    /// League's; None (or 0) sends lines of any length whole.
+    pub fn with_char_limit(mut self, limit: Option<usize>) -> Self {
+        self.char_limit = limit.filter(|&max| max > 0);
+        self
+    }
+
+    /// Choose what happens if another send is already running.
+    pub fn with_busy_policy(mut self, busy: BusyPolicy) -> Self {
+        self.busy = busy;
+        self
+    }
+
+    /// Type into the given chat channel instead of all chat.
+    pub fn with_chat(mut self, chat: ChatScope) -> Self {
+        self.chat = chat;
+        self
+    }
+
+    /// Write every injected key event of this send to a trace file.
+    pub fn with_trace_input(mut self, trace_input: bool) -> Self {
+        self.trace_input = trace_input;
+        self
+    }
+
+    /// Type lines starting with `/` (see [`Line::is_command`]) as chat
+    /// commands, whatever channel the send or the line asks for.
+    pub fn with_slash_commands(mut self, slash_commands: bool) -> Self {
+        self.slash_commands = slash_commands;
+        self
+    }
+
+    /// Refuse to start if this send, together with the sends queued
+    /// before it, types more than `max` messages into each window (after
+    /// splitting long lines).
+    pub fn with_max_lines_per_send(mut self, max: u64) -> Self {
+        self.max_lines_per_send = Some(max);
+        self
+    }
+
+    /// When a target window that already took lines disappears (e.g. the
+    /// game closing at the end of a match), wait up to `wait` for it to
+    /// come back (see [`SendEvent::WindowLost`]) instead of failing the
+    /// next line. A window that was never found still fails right away.
+    pub fn with_window_lost_wait(mut self, wait: Duration) -> Self {
+        self.window_lost_wait = wait;
+        self
+    }
+
+    /// Choose how lines get into the chat box. With [`SendMethod::Auto`],
+    /// windows paste until they fall back to typing (see
+    /// [`crate::paste`]).
+    pub fn with_send_method(mut self, method: SendMethod) -> Self {
+        self.send_method = method;
+        self
+    }
+
+    /// Windows known to ignore pasting, typed into with [`SendMethod::Auto`].
+    pub fn with_typed_windows(mut self, windows: Vec<String>) -> Self {
+        self.typed_windows = windows;
+        self
+    }
+
+    /// Extra focus delays learned per window title, added to the focus
+    /// delay before each line sent there (see [`crate::focus_quirks`]).
+    pub fn with_focus_delays(mut self, delays: BTreeMap<String, u64>) -> Self {
+        self.focus_delays = delays;
+        self
+    }
+
+    /// How often a character whose key events were dropped is retried.
+    pub fn with_char_retries(mut self, retries: u64) -> Self {
+        self.char_retries = retries;
+        self
+    }
+
+    /// Command run after the first line pasted into each window with
+    /// [`SendMethod::Auto`]; if it fails, the line is typed again and the
+    /// window is typed into from then on.
+    pub fn with_paste_check_hook(mut self, command: &str) -> Self {
+        self.paste_check_hook = command.to_string();
+        self
+    }
+
+    /// Clean every line with `filter` before typing it. Lines it leaves
+    /// empty are skipped.
+    pub fn with_char_filter(mut self, filter: CharFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Expand the abbreviation tokens of every line before sending it.
+    pub fn with_abbreviations(mut self, abbreviations: Abbreviations) -> Self {
+        self.abbreviations = abbreviations;
+        self
+    }
+
+    /// Render time-of-day templates for `time` instead of the clock's
+    /// local time when the send starts.
+    pub fn with_local_time(mut self, time: LocalTime) -> Self {
+        self.local_time = Some(time);
+        self
+    }
+
+    /// Check every sent line with `verify` (e.g. by reading the screen). A
+    /// line it doesn't see is typed once more; if it still isn't seen, it
+    /// is listed in [`TargetReport::unverified`]. A check that takes longer
+    /// than `VERIFY_TIMEOUT_MS` counts as not seen, so it can't stall the
+    /// send.
+    pub fn with_verifier(mut self, verify: impl Fn(usize, &str, &str) -> bool + Send + Sync + 'static) -> Self {
+        self.verifier = Some(Arc::new(verify));
+        self
+    }
+
+    /// Check every sent line with `verify_hook` (see [`Self::with_verifier`]
+    /// and [`hooks::verify`]). An empty command leaves lines unchecked.
+    pub fn with_verify_hook(self, command: &str) -> Self {
+        if command.trim().is_empty() {
+            return self;
+        }
+        let command = command.to_string();
+        self.with_verifier(move |index, text, window| match hooks::verify(&command, window, index, text) {
+            Ok(()) => true,
+            Err(e) => {
+                log(&e);
+                false
+            }
+        })
+    }
+
+    /// Leave out the lines at these zero-based indexes, on every target.
+    /// They count as [`TargetReport::excluded`] rather than skipped, and
+    /// not towards the line cap.
+    pub fn with_excluded_lines(mut self, excluded: HashSet<usize>) -> Self {
+        self.excluded = excluded;
+        self
+    }
+
+    /// Keep a [`journal`] of the lines of `source` that went out, so a
+    /// send cut off by a crash can be resumed on the next start.
+    pub fn with_journal(mut self, source: PathBuf) -> Self {
+        self.journal = Some(source);
+        self
+    }
+
+    /// Save what this send does, line by line, to a `.session.json` file
+    /// for `--replay`.
+    pub fn with_recording(mut self, record: bool) -> Self {
+        self.record = record;
+        self
+    }
+
+    /// Wait for the frontend before each line after the first (see
+    /// [`SendEvent::AwaitingAdvance`]) instead of pausing for the line
+    /// delays. Switching windows still waits for focus to settle.
+    pub fn with_manual_advance(mut self, manual: bool) -> Self {
+        self.manual_advance = manual;
+        self
+    }
+
+    /// Start at `speed` instead of [`Speed::NORMAL`]; the frontend can
+    /// still change it mid-send.
+    pub fn with_speed(mut self, speed: Speed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Leave out lines repeating an earlier line's text, counted as
+    /// excluded like the lines of [`Self::with_excluded_lines`].
+    pub fn with_skip_repeats(mut self, skip_repeats: bool) -> Self {
+        self.skip_repeats = skip_repeats;
+        self
+    }
+
+    /// Apply the user's [`SendChoices`]: channel, order, starting speed,
+    /// repeats and manual advance. Dry run is up to the caller, which
+    /// passes the sender to [`Self::run`].
+    pub fn with_choices(mut self, choices: SendChoices) -> Self {
+        self.mode = choices.order;
+        self.chat = choices.chat;
+        self.speed = choices.speed;
+        self.skip_repeats = choices.skip_repeats;
+        self.manual_advance = choices.manual_advance;
+        self
+    }
+
+    /// Use the given delays instead of the built-in defaults.
+    pub fn with_delays(mut self, delays: SendDelays) -> Self {
+        self.delays = delays;
+        self
+    }
+
+    /// Send all lines, calling `on_event` before and after each one.
+    ///
+    /// A failure on one target stops sending to that target only; the
+    /// remaining targets continue. Returning [`Control::Cancel`] from the
+    /// callback stops everything and counts the rest as skipped.
+    ///
+    /// Only one session runs at a time per process. If another one is
+    /// running, this fails or waits depending on the [`BusyPolicy`].
+    ///
+    /// Fails before typing anything if a line is over the game's message
+    /// limit and splitting is off, if the send is over its line cap, or if
+    /// the tool is disarmed (see [`arming`]).
+    pub fn run(
+        &self,
+        sender: &mut (impl LineSender + ?Sized),
+        mut on_event: impl FnMut(&SendEvent) -> Control,
+    ) -> Result<SendReport, String> {
+        arming::check()?;
+        let expanded = self.abbreviations.expand_lines(self.lines);
+        let lines = template::render_lines(&expanded, self.local_time.unwrap_or_else(local_time));
+        let mut excluded = self.excluded.clone();
+        if self.skip_repeats {
+            excluded.extend(repeated_lines(&lines));
+        }
+        let included: Vec<Line> = lines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !excluded.contains(index))
+            .map(|(_, line)| line.clone())
+            .collect();
+        if let Some(max) = self.char_limit.filter(|_| !self.split_long_lines) {
+            let too_long = lines
+                .iter()
+                .enumerate()
+                .find(|(index, l)| !excluded.contains(index) && l.text.chars().count() > max);
+            if let Some((index, line)) = too_long {
+                return Err(format!(
+                    "Line {} is {} characters, over the game's {}-character limit (enable \"Split long lines\" to send it in parts)",
+                    index + 1,
+                    line.text.chars().count(),
+                    max
+                ));
+            }
+        }
+        let count = message_count(&included, self.split_long_lines, self.char_limit);
+        let _pending = reserve_messages(count, self.max_lines_per_send)?;
+        let _lock = acquire_send_lock(self.busy)?;
+        let total = lines.len();
+        let mut report = SendReport {
+            targets: self.targets
+                .iter()
+                .map(|t| TargetReport { window_title: t.clone(), ..TargetReport::default() })
+                .collect(),
+            cancelled: false,
+            cancelled_by: None,
+            trace_file: None,
+            session_file: None,
+        };
+        // A request that arrived after the previous send ended is stale
+        clear_cancel_request();
+        take_paste_flag();
+        // A shutdown stops the send like Esc does, and lets go of any
+        // modifier it held down
+        let _registration = shutdown::register("send", || {
+            cancel_running_send(SHUTDOWN_REQUESTER);
+            while is_sending() {
+                thread::sleep(Duration::from_millis(SHUTDOWN_TICK_MS));
+            }
+            platform::release_modifier_keys();
+        });
+        if self.trace_input {
+            trace::start();
+        }
+        sender.begin(&self.targets);
+        let mut journal = self.journal.as_ref().and_then(|source| {
+            match journal::journal_path().and_then(|path| Journal::start(&path, source, total)) {
+                Ok(journal) => Some(journal),
+                Err(e) => {
+                    log(&format!("Send journal not kept: {}", e));
+                    None
+                }
+            }
+        });
+
+        let steps: Vec<(usize, usize)> = match self.mode {
+            BroadcastMode::PerLine => (0..total)
+                .flat_map(|line| (0..self.targets.len()).map(move |target| (line, target)))
+                .collect(),
+            BroadcastMode::PerFile => (0..self.targets.len())
+                .flat_map(|target| (0..total).map(move |line| (line, target)))
+                .collect(),
+        };
+
+        let started = sender.now();
+        let mut recording = self.record.then(|| Recording {
+            targets: self.targets.clone(),
+            mode: self.mode,
+            delays: self.delays,
+            split_long_lines: self.split_long_lines,
+            char_limit: self.char_limit,
+            manual_advance: self.manual_advance,
+            clock_start_ms: self.clock_start.as_millis() as u64,
+            steps: Vec::new(),
+        });
+        let mut speed = self.speed;
+        let mut delays = self.delays;
+        let mut previous: Option<(usize, usize)> = None;
+        // Line last let through or skipped in manual advance mode, so
+        // broadcasts ask once per line rather than once per target
+        let mut advanced: Option<usize> = None;
+        let mut skipped: Option<usize> = None;
+        // Targets that took a line; only those are waited for when they go missing
+        let mut reached = vec![false; self.targets.len()];
+        let mut pasting = vec![false; self.targets.len()];
+        // Targets whose first pasted line went through the paste check
+        let mut paste_checked = vec![false; self.targets.len()];
+        if self.send_method != SendMethod::Type {
+            for (target_idx, target) in self.targets.iter().enumerate() {
+                let (paste, reason) = match self.send_method {
+                    SendMethod::Auto if self.typed_windows.contains(target) => {
+                        (false, "auto: this window ignored pasting in an earlier send")
+                    }
+                    SendMethod::Auto => (true, "auto: trying paste"),
+                    SendMethod::PostMessage => (false, "send_method = post_message"),
+                    _ => (true, "send_method = paste"),
+                };
+                pasting[target_idx] = paste;
+                let method = match self.send_method {
+                    SendMethod::PostMessage => SendMethod::PostMessage,
+                    _ if paste => SendMethod::Paste,
+                    _ => SendMethod::Type,
+                };
+                log(&format!("Send method for '{}': {:?} ({})", target, method, reason));
+                on_event(&SendEvent::MethodChosen { target, method, reason });
+            }
+        }
+        for (step, &(index, target_idx)) in steps.iter().enumerate() {
+            if !report.cancelled {
+                check_cancel_request(&mut report);
+            }
+            let target_report = &mut report.targets[target_idx];
+            if report.cancelled || target_report.failed.is_some() {
+                target_report.skipped += 1;
+                continue;
+            }
+
+            let line = &lines[index];
+            let target = self.targets[target_idx].as_str();
+
+            if excluded.contains(&index) {
+                log(&progress_record(index, total, &line.text, target, "left out", Duration::ZERO, Duration::ZERO));
+                report.targets[target_idx].excluded += 1;
+                if let Some(recording) = recording.as_mut() {
+                    recording.steps.push(Step {
+                        line: index,
+                        target: target.to_string(),
+                        text: line.text.clone(),
+                        options: TypingOptions { speed, chat: self.chat_for(line), ..TypingOptions::default() },
+                        at_ms: sender.now().saturating_duration_since(started).as_millis() as u64,
+                        duration_ms: 0,
+                        outcome: StepOutcome::Skipped,
+                    });
+                }
+                continue;
+            }
+
+            if self.send_method == SendMethod::Auto && take_paste_flag() {
+                for t in 0..self.targets.len() {
+                    if pasting[t] {
+                        self.fall_back_to_typing(&mut report, &mut pasting, t, "flagged by the user", &mut on_event);
+                    }
+                }
+            }
+
+            // A line the filter or its time conditions empty would be typed
+            // as a bare Enter press
+            let filtered = self.filter.is_active().then(|| self.filter.apply(&line.text).text);
+            let text = filtered.as_deref().unwrap_or(&line.text);
+            if text.is_empty() {
+                let reason = if line.text.is_empty() {
+                    "skipped, empty at this time of day"
+                } else {
+                    "skipped, empty after the character filter"
+                };
+                log(&progress_record(index, total, &line.text, target, reason, Duration::ZERO, Duration::ZERO));
+                report.targets[target_idx].skipped += 1;
+                if let Some(recording) = recording.as_mut() {
+                    recording.steps.push(Step {
+                        line: index,
+                        target: target.to_string(),
+                        text: line.text.clone(),
+                        options: TypingOptions { speed, chat: self.chat_for(line), ..TypingOptions::default() },
+                        at_ms: sender.now().saturating_duration_since(started).as_millis() as u64,
+                        duration_ms: 0,
+                        outcome: StepOutcome::Skipped,
+                    });
+                }
+                continue;
+            }
+
+            // The user sets the pace in manual mode: wait for them instead
+            if self.manual_advance && advanced != Some(index) && previous.is_some_and(|(prev, _)| prev != index) {
+                advanced = Some(index);
+                loop {
+                    if check_cancel_request(&mut report) {
+                        break;
+                    }
+                    match on_event(&SendEvent::AwaitingAdvance { index, total, line, target }) {
+                        Control::Continue => sender.sleep(MANUAL_ADVANCE_TICK_MS),
+                        Control::Advance => break,
+                        Control::Skip => {
+                            skipped = Some(index);
+                            break;
+                        }
+                        Control::Cancel => {
+                            log("Send cancelled by user");
+                            report.cancelled = true;
+                            break;
+                        }
+                        Control::SetSpeed(new_speed) => speed = new_speed,
+                        Control::SetDelays(new_delays) => delays = new_delays,
+                    }
+                }
+            }
+            if report.cancelled || skipped == Some(index) {
+                if !report.cancelled {
+                    log(&progress_record(index, total, &line.text, target, "skipped by user", Duration::ZERO, Duration::ZERO));
+                    report.targets[target_idx].skipped_by_user += 1;
+                }
+                report.targets[target_idx].skipped += 1;
+                if let Some(recording) = recording.as_mut().filter(|_| skipped == Some(index)) {
+                    recording.steps.push(Step {
+                        line: index,
+                        target: target.to_string(),
+                        text: line.text.clone(),
+                        options: TypingOptions { speed, chat: self.chat_for(line), ..TypingOptions::default() },
+                        at_ms: sender.now().saturating_duration_since(started).as_millis() as u64,
+                        duration_ms: 0,
+                        outcome: StepOutcome::Skipped,
+                    });
+                }
+                continue;
+            }
+
+            // Pause between lines; switching windows needs time for focus to settle
+            if let Some((_, prev_target)) = previous {
+                if prev_target != target_idx {
+                    sender.sleep(speed.scale(delays.broadcast_settle_ms));
+                } else if !self.manual_advance {
+                    sender.sleep(speed.scale(delays.next_line_ms));
+                }
+            }
+            if !self.manual_advance {
+                let mut cancelled = false;
+                if let Some(delay_ms) = line.delay_ms {
+                    cancelled = sleep_or_cancel(sender, delay_ms, &mut report);
+                }
+                let pause_ms = blank_pause_ms(line, &delays);
+                if pause_ms > 0 && !cancelled {
+                    cancelled = sleep_or_cancel(sender, speed.scale(pause_ms), &mut report);
+                }
+                if cancelled {
+                    report.targets[target_idx].skipped += 1;
+                    continue;
+                }
+            }
+
+            // Hold timed lines until the send clock reaches their time
+            if let Some(at) = line.at {
+                loop {
+                    if check_cancel_request(&mut report) {
+                        break;
+                    }
+                    let clock = self.clock_start + sender.now().saturating_duration_since(started);
+                    let left = at.saturating_sub(clock);
+                    if left.is_zero() {
+                        break;
+                    }
+                    match on_event(&SendEvent::Waiting { index, total, line, target, at, left }) {
+                        Control::Continue => {}
+                        Control::Cancel => {
+                            log("Send cancelled by user");
+                            report.cancelled = true;
+                            break;
+                        }
+                        Control::SetSpeed(new_speed) => speed = new_speed,
+                        Control::SetDelays(new_delays) => delays = new_delays,
+                        Control::Advance | Control::Skip => {}
+                    }
+                    sender.sleep((left.as_millis() as u64).min(TIMED_LINE_TICK_MS));
+                }
+                if report.cancelled {
+                    report.targets[target_idx].skipped += 1;
+                    continue;
+                }
+            }
+
+            // The game window closes for a moment at the end of a match;
+            // wait for it rather than failing the rest of the file
+            let mut window_lost = None;
+            if !self.window_lost_wait.is_zero() && reached[target_idx] && !sender.window_exists(target) {
+                log(&format!(
+                    "Window '{}' lost before line {}, waiting up to {}s",
+                    target,
+                    index + 1,
+                    self.window_lost_wait.as_secs()
+                ));
+                let lost_at = sender.now();
+                loop {
+                    if check_cancel_request(&mut report) {
+                        break;
+                    }
+                    if sender.window_exists(target) {
+                        log(&format!("Window '{}' is back", target));
+                        break;
+                    }
+                    let left = self.window_lost_wait.saturating_sub(sender.now().saturating_duration_since(lost_at));
+                    if left.is_zero() {
+                        window_lost = Some(format!(
+                            "window lost: '{}' closed and didn't come back within {}s",
+                            target,
+                            self.window_lost_wait.as_secs()
+                        ));
+                        break;
+                    }
+                    match on_event(&SendEvent::WindowLost { index, total, line, target, left }) {
+                        Control::Continue | Control::Advance | Control::Skip => {}
+                        Control::Cancel => {
+                            log("Send cancelled by user");
+                            report.cancelled = true;
+                            break;
+                        }
+                        Control::SetSpeed(new_speed) => speed = new_speed,
+                        Control::SetDelays(new_delays) => delays = new_delays,
+                    }
+                    sender.sleep((left.as_millis() as u64).min(WINDOW_LOST_TICK_MS));
+                }
+                if report.cancelled {
+                    report.targets[target_idx].skipped += 1;
+                    continue;
+                }
+            }
+
+            let control = loop {
+                let remaining = steps[step..]
+                    .iter()
+                    .filter(|&&(i, t)| report.targets[t].failed.is_none() && !excluded.contains(&i))
+                    .map(|&(i, _)| estimate_line(&lines[i], speed, &delays))
+                    .sum();
+                match on_event(&SendEvent::LineStarted { index, total, line, target, speed, remaining }) {
+                    Control::SetSpeed(new_speed) => {
+                        log(&format!("Speed changed to {}", new_speed.label()));
+                        speed = new_speed;
+                    }
+                    Control::SetDelays(new_delays) => {
+                        log(&format!("Delays changed to {:?}", new_delays));
+                        delays = new_delays;
+                    }
+                    control => break control,
+                }
+            };
+            if control == Control::Cancel {
+                log("Send cancelled by user");
+                report.cancelled = true;
+                report.targets[target_idx].skipped += 1;
+                continue;
+            }
+
+            trace::record_line(index, target);
+            let at = sender.now().saturating_duration_since(started);
+            let started = Instant::now();
+            let chat = self.chat_for(line);
+            let mut options = TypingOptions {
+                speed,
+                char_delay_ms: delays.char_type_ms,
+                chat,
+                key_mode: self.key_mode,
+                restore_delay_ms: delays.restore_settle_ms,
+                keys: KeyDelays {
+                    focus_ms: delays.keys.focus_ms + self.focus_delays.get(target).copied().unwrap_or(0),
+                    ..delays.keys
+                },
+                paste: pasting[target_idx],
+                post: self.send_method == SendMethod::PostMessage,
+                char_retries: self.char_retries,
+            };
+            let mut result = match window_lost.clone() {
+                Some(error) => Err(error),
+                None => self.type_line(sender, text, target, &options, delays.next_line_ms),
+            };
+            // Only the first pasted line is checked; if it didn't arrive, type it again
+            if options.paste && self.send_method == SendMethod::Auto && result.is_ok() && !paste_checked[target_idx] {
+                paste_checked[target_idx] = true;
+                if let Err(e) = hooks::paste_check(&self.paste_check_hook, target, text) {
+                    self.fall_back_to_typing(&mut report, &mut pasting, target_idx, &e, &mut on_event);
+                    options.paste = false;
+                    result = self.type_line(sender, text, target, &options, delays.next_line_ms);
+                }
+            }
+            // Type a line that wasn't seen once more before calling it unverified
+            let mut verified = None;
+            if result.is_ok() && self.verifier.is_some() {
+                let mut seen = self.verify_line(index, text, target);
+                if !seen {
+                    log(&format!("Line {} not seen in '{}', typing it again", index + 1, target));
+                    sender.sleep(speed.scale(delays.next_line_ms));
+                    result = self.type_line(sender, text, target, &options, delays.next_line_ms);
+                    seen = result.is_ok() && self.verify_line(index, text, target);
+                }
+                verified = Some(seen);
+            }
+            let elapsed = started.elapsed();
+            previous = Some((index, target_idx));
+            if let Some(recording) = &mut recording {
+                recording.steps.push(Step {
+                    line: index,
+                    target: target.to_string(),
+                    text: text.to_string(),
+                    options,
+                    at_ms: at.as_millis() as u64,
+                    duration_ms: elapsed.as_millis() as u64,
+                    outcome: match &result {
+                        Ok(_) if verified == Some(false) => StepOutcome::Unverified,
+                        Ok(_) => StepOutcome::Sent,
+                        Err(e) => StepOutcome::Failed(e.clone()),
+                    },
+                });
+            }
+
+            let target_report = &mut report.targets[target_idx];
+            let outcome = match (&result, verified) {
+                (Ok(_), Some(false)) => "sent, not seen in chat".to_string(),
+                (Ok(_), _) => "sent".to_string(),
+                (Err(e), _) => format!("failed: {}", e),
+            };
+            let window_time = result.as_ref().map_or(Duration::ZERO, |stats| stats.window_time);
+            log(&progress_record(index, total, &line.text, target, &outcome, elapsed, window_time));
+            match &result {
+                Ok(stats) => {
+                    target_report.sent += 1;
+                    target_report.retries += stats.retries;
+                    target_report.window_time += stats.window_time;
+                    if stats.focus != FocusCheck::Unchecked {
+                        target_report.focus_checks.push(stats.focus);
+                    }
+                    if let Some(Err(e)) = journal.as_mut().map(|j| j.line_sent(index, target)) {
+                        log(&e);
+                    }
+                    reached[target_idx] = true;
+                    match verified {
+                        Some(true) => target_report.verified += 1,
+                        Some(false) => target_report.unverified.push(index),
+                        None => {}
+                    }
+                }
+                Err(e) => {
+                    target_report.failed = Some((index, line.text.clone(), e.clone()));
+                    target_report.window_lost = window_lost.is_some();
+                }
+            }
+
+            let event = SendEvent::LineFinished { index, total, line, target, result: &result, elapsed };
+            match on_event(&event) {
+                Control::Continue | Control::Advance | Control::Skip => {}
+                Control::Cancel => {
+                    log("Send cancelled by user");
+                    report.cancelled = true;
+                }
+                Control::SetSpeed(new_speed) => {
+                    log(&format!("Speed changed to {}", new_speed.label()));
+                    speed = new_speed;
+                }
+                Control::SetDelays(new_delays) => {
+                    log(&format!("Delays changed to {:?}", new_delays));
+                    delays = new_delays;
+                }
+            }
+        }
+
+        complete_cancel_request();
+        // Without its completion record, a send cut off by closing the
+        // console is offered for resuming at the next start
+        let shut_down = report.cancelled_by.as_deref() == Some(SHUTDOWN_REQUESTER);
+        if let Some(Err(e)) = journal.filter(|_| !shut_down).map(|j| j.finish(report.sent())) {
+            log(&e);
+        }
+        if self.trace_input {
+            match trace::finish() {
+                Some(Ok(path)) => {
+                    log(&format!("Input trace written to {}", path.display()));
+                    report.trace_file = Some(path);
+                }
+                Some(Err(e)) => log(&format!("Failed to write the input trace: {}", e)),
+                None => {}
+            }
+        }
+        if let Some(recording) = recording {
+            match replay::save(&recording) {
+                Ok(path) => {
+                    log(&format!("Session recorded to {}", path.display()));
+                    report.session_file = Some(path);
+                }
+                Err(e) => log(&format!("Failed to record the session: {}", e)),
+            }
+        }
+        log(&format!(
+            "Send finished: {} sent, {} skipped, {} left out, {} window(s) failed{} ({} ms on windows)",
+            report.sent(),
+            report.skipped(),
+            report.excluded(),
+            report.failed(),
+            if report.cancelled { ", cancelled" } else { "" },
+            report.targets.iter().map(|t| t.window_time).sum::<Duration>().as_millis()
+        ));
+        on_event(&SendEvent::Finished { report: &report });
+        Ok(report)
+    }
+
+    /// Type into the target at `target_idx` instead of pasting from now on.
+    fn fall_back_to_typing(
+        &self,
+        report: &mut SendReport,
+        pasting: &mut [bool],
+        target_idx: usize,
+        reason: &str,
+        on_event: &mut impl FnMut(&SendEvent) -> Control,
+    ) {
+        let target = self.targets[target_idx].as_str();
+        log(&format!("Send method for '{}': falling back to typing ({})", target, reason));
+        pasting[target_idx] = false;
+        report.targets[target_idx].paste_fallback = Some(reason.to_string());
+        on_event(&SendEvent::MethodChosen { target, method: SendMethod::Type, reason });
+    }
+
+    /// Whether the verifier saw line `index` in `target`, giving up after
+    /// `VERIFY_TIMEOUT_MS`. A verifier still running then is left to
+    /// finish on its own.
+    fn verify_line(&self, index: usize, text: &str, target: &str) -> bool {
+        let Some(verifier) = self.verifier.clone() else {
+            return true;
+        };
+        let (tx, rx) = mpsc::channel();
+        let (text, target) = (text.to_string(), target.to_string());
+        thread::spawn(move || {
+            let _ = tx.send(verifier(index, &text, &target));
+        });
+        match rx.recv_timeout(Duration::from_millis(VERIFY_TIMEOUT_MS)) {
+            Ok(seen) => seen,
+            Err(_) => {
+                log(&format!("Verifying line {} took over {} ms, counted as not seen", index + 1, VERIFY_TIMEOUT_MS));
+                false
+            }
+        }
+    }
+
+    /// Chat channel `line` is typed into.
+    fn chat_for(&self, line: &Line) -> ChatScope {
+        if self.slash_commands && line.is_command() {
+            ChatScope::Command
+        } else {
+            line.chat.unwrap_or(self.chat)
+        }
+    }
+
+    /// Type one line, as several messages if it's over the game limit,
+    /// pausing `part_delay_ms` between parts.
+    fn type_line(
+        &self,
+        sender: &mut (impl LineSender + ?Sized),
+        text: &str,
+        target: &str,
+        options: &TypingOptions,
+        part_delay_ms: u64,
+    ) -> Result<TypeStats, String> {
+        let mut stats = TypeStats::default();
+        for (i, part) in message_parts(text, self.char_limit).into_iter().enumerate() {
+            if i > 0 {
+                sender.sleep(options.speed.scale(part_delay_ms));
+            }
+            stats.add(sender.send_line(part, target, options)?);
+        }
+        Ok(stats)
+    }
+}
+
+/// Split `text` into messages of at most `max_chars` characters, breaking
+/// at the last space that fits (or mid-word if there is none). A limit of 0
+/// leaves `text` whole.
+pub fn split_message(text: &str, max_chars: usize) -> Vec<&str> {
+    if max_chars == 0 {
+        return vec![text];
+    }
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+    while let Some((limit, _)) = rest.char_indices().nth(max_chars) {
+        let end = rest[..limit].rfind(' ').filter(|&end| end > 0).unwrap_or(limit);
+        parts.push(rest[..end].trim_end());
+        rest = rest[end..].trim_start();
+    }
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(rest);
+    }
+    parts
+}
+
+/// `text` as the messages typed for it under `char_limit`; whole if there
+/// is no limit.
+pub fn message_parts(text: &str, char_limit: Option<usize>) -> Vec<&str> {
+    match char_limit {
+        Some(max) => split_message(text, max),
+        None => vec![text],
+    }
+}
+
+/// The sound to play for a session event under the configured setting.
+pub fn send_cue(event: &SendEvent, sound: SendSound) -> Option<Cue> {
+    match (event, sound) {
+        (_, SendSound::Off) => None,
+        (SendEvent::LineFinished { result: Err(_), .. }, _) => Some(Cue::Failed),
+        (SendEvent::LineFinished { result: Ok(_), .. }, SendSound::Line) => Some(Cue::LineSent),
+        (SendEvent::Finished { .. }, _) => Some(Cue::Finished),
+        _ => None,
+    }
+}
+
+/// Title and text of the notification shown when a send of `file_name` is
+/// over, e.g. ("gg.txt sent", "14 of 14 line(s) sent").
+pub fn finish_notice(file_name: &str, report: &SendReport, total: usize) -> (String, String) {
+    let outcome = if report.cancelled {
+        "cancelled"
+    } else if report.failed() > 0 {
+        "failed"
+    } else {
+        "sent"
+    };
+    let mut text = format!("{} of {} line(s) sent", report.sent(), total * report.targets.len());
+    if report.failed() > 0 {
+        text.push_str(&format!(", {} window(s) failed", report.failed()));
+    }
+    if report.skipped() > 0 {
+        text.push_str(&format!(", {} skipped", report.skipped()));
+    }
+    (format!("{} {}", file_name, outcome), text)
+}
+
+/// Type a single harmless line into team chat of every target window.
+///
+/// Goes through the same session pipeline as a real send (focus check,
+/// chat-open sequence, timing), so success means a real send should work.
+pub fn ping(
+    sender: &mut impl LineSender,
+    text: &str,
+    targets: Vec<String>,
+    config: &Config,
+) -> Result<SendReport, String> {
+    let lines = [Line::new(text)];
+    SendSession::new(&lines, targets, BroadcastMode::PerLine)
+        .with_delays(SendDelays::from(config))
+        .with_key_mode(KeyMode::from(config))
+        .with_char_limit(config.char_limit())
+        .with_chat(ChatScope::Team)
+        .run(sender, |_| Control::Continue)
+}
+
+/// Pause before `line` for the blank lines above it.
+pub fn blank_pause_ms(line: &Line, delays: &SendDelays) -> u64 {
+    line.blank_before as u64 * delays.blank_line_pause_ms
+}
+
+/// Rough time needed to send one line at the given speed, based on the
+/// configured delays (assumes no Shift and no dropped characters).
+pub fn estimate_line(line: &Line, speed: Speed, delays: &SendDelays) -> Duration {
+    let chars = line.text.chars().count() as u64;
+    let keys = &delays.keys;
+    let typing = 2 * keys.shift_key_ms
+        + 2 * keys.key_press_ms
+        + keys.chat_open_ms
+        + keys.after_type_ms
+        + keys.after_send_ms
+        + delays.next_line_ms
+        + chars * (keys.key_press_ms + delays.char_type_ms);
+    // Focus delays are not affected by the speed multiplier
+    let ms = keys.focus_ms
+        + WINDOW_FOCUS_DELAY_MS
+        + speed.scale(typing + blank_pause_ms(line, delays))
+        + line.delay_ms.unwrap_or(0);
+    Duration::from_millis(ms)
+}
+
+/// Lines of `lines` over `max` characters, which a send refuses unless
+/// long lines are split.
+pub fn long_lines(lines: &[Line], max: usize) -> usize {
+    lines.iter().filter(|l| l.text.chars().count() > max).count()
+}
+
+/// Messages typed into each window for `lines`: one per line, or one per
+/// part of lines over `char_limit` when they are split.
+pub fn message_count(lines: &[Line], split_long_lines: bool, char_limit: Option<usize>) -> usize {
+    if !split_long_lines {
+        return lines.len();
+    }
+    lines.iter().map(|l| message_parts(&l.text, char_limit).len()).sum()
+}
+
+/// Why a send of `count` messages can't start with `queued` messages of
+/// other sends still to go, under a cap of `max` per send; None if it can.
+pub fn line_cap_problem(count: usize, queued: usize, max: u64) -> Option<String> {
+    let total = (count + queued) as u64;
+    if total <= max {
+        return None;
+    }
+    let what = if queued == 0 {
+        format!("This send is {} messages", count)
+    } else {
+        format!("This send is {} messages with {} more queued", count, queued)
+    };
+    Some(format!(
+        "{}, over the limit of {} per send (raise \"Max lines per send\" in F2 or max_lines_per_send in madtyping.toml)",
+        what, max
+    ))
+}
+
+/// The game's chat spam limit: more than `messages` lines within `window`
+/// is likely to get the sender muted. `messages` 0 means no limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChatLimit {
+    pub messages: u64,
+    pub window: Duration,
+}
+
+impl From<&Config> for ChatLimit {
+    fn from(config: &Config) -> Self {
+        Self {
+            messages: config.chat_limit_messages,
+            window: Duration::from_secs(config.chat_limit_window_secs),
+        }
+    }
+}
+
+/// A send that will probably trip the chat spam limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MuteRisk {
+    /// Lines in the send
+    pub messages: usize,
+    /// Estimated time to send all of them
+    pub within: Duration,
+}
+
+impl MuteRisk {
+    /// Indicator text, e.g. "⚠ likely mute: 42 msgs in <60s at current speed".
+    pub fn label(&self) -> String {
+        let secs = self.within.as_millis().div_ceil(1000);
+        format!("⚠ likely mute: {} msgs in <{}s at current speed", self.messages, secs)
+    }
+}
+
+/// Check whether sending `line_count` lines, each taking about `per_line`,
+/// goes over `limit`. Informational only; nothing refuses to send on it.
+pub fn mute_risk(line_count: usize, per_line: Duration, limit: ChatLimit) -> Option<MuteRisk> {
+    // 0 turns the chat limit off
+    if limit.messages == 0 || line_count as u64 <= limit.messages {
+        return None;
+    }
+    // The first `messages + 1` lines are `messages` line intervals apart
+    let burst = per_line.saturating_mul(u32::try_from(limit.messages).unwrap_or(u32::MAX));
+    if burst >= limit.window {
+        return None;
+    }
+    Some(MuteRisk {
+        messages: line_count,
+        within: per_line.saturating_mul(u32::try_from(line_count).unwrap_or(u32::MAX)),
+    })
+}
+
+/// Log record of one line's outcome on one target, e.g.
+/// `Line 3/12 'gg wp' -> 'League of Legends (TM) Client': sent (412 ms, 3 ms on the window)`.
+/// Every send writes one per line and target at the default log level, so
+/// the log shows where a send stopped whichever frontend ran it. A zero
+/// `window_time` is left out.
+fn progress_record(
+    index: usize,
+    total: usize,
+    text: &str,
+    target: &str,
+    outcome: &str,
+    elapsed: Duration,
+    window_time: Duration,
+) -> String {
+    // Long lines are cut like the progress rows of the terminal UI
+    let preview: String = text.chars().take(40).collect();
+    let cut = if preview.len() < text.len() { "..." } else { "" };
+    let window = if window_time.is_zero() { String::new() } else { format!(", {} ms on the window", window_time.as_millis()) };
+    format!("Line {}/{} '{}{}' -> '{}': {} ({} ms{})", index + 1, total, preview, cut, target, outcome, elapsed.as_millis(), window)
+}
+
+/// [`mute_risk`] for sending `lines` at `speed` with the configured delays
+/// and chat limit (per target window; each window has its own chat).
+pub fn file_mute_risk(lines: &[Line], speed: Speed, config: &Config) -> Option<MuteRisk> {
+    if lines.is_empty() {
+        return None;
+    }
+    let delays = SendDelays::from(config);
+    let total: Duration = lines.iter().map(|line| estimate_line(line, speed, &delays)).sum();
+    mute_risk(lines.len(), total / lines.len() as u32, ChatLimit::from(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records which line went to which window; fails on configured pairs.
+    #[derive(Default)]
+    struct MockSender {
+        sent: Vec<(String, String)>,
+        sleeps: Vec<u64>,
+        fail_on: Vec<(String, String)>,
+        chats: Vec<ChatScope>,
+        pastes: Vec<bool>,
+        posts: Vec<bool>,
+    }
+
+    impl LineSender for MockSender {
+        fn send_line(&mut self, text: &str, window_title: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+            self.chats.push(options.chat);
+            self.pastes.push(options.paste);
+            self.posts.push(options.post);
+            let pair = (text.to_string(), window_title.to_string());
+            if self.fail_on.contains(&pair) {
+                return Err("window closed".to_string());
+            }
+            self.sent.push(pair);
+            Ok(TypeStats { chars_typed: text.len(), ..TypeStats::default() })
+        }
+
+        fn sleep(&mut self, ms: u64) {
+            self.sleeps.push(ms);
+        }
+    }
+
+    fn lines(texts: &[&str]) -> Vec<Line> {
+        texts.iter().map(|t| Line::new(*t)).collect()
+    }
+
+    fn pairs(sent: &[(String, String)]) -> Vec<(&str, &str)> {
+        sent.iter().map(|(l, w)| (l.as_str(), w.as_str())).collect()
+    }
+
+    #[test]
+    fn test_every_line_outcome_logged() {
+        let lines = lines(&["gl hf", "", "push mid", "this line is rather long and gets cut in the log record"]);
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerLine)
+            .with_excluded_lines([1].into());
+        let mut sender = MockSender { fail_on: vec![("push mid".into(), "two".into())], ..MockSender::default() };
+        let (report, logged) = crate::logging::capture(|| session.run(&mut sender, |_| Control::Continue).unwrap());
+        assert_eq!(report.sent(), 4);
+
+        // Durations vary, so compare without them
+        let records: Vec<&str> = logged
+            .iter()
+            .filter(|m| m.starts_with("Line ") || m.starts_with("Send finished"))
+            .map(|m| m.rsplit_once(" (").map_or(m.as_str(), |(record, _)| record))
+            .collect();
+        assert_eq!(records, vec![
+            "Line 1/4 'gl hf' -> 'one': sent",
+            "Line 1/4 'gl hf' -> 'two': sent",
+            "Line 2/4 '' -> 'one': left out",
+            "Line 2/4 '' -> 'two': left out",
+            "Line 3/4 'push mid' -> 'one': sent",
+            "Line 3/4 'push mid' -> 'two': failed: window closed",
+            "Line 4/4 'this line is rather long and gets cut in...' -> 'one': sent",
+            "Send finished: 4 sent, 1 skipped, 2 left out, 1 window(s) failed",
+        ]);
+        assert!(logged.iter().filter(|m| m.starts_with("Line ")).all(|m| m.ends_with(" ms)")));
+    }
+
+    #[test]
+    fn test_broadcast_per_line_order() {
+        let lines = lines(&["a", "b"]);
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender::default();
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        assert_eq!(pairs(&sender.sent), vec![("a", "one"), ("a", "two"), ("b", "one"), ("b", "two")]);
+        assert_eq!(report.sent(), 4);
+        assert_eq!(sender.sleeps, vec![BROADCAST_SETTLE_DELAY_MS; 3]);
+    }
+
+    #[test]
+    fn test_broadcast_per_file_order() {
+        let lines = lines(&["a", "b"]);
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerFile);
+        let mut sender = MockSender::default();
+        session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        assert_eq!(pairs(&sender.sent), vec![("a", "one"), ("b", "one"), ("a", "two"), ("b", "two")]);
+        assert_eq!(sender.sleeps, vec![NEXT_LINE_DELAY_MS, BROADCAST_SETTLE_DELAY_MS, NEXT_LINE_DELAY_MS]);
+    }
+
+    #[test]
+    fn test_failure_on_one_target_does_not_abort_others() {
+        let lines = lines(&["a", "b", "c"]);
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender { fail_on: vec![("b".into(), "one".into())], ..MockSender::default() };
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        let one = &report.targets[0];
+        assert_eq!((one.sent, one.skipped), (1, 1));
+        assert_eq!(one.failed, Some((1, "b".into(), "window closed".into())));
+        let two = &report.targets[1];
+        assert_eq!((two.sent, two.skipped, two.failed.is_none()), (3, 0, true));
+    }
+
+    #[test]
+    fn test_cancel_skips_remaining() {
+        let lines = lines(&["a", "b", "c"]);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender::default();
+        let report = session.run(&mut sender, |event| match event {
+            SendEvent::LineStarted { index: 1, .. } => Control::Cancel,
+            _ => Control::Continue,
+        }).unwrap();
+
+        assert!(report.cancelled);
+        assert_eq!((report.sent(), report.skipped()), (1, 2));
+    }
+
+    #[test]
+    fn test_manual_advance_waits_for_each_line() {
+        let mut lines = lines(&["a", "b", "c", "d"]);
+        lines[1].delay_ms = Some(1234);
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerLine)
+            .with_manual_advance(true);
+        let mut sender = MockSender::default();
+        let mut asked = Vec::new();
+        let report = session.run(&mut sender, |event| match *event {
+            SendEvent::AwaitingAdvance { index, .. } => {
+                asked.push(index);
+                match (index, asked.iter().filter(|&&i| i == index).count()) {
+                    (1, 1) => Control::Continue,
+                    (2, _) => Control::Skip,
+                    (3, _) => Control::Cancel,
+                    _ => Control::Advance,
+                }
+            }
+            _ => Control::Continue,
+        }).unwrap();
+
+        // Asked once per line, not per target; no line delays in between
+        assert_eq!(asked, vec![1, 1, 2, 3]);
+        assert_eq!(pairs(&sender.sent), vec![("a", "one"), ("a", "two"), ("b", "one"), ("b", "two")]);
+        assert_eq!(
+            sender.sleeps,
+            vec![BROADCAST_SETTLE_DELAY_MS, MANUAL_ADVANCE_TICK_MS, BROADCAST_SETTLE_DELAY_MS, BROADCAST_SETTLE_DELAY_MS]
+        );
+        assert!(report.cancelled);
+        assert_eq!((report.sent(), report.skipped()), (4, 4));
+        assert_eq!(report.targets[0].skipped_by_user, 1);
+    }
+
+    #[test]
+    fn test_skipped_lines_keep_a_send_from_being_full() {
+        let lines = lines(&["a", "b", "c"]);
+        let run = |skip: bool| {
+            SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+                .with_manual_advance(true)
+                .with_char_filter(CharFilter::new(vec![('c' as u32, 'c' as u32)], None, false))
+                .run(&mut MockSender::default(), |event| match *event {
+                    SendEvent::AwaitingAdvance { index: 1, .. } if skip => Control::Skip,
+                    SendEvent::AwaitingAdvance { .. } => Control::Advance,
+                    _ => Control::Continue,
+                })
+                .unwrap()
+        };
+
+        // "c" is emptied by the filter; that alone doesn't count
+        let report = run(false);
+        assert_eq!((report.sent(), report.skipped()), (2, 1));
+        assert!(report.fully_sent());
+
+        let report = run(true);
+        assert!(!report.cancelled);
+        assert_eq!((report.sent(), report.skipped()), (1, 2));
+        assert!(!report.fully_sent());
+    }
+
+    #[test]
+    fn test_line_delays_honored() {
+        let mut lines = lines(&["a", "b"]);
+        lines[1].delay_ms = Some(1234);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender::default();
+        session.run(&mut sender, |_| Control::Continue).unwrap();
+        // In ticks, so Esc isn't ignored for the whole delay
+        assert_eq!(sender.sleeps, vec![NEXT_LINE_DELAY_MS, 250, 250, 250, 250, 234]);
+    }
+
+    #[test]
+    fn test_cancel_during_line_delay() {
+        /// Sender that asks to cancel once it has slept `cancel_after` ms.
+        #[derive(Default)]
+        struct CancellingSender {
+            slept_ms: u64,
+            cancel_after: u64,
+            sent: Vec<String>,
+        }
+
+        impl LineSender for CancellingSender {
+            fn send_line(&mut self, text: &str, _window_title: &str, _options: &TypingOptions) -> Result<TypeStats, String> {
+                self.sent.push(text.to_string());
+                Ok(TypeStats::default())
+            }
+
+            fn sleep(&mut self, ms: u64) {
+                self.slept_ms += ms;
+                if self.slept_ms >= self.cancel_after {
+                    cancel_running_send("Esc");
+                }
+            }
+        }
+
+        let mut lines = lines(&["a", "b", "c"]);
+        lines[1].delay_ms = Some(60_000);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_delays(SendDelays { next_line_ms: 0, ..SendDelays::default() });
+        let mut sender = CancellingSender { cancel_after: 1000, ..CancellingSender::default() };
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+        assert_eq!(sender.sent, vec!["a"]);
+        assert_eq!(sender.slept_ms, 1000);
+        assert!(report.cancelled);
+        assert_eq!(report.targets[0].skipped, 2);
+    }
+
+    #[test]
+    fn test_blank_line_pauses() {
+        let mut lines = lines(&["a", "b", "c"]);
+        lines[1].blank_before = 2;
+        lines[2].blank_before = 1;
+        let session = || SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender::default();
+        session().run(&mut sender, |_| Control::Continue).unwrap();
+        assert_eq!(sender.sleeps, vec![NEXT_LINE_DELAY_MS, NEXT_LINE_DELAY_MS]);
+
+        let delays = SendDelays { blank_line_pause_ms: 300, ..SendDelays::default() };
+        let mut sender = MockSender::default();
+        session().with_delays(delays).run(&mut sender, |_| Control::Continue).unwrap();
+        assert_eq!(sender.sleeps, vec![NEXT_LINE_DELAY_MS, 250, 250, 100, NEXT_LINE_DELAY_MS, 250, 50]);
+    }
+
+    #[test]
+    fn test_configured_delays_used() {
+        let lines = lines(&["a", "b"]);
+        let delays = SendDelays { next_line_ms: 40, broadcast_settle_ms: 70, ..SendDelays::default() };
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerFile)
+            .with_delays(delays);
+        let mut sender = MockSender::default();
+        session.run(&mut sender, |_| Control::Continue).unwrap();
+        assert_eq!(sender.sleeps, vec![40, 70, 40]);
+    }
+
+    #[test]
+    fn test_custom_key_delays_reach_the_backend() {
+        let lines = lines(&["a", "b"]);
+        let keys = KeyDelays {
+            key_press_ms: 3,
+            key_batch_ms: 5,
+            chat_open_ms: 7,
+            after_type_ms: 8,
+            after_send_ms: 9,
+            ..KeyDelays::default()
+        };
+        let configured = SendDelays { char_type_ms: 2, next_line_ms: 40, ..SendDelays::default() };
+        let delays = SendDelays { keys, ..configured };
+        let mut sender = BackendSender::new(platform::MockBackend::new());
+        SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_delays(delays)
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap();
+
+        let message = [5, 7, 3, 2, 8, 5, 9];
+        assert_eq!(sender.backend.sleeps, [&message[..], &[40], &message[..]].concat());
+        assert!(estimate_line(&lines[0], Speed::NORMAL, &delays) < estimate_line(&lines[0], Speed::NORMAL, &configured));
+    }
+
+    #[test]
+    fn test_ping_uses_team_chat_on_every_target() {
+        let mut sender = MockSender { fail_on: vec![(".".into(), "two".into())], ..MockSender::default() };
+        let report = ping(&mut sender, ".", vec!["one".into(), "two".into()], &Config::default()).unwrap();
+
+        assert_eq!(sender.chats, vec![ChatScope::Team, ChatScope::Team]);
+        assert_eq!(report.targets[0].sent, 1);
+        assert_eq!(report.targets[1].failed, Some((0, ".".into(), "window closed".into())));
+    }
+
+    #[test]
+    fn test_line_channel_overrides_session_channel() {
+        let lines = vec![Line::new("a"), Line { chat: Some(ChatScope::Team), ..Line::new("b") }];
+        let mut sender = MockSender::default();
+        SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap();
+        assert_eq!(sender.chats, vec![ChatScope::All, ChatScope::Team]);
+    }
+
+    #[test]
+    fn test_slash_lines_sent_as_commands() {
+        let lines = vec![
+            Line::new("/mute all"),
+            Line { chat: Some(ChatScope::All), ..Line::new("/ff") },
+            Line::new("gl hf"),
+            Line::new("/ 2"),
+        ];
+        let mut sender = MockSender::default();
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine).with_chat(ChatScope::Team);
+        session.run(&mut sender, |_| Control::Continue).unwrap();
+        assert_eq!(sender.chats, vec![ChatScope::Team, ChatScope::All, ChatScope::Team, ChatScope::Team]);
+
+        let mut sender = MockSender::default();
+        session.with_slash_commands(true).run(&mut sender, |_| Control::Continue).unwrap();
+        assert_eq!(sender.chats, vec![ChatScope::Command, ChatScope::Command, ChatScope::Team, ChatScope::Team]);
+    }
+
+    #[test]
+    fn test_lines_emptied_by_the_filter_are_skipped() {
+        let lines = lines(&["gg 😀", "😀 🎉", "wp"]);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_char_filter(CharFilter::new(Vec::new(), None, true));
+        let mut sender = MockSender::default();
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        assert_eq!(pairs(&sender.sent), vec![("gg", "one"), ("wp", "one")]);
+        assert_eq!((report.sent(), report.skipped()), (2, 1));
+    }
+
+    #[test]
+    fn test_learned_focus_delays_applied_and_checks_reported() {
+        /// Notes the focus delay of each line; "slow" only focuses late
+        #[derive(Default)]
+        struct FocusSender {
+            focus_ms: Vec<(String, u64)>,
+        }
+
+        impl LineSender for FocusSender {
+            fn send_line(&mut self, _text: &str, window_title: &str, options: &TypingOptions) -> Result<TypeStats, String> {
+                self.focus_ms.push((window_title.to_string(), options.keys.focus_ms));
+                let focus = if window_title == "slow" { FocusCheck::Late { extra_ms: 80 } } else { FocusCheck::Focused };
+                Ok(TypeStats { focus, ..TypeStats::default() })
+            }
+
+            fn sleep(&mut self, _ms: u64) {}
+        }
+
+        let lines = lines(&["gl"]);
+        let session = SendSession::new(&lines, vec!["slow".into(), "fast".into()], BroadcastMode::PerLine)
+            .with_focus_delays(BTreeMap::from([("slow".to_string(), 120)]));
+        let mut sender = FocusSender::default();
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        let base = KeyDelays::default().focus_ms;
+        assert_eq!(sender.focus_ms, vec![("slow".to_string(), base + 120), ("fast".to_string(), base)]);
+        assert_eq!(report.targets[0].focus_checks, vec![FocusCheck::Late { extra_ms: 80 }]);
+        assert_eq!(report.targets[1].focus_checks, vec![FocusCheck::Focused]);
+    }
+
+    #[test]
+    fn test_time_conditions_render_and_skip_empty_lines() {
+        let lines = lines(&["{?morning:good morning}{?evening:good evening} summoners", "{?morning:gm}", "happy {dow}"]);
+        let evening = LocalTime { hour: 19, minute: 30, weekday: 6 };
+        let session =
+            SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine).with_local_time(evening);
+        let mut sender = MockSender::default();
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        assert_eq!(pairs(&sender.sent), vec![("good evening summoners", "one"), ("happy Saturday", "one")]);
+        assert_eq!((report.sent(), report.skipped()), (2, 1));
+    }
+
+    #[test]
+    fn test_excluded_lines_are_left_out_and_counted_apart() {
+        let long = "x".repeat(GAME_MESSAGE_MAX_CHARS + 1);
+        let lines = lines(&["gl", &long, "hf", "gg"]);
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerLine)
+            .with_excluded_lines(HashSet::from([1, 3]))
+            .with_max_lines_per_send(2);
+        let mut sender = MockSender::default();
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        assert_eq!(pairs(&sender.sent), vec![("gl", "one"), ("gl", "two"), ("hf", "one"), ("hf", "two")]);
+        assert_eq!((report.sent(), report.skipped(), report.excluded()), (4, 0, 4));
+        assert_eq!(report.targets[1].excluded, 2);
+    }
+
+    #[test]
+    fn test_choices_set_channel_order_speed_and_repeats() {
+        let lines = lines(&["gl", "hf", " gl ", "gg"]);
+        assert_eq!(repeated_lines(&lines), HashSet::from([2]));
+        let choices = SendChoices {
+            chat: ChatScope::Team,
+            order: BroadcastMode::PerFile,
+            speed: Speed::from_percent(200).unwrap(),
+            skip_repeats: true,
+            ..SendChoices::default()
+        };
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerLine).with_choices(choices);
+        let mut mock = MockSender::default();
+        // Frontends pick the sender at runtime for dry runs
+        let sender: &mut dyn LineSender = &mut mock;
+        let mut speeds = Vec::new();
+        let report = session
+            .run(sender, |event| {
+                if let SendEvent::LineStarted { speed, .. } = event {
+                    speeds.push(speed.percent());
+                }
+                Control::Continue
+            })
+            .unwrap();
+
+        assert_eq!(
+            pairs(&mock.sent),
+            vec![("gl", "one"), ("hf", "one"), ("gg", "one"), ("gl", "two"), ("hf", "two"), ("gg", "two")]
+        );
+        assert_eq!(report.excluded(), 2);
+        assert!(mock.chats.iter().all(|&c| c == ChatScope::Team));
+        assert!(speeds.iter().all(|&s| s == 200));
+    }
+
+    #[test]
+    fn test_post_message_method_posts_every_line() {
+        let lines = lines(&["a", "b"]);
+        let session = SendSession::new(&lines, vec!["Notepad".into()], BroadcastMode::PerLine).with_send_method(SendMethod::PostMessage);
+        let mut sender = MockSender::default();
+        let mut chosen = Vec::new();
+        session
+            .run(&mut sender, |event| {
+                if let SendEvent::MethodChosen { method, reason, .. } = event {
+                    chosen.push((*method, reason.to_string()));
+                }
+                Control::Continue
+            })
+            .unwrap();
+
+        assert_eq!(chosen, vec![(SendMethod::PostMessage, "send_method = post_message".to_string())]);
+        assert_eq!((sender.posts, sender.pastes), (vec![true, true], vec![false, false]));
+    }
+
+    #[test]
+    fn test_auto_method_falls_back_when_flagged() {
+        let lines = lines(&["a", "b", "c"]);
+        let session = SendSession::new(&lines, vec!["one".into(), "two".into()], BroadcastMode::PerLine)
+            .with_send_method(SendMethod::Auto)
+            .with_typed_windows(vec!["two".into()]);
+        let mut sender = MockSender::default();
+        let mut chosen = Vec::new();
+        let report = session.run(&mut sender, |event| {
+            match event {
+                SendEvent::MethodChosen { target, method, .. } => chosen.push((target.to_string(), *method)),
+                SendEvent::LineFinished { index: 0, target: "two", .. } => assert!(flag_paste_failed()),
+                _ => {}
+            }
+            Control::Continue
+        }).unwrap();
+
+        assert_eq!(sender.pastes, vec![true, false, false, false, false, false]);
+        let chosen: Vec<(&str, SendMethod)> = chosen.iter().map(|(t, m)| (t.as_str(), *m)).collect();
+        assert_eq!(chosen, vec![("one", SendMethod::Paste), ("two", SendMethod::Type), ("one", SendMethod::Type)]);
+        assert_eq!(report.targets[0].paste_fallback.as_deref(), Some("flagged by the user"));
+        assert_eq!(report.targets[1].paste_fallback, None);
+        assert!(!flag_paste_failed());
+    }
+
+    #[test]
+    fn test_unseen_lines_are_typed_again_then_unverified() {
+        let lines = lines(&["a", "b", "c"]);
+        let checks = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&checks);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine).with_verifier(move |index, text, window| {
+            let mut seen = seen.lock().unwrap();
+            seen.push((index, text.to_string(), window.to_string()));
+            // "b" shows up the second time, "c" never does
+            text == "a" || (text == "b" && seen.len() == 3)
+        });
+        let mut sender = MockSender::default();
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        assert_eq!(pairs(&sender.sent), vec![("a", "one"), ("b", "one"), ("b", "one"), ("c", "one"), ("c", "one")]);
+        assert_eq!(checks.lock().unwrap().len(), 5);
+        assert_eq!(checks.lock().unwrap()[3], (2, "c".to_string(), "one".to_string()));
+        assert_eq!((report.sent(), report.verified(), report.unverified()), (3, 2, 1));
+        assert_eq!(report.targets[0].unverified, vec![2]);
+        assert_eq!(
+            report.verification_summary().as_deref(),
+            Some("Verified 2 line(s); not seen in chat even after typing it again: line 3")
+        );
+        assert_eq!(SendReport::default().verification_summary(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_paste_check_types_the_line_again() {
+        let lines = lines(&["a", "b"]);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_send_method(SendMethod::Auto)
+            .with_paste_check_hook("exit 1");
+        let mut sender = MockSender::default();
+        let report = session.run(&mut sender, |_| Control::Continue).unwrap();
+
+        assert_eq!(pairs(&sender.sent), vec![("a", "one"), ("a", "one"), ("b", "one")]);
+        assert_eq!(sender.pastes, vec![true, false, false]);
+        assert_eq!(report.sent(), 2);
+        assert_eq!(report.targets[0].paste_fallback.as_deref(), Some("paste_check_hook exited with code 1"));
+    }
+
+    #[test]
+    fn test_speed_change_scales_later_delays_and_estimate() {
+        let lines = lines(&["a", "b", "c"]);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender::default();
+        let mut estimates = Vec::new();
+        session.run(&mut sender, |event| match *event {
+            SendEvent::LineStarted { index, speed, remaining, .. } => {
+                estimates.push(remaining);
+                if index == 1 && speed == Speed::NORMAL {
+                    Control::SetSpeed(Speed::NORMAL.slower().slower())
+                } else {
+                    Control::Continue
+                }
+            }
+            _ => Control::Continue,
+        }).unwrap();
+
+        // Line 2 is re-announced at half speed, so its estimate goes up
+        assert_eq!(estimates.len(), 4);
+        assert!(estimates[2] > estimates[1]);
+        assert_eq!(sender.sleeps, vec![NEXT_LINE_DELAY_MS, NEXT_LINE_DELAY_MS * 2]);
+    }
+
+    /// Sender whose clock only moves when it sleeps.
+    struct ClockSender {
+        start: Instant,
+        slept_ms: u64,
+        /// (line, clock in ms when it was sent)
+        sent: Vec<(String, u64)>,
+    }
+
+    impl LineSender for ClockSender {
+        fn send_line(&mut self, text: &str, _window_title: &str, _options: &TypingOptions) -> Result<TypeStats, String> {
+            self.sent.push((text.to_string(), self.slept_ms));
+            Ok(TypeStats { chars_typed: text.len(), ..TypeStats::default() })
+        }
+
+        fn sleep(&mut self, ms: u64) {
+            self.slept_ms += ms;
+        }
+
+        fn now(&mut self) -> Instant {
+            self.start + Duration::from_millis(self.slept_ms)
+        }
+    }
+
+    #[test]
+    fn test_timed_lines_wait_for_the_clock() {
+        let at = |secs, text| Line { at: Some(Duration::from_secs(secs)), ..Line::new(text) };
+        let lines = vec![Line::new("gl hf"), at(3, "ff?"), at(1, "late")];
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_delays(SendDelays { next_line_ms: 0, ..SendDelays::default() })
+            .with_clock_start(Duration::from_secs(1));
+        let mut sender = ClockSender { start: Instant::now(), slept_ms: 0, sent: Vec::new() };
+        let mut countdown = Vec::new();
+        session.run(&mut sender, |event| {
+            if let SendEvent::Waiting { left, .. } = event {
+                countdown.push(left.as_millis());
+            }
+            Control::Continue
+        }).unwrap();
+
+        // The clock starts at 0:01, so "ff?" waits 2s; "late" is overdue
+        let sent: Vec<(&str, u64)> = sender.sent.iter().map(|(t, ms)| (t.as_str(), *ms)).collect();
+        assert_eq!(sent, vec![("gl hf", 0), ("ff?", 2000), ("late", 2000)]);
+        assert_eq!(countdown.len(), 8);
+        assert_eq!((countdown[0], countdown[7]), (2000, 250));
+    }
+
+    #[test]
+    fn test_cancel_request_from_outside() {
+        let lines = lines(&["a", "b", "c"]);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender::default();
+        let mut answers = Vec::new();
+        let report = session.run(&mut sender, |event| {
+            if let SendEvent::LineFinished { index: 0, .. } = event {
+                answers.push(cancel_running_send("remote control"));
+                answers.push(cancel_running_send("hotkey"));
+            }
+            Control::Continue
+        }).unwrap();
+
+        assert_eq!(answers, vec![true, false]);
+        assert_eq!(pairs(&sender.sent), vec![("a", "one")]);
+        assert!(report.cancelled);
+        assert_eq!(report.cancelled_by.as_deref(), Some("remote control"));
+        assert_eq!(report.targets[0].skipped, 2);
+    }
+
+    #[test]
+    fn test_cancel_phases() {
+        let lines = lines(&["a", "b"]);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut phases = Vec::new();
+        session.run(&mut MockSender::default(), |event| {
+            match event {
+                SendEvent::LineStarted { index: 0, .. } => phases.push(cancel_phase()),
+                SendEvent::LineFinished { index: 0, .. } => {
+                    cancel_running_send("Esc");
+                    phases.push(cancel_phase());
+                }
+                SendEvent::Finished { .. } => phases.push(cancel_phase()),
+                _ => {}
+            }
+            Control::Continue
+        }).unwrap();
+        assert_eq!(phases, vec![CancelPhase::None, CancelPhase::Requested, CancelPhase::Completed]);
+
+        // The send acknowledges the request at its next check
+        let _lock = acquire_send_lock(BusyPolicy::Queue).unwrap();
+        clear_cancel_request();
+        assert!(cancel_running_send("Esc"));
+        let mut report = SendReport::default();
+        assert!(check_cancel_request(&mut report));
+        assert_eq!(cancel_phase(), CancelPhase::Acknowledged);
+        complete_cancel_request();
+        assert_eq!(cancel_phase(), CancelPhase::Completed);
+        clear_cancel_request();
+    }
+
+    #[test]
+    fn test_cancel_while_waiting() {
+        let lines = vec![Line { at: Some(Duration::from_secs(60)), ..Line::new("ff?") }, Line::new("gg")];
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut sender = ClockSender { start: Instant::now(), slept_ms: 0, sent: Vec::new() };
+        let report = session.run(&mut sender, |event| match event {
+            SendEvent::Waiting { .. } => Control::Cancel,
+            _ => Control::Continue,
+        }).unwrap();
+
+        assert!(sender.sent.is_empty());
+        assert!(report.cancelled);
+        assert_eq!(report.targets[0].skipped, 2);
+    }
+
+    /// Sender whose window closes after `gone_after` lines and comes back
+    /// `gone_for_ms` of sleeping later. Its clock only moves when it sleeps.
+    struct FlakySender {
+        start: Instant,
+        slept_ms: u64,
+        sent: Vec<String>,
+        gone_after: usize,
+        gone_for_ms: u64,
+        gone_since: Option<u64>,
+    }
+
+    impl FlakySender {
+        fn new(gone_after: usize, gone_for_ms: u64) -> Self {
+            FlakySender { start: Instant::now(), slept_ms: 0, sent: Vec::new(), gone_after, gone_for_ms, gone_since: None }
+        }
+    }
+
+    impl LineSender for FlakySender {
+        fn send_line(&mut self, text: &str, window_title: &str, _options: &TypingOptions) -> Result<TypeStats, String> {
+            if !self.window_exists(window_title) {
+                return Err("window not found".to_string());
+            }
+            self.sent.push(text.to_string());
+            Ok(TypeStats { chars_typed: text.len(), ..TypeStats::default() })
+        }
+
+        fn sleep(&mut self, ms: u64) {
+            self.slept_ms += ms;
+        }
+
+        fn now(&mut self) -> Instant {
+            self.start + Duration::from_millis(self.slept_ms)
+        }
+
+        fn window_exists(&mut self, _window_title: &str) -> bool {
+            if self.sent.len() < self.gone_after {
+                return true;
+            }
+            let since = *self.gone_since.get_or_insert(self.slept_ms);
+            self.slept_ms - since >= self.gone_for_ms
+        }
+    }
+
+    fn flaky_session(lines: &[Line]) -> SendSession<'_> {
+        SendSession::new(lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_delays(SendDelays { next_line_ms: 0, ..SendDelays::default() })
+            .with_window_lost_wait(Duration::from_secs(20))
+    }
+
+    #[test]
+    fn test_send_waits_for_a_lost_window() {
+        let lines = lines(&["a", "b", "c"]);
+        let mut sender = FlakySender::new(1, 3000);
+        let mut countdown = Vec::new();
+        let report = flaky_session(&lines).run(&mut sender, |event| {
+            if let SendEvent::WindowLost { index, left, .. } = event {
+                countdown.push((*index, left.as_secs()));
+            }
+            Control::Continue
+        }).unwrap();
+
+        assert_eq!(sender.sent, vec!["a", "b", "c"]);
+        assert_eq!(report.sent(), 3);
+        assert_eq!(countdown.len(), 6);
+        assert_eq!((countdown[0], countdown[5]), ((1, 20), (1, 17)));
+    }
+
+    #[test]
+    fn test_lost_window_times_out_or_is_cancelled() {
+        let lines = lines(&["a", "b", "c"]);
+        let mut sender = FlakySender::new(1, u64::MAX);
+        let report = flaky_session(&lines).run(&mut sender, |_| Control::Continue).unwrap();
+        let one = &report.targets[0];
+        assert_eq!((one.sent, one.skipped, one.window_lost), (1, 1, true));
+        assert_eq!(sender.slept_ms, 20_000);
+        assert!(one.failed.as_ref().unwrap().2.starts_with("window lost"));
+
+        let mut sender = FlakySender::new(1, u64::MAX);
+        let report = flaky_session(&lines).run(&mut sender, |event| match event {
+            SendEvent::WindowLost { .. } => Control::Cancel,
+            _ => Control::Continue,
+        }).unwrap();
+        assert!(report.cancelled);
+        assert_eq!((report.targets[0].sent, report.targets[0].skipped), (1, 2));
+        assert!(report.targets[0].failed.is_none());
+    }
+
+    #[test]
+    fn test_window_never_seen_fails_without_waiting() {
+        let lines = lines(&["a", "b"]);
+        let mut sender = FlakySender::new(0, u64::MAX);
+        let mut lost = 0;
+        let report = flaky_session(&lines).run(&mut sender, |event| {
+            if let SendEvent::WindowLost { .. } = event {
+                lost += 1;
+            }
+            Control::Continue
+        }).unwrap();
+
+        assert_eq!(lost, 0);
+        assert_eq!(sender.slept_ms, 0);
+        let one = &report.targets[0];
+        assert_eq!((one.sent, one.window_lost), (0, false));
+        assert_eq!(one.failed.as_ref().unwrap().2, "window not found");
+    }
+
+    #[test]
+    fn test_new_delays_apply_from_next_pause() {
+        let lines = lines(&["a", "b", "c"]);
+        let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+        let mut sender = MockSender::default();
+        let reloaded = SendDelays { next_line_ms: 42, ..SendDelays::default() };
+        session.run(&mut sender, |event| match *event {
+            SendEvent::LineFinished { index: 1, .. } => Control::SetDelays(reloaded),
+            _ => Control::Continue,
+        }).unwrap();
+
+        assert_eq!(sender.sleeps, vec![NEXT_LINE_DELAY_MS, 42]);
+    }
+
+    /// Sender that sleeps for real while "typing", to make sends overlap.
+    struct SlowSender {
+        id: usize,
+        log: std::sync::Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl LineSender for SlowSender {
+        fn send_line(&mut self, text: &str, _window_title: &str, _options: &TypingOptions) -> Result<TypeStats, String> {
+            self.log.lock().unwrap().push(self.id);
+            thread::sleep(Duration::from_millis(20));
+            Ok(TypeStats { chars_typed: text.len(), ..TypeStats::default() })
+        }
+
+        fn sleep(&mut self, _ms: u64) {}
+    }
+
+    #[test]
+    fn test_second_send_rejected_while_busy() {
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let log = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let first_log = log.clone();
+        let first = thread::spawn(move || {
+            let lines = lines(&["a", "b", "c"]);
+            let session = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine);
+            session.run(&mut SlowSender { id: 1, log: first_log }, |event| {
+                if let SendEvent::LineStarted { index: 0, .. } = event {
+                    started_tx.send(()).unwrap();
+                }
+                Control::Continue
+            })
+        });
+
+        started_rx.recv().unwrap();
+        let lines = lines(&["x"]);
+        let second = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_busy_policy(BusyPolicy::Reject)
+            .run(&mut SlowSender { id: 2, log: log.clone() }, |_| Control::Continue);
+
+        assert_eq!(second.unwrap_err(), "Send already in progress");
+        assert_eq!(first.join().unwrap().unwrap().sent(), 3);
+        assert_eq!(*log.lock().unwrap(), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_queued_sends_do_not_interleave() {
+        let log = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let handles: Vec<_> = (1..=2)
+            .map(|id| {
+                let log = log.clone();
+                thread::spawn(move || {
+                    let lines = lines(&["a", "b", "c"]);
+                    SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+                        .with_busy_policy(BusyPolicy::Queue)
+                        .run(&mut SlowSender { id, log }, |_| Control::Continue)
+                        .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().sent(), 3);
+        }
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 6);
+        assert!(log[..3].iter().all(|&id| id == log[0]) && log[3..].iter().all(|&id| id == log[3]));
+    }
+
+    #[test]
+    fn test_line_cap_counts_split_parts_and_queue() {
+        let long = "word ".repeat(50);
+        let mut lines = lines(&["gl hf", long.trim()]);
+        assert_eq!(message_count(&lines, false, Some(GAME_MESSAGE_MAX_CHARS)), 2);
+        assert_eq!(message_count(&lines, true, Some(GAME_MESSAGE_MAX_CHARS)), 3);
+
+        assert_eq!(line_cap_problem(3, 0, 3), None);
+        assert_eq!(
+            line_cap_problem(3, 0, 2).unwrap(),
+            "This send is 3 messages, over the limit of 2 per send \
+             (raise \"Max lines per send\" in F2 or max_lines_per_send in madtyping.toml)"
+        );
+        assert!(line_cap_problem(3, 98, 100).unwrap().starts_with("This send is 3 messages with 98 more queued"));
+
+        // Splitting pushes the send over the cap before anything is typed
+        lines.push(Line::new("wp"));
+        let mut sender = MockSender::default();
+        let result = SendSession::new(&lines, vec!["one".into()], BroadcastMode::PerLine)
+            .with_split_long_lines(true)
+            .with_max_lines_per_send(3)
+            .run(&mut sender, |_| Control::Continue);
+        assert!(result.unwrap_err().starts_with("This send is 4 messages"));
+        assert!(sender.sent.is_empty());
+    }
+
+    #[test]
+    fn test_mute_risk() {
+        let limit = ChatLimit { messages: 8, window: Duration::from_secs(10) };
+        let fast = Duration::from_millis(700);
+
+        // At or under the message limit is always fine
+        assert_eq!(mute_risk(8, fast, limit), None);
+        // 9 lines 700ms apart: the first 9 land within 5.6s
+        assert_eq!(
+            mute_risk(42, fast, limit).map(|r| r.label()),
+            Some("⚠ likely mute: 42 msgs in <30s at current speed".to_string())
+        );
+        // Slow enough that 8 intervals take the whole window
+        assert_eq!(mute_risk(42, Duration::from_millis(1250), limit), None);
+        // 0 turns the limit off
+        assert_eq!(mute_risk(42, fast, ChatLimit { messages: 0, ..limit }), None);
+    }
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("gl hf", 200), vec!["gl hf"]);
+        assert_eq!(split_message("ward the dragon pit", 10), vec!["ward the", "dragon pit"]);
+        assert_eq!(split_message("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(split_message("abcdefghij", 0), vec!["abcdefghij"]);
+    }
+
+    #[test]
+    fn test_long_lines_refused_unless_split() {
+        let long = format!("{} end", "word ".repeat(45));
+        let lines = lines(&["ok", &long]);
+        let mut sender = MockSender::default();
+        let err = SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap_err();
+        assert!(err.starts_with("Line 2 is 229 characters"), "{}", err);
+        assert!(sender.sent.is_empty());
+
+        let report = SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+            .with_split_long_lines(true)
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap();
+        assert_eq!(report.sent(), 2);
+        assert_eq!(sender.sent.len(), 3);
+        assert!(sender.sent.iter().all(|(text, _)| text.chars().count() <= GAME_MESSAGE_MAX_CHARS));
+
+        // Another game's limit splits (or refuses) at its own length
+        let err = SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+            .with_char_limit(Some(100))
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap_err();
+        assert!(err.contains("over the game's 100-character limit"), "{}", err);
+        assert_eq!(message_count(&lines, true, Some(100)), 4);
+
+        // Without a limit every line goes out whole
+        let mut sender = MockSender::default();
+        SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+            .with_char_limit(None)
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap();
+        assert_eq!(sender.sent.len(), 2);
+        assert_eq!(sender.sent[1].0, long);
+        assert_eq!(message_count(&lines, true, None), 2);
+        assert_eq!(message_count(&lines, true, Some(0)), 2);
+
+        // Nor with a limit of 0
+        let mut sender = MockSender::default();
+        SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+            .with_char_limit(Some(0))
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap();
+        assert_eq!(sender.sent[1].0, long);
+        assert_eq!((long_lines(&lines, 100), long_lines(&lines, 229)), (1, 0));
+    }
+
+    #[test]
+    fn test_send_cues() {
+        let lines = lines(&["a", "b"]);
+        let cues = |sound| {
+            let mut sender = MockSender { fail_on: vec![("b".into(), "game".into())], ..MockSender::default() };
+            let mut cues = Vec::new();
+            SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+                .run(&mut sender, |event| {
+                    cues.extend(send_cue(event, sound));
+                    Control::Continue
+                })
+                .unwrap();
+            cues
+        };
+
+        assert_eq!(cues(SendSound::Off), vec![]);
+        assert_eq!(cues(SendSound::End), vec![Cue::Failed, Cue::Finished]);
+        assert_eq!(cues(SendSound::Line), vec![Cue::LineSent, Cue::Failed, Cue::Finished]);
+    }
+
+    #[test]
+    fn test_finish_notice() {
+        let lines = lines(&["a", "b", "c"]);
+        let mut sender = MockSender { fail_on: vec![("b".into(), "game".into())], ..MockSender::default() };
+        let report = SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine)
+            .run(&mut sender, |_| Control::Continue)
+            .unwrap();
+        assert_eq!(
+            finish_notice("gg.txt", &report, lines.len()),
+            ("gg.txt failed".to_string(), "1 of 3 line(s) sent, 1 window(s) failed, 1 skipped".to_string())
+        );
+
+        let report = SendReport { cancelled: true, ..report };
+        assert_eq!(finish_notice("gg.txt", &report, lines.len()).0, "gg.txt cancelled");
+    }
+}
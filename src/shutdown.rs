@@ -0,0 +1,173 @@
+//! Shutdown coordinator
+//!
+//! Closing the console window while the UI runs must not leave threads
+//! behind or a send half done. Every background part (the file worker, the
+//! send key watcher, a running send) registers a stop action with
+//! [`register`] and keeps the returned [`Registration`] while it lives.
+//! [`shutdown`], called when the console is closed or the terminal is
+//! lost, takes the same way out as Esc: it stops the registered parts
+//! newest first, so a send is cancelled before the threads it relies on go
+//! away. Unlike Esc, the send's journal is left unfinished, so the next
+//! start offers to resume it. The whole shutdown takes at most
+//! [`SHUTDOWN_TIMEOUT_MS`]; a part that doesn't stop in its share of that
+//! is left behind.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config::SHUTDOWN_TIMEOUT_MS;
+use crate::logging::log;
+
+/// Name of the cancel request a shutdown makes.
+pub const SHUTDOWN_REQUESTER: &str = "shutdown";
+
+type StopAction = Box<dyn FnOnce() + Send>;
+
+/// Parts to stop on shutdown, in registration order.
+pub struct Coordinator {
+    next_id: usize,
+    parts: Vec<(usize, String, StopAction)>,
+}
+
+impl Coordinator {
+    pub const fn new() -> Self {
+        Self { next_id: 0, parts: Vec::new() }
+    }
+
+    /// Add a part called `name`, stopped by `stop`. Returns its id.
+    pub fn register(&mut self, name: &str, stop: impl FnOnce() + Send + 'static) -> usize {
+        self.next_id += 1;
+        self.parts.push((self.next_id, name.to_string(), Box::new(stop)));
+        self.next_id
+    }
+
+    /// Forget part `id`; it stopped on its own.
+    pub fn unregister(&mut self, id: usize) {
+        self.parts.retain(|(part, _, _)| *part != id);
+    }
+
+    /// Names of the registered parts, in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.parts.iter().map(|(_, name, _)| name.as_str()).collect()
+    }
+
+    /// Stop every part, newest first, within `timeout` in all. Returns
+    /// the names of the parts that hadn't stopped when their time ran out.
+    pub fn stop_all(&mut self, timeout: Duration) -> Vec<String> {
+        let deadline = Instant::now() + timeout;
+        let mut stuck = Vec::new();
+        while let Some((_, name, stop)) = self.parts.pop() {
+            // Each stop runs on its own thread so a hung part can be left
+            let (done, finished) = mpsc::channel();
+            thread::spawn(move || {
+                stop();
+                let _ = done.send(());
+            });
+            let left = deadline.saturating_duration_since(Instant::now());
+            if finished.recv_timeout(left).is_ok() {
+                log(&format!("Shutdown: stopped {}", name));
+            } else {
+                log(&format!("Shutdown: {} didn't stop in time", name));
+                stuck.push(name);
+            }
+        }
+        stuck
+    }
+}
+
+impl Default for Coordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static COORDINATOR: Mutex<Coordinator> = Mutex::new(Coordinator::new());
+
+/// Set once [`shutdown`] has been called.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// A part registered with [`register`]. Dropping it unregisters the part.
+#[derive(Debug)]
+pub struct Registration {
+    id: usize,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        COORDINATOR.lock().unwrap_or_else(|e| e.into_inner()).unregister(self.id);
+    }
+}
+
+/// Stop the background part `name` with `stop` on shutdown, for as long as
+/// the returned registration is kept.
+pub fn register(name: &str, stop: impl FnOnce() + Send + 'static) -> Registration {
+    let id = COORDINATOR.lock().unwrap_or_else(|e| e.into_inner()).register(name, stop);
+    Registration { id }
+}
+
+/// Stop every registered part because of `reason`, within
+/// [`SHUTDOWN_TIMEOUT_MS`]. Returns false if a shutdown already ran.
+pub fn shutdown(reason: &str) -> bool {
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    // Stopping a part drops its registration, which takes the lock again
+    let taken = std::mem::take(&mut COORDINATOR.lock().unwrap_or_else(|e| e.into_inner()).parts);
+    let mut parts = Coordinator { next_id: 0, parts: taken };
+    log(&format!("Shutting down ({}): {}", reason, parts.names().join(", ")));
+    let stuck = parts.stop_all(Duration::from_millis(SHUTDOWN_TIMEOUT_MS));
+    if !stuck.is_empty() {
+        log(&format!("Shutdown left {} behind", stuck.join(", ")));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parts_stop_newest_first() {
+        let stopped = Arc::new(Mutex::new(Vec::new()));
+        let mut coordinator = Coordinator::new();
+        let mut ids = Vec::new();
+        for name in ["worker", "watcher", "keys", "send"] {
+            let stopped = Arc::clone(&stopped);
+            ids.push(coordinator.register(name, move || stopped.lock().unwrap().push(name)));
+        }
+        // The key watcher ended on its own before the shutdown
+        coordinator.unregister(ids[2]);
+        assert_eq!(coordinator.names(), vec!["worker", "watcher", "send"]);
+
+        assert!(coordinator.stop_all(Duration::from_secs(5)).is_empty());
+        assert_eq!(*stopped.lock().unwrap(), vec!["send", "watcher", "worker"]);
+        assert!(coordinator.names().is_empty());
+    }
+
+    #[test]
+    fn test_hung_part_is_left_behind() {
+        let stopped = Arc::new(Mutex::new(Vec::new()));
+        let mut coordinator = Coordinator::new();
+        let first = Arc::clone(&stopped);
+        coordinator.register("worker", move || first.lock().unwrap().push("worker"));
+        let (_release, hang) = mpsc::channel::<()>();
+        coordinator.register("send", move || {
+            let _ = hang.recv();
+        });
+
+        let started = Instant::now();
+        // The hung send uses up the time; the worker still gets its stop
+        let stuck = coordinator.stop_all(Duration::from_millis(100));
+        assert_eq!(stuck.first().map(String::as_str), Some("send"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(*stopped.lock().unwrap(), vec!["worker"]);
+    }
+}
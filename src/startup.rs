@@ -0,0 +1,137 @@
+//! Startup timing
+//!
+//! The terminal UI draws its first frame, the "scanning…" skeleton of the
+//! list, before anything that waits on the disk: discovery and the file
+//! reads run on the [`FsWorker`], and the state file, watchers and send
+//! journal are only read once the frame is up. [`Startup`] logs how long
+//! the first frame and the first usable list took.
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use crate::app::App;
+use crate::files::ScanOptions;
+use crate::logging::log;
+use crate::worker::FsWorker;
+
+/// Time to the first frame and to a usable list, measured from `began`.
+#[derive(Clone, Debug)]
+pub struct Startup {
+    began: Instant,
+    first_frame: Option<Duration>,
+    interactive: Option<Duration>,
+}
+
+impl Startup {
+    /// Measure from `began`, e.g. when the process started.
+    pub fn new(began: Instant) -> Self {
+        Self { began, first_frame: None, interactive: None }
+    }
+
+    /// The first frame is on screen; logged the first time only.
+    pub fn frame_rendered(&mut self) {
+        if self.first_frame.is_none() {
+            let elapsed = self.began.elapsed();
+            log(&format!("Time to first render: {} ms", elapsed.as_millis()));
+            self.first_frame = Some(elapsed);
+        }
+    }
+
+    /// The first scan arrived, so files can be picked; logged the first
+    /// time only.
+    pub fn interactive(&mut self) {
+        if self.interactive.is_none() {
+            let elapsed = self.began.elapsed();
+            log(&format!("Time to interactive: {} ms", elapsed.as_millis()));
+            self.interactive = Some(elapsed);
+        }
+    }
+
+    /// Time to the first frame, once it's drawn.
+    pub fn first_frame(&self) -> Option<Duration> {
+        self.first_frame
+    }
+
+    /// Time to the first usable list, once it's there.
+    pub fn time_to_interactive(&self) -> Option<Duration> {
+        self.interactive
+    }
+}
+
+/// Start discovery on `worker` and draw the first frame of `app` with
+/// `render`, without waiting for the scan.
+pub fn first_frame(
+    app: &mut App,
+    worker: &mut FsWorker,
+    options: ScanOptions,
+    render: impl FnOnce(&App) -> io::Result<()>,
+) -> io::Result<()> {
+    worker.refresh(options);
+    app.set_scanning(true);
+    render(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::{Line, Scan, TextFile};
+    use crate::worker::FsResponse;
+    use std::{
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    #[test]
+    fn test_first_frame_drawn_before_slow_discovery() {
+        // A drive that takes its time to spin up
+        let scanned = Arc::new(AtomicBool::new(false));
+        let done = Arc::clone(&scanned);
+        let mut worker = FsWorker::with_handler(move |_| {
+            thread::sleep(Duration::from_millis(300));
+            done.store(true, Ordering::SeqCst);
+            let file = TextFile {
+                name: "gg.txt".into(),
+                path: PathBuf::from("gg.txt"),
+                lines: vec![Line::new("gl hf")],
+                warnings: Vec::new(),
+                modified: None,
+                tags: Vec::new(),
+                front: Default::default(),
+                provenance: Default::default(),
+            };
+            FsResponse::Discovered(Ok(Scan { files: vec![file], ..Scan::default() }))
+        });
+        let mut app = App::empty();
+        let mut startup = Startup::new(Instant::now());
+
+        let mut frame = None;
+        first_frame(&mut app, &mut worker, ScanOptions::default(), |app| {
+            frame = Some((scanned.load(Ordering::SeqCst), app.scanning_since().is_some(), app.total_count()));
+            Ok(())
+        })
+        .unwrap();
+        startup.frame_rendered();
+        assert_eq!(frame, Some((false, true, 0)));
+        assert!(startup.first_frame().unwrap() < Duration::from_millis(300));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let scan = loop {
+            if let Some(result) = worker.take_discovered() {
+                break result.unwrap();
+            }
+            assert!(Instant::now() < deadline, "discovery never finished");
+            thread::sleep(Duration::from_millis(10));
+        };
+        app.set_files(scan.files);
+        app.set_scanning(worker.is_scanning());
+        startup.interactive();
+        assert_eq!(app.total_count(), 1);
+        assert!(startup.time_to_interactive().unwrap() >= Duration::from_millis(300));
+    }
+}
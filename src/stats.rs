@@ -0,0 +1,207 @@
+//! Usage statistics for MadTyping
+//!
+//! [`Stats`] counts what was sent. It is fed from [`SendEvent`]s so the
+//! counters stay correct no matter which frontend drove the send.
+//! Lifetime totals can be persisted through the persistence module.
+
+use std::time::Duration;
+
+use crate::persistence::{self, State};
+use crate::send::SendEvent;
+
+/// Counters for sent files, lines and characters.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Sends that delivered at least one line
+    pub files_sent: u64,
+    /// Lines typed successfully
+    pub lines_sent: u64,
+    /// Characters typed successfully
+    pub chars_typed: u64,
+    /// Sends cancelled by the user
+    pub cancellations: u64,
+    /// Lines that failed to send
+    pub failures: u64,
+    /// Time spent focusing windows and typing
+    pub typing_time: Duration,
+    /// Part of `typing_time` spent finding and focusing windows
+    pub window_time: Duration,
+}
+
+impl Stats {
+    /// Update the counters from a send session event.
+    pub fn record(&mut self, event: &SendEvent) {
+        match event {
+            SendEvent::LineStarted { .. }
+            | SendEvent::Waiting { .. }
+            | SendEvent::WindowLost { .. }
+            | SendEvent::AwaitingAdvance { .. }
+            | SendEvent::MethodChosen { .. } => {}
+            SendEvent::LineFinished { result, elapsed, .. } => {
+                self.typing_time += *elapsed;
+                match result {
+                    Ok(typed) => {
+                        self.lines_sent += 1;
+                        self.chars_typed += typed.chars_typed as u64;
+                        self.window_time += typed.window_time;
+                    }
+                    Err(_) => self.failures += 1,
+                }
+            }
+            SendEvent::Finished { report } => {
+                if report.sent() > 0 {
+                    self.files_sent += 1;
+                }
+                if report.cancelled {
+                    self.cancellations += 1;
+                }
+            }
+        }
+    }
+
+    /// One-line summary, e.g. "2 files, 14 lines, 380 chars in 0:21".
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} file(s), {} line(s), {} char(s) in {}",
+            self.files_sent,
+            self.lines_sent,
+            self.chars_typed,
+            format_duration(self.typing_time)
+        );
+        if self.cancellations > 0 {
+            summary.push_str(&format!(", {} cancelled", self.cancellations));
+        }
+        if self.failures > 0 {
+            summary.push_str(&format!(", {} failed", self.failures));
+        }
+        summary
+    }
+
+    /// Labelled values for the stats screen.
+    pub fn rows(&self) -> [(&'static str, String); 7] {
+        [
+            ("Files sent", self.files_sent.to_string()),
+            ("Lines sent", self.lines_sent.to_string()),
+            ("Characters", self.chars_typed.to_string()),
+            ("Cancelled", self.cancellations.to_string()),
+            ("Failures", self.failures.to_string()),
+            ("Typing time", format_duration(self.typing_time)),
+            ("Window time", format_duration(self.window_time)),
+        ]
+    }
+
+    /// Read lifetime stats from persisted state.
+    pub fn from_state(state: &State) -> Self {
+        Self {
+            files_sent: state.get_u64("stats.files_sent"),
+            lines_sent: state.get_u64("stats.lines_sent"),
+            chars_typed: state.get_u64("stats.chars_typed"),
+            cancellations: state.get_u64("stats.cancellations"),
+            failures: state.get_u64("stats.failures"),
+            typing_time: Duration::from_millis(state.get_u64("stats.typing_ms")),
+            window_time: Duration::from_millis(state.get_u64("stats.window_ms")),
+        }
+    }
+
+    /// Write these stats into persisted state.
+    pub fn write_state(&self, state: &mut State) {
+        state.set("stats.files_sent", self.files_sent);
+        state.set("stats.lines_sent", self.lines_sent);
+        state.set("stats.chars_typed", self.chars_typed);
+        state.set("stats.cancellations", self.cancellations);
+        state.set("stats.failures", self.failures);
+        state.set("stats.typing_ms", self.typing_time.as_millis());
+        state.set("stats.window_ms", self.window_time.as_millis());
+    }
+
+    /// Load lifetime stats from the state file.
+    pub fn load_lifetime() -> Self {
+        Self::from_state(&persistence::load())
+    }
+
+    /// Save these stats as the lifetime stats in the state file.
+    pub fn save_lifetime(&self) -> Result<(), String> {
+        let mut state = persistence::load();
+        self.write_state(&mut state);
+        persistence::save(&state)
+    }
+}
+
+/// Format a duration as `m:ss`, or `h:mm:ss` from one hour up.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::Line;
+    use crate::platform::{TypeStats, TypingOptions};
+    use crate::send::{BroadcastMode, Control, LineSender, SendSession};
+
+    struct FailOn(&'static str);
+
+    impl LineSender for FailOn {
+        fn send_line(&mut self, text: &str, _window_title: &str, _options: &TypingOptions) -> Result<TypeStats, String> {
+            if text == self.0 {
+                return Err("window closed".to_string());
+            }
+            Ok(TypeStats { chars_typed: text.chars().count(), window_time: Duration::from_millis(2), ..TypeStats::default() })
+        }
+
+        fn sleep(&mut self, _ms: u64) {}
+    }
+
+    #[test]
+    fn test_counts_from_session_events() {
+        let lines: Vec<Line> = ["hello", "bad", "skipped"].iter().map(|t| Line::new(*t)).collect();
+        let session = SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine);
+        let mut stats = Stats::default();
+        session.run(&mut FailOn("bad"), |event| {
+            stats.record(event);
+            Control::Continue
+        }).unwrap();
+
+        assert_eq!((stats.files_sent, stats.lines_sent, stats.chars_typed), (1, 1, 5));
+        assert_eq!((stats.failures, stats.cancellations), (1, 0));
+        assert_eq!(stats.window_time, Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_cancel_counted() {
+        let lines = vec![Line::new("a"), Line::new("b")];
+        let session = SendSession::new(&lines, vec!["game".into()], BroadcastMode::PerLine);
+        let mut stats = Stats::default();
+        session.run(&mut FailOn(""), |event| {
+            stats.record(event);
+            match event {
+                SendEvent::LineStarted { index: 0, .. } => Control::Cancel,
+                _ => Control::Continue,
+            }
+        }).unwrap();
+
+        assert_eq!((stats.files_sent, stats.cancellations), (0, 1));
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        let stats = Stats {
+            files_sent: 3,
+            lines_sent: 20,
+            chars_typed: 512,
+            cancellations: 1,
+            failures: 2,
+            typing_time: Duration::from_millis(65_250),
+            window_time: Duration::from_millis(4_100),
+        };
+        let mut state = State::default();
+        stats.write_state(&mut state);
+        assert_eq!(Stats::from_state(&state), stats);
+        assert_eq!(stats.summary(), "3 file(s), 20 line(s), 512 char(s) in 1:05, 1 cancelled, 2 failed");
+    }
+}
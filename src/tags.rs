@@ -0,0 +1,101 @@
+//! File tags for MadTyping
+//!
+//! An optional `tags.toml` in the scan directory labels message files
+//! without renaming them:
+//!
+//! ```toml
+//! "greetings.txt" = ["aram", "ranked"]
+//! taunts.md = ["troll"]
+//! ```
+//!
+//! Tags are lowercased. Entries for files that weren't discovered are
+//! ignored with a logged warning.
+
+use std::{fs, path::Path};
+
+use crate::files::TextFile;
+use crate::logging::log;
+
+/// File name of the tags file, stored in the scan directory.
+pub const TAGS_FILE_NAME: &str = "tags.toml";
+
+/// Parse tags file contents into (file name, tags) pairs plus warnings.
+pub fn parse(content: &str) -> (Vec<(String, Vec<String>)>, Vec<String>) {
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (number, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line.split_once('=').and_then(|(name, list)| {
+            let name = name.trim();
+            let name = name.strip_prefix('"').and_then(|n| n.strip_suffix('"')).unwrap_or(name);
+            let list = list.trim().strip_prefix('[')?.strip_suffix(']')?;
+            let tags = list
+                .split(',')
+                .map(|t| t.trim().trim_matches('"').trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            Some((name.to_string(), tags))
+        });
+        match parsed {
+            Some(entry) => entries.push(entry),
+            None => warnings.push(format!("line {}: expected `\"file\" = [\"tag\", ...]`, ignored", number + 1)),
+        }
+    }
+
+    (entries, warnings)
+}
+
+/// Read the tags file in `dir` (if any) and attach tags to `files`.
+pub(crate) fn apply(files: &mut [TextFile], dir: &Path) {
+    let path = dir.join(TAGS_FILE_NAME);
+    let Ok(content) = fs::read_to_string(&path) else {
+        return;
+    };
+
+    let (entries, warnings) = parse(&content);
+    for warning in warnings {
+        log(&format!("Warning in {}: {}", TAGS_FILE_NAME, warning));
+    }
+    for (name, tags) in entries {
+        match files.iter_mut().find(|f| f.name.eq_ignore_ascii_case(&name)) {
+            Some(file) => {
+                for tag in tags {
+                    if !file.tags.contains(&tag) {
+                        file.tags.push(tag);
+                    }
+                }
+            }
+            None => log(&format!("Warning in {}: unknown file '{}' ignored", TAGS_FILE_NAME, name)),
+        }
+    }
+}
+
+/// All distinct tags used by `files`, sorted.
+pub fn all_tags<'a>(files: impl IntoIterator<Item = &'a TextFile>) -> Vec<String> {
+    let mut tags: Vec<String> = files.into_iter().flat_map(|f| f.tags.iter().cloned()).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags_file() {
+        let content = "# tags\n\"Greetings.txt\" = [\"ARAM\", \"ranked\"]\ntaunts.md = [troll]\nbroken line\nempty.txt = []\n";
+        let (entries, warnings) = parse(content);
+        assert_eq!(entries, vec![
+            ("Greetings.txt".to_string(), vec!["aram".to_string(), "ranked".to_string()]),
+            ("taunts.md".to_string(), vec!["troll".to_string()]),
+            ("empty.txt".to_string(), vec![]),
+        ]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("line 4"));
+    }
+}
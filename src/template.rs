@@ -0,0 +1,324 @@
+//! Time-of-day templates for MadTyping
+//!
+//! Lines can change with the local time when they are sent:
+//!
+//! ```text
+//! {?morning:good morning}{?evening:good evening} summoners
+//! happy {dow}, gl hf
+//! ```
+//!
+//! `{?period:text}` sends `text` only during that part of the day
+//! (`morning`, `afternoon`, `evening` or `night`, starting at the
+//! `*_START_HOUR` constants) and nothing otherwise; branches may hold
+//! further placeholders. `{dow}` is the day of the week. `{{` and `}}` are
+//! literal braces; in a branch a `}` always closes it, so only `{{` works
+//! there. Spaces left at either end by an empty branch are trimmed, and a
+//! line that renders to nothing is skipped rather than sent as a blank
+//! message.
+//!
+//! Files keep the raw text. A line that doesn't parse is sent as written,
+//! and the problem, with its column, is a validation warning on load.
+
+use std::{borrow::Cow, fmt};
+
+use crate::config::{AFTERNOON_START_HOUR, EVENING_START_HOUR, MORNING_START_HOUR, NIGHT_START_HOUR};
+use crate::files::Line;
+use crate::platform::LocalTime;
+
+/// Day names for `{dow}`, from Sunday.
+pub const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+/// Part of the day a `{?period:…}` branch applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+
+impl Period {
+    pub const ALL: [Period; 4] = [Period::Morning, Period::Afternoon, Period::Evening, Period::Night];
+    pub const NAMES: [&'static str; 4] = ["morning", "afternoon", "evening", "night"];
+
+    /// The part of the day `hour` (0 to 23) falls in.
+    pub fn from_hour(hour: u8) -> Self {
+        if !(MORNING_START_HOUR..NIGHT_START_HOUR).contains(&hour) {
+            Period::Night
+        } else if hour >= EVENING_START_HOUR {
+            Period::Evening
+        } else if hour >= AFTERNOON_START_HOUR {
+            Period::Afternoon
+        } else {
+            Period::Morning
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|&n| n == name).map(|i| Self::ALL[i])
+    }
+}
+
+/// Piece of a parsed line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Weekday,
+    When(Period, Vec<Part>),
+}
+
+/// A line that failed to parse, with the column (in characters, from 1)
+/// the problem starts at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+/// A parsed line, ready to render for any time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, TemplateError> {
+        let mut parser = Parser { chars: text.chars().collect(), pos: 0 };
+        Ok(Self { parts: parser.parts(None)? })
+    }
+
+    /// Whether the line has a `{?period:…}` branch.
+    pub fn has_conditions(&self) -> bool {
+        self.parts.iter().any(|p| matches!(p, Part::When(..)))
+    }
+
+    /// The text to send at `time`.
+    pub fn render(&self, time: LocalTime) -> String {
+        let mut text = String::new();
+        render_parts(&self.parts, time, &mut text);
+        if self.has_conditions() {
+            text.trim().to_string()
+        } else {
+            text
+        }
+    }
+}
+
+fn render_parts(parts: &[Part], time: LocalTime, out: &mut String) {
+    for part in parts {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Weekday => out.push_str(WEEKDAYS[usize::from(time.weekday % 7)]),
+            Part::When(period, branch) => {
+                if Period::from_hour(time.hour) == *period {
+                    render_parts(branch, time, out);
+                }
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn error(at: usize, message: impl Into<String>) -> TemplateError {
+        TemplateError { column: at + 1, message: message.into() }
+    }
+
+    /// Parts up to the end of the text, or up to the `}` closing the
+    /// branch opened at `branch`.
+    fn parts(&mut self, branch: Option<usize>) -> Result<Vec<Part>, TemplateError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        loop {
+            match (self.peek_at(0), self.peek_at(1)) {
+                (None, _) => match branch {
+                    Some(open) => return Err(Self::error(open, "'{' is never closed")),
+                    None => break,
+                },
+                (Some('{'), Some('{')) => {
+                    text.push('{');
+                    self.pos += 2;
+                }
+                (Some('}'), Some('}')) if branch.is_none() => {
+                    text.push('}');
+                    self.pos += 2;
+                }
+                (Some('{'), _) => {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(self.placeholder()?);
+                }
+                (Some('}'), _) => {
+                    if branch.is_some() {
+                        self.pos += 1;
+                        break;
+                    }
+                    return Err(Self::error(self.pos, "unmatched '}' (write }} for a literal brace)"));
+                }
+                (Some(c), _) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(parts)
+    }
+
+    /// The placeholder starting at the `{` under the cursor.
+    fn placeholder(&mut self) -> Result<Part, TemplateError> {
+        let open = self.pos;
+        self.pos += 1;
+        let conditional = self.peek_at(0) == Some('?');
+        if conditional {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        let stops: &[char] = if conditional { &[':', '}', '{'] } else { &['}', '{'] };
+        while self.peek_at(0).is_some_and(|c| !stops.contains(&c)) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect::<String>().trim().to_string();
+        let end = self.peek_at(0);
+        if end.is_none() || end == Some('{') {
+            return Err(Self::error(open, "'{' is never closed (write {{ for a literal brace)"));
+        }
+        self.pos += 1;
+        if !conditional {
+            return if name == "dow" {
+                Ok(Part::Weekday)
+            } else {
+                Err(Self::error(open, format!("unknown variable {{{}}} (write {{{{ for a literal brace)", name)))
+            };
+        }
+        if end == Some('}') {
+            return Err(Self::error(open, format!("missing ':' after {{?{}", name)));
+        }
+        let period = Period::from_name(&name).ok_or_else(|| {
+            Self::error(open, format!("unknown time of day '{}' (use {})", name, Period::NAMES.join(", ")))
+        })?;
+        Ok(Part::When(period, self.parts(Some(open))?))
+    }
+}
+
+/// Whether `text` has anything for [`Template::parse`] to do.
+fn has_braces(text: &str) -> bool {
+    text.contains(['{', '}'])
+}
+
+/// `lines` rendered for `time`, borrowed if none has a placeholder. Lines
+/// that don't parse are kept as written.
+pub fn render_lines(lines: &[Line], time: LocalTime) -> Cow<'_, [Line]> {
+    if !lines.iter().any(|l| has_braces(&l.text)) {
+        return Cow::Borrowed(lines);
+    }
+    Cow::Owned(
+        lines
+            .iter()
+            .map(|line| match Template::parse(&line.text) {
+                Ok(template) if has_braces(&line.text) => Line { text: template.render(time), ..line.clone() },
+                _ => line.clone(),
+            })
+            .collect(),
+    )
+}
+
+/// Validation warnings for the lines of `lines` that don't parse; they are
+/// sent as written.
+pub fn warnings(lines: &[Line]) -> Vec<String> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| has_braces(&l.text))
+        .filter_map(|(index, line)| {
+            Template::parse(&line.text)
+                .err()
+                .map(|e| format!("line {}, {}, sent as written", index + 1, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u8) -> LocalTime {
+        LocalTime { hour, minute: 0, weekday: 5 }
+    }
+
+    fn render(text: &str, hour: u8) -> String {
+        Template::parse(text).unwrap().render(at(hour))
+    }
+
+    #[test]
+    fn test_branches_follow_the_hour() {
+        let line = "{?morning:good morning}{?afternoon:hi}{?evening:good evening}{?night:gn} summoners";
+        for (hour, expected) in [
+            (0, "gn summoners"),
+            (4, "gn summoners"),
+            (5, "good morning summoners"),
+            (11, "good morning summoners"),
+            (12, "hi summoners"),
+            (16, "hi summoners"),
+            (17, "good evening summoners"),
+            (21, "good evening summoners"),
+            (22, "gn summoners"),
+            (23, "gn summoners"),
+        ] {
+            assert_eq!(render(line, hour), expected, "at {}:00", hour);
+        }
+        // Spaces left by an empty branch are trimmed
+        assert_eq!(render("{?morning:good morning}{?evening:good evening} summoners", 13), "summoners");
+        assert_eq!(render("{?night:late {?night:very late}}", 23), "late very late");
+    }
+
+    #[test]
+    fn test_weekday_and_escapes() {
+        assert_eq!(render("happy {dow}!", 12), "happy Friday!");
+        assert_eq!(render("{{dow}} is {{}}", 12), "{dow} is {}");
+        assert_eq!(render("{?evening:{{gg}", 18), "{gg");
+        assert_eq!(render("{?evening:happy {dow}}", 18), "happy Friday");
+        assert_eq!(Template::parse("gg {dow}").unwrap().render(LocalTime { weekday: 0, ..at(9) }), "gg Sunday");
+    }
+
+    #[test]
+    fn test_errors_name_the_column() {
+        let error = |text: &str| Template::parse(text).unwrap_err().to_string();
+        assert_eq!(error("hi {name}"), "column 4: unknown variable {name} (write {{ for a literal brace)");
+        assert_eq!(error("{?brunch:food}"), "column 1: unknown time of day 'brunch' (use morning, afternoon, evening, night)");
+        assert_eq!(error("ok {?morning}"), "column 4: missing ':' after {?morning");
+        assert_eq!(error("{?morning:gm"), "column 1: '{' is never closed");
+        assert_eq!(error("a {dow"), "column 3: '{' is never closed (write {{ for a literal brace)");
+        assert_eq!(error(":} bye"), "column 2: unmatched '}' (write }} for a literal brace)");
+    }
+
+    #[test]
+    fn test_lines_and_warnings() {
+        let lines = vec![Line::new("plain"), Line::new("{?morning:gm}"), Line::new("bad {x}")];
+        assert!(matches!(render_lines(&lines[..1], at(9)), Cow::Borrowed(_)));
+        let rendered = render_lines(&lines, at(20));
+        let texts: Vec<&str> = rendered.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["plain", "", "bad {x}"]);
+        assert_eq!(
+            warnings(&lines),
+            vec!["line 3, column 5: unknown variable {x} (write {{ for a literal brace), sent as written"]
+        );
+    }
+}
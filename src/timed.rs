@@ -0,0 +1,140 @@
+//! Timed lines for MadTyping
+//!
+//! A line starting with `!at MM:SS` is held back until the send clock
+//! reaches that time:
+//!
+//! ```text
+//! gl hf
+//! !at 15:00 ff?
+//! ```
+//!
+//! The clock starts at 00:00 when the send starts, or at the game time the
+//! user enters when `ask_game_time` is on. Lines whose time has already
+//! passed are sent right away.
+
+use std::time::{Duration, Instant};
+
+use crate::files::Line;
+
+/// Prefix of a timed line (matched case-insensitively).
+const AT_DIRECTIVE: &str = "!at ";
+
+/// A game time reading and when it was taken, so the clock keeps running
+/// while the send is prepared.
+#[derive(Clone, Copy, Debug)]
+pub struct GameClock {
+    /// Game time entered by the user
+    pub time: Duration,
+    /// When it was entered
+    pub read_at: Instant,
+}
+
+impl GameClock {
+    /// Start a clock reading `time` now.
+    pub fn new(time: Duration) -> Self {
+        Self { time, read_at: Instant::now() }
+    }
+
+    /// Current game time.
+    pub fn now(&self) -> Duration {
+        self.time + self.read_at.elapsed()
+    }
+}
+
+/// Parse `MM:SS` (minutes may go past 59) into a duration; None if it
+/// doesn't fit in seconds.
+pub fn parse_clock(text: &str) -> Option<Duration> {
+    let (minutes, seconds) = text.trim().split_once(':')?;
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: u64 = seconds.parse().ok().filter(|s| *s < 60 && seconds.len() == 2)?;
+    Some(Duration::from_secs(minutes.checked_mul(60)?.checked_add(seconds)?))
+}
+
+/// Format a duration as `MM:SS`.
+pub fn format_clock(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Move `!at MM:SS` prefixes into [`Line::at`], dropping lines that have
+/// no message after the time.
+///
+/// Returns warnings for bad times (the line is sent as is), empty timed
+/// lines and times earlier than a previous line's. Warnings number lines
+/// from 1 by their place among the messages kept, as the viewer does.
+pub fn apply_directives(lines: &mut Vec<Line>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut latest: Option<(usize, Duration)> = None;
+    let mut number = 0;
+    lines.retain_mut(|line| {
+        number += 1;
+        let Some(rest) = line
+            .text
+            .get(..AT_DIRECTIVE.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(AT_DIRECTIVE))
+            .map(|_| line.text[AT_DIRECTIVE.len()..].trim_start())
+        else {
+            return true;
+        };
+        let (time, message) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let Some(at) = parse_clock(time) else {
+            warnings.push(format!("line {}: '{}' is not a MM:SS time, sent as is", number, time));
+            return true;
+        };
+        let message = message.trim().to_string();
+        if message.is_empty() {
+            warnings.push(format!("line {}: !at {} has no message, ignored", number, time));
+            number -= 1;
+            return false;
+        }
+        match latest {
+            Some((earlier, latest_at)) if at < latest_at => warnings.push(format!(
+                "line {}: !at {} comes after !at {} on line {}, so it is sent right after it",
+                number,
+                format_clock(at),
+                format_clock(latest_at),
+                earlier
+            )),
+            _ => latest = Some((number, at)),
+        }
+        line.text = message;
+        line.at = Some(at);
+        true
+    });
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clock() {
+        assert_eq!(parse_clock("15:00"), Some(Duration::from_secs(900)));
+        assert_eq!(parse_clock("75:05"), Some(Duration::from_secs(4505)));
+        let overflowing = format!("{}:00", u64::MAX / 60 + 1);
+        for bad in ["15", "15:60", "1:5", "a:00", ":30", "307445734561825860:59", &overflowing] {
+            assert_eq!(parse_clock(bad), None, "{}", bad);
+        }
+        assert_eq!(format_clock(Duration::from_secs(905)), "15:05");
+    }
+
+    #[test]
+    fn test_directives_moved_into_lines() {
+        let mut lines: Vec<Line> = ["gl hf", "!at 20:00", "!at 15:00 ff?", "!AT 10:00 early", "!at 1:5 oops"]
+            .into_iter()
+            .map(Line::new)
+            .collect();
+        let warnings = apply_directives(&mut lines);
+
+        let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["gl hf", "ff?", "early", "!at 1:5 oops"]);
+        assert_eq!(lines[1].at, Some(Duration::from_secs(900)));
+        assert_eq!(lines[3].at, None);
+        assert_eq!(warnings, vec![
+            "line 2: !at 20:00 has no message, ignored",
+            "line 3: !at 10:00 comes after !at 15:00 on line 2, so it is sent right after it",
+            "line 4: '1:5' is not a MM:SS time, sent as is",
+        ]);
+    }
+}
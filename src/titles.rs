@@ -0,0 +1,106 @@
+//! Window title suggestions for MadTyping
+//!
+//! Localized clients use different window titles than the default, so when
+//! no target window is found the titles of open windows are ranked by
+//! similarity and the closest ones are offered as "did you mean ...?". A
+//! title that only matches the League client gets a warning instead.
+
+use crate::config::{TITLE_SUGGESTIONS, TITLE_SUGGESTION_MIN_SCORE};
+use crate::logging::log;
+use crate::platform::{target_kind, window_titles, WindowKind, CLIENT_ONLY_WARNING};
+
+/// Similarity of two titles, from 0.0 (nothing shared) to 1.0 (equal
+/// ignoring case): the Dice coefficient of their character pairs.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let pairs = |s: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = s.to_lowercase().chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let (a, mut b) = (pairs(a), pairs(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let total = a.len() + b.len();
+    let mut shared = 0;
+    for pair in &a {
+        if let Some(pos) = b.iter().position(|p| p == pair) {
+            b.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / total as f64
+}
+
+/// Up to `count` candidates most similar to `target`, best first.
+/// Candidates scoring below `min_score` are left out.
+pub fn closest<'a>(target: &str, candidates: &'a [String], count: usize, min_score: f64) -> Vec<&'a str> {
+    let mut scored: Vec<(f64, &str)> = candidates
+        .iter()
+        .map(|c| (similarity(target, c), c.as_str()))
+        .filter(|&(score, _)| score >= min_score)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored.into_iter().take(count).map(|(_, title)| title).collect()
+}
+
+/// Error message for target windows that aren't running, suggesting the
+/// open window titles closest to the first target.
+pub fn not_running_message(targets: &[String], open_titles: &[String]) -> String {
+    let message = format!("'{}' is not running!", targets.join("', '"));
+    let Some(first) = targets.first() else {
+        return message;
+    };
+    let suggestions = closest(first, open_titles, TITLE_SUGGESTIONS, TITLE_SUGGESTION_MIN_SCORE);
+    match suggestions.split_last() {
+        None => message,
+        Some((last, [])) => format!("'{}' not found; did you mean '{}'?", targets.join("', '"), last),
+        Some((last, rest)) => format!(
+            "'{}' not found; did you mean '{}' or '{}'?",
+            targets.join("', '"),
+            rest.join("', '"),
+            last
+        ),
+    }
+}
+
+/// [`not_running_message`] for the currently open windows, or the client
+/// warning if a target only matches the League client; also logged.
+pub fn not_running_error(targets: &[String]) -> String {
+    let message = match targets.iter().find(|t| target_kind(t) == Some(WindowKind::Client)) {
+        Some(target) => format!("'{}': {}", target, CLIENT_ONLY_WARNING),
+        None => not_running_message(targets, &window_titles()),
+    };
+    log(&format!("ERROR: {}", message));
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("League", "league"), 1.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert_eq!(similarity("a", "a"), 0.0);
+        let localized = similarity("League of Legends (TM) Client", "League of Legends (TM) クライアント");
+        let unrelated = similarity("League of Legends (TM) Client", "Discord");
+        assert!(localized > 0.6 && unrelated < 0.2, "{} {}", localized, unrelated);
+    }
+
+    #[test]
+    fn test_not_running_suggestions() {
+        let targets = vec!["League of Legends (TM) Client".to_string()];
+        let open: Vec<String> = ["Discord", "League of Legends (TM) クライアント", "League of Legends", "Notepad"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(
+            not_running_message(&targets, &open),
+            "'League of Legends (TM) Client' not found; did you mean 'League of Legends (TM) クライアント' or 'League of Legends'?"
+        );
+        assert_eq!(not_running_message(&targets, &open[..1]), "'League of Legends (TM) Client' is not running!");
+    }
+}
@@ -0,0 +1,242 @@
+//! Keystroke traces for MadTyping
+//!
+//! With `trace_input` on (or `--trace-input`), each send records every
+//! injected key event with its time and what the backend (SendInput)
+//! returned, plus the focus checks between lines. Entries stay in memory
+//! while typing, so writing them can't change the timing being traced, and
+//! go to `madtyping-trace-<time>.txt` in the data directory when the send
+//! ends, followed by a summary of the gaps between key injections.
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::Instant,
+};
+
+use crate::config::{TRACE_MAX_BYTES, TRACE_MAX_ENTRIES, TRACE_SLOW_GAP_MS};
+use crate::paths;
+use crate::platform::KeyInput;
+use crate::report::{file_stamp, now_secs};
+
+/// What happened at one point of a traced send.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    /// A line is about to be typed into `target`
+    Line { index: usize, target: String },
+    /// Whether `target` had focus right before typing
+    Focus { target: String, focused: bool },
+    /// Event `position` of one backend call of `count` events, which
+    /// accepted `accepted` of them
+    Input { event: KeyInput, position: usize, count: usize, accepted: u32 },
+}
+
+/// Entries of the send being traced, timed in µs from its start.
+struct Trace {
+    started: Instant,
+    entries: Vec<(u64, Entry)>,
+    /// Entries not kept once `TRACE_MAX_ENTRIES` was reached
+    dropped: usize,
+}
+
+static TRACE: Mutex<Option<Trace>> = Mutex::new(None);
+
+/// Gaps between consecutive key injections, in µs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GapSummary {
+    pub count: usize,
+    pub min_us: u64,
+    pub median_us: u64,
+    pub p95_us: u64,
+    pub max_us: u64,
+    /// Gaps of `TRACE_SLOW_GAP_MS` or more
+    pub slow: usize,
+}
+
+impl GapSummary {
+    /// Summarize the gaps between injection times `times_us` (in order).
+    /// None with fewer than two injections.
+    pub fn from_times(times_us: &[u64]) -> Option<Self> {
+        let mut gaps: Vec<u64> = times_us.windows(2).map(|w| w[1].saturating_sub(w[0])).collect();
+        if gaps.is_empty() {
+            return None;
+        }
+        gaps.sort_unstable();
+        let at = |fraction: f64| gaps[((gaps.len() - 1) as f64 * fraction).round() as usize];
+        Some(Self {
+            count: gaps.len(),
+            min_us: gaps[0],
+            median_us: at(0.5),
+            p95_us: at(0.95),
+            max_us: gaps[gaps.len() - 1],
+            slow: gaps.iter().filter(|&&gap| gap >= TRACE_SLOW_GAP_MS * 1000).count(),
+        })
+    }
+
+    /// Lines for the end of the trace file.
+    pub fn describe(&self) -> String {
+        format!(
+            "{} gaps between injections: min {} µs, median {} µs, 95th percentile {} µs, max {} µs\n\
+             {} gap(s) of {} ms or more\n",
+            self.count, self.min_us, self.median_us, self.p95_us, self.max_us, self.slow, TRACE_SLOW_GAP_MS
+        )
+    }
+}
+
+/// Start tracing a send, discarding any unfinished trace.
+pub fn start() {
+    *lock() = Some(Trace { started: Instant::now(), entries: Vec::new(), dropped: 0 });
+}
+
+/// Whether a send is being traced.
+pub fn is_active() -> bool {
+    lock().is_some()
+}
+
+/// Record a backend call that injected `events`, of which `accepted` got through.
+pub fn record_input(events: &[KeyInput], accepted: u32) {
+    let count = events.len();
+    record(|| {
+        events
+            .iter()
+            .enumerate()
+            .map(|(position, &event)| Entry::Input { event, position, count, accepted })
+            .collect()
+    });
+}
+
+/// Record that line `index` is about to be typed into `target`.
+pub fn record_line(index: usize, target: &str) {
+    record(|| vec![Entry::Line { index, target: target.to_string() }]);
+}
+
+/// Record whether `target` had focus right before typing.
+pub fn record_focus(target: &str, focused: bool) {
+    record(|| vec![Entry::Focus { target: target.to_string(), focused }]);
+}
+
+/// Stop tracing and write the trace file. None if no send was traced.
+pub fn finish() -> Option<Result<PathBuf, String>> {
+    let trace = lock().take()?;
+    let text = render(&trace.entries, trace.dropped, TRACE_MAX_BYTES);
+    Some(paths::data_file(&format!("madtyping-trace-{}.txt", file_stamp(now_secs()))).and_then(|path| {
+        fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }))
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Trace>> {
+    TRACE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Add the entries from `entries` if a send is being traced (only building
+/// them then), up to `TRACE_MAX_ENTRIES`.
+fn record(entries: impl FnOnce() -> Vec<Entry>) {
+    let mut trace = lock();
+    let Some(trace) = trace.as_mut() else {
+        return;
+    };
+    let at = trace.started.elapsed().as_micros() as u64;
+    for entry in entries() {
+        if trace.entries.len() < TRACE_MAX_ENTRIES {
+            trace.entries.push((at, entry));
+        } else {
+            trace.dropped += 1;
+        }
+    }
+}
+
+/// One line of the trace file.
+fn format_entry(at: u64, entry: &Entry) -> String {
+    match entry {
+        Entry::Line { index, target } => format!("{:>12} µs  line {} -> '{}'", at, index + 1, target),
+        Entry::Focus { target, focused } => {
+            format!("{:>12} µs  focus '{}': {}", at, target, if *focused { "ok" } else { "NOT FOCUSED" })
+        }
+        Entry::Input { event, position, count, accepted } => {
+            let (key, up) = match event {
+                KeyInput::Key { vk, up } => (format!("vk 0x{:02X}", vk), up),
+                KeyInput::Unicode { unit, up } => (format!("char U+{:04X}", unit), up),
+            };
+            format!(
+                "{:>12} µs  {:<12} {:<4}  SendInput {}/{} [{}/{}]",
+                at,
+                key,
+                if *up { "up" } else { "down" },
+                accepted,
+                count,
+                position + 1,
+                count
+            )
+        }
+    }
+}
+
+/// Build the trace file, keeping the entries under about `max_bytes` and
+/// always ending with the gap summary over all recorded injections.
+fn render(entries: &[(u64, Entry)], dropped: usize, max_bytes: usize) -> String {
+    let mut text = String::from("MadTyping input trace (times from the start of the send)\n\n");
+    let mut unwritten = dropped;
+    let mut capped = false;
+    for (at, entry) in entries {
+        let line = format_entry(*at, entry);
+        capped = capped || text.len() + line.len() + 1 > max_bytes;
+        if capped {
+            unwritten += 1;
+            continue;
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    if unwritten > 0 {
+        text.push_str(&format!("... {} more entries not written (trace capped)\n", unwritten));
+    }
+
+    // The first event of each call marks when it was injected
+    let times: Vec<u64> = entries
+        .iter()
+        .filter(|(_, entry)| matches!(entry, Entry::Input { position: 0, .. }))
+        .map(|(at, _)| *at)
+        .collect();
+    text.push_str("\n== Summary ==\n");
+    match GapSummary::from_times(&times) {
+        Some(summary) => text.push_str(&summary.describe()),
+        None => text.push_str("fewer than two key injections\n"),
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_summary() {
+        assert_eq!(GapSummary::from_times(&[5]), None);
+        let summary = GapSummary::from_times(&[0, 100, 300, 600, 60_600]).unwrap();
+        assert_eq!(summary, GapSummary { count: 4, min_us: 100, median_us: 300, p95_us: 60_000, max_us: 60_000, slow: 1 });
+    }
+
+    #[test]
+    fn test_trace_rendered_and_capped() {
+        let key = |vk, up| KeyInput::Key { vk, up };
+        let entries = vec![
+            (0, Entry::Line { index: 0, target: "Game".to_string() }),
+            (10, Entry::Focus { target: "Game".to_string(), focused: false }),
+            (20, Entry::Input { event: key(0x10, false), position: 0, count: 2, accepted: 1 }),
+            (20, Entry::Input { event: key(0x0D, false), position: 1, count: 2, accepted: 1 }),
+            (1_500, Entry::Input { event: KeyInput::Unicode { unit: 0xE9, up: true }, position: 0, count: 1, accepted: 1 }),
+        ];
+        let text = render(&entries, 0, usize::MAX);
+        assert!(text.contains("          10 µs  focus 'Game': NOT FOCUSED\n"));
+        assert!(text.contains("          20 µs  vk 0x10      down  SendInput 1/2 [1/2]\n"));
+        assert!(text.contains("        1500 µs  char U+00E9  up    SendInput 1/1 [1/1]\n"));
+        assert!(text.ends_with("1 gaps between injections: min 1480 µs, median 1480 µs, 95th percentile 1480 µs, max 1480 µs\n0 gap(s) of 50 ms or more\n"));
+
+        let capped = render(&entries, 3, 120);
+        assert!(capped.contains("line 1 -> 'Game'"));
+        assert!(!capped.contains("SendInput"));
+        assert!(capped.contains("... 7 more entries not written (trace capped)"));
+        assert!(capped.contains("min 1480 µs"));
+    }
+}
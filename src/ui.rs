@@ -7,132 +7,381 @@
 
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
-    event::{self, Event, KeyCode, KeyEventKind, poll, read},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind,
+    },
     execute,
-    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::{
-    io::{self, Write},
-    thread,
-    time::Duration,
+    collections::HashSet,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
 };
 
-use crate::app::App;
-use crate::config::{NEXT_LINE_DELAY_MS, USER_READ_DELAY_SECS, CANCEL_DELAY_SECS};
-use crate::logging::log;
-use crate::platform::{is_window_running, send_text};
+use crate::arming;
+use crate::app::{App, Focus, LastSend, ListRow, SendResult};
+use crate::build_info::{BUILD_DATE, GIT_HASH, VERSION};
+use crate::abbreviations::{self, Abbreviations};
+use crate::archive;
+use crate::charfilter::{self, CharFilter};
+use crate::files::{self, content_hash, diff_lines, diff_rows, DiffRow, Line, LineDiff, LoadReason, Provenance, ScanOptions, TextFile};
+use crate::config::{
+    Choice, Config, GameProfile, Limit, SendMethod, Toggle, CHOICES, LIMITS, TOGGLES,
+    VIEWER_POLL_MS, UI_POLL_MS, CURSOR_BLINK_MS, RELOAD_NOTE_SECS, PERSIST_LIFETIME_STATS,
+    NOTIFY_MIN_SEND_SECS, SCRATCH_FILE_NAME, ARCHIVE_DIRECTORY,
+};
+use crate::logging::{self, log};
+use crate::pack::{self, default_subdirectory, format_size, safe_relative_path, Collision};
+use crate::paste;
+use crate::paths;
+use crate::report;
+use crate::platform::{
+    ChatScope, KeyMode, Speed, check_keyboard_state, play_cue, focus_console_window, is_window_minimized, is_window_running,
+    best_kind, game_guard, set_window_class, target_kind,
+    minimize_console_window, notify,
+    restore_console_window, restore_keyboard_state, window_display_mode,
+};
+use crate::doctor::display_mode_warning;
+use crate::edit;
+use crate::focus_quirks;
+use crate::health::{Health, HealthCache, Level};
+use crate::hooks;
+use crate::journal::{self, Interrupted};
+use crate::lang;
+use crate::config_file::{self, ConfigWatcher, LoadedConfig, CONFIG_FILE_NAME};
+use crate::replay::DryRunSender;
+use crate::send::{
+    file_mute_risk, finish_notice, flag_paste_failed, long_lines, message_count, message_parts, repeated_lines, send_cue, BusyPolicy, ChatLimit, Control, LineSender,
+    PlatformSender,
+    SendChoices, SendDelays, SendEvent, SendReport, SendSession,
+};
+use crate::startup::{self, Startup};
+use crate::stats::format_duration;
+use crate::timed::{format_clock, parse_clock, GameClock};
+use crate::titles::not_running_error;
+use crate::warnings;
+use crate::watch::DirWatcher;
+use crate::weights::{self, Rng};
+use crate::worker::{FileChange, FsWorker};
+
+mod cancel;
+mod events;
+mod keys;
+mod layout;
+mod options;
+mod probe;
+mod scroll;
+
+use cancel::SendKeys;
+use events::{poll, read};
+use layout::{Layout, HINT_SEPARATOR};
+use options::{OptionsOverlay, Outcome};
+use probe::{screen_size, Output, Probe};
+
+/// Frames of the "scanning…" spinner, advanced every 100ms.
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Screen row of the search box.
+const SEARCH_ROW: u16 = 4;
 
 /// CLI renderer and event handler.
 /// 
 /// Manages terminal rendering and user input for the main interface
 /// and file viewer.
 pub struct Cli {
-    stdout: io::Stdout,
+    stdout: Output,
     header_name: String,
-    window_title: String,
+    window_titles: Vec<String>,
+    config: Config,
+    /// Config file the settings were loaded from, if any
+    config_path: Option<PathBuf>,
+    /// Picks up edits to the config file while the UI runs
+    config_watcher: Option<ConfigWatcher>,
+    /// Picks up message file changes while the list is shown
+    dir_watcher: Option<DirWatcher>,
+    /// Start of the current cursor blink cycle (reset on every key press)
+    blink_epoch: Instant,
+    /// Whether the search cursor is currently drawn
+    cursor_drawn: bool,
+    /// Seconds of chat limit cooldown shown in the header, if any
+    cooldown_drawn: Option<u64>,
+    /// Health of the files selected so far
+    health: HealthCache,
+    /// File to open in the viewer at startup, and whether to quit after it
+    initial_view: Option<(TextFile, bool)>,
+    /// Time to the first frame and the first scan
+    startup: Startup,
+    /// Send the last run didn't finish, until it's sent again
+    interrupted: Option<Interrupted>,
 }
 
 impl Cli {
-    /// Create a new CLI instance with custom header and target window title.
-    pub fn new(header_name: String, window_title: String) -> Self {
+    /// Create a new CLI instance with custom header and target window titles.
+    /// With more than one title, every file is broadcast to all windows.
+    pub fn new(header_name: String, window_titles: Vec<String>) -> Self {
         Self {
-            stdout: io::stdout(),
+            stdout: Output::new(),
             header_name,
-            window_title,
+            window_titles,
+            config: Config::default(),
+            config_path: None,
+            config_watcher: None,
+            dir_watcher: None,
+            blink_epoch: Instant::now(),
+            cursor_drawn: false,
+            cooldown_drawn: None,
+            health: HealthCache::new(),
+            initial_view: None,
+            startup: Startup::new(Instant::now()),
+            interrupted: None,
         }
     }
 
-    /// Initialize the terminal for the interactive UI.
+    /// Measure startup times from `began` rather than from [`Cli::new`].
+    pub fn with_start_time(mut self, began: Instant) -> Self {
+        self.startup = Startup::new(began);
+        self
+    }
+
+    /// Use settings loaded from a config file instead of the defaults.
+    pub fn with_config(mut self, config: Config, config_path: Option<PathBuf>) -> Self {
+        self.config = config;
+        self.config_path = config_path;
+        self
+    }
+
+    /// Open `file` in the viewer as soon as the UI starts (`--view`). With
+    /// `quit_after`, closing the viewer quits instead of showing the list.
+    pub fn with_initial_view(mut self, file: TextFile, quit_after: bool) -> Self {
+        self.initial_view = Some((file, quit_after));
+        self
+    }
+
+    /// Initialize the terminal for the interactive UI. The terminal is
+    /// probed first; one that can't be trusted gets the plain mode.
     pub fn init(&mut self) -> Result<(), String> {
-        terminal::enable_raw_mode()
-            .map_err(|e| format!("Failed to enable raw mode: {}", e))?;
-        
-        execute!(self.stdout, EnterAlternateScreen, Hide)
-            .map_err(|e| format!("Failed to setup terminal: {}", e))?;
+        terminal::enable_raw_mode().map_err(|e| {
+            log(&format!("Failed to enable raw mode: {}", e));
+            lang::format("error.terminal", &[("error", &e), ("flags", &HEADLESS_FLAGS)])
+        })?;
+
+        let found = Probe::run();
+        let capabilities = probe::select(&found);
+        log(&format!("Terminal probe: {}; using {}", found.summary(), capabilities.describe()));
+        let alternate_screen = capabilities.alternate_screen;
+        probe::activate(capabilities);
+
+        if alternate_screen {
+            execute!(self.stdout, EnterAlternateScreen, Hide, EnableMouseCapture)
+        } else {
+            execute!(self.stdout, Clear(ClearType::All), MoveTo(0, 0), Hide)
+        }
+        .map_err(|e| lang::format("error.terminal_setup", &[("error", &e)]))?;
         
         Ok(())
     }
 
     /// Cleanup the terminal state.
     pub fn cleanup(&mut self) -> Result<(), String> {
-        execute!(self.stdout, LeaveAlternateScreen, Show)
-            .map_err(|e| format!("Failed to cleanup terminal: {}", e))?;
+        if probe::active().alternate_screen {
+            execute!(self.stdout, DisableMouseCapture, LeaveAlternateScreen, Show)
+        } else {
+            execute!(self.stdout, Clear(ClearType::All), MoveTo(0, 0), Show)
+        }
+        .map_err(|e| lang::format("error.terminal_cleanup", &[("error", &e)]))?;
         terminal::disable_raw_mode()
-            .map_err(|e| format!("Failed to disable raw mode: {}", e))?;
+            .map_err(|e| lang::format("error.raw_mode", &[("error", &e)]))?;
         Ok(())
     }
 
-    /// Render static header (only needs to be called once or on full refresh).
-    fn render_header(&mut self) -> io::Result<()> {
+    /// Render static header (only needs to be called once or on full refresh),
+    /// with the kind of window the targets match (game or client).
+    fn render_header(&mut self, app: &App) -> io::Result<()> {
         execute!(self.stdout, MoveTo(0, 0))?;
         
-        let header_line = format!("  {}  ", self.header_name);
-        let padding = (63 - header_line.len()) / 2;
-        let header_centered = format!(
-            "{}{}{}",
-            " ".repeat(padding),
-            header_line,
-            " ".repeat(63 - padding - header_line.len())
-        );
-        
+        let mut notes = Vec::new();
+        // With arming required, its state comes first and colors the header
+        let mut color = Color::Cyan;
+        if arming::is_enabled() {
+            let armed = arming::is_armed();
+            notes.push(lang::text(if armed { "header.armed" } else { "header.disarmed" }));
+            color = if armed { Color::Red } else { Color::Green };
+        }
+        if self.config.read_only {
+            notes.push(lang::text("header.read_only"));
+        }
+        if let Some(kind) = best_kind(self.window_titles.iter().filter_map(|t| target_kind(t))) {
+            notes.push(kind.describe());
+        }
+        self.cooldown_drawn = self.cooldown(app).map(seconds_left);
+        let cooldown = self.cooldown_drawn.map(|secs| lang::format("header.cooldown", &[("secs", &secs)]));
+        notes.extend(cooldown.as_deref());
+        let title = if notes.is_empty() {
+            self.header_name.clone()
+        } else {
+            format!("{} ({})", self.header_name, notes.join(", "))
+        };
+        self.render_title_in(Layout::current(), &title, color)
+    }
+
+    /// Render `title` centered between two rules at the top of the screen.
+    fn render_title(&mut self, layout: Layout, title: &str) -> io::Result<()> {
+        self.render_title_in(layout, title, Color::Cyan)
+    }
+
+    /// Render `title` and its rules in `color`.
+    fn render_title_in(&mut self, layout: Layout, title: &str, color: Color) -> io::Result<()> {
         execute!(
             self.stdout,
-            SetForegroundColor(Color::Cyan),
-            Print("═══════════════════════════════════════════════════════════════\n"),
-            Print(format!("{}\n", header_centered)),
-            Print("═══════════════════════════════════════════════════════════════\n"),
+            MoveTo(0, 0),
+            SetForegroundColor(color),
+            Print(format!("{}\n", layout.rule())),
+            Print(format!("{}\n", layout.centered(title))),
+            Print(format!("{}\n", layout.rule())),
             ResetColor
-        )?;
-        Ok(())
+        )
     }
 
-    /// Render static footer (only needs to be called once or on full refresh).
-    fn render_footer(&mut self) -> io::Result<()> {
-        let (_, height) = terminal::size().unwrap_or((80, 24));
-        let footer_y = height.saturating_sub(3);
-        execute!(self.stdout, MoveTo(0, footer_y))?;
-        
+    /// Render a footer separator on row `y`.
+    fn render_separator(&mut self, layout: Layout, y: u16) -> io::Result<()> {
         execute!(
             self.stdout,
+            MoveTo(0, y),
             SetForegroundColor(Color::DarkGrey),
-            Print("───────────────────────────────────────────────────────────────\n"),
-            ResetColor,
-            SetForegroundColor(Color::Green),
-            Print(" [↑↓] Navigate │ [Enter] Run │ [Tab] View │ [F5] Refresh │ [Esc] Quit"),
+            Print(layout.separator()),
             ResetColor
-        )?;
+        )
+    }
+
+    /// Render as many `hints` (text and color) as fit on row `y`, replacing
+    /// the row. Separators take the first hint's color. Returns the width
+    /// used.
+    fn render_hints(&mut self, layout: Layout, y: u16, hints: &[(&str, Color)]) -> io::Result<usize> {
+        let texts: Vec<&str> = hints.iter().map(|(text, _)| *text).collect();
+        let count = layout.hints_that_fit(&texts);
+        execute!(self.stdout, MoveTo(0, y), Clear(ClearType::CurrentLine), Print(" "))?;
+        for (i, (text, color)) in hints[..count].iter().enumerate() {
+            if i > 0 {
+                execute!(self.stdout, SetForegroundColor(hints[0].1), Print(HINT_SEPARATOR))?;
+            }
+            execute!(self.stdout, SetForegroundColor(*color), Print(text))?;
+        }
+        execute!(self.stdout, ResetColor)?;
+        Ok(layout.hints_width(&texts, count))
+    }
+
+    /// Render a separator and a row of hints at the bottom of the screen.
+    fn render_footer_hints(&mut self, hints: &[(&str, Color)]) -> io::Result<()> {
+        let layout = Layout::current();
+        let (_, height) = screen_size();
+        let footer_y = height.saturating_sub(2);
+        self.render_separator(layout, footer_y)?;
+        self.render_hints(layout, footer_y + 1, hints)?;
         Ok(())
     }
 
-    /// Render the dynamic content area (search box, file list, error message).
-    fn render_content(&mut self, app: &App) -> io::Result<()> {
-        let (_, height) = terminal::size().unwrap_or((80, 24));
-        
-        // Search box (line 4)
-        execute!(self.stdout, MoveTo(0, 4))?;
+    /// Render the footer hints for the current focus (on full refresh).
+    /// Hints that don't fit the width are left out, least important last;
+    /// F1 lists every key.
+    fn render_footer(&mut self, focus: Focus) -> io::Result<()> {
+        let layout = Layout::current();
+        let (_, height) = screen_size();
+        let footer_y = height.saturating_sub(3);
+        self.render_separator(layout, footer_y)?;
+
+        let send_hint = self.send_hint_color();
+        let green = Color::Green;
+        let hints: &[(&str, Color)] = if focus == Focus::Search {
+            &[
+                (lang::text("hint.type_filter"), green),
+                (lang::text("hint.navigate"), green),
+                (lang::text("hint.run"), send_hint),
+                (lang::text("hint.back_to_list"), green),
+            ]
+        } else {
+            &[
+                (lang::text("hint.navigate"), green),
+                (lang::text("hint.search"), green),
+                (lang::text("hint.run"), send_hint),
+                (lang::text("hint.view"), green),
+                (lang::text("hint.help"), green),
+                (lang::text("hint.quit"), green),
+                (lang::text("hint.settings"), green),
+                (lang::text("hint.tag"), green),
+                (lang::text("hint.import"), send_hint),
+                (lang::text("hint.refresh"), green),
+                (lang::text("hint.export"), green),
+                (lang::text("hint.stats"), green),
+            ]
+        };
+        self.render_hints(layout, footer_y + 1, hints)?;
+        Ok(())
+    }
+
+    /// Color for footer hints of sending and importing, dimmed in read-only mode.
+    fn send_hint_color(&self) -> Color {
+        if self.config.read_only { Color::DarkGrey } else { Color::Green }
+    }
+
+    /// Whether the blinking search cursor is in its visible half.
+    fn cursor_visible(&self) -> bool {
+        (self.blink_epoch.elapsed().as_millis() / CURSOR_BLINK_MS as u128).is_multiple_of(2)
+    }
+
+    /// Render the search box: highlighted with a blinking cursor while it
+    /// has focus, plain otherwise.
+    fn render_search_box(&mut self, app: &App) -> io::Result<()> {
+        let focused = app.focus() == Focus::Search;
+        self.cursor_drawn = focused && self.cursor_visible();
+        execute!(self.stdout, MoveTo(0, SEARCH_ROW), Clear(ClearType::CurrentLine))?;
+        if focused {
+            execute!(
+                self.stdout,
+                SetBackgroundColor(Color::Yellow),
+                SetForegroundColor(Color::Black),
+                Print(lang::text("search.label")),
+                ResetColor,
+                Print(" "),
+            )?;
+        } else {
+            execute!(self.stdout, SetForegroundColor(Color::Yellow), Print(lang::text("search.label")), ResetColor)?;
+        }
         execute!(
             self.stdout,
-            Clear(ClearType::CurrentLine),
-            SetForegroundColor(Color::Yellow),
-            Print(" Search: "),
-            ResetColor,
             SetForegroundColor(Color::White),
             Print(app.search_query()),
-            SetForegroundColor(Color::DarkGrey),
-            Print("█"),
+            Print(if self.cursor_drawn { "█" } else { " " }),
             ResetColor,
-            Print(format!("  ({} files)  ", app.filtered_count())),
         )?;
-
-        let visible_files = (height as usize).saturating_sub(12);
-        let filtered = app.filtered_files();
-        
-        let scroll_offset = if app.selected_index() >= visible_files {
-            app.selected_index() - visible_files + 1
+        if !focused && app.is_search_empty() {
+            execute!(self.stdout, SetForegroundColor(Color::DarkGrey), Print(lang::text("search.placeholder")), ResetColor)?;
+        }
+        let count = if app.is_capped() && app.is_search_empty() {
+            lang::format("search.count_capped", &[("count", &app.total_count())])
         } else {
-            0
+            lang::format("search.count", &[("count", &app.filtered_count())])
         };
+        execute!(self.stdout, Print(format!("  {}  ", count)))
+    }
+
+    /// Render the dynamic content area (search box, file list, error message).
+    fn render_content(&mut self, app: &App) -> io::Result<()> {
+        let (width, height) = screen_size();
+        
+        self.render_search_box(app)?;
+        // Checked once per version when first selected
+        if let Some(file) = app.selection() {
+            self.health.check(file, &self.config);
+        }
+
+        let visible_files = (height as usize).saturating_sub(12);
+        let rows = app.list_rows();
+        let grid = app.list_grid(width as usize);
+        let selected = app.selected_row();
+        let scroll_offset = grid.scroll_offset(selected, visible_files);
 
         // Clear file list area and display files
         let file_start_y = 6;
@@ -145,37 +394,30 @@ impl Cli {
         }
 
         // Display filtered files
-        if filtered.is_empty() {
+        if rows.is_empty() {
             execute!(
                 self.stdout,
                 MoveTo(0, file_start_y as u16),
                 SetForegroundColor(Color::DarkGrey),
-                Print("   No files match your search."),
+                Print(format!("   {}", lang::text(if app.scanning_since().is_some() { "list.scanning" } else { "list.no_match" }))),
                 ResetColor
             )?;
         } else {
-            for (i, file) in filtered.iter().enumerate().skip(scroll_offset).take(visible_files) {
-                execute!(self.stdout, MoveTo(0, (file_start_y + i - scroll_offset) as u16))?;
-
-                if i == app.selected_index() {
-                    execute!(
-                        self.stdout,
-                        SetBackgroundColor(Color::DarkBlue),
-                        SetForegroundColor(Color::White),
-                        Print(format!(" ► {} ", file.name)),
-                        ResetColor,
-                        SetForegroundColor(Color::DarkGrey),
-                        Print(format!("  ({} lines)", file.lines.len())),
-                        ResetColor
-                    )?;
-                } else {
-                    execute!(
-                        self.stdout,
-                        Print(format!("   {} ", file.name)),
-                        SetForegroundColor(Color::DarkGrey),
-                        Print(format!("  ({} lines)", file.lines.len())),
-                        ResetColor
-                    )?;
+            // A single column may run to the edge; grid columns must not overlap
+            let column_width = width as usize / grid.columns;
+            let room = if grid.columns == 1 { usize::MAX } else { column_width.saturating_sub(1) };
+            for row in 0..visible_files {
+                for column in 0..grid.columns {
+                    let Some(i) = grid.index(column, scroll_offset + row, rows.len()) else {
+                        continue;
+                    };
+                    execute!(self.stdout, MoveTo((column * column_width) as u16, (file_start_y + row) as u16))?;
+                    match rows[i] {
+                        ListRow::File(file) => self.render_file_entry(app, file, i == selected, room)?,
+                        ListRow::Folder { name, count, collapsed } => {
+                            self.render_folder_entry(name, count, collapsed, i == selected)?
+                        }
+                    }
                 }
             }
         }
@@ -195,62 +437,529 @@ impl Cli {
                 Print(format!(" ⚠ {} ", error)),
                 ResetColor
             )?;
+        } else if let Some(notice) = app.get_notice() {
+            execute!(
+                self.stdout,
+                SetForegroundColor(Color::Green),
+                Print(format!(" ✓ {} ", notice)),
+                ResetColor
+            )?;
+        } else if let Some(since) = app.scanning_since() {
+            let frame = SPINNER_FRAMES[(since.elapsed().as_millis() / 100) as usize % SPINNER_FRAMES.len()];
+            execute!(
+                self.stdout,
+                SetForegroundColor(Color::Cyan),
+                Print(format!(" {} {} ", frame, lang::text("status.scanning"))),
+                ResetColor
+            )?;
+        } else if let Some(last) = app.selection().and_then(|f| app.last_send(f)) {
+            let (glyph, color) = send_result_glyph(last.result);
+            execute!(
+                self.stdout,
+                SetForegroundColor(color),
+                Print(format!(" {} {} ", glyph, send_result_detail(last))),
+                ResetColor
+            )?;
+        } else if let Some(error) = app.selection().and_then(|f| self.health.get(f)).and_then(|h| h.errors.first()) {
+            execute!(
+                self.stdout,
+                SetForegroundColor(Color::Red),
+                Print(format!(" ⛔ {} ", error)),
+                ResetColor
+            )?;
+        } else if let Some((_, warning)) = app.selection().and_then(|f| visible_file_warning(f, self.health.get(f))) {
+            execute!(
+                self.stdout,
+                SetForegroundColor(Color::Yellow),
+                Print(format!(" ⚠ {} ", warning)),
+                ResetColor
+            )?;
+        } else if !app.skipped().is_empty() {
+            execute!(
+                self.stdout,
+                SetForegroundColor(Color::DarkYellow),
+                Print(format!(" ⚠ {} ", lang::format("status.skipped", &[("count", &app.skipped().len())]))),
+                ResetColor
+            )?;
+        } else if let Some(file) = app.selection() {
+            execute!(
+                self.stdout,
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!(" {} ", provenance_detail(&file.provenance))),
+                ResetColor
+            )?;
         }
 
         self.stdout.flush()?;
         Ok(())
     }
 
+    /// Render a folder's header row: ▸ when collapsed, ▾ when expanded,
+    /// with its number of listed files.
+    fn render_folder_entry(&mut self, name: &str, count: usize, collapsed: bool, selected: bool) -> io::Result<()> {
+        if selected {
+            execute!(self.stdout, SetBackgroundColor(Color::DarkBlue))?;
+        }
+        let arrow = if collapsed { "▸" } else { "▾" };
+        execute!(
+            self.stdout,
+            SetForegroundColor(Color::Cyan),
+            Print(format!(" {} {}/ ", arrow, name)),
+            SetForegroundColor(Color::DarkGrey),
+            Print(if count == 1 {
+                format!("{} ", lang::text("list.folder_one"))
+            } else {
+                format!("{} ", lang::format("list.folder_many", &[("count", &count)]))
+            }),
+            ResetColor
+        )
+    }
+
+    /// Render one file list entry: name, tags, line count and warnings,
+    /// leaving out what doesn't fit in `room` characters.
+    fn render_file_entry(&mut self, app: &App, file: &TextFile, selected: bool, room: usize) -> io::Result<()> {
+        let marked = app.is_marked(file);
+        let (marker, color) = if selected {
+            (if marked { "•" } else { "►" }, Color::White)
+        } else if marked {
+            ("•", Color::Yellow)
+        } else if app.is_highlighted(file) {
+            ("+", Color::Green)
+        } else {
+            (" ", Color::Reset)
+        };
+        // Files in a subfolder are listed under its header, indented
+        let (indent, name) = match file.name.rsplit_once('/') {
+            Some((_, base)) => ("  ", base),
+            None => ("", file.name.as_str()),
+        };
+        let last = app.last_send(file);
+        let glyph = last.map(|l| format!("{} ", send_result_glyph(l.result).0)).unwrap_or_default();
+        let health = self.health.get(file).map(|h| health_badge(h.level()));
+        let badge = health.as_ref().map(|(text, _)| format!("{} ", text)).unwrap_or_default();
+        // Room for the name after the marker, glyphs and spaces, minus "..."
+        let name_room = room.saturating_sub(indent.len() + glyph.chars().count() + badge.chars().count());
+        let name = if name.chars().count() + 4 > name_room {
+            truncate_line(name, name_room.saturating_sub(7))
+        } else {
+            name.to_string()
+        };
+        let entry = format!("{} {} {}{}{} ", indent, marker, glyph, badge, name);
+        let mut room = room.saturating_sub(entry.chars().count());
+        if selected {
+            execute!(self.stdout, SetBackgroundColor(Color::DarkBlue))?;
+        }
+        execute!(self.stdout, SetForegroundColor(color), Print(format!("{} {} ", indent, marker)))?;
+        if let Some(last) = last {
+            execute!(self.stdout, SetForegroundColor(send_result_glyph(last.result).1), Print(&glyph))?;
+        }
+        if let Some((_, health_color)) = health {
+            execute!(self.stdout, SetForegroundColor(health_color), Print(&badge))?;
+        }
+        execute!(self.stdout, SetForegroundColor(color), Print(format!("{} ", name)), ResetColor)?;
+
+        self.render_tags(&file.tags, &mut room)?;
+        let count = file.lines.len();
+        let excluded = app.excluded_lines(file).len();
+        let lines = if excluded > 0 {
+            lang::format("list.lines_excluded", &[("count", &count), ("excluded", &excluded)])
+        } else {
+            lang::format("list.lines", &[("count", &count)])
+        };
+        self.render_suffix(&format!("  {}", lines), Color::DarkGrey, &mut room)?;
+        let warnings = file.warnings.len();
+        if warnings > 0 {
+            let text = if warnings == 1 {
+                lang::text("list.warning_one").to_string()
+            } else {
+                lang::format("list.warning_many", &[("count", &warnings)])
+            };
+            self.render_suffix(&format!("  {}", text), Color::Yellow, &mut room)?;
+        }
+        let commands = file.lines.iter().filter(|l| l.is_command()).count();
+        if self.config.slash_commands && commands > 0 {
+            self.render_suffix(&format!("  {}", lang::format("list.commands", &[("count", &commands)])), Color::Green, &mut room)?;
+        }
+        if let Some(max) = self.config.char_limit().filter(|_| !self.config.split_long_lines) {
+            let long = long_lines(&file.lines, max);
+            if long > 0 {
+                let text = lang::format("list.long_lines", &[("count", &long), ("max", &max)]);
+                self.render_suffix(&format!("  {}", text), Color::Red, &mut room)?;
+            }
+        }
+        let max = self.config.max_lines_per_send;
+        if message_count(&file.lines, self.config.split_long_lines, self.config.char_limit()) as u64 > max {
+            self.render_suffix(&format!("  {}", lang::format("list.over_limit", &[("max", &max)])), Color::Red, &mut room)?;
+        }
+        if let Some(risk) = file_mute_risk(&file.lines, Speed::NORMAL, &self.config) {
+            self.render_suffix(&format!("  {}", risk.label()), Color::Yellow, &mut room)?;
+        }
+        if let Some(original) = app.duplicate_of(file) {
+            self.render_suffix(&format!("  {}", lang::format("list.duplicate", &[("name", &original)])), Color::Magenta, &mut room)?;
+        }
+        if app.is_manual_advance(file) {
+            self.render_suffix(&format!("  {}", lang::text("list.manual")), Color::Cyan, &mut room)?;
+        }
+        if let Some(last) = last {
+            let ago = sent_ago_label(last.at.elapsed());
+            self.render_suffix(&format!("  {}", ago), send_result_glyph(last.result).1, &mut room)?;
+        }
+        Ok(())
+    }
+
+    /// Render a file's tags as colored chips after its name, as many as
+    /// fit in `room`.
+    fn render_tags(&mut self, tags: &[String], room: &mut usize) -> io::Result<()> {
+        for tag in tags {
+            // " [tag]" and the " tag " chip after a space are equally wide
+            let width = tag.chars().count() + 3;
+            if width > *room {
+                break;
+            }
+            *room -= width;
+            if colors_enabled() {
+                execute!(
+                    self.stdout,
+                    Print(" "),
+                    SetBackgroundColor(tag_color(tag)),
+                    SetForegroundColor(Color::Black),
+                    Print(format!(" {} ", tag)),
+                    ResetColor
+                )?;
+            } else {
+                execute!(self.stdout, Print(format!(" [{}]", tag)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Render `text` after a file entry if it fits in `room`.
+    fn render_suffix(&mut self, text: &str, color: Color, room: &mut usize) -> io::Result<()> {
+        let width = text.chars().count();
+        if width > *room {
+            // Nothing more goes after something left out
+            *room = 0;
+            return Ok(());
+        }
+        *room -= width;
+        execute!(self.stdout, SetForegroundColor(color), Print(text), ResetColor)
+    }
+
     /// Full render - clears screen and renders everything (header, content, footer).
-    pub fn render(&mut self, app: &App) -> io::Result<()> {
+    pub(crate) fn render(&mut self, app: &App) -> io::Result<()> {
         execute!(self.stdout, Clear(ClearType::All))?;
-        self.render_header()?;
+        self.render_header(app)?;
         self.render_content(app)?;
-        self.render_footer()?;
+        self.render_footer(app.focus())?;
         self.stdout.flush()?;
         Ok(())
     }
 
+    /// Apply a finished background scan to `app`. Returns whether there was one.
+    fn apply_scan_result(&mut self, app: &mut App, worker: &mut FsWorker) -> bool {
+        let Some(result) = worker.take_discovered() else {
+            return false;
+        };
+        self.startup.interactive();
+        match result {
+            Ok(scan) => {
+                let duplicates = app.duplicate_count();
+                app.set_capped(scan.cap_warning.is_some());
+                let changed = app.set_files(scan.files);
+                let newly_skipped = app.set_skipped(scan.skipped);
+                log(&format!(
+                    "File list refreshed ({} file(s), {} changed, {} newly skipped)",
+                    app.total_count(),
+                    changed,
+                    newly_skipped.len()
+                ));
+                let cap_shown = scan.cap_warning.is_some_and(|warning| app.set_warning("scan:cap", warning));
+                if cap_shown {
+                    // The cap hides everything else
+                } else if let Some(first) = newly_skipped.first() {
+                    app.set_error(if newly_skipped.len() == 1 {
+                        lang::format("error.skipped_one", &[("name", first)])
+                    } else {
+                        lang::format("error.skipped_many", &[("count", &newly_skipped.len())])
+                    });
+                } else if app.duplicate_count() > duplicates {
+                    log(&format!("{} file(s) duplicate another file", app.duplicate_count()));
+                    app.set_warning_notice("duplicates", lang::format("notice.duplicates", &[("count", &app.duplicate_count())]));
+                }
+            }
+            Err(e) => {
+                log(&format!("Refresh failed: {}", e));
+                app.set_error(lang::format("error.refresh_failed", &[("error", &e)]));
+            }
+        }
+        app.set_scanning(worker.is_scanning());
+        true
+    }
+
+    /// Apply the changes the directory watcher has settled on, file by
+    /// file (see `watch_files`). Returns whether the list changed.
+    fn apply_file_events(&mut self, app: &mut App) -> bool {
+        let options = ScanOptions::from(&self.config);
+        let Some(watcher) = self.dir_watcher.as_mut().filter(|_| self.config.watch_files) else {
+            return false;
+        };
+        let events = watcher.poll(options);
+        let changed = events.iter().filter(|event| app.apply_file_event(event, watcher.dir())).count();
+        if changed > 0 {
+            log(&format!("Watcher applied {} change(s)", changed));
+        }
+        changed > 0
+    }
+
     /// Run the main event loop.
+    ///
+    /// Files are scanned on a background worker, starting with an initial
+    /// scan, so a slow drive never blocks input. The first frame is drawn
+    /// before that scan is done and before anything else is read from
+    /// disk (see [`startup`]).
     pub fn run(&mut self, app: &mut App) -> Result<(), String> {
-        // Initial full render (header + content + footer)
+        let mut worker = FsWorker::spawn();
+        configure_arming(&self.config);
+        startup::first_frame(app, &mut worker, ScanOptions::from(&self.config), |app| self.render(app))
+            .map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+        self.startup.frame_rendered();
+
+        app.load_lifetime_stats();
+        self.config_watcher = config_file::config_path().ok().map(ConfigWatcher::new);
+        self.dir_watcher = files::scan_directory().ok().map(|dir| DirWatcher::new(dir, ScanOptions::from(&self.config)));
+
+        if let Some((file, quit_after)) = self.initial_view.take() {
+            let mut excluded = app.excluded_lines(&file);
+            let path = file.path.clone();
+            let exit = self.view_file(file, None, &mut excluded, &mut worker)?;
+            if quit_after {
+                return Ok(());
+            }
+            match exit {
+                ViewerExit::Unchanged => {}
+                ViewerExit::Reloaded(updated) => app.replace_file(updated),
+                ViewerExit::Removed(path) => app.remove_file(&path),
+            }
+            app.set_excluded_lines(&path, excluded);
+        }
+
+        // The file is checked against the journal when it is sent
+        self.interrupted = journal::take_interrupted().filter(|i| i.is_resumable(i.total));
+        if let Some(interrupted) = &self.interrupted {
+            let name = interrupted.source.file_name().map_or_else(|| interrupted.source.display().to_string(), |n| n.to_string_lossy().into_owned());
+            let line = interrupted.resume_from() + 1;
+            log(&format!("Send of '{}' started at {} was interrupted at line {}", interrupted.source.display(), interrupted.started, line));
+            app.set_notice(lang::format("notice.interrupted", &[("name", &name), ("line", &line), ("total", &interrupted.total)]));
+        }
+
+        // Full render again (header + content + footer) with what was read since
         if let Err(e) = self.render(app) {
-            return Err(format!("Render error: {}", e));
+            return Err(lang::format("error.render", &[("error", &e)]));
         }
 
         loop {
-            // Wait for input (blocking until event occurs)
-            if let Ok(Event::Key(key_event)) = event::read() {
-                // Only handle key press events, ignore release events
-                if key_event.kind != KeyEventKind::Press {
+            // Wait for input, picking up scan results and animating the spinner in between
+            if !poll(Duration::from_millis(UI_POLL_MS)).unwrap_or(false) {
+                if app.focus() == Focus::Search && self.cursor_visible() != self.cursor_drawn {
+                    self.render_search_box(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                    let _ = self.stdout.flush();
+                }
+                if let Some(result) = self.config_watcher.as_mut().and_then(|w| w.poll()) {
+                    match apply_reload(&mut self.config, &mut self.config_path, result) {
+                        Ok(notice) => app.set_notice(notice),
+                        Err(e) => app.set_error(e),
+                    }
+                    self.render(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                    continue;
+                }
+                if self.cooldown(app).map(seconds_left) != self.cooldown_drawn {
+                    app.cooldown_mut().prune(ChatLimit::from(&self.config), Instant::now());
+                    self.render_header(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                    let _ = self.stdout.flush();
+                }
+                if arming::expire() {
+                    log("Sending disarmed after the idle timeout");
+                    app.set_notice(lang::format("notice.arming_expired", &[("secs", &self.config.arming_timeout_secs)]));
+                    self.render(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                    continue;
+                }
+                if self.apply_scan_result(app, &mut worker) || app.scanning_since().is_some() || self.apply_file_events(app) {
+                    self.render_content(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                }
+                continue;
+            }
+            let event = read();
+            if let Ok(Event::Resize(..)) = &event {
+                // The list may gain or lose columns
+                self.render(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                continue;
+            }
+            if let Ok(Event::Mouse(mouse)) = &event {
+                // Clicking the search line focuses it, clicking elsewhere leaves it
+                if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+                    if mouse.row == SEARCH_ROW {
+                        app.focus_search();
+                    } else {
+                        app.focus_list();
+                    }
+                    self.blink_epoch = Instant::now();
+                    self.render(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                }
+                continue;
+            }
+            if let Ok(Event::Key(key_event)) = event {
+                // Releases, and repeats of keys that don't move or scroll
+                if !keys::accept(&key_event) {
+                    continue;
+                }
+                // Keep the cursor solid while typing
+                self.blink_epoch = Instant::now();
+
+                if key_event.code == KeyCode::Char('d') && key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                    app.clear_error();
+                    let marked: Vec<TextFile> = app.marked_or_selected().into_iter().cloned().collect();
+                    match &marked[..] {
+                        [old, new] => self.compare_screen(old, new)?,
+                        _ => app.set_error(lang::text("error.compare_two").to_string()),
+                    }
+                    self.render(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                    continue;
+                }
+                if arming::is_enabled() && key_event.code == KeyCode::Char('a') && key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                    let armed = arming::toggle();
+                    log(if armed { "Sending armed" } else { "Sending disarmed" });
+                    app.set_notice(lang::text(if armed { "notice.armed" } else { "notice.disarmed" }).to_string());
+                    self.render(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                    continue;
+                }
+                arming::touch();
+                if key_event.code == KeyCode::Char('r') && key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                    app.clear_error();
+                    self.send_random(app)?;
+                    self.render(app).map_err(|e| lang::format("error.render", &[("error", &e)]))?;
                     continue;
                 }
                 
                 // Track if we need full render (header/footer changed or screen was cleared)
                 let mut needs_full_render = false;
                 
-                match key_event.code {
-                    KeyCode::Esc => {
-                        return Ok(());
+                let focus = app.focus();
+                match search_key(app, key_event.code) {
+                    SearchKey::Quit => return Ok(()),
+                    SearchKey::Unchanged => continue,
+                    SearchKey::Handled => {
+                        let render_result = if app.focus() != focus { self.render(app) } else { self.render_content(app) };
+                        render_result.map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+                        continue;
                     }
+                    SearchKey::Ignored => {}
+                }
+
+                match key_event.code {
                     KeyCode::F(5) => {
-                        // Refresh file list - needs full render
-                        match app.refresh_files() {
-                            Ok(_) => {
-                                log("File list refreshed");
+                        // Rescan in the background; results arrive in the poll loop
+                        app.clear_error();
+                        app.set_highlighted(&[]);
+                        if !worker.refresh(ScanOptions::from(&self.config)) {
+                            log("Refresh requested while scanning, queued");
+                        }
+                        app.set_scanning(true);
+                    }
+                    KeyCode::F(1) => {
+                        self.help_screen()?;
+                        needs_full_render = true;
+                    }
+                    KeyCode::F(2) => {
+                        let lines = app.selection().map(|f| f.lines.clone());
+                        loop {
+                            match self.settings_screen(lines.as_deref())? {
+                                SettingsExit::Back => {
+                                    configure_arming(&self.config);
+                                    break;
+                                }
+                                SettingsExit::TestSend => {
+                                    self.test_send(app)?;
+                                    break;
+                                }
+                                SettingsExit::HiddenWarnings => self.hidden_warnings_screen()?,
                             }
-                            Err(e) => {
-                                app.set_error(format!("Refresh failed: {}", e));
+                        }
+                        needs_full_render = true;
+                    }
+                    KeyCode::F(3) => {
+                        app.clear_error();
+                        app.cycle_tag_filter();
+                    }
+                    KeyCode::F(4) => {
+                        app.clear_error();
+                        self.import_pack(app, &mut worker)?;
+                        needs_full_render = true;
+                    }
+                    KeyCode::F(6) => {
+                        app.clear_error();
+                        self.export_pack(app)?;
+                        needs_full_render = true;
+                    }
+                    KeyCode::Insert => {
+                        app.clear_error();
+                        app.toggle_mark();
+                        app.move_down();
+                    }
+                    KeyCode::Delete => {
+                        // Don't show the warning on the status line again
+                        let id = app
+                            .shown_warning()
+                            .map(str::to_string)
+                            .or_else(|| app.selection().and_then(|f| visible_file_warning(f, self.health.get(f))).map(|(id, _)| id));
+                        if let Some(id) = id {
+                            app.clear_error();
+                            match warnings::suppress(&id) {
+                                Ok(()) => {
+                                    log(&format!("Warning '{}' hidden", id));
+                                    app.set_notice(lang::text("notice.warning_hidden").to_string());
+                                }
+                                Err(e) => app.set_error(e),
                             }
                         }
+                    }
+                    KeyCode::F(7) => {
+                        app.clear_error();
+                        if !app.select_next_duplicate() {
+                            app.set_notice(lang::text("notice.no_copies").to_string());
+                        }
+                    }
+                    KeyCode::F(8) => {
+                        app.clear_error();
+                        if let Some(name) = app.selection().map(|f| f.name.clone()) {
+                            let notice = if app.toggle_manual_advance() {
+                                lang::format("notice.manual_on", &[("name", &name)])
+                            } else {
+                                lang::format("notice.manual_off", &[("name", &name)])
+                            };
+                            app.set_notice(notice);
+                        }
+                    }
+                    KeyCode::F(9) => {
+                        self.stats_screen(app)?;
+                        needs_full_render = true;
+                    }
+                    KeyCode::F(10) => {
+                        app.clear_error();
+                        self.skipped_screen(app)?;
                         needs_full_render = true;
                     }
                     KeyCode::Tab => {
                         // View file contents
-                        if let Some(file) = app.get_selected() {
-                            let lines = file.lines.clone();
-                            let file_name = file.name.clone();
-                            self.view_file(&file_name, &lines)?;
+                        if let Some(file) = app.selection().cloned() {
+                            let start = app.take_view_target(&file);
+                            let mut excluded = app.excluded_lines(&file);
+                            let path = file.path.clone();
+                            match self.view_file(file, start, &mut excluded, &mut worker)? {
+                                ViewerExit::Unchanged => {}
+                                ViewerExit::Reloaded(updated) => app.replace_file(updated),
+                                ViewerExit::Removed(path) => app.remove_file(&path),
+                            }
+                            app.set_excluded_lines(&path, excluded);
                         }
                         // After returning from view, need full render
                         needs_full_render = true;
@@ -263,46 +972,117 @@ impl Cli {
                         app.clear_error();
                         app.move_down();
                     }
-                    KeyCode::Backspace => {
-                        if !app.is_search_empty() {
-                            app.clear_error();
-                            app.remove_search_char();
+                    KeyCode::Char(':') => {
+                        app.clear_error();
+                        self.send_adhoc(app, &mut worker)?;
+                        needs_full_render = true;
+                    }
+                    KeyCode::Left | KeyCode::Right if app.is_grouped() => {
+                        app.clear_error();
+                        if key_event.code == KeyCode::Left {
+                            app.collapse_folder();
                         } else {
-                            continue; // Don't re-render if nothing to delete
+                            app.expand_folder();
                         }
                     }
-                    KeyCode::Char(c) => {
+                    KeyCode::Left | KeyCode::Right => {
+                        let width = screen_size().0 as usize;
+                        let grid = app.list_grid(width);
+                        if grid.columns == 1 {
+                            continue;
+                        }
+                        app.clear_error();
+                        if key_event.code == KeyCode::Left {
+                            app.move_left(grid);
+                        } else {
+                            app.move_right(grid);
+                        }
+                    }
+                    KeyCode::Enter if app.selected_folder().is_some() => {
                         app.clear_error();
-                        app.add_search_char(c);
+                        app.toggle_folder();
                     }
                     KeyCode::Enter => {
                         app.clear_error(); // Clear any previous error first
+                        // The options overlay covers part of the list
+                        needs_full_render = self.config.send_options_prompt;
                         
-                        if let Some(file) = app.get_selected() {
+                        if self.config.read_only && app.selection().is_some() {
+                            app.set_error(lang::format("error.cant_send", &[("reason", &lang::text("error.read_only"))]));
+                        } else if !arming::is_armed() && app.selection().is_some() {
+                            app.set_error(lang::format("error.cant_send", &[("reason", &lang::text("error.disarmed"))]));
+                        } else if let Some((file, choices)) = app
+                            .selection()
+                            .cloned()
+                            .and_then(|f| self.version_to_send(app, f))
+                            .filter(|f| self.resend_allowed(app, f))
+                            .filter(|f| self.cooldown_allows(app, f))
+                            .and_then(|f| self.ask_send_options(app, &f).map(|choices| (f, choices)))
+                        {
                             let lines = file.lines.clone();
                             let file_name = file.name.clone();
+                            let targets = file.targets(&self.window_titles);
+                            let overridden = file.front.window_title.is_some();
+                            let mut excluded = app.excluded_lines(&file);
+                            if let Some(from) = self.resume_point(&file) {
+                                excluded.extend(0..from);
+                            }
                             
                             log(&format!("User selected file: '{}' with {} lines", file_name, lines.len()));
                             
-                            // Check if target window is running before proceeding
-                            if !is_window_running(&self.window_title) {
-                                log("ERROR: Target window is not running!");
-                                app.set_error(format!("'{}' is not running!", self.window_title));
-                            } else {
+                            // Check if a target window is running before proceeding;
+                            // a dry run doesn't need one
+                            if !choices.dry_run && !targets.iter().any(|t| is_window_running(t)) {
+                                app.set_error(not_running_error(&targets));
+                            } else if !choices.dry_run && !self.guard_allows(app, &targets) {
+                                // Refused, with the reason on the status line
+                            } else if let Some(game_clock) = self.ask_game_clock(app, &lines)? {
                                 // Exit CLI to send messages (send_text will handle window focus)
                                 self.cleanup()?;
                                 
                                 // Clear screen before showing progress
                                 print!("\x1B[2J\x1B[1;1H");
                                 
-                                println!(">>> Selected: {}", file_name);
-                                println!(">>> Sending {} lines...\n", lines.len());
+                                println!("{}", lang::format("send.selected", &[("name", &file_name)]));
+                                if overridden {
+                                    println!("{}", lang::format("send.target_override", &[("windows", &targets.join(", "))]));
+                                }
+                                if excluded.is_empty() {
+                                    println!("{}\n", lang::format("send.lines", &[("count", &lines.len())]));
+                                } else {
+                                    let count = lines.len().saturating_sub(excluded.len());
+                                    println!("{}\n", lang::format("send.lines_excluded", &[("count", &count), ("excluded", &excluded.len())]));
+                                }
 
-                                self.send_all_lines(&lines);
+                                let options = SendOptions { choices, game_clock, excluded, source: Some(file.path.clone()) };
+                                let mut failed_line = None;
+                                let mut finished = None;
+                                self.send_all_lines(&file_name, &lines, &targets, options, |event| {
+                                    match event {
+                                        SendEvent::LineFinished { index, result: Err(_), .. } => {
+                                            failed_line.get_or_insert(*index);
+                                        }
+                                        SendEvent::Finished { report } => finished = Some(SendReport::clone(report)),
+                                        _ => {}
+                                    }
+                                    if !choices.dry_run {
+                                        app.record_send_event(event);
+                                    }
+                                });
+                                if let Some(report) = finished.filter(|_| !choices.dry_run) {
+                                    app.record_file_send(&file, &report);
+                                    if archive::should_archive(&file, &self.config, &report) {
+                                        self.archive_sent(app, &file);
+                                    }
+                                }
                                 
                                 log("All messages sent, re-initializing CLI...");
                                 // Re-initialize CLI and continue
                                 self.init()?;
+                                if let Some(index) = failed_line {
+                                    app.set_view_target(&file.path, index);
+                                    app.set_error(lang::format("error.line_failed", &[("line", &(index + 1))]));
+                                }
                                 needs_full_render = true;
                             }
                         }
@@ -318,101 +1098,1460 @@ impl Cli {
                 };
                 
                 if let Err(e) = render_result {
-                    return Err(format!("Render error: {}", e));
+                    return Err(lang::format("error.render", &[("error", &e)]));
                 }
             }
         }
     }
 
-    /// Send all lines from the selected file (with cancel support).
-    fn send_all_lines(&self, lines: &[String]) {
-        let total = lines.len();
-        
-        println!("Press [Esc] to cancel at any time.\n");
-        
-        for (i, line) in lines.iter().enumerate() {
-            // Check for Esc key to cancel
-            if poll(Duration::from_millis(10)).unwrap_or(false) {
-                if let Ok(Event::Key(key)) = read() {
-                    if key.code == KeyCode::Esc {
-                        println!("\n⚠ Cancelled by user.");
-                        println!("\nReturning to file selection...");
-                        thread::sleep(Duration::from_secs(CANCEL_DELAY_SECS));
-                        return;
-                    }
+    /// Move a fully sent file to the archive and take it off the list.
+    fn archive_sent(&mut self, app: &mut App, file: &TextFile) {
+        match files::scan_directory().and_then(|dir| archive::archive(file, &dir)) {
+            Ok(_) => {
+                app.remove_file(&file.path);
+                self.health.invalidate(&file.path);
+                app.set_notice(lang::format("notice.archived", &[("name", &file.name), ("folder", &ARCHIVE_DIRECTORY)]));
+            }
+            Err(e) => {
+                log(&e);
+                app.set_error(lang::format("error.cant_archive", &[("error", &e)]));
+            }
+        }
+    }
+
+    /// Import a zip message pack: ask for the archive and a subdirectory,
+    /// extract it and highlight the new files once the list is refreshed.
+    fn import_pack(&mut self, app: &mut App, worker: &mut FsWorker) -> Result<(), String> {
+        if self.config.read_only {
+            app.set_error(lang::format("error.cant_import", &[("reason", &lang::text("error.read_only"))]));
+            return Ok(());
+        }
+        let scan_dir = files::scan_directory()?;
+        let Some(archive) = self.prompt(lang::text("prompt.import"), "")? else {
+            return Ok(());
+        };
+        if archive.trim().is_empty() {
+            return Ok(());
+        }
+        // Relative paths are taken from the scan directory, absolute ones as is
+        let archive = scan_dir.join(archive.trim());
+        let suggested = if self.config.recursive_discovery { default_subdirectory(&archive) } else { String::new() };
+        let Some(subdir) = self.prompt(lang::text("prompt.subfolder"), &suggested)? else {
+            return Ok(());
+        };
+        let Some(relative) = safe_relative_path(subdir.trim()) else {
+            app.set_error(lang::format("error.not_a_folder", &[("path", &subdir.trim())]));
+            return Ok(());
+        };
+
+        match pack::import(&archive, &scan_dir, &relative, |path| self.ask_collision(path)) {
+            Ok(report) => {
+                let mut notice = report.summary();
+                if !relative.as_os_str().is_empty() && !self.config.recursive_discovery {
+                    notice.push_str(lang::text("notice.import_subfolders"));
                 }
+                app.set_notice(notice);
+                app.set_highlighted(&report.imported);
+                worker.refresh(ScanOptions::from(&self.config));
+                app.set_scanning(true);
             }
-            
-            // Calculate width for consistent formatting
-            let width = total.to_string().len();
-            println!(
-                "[{:>width$}/{:>width$}] Sending: {}",
-                i + 1,
-                total,
-                truncate_line(line, 50),
-                width = width
+            Err(e) => app.set_error(e),
+        }
+        Ok(())
+    }
+
+    /// Export the marked files, or the selected one, to a prompted .zip or
+    /// .md path (relative to the scan directory).
+    fn export_pack(&mut self, app: &mut App) -> Result<(), String> {
+        let files = app.marked_or_selected();
+        if files.is_empty() {
+            return Ok(());
+        }
+        let scan_dir = files::scan_directory()?;
+        let label = lang::format("prompt.export", &[("count", &files.len())]);
+        let Some(output) = self.prompt(&label, "pack.zip")? else {
+            return Ok(());
+        };
+        if output.trim().is_empty() {
+            return Ok(());
+        }
+        let output = scan_dir.join(output.trim());
+        if output.exists() {
+            let name = output.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if !self.confirm(&lang::format("prompt.overwrite", &[("name", &truncate_line(&name, 40))])) {
+                return Ok(());
+            }
+        }
+
+        let count = files.len();
+        match pack::export(&files, &output) {
+            Ok(size) => {
+                app.set_notice(lang::format("notice.exported", &[("count", &count), ("path", &output.display()), ("size", &format_size(size))]));
+                app.clear_marks();
+            }
+            Err(e) => app.set_error(e),
+        }
+        Ok(())
+    }
+
+    /// Ask a yes/no question on the status row.
+    fn confirm(&mut self, question: &str) -> bool {
+        let (_, height) = screen_size();
+        self.confirm_at(height.saturating_sub(5), question)
+    }
+
+    /// [`Self::confirm`] on row `y`.
+    fn confirm_at(&mut self, y: u16, question: &str) -> bool {
+        let _ = execute!(
+            self.stdout,
+            MoveTo(0, y),
+            Clear(ClearType::CurrentLine),
+            SetForegroundColor(Color::Yellow),
+            Print(format!(" {} ", question)),
+            ResetColor
+        );
+        loop {
+            if let Ok(Event::Key(key)) = read() {
+                if keys::accept(&key) {
+                    return matches!(key.code, KeyCode::Char('y' | 'Y'));
+                }
+            }
+        }
+    }
+
+    /// With the game guard on, check that `targets` are the in-game window
+    /// and otherwise ask whether to send anyway. Sets the reason as the
+    /// error when the send shouldn't go ahead.
+    fn guard_allows(&mut self, app: &mut App, targets: &[String]) -> bool {
+        let Some(problem) = self.config.game_guard.then(|| game_guard(targets)).flatten() else {
+            return true;
+        };
+        log(&format!("Game guard: {}", problem));
+        if self.confirm(&lang::format("prompt.send_anyway", &[("problem", &truncate_line(&problem, 60))])) {
+            log("Game guard overridden");
+            return true;
+        }
+        app.set_error(lang::format("error.game_guard", &[("problem", &problem)]));
+        false
+    }
+
+    /// Ask before sending `file` again if it was sent from the list less
+    /// than `resend_guard_secs` ago.
+    fn resend_allowed(&mut self, app: &App, file: &TextFile) -> bool {
+        let window = Duration::from_secs(self.config.resend_guard_secs);
+        let Some((ago, delivered)) = app.recent_send(file, window) else {
+            return true;
+        };
+        log(&format!("'{}' was sent {}s ago", file.name, ago.as_secs()));
+        let question = lang::format("prompt.resend", &[("ago", &format_ago(ago)), ("count", &delivered)]);
+        if self.confirm(&question) {
+            return true;
+        }
+        log("Repeated send declined");
+        false
+    }
+
+    /// Viewer label for a line that is over the game's message limit:
+    /// how many messages it is split into, or that it will be refused.
+    fn long_line_label(&self, text: &str) -> Option<(String, Color)> {
+        let max = self.config.char_limit().filter(|&max| text.chars().count() > max)?;
+        Some(if self.config.split_long_lines {
+            (lang::format("viewer.split", &[("parts", &message_parts(text, Some(max)).len())]), Color::Yellow)
+        } else {
+            (lang::format("viewer.too_long", &[("max", &max)]), Color::Red)
+        })
+    }
+
+    /// How long until the selected file (one line without a selection)
+    /// fits the chat limit budget of every window it goes to.
+    fn cooldown(&self, app: &App) -> Option<Duration> {
+        let (targets, needed) = match app.selection() {
+            Some(file) => (file.targets(&self.window_titles), message_count(&file.lines, self.config.split_long_lines, self.config.char_limit()) as u64),
+            None => (self.window_titles.clone(), 1),
+        };
+        let limit = ChatLimit::from(&self.config);
+        let now = Instant::now();
+        targets.iter().filter_map(|t| app.cooldown().ready_in(t, needed, limit, now)).max()
+    }
+
+    /// Ask before sending `file` while the chat limit cooldown still runs.
+    fn cooldown_allows(&mut self, app: &App, file: &TextFile) -> bool {
+        let limit = ChatLimit::from(&self.config);
+        let needed = message_count(&file.lines, self.config.split_long_lines, self.config.char_limit()) as u64;
+        let now = Instant::now();
+        let targets = file.targets(&self.window_titles);
+        let Some(wait) = targets.iter().filter_map(|t| app.cooldown().ready_in(t, needed, limit, now)).max() else {
+            return true;
+        };
+        let left = targets.iter().map(|t| app.cooldown().remaining(t, limit, now)).min().unwrap_or(limit.messages);
+        log(&format!("'{}' needs {} message(s), {} left under the chat limit", file.name, needed, left));
+        let question =
+            lang::format("prompt.cooldown", &[("needed", &needed), ("left", &left), ("secs", &seconds_left(wait))]);
+        if self.confirm(&question) {
+            log("Sending during the chat limit cooldown");
+            return true;
+        }
+        log("Send during the chat limit cooldown declined");
+        false
+    }
+
+    /// If the last run was cut off sending `file`, ask whether to pick up
+    /// where it stopped. Returns the zero-based line to start from. The
+    /// offer is made once either way, and not at all if the file's line
+    /// count changed since.
+    fn resume_point(&mut self, file: &TextFile) -> Option<usize> {
+        let interrupted = self.interrupted.take_if(|i| i.source == file.path)?;
+        if !interrupted.is_resumable(file.lines.len()) {
+            log(&format!("'{}' changed since the interrupted send, not offering to resume", file.name));
+            return None;
+        }
+        let from = interrupted.resume_from().min(file.lines.len());
+        let question = lang::format("prompt.resume", &[("name", &file.name), ("line", &(from + 1))]);
+        if self.confirm(&question) {
+            log(&format!("Resuming '{}' from line {}", file.name, from + 1));
+            return Some(from);
+        }
+        log("Resume declined, sending the whole file");
+        None
+    }
+
+    /// Show the send options for `file`, starting from the ones last picked
+    /// for it, and return the choices to send with. None on Esc. Without
+    /// the overlay (see `send_options_prompt`) the remembered or default
+    /// choices are used as they are.
+    fn ask_send_options(&mut self, app: &mut App, file: &TextFile) -> Option<SendChoices> {
+        let defaults = app.send_choices(file, SendChoices::from(&self.config));
+        if !self.config.send_options_prompt {
+            return Some(defaults);
+        }
+        let (_, height) = screen_size();
+        let layout = Layout::current();
+        let repeats = repeated_lines(&file.lines).len();
+        let mut overlay = OptionsOverlay::new(defaults);
+        // Title and hint around the rows, ending on the status row
+        let top = height.saturating_sub(5 + options::FIELDS.len() as u16 + 1);
+        loop {
+            let title = lang::format("options.title", &[("name", &file.name)]);
+            let _ = execute!(
+                self.stdout,
+                MoveTo(0, top),
+                Clear(ClearType::CurrentLine),
+                SetForegroundColor(Color::Yellow),
+                Print(layout.fit(&format!(" {}", title), layout.width)),
+                ResetColor
             );
+            for (i, (label, value)) in overlay.rows(repeats).into_iter().enumerate() {
+                let selected = i == overlay.selected();
+                let marker = if selected { "▶" } else { " " };
+                let _ = execute!(
+                    self.stdout,
+                    MoveTo(0, top + 1 + i as u16),
+                    Clear(ClearType::CurrentLine),
+                    SetForegroundColor(if selected { Color::Cyan } else { Color::White }),
+                    Print(format!(" {} {:<28} ◀ {} ▶", marker, label, value)),
+                    ResetColor
+                );
+            }
+            let _ = execute!(
+                self.stdout,
+                MoveTo(0, top + 1 + options::FIELDS.len() as u16),
+                Clear(ClearType::CurrentLine),
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!(" {}", lang::text("options.hint"))),
+                ResetColor
+            );
+            let Ok(Event::Key(key)) = read() else {
+                continue;
+            };
+            if !keys::accept(&key) {
+                continue;
+            }
+            match overlay.key(key.code) {
+                Outcome::Open => {}
+                Outcome::Send(choices) => {
+                    app.set_send_choices(file, choices);
+                    log(&format!("Send options for '{}': {:?}", file.name, choices));
+                    return Some(choices);
+                }
+                Outcome::Cancel => {
+                    log("Send options closed without sending");
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Ask for the current game time before sending timed lines, if the
+    /// setting is on. Returns None (with an error set for bad input) if the
+    /// send should not go ahead, Some(None) to count from the send start.
+    fn ask_game_clock(&mut self, app: &mut App, lines: &[Line]) -> Result<Option<Option<GameClock>>, String> {
+        if !self.config.ask_game_time || lines.iter().all(|l| l.at.is_none()) {
+            return Ok(Some(None));
+        }
+        let Some(input) = self.prompt(lang::text("prompt.game_time"), "")? else {
+            return Ok(None);
+        };
+        if input.trim().is_empty() {
+            return Ok(Some(None));
+        }
+        match parse_clock(&input) {
+            Some(time) => Ok(Some(Some(GameClock::new(time)))),
+            None => {
+                app.set_error(lang::format("error.not_game_time", &[("input", &input.trim())]));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Read a line of text on the status row. Returns None on Esc.
+    fn prompt(&mut self, label: &str, initial: &str) -> Result<Option<String>, String> {
+        let (_, height) = screen_size();
+        self.prompt_at(height.saturating_sub(5), label, initial)
+    }
+
+    /// [`Self::prompt`] on row `y`.
+    fn prompt_at(&mut self, y: u16, label: &str, initial: &str) -> Result<Option<String>, String> {
+        self.prompt_with_history(y, label, initial, &[])
+    }
+
+    /// [`Self::prompt_at`] where Up and Down step through `history`
+    /// (oldest first), coming back to the typed text past the newest entry.
+    fn prompt_with_history(&mut self, y: u16, label: &str, initial: &str, history: &[String]) -> Result<Option<String>, String> {
+        let mut input = initial.to_string();
+        let mut draft = input.clone();
+        let mut recalled: Option<usize> = None;
+        loop {
+            execute!(
+                self.stdout,
+                MoveTo(0, y),
+                Clear(ClearType::CurrentLine),
+                SetForegroundColor(Color::Yellow),
+                Print(format!(" {}", label)),
+                ResetColor,
+                Print(&input),
+                SetForegroundColor(Color::DarkGrey),
+                Print("█"),
+                ResetColor
+            )
+            .map_err(|e| lang::format("error.render", &[("error", &e)]))?;
+            if let Ok(Event::Key(key)) = read() {
+                if !keys::accept(&key) {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Enter => return Ok(Some(input)),
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) => input.push(c),
+                    KeyCode::Up | KeyCode::Down if !history.is_empty() => {
+                        if recalled.is_none() {
+                            draft = input.clone();
+                        }
+                        recalled = recall(history.len(), recalled, key.code == KeyCode::Up);
+                        input = recalled.map_or_else(|| draft.clone(), |i| history[i].clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
 
-            match send_text(line, &self.window_title) {
-                Ok(()) => {
-                    thread::sleep(Duration::from_millis(NEXT_LINE_DELAY_MS));
+    /// The version of `file` to send. If it changed on disk since it was
+    /// loaded, the new version is read and, if its messages differ, a
+    /// summary of the changes is shown with the choice of sending the new
+    /// version or the one loaded. None if cancelled or unreadable.
+    fn version_to_send(&mut self, app: &mut App, file: TextFile) -> Option<TextFile> {
+        if !edit::changed_on_disk(&file) {
+            return Some(file);
+        }
+        let Some(updated) = TextFile::from_path(file.path.clone()) else {
+            log(&format!("'{}' changed on disk and couldn't be reloaded", file.name));
+            app.set_error(lang::format("error.changed_unreadable", &[("name", &file.name)]));
+            return None;
+        };
+        let mut updated = TextFile { name: file.name.clone(), tags: file.tags.clone(), ..updated };
+        let diff = diff_lines(&file.lines, &updated.lines);
+        if diff.is_empty() {
+            updated.mark_reloaded(LoadReason::SendReload);
+            app.replace_file(updated.clone());
+            return Some(updated);
+        }
+        log(&format!("'{}' changed on disk before sending: {}", file.name, diff.summary()));
+        match self.ask_changed_version(&diff) {
+            Some(true) => {
+                updated.mark_reloaded(LoadReason::SendReload);
+                app.replace_file(updated.clone());
+                Some(updated)
+            }
+            Some(false) => Some(file),
+            None => {
+                log("Send of changed file cancelled");
+                None
+            }
+        }
+    }
+
+    /// Show the changes to a file on the status rows and ask whether to
+    /// send the new version (true) or the one loaded (false). None on Esc.
+    fn ask_changed_version(&mut self, diff: &LineDiff) -> Option<bool> {
+        let (_, height) = screen_size();
+        let layout = Layout::current();
+        let mut first = format!(" {}", lang::format("prompt.changed", &[("summary", &diff.summary())]));
+        if let Some((index, old, new)) = &diff.first_change {
+            let change = match (old, new) {
+                (Some(old), Some(new)) => format!("\"{}\" → \"{}\"", old, new),
+                (None, Some(new)) => format!("+ \"{}\"", new),
+                (Some(old), None) => format!("- \"{}\"", old),
+                (None, None) => String::new(),
+            };
+            first.push_str(&lang::format("prompt.changed_line", &[("line", &(index + 1)), ("change", &change)]));
+        }
+        let _ = execute!(
+            self.stdout,
+            MoveTo(0, height.saturating_sub(6)),
+            Clear(ClearType::CurrentLine),
+            SetForegroundColor(Color::Yellow),
+            Print(layout.fit(&first, layout.width)),
+            MoveTo(0, height.saturating_sub(5)),
+            Clear(ClearType::CurrentLine),
+            Print(format!(" {} ", lang::text("prompt.new_or_original"))),
+            ResetColor
+        );
+        loop {
+            if let Ok(Event::Key(key)) = read() {
+                if !keys::accept(&key) {
+                    continue;
                 }
-                Err(e) => {
-                    println!("❌ Error: {}", e);
-                    println!("Stopping. Make sure the target window is open.");
-                    thread::sleep(Duration::from_secs(USER_READ_DELAY_SECS));
-                    return;
+                match key.code {
+                    KeyCode::Char('n' | 'N') => return Some(true),
+                    KeyCode::Char('o' | 'O') => return Some(false),
+                    KeyCode::Esc => return None,
+                    _ => {}
                 }
             }
         }
+    }
 
-        println!("\n✅ Done! Sent {} messages.", total);
-        println!("\nReturning to file selection...");
-        thread::sleep(Duration::from_secs(USER_READ_DELAY_SECS));
+    /// Ask on the status row which chat an ad-hoc line goes to. None on Esc.
+    fn ask_chat(&mut self) -> Option<ChatScope> {
+        let (_, height) = screen_size();
+        let _ = execute!(
+            self.stdout,
+            MoveTo(0, height.saturating_sub(5)),
+            Clear(ClearType::CurrentLine),
+            SetForegroundColor(Color::Yellow),
+            Print(format!(" {} ", lang::text("prompt.chat"))),
+            ResetColor
+        );
+        loop {
+            if let Ok(Event::Key(key)) = read() {
+                if !keys::accept(&key) {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('a' | 'A') | KeyCode::Enter => return Some(ChatScope::All),
+                    KeyCode::Char('t' | 'T') => return Some(ChatScope::Team),
+                    KeyCode::Esc => return None,
+                    _ => {}
+                }
+            }
+        }
     }
 
-    /// View file contents in a scrollable viewer.
-    fn view_file(&mut self, file_name: &str, lines: &[String]) -> Result<(), String> {
-        let mut scroll_offset: usize = 0;
-        let mut last_scroll_offset: usize = usize::MAX; // Force initial render
-        let (_, term_height) = terminal::size().unwrap_or((80, 24));
-        let visible_lines = (term_height as usize).saturating_sub(6);
-        
-        // Initial full render with header
-        execute!(
+    /// Ask for one line and send it through the normal send path, without
+    /// a file. With `save_adhoc_lines` on it is also appended to the
+    /// scratch file in the scan directory.
+    fn send_adhoc(&mut self, app: &mut App, worker: &mut FsWorker) -> Result<(), String> {
+        if self.config.read_only {
+            app.set_error(lang::format("error.cant_send", &[("reason", &lang::text("error.read_only"))]));
+            return Ok(());
+        }
+        if !arming::is_armed() {
+            app.set_error(lang::format("error.cant_send", &[("reason", &lang::text("error.disarmed"))]));
+            return Ok(());
+        }
+        let (_, height) = screen_size();
+        let history = app.adhoc_history().to_vec();
+        let Some(text) = self.prompt_with_history(height.saturating_sub(5), lang::text("prompt.say"), "", &history)? else {
+            return Ok(());
+        };
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return Ok(());
+        }
+        app.push_adhoc(&text);
+        let Some(chat) = self.ask_chat() else {
+            return Ok(());
+        };
+        let targets = self.window_titles.clone();
+        if !targets.iter().any(|t| is_window_running(t)) {
+            app.set_error(not_running_error(&targets));
+            return Ok(());
+        }
+        if !self.guard_allows(app, &targets) {
+            return Ok(());
+        }
+
+        if self.config.save_adhoc_lines {
+            match files::scan_directory().and_then(|dir| files::append_line(&dir, SCRATCH_FILE_NAME, &text)) {
+                Ok(path) => {
+                    log(&format!("Ad-hoc line saved to {}", path.display()));
+                    worker.refresh(ScanOptions::from(&self.config));
+                    app.set_scanning(true);
+                }
+                Err(e) => app.set_error(e),
+            }
+        }
+
+        log(&format!("Sending ad-hoc line ({} chars)", text.chars().count()));
+        self.cleanup()?;
+        print!("\x1B[2J\x1B[1;1H");
+        let lines = [Line::new(text)];
+        if self.config.slash_commands && lines[0].is_command() {
+            println!("{}\n", lang::format("send.adhoc_command", &[("text", &lines[0].text)]));
+        } else {
+            let id = if chat == ChatScope::Team { "send.adhoc_team" } else { "send.adhoc_all" };
+            println!("{}\n", lang::format(id, &[("text", &lines[0].text)]));
+        }
+        self.send_all_lines("Ad-hoc line", &lines, &targets, SendOptions::new(SendChoices { chat, ..SendChoices::from(&self.config) }), |event| {
+            app.record_send_event(event)
+        });
+        self.init()
+    }
+
+    /// Send one line of the selected file picked at random, weighted by the
+    /// lines' `{w=N}`. Lines marked not to be sent are left out.
+    fn send_random(&mut self, app: &mut App) -> Result<(), String> {
+        let Some(file) = app.selection().cloned() else {
+            return Ok(());
+        };
+        if self.config.read_only {
+            app.set_error(lang::format("error.cant_send", &[("reason", &lang::text("error.read_only"))]));
+            return Ok(());
+        }
+        if !arming::is_armed() {
+            app.set_error(lang::format("error.cant_send", &[("reason", &lang::text("error.disarmed"))]));
+            return Ok(());
+        }
+        let Some(index) = weights::pick_line(&file.lines, &app.excluded_lines(&file), &mut Rng::from_time()) else {
+            app.set_error(lang::format("error.no_random_line", &[("name", &file.name)]));
+            return Ok(());
+        };
+        let targets = file.targets(&self.window_titles);
+        if !targets.iter().any(|t| is_window_running(t)) {
+            app.set_error(not_running_error(&targets));
+            return Ok(());
+        }
+        if !self.guard_allows(app, &targets) {
+            return Ok(());
+        }
+
+        // Picked on its own, the line doesn't wait for its delay or time
+        let line = Line { delay_ms: None, at: None, blank_before: 0, ..file.lines[index].clone() };
+        log(&format!("Sending random line {} of '{}' (weight {})", index + 1, file.name, line.weight));
+        self.cleanup()?;
+        print!("\x1B[2J\x1B[1;1H");
+        println!("{}\n", lang::format("send.random", &[("line", &(index + 1)), ("name", &file.name), ("text", &line.text)]));
+        self.send_all_lines("Random line", &[line], &targets, SendOptions::new(SendChoices::from(&self.config)), |event| {
+            app.record_send_event(event)
+        });
+        self.init()
+    }
+
+    /// Ask on the status row whether to overwrite, rename or skip an existing file.
+    fn ask_collision(&mut self, path: &std::path::Path) -> Collision {
+        let (_, height) = screen_size();
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let _ = execute!(
             self.stdout,
-            Clear(ClearType::All),
-            MoveTo(0, 0)
-        ).map_err(|e| e.to_string())?;
-        
-        // Static header (only rendered once)
+            MoveTo(0, height.saturating_sub(5)),
+            Clear(ClearType::CurrentLine),
+            SetForegroundColor(Color::Yellow),
+            Print(format!(" {} ", lang::format("prompt.collision", &[("name", &truncate_line(&name, 40))]))),
+            ResetColor
+        );
+        loop {
+            if let Ok(Event::Key(key)) = read() {
+                if !keys::accept(&key) {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('o' | 'O') => return Collision::Overwrite,
+                    KeyCode::Char('r' | 'R') => return Collision::Rename,
+                    KeyCode::Char('s' | 'S') | KeyCode::Esc => return Collision::Skip,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Type the configured ping text into team chat using the normal send
+    /// path, so a passing test send means a real send will work.
+    fn test_send(&mut self, app: &mut App) -> Result<(), String> {
+        if self.config.read_only {
+            app.set_error(lang::format("error.test_send", &[("reason", &lang::text("error.read_only"))]));
+            return Ok(());
+        }
+        if !arming::is_armed() {
+            app.set_error(lang::format("error.test_send", &[("reason", &lang::text("error.disarmed"))]));
+            return Ok(());
+        }
+        if !self.window_titles.iter().any(|t| is_window_running(t)) {
+            app.set_error(lang::format("error.test_send", &[("reason", &not_running_error(&self.window_titles))]));
+            return Ok(());
+        }
+
+        self.cleanup()?;
+        print!("\x1B[2J\x1B[1;1H");
+        println!("{}\n", lang::format("send.test", &[("text", &self.config.ping_text)]));
+        let lines = [Line::new(self.config.ping_text.clone())];
+        let targets = self.window_titles.clone();
+        let options = SendOptions::new(SendChoices { chat: ChatScope::Team, ..SendChoices::from(&self.config) });
+        self.send_all_lines("Test send", &lines, &targets, options, |_| {});
+        self.init()
+    }
+
+    /// Send all lines from the file `name` to `targets` (with cancel
+    /// support).
+    ///
+    /// Each progress row is re-printed in place once its outcome is known,
+    /// colored green (sent), yellow (skipped) or red (failed). Every session
+    /// event is also passed to `on_event` (used for statistics).
+    fn send_all_lines(
+        &mut self,
+        name: &str,
+        lines: &[Line],
+        targets: &[String],
+        options: SendOptions,
+        mut on_event: impl FnMut(&SendEvent),
+    ) {
+        let SendOptions { choices, game_clock, excluded, source } = options;
+        let total = lines.len();
+        let width = total.to_string().len();
+        let broadcast = targets.len() > 1;
+        let mut stdout = Output::new();
+
+        if choices.dry_run {
+            let _ = print_colored(&mut stdout, Color::Cyan, &format!("{}\n", lang::text("send.dry_run")));
+        }
+        if choices.manual_advance {
+            println!("{}\n", lang::text("send.manual_keys"));
+        } else {
+            println!("{}", lang::text("send.cancel_keys"));
+            if self.config.send_method == SendMethod::Auto {
+                println!("{}", lang::text("send.paste_keys"));
+            }
+            println!();
+        }
+
+        if self.config.check_fullscreen {
+            for title in targets {
+                let warning = window_display_mode(title).and_then(display_mode_warning);
+                if let Some(warning) = warning.filter(|_| warnings::report(&format!("display:fullscreen:{}", title))) {
+                    log(&format!("Fullscreen warning for '{}'", title));
+                    let _ = print_colored(&mut stdout, Color::Yellow, &format!("⚠ '{}' {}\n\n", title, warning));
+                }
+            }
+        }
+
+        // A minimized window is restored when focused; keys typed during the
+        // restore animation are lost, so either ask first or wait longer
+        for title in targets.iter().filter(|t| is_window_minimized(t)) {
+            if !self.config.prompt_if_minimized {
+                log(&format!("'{}' is minimized, waiting {} ms after restoring", title, self.config.restore_settle_delay_ms));
+                continue;
+            }
+            if !confirm_minimized(&mut stdout, title) {
+                log(&format!("Send to minimized '{}' declined", title));
+                press_any_key(&mut stdout);
+                return;
+            }
+        }
+
+        if let Some(risk) = file_mute_risk(lines, choices.speed, &self.config) {
+            log(&format!("Mute risk: {}", risk.label()));
+            let _ = print_colored(&mut stdout, Color::Yellow, &format!("{}\n\n", risk.label()));
+        }
+        let filter = CharFilter::from(&self.config);
+        if let Some(summary) = charfilter::summary(&filter, lines) {
+            log(&summary);
+            let _ = print_colored(&mut stdout, Color::Yellow, &format!("⚠ {}\n\n", summary));
+        }
+
+        if let Err(e) = hooks::pre_send(&self.config.pre_send_hook, name, lines.len(), targets) {
+            log(&e);
+            let _ = print_colored(&mut stdout, Color::Red, &format!("❌ {}\n", e));
+            press_any_key(&mut stdout);
+            return;
+        }
+
+        report::record_before_send(targets, &self.config);
+
+        // Caps Lock or a held Ctrl would corrupt every typed character; a
+        // dry run types nothing, so it leaves the keyboard alone
+        let keyboard = if choices.dry_run {
+            Ok(false)
+        } else {
+            check_keyboard_state(self.config.compensate_keyboard_state, |held| {
+                let _ = print_colored(
+                    &mut stdout,
+                    Color::Yellow,
+                    &format!("{}\n", lang::format("send.release", &[("keys", &held.join("+"))])),
+                );
+            })
+        };
+        let caps_was_on = match keyboard {
+            Ok(caps_was_on) => caps_was_on,
+            Err(e) => {
+                log(&format!("Keyboard check failed: {}", e));
+                let _ = print_colored(&mut stdout, Color::Red, &format!("❌ {}\n", e));
+                press_any_key(&mut stdout);
+                return;
+            }
+        };
+
+        // Get out of the way before the game window is focused
+        let minimized = !choices.dry_run && self.config.minimize_console_on_send && minimize_console_window();
+
+        let session = SendSession::new(lines, targets.to_vec(), choices.order)
+            .with_clock_start(game_clock.map(|c| c.now()).unwrap_or_default())
+            .with_delays(SendDelays::from(&self.config))
+            .with_choices(choices)
+            .with_busy_policy(BusyPolicy::from(&self.config))
+            .with_split_long_lines(self.config.split_long_lines)
+            .with_char_limit(self.config.char_limit())
+            .with_key_mode(KeyMode::from(&self.config))
+            .with_trace_input(self.config.trace_input)
+            .with_recording(self.config.record_sessions)
+            .with_max_lines_per_send(self.config.max_lines_per_send)
+            .with_slash_commands(self.config.slash_commands)
+            .with_window_lost_wait(Duration::from_secs(self.config.window_lost_wait_secs))
+            .with_send_method(self.config.send_method)
+            .with_typed_windows(paste::load())
+            .with_focus_delays(focus_quirks::load())
+            .with_char_retries(self.config.char_retry_limit)
+            .with_paste_check_hook(&self.config.paste_check_hook)
+            .with_verify_hook(&self.config.verify_hook)
+            .with_char_filter(filter)
+            .with_abbreviations(Abbreviations::from(&self.config))
+            .with_excluded_lines(excluded);
+        // A dry run types nothing, so there's nothing to resume
+        let session = match source.filter(|_| !choices.dry_run) {
+            Some(source) => session.with_journal(source),
+            None => session,
+        };
+        let started = Instant::now();
+        // Line the console was last brought back for, so keys reach it
+        let mut prompted: Option<usize> = None;
+        let (mut platform, mut dry_run) = (PlatformSender, DryRunSender::default());
+        let sender: &mut dyn LineSender = if choices.dry_run { &mut dry_run } else { &mut platform };
+        // The time spent sending doesn't count as idle
+        arming::touch();
+        let send_keys = SendKeys::start(self.config.flash_on_cancel);
+        let result = session.run(sender, |event| {
+            // The key watcher draws the cancel banner; keep it off our lines
+            let _frame = io::stdout().lock();
+            on_event(event);
+            if let Some(cue) = send_cue(event, self.config.send_sound) {
+                play_cue(cue);
+            }
+            match *event {
+                SendEvent::LineStarted { index, line, target, speed, remaining, .. } => {
+                    // A config edit mid-send only changes the delays; the
+                    // other settings apply from the next send
+                    if let Some(result) = self.config_watcher.as_mut().and_then(|w| w.poll()) {
+                        let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                        match apply_reload(&mut self.config, &mut self.config_path, result) {
+                            Ok(notice) => {
+                                let _ = print_colored(&mut stdout, Color::Cyan, &format!("{}\n", lang::format("send.reloaded", &[("notice", &notice)])));
+                                return Control::SetDelays(SendDelays::from(&self.config));
+                            }
+                            Err(e) => {
+                                let _ = print_colored(&mut stdout, Color::Yellow, &format!("⚠ {}\n", e));
+                            }
+                        }
+                    }
+
+                    // Esc while the last line was typed; don't start this one
+                    if cancel::is_cancelling() {
+                        return Control::Cancel;
+                    }
+                    // +/- change the speed
+                    if let Some(key) = send_keys.next() {
+                        let new_speed = match key.code {
+                            KeyCode::Char('+' | '=') => speed.faster(),
+                            KeyCode::Char('-') => speed.slower(),
+                            KeyCode::Char('t' | 'T') if self.config.send_method == SendMethod::Auto => {
+                                flag_paste_failed();
+                                speed
+                            }
+                            _ => speed,
+                        };
+                        if new_speed != speed {
+                            return Control::SetSpeed(new_speed);
+                        }
+                    }
+
+                    let row = progress_row(index, total, width, target, broadcast);
+                    let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                    print!("{} {}", row, lang::format("send.sending", &[("text", &truncate_line(&line.text, 50))]));
+                    let _ = print_colored(
+                        &mut stdout,
+                        Color::DarkGrey,
+                        &format!("  {}", lang::format("send.left", &[("speed", &speed.label()), ("left", &format_duration(remaining))])),
+                    );
+                    let _ = stdout.flush();
+                }
+                SendEvent::LineFinished { index, line, target, result, .. } => {
+                    let outcome = if result.is_ok() { LineOutcome::Sent } else { LineOutcome::Failed };
+                    let row = progress_row(index, total, width, target, broadcast);
+
+                    // Re-render the row now that its outcome is known
+                    let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                    let _ = print_colored(
+                        &mut stdout,
+                        outcome.color(),
+                        &format!("{} {} {}\n", row, outcome.symbol(), truncate_line(&line.text, 50)),
+                    );
+
+                    if let Err(e) = result {
+                        let _ = print_colored(&mut stdout, Color::Red, &format!(
+                            "\n{}\n{}\n",
+                            lang::format("send.failed_at", &[("line", &(index + 1)), ("text", &truncate_line(&line.text, 50))]),
+                            lang::format("send.error", &[("error", e)])
+                        ));
+                        if broadcast {
+                            println!("{}\n", lang::format("send.no_more", &[("target", &target)]));
+                        } else {
+                            println!("{}", lang::format("send.remaining", &[("count", &(total - index - 1))]));
+                            println!("{}", lang::format("send.stopping", &[("target", &target)]));
+                        }
+                    }
+                }
+                SendEvent::Waiting { index, line, target, at, left, .. } => {
+                    let row = progress_row(index, total, width, target, broadcast);
+                    let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                    print!("{} {}", row, lang::format("send.waiting", &[("at", &format_clock(at)), ("text", &truncate_line(&line.text, 40))]));
+                    let _ = print_colored(&mut stdout, Color::Magenta, &format!("  {}", lang::format("send.time_left", &[("left", &format_clock(left))])));
+                    let _ = stdout.flush();
+                }
+                SendEvent::WindowLost { index, line, target, left, .. } => {
+                    let row = progress_row(index, total, width, target, broadcast);
+                    let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                    print!("{} {}", row, truncate_line(&line.text, 40));
+                    let _ = print_colored(
+                        &mut stdout,
+                        Color::Yellow,
+                        &format!("  {}", lang::format("send.window_lost", &[("secs", &(left.as_secs_f64().ceil() as u64))])),
+                    );
+                    let _ = stdout.flush();
+                }
+                SendEvent::AwaitingAdvance { index, line, target, .. } => {
+                    // The game has focus after a line; take it back once so
+                    // the key reaches us. Sending re-focuses and re-checks
+                    // the game window.
+                    if prompted != Some(index) {
+                        prompted = Some(index);
+                        if !focus_console_window() {
+                            log("No console window to focus for manual advance");
+                        }
+                        let row = progress_row(index, total, width, target, broadcast);
+                        let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                        print!("{} {}", row, lang::format("send.next", &[("text", &truncate_line(&line.text, 40))]));
+                        let _ = print_colored(&mut stdout, Color::Cyan, &format!("  {}", lang::text("send.advance_keys")));
+                        let _ = stdout.flush();
+                    }
+                    if let Some(key) = send_keys.next() {
+                        match key.code {
+                            KeyCode::Char(' ') => return Control::Advance,
+                            KeyCode::Char('s' | 'S') => {
+                                let row = progress_row(index, total, width, target, broadcast);
+                                let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                                let skipped = LineOutcome::Skipped;
+                                let _ = print_colored(
+                                    &mut stdout,
+                                    skipped.color(),
+                                    &format!("{} {} {}\n", row, skipped.symbol(), truncate_line(&line.text, 50)),
+                                );
+                                return Control::Skip;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                SendEvent::MethodChosen { target, method, reason } => {
+                    let id = match method {
+                        SendMethod::Paste => "send.pasting",
+                        SendMethod::PostMessage => "send.posting",
+                        _ => "send.typing",
+                    };
+                    let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+                    let text = lang::format(id, &[("target", &target), ("reason", &reason)]);
+                    let _ = print_colored(&mut stdout, Color::Cyan, &format!("{}\n", text));
+                }
+                SendEvent::Finished { .. } => {}
+            }
+            // Until the session stops, the banner stays below the rows
+            if cancel::is_cancelling() {
+                cancel::draw_banner(false);
+            }
+            Control::Continue
+        });
+        drop(send_keys);
+
+        restore_keyboard_state(caps_was_on);
+        if minimized {
+            restore_console_window();
+        }
+        if self.config.refocus_console_after_send && !focus_console_window() {
+            log("No console window to refocus");
+        }
+
+        let report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                log(&format!("Send refused: {}", e));
+                let _ = print_colored(&mut stdout, Color::Red, &format!("❌ {}\n", e));
+                press_any_key(&mut stdout);
+                return;
+            }
+        };
+        // The cancel banner, or a progress line cut short
+        let _ = execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine));
+        if report.cancelled {
+            let text = match report.cancelled_by.as_deref() {
+                Some(requester) if requester != cancel::ESC_REQUESTER => {
+                    lang::format("send.cancelled_by", &[("requester", &requester)])
+                }
+                _ => lang::text("send.cancelled").to_string(),
+            };
+            let _ = print_colored(&mut stdout, Color::Yellow, &format!("{}\n", text));
+        }
+        let _ = render_send_summary(&mut stdout, &report, total);
+        save_paste_fallbacks(&mut stdout, &report);
+        if let Some(path) = &report.trace_file {
+            println!("{}", lang::format("send.trace", &[("path", &path.display())]));
+        }
+        if let Some(path) = &report.session_file {
+            println!("{}", lang::format("send.recording", &[("path", &path.display())]));
+        }
+        if self.config.notify_when_finished && started.elapsed().as_secs() >= NOTIFY_MIN_SEND_SECS {
+            let (title, text) = finish_notice(name, &report, total);
+            notify(&title, &text);
+        }
+        if let Err(e) = hooks::post_send(&self.config.post_send_hook, name, &report, started.elapsed()) {
+            log(&e);
+            let _ = print_colored(&mut stdout, Color::Yellow, &format!("⚠ {}\n", e));
+            notify("MadTyping", &e);
+        }
+
+        press_any_key(&mut stdout);
+    }
+
+    /// Show key bindings and the about section (version, build, environment).
+    fn help_screen(&mut self) -> Result<(), String> {
+        let scan_dir = match files::chosen_scan_directory() {
+            Some(dir) => Ok(dir.describe()),
+            None => files::scan_directory().map(|p| p.display().to_string()),
+        }
+        .unwrap_or_else(|e| lang::format("about.unavailable", &[("error", &e)]));
+
+        let keys = [
+            ("↑ ↓", "help.navigate"),
+            ("← →", "help.columns"),
+            ("Enter", "help.send"),
+            (":", "help.adhoc"),
+            ("Tab", "help.view"),
+            ("F1", "help.help"),
+            ("F2", "help.settings"),
+            ("F3", "help.tag"),
+            ("F4", "help.import"),
+            ("Insert", "help.mark"),
+            ("Ctrl+D", "help.compare"),
+            ("Ctrl+R", "help.random"),
+            ("Del", "help.hide_warning"),
+            ("Ctrl+A", "help.arm"),
+            ("F6", "help.export"),
+            ("F7", "help.duplicate"),
+            ("F8", "help.manual"),
+            ("F9", "help.stats"),
+            ("F10", "help.skipped"),
+            ("F5", "help.refresh"),
+            ("/", "help.search"),
+            (lang::text("help.type_key"), "help.type"),
+            ("Esc", "help.esc"),
+        ];
+        let about = [
+            ("about.version", VERSION.to_string()),
+            ("about.commit", GIT_HASH.to_string()),
+            ("about.built", BUILD_DATE.to_string()),
+            ("about.config", match &self.config_path {
+                Some(path) => path.display().to_string(),
+                None => lang::format("about.defaults", &[("file", &CONFIG_FILE_NAME)]),
+            }),
+            ("about.scan_dir", scan_dir),
+            ("about.extensions", files::supported_extensions().join(", ")),
+            ("about.data_dir", paths::data_directory()
+                .map(|dir| dir.describe())
+                .unwrap_or_else(|e| lang::format("about.unavailable", &[("error", &e)]))),
+            ("about.log", logging::log_path()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| lang::text("about.off").to_string())),
+            ("about.target", self.window_titles.join(", ")),
+            ("about.profile", GameProfile::NAMES[self.config.game_profile as usize].to_string()),
+            ("about.terminal", probe::active().describe()),
+        ];
+
+        execute!(self.stdout, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+        self.render_title(Layout::current(), lang::text("help.title")).map_err(|e| e.to_string())?;
+
+        let mut y = 4;
+        for (key, action) in keys {
+            execute!(
+                self.stdout,
+                MoveTo(0, y),
+                SetForegroundColor(Color::Yellow),
+                Print(format!("   {:<8}", key)),
+                ResetColor,
+                Print(lang::text(action))
+            ).map_err(|e| e.to_string())?;
+            y += 1;
+        }
+
+        y += 1;
         execute!(
             self.stdout,
+            MoveTo(0, y),
             SetForegroundColor(Color::Cyan),
-            Print("═══════════════════════════════════════════════════════════════\n"),
-            Print(format!("                   Viewing: {}\n", file_name)),
-            Print("═══════════════════════════════════════════════════════════════\n"),
+            Print(format!(" {}", lang::text("about.title"))),
             ResetColor
         ).map_err(|e| e.to_string())?;
+        y += 1;
+        for (label, value) in about {
+            execute!(
+                self.stdout,
+                MoveTo(0, y),
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("   {:<10}", lang::text(label))),
+                ResetColor,
+                Print(value)
+            ).map_err(|e| e.to_string())?;
+            y += 1;
+        }
+
+        self.render_footer_hints(&[(lang::text("hint.help_back"), Color::Green)]).map_err(|e| e.to_string())?;
+        self.stdout.flush().map_err(|e| e.to_string())?;
+
+        loop {
+            if let Ok(Event::Key(key)) = read() {
+                if keys::accept(&key) && matches!(key.code, KeyCode::Esc | KeyCode::F(1)) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Show the settings screen where toggles can be switched on and off
+    /// and numeric settings adjusted. `lines` (the selected file) is used
+    /// to show its chat-mute risk as the delays change.
+    fn settings_screen(&mut self, lines: Option<&[Line]>) -> Result<SettingsExit, String> {
+        let mut selected: usize = 0;
+        let rows = setting_rows();
+
+        execute!(self.stdout, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+        self.render_title(Layout::current(), lang::text("settings.title")).map_err(|e| e.to_string())?;
+
+        let send_hint = self.send_hint_color();
+        self.render_footer_hints(&[
+            (lang::text("hint.navigate"), Color::Green),
+            (lang::text("hint.settings_back"), Color::Green),
+            (lang::text("hint.toggle"), Color::Green),
+            (lang::text("hint.adjust"), Color::Green),
+            (lang::text("hint.test_send"), send_hint),
+            (lang::text("hint.forget_paste"), Color::Green),
+            (lang::text("hint.focus_delays"), Color::Green),
+            (lang::text("hint.hidden_warnings"), Color::Green),
+        ])
+        .map_err(|e| e.to_string())?;
+
+        let mut notice = String::new();
+        // The learned focus delays are listed on the first [L], forgotten on the second
+        let mut focus_delays_shown = false;
+        loop {
+            let content_start_y = 4;
+            for (i, row) in rows.iter().enumerate() {
+                let marker = if i == selected { "►" } else { " " };
+                let text = match row {
+                    SettingRow::Toggle(toggle) => {
+                        let state = if *(toggle.field)(&mut self.config) { "[on] " } else { "[off]" };
+                        format!(" {} {} {} ", marker, state, toggle.label)
+                    }
+                    SettingRow::Choice(choice) => {
+                        let value = choice.options[(choice.get)(&self.config)];
+                        format!(" {} {:>5} {} ", marker, value, choice.label)
+                    }
+                    SettingRow::Limit(limit) => {
+                        format!(" {} {:>5} {} ", marker, *(limit.field)(&mut self.config), limit.label)
+                    }
+                };
+                execute!(
+                    self.stdout,
+                    MoveTo(0, (content_start_y + i) as u16),
+                    Clear(ClearType::CurrentLine),
+                ).map_err(|e| e.to_string())?;
+                if i == selected {
+                    execute!(self.stdout, SetBackgroundColor(Color::DarkBlue), SetForegroundColor(Color::White))
+                        .map_err(|e| e.to_string())?;
+                }
+                execute!(self.stdout, Print(text), ResetColor).map_err(|e| e.to_string())?;
+            }
+
+            let description = match rows[selected] {
+                SettingRow::Toggle(toggle) => toggle.description.to_string(),
+                SettingRow::Choice(choice) => format!("{} ({})", choice.description, choice.options.join(" / ")),
+                SettingRow::Limit(limit) => format!("{} ({} to {})", limit.key, limit.min, limit.max),
+            };
+            let risk = lines.and_then(|lines| file_mute_risk(lines, Speed::NORMAL, &self.config));
+            execute!(
+                self.stdout,
+                MoveTo(0, (content_start_y + rows.len() + 1) as u16),
+                Clear(ClearType::CurrentLine),
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("   {}", description)),
+                MoveTo(0, (content_start_y + rows.len() + 2) as u16),
+                Clear(ClearType::CurrentLine),
+                SetForegroundColor(Color::Yellow),
+                Print(risk.map(|r| format!("   {}", lang::format("settings.selected_file", &[("risk", &r.label())]))).unwrap_or_default()),
+                MoveTo(0, (content_start_y + rows.len() + 3) as u16),
+                Clear(ClearType::CurrentLine),
+                SetForegroundColor(Color::Cyan),
+                Print(&notice),
+                ResetColor
+            ).map_err(|e| e.to_string())?;
+            self.stdout.flush().map_err(|e| e.to_string())?;
+
+            if let Ok(Event::Key(key)) = read() {
+                if !keys::accept(&key) {
+                    continue;
+                }
+                let forget_focus_delays = std::mem::take(&mut focus_delays_shown);
+                match key.code {
+                    KeyCode::Esc | KeyCode::F(2) => return Ok(SettingsExit::Back),
+                    KeyCode::Char('t' | 'T') => return Ok(SettingsExit::TestSend),
+                    KeyCode::Char('w' | 'W') => return Ok(SettingsExit::HiddenWarnings),
+                    KeyCode::Char('p' | 'P') => {
+                        notice = match paste::forget() {
+                            Ok(0) => format!("   {}", lang::text("settings.no_fallbacks")),
+                            Ok(count) => {
+                                log(&format!("Forgot the paste fallback of {} window(s)", count));
+                                format!("   {}", lang::format("settings.forgot_fallbacks", &[("count", &count)]))
+                            }
+                            Err(e) => format!("   {}", e),
+                        };
+                    }
+                    KeyCode::Char('l' | 'L') if forget_focus_delays => {
+                        notice = match focus_quirks::forget() {
+                            Ok(count) => {
+                                log(&format!("Forgot the learned focus delay of {} window(s)", count));
+                                format!("   {}", lang::format("settings.forgot_focus_delays", &[("count", &count)]))
+                            }
+                            Err(e) => format!("   {}", e),
+                        };
+                    }
+                    KeyCode::Char('l' | 'L') => {
+                        let delays = focus_quirks::load();
+                        focus_delays_shown = !delays.is_empty();
+                        notice = if delays.is_empty() {
+                            format!("   {}", lang::text("settings.no_focus_delays"))
+                        } else {
+                            format!("   {}", lang::format("settings.focus_delays", &[("list", &focus_quirks::describe(&delays))]))
+                        };
+                    }
+                    KeyCode::Up => {
+                        selected = selected.checked_sub(1).unwrap_or(rows.len() - 1);
+                    }
+                    KeyCode::Down => {
+                        selected = (selected + 1) % rows.len();
+                    }
+                    KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right => {
+                        let back = key.code == KeyCode::Left;
+                        match rows[selected] {
+                            SettingRow::Toggle(toggle) => {
+                                let field = (toggle.field)(&mut self.config);
+                                *field = !*field;
+                                log(&format!("Setting '{}' changed to {}", toggle.label, *field));
+                            }
+                            SettingRow::Choice(choice) => {
+                                let count = choice.options.len();
+                                let current = (choice.get)(&self.config);
+                                let next = if back { (current + count - 1) % count } else { (current + 1) % count };
+                                (choice.set)(&mut self.config, next);
+                                log(&format!("Setting '{}' changed to {}", choice.key, choice.options[next]));
+                            }
+                            SettingRow::Limit(limit) if matches!(key.code, KeyCode::Left | KeyCode::Right) => {
+                                let field = (limit.field)(&mut self.config);
+                                *field = if back {
+                                    field.saturating_sub(limit.step).max(limit.min)
+                                } else {
+                                    (*field + limit.step).min(limit.max)
+                                };
+                                log(&format!("Setting '{}' changed to {}", limit.key, *field));
+                            }
+                            SettingRow::Limit(_) => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Show session and lifetime statistics; [R] resets the lifetime stats.
+    fn stats_screen(&mut self, app: &mut App) -> Result<(), String> {
+        let mut confirming = false;
+
+        loop {
+            execute!(self.stdout, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+            self.render_title(Layout::current(), lang::text("stats.title")).map_err(|e| e.to_string())?;
+            execute!(
+                self.stdout,
+                MoveTo(0, 4),
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("   {:<14}{:>14}{:>14}", "", lang::text("stats.session"), lang::text("stats.lifetime"))),
+                ResetColor
+            ).map_err(|e| e.to_string())?;
+
+            let session = app.session_stats().rows();
+            let lifetime = app.lifetime_stats().rows();
+            for (i, ((label, now), (_, total))) in session.iter().zip(lifetime.iter()).enumerate() {
+                execute!(
+                    self.stdout,
+                    MoveTo(0, 5 + i as u16),
+                    SetForegroundColor(Color::Yellow),
+                    Print(format!("   {:<14}", label)),
+                    ResetColor,
+                    Print(format!("{:>14}{:>14}", now, total))
+                ).map_err(|e| e.to_string())?;
+            }
+
+            if !PERSIST_LIFETIME_STATS {
+                execute!(
+                    self.stdout,
+                    MoveTo(0, 12),
+                    SetForegroundColor(Color::DarkGrey),
+                    Print(format!("   {}", lang::text("stats.not_saved"))),
+                    ResetColor
+                ).map_err(|e| e.to_string())?;
+            }
+
+            let footer: &[(&str, Color)] = if confirming {
+                &[(lang::text("hint.reset_confirm"), Color::Yellow), (lang::text("hint.no"), Color::Yellow)]
+            } else {
+                &[(lang::text("hint.stats_back"), Color::Green), (lang::text("hint.reset_lifetime"), Color::Green)]
+            };
+            self.render_footer_hints(footer).map_err(|e| e.to_string())?;
+            self.stdout.flush().map_err(|e| e.to_string())?;
+
+            if let Ok(Event::Key(key)) = read() {
+                if !keys::accept(&key) {
+                    continue;
+                }
+                match (confirming, key.code) {
+                    (false, KeyCode::Esc | KeyCode::F(9)) => return Ok(()),
+                    (false, KeyCode::Char('r' | 'R')) => confirming = true,
+                    (true, KeyCode::Char('y' | 'Y')) => {
+                        app.reset_lifetime_stats()?;
+                        confirming = false;
+                    }
+                    (true, _) => confirming = false,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// List the supported files the last scan left out, with the reason.
+    /// They are never sent or opened in the viewer.
+    /// List the warnings hidden with Delete; Enter shows the selected one again.
+    fn hidden_warnings_screen(&mut self) -> Result<(), String> {
+        let mut selected = 0;
+        let mut notice = String::new();
+        loop {
+            let hidden = warnings::suppressed();
+            selected = selected.min(hidden.len().saturating_sub(1));
+            execute!(self.stdout, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+            self.render_title(Layout::current(), lang::text("hidden.title")).map_err(|e| e.to_string())?;
+            let (width, height) = screen_size();
+            let rows = height.saturating_sub(9) as usize;
+            if hidden.is_empty() {
+                execute!(
+                    self.stdout,
+                    MoveTo(0, 4),
+                    SetForegroundColor(Color::DarkGrey),
+                    Print(format!("   {}", lang::text("hidden.none"))),
+                    ResetColor
+                ).map_err(|e| e.to_string())?;
+            }
+            let first = scroll::centered(selected, rows, hidden.len());
+            for (i, id) in hidden.iter().enumerate().skip(first).take(rows) {
+                let marker = if i == selected { "►" } else { " " };
+                execute!(self.stdout, MoveTo(0, 4 + (i - first) as u16)).map_err(|e| e.to_string())?;
+                if i == selected {
+                    execute!(self.stdout, SetBackgroundColor(Color::DarkBlue), SetForegroundColor(Color::White))
+                        .map_err(|e| e.to_string())?;
+                }
+                let entry = truncate_line(&format!(" {} {} ", marker, id), (width as usize).saturating_sub(2));
+                execute!(self.stdout, Print(entry), ResetColor).map_err(|e| e.to_string())?;
+            }
+            execute!(
+                self.stdout,
+                MoveTo(0, 5 + rows as u16),
+                SetForegroundColor(Color::Cyan),
+                Print(&notice),
+                ResetColor
+            ).map_err(|e| e.to_string())?;
+            self.render_footer_hints(&[
+                (lang::text("hint.navigate"), Color::Green),
+                (lang::text("hint.show_warning"), Color::Green),
+                (lang::text("hint.settings_back"), Color::Green),
+            ])
+            .map_err(|e| e.to_string())?;
+            self.stdout.flush().map_err(|e| e.to_string())?;
+
+            let Ok(Event::Key(key)) = read() else {
+                continue;
+            };
+            if !keys::accept(&key) {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc | KeyCode::F(2) => return Ok(()),
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected = (selected + 1).min(hidden.len().saturating_sub(1)),
+                KeyCode::Enter | KeyCode::Delete => {
+                    if let Some(id) = hidden.get(selected) {
+                        notice = match warnings::unsuppress(id) {
+                            Ok(()) => {
+                                log(&format!("Warning '{}' shown again", id));
+                                format!("   {}", lang::text("hidden.shown_again"))
+                            }
+                            Err(e) => format!("   {}", e),
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn skipped_screen(&mut self, app: &App) -> Result<(), String> {
+        execute!(self.stdout, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+        self.render_title(Layout::current(), lang::text("skipped.title")).map_err(|e| e.to_string())?;
+        let (width, height) = screen_size();
+
+        let rows = height.saturating_sub(8) as usize;
+        if app.skipped().is_empty() {
+            execute!(
+                self.stdout,
+                MoveTo(0, 4),
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("   {}", lang::text("skipped.none"))),
+                ResetColor
+            ).map_err(|e| e.to_string())?;
+        }
+        for (i, file) in app.skipped().iter().take(rows).enumerate() {
+            let entry = truncate_line(&format!("{} — {}", file.name, file.reason), (width as usize).saturating_sub(4));
+            execute!(
+                self.stdout,
+                MoveTo(0, 4 + i as u16),
+                SetForegroundColor(Color::Yellow),
+                Print(format!("   {}", entry)),
+                ResetColor
+            ).map_err(|e| e.to_string())?;
+        }
+        if app.skipped().len() > rows {
+            execute!(
+                self.stdout,
+                MoveTo(0, 4 + rows as u16),
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("   {}", lang::format("skipped.more", &[("count", &(app.skipped().len() - rows))]))),
+                ResetColor
+            ).map_err(|e| e.to_string())?;
+        }
+
+        self.render_footer_hints(&[(lang::text("hint.skipped_back"), Color::Green)]).map_err(|e| e.to_string())?;
+        self.stdout.flush().map_err(|e| e.to_string())?;
+
+        loop {
+            if let Ok(Event::Key(key)) = read() {
+                if keys::accept(&key) && matches!(key.code, KeyCode::Esc | KeyCode::F(10)) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// View file contents in a scrollable viewer, with the cursor on
+    /// message `start` (centered) if given.
+    ///
+    /// The file's modification time is polled while the viewer is open so
+    /// external edits show up live. Returns what happened to the file.
+    fn view_file(
+        &mut self,
+        mut file: TextFile,
+        start: Option<usize>,
+        excluded: &mut HashSet<usize>,
+        worker: &mut FsWorker,
+    ) -> Result<ViewerExit, String> {
+        let mut scroll_offset: usize = 0;
+        let mut cursor: usize = 0;
+        let mut needs_render = true;
+        let mut reloaded = false;
+        let mut note: Option<(String, Instant)> = None;
+        // Shown until the next key press
+        let mut error_note: Option<String> = None;
+        let mut last_modified = file.modified;
+        // Show lines as the character filter will send them
+        let mut filter = CharFilter::from(&self.config);
+        let mut filtered_preview = false;
+        // Show lines with their abbreviations expanded
+        let mut abbreviations = Abbreviations::from(&self.config);
+        let mut expanded_preview = false;
+        let (term_width, term_height) = screen_size();
+        let visible_lines = (term_height as usize).saturating_sub(6);
+        if let Some(start) = start {
+            let rows = viewer_rows(&file.lines, self.config.blank_line_pause_ms > 0);
+            cursor = start.min(file.lines.len().saturating_sub(1));
+            let row = rows.iter().position(|r| *r == ViewerRow::Line(cursor)).unwrap_or(0);
+            scroll_offset = scroll::centered(row, visible_lines, rows.len());
+        }
         
-        // Static footer separator (only rendered once)
-        let footer_y = term_height.saturating_sub(2);
+        // Initial full render with header
         execute!(
             self.stdout,
-            MoveTo(0, footer_y),
-            SetForegroundColor(Color::DarkGrey),
-            Print("───────────────────────────────────────────────────────────────"),
-            ResetColor
+            Clear(ClearType::All),
+            MoveTo(0, 0)
         ).map_err(|e| e.to_string())?;
         
+        // Footer separator (only rendered once)
+        let layout = Layout::new(term_width);
+        let footer_y = term_height.saturating_sub(2);
+        self.render_separator(layout, footer_y).map_err(|e| e.to_string())?;
+        
         loop {
-            // Only render content if scroll position changed
-            if scroll_offset != last_scroll_offset {
-                last_scroll_offset = scroll_offset;
+            // Only render content if something changed
+            if needs_render {
+                needs_render = false;
+                let title = lang::format("viewer.title", &[("name", &file.name), ("loaded", &provenance_detail(&file.provenance))]);
+                self.render_title(layout, &title).map_err(|e| e.to_string())?;
+                let lines = &file.lines;
+                let rows = viewer_rows(lines, self.config.blank_line_pause_ms > 0);
                 
                 // Render content area only
                 let content_start_y = 4;
-                let end = (scroll_offset + visible_lines).min(lines.len());
+                let end = (scroll_offset + visible_lines).min(rows.len());
                 
                 // Clear and render content lines
                 for row in 0..visible_lines {
@@ -422,74 +2561,962 @@ impl Cli {
                         Clear(ClearType::CurrentLine)
                     ).map_err(|e| e.to_string())?;
                     
-                    let line_idx = scroll_offset + row;
-                    if line_idx < lines.len() {
+                    if let Some(ViewerRow::Pause(line_idx)) = rows.get(scroll_offset + row) {
+                        let pause_ms = lines[*line_idx].blank_before as u64 * self.config.blank_line_pause_ms;
+                        execute!(
+                            self.stdout,
+                            SetForegroundColor(Color::DarkGrey),
+                            Print(format!("       ··· ({}ms)", pause_ms)),
+                            ResetColor
+                        ).map_err(|e| e.to_string())?;
+                    }
+                    if let Some(&ViewerRow::Line(line_idx)) = rows.get(scroll_offset + row) {
                         let line_num = line_idx + 1;
+                        let (color, marker) = if line_idx == cursor { (Color::Yellow, '▶') } else { (Color::DarkGrey, '│') };
+                        execute!(
+                            self.stdout,
+                            SetForegroundColor(color),
+                            Print(format!("{:4} {} ", line_num, marker)),
+                            ResetColor
+                        ).map_err(|e| e.to_string())?;
+                        let mut column = 7;
+                        if let Some(delay_ms) = lines[line_idx].delay_ms {
+                            let prefix = format!("(+{}ms) ", delay_ms);
+                            column += prefix.len();
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(Color::DarkGrey),
+                                Print(prefix),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                        }
+                        if self.config.slash_commands && lines[line_idx].is_command() {
+                            let label = format!("{} ", lang::text("viewer.command"));
+                            column += label.chars().count();
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(Color::Green),
+                                Print(label),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                        } else if lines[line_idx].chat == Some(ChatScope::Team) {
+                            let label = format!("{} ", lang::text("viewer.team"));
+                            column += label.chars().count();
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(Color::Cyan),
+                                Print(label),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                        }
+                        if let Some(at) = lines[line_idx].at {
+                            column += 8;
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(Color::Magenta),
+                                Print(format!("[{}] ", format_clock(at))),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                        }
+                        if lines[line_idx].truncated {
+                            let label = format!("{} ", lang::text("viewer.truncated"));
+                            column += label.chars().count();
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(Color::Yellow),
+                                Print(label),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                        }
+                        if let Some((label, color)) = self.long_line_label(&abbreviations.expand(&lines[line_idx].text).text) {
+                            let label = format!("{} ", label);
+                            column += label.chars().count();
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(color),
+                                Print(label),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                        }
+                        let mut text = lines[line_idx].text.clone();
+                        if expanded_preview {
+                            text = abbreviations.expand(&text).text;
+                        }
+                        if filtered_preview {
+                            let filtered = filter.apply(&text);
+                            let lost = if filtered.text.is_empty() {
+                                format!("{} ", lang::text("viewer.skipped"))
+                            } else if filtered.lost > 0 {
+                                format!("[-{}] ", filtered.lost)
+                            } else {
+                                String::new()
+                            };
+                            column += lost.chars().count();
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(Color::Yellow),
+                                Print(lost),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                            text = filtered.text;
+                        }
+                        // Weights other than 1 follow the text, dimmed
+                        let weight = lines[line_idx].weight;
+                        let weight_label = if weight == 1 { String::new() } else { format!(" w={}", weight) };
+                        // Only lay out what fits; long lines would wrap over the rest of the screen
+                        let room = (term_width as usize).saturating_sub(column + 1 + weight_label.len());
+                        let shown: String = text.chars().take(room).collect();
+                        if excluded.contains(&line_idx) {
+                            execute!(
+                                self.stdout,
+                                SetForegroundColor(Color::DarkGrey),
+                                SetAttribute(Attribute::CrossedOut),
+                                Print(&shown),
+                                SetAttribute(Attribute::NotCrossedOut),
+                                ResetColor
+                            ).map_err(|e| e.to_string())?;
+                        } else {
+                            execute!(self.stdout, Print(&shown)).map_err(|e| e.to_string())?;
+                        }
+                        if shown.len() < text.len() {
+                            execute!(self.stdout, Print("…")).map_err(|e| e.to_string())?;
+                        }
                         execute!(
                             self.stdout,
                             SetForegroundColor(Color::DarkGrey),
-                            Print(format!("{:4} │ ", line_num)),
-                            ResetColor,
-                            Print(&lines[line_idx])
+                            Print(weight_label),
+                            ResetColor
                         ).map_err(|e| e.to_string())?;
                     }
                 }
                 
-                // Update footer info line (dynamic scroll info)
-                let scroll_info = format!("Lines {}-{} of {}", scroll_offset + 1, end, lines.len());
-                execute!(
-                    self.stdout,
-                    MoveTo(0, footer_y + 1),
-                    Clear(ClearType::CurrentLine),
-                    SetForegroundColor(Color::Green),
-                    Print(format!(" [↑↓] Scroll │ [Esc/Tab] Back │ {}", scroll_info)),
-                    ResetColor
-                ).map_err(|e| e.to_string())?;
+                // Update footer info line (dynamic scroll info); pause rows don't count
+                let shown: Vec<usize> = rows[scroll_offset.min(end)..end]
+                    .iter()
+                    .filter_map(|row| match row {
+                        ViewerRow::Line(index) => Some(*index),
+                        ViewerRow::Pause(_) => None,
+                    })
+                    .collect();
+                let (first, last) = match (shown.first(), shown.last()) {
+                    (Some(first), Some(last)) => (first + 1, last + 1),
+                    _ => (0, 0),
+                };
+                let mut scroll_info = lang::format("viewer.lines", &[("first", &first), ("last", &last), ("total", &lines.len())]);
+                if !excluded.is_empty() {
+                    scroll_info.push_str(&lang::format("viewer.excluded", &[("count", &excluded.len())]));
+                }
+                let mut hints = vec![
+                    (lang::text("hint.viewer_back"), Color::Green),
+                    (lang::text("hint.move"), Color::Green),
+                    (lang::text("hint.edit_line"), Color::Green),
+                    (lang::text("hint.exclude"), Color::Green),
+                    (lang::text("hint.go_to_line"), Color::Green),
+                ];
+                if filter.is_active() {
+                    hints.push((lang::text(if filtered_preview { "hint.original" } else { "hint.filtered" }), Color::Green));
+                }
+                if abbreviations.is_active() {
+                    hints.push((lang::text(if expanded_preview { "hint.raw" } else { "hint.expanded" }), Color::Green));
+                }
+                hints.push((scroll_info.as_str(), Color::Green));
+                let used = self.render_hints(layout, footer_y + 1, &hints).map_err(|e| e.to_string())?;
+                // Notes get whatever room is left
+                let room = layout.width.saturating_sub(used + HINT_SEPARATOR.chars().count());
+                if let Some(error) = &error_note {
+                    execute!(
+                        self.stdout,
+                        SetForegroundColor(Color::Red),
+                        Print(format!("{}{}", HINT_SEPARATOR, layout.fit(error, room))),
+                        ResetColor
+                    ).map_err(|e| e.to_string())?;
+                } else if let Some((text, _)) = &note {
+                    execute!(
+                        self.stdout,
+                        SetForegroundColor(Color::Yellow),
+                        Print(format!("{}{}", HINT_SEPARATOR, layout.fit(text, room))),
+                        ResetColor
+                    ).map_err(|e| e.to_string())?;
+                }
                 
                 self.stdout.flush().map_err(|e| e.to_string())?;
             }
+
+            // Expire the footer note
+            if note.as_ref().is_some_and(|(_, shown)| shown.elapsed() >= Duration::from_secs(RELOAD_NOTE_SECS)) {
+                note = None;
+                needs_render = true;
+            }
             
+            // Wait for input, checking the file for changes in between
+            if !poll(Duration::from_millis(VIEWER_POLL_MS)).unwrap_or(false) {
+                if let Some(result) = self.config_watcher.as_mut().and_then(|w| w.poll()) {
+                    // A new game profile, filter or table changes the line labels
+                    match apply_reload(&mut self.config, &mut self.config_path, result) {
+                        Ok(notice) => note = Some((notice, Instant::now())),
+                        Err(e) => error_note = Some(e),
+                    }
+                    filter = CharFilter::from(&self.config);
+                    filtered_preview &= filter.is_active();
+                    abbreviations = Abbreviations::from(&self.config);
+                    expanded_preview &= abbreviations.is_active();
+                    needs_render = true;
+                    continue;
+                }
+                // Stat and reload on the worker so a slow drive can't freeze the viewer
+                match worker.take_checked() {
+                    Some((path, FileChange::Removed)) if path == file.path => {
+                        log(&format!("Viewed file removed: {}", file.path.display()));
+                        self.render_removed_banner(footer_y)?;
+                        return Ok(ViewerExit::Removed(file.path));
+                    }
+                    Some((path, FileChange::Reloaded(mut updated))) if path == file.path => {
+                        updated.mark_reloaded(LoadReason::Watcher);
+                        last_modified = updated.modified;
+                        let mut text = lang::text("viewer.reloaded").to_string();
+                        if !excluded.is_empty() && content_hash(&updated.lines) != content_hash(&file.lines) {
+                            excluded.clear();
+                            text.push_str(lang::text("viewer.exclusions_cleared"));
+                        }
+                        file = updated;
+                        let row_count = viewer_rows(&file.lines, self.config.blank_line_pause_ms > 0).len();
+                        scroll_offset = scroll_offset.min(scroll::last_page(visible_lines, row_count));
+                        cursor = cursor.min(file.lines.len().saturating_sub(1));
+                        reloaded = true;
+                        note = Some((text, Instant::now()));
+                        needs_render = true;
+                    }
+                    Some((path, FileChange::Unreadable(modified))) if path == file.path => {
+                        last_modified = modified;
+                        note = Some((lang::text("viewer.reload_failed").to_string(), Instant::now()));
+                        needs_render = true;
+                    }
+                    _ => {}
+                }
+                worker.check(file.path.clone(), last_modified);
+                continue;
+            }
+
             // Handle input
             if let Ok(Event::Key(key)) = read() {
-                if key.kind != KeyEventKind::Press {
+                if !keys::accept(&key) {
                     continue;
                 }
+                if error_note.take().is_some() {
+                    needs_render = true;
+                }
+                let lines = &file.lines;
+                let rows = viewer_rows(lines, self.config.blank_line_pause_ms > 0);
+                let row_of = |line: usize| rows.iter().position(|r| *r == ViewerRow::Line(line)).unwrap_or(0);
+                let previous = (scroll_offset, cursor);
                 match key.code {
                     KeyCode::Esc | KeyCode::Tab => {
-                        return Ok(());
+                        return Ok(if reloaded { ViewerExit::Reloaded(file) } else { ViewerExit::Unchanged });
+                    }
+                    KeyCode::Char('i') => {
+                        match self.edit_viewed_line(&mut file, cursor, footer_y + 1)? {
+                            Ok(Some(saved)) => {
+                                last_modified = file.modified;
+                                reloaded = true;
+                                note = Some((saved, Instant::now()));
+                            }
+                            Ok(None) => {}
+                            Err(e) => error_note = Some(e),
+                        }
+                        needs_render = true;
+                        continue;
                     }
                     KeyCode::Up => {
-                        scroll_offset = scroll_offset.saturating_sub(1);
+                        cursor = cursor.saturating_sub(1);
+                        // Bring the pause above the line into view too
+                        let pause = usize::from(lines.get(cursor).is_some_and(|l| l.blank_before > 0));
+                        scroll_offset = scroll_offset.min(row_of(cursor).saturating_sub(pause));
                     }
-                    KeyCode::Down => {
-                        if scroll_offset + visible_lines < lines.len() {
-                            scroll_offset += 1;
+                    KeyCode::Down if cursor + 1 < lines.len() => {
+                        cursor += 1;
+                        let row = row_of(cursor);
+                        if row >= scroll_offset + visible_lines {
+                            scroll_offset = row + 1 - visible_lines;
                         }
                     }
                     KeyCode::PageUp => {
-                        scroll_offset = scroll_offset.saturating_sub(visible_lines);
+                        scroll_offset = scroll::page_up(scroll_offset, visible_lines);
                     }
                     KeyCode::PageDown => {
-                        scroll_offset = (scroll_offset + visible_lines)
-                            .min(lines.len().saturating_sub(visible_lines));
+                        scroll_offset = scroll::page_down(scroll_offset, visible_lines, rows.len());
                     }
                     KeyCode::Home => {
                         scroll_offset = 0;
                     }
-                    KeyCode::End => {
-                        scroll_offset = lines.len().saturating_sub(visible_lines);
+                    KeyCode::End | KeyCode::Char('G') => {
+                        scroll_offset = scroll::last_page(visible_lines, rows.len());
+                    }
+                    KeyCode::Char('d') if !lines.is_empty() => {
+                        if !excluded.remove(&cursor) {
+                            excluded.insert(cursor);
+                        }
+                        needs_render = true;
+                    }
+                    KeyCode::Char('f') if filter.is_active() => {
+                        filtered_preview = !filtered_preview;
+                        needs_render = true;
+                    }
+                    KeyCode::Char('x') if abbreviations.is_active() => {
+                        expanded_preview = !expanded_preview;
+                        needs_render = true;
+                    }
+                    KeyCode::Char(':' | 'g') => {
+                        let Some(input) = self.prompt_at(footer_y + 1, lang::text("prompt.go_to_line"), "")? else {
+                            needs_render = true;
+                            continue;
+                        };
+                        match scroll::line_index(&input, lines.len()) {
+                            Some(index) => {
+                                cursor = index;
+                                scroll_offset = scroll::centered(row_of(index), visible_lines, rows.len());
+                            }
+                            None if input.trim().is_empty() => {}
+                            None => error_note = Some(lang::format("viewer.not_a_line", &[("input", &input.trim())])),
+                        }
+                        needs_render = true;
                     }
                     _ => {}
                 }
+                // Page keys move the view; keep the cursor on a visible line
+                if scroll_offset != previous.0 {
+                    let visible = scroll_offset..scroll_offset + visible_lines;
+                    let on_screen: Vec<usize> = rows
+                        .iter()
+                        .enumerate()
+                        .filter(|(row, _)| visible.contains(row))
+                        .filter_map(|(_, r)| match r {
+                            ViewerRow::Line(index) => Some(*index),
+                            ViewerRow::Pause(_) => None,
+                        })
+                        .collect();
+                    if let (Some(&first), Some(&last)) = (on_screen.first(), on_screen.last()) {
+                        cursor = cursor.clamp(first, last);
+                    }
+                }
+                needs_render = needs_render || (scroll_offset, cursor) != previous;
+            }
+        }
+    }
+
+    /// Edit line `index` of the viewed file on row `y` and write it back.
+    /// Returns the note to show once saved, None if nothing changed, or
+    /// the reason it wasn't saved.
+    fn edit_viewed_line(&mut self, file: &mut TextFile, index: usize, y: u16) -> Result<Result<Option<String>, String>, String> {
+        let Some(line) = file.lines.get(index) else {
+            return Ok(Ok(None));
+        };
+        let old = line.text.clone();
+        let Some(text) = self.prompt_at(y, lang::text("prompt.edit"), &old)? else {
+            return Ok(Ok(None));
+        };
+        if text.trim() == old {
+            return Ok(Ok(None));
+        }
+        if edit::changed_on_disk(file)
+            && !self.confirm_at(y, lang::text("prompt.save_changed"))
+        {
+            return Ok(Err(lang::text("viewer.not_saved").to_string()));
+        }
+        Ok(match edit::rewrite_line(file, index, &text) {
+            Ok(()) => Ok(Some(lang::format("viewer.saved", &[("line", &(index + 1))]))),
+            Err(e) => {
+                log(&format!("Edit failed: {}", e));
+                Err(e)
+            }
+        })
+    }
+
+    /// Show `old` and `new` side by side, lined up with [`diff_rows`]:
+    /// removed messages red on the left, added ones green on the right and
+    /// changed ones yellow on both sides. Both sides scroll together.
+    fn compare_screen(&mut self, old: &TextFile, new: &TextFile) -> Result<(), String> {
+        let rows = diff_rows(&old.lines, &new.lines);
+        let summary = diff_lines(&old.lines, &new.lines).summary();
+        log(&format!("Comparing '{}' with '{}': {}", old.name, new.name, summary));
+        let (term_width, term_height) = screen_size();
+        let visible_lines = (term_height as usize).saturating_sub(6);
+        let layout = Layout::new(term_width);
+        let footer_y = term_height.saturating_sub(2);
+        // Each side: a 4-digit line number, a space and the text
+        let side = (term_width as usize).saturating_sub(3) / 2;
+        let room = side.saturating_sub(5);
+        let mut scroll_offset = 0;
+        let mut error_note: Option<String> = None;
+
+        execute!(self.stdout, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+        self.render_separator(layout, footer_y).map_err(|e| e.to_string())?;
+        loop {
+            let title = lang::format("compare.title", &[("old", &old.name), ("new", &new.name)]);
+            self.render_title(layout, &title).map_err(|e| e.to_string())?;
+            for (y, row) in (4..).zip(rows.iter().skip(scroll_offset).map(Some).chain(std::iter::repeat(None)).take(visible_lines)) {
+                execute!(self.stdout, MoveTo(0, y), Clear(ClearType::CurrentLine)).map_err(|e| e.to_string())?;
+                let Some(&DiffRow { old: left, new: right }) = row else {
+                    continue;
+                };
+                let changed = match (left, right) {
+                    (Some(a), Some(b)) => old.lines[a].text != new.lines[b].text,
+                    _ => true,
+                };
+                let cell = |lines: &[Line], index: Option<usize>| match index {
+                    Some(index) => {
+                        let text: String = lines[index].text.chars().take(room).collect();
+                        format!("{:4} {:<room$}", index + 1, text)
+                    }
+                    None => " ".repeat(side),
+                };
+                let (left_color, right_color) = match (left, right) {
+                    _ if !changed => (Color::Reset, Color::Reset),
+                    (Some(_), Some(_)) => (Color::Yellow, Color::Yellow),
+                    _ => (Color::Red, Color::Green),
+                };
+                execute!(
+                    self.stdout,
+                    SetForegroundColor(left_color),
+                    Print(cell(&old.lines, left)),
+                    SetForegroundColor(Color::DarkGrey),
+                    Print(" │ "),
+                    SetForegroundColor(right_color),
+                    Print(cell(&new.lines, right)),
+                    ResetColor
+                ).map_err(|e| e.to_string())?;
+            }
+
+            let end = (scroll_offset + visible_lines).min(rows.len());
+            let first = if end > scroll_offset { scroll_offset + 1 } else { 0 };
+            let scroll_info = lang::format("compare.rows", &[("first", &first), ("last", &end), ("total", &rows.len())]);
+            let hints = [
+                (lang::text("hint.viewer_back"), Color::Green),
+                (lang::text("hint.move"), Color::Green),
+                (lang::text("hint.go_to_line"), Color::Green),
+                (summary.as_str(), Color::Green),
+                (scroll_info.as_str(), Color::Green),
+            ];
+            execute!(self.stdout, MoveTo(0, footer_y + 1), Clear(ClearType::CurrentLine)).map_err(|e| e.to_string())?;
+            let used = self.render_hints(layout, footer_y + 1, &hints).map_err(|e| e.to_string())?;
+            if let Some(error) = &error_note {
+                let room = layout.width.saturating_sub(used + HINT_SEPARATOR.chars().count());
+                execute!(
+                    self.stdout,
+                    SetForegroundColor(Color::Red),
+                    Print(format!("{}{}", HINT_SEPARATOR, layout.fit(error, room))),
+                    ResetColor
+                ).map_err(|e| e.to_string())?;
+            }
+            self.stdout.flush().map_err(|e| e.to_string())?;
+
+            let Ok(Event::Key(key)) = read() else {
+                continue;
+            };
+            if !keys::accept(&key) {
+                continue;
+            }
+            error_note = None;
+            match key.code {
+                KeyCode::Esc | KeyCode::Tab => return Ok(()),
+                KeyCode::Up => scroll_offset = scroll_offset.saturating_sub(1),
+                KeyCode::Down => scroll_offset = (scroll_offset + 1).min(scroll::last_page(visible_lines, rows.len())),
+                KeyCode::PageUp => scroll_offset = scroll::page_up(scroll_offset, visible_lines),
+                KeyCode::PageDown => scroll_offset = scroll::page_down(scroll_offset, visible_lines, rows.len()),
+                KeyCode::Home => scroll_offset = 0,
+                KeyCode::End | KeyCode::Char('G') => scroll_offset = scroll::last_page(visible_lines, rows.len()),
+                KeyCode::Char(':' | 'g') => {
+                    // Line numbers of the left file
+                    let Some(input) = self.prompt_at(footer_y + 1, lang::text("prompt.go_to_line"), "")? else {
+                        continue;
+                    };
+                    match scroll::line_index(&input, old.lines.len()) {
+                        Some(index) => {
+                            let row = rows.iter().position(|r| r.old == Some(index)).unwrap_or(0);
+                            scroll_offset = scroll::centered(row, visible_lines, rows.len());
+                        }
+                        None if input.trim().is_empty() => {}
+                        None => error_note = Some(lang::format("viewer.not_a_line", &[("input", &input.trim())])),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Show the "file removed" banner and wait for any key.
+    fn render_removed_banner(&mut self, footer_y: u16) -> Result<(), String> {
+        execute!(
+            self.stdout,
+            MoveTo(0, 4),
+            Clear(ClearType::CurrentLine),
+            SetBackgroundColor(Color::DarkRed),
+            SetForegroundColor(Color::White),
+            Print(format!(" {} ", lang::text("viewer.removed"))),
+            ResetColor,
+            MoveTo(0, footer_y + 1),
+            Clear(ClearType::CurrentLine),
+            SetForegroundColor(Color::Green),
+            Print(format!(" {}", lang::text("viewer.any_key"))),
+            ResetColor
+        ).map_err(|e| e.to_string())?;
+        self.stdout.flush().map_err(|e| e.to_string())?;
+
+        loop {
+            if let Ok(Event::Key(key)) = read() {
+                if keys::accept(&key) {
+                    return Ok(());
+                }
             }
         }
     }
 }
 
+/// One row of the file viewer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ViewerRow {
+    /// Message line at this index
+    Line(usize),
+    /// `···` pause for the blank lines above this line index
+    Pause(usize),
+}
+
+/// Rows of the file viewer: one per line, plus a pause row above lines
+/// that follow blank lines when `pauses` is on.
+fn viewer_rows(lines: &[Line], pauses: bool) -> Vec<ViewerRow> {
+    let mut rows = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        if pauses && line.blank_before > 0 {
+            rows.push(ViewerRow::Pause(index));
+        }
+        rows.push(ViewerRow::Line(index));
+    }
+    rows
+}
+
+/// What happened to a file while it was open in the viewer.
+enum ViewerExit {
+    /// Nothing changed on disk
+    Unchanged,
+    /// The file was edited externally and re-read
+    Reloaded(TextFile),
+    /// The file was deleted while being viewed
+    Removed(PathBuf),
+}
+
+/// Headless alternatives suggested when the terminal UI can't run.
+pub const HEADLESS_FLAGS: &str = "--list or --send FILE";
+
+/// Check whether stdin and stdout are terminals, as the interactive UI
+/// needs. Returns a description of the problem if not.
+pub fn terminal_problem() -> Option<&'static str> {
+    tty_problem(io::stdin().is_terminal(), io::stdout().is_terminal())
+}
+
+/// Describe which of stdin/stdout is not a terminal, if any.
+fn tty_problem(stdin_tty: bool, stdout_tty: bool) -> Option<&'static str> {
+    match (stdin_tty, stdout_tty) {
+        (true, true) => None,
+        (false, true) => Some(lang::text("tty.stdin")),
+        (true, false) => Some(lang::text("tty.stdout")),
+        (false, false) => Some(lang::text("tty.both")),
+    }
+}
+
+/// One row of the settings screen.
+#[derive(Clone, Copy)]
+enum SettingRow {
+    Toggle(&'static Toggle),
+    Choice(&'static Choice),
+    Limit(&'static Limit),
+}
+
+/// Settings screen rows: toggles, then choices, then numeric settings.
+fn setting_rows() -> Vec<SettingRow> {
+    TOGGLES
+        .iter()
+        .map(SettingRow::Toggle)
+        .chain(CHOICES.iter().map(SettingRow::Choice))
+        .chain(LIMITS.iter().map(SettingRow::Limit))
+        .collect()
+}
+
+/// How the settings screen was left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SettingsExit {
+    Back,
+    /// The user asked for a test send
+    TestSend,
+    /// The user asked for the list of hidden warnings
+    HiddenWarnings,
+}
+
+/// Outcome of sending a single line.
+/// How `send_all_lines` runs a send.
+#[derive(Clone, Debug, Default)]
+struct SendOptions {
+    /// Channel, order, speed and the other choices of the options overlay
+    choices: SendChoices,
+    /// Game time when the send starts, for timed lines
+    game_clock: Option<GameClock>,
+    /// Lines excluded in the viewer, left out of the send
+    excluded: HashSet<usize>,
+    /// File the lines come from, journaled while they're sent
+    source: Option<PathBuf>,
+}
+
+impl SendOptions {
+    /// Options for a send with `choices` and nothing else.
+    fn new(choices: SendChoices) -> Self {
+        Self { choices, ..Self::default() }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineOutcome {
+    Sent,
+    Skipped,
+    Failed,
+}
+
+impl LineOutcome {
+    /// Color used for progress rows and summary counts.
+    fn color(self) -> Color {
+        match self {
+            LineOutcome::Sent => Color::Green,
+            LineOutcome::Skipped => Color::Yellow,
+            LineOutcome::Failed => Color::Red,
+        }
+    }
+
+    /// Status symbol shown in front of the line text.
+    fn symbol(self) -> &'static str {
+        match self {
+            LineOutcome::Sent => "✓",
+            LineOutcome::Skipped => "○",
+            LineOutcome::Failed => "✗",
+        }
+    }
+}
+
 impl Default for Cli {
     fn default() -> Self {
-        Self::new("MadTyping".to_string(), "untitled".to_string())
+        Self::new("MadTyping".to_string(), vec!["untitled".to_string()])
+    }
+}
+
+/// Apply a reloaded config file to `config`, returning the notice to show
+/// or, if the file was rejected, the error (the current settings stay).
+///
+/// Read-only mode and required arming can be turned on by a reload but not
+/// off.
+fn apply_reload(
+    config: &mut Config,
+    config_path: &mut Option<PathBuf>,
+    result: Result<LoadedConfig, String>,
+) -> Result<String, String> {
+    let loaded = result.map_err(|e| {
+        log(&format!("Config reload rejected: {}", e));
+        lang::format("error.config_rejected", &[("error", &e)])
+    })?;
+    let (read_only, require_arming) = (config.read_only, config.require_arming);
+    *config = loaded.config;
+    config.read_only |= read_only;
+    config.require_arming |= require_arming;
+    configure_arming(config);
+    set_window_class(&config.window_class);
+    abbreviations::set_abbreviations(Abbreviations::from(&*config));
+    *config_path = loaded.path;
+    // Warnings already shown this session (or hidden) aren't repeated
+    let fresh: Vec<&String> = loaded.warnings.iter().filter(|w| warnings::report(&format!("config:{}", w))).collect();
+    Ok(match fresh.first() {
+        Some(first) => lang::format("notice.config_reloaded_warnings", &[("count", &fresh.len()), ("first", first)]),
+        None => lang::text("notice.config_reloaded").to_string(),
+    })
+}
+
+/// Apply `require_arming` and `arming_timeout_secs` to the session.
+fn configure_arming(config: &Config) {
+    arming::configure(config.require_arming, Duration::from_secs(config.arming_timeout_secs));
+}
+
+/// Result of [`search_key`].
+#[derive(Debug, PartialEq, Eq)]
+enum SearchKey {
+    /// The key changed the query or the focus
+    Handled,
+    /// The key belongs to search but changed nothing
+    Unchanged,
+    /// Esc with the list focused
+    Quit,
+    /// Not a search key; handle it as a list key
+    Ignored,
+}
+
+/// Apply the search box's part of a key press on the main screen.
+///
+/// With the search focused, every printable key edits the query and Esc
+/// gives focus back to the list. With the list focused, `/` focuses the
+/// search, other printable keys start a search with that character, and
+/// Esc quits.
+fn search_key(app: &mut App, code: KeyCode) -> SearchKey {
+    match (app.focus(), code) {
+        (Focus::Search, KeyCode::Esc) => {
+            app.focus_list();
+            SearchKey::Handled
+        }
+        (Focus::List, KeyCode::Esc) => SearchKey::Quit,
+        // Ad-hoc line, handled by the caller
+        (Focus::List, KeyCode::Char(':')) => SearchKey::Ignored,
+        (Focus::List, KeyCode::Char('/')) => {
+            app.clear_error();
+            app.focus_search();
+            SearchKey::Handled
+        }
+        (_, KeyCode::Char(c)) => {
+            app.clear_error();
+            app.focus_search();
+            app.add_search_char(c);
+            SearchKey::Handled
+        }
+        (_, KeyCode::Backspace) if app.is_search_empty() => SearchKey::Unchanged,
+        (_, KeyCode::Backspace) => {
+            app.clear_error();
+            app.remove_search_char();
+            SearchKey::Handled
+        }
+        _ => SearchKey::Ignored,
+    }
+}
+
+/// Step through `len` history entries from `current` (None = the typed
+/// text, past the newest entry): Up goes to older entries, Down to newer.
+fn recall(len: usize, current: Option<usize>, up: bool) -> Option<usize> {
+    match (current, up) {
+        (None, true) => len.checked_sub(1),
+        (Some(i), true) => Some(i.saturating_sub(1)),
+        (Some(i), false) if i + 1 < len => Some(i + 1),
+        (_, false) => None,
+    }
+}
+
+/// Wait for a key before going back to the list, so the outcome can be read.
+fn press_any_key(stdout: &mut Output) {
+    println!("\n{}", lang::text("send.press_key"));
+    let _ = stdout.flush();
+    // Keys pressed while sending don't count
+    while poll(Duration::ZERO).unwrap_or(false) {
+        let _ = read();
+    }
+    loop {
+        if let Ok(Event::Key(key)) = read() {
+            if keys::accept(&key) {
+                return;
+            }
+        }
+    }
+}
+
+/// Ask whether to restore the minimized window `title` and send anyway.
+fn confirm_minimized(stdout: &mut Output, title: &str) -> bool {
+    let _ = print_colored(
+        stdout,
+        Color::Yellow,
+        &lang::format("prompt.minimized", &[("title", &title)]),
+    );
+    let _ = stdout.flush();
+    loop {
+        if let Ok(Event::Key(key)) = read() {
+            if !keys::accept(&key) {
+                continue;
+            }
+            let yes = matches!(key.code, KeyCode::Char('y' | 'Y'));
+            println!("{}\n", if yes { "y" } else { "n" });
+            return yes;
+        }
+    }
+}
+
+/// Progress row prefix, e.g. "[ 3/12]" or "[ 3/12] → Window" when broadcasting.
+fn progress_row(index: usize, total: usize, width: usize, target: &str, broadcast: bool) -> String {
+    let row = format!("[{:>width$}/{:>width$}]", index + 1, total, width = width);
+    if broadcast {
+        format!("{} → {}", row, truncate_line(target, 20))
+    } else {
+        row
+    }
+}
+
+/// Print the colored per-outcome counts after a send finishes or stops.
+fn render_send_summary(stdout: &mut Output, report: &SendReport, total: usize) -> io::Result<()> {
+    if report.sent() + report.excluded() == total * report.targets.len() {
+        print_colored(stdout, Color::Green, &format!("\n{}\n", lang::format("summary.done", &[("count", &report.sent())])))?;
+        if report.excluded() > 0 {
+            println!("{}", lang::format("summary.excluded_left_out", &[("count", &report.excluded())]));
+        }
+        if report.retries() > 0 {
+            println!("{}", lang::format("summary.retried", &[("count", &report.retries())]));
+        }
+        if let Some(summary) = report.verification_summary() {
+            let color = if report.unverified() > 0 { Color::Yellow } else { Color::Green };
+            print_colored(stdout, color, &format!("   {}\n", summary))?;
+        }
+        return Ok(());
     }
+
+    println!("\n{}", lang::text("summary.title"));
+    for target in &report.targets {
+        if report.targets.len() > 1 {
+            println!(" {}", target.window_title);
+        }
+        let failed = usize::from(target.failed.is_some());
+        let counts = [
+            (LineOutcome::Sent, "summary.sent", target.sent),
+            (LineOutcome::Skipped, "summary.skipped", target.skipped),
+        ];
+        for (outcome, id, count) in counts {
+            print_colored(stdout, outcome.color(), &format!("  {} {}\n", outcome.symbol(), lang::format(id, &[("count", &count)])))?;
+        }
+        if target.excluded > 0 {
+            print_colored(stdout, Color::DarkGrey, &format!("  {}\n", lang::format("summary.excluded", &[("count", &target.excluded)])))?;
+        }
+        let failed_text = lang::format("summary.failed", &[("count", &failed)]);
+        print_colored(stdout, LineOutcome::Failed.color(), &format!("  {} {}\n", LineOutcome::Failed.symbol(), failed_text))?;
+
+        if let Some((index, text, _)) = &target.failed {
+            let id = if target.window_lost { "summary.window_lost" } else { "summary.failed_line" };
+            let what = lang::format(id, &[("line", &(index + 1)), ("text", &truncate_line(text, 50))]);
+            print_colored(stdout, Color::Red, &format!("  {}\n", what))?;
+        }
+    }
+    if report.retries() > 0 {
+        println!("{}", lang::format("summary.retried_chars", &[("count", &report.retries())]));
+    }
+    if let Some(summary) = report.verification_summary() {
+        let color = if report.unverified() > 0 { Color::Yellow } else { Color::Green };
+        print_colored(stdout, color, &format!("  {}\n", summary))?;
+    }
+    Ok(())
+}
+
+/// Remember the windows that fell back from pasting to typing, saying so.
+fn save_paste_fallbacks(stdout: &mut Output, report: &SendReport) {
+    for target in &report.targets {
+        if let Some(reason) = &target.paste_fallback {
+            let _ = print_colored(
+                stdout,
+                Color::Yellow,
+                &format!("{}\n", lang::format("send.paste_fallback", &[("window", &target.window_title), ("reason", reason)])),
+            );
+        }
+    }
+    if let Err(e) = paste::save(report) {
+        log(&e);
+    }
+    if let Err(e) = focus_quirks::save(report) {
+        log(&e);
+    }
+}
+
+/// Chip color for a tag; the same tag always gets the same color.
+fn tag_color(tag: &str) -> Color {
+    const PALETTE: [Color; 6] = [Color::Cyan, Color::Green, Color::Magenta, Color::Yellow, Color::Blue, Color::Red];
+    let hash = tag.bytes().fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+    PALETTE[hash % PALETTE.len()]
+}
+
+/// Check whether colored output is allowed (honors the NO_COLOR convention
+/// and the plain mode).
+fn colors_enabled() -> bool {
+    probe::active().colors && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+/// Print text in the given color, or plain when colors are disabled.
+fn print_colored(stdout: &mut Output, color: Color, text: &str) -> io::Result<()> {
+    if colors_enabled() {
+        execute!(stdout, SetForegroundColor(color), Print(text), ResetColor)
+    } else {
+        execute!(stdout, Print(text))
+    }
+}
+
+/// Glyph and color of a file's last send result in the list.
+fn send_result_glyph(result: SendResult) -> (&'static str, Color) {
+    match result {
+        SendResult::Sent => ("✓", Color::Green),
+        SendResult::Partial { .. } => ("◐", Color::Yellow),
+        SendResult::Failed { .. } => ("✗", Color::Red),
+    }
+}
+
+/// Status line text for the selected file's last send.
+fn send_result_detail(last: &LastSend) -> String {
+    let ago = format_ago(last.at.elapsed());
+    match last.result {
+        SendResult::Sent => lang::format("status.last_sent", &[("ago", &ago), ("count", &last.delivered)]),
+        SendResult::Partial { reached } => lang::format("status.last_partial", &[("ago", &ago), ("count", &reached)]),
+        SendResult::Failed { line } => lang::format("status.last_failed", &[("ago", &ago), ("line", &(line + 1))]),
+    }
+}
+
+/// List badge for a file's health: a green dot when clean, the number of
+/// warnings in yellow, a red cross with errors.
+fn health_badge(level: Level) -> (String, Color) {
+    match level {
+        Level::Clean => ("●".to_string(), Color::Green),
+        Level::Warnings(count) => (count.min(99).to_string(), Color::Yellow),
+        Level::Errors(_) => ("✖".to_string(), Color::Red),
+    }
+}
+
+/// The first of `file`'s warnings that isn't hidden, with its registry id;
+/// from its `health` check once it has one.
+fn visible_file_warning<'a>(file: &'a TextFile, health: Option<&'a Health>) -> Option<(String, &'a String)> {
+    health
+        .map_or(&file.warnings, |h| &h.warnings)
+        .iter()
+        .map(|warning| (files::file_warning_id(&file.path, warning), warning))
+        .find(|(id, _)| !warnings::is_suppressed(id))
+}
+
+/// When and why a file's lines were loaded, e.g. "loaded 3m 05s ago
+/// (edited here)".
+fn provenance_detail(provenance: &Provenance) -> String {
+    let ago = format_ago(provenance.loaded_at.elapsed().unwrap_or_default());
+    let reason = lang::text(match provenance.reason {
+        LoadReason::Initial => "loaded.initial",
+        LoadReason::Rescan => "loaded.rescan",
+        LoadReason::Watcher => "loaded.watcher",
+        LoadReason::Edit => "loaded.edit",
+        LoadReason::SendReload => "loaded.send_reload",
+    });
+    lang::format("status.loaded", &[("ago", &ago), ("reason", &reason)])
+}
+
+/// When a file was sent, rounded for the list: "sent just now", "sent
+/// 40s ago", "sent 2m ago" or "sent 1h ago".
+fn sent_ago_label(elapsed: Duration) -> String {
+    let ago = match elapsed.as_secs() {
+        0..=4 => return lang::text("list.sent_now").to_string(),
+        secs @ 5..=59 => format!("{}s", secs),
+        secs @ 60..=3599 => format!("{}m", secs / 60),
+        secs => format!("{}h", secs / 3600),
+    };
+    lang::format("list.sent_ago", &[("ago", &ago)])
+}
+
+/// How long ago something happened, e.g. "22s" or "3m 05s".
+fn format_ago(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Whole seconds left of `wait`, rounded up so "ready in 0s" never shows.
+fn seconds_left(wait: Duration) -> u64 {
+    u64::try_from(wait.as_millis().div_ceil(1000)).unwrap_or(u64::MAX)
 }
 
 /// Truncate a line for display, adding ellipsis if too long.
@@ -501,3 +3528,80 @@ fn truncate_line(line: &str, max_len: usize) -> String {
         line.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `keys` to [`search_key`], returning the result of the last one.
+    fn press(app: &mut App, keys: &[KeyCode]) -> SearchKey {
+        keys.iter().map(|&key| search_key(app, key)).last().unwrap()
+    }
+
+    #[test]
+    fn test_search_focus_state_machine() {
+        let mut app = App::new(Vec::new());
+        assert_eq!(app.focus(), Focus::List);
+
+        // `:` in the list is the ad-hoc line key, not a search
+        assert_eq!(press(&mut app, &[KeyCode::Char(':')]), SearchKey::Ignored);
+        assert_eq!(app.focus(), Focus::List);
+
+        // `/` only focuses; later keys, even `/`, go to the query
+        assert_eq!(press(&mut app, &[KeyCode::Char('/')]), SearchKey::Handled);
+        assert_eq!(app.focus(), Focus::Search);
+        press(&mut app, &[KeyCode::Char('g'), KeyCode::Char('/'), KeyCode::Backspace]);
+        assert_eq!(app.search_query(), "g");
+        assert_eq!(press(&mut app, &[KeyCode::Up]), SearchKey::Ignored);
+
+        // Esc leaves the search but keeps the query, a second Esc quits
+        assert_eq!(press(&mut app, &[KeyCode::Esc]), SearchKey::Handled);
+        assert_eq!((app.focus(), app.search_query()), (Focus::List, "g"));
+        assert_eq!(press(&mut app, &[KeyCode::Esc]), SearchKey::Quit);
+
+        // Typing from the list starts a search with that character
+        press(&mut app, &[KeyCode::Char('g')]);
+        assert_eq!((app.focus(), app.search_query()), (Focus::Search, "gg"));
+        press(&mut app, &[KeyCode::Esc, KeyCode::Backspace, KeyCode::Backspace]);
+        assert_eq!(press(&mut app, &[KeyCode::Backspace]), SearchKey::Unchanged);
+        assert_eq!((app.focus(), app.search_query()), (Focus::List, ""));
+    }
+
+    #[test]
+    fn test_history_recall() {
+        assert_eq!(recall(0, None, true), None);
+        assert_eq!(recall(3, None, true), Some(2));
+        assert_eq!(recall(3, Some(2), true), Some(1));
+        assert_eq!(recall(3, Some(0), true), Some(0));
+        assert_eq!(recall(3, Some(1), false), Some(2));
+        assert_eq!(recall(3, Some(2), false), None);
+        assert_eq!(recall(3, None, false), None);
+    }
+
+    #[test]
+    fn test_sent_ago_label_rounds_down() {
+        assert_eq!(sent_ago_label(Duration::from_secs(3)), "sent just now");
+        assert_eq!(sent_ago_label(Duration::from_secs(40)), "sent 40s ago");
+        assert_eq!(sent_ago_label(Duration::from_secs(179)), "sent 2m ago");
+        assert_eq!(sent_ago_label(Duration::from_secs(7300)), "sent 2h ago");
+        assert_eq!(send_result_glyph(SendResult::Failed { line: 3 }), ("✗", Color::Red));
+    }
+
+    #[test]
+    fn test_tty_problem() {
+        assert_eq!(tty_problem(true, true), None);
+        assert_eq!(tty_problem(false, true), Some("stdin is not a terminal"));
+        assert_eq!(tty_problem(true, false), Some("stdout is not a terminal"));
+        assert_eq!(tty_problem(false, false), Some("stdin and stdout are not terminals"));
+    }
+
+    #[test]
+    fn test_viewer_pause_rows() {
+        let lines = vec![Line::new("a"), Line { blank_before: 2, ..Line::new("b") }, Line::new("c")];
+        assert_eq!(
+            viewer_rows(&lines, true),
+            vec![ViewerRow::Line(0), ViewerRow::Pause(1), ViewerRow::Line(1), ViewerRow::Line(2)]
+        );
+        assert_eq!(viewer_rows(&lines, false).len(), 3);
+    }
+}
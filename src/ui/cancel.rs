@@ -0,0 +1,131 @@
+//! Key watcher while sending
+//!
+//! The send view only gets control between lines, so a key read there
+//! could sit unanswered while a long line is typed or a delay runs.
+//! [`SendKeys`] reads the keyboard on its own thread for the length of a
+//! send. Esc asks the session to stop through the cancel request at once
+//! and covers the progress line with a "CANCELLING…" banner, inverted for
+//! [`CANCEL_FLASH_MS`]; the banner stays until the session has stopped.
+//! Every other key is handed to the send view.
+
+use std::{
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossterm::{
+    event::{Event, KeyCode, KeyEvent},
+    execute,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    terminal::{Clear, ClearType},
+};
+
+use crate::config::CANCEL_FLASH_MS;
+use crate::lang;
+use crate::send::{cancel_phase, cancel_running_send, CancelPhase};
+use crate::shutdown::{self, Registration};
+
+use super::events::{poll, read};
+use super::keys;
+use super::probe::Output;
+
+/// Name of the cancel request made by Esc.
+pub const ESC_REQUESTER: &str = "Esc";
+
+/// Keys read while a send runs.
+pub struct SendKeys {
+    keys: Receiver<KeyEvent>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    _registration: Registration,
+}
+
+impl SendKeys {
+    /// Start reading keys; with `flash`, Esc inverts the banner at first.
+    pub fn start(flash: bool) -> Self {
+        let (sender, keys) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                if !poll(Duration::from_millis(10)).unwrap_or(false) {
+                    continue;
+                }
+                let Ok(Event::Key(key)) = read() else {
+                    continue;
+                };
+                if !keys::accept(&key) {
+                    continue;
+                }
+                if key.code != KeyCode::Esc {
+                    let _ = sender.send(key);
+                } else if cancel_running_send(ESC_REQUESTER) {
+                    draw_banner(flash);
+                    if flash {
+                        thread::sleep(Duration::from_millis(CANCEL_FLASH_MS));
+                        if is_cancelling() {
+                            draw_banner(false);
+                        }
+                    }
+                }
+            }
+        });
+        let stopping = Arc::clone(&stop);
+        let _registration = shutdown::register("send keys", move || stopping.store(true, Ordering::SeqCst));
+        Self { keys, stop, thread: Some(thread), _registration }
+    }
+
+    /// The next key pressed, if any.
+    pub fn next(&self) -> Option<KeyEvent> {
+        self.keys.try_recv().ok()
+    }
+}
+
+impl Drop for SendKeys {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Whether a cancel registered and the send hasn't stopped yet.
+pub fn is_cancelling() -> bool {
+    matches!(cancel_phase(), CancelPhase::Requested | CancelPhase::Acknowledged)
+}
+
+/// Draw the "CANCELLING…" banner over the progress line, inverted with
+/// `inverted`.
+pub fn draw_banner(inverted: bool) {
+    // The send view holds the lock while it draws, so lines don't mix
+    let _frame = io::stdout().lock();
+    let mut stdout = Output::new();
+    let banner = format!(" {} ", lang::text("send.cancelling"));
+    let _ = if inverted {
+        execute!(
+            stdout,
+            Print("\r"),
+            Clear(ClearType::CurrentLine),
+            SetAttribute(Attribute::Reverse),
+            Print(banner),
+            SetAttribute(Attribute::Reset)
+        )
+    } else {
+        execute!(
+            stdout,
+            Print("\r"),
+            Clear(ClearType::CurrentLine),
+            SetForegroundColor(Color::Yellow),
+            Print(banner),
+            ResetColor
+        )
+    };
+    let _ = stdout.flush();
+}
@@ -0,0 +1,32 @@
+//! Terminal events
+//!
+//! Every screen reads the terminal through [`poll`] and [`read`] instead
+//! of crossterm's. Once the terminal is gone (its window was closed but
+//! the hang-up didn't end the process), every read fails at once and a
+//! screen waiting for a key would spin forever. The first failure here
+//! runs the [`shutdown`] instead, the way Esc would stop a send, and exits.
+
+use std::{io, process, time::Duration};
+
+use crossterm::event::{self, Event};
+
+use crate::logging::log;
+use crate::shutdown;
+
+/// [`event::poll`], exiting if the terminal is gone.
+pub fn poll(timeout: Duration) -> io::Result<bool> {
+    event::poll(timeout).map_err(|e| terminal_lost(&e))
+}
+
+/// [`event::read`], exiting if the terminal is gone.
+pub fn read() -> io::Result<Event> {
+    event::read().map_err(|e| terminal_lost(&e))
+}
+
+/// Shut down and exit; there's no terminal left to clean up.
+fn terminal_lost(error: &io::Error) -> ! {
+    log(&format!("Terminal lost: {}", error));
+    shutdown::shutdown("terminal lost");
+    log("MadTyping exited");
+    process::exit(1)
+}
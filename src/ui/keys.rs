@@ -0,0 +1,100 @@
+//! Key event policy
+//!
+//! Some Windows terminals report a held key as a Press followed by Repeat
+//! events, and with crossterm's keyboard enhancement flags releases arrive
+//! too. Every screen, the send controls included, filters its key events
+//! with [`accept`]: releases are dropped, and repeats only count for keys
+//! that move a cursor or scroll. Holding Down keeps scrolling; holding
+//! Enter, Delete or Ctrl+A acts once.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+
+/// Whether a screen should act on `key`.
+pub fn accept(key: &KeyEvent) -> bool {
+    match key.kind {
+        KeyEventKind::Press => true,
+        KeyEventKind::Repeat => repeats(key.code),
+        KeyEventKind::Release => false,
+    }
+}
+
+/// Keys that act again while held: cursor and scroll keys, and Backspace
+/// in text fields.
+fn repeats(code: KeyCode) -> bool {
+    matches!(
+        code,
+        KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End
+            | KeyCode::Backspace
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::files::{Line, TextFile};
+    use crossterm::event::{KeyEventState, KeyModifiers};
+    use std::path::PathBuf;
+
+    fn event(code: KeyCode, kind: KeyEventKind) -> KeyEvent {
+        KeyEvent { code, modifiers: KeyModifiers::NONE, kind, state: KeyEventState::NONE }
+    }
+
+    /// Press, `repeats` Repeat events and a Release of `code`.
+    fn hold(code: KeyCode, repeats: usize) -> Vec<KeyEvent> {
+        let mut events = vec![event(code, KeyEventKind::Press)];
+        events.extend((0..repeats).map(|_| event(code, KeyEventKind::Repeat)));
+        events.push(event(code, KeyEventKind::Release));
+        events
+    }
+
+    #[test]
+    fn test_repeats_move_but_never_act() {
+        let files = (0..6)
+            .map(|i| TextFile {
+                name: format!("{}.txt", i),
+                path: PathBuf::from(format!("{}.txt", i)),
+                lines: vec![Line::new("gg")],
+                warnings: Vec::new(),
+                modified: None,
+                tags: Vec::new(),
+                front: Default::default(),
+                provenance: Default::default(),
+            })
+            .collect();
+        let mut app = App::new(files);
+
+        // Held Down, then held Enter, then a tap of Up
+        let script = [hold(KeyCode::Down, 3), hold(KeyCode::Enter, 5), hold(KeyCode::Up, 0)].concat();
+        let mut sends = 0;
+        for key in script.iter().filter(|key| accept(key)) {
+            match key.code {
+                KeyCode::Down => app.move_down(),
+                KeyCode::Up => app.move_up(),
+                KeyCode::Enter => sends += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(app.selected_index(), 3);
+        assert_eq!(sends, 1);
+    }
+
+    #[test]
+    fn test_policy_per_key() {
+        for code in [KeyCode::PageDown, KeyCode::Home, KeyCode::Left, KeyCode::Backspace] {
+            assert!(accept(&event(code, KeyEventKind::Repeat)), "{:?}", code);
+        }
+        for code in [KeyCode::Enter, KeyCode::Delete, KeyCode::Esc, KeyCode::Char('a'), KeyCode::Char('+')] {
+            assert!(accept(&event(code, KeyEventKind::Press)), "{:?}", code);
+            assert!(!accept(&event(code, KeyEventKind::Repeat)), "{:?}", code);
+        }
+        assert!(!accept(&event(KeyCode::Down, KeyEventKind::Release)));
+    }
+}
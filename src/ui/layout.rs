@@ -0,0 +1,113 @@
+//! Screen layout for the terminal UI
+//!
+//! Separators, titles and footer hints are sized from the terminal width
+//! at each render, so they span the screen without wrapping. The last
+//! column is left free because writing it moves some consoles to the next
+//! line. Terminals narrower than [`MIN_LAYOUT_WIDTH`] are laid out at that
+//! width.
+
+use crate::config::MIN_LAYOUT_WIDTH;
+
+use super::probe;
+
+/// Separator between footer hints.
+pub const HINT_SEPARATOR: &str = " │ ";
+
+/// Content width of the screen being rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub width: usize,
+}
+
+impl Layout {
+    /// Layout for a terminal `columns` wide.
+    pub fn new(columns: u16) -> Self {
+        Self { width: (columns as usize).saturating_sub(1).max(MIN_LAYOUT_WIDTH) }
+    }
+
+    /// Layout for the terminal as it is now.
+    pub fn current() -> Self {
+        Self::new(probe::screen_size().0)
+    }
+
+    /// Double line above and below titles.
+    pub fn rule(&self) -> String {
+        "═".repeat(self.width)
+    }
+
+    /// Single line above footers.
+    pub fn separator(&self) -> String {
+        "─".repeat(self.width)
+    }
+
+    /// `title` centered on a full-width line, cut to fit.
+    pub fn centered(&self, title: &str) -> String {
+        let title = self.fit(title, self.width);
+        let width = title.chars().count();
+        let left = (self.width - width) / 2;
+        format!("{}{}{}", " ".repeat(left), title, " ".repeat(self.width - width - left))
+    }
+
+    /// `text` cut to `room` characters, ending in "…" when cut.
+    pub fn fit(&self, text: &str, room: usize) -> String {
+        if text.chars().count() <= room {
+            return text.to_string();
+        }
+        let mut cut: String = text.chars().take(room.saturating_sub(1)).collect();
+        if room > 0 {
+            cut.push('…');
+        }
+        cut
+    }
+
+    /// How many of `hints`, from the first, fit on a footer line after its
+    /// leading space.
+    pub fn hints_that_fit(&self, hints: &[&str]) -> usize {
+        let mut used = 1;
+        for (i, hint) in hints.iter().enumerate() {
+            let needed = hint.chars().count() + if i == 0 { 0 } else { HINT_SEPARATOR.chars().count() };
+            if used + needed > self.width {
+                return i;
+            }
+            used += needed;
+        }
+        hints.len()
+    }
+
+    /// Width taken by the first `count` hints and their leading space.
+    pub fn hints_width(&self, hints: &[&str], count: usize) -> usize {
+        1 + hints[..count].iter().map(|h| h.chars().count()).sum::<usize>()
+            + count.saturating_sub(1) * HINT_SEPARATOR.chars().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN_HINTS: &[&str] =
+        &["[↑↓] Navigate", "[/] Search", "[Enter] Run", "[Tab] View", "[F1] Help", "[F2] Settings", "[Esc] Quit"];
+
+    #[test]
+    fn test_lines_fit_each_width() {
+        for (columns, width) in [(50, 49), (80, 79), (120, 119), (20, MIN_LAYOUT_WIDTH)] {
+            let layout = Layout::new(columns);
+            assert_eq!(layout.width, width);
+            assert_eq!(layout.rule().chars().count(), width);
+            assert_eq!(layout.separator().chars().count(), width);
+            assert_eq!(layout.centered("MadTyping (in game)").chars().count(), width);
+            let count = layout.hints_that_fit(MAIN_HINTS);
+            assert!(layout.hints_width(MAIN_HINTS, count) <= width, "{} columns", columns);
+        }
+    }
+
+    #[test]
+    fn test_layout_snapshots() {
+        let narrow = Layout::new(50);
+        assert_eq!(narrow.centered("  Viewing: a very long file name that cannot fit.txt  "), "  Viewing: a very long file name that cannot fit…");
+        assert_eq!(narrow.centered("Help"), format!("{}Help{}", " ".repeat(22), " ".repeat(23)));
+        assert_eq!(narrow.hints_that_fit(MAIN_HINTS), 3);
+        assert_eq!(Layout::new(80).hints_that_fit(MAIN_HINTS), 5);
+        assert_eq!(Layout::new(120).hints_that_fit(MAIN_HINTS), MAIN_HINTS.len());
+    }
+}
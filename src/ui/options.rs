@@ -0,0 +1,163 @@
+//! Send options overlay
+//!
+//! The rows shown after Enter in the file list, one per [`SendChoices`]
+//! field with its current value. Up and Down pick a row, Left and Right
+//! (or Space) change it, Enter sends and Esc goes back to the list. Key
+//! handling is kept apart from drawing so key sequences can be tested.
+
+use crossterm::event::KeyCode;
+
+use crate::lang;
+use crate::platform::ChatScope;
+use crate::send::{BroadcastMode, SendChoices};
+
+/// The option rows, in display order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Channel,
+    Order,
+    Speed,
+    SkipRepeats,
+    ManualAdvance,
+    DryRun,
+}
+
+pub const FIELDS: [Field; 6] = [Field::Channel, Field::Order, Field::Speed, Field::SkipRepeats, Field::ManualAdvance, Field::DryRun];
+
+/// What a key did to the overlay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Still open
+    Open,
+    /// Send with these choices
+    Send(SendChoices),
+    /// Back to the list without sending
+    Cancel,
+}
+
+/// The overlay's state: the choices so far and the highlighted row.
+#[derive(Clone, Debug)]
+pub struct OptionsOverlay {
+    choices: SendChoices,
+    selected: usize,
+}
+
+impl OptionsOverlay {
+    /// Open the overlay showing `choices`, with the first row highlighted.
+    pub fn new(choices: SendChoices) -> Self {
+        Self { choices, selected: 0 }
+    }
+
+    /// Index of the highlighted row in [`FIELDS`].
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Handle a key press.
+    pub fn key(&mut self, code: KeyCode) -> Outcome {
+        match code {
+            KeyCode::Enter => return Outcome::Send(self.choices),
+            KeyCode::Esc => return Outcome::Cancel,
+            KeyCode::Up => self.selected = self.selected.checked_sub(1).unwrap_or(FIELDS.len() - 1),
+            KeyCode::Down | KeyCode::Tab => self.selected = (self.selected + 1) % FIELDS.len(),
+            KeyCode::Left => self.change(false),
+            KeyCode::Right | KeyCode::Char(' ') => self.change(true),
+            _ => {}
+        }
+        Outcome::Open
+    }
+
+    /// Step the highlighted option forward or back.
+    fn change(&mut self, forward: bool) {
+        let choices = &mut self.choices;
+        match FIELDS[self.selected] {
+            Field::Channel => {
+                choices.chat = if choices.chat == ChatScope::Team { ChatScope::All } else { ChatScope::Team };
+            }
+            Field::Order => {
+                choices.order = match choices.order {
+                    BroadcastMode::PerLine => BroadcastMode::PerFile,
+                    BroadcastMode::PerFile => BroadcastMode::PerLine,
+                };
+            }
+            Field::Speed => choices.speed = if forward { choices.speed.faster() } else { choices.speed.slower() },
+            Field::SkipRepeats => choices.skip_repeats = !choices.skip_repeats,
+            Field::ManualAdvance => choices.manual_advance = !choices.manual_advance,
+            Field::DryRun => choices.dry_run = !choices.dry_run,
+        }
+    }
+
+    /// Label and value of each row. `repeats` is how many lines skipping
+    /// repeats would leave out.
+    pub fn rows(&self, repeats: usize) -> Vec<(String, String)> {
+        let on_off = |on: bool| lang::text(if on { "options.on" } else { "options.off" }).to_string();
+        let choices = &self.choices;
+        FIELDS
+            .iter()
+            .map(|field| match field {
+                Field::Channel => (
+                    lang::text("options.channel").to_string(),
+                    lang::text(if choices.chat == ChatScope::Team { "options.team" } else { "options.all" }).to_string(),
+                ),
+                Field::Order => (
+                    lang::text("options.order").to_string(),
+                    lang::text(match choices.order {
+                        BroadcastMode::PerLine => "options.per_line",
+                        BroadcastMode::PerFile => "options.per_file",
+                    })
+                    .to_string(),
+                ),
+                Field::Speed => (lang::text("options.speed").to_string(), choices.speed.label()),
+                Field::SkipRepeats => (
+                    lang::format("options.skip_repeats", &[("count", &repeats)]),
+                    on_off(choices.skip_repeats),
+                ),
+                Field::ManualAdvance => (lang::text("options.manual").to_string(), on_off(choices.manual_advance)),
+                Field::DryRun => (lang::text("options.dry_run").to_string(), on_off(choices.dry_run)),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(overlay: &mut OptionsOverlay, keys: &[KeyCode]) -> Outcome {
+        keys.iter().fold(Outcome::Open, |_, &key| overlay.key(key))
+    }
+
+    #[test]
+    fn test_enter_right_away_keeps_the_defaults() {
+        let defaults = SendChoices { order: BroadcastMode::PerFile, ..SendChoices::default() };
+        assert_eq!(OptionsOverlay::new(defaults).key(KeyCode::Enter), Outcome::Send(defaults));
+        assert_eq!(OptionsOverlay::new(defaults).key(KeyCode::Esc), Outcome::Cancel);
+    }
+
+    #[test]
+    fn test_scripted_keys_change_each_option() {
+        let mut overlay = OptionsOverlay::new(SendChoices::default());
+        use KeyCode::*;
+        let outcome = press(
+            &mut overlay,
+            &[Right, Down, Right, Down, Right, Right, Left, Down, Char(' '), Down, Right, Down, Right, Char('x'), Enter],
+        );
+        let Outcome::Send(choices) = outcome else { panic!("not sent: {:?}", outcome) };
+        assert_eq!(choices.chat, ChatScope::Team);
+        assert_eq!(choices.order, BroadcastMode::PerFile);
+        assert_eq!(choices.speed.label(), "1.5x");
+        assert!(choices.skip_repeats && choices.manual_advance && choices.dry_run);
+        assert_eq!(overlay.selected(), 5);
+    }
+
+    #[test]
+    fn test_selection_wraps_around() {
+        let mut overlay = OptionsOverlay::new(SendChoices::default());
+        assert_eq!(overlay.key(KeyCode::Up), Outcome::Open);
+        assert_eq!(overlay.selected(), FIELDS.len() - 1);
+        overlay.key(KeyCode::Down);
+        assert_eq!(overlay.selected(), 0);
+        assert_eq!(overlay.rows(3).len(), FIELDS.len());
+        assert_eq!(overlay.rows(3)[3].0, "Skip repeated lines (3)");
+    }
+}
@@ -0,0 +1,356 @@
+//! Terminal capability probe
+//!
+//! The legacy Windows console, mintty (Git Bash) and Wine's console can
+//! report a wrong size or ignore escape sequences, which garbles the UI.
+//! At startup [`Probe::run`] asks the terminal for its size and the cursor
+//! position and reads a few environment variables; [`select`] turns the
+//! answers into the [`Capabilities`] the UI renders with. A terminal that
+//! can't be trusted gets the plain mode: no alternate screen, colors or
+//! mouse, a layout for a fixed [`FALLBACK_SCREEN_SIZE`], and [`Output`]
+//! swaps the UI's lines, arrows and status glyphs for ASCII ones of the
+//! same width.
+
+use std::{
+    env,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use crossterm::{cursor, style, terminal};
+
+use crate::config::{FALLBACK_SCREEN_SIZE, MAX_SCREEN_SIZE};
+use crate::lang;
+
+/// Environment variables that identify odd consoles.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TermEnv {
+    /// `TERM`
+    pub term: Option<String>,
+    /// `TERM_PROGRAM`
+    pub term_program: Option<String>,
+    /// `MSYSTEM` is set: an MSYS2 or Git Bash shell
+    pub msys: bool,
+    /// `WT_SESSION` is set: running in Windows Terminal
+    pub windows_terminal: bool,
+    /// `WINEPREFIX` or `WINELOADER` is set
+    pub wine: bool,
+    /// `NO_COLOR` is set and not empty
+    pub no_color: bool,
+}
+
+impl TermEnv {
+    /// The environment of this process.
+    pub fn current() -> Self {
+        let set = |name: &str| env::var_os(name).is_some_and(|v| !v.is_empty());
+        Self {
+            term: env::var("TERM").ok(),
+            term_program: env::var("TERM_PROGRAM").ok(),
+            msys: set("MSYSTEM"),
+            windows_terminal: set("WT_SESSION"),
+            wine: set("WINEPREFIX") || set("WINELOADER"),
+            no_color: set("NO_COLOR"),
+        }
+    }
+
+    /// `TERM=dumb`: escape sequences aren't understood at all.
+    fn is_dumb(&self) -> bool {
+        self.term.as_deref() == Some("dumb")
+    }
+
+    /// mintty, which Git Bash runs in unless started from Windows Terminal.
+    fn is_mintty(&self) -> bool {
+        self.term_program.as_deref() == Some("mintty") || (self.msys && !self.windows_terminal)
+    }
+}
+
+/// What the terminal answered at startup.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Probe {
+    /// Size the terminal reported
+    pub size: Option<(u16, u16)>,
+    /// Whether the terminal answered a cursor position query
+    pub answers_queries: bool,
+    /// Whether escape sequences are honored; false on a Windows console
+    /// without virtual terminal support
+    pub ansi: bool,
+    /// Colors the terminal claims to have
+    pub colors: u16,
+    pub env: TermEnv,
+}
+
+impl Probe {
+    /// Ask the terminal, which must already be in raw mode. A dumb
+    /// terminal isn't queried.
+    pub fn run() -> Self {
+        let env = TermEnv::current();
+        Self {
+            size: terminal::size().ok(),
+            answers_queries: !env.is_dumb() && cursor::position().is_ok(),
+            ansi: ansi_supported(),
+            colors: style::available_color_count(),
+            env,
+        }
+    }
+
+    /// One line for the log.
+    pub fn summary(&self) -> String {
+        format!(
+            "size {}, answers queries: {}, ANSI: {}, {} colors, TERM={}, TERM_PROGRAM={}{}{}",
+            self.size.map(|(w, h)| format!("{}x{}", w, h)).unwrap_or_else(|| "unknown".to_string()),
+            self.answers_queries,
+            self.ansi,
+            self.colors,
+            self.env.term.as_deref().unwrap_or("-"),
+            self.env.term_program.as_deref().unwrap_or("-"),
+            if self.env.msys { ", MSYS" } else { "" },
+            if self.env.wine { ", Wine" } else { "" },
+        )
+    }
+}
+
+#[cfg(windows)]
+fn ansi_supported() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(not(windows))]
+fn ansi_supported() -> bool {
+    true
+}
+
+/// Why the UI doesn't use the terminal's full features.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// `TERM=dumb`
+    Dumb,
+    /// The cursor position query went unanswered
+    NoAnswer,
+    /// A Windows console without virtual terminal support
+    NoAnsi,
+    /// Running under Wine
+    Wine,
+    /// Running in mintty, whose reported size can be off
+    Mintty,
+    /// No size, or an impossible one, was reported
+    BadSize,
+}
+
+impl Problem {
+    /// Short description for the about screen.
+    pub fn label(self) -> &'static str {
+        lang::text(match self {
+            Problem::Dumb => "terminal.dumb",
+            Problem::NoAnswer => "terminal.no_answer",
+            Problem::NoAnsi => "terminal.no_ansi",
+            Problem::Wine => "terminal.wine",
+            Problem::Mintty => "terminal.mintty",
+            Problem::BadSize => "terminal.bad_size",
+        })
+    }
+
+    /// Whether escape sequences beyond cursor moves can't be relied on.
+    fn needs_plain(self) -> bool {
+        matches!(self, Problem::Dumb | Problem::NoAnswer | Problem::NoAnsi | Problem::Wine)
+    }
+}
+
+/// How the UI uses the terminal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Draw on the alternate screen, with mouse capture
+    pub alternate_screen: bool,
+    pub colors: bool,
+    /// Lines and status glyphs in ASCII
+    pub ascii: bool,
+    /// Size to lay out for instead of the terminal's
+    pub fixed_size: Option<(u16, u16)>,
+    /// What was wrong, empty for a well-behaved terminal
+    pub problems: Vec<Problem>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self { alternate_screen: true, colors: true, ascii: false, fixed_size: None, problems: Vec::new() }
+    }
+}
+
+impl Capabilities {
+    /// The mode and what caused it, for the about screen and the log.
+    pub fn describe(&self) -> String {
+        if self.problems.is_empty() {
+            return lang::text(if self.colors { "terminal.full" } else { "terminal.no_colors" }).to_string();
+        }
+        let (width, height) = self.fixed_size.unwrap_or(FALLBACK_SCREEN_SIZE);
+        let problems = self.problems.iter().map(|p| p.label()).collect::<Vec<_>>().join(", ");
+        let id = if self.ascii { "terminal.plain" } else { "terminal.fixed" };
+        lang::format(id, &[("width", &width), ("height", &height), ("problems", &problems)])
+    }
+}
+
+/// The capabilities to use for what `probe` found. A terminal that can't be
+/// queried or is known to mishandle escape sequences gets the plain mode;
+/// one whose size can't be trusted gets the fixed layout.
+pub fn select(probe: &Probe) -> Capabilities {
+    let mut problems = Vec::new();
+    if probe.env.is_dumb() {
+        problems.push(Problem::Dumb);
+    } else if !probe.answers_queries {
+        problems.push(Problem::NoAnswer);
+    }
+    if !probe.ansi {
+        problems.push(Problem::NoAnsi);
+    }
+    if probe.env.wine {
+        problems.push(Problem::Wine);
+    }
+    if probe.env.is_mintty() {
+        problems.push(Problem::Mintty);
+    }
+    let (max_width, max_height) = MAX_SCREEN_SIZE;
+    if !probe.size.is_some_and(|(w, h)| w > 0 && h > 0 && w <= max_width && h <= max_height) {
+        problems.push(Problem::BadSize);
+    }
+
+    let plain = problems.iter().any(|p| p.needs_plain());
+    Capabilities {
+        alternate_screen: !plain,
+        colors: !plain && !probe.env.no_color && probe.colors >= 8,
+        ascii: plain,
+        fixed_size: (!problems.is_empty()).then_some(FALLBACK_SCREEN_SIZE),
+        problems,
+    }
+}
+
+/// The capabilities the UI runs with; full ones until [`activate`].
+static ACTIVE: Mutex<Option<Capabilities>> = Mutex::new(None);
+
+/// Run the UI with `capabilities` from now on.
+pub fn activate(capabilities: Capabilities) {
+    style::force_color_output(capabilities.colors);
+    *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(capabilities);
+}
+
+/// The capabilities the UI runs with.
+pub fn active() -> Capabilities {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).clone().unwrap_or_default()
+}
+
+/// Size to lay the screen out for: the fixed one, or the terminal's.
+pub fn screen_size() -> (u16, u16) {
+    match active().fixed_size {
+        Some(size) => size,
+        None => terminal::size().unwrap_or(FALLBACK_SCREEN_SIZE),
+    }
+}
+
+/// ASCII stand-ins for the glyphs the UI draws, one column each.
+const ASCII_GLYPHS: &[(char, char)] = &[
+    ('═', '='), ('─', '-'), ('│', '|'), ('█', '#'), ('·', '.'), ('—', '-'), ('•', '*'), ('…', '.'),
+    ('←', '<'), ('→', '>'), ('↑', '^'), ('↓', 'v'), ('▶', '>'), ('▸', '>'), ('►', '>'), ('◀', '<'), ('▾', 'v'),
+    ('○', 'o'), ('◐', '~'), ('✓', '+'), ('✅', '+'), ('✗', 'x'), ('❌', 'x'), ('⛔', 'x'), ('⊘', '-'),
+    ('⚠', '!'), ('⌨', '*'), ('⏱', '*'), ('⚙', '*'), ('✉', '*'), ('✋', '*'), ('⧉', '*'), ('📋', '*'),
+    // The scanning spinner
+    ('⠋', '|'), ('⠙', '/'), ('⠹', '-'), ('⠸', '\\'), ('⠼', '|'), ('⠴', '/'), ('⠦', '-'), ('⠧', '\\'), ('⠇', '|'),
+    ('⠏', '/'),
+];
+
+/// `text` with the UI's glyphs in ASCII. Other characters, such as those
+/// of a message, are kept.
+pub fn to_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| ASCII_GLYPHS.iter().find(|(glyph, _)| *glyph == c).map_or(c, |&(_, ascii)| ascii))
+        .collect()
+}
+
+/// Standard output of the UI, writing glyphs in ASCII in the plain mode.
+pub struct Output {
+    inner: io::Stdout,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self { inner: io::stdout() }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Commands write whole strings, so a glyph is never split
+        match std::str::from_utf8(buf) {
+            Ok(text) if !text.is_ascii() && active().ascii => {
+                self.inner.write_all(to_ascii(text).as_bytes())?;
+                Ok(buf.len())
+            }
+            _ => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xterm() -> Probe {
+        Probe {
+            size: Some((120, 40)),
+            answers_queries: true,
+            ansi: true,
+            colors: 256,
+            env: TermEnv { term: Some("xterm-256color".to_string()), ..TermEnv::default() },
+        }
+    }
+
+    #[test]
+    fn test_well_behaved_terminals_keep_full_features() {
+        assert_eq!(select(&xterm()), Capabilities::default());
+        assert_eq!(select(&xterm()).describe(), "full");
+
+        let no_color = Probe { env: TermEnv { no_color: true, ..xterm().env }, ..xterm() };
+        assert_eq!(select(&no_color), Capabilities { colors: false, ..Capabilities::default() });
+
+        // Git Bash started from Windows Terminal is fine
+        let git_bash = Probe { env: TermEnv { msys: true, windows_terminal: true, ..xterm().env }, ..xterm() };
+        assert_eq!(select(&git_bash), Capabilities::default());
+    }
+
+    #[test]
+    fn test_untrusted_terminals_degrade() {
+        let plain = |problems| Capabilities {
+            alternate_screen: false,
+            colors: false,
+            ascii: true,
+            fixed_size: Some(FALLBACK_SCREEN_SIZE),
+            problems,
+        };
+
+        let silent = Probe { answers_queries: false, ..xterm() };
+        assert_eq!(select(&silent), plain(vec![Problem::NoAnswer]));
+
+        let conhost = Probe { ansi: false, colors: 8, env: TermEnv::default(), ..xterm() };
+        assert_eq!(select(&conhost), plain(vec![Problem::NoAnsi]));
+
+        let dumb = Probe { answers_queries: false, env: TermEnv { term: Some("dumb".to_string()), ..TermEnv::default() }, ..xterm() };
+        assert_eq!(select(&dumb), plain(vec![Problem::Dumb]));
+
+        let wine = Probe { size: Some((0, 0)), env: TermEnv { wine: true, ..TermEnv::default() }, ..xterm() };
+        assert_eq!(select(&wine), plain(vec![Problem::Wine, Problem::BadSize]));
+        assert_eq!(select(&wine).describe(), "plain ASCII, 80x24 layout (Wine, no usable size reported)");
+
+        // mintty answers queries, only its size is off
+        let mintty = Probe { size: None, env: TermEnv { msys: true, ..xterm().env }, ..xterm() };
+        let fixed = Capabilities { fixed_size: Some(FALLBACK_SCREEN_SIZE), problems: vec![Problem::Mintty, Problem::BadSize], ..Capabilities::default() };
+        assert_eq!(select(&mintty), fixed);
+        assert_eq!(select(&Probe { size: Some((u16::MAX, 24)), ..xterm() }).problems, vec![Problem::BadSize]);
+    }
+
+    #[test]
+    fn test_ascii_glyphs_keep_width_and_messages() {
+        assert_eq!(to_ascii("═══ ► • gg.txt ✓ │ [↑↓] Navigate ⠹"), "=== > * gg.txt + | [^v] Navigate -");
+        assert_eq!(to_ascii("ありがとう gg wp"), "ありがとう gg wp");
+        assert!(ASCII_GLYPHS.iter().all(|(_, ascii)| ascii.is_ascii()));
+    }
+}
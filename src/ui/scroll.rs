@@ -0,0 +1,71 @@
+//! Viewer scrolling
+//!
+//! Where the viewer's first visible row ends up for the page keys, End and
+//! jumps to a line. Offsets never go past the last full page, so the end of
+//! a file always fills the screen.
+
+/// First row of the page above `offset`.
+pub fn page_up(offset: usize, visible: usize) -> usize {
+    offset.saturating_sub(visible)
+}
+
+/// First row of the page below `offset`, stopping at the last full page.
+pub fn page_down(offset: usize, visible: usize, rows: usize) -> usize {
+    (offset + visible).min(last_page(visible, rows))
+}
+
+/// First row of the last full page of `rows` rows.
+pub fn last_page(visible: usize, rows: usize) -> usize {
+    rows.saturating_sub(visible)
+}
+
+/// First row that puts `row` in the middle of the view, as close as the
+/// first and last pages allow.
+pub fn centered(row: usize, visible: usize, rows: usize) -> usize {
+    row.saturating_sub(visible / 2).min(last_page(visible, rows))
+}
+
+/// Index of the message for a typed line number (counting from 1),
+/// clamped to the `count` messages of the file. None if the input isn't a
+/// number or the file is empty.
+pub fn line_index(input: &str, count: usize) -> Option<usize> {
+    let number: u64 = input.trim().parse().ok()?;
+    let last = count.checked_sub(1)?;
+    Some(number.saturating_sub(1).min(last as u64) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_keys_stay_within_the_file() {
+        assert_eq!(page_up(30, 20), 10);
+        assert_eq!(page_up(5, 20), 0);
+        assert_eq!(page_down(0, 20, 100), 20);
+        assert_eq!(page_down(70, 20, 100), 80);
+        assert_eq!(page_down(0, 20, 10), 0);
+        assert_eq!(last_page(20, 100), 80);
+        assert_eq!(last_page(20, 5), 0);
+    }
+
+    #[test]
+    fn test_centered_clamps_to_first_and_last_page() {
+        assert_eq!(centered(310, 20, 500), 300);
+        assert_eq!(centered(3, 20, 500), 0);
+        assert_eq!(centered(495, 20, 500), 480);
+        assert_eq!(centered(4, 20, 10), 0);
+        assert_eq!(centered(10, 1, 500), 10);
+    }
+
+    #[test]
+    fn test_line_index_clamps_out_of_range_numbers() {
+        assert_eq!(line_index("311", 500), Some(310));
+        assert_eq!(line_index(" 1 ", 500), Some(0));
+        assert_eq!(line_index("0", 500), Some(0));
+        assert_eq!(line_index("9999999999999999999", 500), Some(499));
+        assert_eq!(line_index("-3", 500), None);
+        assert_eq!(line_index("abc", 500), None);
+        assert_eq!(line_index("4", 0), None);
+    }
+}
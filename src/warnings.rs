@@ -0,0 +1,171 @@
+//! Warn-once registry for recurring warnings
+//!
+//! Non-fatal warnings such as a file's parse problems, the scan cap, a
+//! fullscreen game or a folder the watcher can't list come back on every
+//! refresh or send. Emitters report them under a stable id (for example
+//! `file:openers.txt:line 3 ...` or `scan:cap`) and only show them the
+//! first time in a session; repeats are counted and the count goes to the
+//! log. An id can also be hidden for good ("don't show again"), which is
+//! kept in the state file and undone from the settings screen.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use crate::logging::log;
+use crate::persistence::{self, State};
+
+/// State key of the hidden warning ids, a [`State::set_list`] list.
+const STATE_KEY: &str = "warnings.suppressed";
+
+/// Which warnings were seen this session, and which are hidden.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Registry {
+    counts: HashMap<String, u32>,
+    suppressed: BTreeSet<String>,
+}
+
+impl Registry {
+    /// A registry hiding the `suppressed` ids.
+    pub fn new(suppressed: impl IntoIterator<Item = String>) -> Self {
+        Self { counts: HashMap::new(), suppressed: suppressed.into_iter().collect() }
+    }
+
+    /// The ids hidden in `state`.
+    pub fn from_state(state: &State) -> Self {
+        Self::new(state.get_list(STATE_KEY))
+    }
+
+    /// Store the hidden ids in `state`.
+    pub fn save_to(&self, state: &mut State) {
+        state.set_list(STATE_KEY, &self.suppressed);
+    }
+
+    /// Count warning `id`. Returns whether to show it: the first time this
+    /// session, unless it's hidden. Repeats are logged with their count
+    /// at 2, 4, 8, ... so a warning hit on every refresh stays one line
+    /// per doubling.
+    pub fn report(&mut self, id: &str) -> bool {
+        let id = storable(id);
+        let count = self.counts.entry(id.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 && count.is_power_of_two() {
+            log(&format!("Warning '{}' repeated ({} times this session)", id, count));
+        }
+        *count == 1 && !self.suppressed.contains(&id)
+    }
+
+    /// How often `id` was reported this session.
+    pub fn count(&self, id: &str) -> u32 {
+        self.counts.get(&storable(id)).copied().unwrap_or(0)
+    }
+
+    /// Whether `id` is hidden.
+    pub fn is_suppressed(&self, id: &str) -> bool {
+        self.suppressed.contains(&storable(id))
+    }
+
+    /// Hide `id` from now on. Returns false if it already was.
+    pub fn suppress(&mut self, id: &str) -> bool {
+        self.suppressed.insert(storable(id))
+    }
+
+    /// Show `id` again, the next time it's reported. Returns false if it
+    /// wasn't hidden.
+    pub fn unsuppress(&mut self, id: &str) -> bool {
+        let id = storable(id);
+        self.counts.remove(&id);
+        self.suppressed.remove(&id)
+    }
+
+    /// The hidden ids, sorted.
+    pub fn suppressed(&self) -> Vec<String> {
+        self.suppressed.iter().cloned().collect()
+    }
+}
+
+/// `id` as kept in the state file, which has one value per line.
+fn storable(id: &str) -> String {
+    id.replace(['\n', '\r'], ",")
+}
+
+/// The session's registry, loaded from the state file on first use.
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> T) -> T {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    f(registry.get_or_insert_with(|| Registry::from_state(&persistence::load())))
+}
+
+/// Count warning `id` in the session's registry; see [`Registry::report`].
+pub fn report(id: &str) -> bool {
+    with_registry(|r| r.report(id))
+}
+
+/// Whether `id` is hidden in the session's registry.
+pub fn is_suppressed(id: &str) -> bool {
+    with_registry(|r| r.is_suppressed(id))
+}
+
+/// The ids hidden for good, sorted.
+pub fn suppressed() -> Vec<String> {
+    with_registry(|r| r.suppressed())
+}
+
+/// Hide warning `id` for good and save that in the state file.
+pub fn suppress(id: &str) -> Result<(), String> {
+    with_registry(|r| r.suppress(id));
+    save()
+}
+
+/// Show warning `id` again and save that in the state file.
+pub fn unsuppress(id: &str) -> Result<(), String> {
+    with_registry(|r| r.unsuppress(id));
+    save()
+}
+
+fn save() -> Result<(), String> {
+    let mut state = persistence::load();
+    with_registry(|r| r.save_to(&mut state));
+    persistence::save(&state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_warning_shown_once_and_counted() {
+        let mut registry = Registry::default();
+        assert!(registry.report("scan:cap"));
+        assert!(!registry.report("scan:cap"));
+        assert!(!registry.report("scan:cap"));
+        assert!(registry.report("file:gg.txt:line 2 truncated"));
+        assert_eq!((registry.count("scan:cap"), registry.count("duplicates")), (3, 0));
+    }
+
+    #[test]
+    fn test_suppressed_ids_survive_the_state_file() {
+        let mut registry = Registry::default();
+        assert!(registry.suppress("display:fullscreen:League"));
+        assert!(registry.suppress("file:a.txt:bad; delay"));
+        assert!(registry.suppress("file:dir\\b.txt:bad"));
+        assert!(!registry.suppress("display:fullscreen:League"));
+        assert!(!registry.report("display:fullscreen:League"));
+        assert!(registry.is_suppressed("file:a.txt:bad; delay"));
+        assert_eq!(registry.count("display:fullscreen:League"), 1);
+        assert!(registry.unsuppress("display:fullscreen:League"));
+        assert!(registry.report("display:fullscreen:League"));
+        registry.suppress("display:fullscreen:League");
+
+        let mut state = State::default();
+        registry.save_to(&mut state);
+        let mut loaded = Registry::from_state(&State::parse(&state.serialize()));
+        assert_eq!(loaded.suppressed(), vec!["display:fullscreen:League", "file:a.txt:bad; delay", "file:dir\\b.txt:bad"]);
+
+        assert!(loaded.unsuppress("display:fullscreen:League"));
+        assert!(!loaded.unsuppress("display:fullscreen:League"));
+        assert!(loaded.report("display:fullscreen:League"));
+    }
+}
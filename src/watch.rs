@@ -0,0 +1,196 @@
+//! Message directory watcher
+//!
+//! While the file list is shown, [`DirWatcher`] lists the scan directory
+//! every [`WATCH_POLL_MS`] and compares modification times with the last
+//! listing. Editors write a file several times per save and leave temp,
+//! backup and lock files next to it, so changes go through a
+//! [`Debouncer`]: temp files are dropped, events for the same file are
+//! merged, and the batch is handed over once the directory has been quiet
+//! for [`WATCH_DEBOUNCE_MS`]. Each event is then applied with
+//! [`App::apply_file_event`](crate::app::App::apply_file_event), which
+//! re-reads only that file.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::config::{MAX_SCAN_DEPTH, WATCH_DEBOUNCE_MS, WATCH_POLL_MS};
+use crate::files::{self, is_temp_file, modified_time, ScanOptions};
+use crate::logging::log;
+use crate::warnings;
+
+/// A change to one file of the scan directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileEvent {
+    /// Added, or its modification time changed
+    Changed(PathBuf),
+    /// No longer there
+    Removed(PathBuf),
+}
+
+impl FileEvent {
+    /// The file the event is about.
+    pub fn path(&self) -> &Path {
+        match self {
+            FileEvent::Changed(path) | FileEvent::Removed(path) => path,
+        }
+    }
+}
+
+/// Collects file events until none arrived for a while, then hands them
+/// over as one batch with at most one event per file.
+#[derive(Debug)]
+pub struct Debouncer {
+    quiet: Duration,
+    /// Latest event per file, in the order files were first seen
+    pending: Vec<FileEvent>,
+    last: Option<Instant>,
+}
+
+impl Debouncer {
+    /// Hand batches over once no event arrived for `quiet`.
+    pub fn new(quiet: Duration) -> Self {
+        Self { quiet, pending: Vec::new(), last: None }
+    }
+
+    /// Add an event seen at `now`. Temp files are ignored; a later event
+    /// for a file replaces the earlier one.
+    pub fn push(&mut self, event: FileEvent, now: Instant) {
+        if is_temp_file(event.path()) {
+            return;
+        }
+        match self.pending.iter_mut().find(|e| e.path() == event.path()) {
+            Some(earlier) => *earlier = event,
+            None => self.pending.push(event),
+        }
+        self.last = Some(now);
+    }
+
+    /// The batch, if events are pending and none arrived in the quiet
+    /// period before `now`. Empty otherwise.
+    pub fn take(&mut self, now: Instant) -> Vec<FileEvent> {
+        match self.last {
+            Some(last) if now.saturating_duration_since(last) >= self.quiet => {
+                self.last = None;
+                std::mem::take(&mut self.pending)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Polls the scan directory for added, changed and removed message files.
+#[derive(Debug)]
+pub struct DirWatcher {
+    dir: PathBuf,
+    /// Modification time of every file in the last listing
+    known: HashMap<PathBuf, Option<SystemTime>>,
+    checked: Instant,
+    debouncer: Debouncer,
+}
+
+impl DirWatcher {
+    /// Watch `dir`, taking its current files as already loaded.
+    pub fn new(dir: PathBuf, options: ScanOptions) -> Self {
+        let known = listing(&dir, options).unwrap_or_default();
+        Self { dir, known, checked: Instant::now(), debouncer: Debouncer::new(Duration::from_millis(WATCH_DEBOUNCE_MS)) }
+    }
+
+    /// The watched directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// List the directory if it wasn't listed in the last
+    /// [`WATCH_POLL_MS`], and return the changes once they've settled.
+    pub fn poll(&mut self, options: ScanOptions) -> Vec<FileEvent> {
+        let now = Instant::now();
+        if now.duration_since(self.checked) >= Duration::from_millis(WATCH_POLL_MS) {
+            self.checked = now;
+            // A directory that can't be listed right now changes nothing
+            if let Some(current) = listing(&self.dir, options) {
+                for event in changes(&self.known, &current) {
+                    self.debouncer.push(event, now);
+                }
+                self.known = current;
+            }
+        }
+        self.debouncer.take(now)
+    }
+}
+
+/// Modification times of the supported files in `dir`, as a scan with
+/// `options` would find them. None if the directory can't be read.
+fn listing(dir: &Path, options: ScanOptions) -> Option<HashMap<PathBuf, Option<SystemTime>>> {
+    let depth = if options.recursive { MAX_SCAN_DEPTH } else { 0 };
+    match files::supported_paths(dir, depth, options.max_files) {
+        Ok(paths) => Some(
+            paths
+                .into_iter()
+                .map(|path| {
+                    let modified = modified_time(&path);
+                    (path, modified)
+                })
+                .collect(),
+        ),
+        Err(e) => {
+            if warnings::report(&format!("watch:{}", dir.display())) {
+                log(&format!("Watcher couldn't list {}: {}", dir.display(), e));
+            }
+            None
+        }
+    }
+}
+
+/// Events turning listing `old` into `new`, in path order.
+fn changes(old: &HashMap<PathBuf, Option<SystemTime>>, new: &HashMap<PathBuf, Option<SystemTime>>) -> Vec<FileEvent> {
+    let mut events: Vec<FileEvent> = new
+        .iter()
+        .filter(|(path, modified)| old.get(*path) != Some(modified))
+        .map(|(path, _)| FileEvent::Changed(path.clone()))
+        .chain(old.keys().filter(|path| !new.contains_key(*path)).map(|path| FileEvent::Removed(path.clone())))
+        .collect();
+    events.sort_by(|a, b| a.path().cmp(b.path()));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(name: &str) -> FileEvent {
+        FileEvent::Changed(PathBuf::from(name))
+    }
+
+    #[test]
+    fn test_burst_coalesced_into_one_batch() {
+        let start = Instant::now();
+        let ms = |n: u64| start + Duration::from_millis(n);
+        let mut debouncer = Debouncer::new(Duration::from_millis(300));
+
+        // An editor saving gg.txt: several writes, a swap file and a backup
+        debouncer.push(changed("gg.txt"), ms(0));
+        debouncer.push(changed(".gg.txt.swp"), ms(10));
+        debouncer.push(changed("gg.txt~"), ms(20));
+        debouncer.push(FileEvent::Removed(PathBuf::from("gg.txt")), ms(40));
+        debouncer.push(changed("gg.txt"), ms(60));
+        debouncer.push(changed("wp.txt"), ms(200));
+        assert!(debouncer.take(ms(300)).is_empty());
+        assert!(debouncer.take(ms(499)).is_empty());
+
+        assert_eq!(debouncer.take(ms(500)), vec![changed("gg.txt"), changed("wp.txt")]);
+        assert!(debouncer.take(ms(900)).is_empty());
+    }
+
+    #[test]
+    fn test_changes_between_listings() {
+        let time = |secs: u64| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let old = HashMap::from([(PathBuf::from("a.txt"), time(1)), (PathBuf::from("b.txt"), time(1))]);
+        let new = HashMap::from([(PathBuf::from("a.txt"), time(1)), (PathBuf::from("b.txt"), time(2)), (PathBuf::from("c.txt"), None)]);
+        assert_eq!(changes(&old, &new), vec![changed("b.txt"), changed("c.txt")]);
+        assert_eq!(changes(&new, &old), vec![changed("b.txt"), FileEvent::Removed(PathBuf::from("c.txt"))]);
+        assert!(changes(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Weighted random lines for MadTyping
+//!
+//! `Ctrl+R` sends one line of a file picked at random. A line ending in
+//! `{w=N}` comes up N times as often as a line without one (weight 1):
+//!
+//! ```text
+//! gg wp
+//! ff at 15 {w=5}
+//! report jungle {w=0}
+//! ```
+//!
+//! The suffix is removed before sending. CSV files and markdown tables
+//! can use a `weight` column instead. A weight of 0 or less leaves the
+//! line out of random picks; a full send still sends it.
+
+use std::{
+    collections::HashSet,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::files::Line;
+
+/// Start of a weight suffix; the number and `}` follow.
+const WEIGHT_SUFFIX: &str = "{w=";
+
+/// Header names (lowercase) recognized for the weight column.
+pub const WEIGHT_HEADERS: &[&str] = &["weight", "w"];
+
+/// Small seedable random number generator (SplitMix64), good enough to
+/// pick lines and reproducible in tests.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Generator that always yields the same numbers for `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generator seeded from the clock.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        Self::seeded(nanos ^ u64::from(std::process::id()))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`; `bound` must not be 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // Rejecting the uneven top of the range keeps every value equally likely
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+/// Parse a weight as written in a suffix or a column. Errors name the
+/// text that isn't a whole number.
+pub fn parse_weight(text: &str) -> Result<i64, String> {
+    text.trim().parse::<i64>().map_err(|_| format!("'{}' is not a whole number", text.trim()))
+}
+
+/// The weight a line gets for `value`: values below 1 become 0 (never
+/// picked), large ones are capped.
+pub fn clamp_weight(value: i64) -> u32 {
+    value.clamp(0, i64::from(u32::MAX)) as u32
+}
+
+/// Move `{w=N}` suffixes into [`Line::weight`], dropping lines that have
+/// no message before the suffix.
+///
+/// Returns warnings for weights that aren't numbers (the line is sent as
+/// is), weights of 0 or less and empty weighted lines, numbered like the
+/// warnings of [`crate::timed::apply_directives`].
+pub fn apply_suffixes(lines: &mut Vec<Line>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut number = 0;
+    lines.retain_mut(|line| {
+        number += 1;
+        let Some(start) = line.text.strip_suffix('}').and_then(|t| t.rfind(WEIGHT_SUFFIX)) else {
+            return true;
+        };
+        let value = &line.text[start + WEIGHT_SUFFIX.len()..line.text.len() - 1];
+        let weight = match parse_weight(value) {
+            Ok(weight) => weight,
+            Err(e) => {
+                warnings.push(format!("line {}: weight {}, sent as is", number, e));
+                return true;
+            }
+        };
+        let message = line.text[..start].trim_end().to_string();
+        if message.is_empty() {
+            warnings.push(format!("line {}: {{w={}}} has no message, ignored", number, weight));
+            number -= 1;
+            return false;
+        }
+        if weight < 1 {
+            warnings.push(zero_weight_warning(number, weight));
+        }
+        line.text = message;
+        line.weight = clamp_weight(weight);
+        true
+    });
+    warnings
+}
+
+/// Validation note for a line (or row) left out of random picks.
+pub fn zero_weight_warning(number: usize, weight: i64) -> String {
+    format!("line {}: weight {} leaves it out of random picks", number, weight)
+}
+
+/// Index into `weights` picked with probability proportional to its
+/// weight, None if every weight is 0.
+pub fn pick(weights: &[u32], rng: &mut Rng) -> Option<usize> {
+    let total: u64 = weights.iter().map(|&w| u64::from(w)).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut target = rng.below(total);
+    weights.iter().position(|&w| {
+        let w = u64::from(w);
+        if target < w {
+            true
+        } else {
+            target -= w;
+            false
+        }
+    })
+}
+
+/// Index of a line of `lines` to send at random, leaving out `excluded`
+/// ones; None if no line can be picked.
+pub fn pick_line(lines: &[Line], excluded: &HashSet<usize>, rng: &mut Rng) -> Option<usize> {
+    let weights: Vec<u32> =
+        lines.iter().enumerate().map(|(i, line)| if excluded.contains(&i) { 0 } else { line.weight }).collect();
+    pick(&weights, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suffixes_moved_into_lines() {
+        let mut lines: Vec<Line> = ["gg wp", "ff at 15 {w=5}", "report {w=0}", "mid {w=-2}", "{w=3}", "odd {w=lots}"]
+            .into_iter()
+            .map(Line::new)
+            .collect();
+        let warnings = apply_suffixes(&mut lines);
+        let texts: Vec<(&str, u32)> = lines.iter().map(|l| (l.text.as_str(), l.weight)).collect();
+        assert_eq!(
+            texts,
+            vec![("gg wp", 1), ("ff at 15", 5), ("report", 0), ("mid", 0), ("odd {w=lots}", 1)]
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "line 3: weight 0 leaves it out of random picks",
+                "line 4: weight -2 leaves it out of random picks",
+                "line 5: {w=3} has no message, ignored",
+                "line 5: weight 'lots' is not a whole number, sent as is",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_picks_follow_weights() {
+        // Chi-squared against the expected counts; 3 degrees of freedom
+        // stay under 16.27 (p = 0.001) for a fair sampler
+        let weights = [1, 5, 0, 2, 12];
+        let draws = 40_000;
+        let mut rng = Rng::seeded(0x5eed);
+        let mut counts = [0u32; 5];
+        for _ in 0..draws {
+            counts[pick(&weights, &mut rng).unwrap()] += 1;
+        }
+        assert_eq!(counts[2], 0);
+        let total: u32 = weights.iter().sum();
+        let chi_squared: f64 = weights
+            .iter()
+            .zip(counts)
+            .filter(|(w, _)| **w > 0)
+            .map(|(&w, count)| {
+                let expected = f64::from(draws) * f64::from(w) / f64::from(total);
+                (f64::from(count) - expected).powi(2) / expected
+            })
+            .sum();
+        assert!(chi_squared < 16.27, "chi-squared {:.2} for {:?}", chi_squared, counts);
+    }
+
+    #[test]
+    fn test_excluded_and_weightless_lines_never_picked() {
+        let lines = vec![Line::new("a"), Line { weight: 0, ..Line::new("b") }, Line::new("c")];
+        let mut rng = Rng::seeded(7);
+        let excluded = HashSet::from([0]);
+        assert!((0..100).all(|_| pick_line(&lines, &excluded, &mut rng) == Some(2)));
+        assert_eq!(pick_line(&lines, &HashSet::from([0, 2]), &mut rng), None);
+        assert_eq!(pick(&[], &mut rng), None);
+    }
+}
@@ -0,0 +1,257 @@
+//! Background filesystem worker for MadTyping
+//!
+//! Directory scans and file checks can take seconds on network shares or
+//! sleeping removable drives. [`FsWorker`] runs them on a separate thread
+//! so the UI keeps responding; the UI polls for results between key presses.
+//!
+//! Only one scan runs at a time. Refresh requests made while a scan is in
+//! flight are coalesced into a single follow-up scan. The worker registers
+//! with the [`shutdown`] coordinator, which stops it once its current
+//! request is done.
+
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::config::WORKER_POLL_MS;
+use crate::files::{self, Scan, ScanOptions, TextFile};
+use crate::shutdown::{self, Registration};
+
+/// Work sent to the worker thread.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FsRequest {
+    /// Re-discover all message files
+    Discover(ScanOptions),
+    /// Check whether a file changed since `known` and reload it if so
+    Check { path: PathBuf, known: Option<SystemTime> },
+}
+
+/// What happened to a checked file.
+#[derive(Clone, Debug)]
+pub enum FileChange {
+    /// Modification time is still the known one
+    Unchanged,
+    /// The file changed and was reloaded
+    Reloaded(TextFile),
+    /// The file changed but is now empty or unreadable
+    Unreadable(Option<SystemTime>),
+    /// The file no longer exists
+    Removed,
+}
+
+/// Results sent back by the worker thread.
+#[derive(Clone, Debug)]
+pub enum FsResponse {
+    /// Result of [`FsRequest::Discover`]
+    Discovered(Result<Scan, String>),
+    /// Result of [`FsRequest::Check`]
+    Checked { path: PathBuf, change: FileChange },
+}
+
+/// Perform a request with the real filesystem.
+pub fn handle(request: FsRequest) -> FsResponse {
+    match request {
+        FsRequest::Discover(options) => FsResponse::Discovered(files::scan(options)),
+        FsRequest::Check { path, known } => {
+            let change = if !path.exists() {
+                FileChange::Removed
+            } else {
+                let modified = files::modified_time(&path);
+                if modified == known {
+                    FileChange::Unchanged
+                } else {
+                    match TextFile::from_path(path.clone()) {
+                        Some(file) => FileChange::Reloaded(file),
+                        None => FileChange::Unreadable(modified),
+                    }
+                }
+            };
+            FsResponse::Checked { path, change }
+        }
+    }
+}
+
+/// Handle to the worker thread. Dropping it stops the thread once the
+/// current request is done.
+pub struct FsWorker {
+    requests: Sender<FsRequest>,
+    responses: Receiver<FsResponse>,
+    /// Responses received but not taken yet
+    stash: VecDeque<FsResponse>,
+    scanning: bool,
+    /// Options of the scan queued behind the running one
+    rescan: Option<ScanOptions>,
+    checking: bool,
+    _registration: Registration,
+}
+
+impl FsWorker {
+    /// Start a worker using the real filesystem.
+    pub fn spawn() -> Self {
+        Self::with_handler(handle)
+    }
+
+    /// Start a worker that answers requests with `handler`.
+    pub fn with_handler(mut handler: impl FnMut(FsRequest) -> FsResponse + Send + 'static) -> Self {
+        let (requests, worker_requests) = mpsc::channel::<FsRequest>();
+        let (worker_responses, responses) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                match worker_requests.recv_timeout(Duration::from_millis(WORKER_POLL_MS)) {
+                    Ok(request) => {
+                        if worker_responses.send(handler(request)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+        let _registration = shutdown::register("file worker", move || {
+            stop.store(true, Ordering::SeqCst);
+            let _ = thread.join();
+        });
+        Self { requests, responses, stash: VecDeque::new(), scanning: false, rescan: None, checking: false, _registration }
+    }
+
+    /// Start a scan. If one is already running, another one is queued to
+    /// run after it (once, however often this is called, with the latest
+    /// options). Returns whether a new scan started right away.
+    pub fn refresh(&mut self, options: ScanOptions) -> bool {
+        if self.scanning {
+            self.rescan = Some(options);
+            return false;
+        }
+        self.scanning = self.requests.send(FsRequest::Discover(options)).is_ok();
+        self.scanning
+    }
+
+    /// Check a file for changes unless a check is already in flight.
+    pub fn check(&mut self, path: PathBuf, known: Option<SystemTime>) -> bool {
+        if self.checking {
+            return false;
+        }
+        self.checking = self.requests.send(FsRequest::Check { path, known }).is_ok();
+        self.checking
+    }
+
+    /// Whether a scan is running or queued.
+    pub fn is_scanning(&mut self) -> bool {
+        self.pump();
+        self.scanning
+    }
+
+    /// Take the result of a finished scan, if there is one.
+    pub fn take_discovered(&mut self) -> Option<Result<Scan, String>> {
+        self.pump();
+        let index = self.stash.iter().position(|r| matches!(r, FsResponse::Discovered(_)))?;
+        match self.stash.remove(index) {
+            Some(FsResponse::Discovered(result)) => Some(result),
+            _ => None,
+        }
+    }
+
+    /// Take the result of a finished file check, if there is one.
+    pub fn take_checked(&mut self) -> Option<(PathBuf, FileChange)> {
+        self.pump();
+        let index = self.stash.iter().position(|r| matches!(r, FsResponse::Checked { .. }))?;
+        match self.stash.remove(index) {
+            Some(FsResponse::Checked { path, change }) => Some((path, change)),
+            _ => None,
+        }
+    }
+
+    /// Move arrived responses into the stash and start a queued rescan.
+    fn pump(&mut self) {
+        while let Ok(response) = self.responses.try_recv() {
+            match response {
+                FsResponse::Discovered(_) => {
+                    self.scanning = false;
+                    if let Some(options) = self.rescan.take() {
+                        self.refresh(options);
+                    }
+                }
+                FsResponse::Checked { .. } => self.checking = false,
+            }
+            self.stash.push_back(response);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+    /// Poll until `f` returns something (worker replies are asynchronous).
+    fn wait_for<T>(mut f: impl FnMut() -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(value) = f() {
+                return value;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for the worker");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_refresh_requests_coalesced() {
+        // Scans block until the test releases them
+        let (release, gate) = mpsc::channel::<()>();
+        let gate = Mutex::new(gate);
+        let scans = Arc::new(Mutex::new(0));
+        let counter = scans.clone();
+        let mut worker = FsWorker::with_handler(move |request| {
+            gate.lock().unwrap().recv().unwrap();
+            *counter.lock().unwrap() += 1;
+            match request {
+                FsRequest::Discover(_) => FsResponse::Discovered(Ok(Scan::default())),
+                FsRequest::Check { path, .. } => FsResponse::Checked { path, change: FileChange::Unchanged },
+            }
+        });
+
+        assert!(worker.refresh(ScanOptions::default()));
+        assert!(!worker.refresh(ScanOptions::default()));
+        assert!(!worker.refresh(ScanOptions::default()));
+        assert!(worker.is_scanning());
+
+        release.send(()).unwrap();
+        assert!(wait_for(|| worker.take_discovered()).is_ok());
+        // The two extra requests became one follow-up scan
+        assert!(worker.is_scanning());
+        release.send(()).unwrap();
+        assert!(wait_for(|| worker.take_discovered()).is_ok());
+        assert!(!worker.is_scanning());
+        assert_eq!(*scans.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_check_results_kept_apart_from_scans() {
+        let mut worker = FsWorker::with_handler(|request| match request {
+            FsRequest::Discover(_) => FsResponse::Discovered(Err("share offline".to_string())),
+            FsRequest::Check { path, .. } => FsResponse::Checked { path, change: FileChange::Removed },
+        });
+
+        assert!(worker.check(PathBuf::from("a.txt"), None));
+        assert!(!worker.check(PathBuf::from("b.txt"), None));
+        worker.refresh(ScanOptions::default());
+
+        let (path, change) = wait_for(|| worker.take_checked());
+        assert_eq!(path, PathBuf::from("a.txt"));
+        assert!(matches!(change, FileChange::Removed));
+        assert_eq!(wait_for(|| worker.take_discovered()).unwrap_err(), "share offline");
+        assert!(worker.check(PathBuf::from("b.txt"), None));
+    }
+}
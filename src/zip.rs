@@ -0,0 +1,397 @@
+//! Minimal zip archive reader and writer for MadTyping
+//!
+//! Reads the files of a zip archive into memory for pack imports. Only what
+//! common zip tools produce for small text packs is supported: stored and
+//! deflated entries, no encryption, no ZIP64. Every entry's CRC is checked.
+//! Exported packs are written with stored (uncompressed) entries.
+
+/// A file read from an archive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Path inside the archive, as stored (`/`-separated, not validated)
+    pub name: String,
+    /// Uncompressed contents
+    pub data: Vec<u8>,
+}
+
+/// Signature of the end of central directory record.
+const END_SIGNATURE: u32 = 0x0605_4b50;
+/// Signature of a central directory file header.
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+/// Signature of a local file header.
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+
+/// Read all file entries of an archive. Directory entries are left out.
+pub fn read_entries(archive: &[u8]) -> Result<Vec<Entry>, String> {
+    let end = find_end_record(archive).ok_or("not a zip archive (no end of central directory)")?;
+    let count = u16_at(archive, end + 10)? as usize;
+    let mut offset = u32_at(archive, end + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if u32_at(archive, offset)? != CENTRAL_SIGNATURE {
+            return Err("corrupt zip central directory".to_string());
+        }
+        let flags = u16_at(archive, offset + 8)?;
+        let method = u16_at(archive, offset + 10)?;
+        let crc = u32_at(archive, offset + 16)?;
+        let compressed_size = u32_at(archive, offset + 20)?;
+        let size = u32_at(archive, offset + 24)?;
+        let name_len = u16_at(archive, offset + 28)? as usize;
+        let extra_len = u16_at(archive, offset + 30)? as usize;
+        let comment_len = u16_at(archive, offset + 32)? as usize;
+        let local = u32_at(archive, offset + 42)? as usize;
+        let name = String::from_utf8_lossy(slice(archive, offset + 46, name_len)?).into_owned();
+        offset += 46 + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(format!("'{}' is encrypted", name));
+        }
+        if compressed_size == u32::MAX || size == u32::MAX {
+            return Err(format!("'{}' needs ZIP64, which isn't supported", name));
+        }
+
+        if u32_at(archive, local)? != LOCAL_SIGNATURE {
+            return Err(format!("corrupt zip entry '{}'", name));
+        }
+        let data_start = local + 30 + u16_at(archive, local + 26)? as usize + u16_at(archive, local + 28)? as usize;
+        let raw = slice(archive, data_start, compressed_size as usize)?;
+        let data = match method {
+            0 => raw.to_vec(),
+            8 => inflate(raw, size as usize).map_err(|e| format!("'{}': {}", name, e))?,
+            other => return Err(format!("'{}' uses unsupported compression method {}", name, other)),
+        };
+        if data.len() != size as usize || crc32(&data) != crc {
+            return Err(format!("'{}' is corrupt (size or CRC mismatch)", name));
+        }
+        entries.push(Entry { name, data });
+    }
+    Ok(entries)
+}
+
+/// Build an archive of stored entries.
+pub fn write_entries(entries: &[Entry]) -> Result<Vec<u8>, String> {
+    if entries.len() > u16::MAX as usize {
+        return Err("too many files for a zip archive".to_string());
+    }
+    let mut archive = Vec::new();
+    let mut central = Vec::new();
+    for entry in entries {
+        let offset = u32::try_from(archive.len()).map_err(|_| "zip archive would need ZIP64")?;
+        let size = u32::try_from(entry.data.len()).map_err(|_| format!("'{}' is too large", entry.name))?;
+        let crc = crc32(&entry.data);
+        let name = entry.name.as_bytes();
+        // Shared part of the local and central headers: version 2.0, UTF-8
+        // names, stored, 1980-01-01 00:00
+        let mut common = Vec::with_capacity(26);
+        for value in [20u16, 0x0800, 0, 0, 0x0021] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc, size, size] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        archive.extend_from_slice(&LOCAL_SIGNATURE.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name);
+        archive.extend_from_slice(&entry.data);
+
+        central.extend_from_slice(&CENTRAL_SIGNATURE.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        // Comment length, disk, internal and external attributes
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name);
+    }
+    let central_offset = u32::try_from(archive.len()).map_err(|_| "zip archive would need ZIP64")?;
+    let count = entries.len() as u16;
+    archive.extend_from_slice(&central);
+    archive.extend_from_slice(&END_SIGNATURE.to_le_bytes());
+    for value in [0u16, 0, count, count] {
+        archive.extend_from_slice(&value.to_le_bytes());
+    }
+    archive.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&central_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    Ok(archive)
+}
+
+/// Find the end of central directory record, searching back over a
+/// trailing archive comment.
+fn find_end_record(archive: &[u8]) -> Option<usize> {
+    let last = archive.len().checked_sub(22)?;
+    let first = last.saturating_sub(u16::MAX as usize);
+    (first..=last).rev().find(|&i| u32_at(archive, i) == Ok(END_SIGNATURE))
+}
+
+fn slice(data: &[u8], start: usize, len: usize) -> Result<&[u8], String> {
+    data.get(start..start.checked_add(len).ok_or("corrupt zip archive")?)
+        .ok_or_else(|| "truncated zip archive".to_string())
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16, String> {
+    let bytes = slice(data, at, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Result<u32, String> {
+    let bytes = slice(data, at, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// CRC-32 (IEEE) as used by zip.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// ============== Inflate (RFC 1951) ==============
+
+/// Base lengths for length codes 257..285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+/// Extra bits for length codes 257..285.
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// Base distances for distance codes 0..29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+/// Extra bits for distance codes 0..29.
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Order in which code length code lengths are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Reads bits least-significant first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u8,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u8) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self.data.get(self.pos).ok_or("unexpected end of deflate data")?;
+            value |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+/// Canonical Huffman code: number of codes per length and symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code".to_string())
+    }
+}
+
+/// Decompress raw deflate data that should expand to `size` bytes.
+/// Output beyond `size` is an error, so a lying header can't exhaust memory.
+fn inflate(data: &[u8], size: usize) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader { data, pos: 0, bit: 0 };
+    let mut out = Vec::with_capacity(size.min(1 << 20));
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = slice(data, reader.pos, 4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err("corrupt stored block".to_string());
+                }
+                if out.len() + len as usize > size {
+                    return Err("more data than declared".to_string());
+                }
+                out.extend_from_slice(slice(data, reader.pos + 4, len as usize)?);
+                reader.pos += 4 + len as usize;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                inflate_block(&mut reader, &mut out, size, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, size, &literals, &distances)?;
+            }
+            _ => return Err("invalid deflate block type".to_string()),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// Read the code tables of a dynamic Huffman block.
+fn read_dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("repeat with no previous length")?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err("invalid code length symbol".to_string()),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() != literal_count + distance_count {
+        return Err("code lengths overflow".to_string());
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+/// Decode one compressed block into `out`, which may grow up to `size`.
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    size: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        if out.len() > size {
+            return Err("more data than declared".to_string());
+        }
+        match literals.decode(reader)? {
+            symbol @ 0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            symbol => {
+                let code = symbol as usize - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err("invalid length code".to_string());
+                }
+                let length = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code])? as usize;
+                let code = distances.decode(reader)? as usize;
+                if code >= DIST_BASE.len() {
+                    return Err("invalid distance code".to_string());
+                }
+                let distance = DIST_BASE[code] as usize + reader.bits(DIST_EXTRA[code])? as usize;
+                if distance > out.len() {
+                    return Err("distance too far back".to_string());
+                }
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Built with Python's zipfile: deflated and stored entries, a
+    /// directory entry and a path-traversal name.
+    const PACK: &[u8] = include_bytes!("../tests/fixtures/pack.zip");
+
+    #[test]
+    fn test_read_stored_and_deflated_entries() {
+        let entries = read_entries(PACK).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["greetings.txt", "taunts.md", "ranked/gg.txt", "readme.exe", "../evil.txt", "stored.txt"]);
+        let greetings = String::from_utf8(entries[0].data.clone()).unwrap();
+        assert_eq!(greetings.lines().count(), 40);
+        assert!(greetings.starts_with("gl hf 1\n"));
+        assert_eq!(entries[5].data, b"stored as is\n");
+    }
+
+    #[test]
+    fn test_garbage_rejected() {
+        assert!(read_entries(b"definitely not a zip").is_err());
+        let mut corrupt = PACK.to_vec();
+        let at = corrupt.windows(9).position(|w| w == b"stored as").unwrap();
+        corrupt[at] ^= 0xFF;
+        assert!(read_entries(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_written_archive_reads_back() {
+        let entries = vec![
+            Entry { name: "gg.txt".to_string(), data: b"gg wp\n".to_vec() },
+            Entry { name: "aram/ez.md".to_string(), data: "ez \u{1F602}\n".as_bytes().to_vec() },
+            Entry { name: "empty.txt".to_string(), data: Vec::new() },
+        ];
+        assert_eq!(read_entries(&write_entries(&entries).unwrap()).unwrap(), entries);
+    }
+}
@@ -0,0 +1,37 @@
+# bash completion for mad_typing
+_mad_typing() {
+    local cur prev
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+    case "$prev" in
+        --speed)
+            COMPREPLY=($(compgen -W "25 50 75 100 150 200 300 400" -- "$cur"))
+            return 0
+            ;;
+        --progress)
+            COMPREPLY=($(compgen -W "compact full none" -- "$cur"))
+            return 0
+            ;;
+        --completions)
+            COMPREPLY=($(compgen -W "bash powershell" -- "$cur"))
+            return 0
+            ;;
+        --send|-s|--view|--export)
+            local IFS=$'\n'
+            COMPREPLY=($(compgen -W "$("${COMP_WORDS[0]}" --list 2>/dev/null | tail -n +2 | sed -E 's/ +[0-9]+ line\(s\).*$//')" -- "$cur"))
+            return 0
+            ;;
+        --import|--output|-o|--replay)
+            local IFS=$'\n'
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return 0
+            ;;
+        --dir|-d)
+            local IFS=$'\n'
+            COMPREPLY=($(compgen -d -- "$cur"))
+            return 0
+            ;;
+    esac
+    COMPREPLY=($(compgen -W "--version -V --help -h --window -w --dir -d --ext --doctor --ping --list -l --send -s --json-result --team --per-file --speed --skip-repeats --dry-run --progress --view --view-only --import --export --output -o --report --trace-input --replay --live --accessible --read-only --completions" -- "$cur"))
+}
+complete -F _mad_typing mad_typing mad_typing.exe
@@ -0,0 +1,23 @@
+# PowerShell completion for mad_typing
+Register-ArgumentCompleter -Native -CommandName 'mad_typing', 'mad_typing.exe' -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $before = @($commandAst.CommandElements | Where-Object { $_.Extent.EndOffset -lt $cursorPosition } | ForEach-Object { $_.ToString() })
+    $prev = if ($before.Count -gt 0) { $before[-1] } else { '' }
+    if ($prev -in @('--import', '--output', '-o', '--replay')) {
+        return [System.Management.Automation.CompletionCompleters]::CompleteFilename($wordToComplete)
+    }
+    if ($prev -in @('--dir', '-d')) {
+        return [System.Management.Automation.CompletionCompleters]::CompleteFilename($wordToComplete) | Where-Object { $_.ResultType -eq 'ProviderContainer' }
+    }
+    $candidates = switch ($prev) {
+        '--speed' { @('25', '50', '75', '100', '150', '200', '300', '400') }
+        '--progress' { @('compact', 'full', 'none') }
+        '--completions' { @('bash', 'powershell') }
+        { $_ -in @('--send', '-s', '--view', '--export') } { @(& $commandAst.CommandElements[0].ToString() --list 2>$null | Select-Object -Skip 1 | ForEach-Object { $_ -replace ' +[0-9]+ line\(s\).*$', '' }) }
+        default { @('--version', '-V', '--help', '-h', '--window', '-w', '--dir', '-d', '--ext', '--doctor', '--ping', '--list', '-l', '--send', '-s', '--json-result', '--team', '--per-file', '--speed', '--skip-repeats', '--dry-run', '--progress', '--view', '--view-only', '--import', '--export', '--output', '-o', '--report', '--trace-input', '--replay', '--live', '--accessible', '--read-only', '--completions') }
+    }
+    $candidates | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+        $text = if ($_ -match '\s') { "'$_'" } else { $_ }
+        [System.Management.Automation.CompletionResult]::new($text, $_, 'ParameterValue', $_)
+    }
+}